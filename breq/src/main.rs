@@ -2,16 +2,21 @@ use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser, Subcommand};
 use colored::Colorize;
 use std::io::IsTerminal;
-use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use toren_lib::claude_session::ClaudeSession;
+use toren_lib::session::SessionLogs;
 use toren_lib::{
-    AssignmentManager, AssignmentRef, AssignmentSource, Config, Segment, SegmentManager,
-    WorkspaceManager,
+    AssignmentManager, AssignmentRef, AssignmentSource, Config, ErrorCode, ListColumn, Segment,
+    SegmentManager, TorenError, WorkspaceManager,
 };
 use tracing::info;
 use tracing_subscriber::fmt::time::FormatTime;
 
+mod spinner;
+mod table;
+mod ui;
+
 /// Custom time formatter that displays only HH:MM:SS (UTC)
 struct ShortTime;
 
@@ -36,6 +41,7 @@ impl FormatTime for ShortTime {
 #[derive(Parser)]
 #[command(name = "breq")]
 #[command(about = "Composable workspace orchestration for Claude ancillaries")]
+#[command(arg_required_else_help = true)]
 struct Cli {
     /// Increase verbosity (-v for DEBUG, -vv for TRACE)
     #[arg(short, long, action = clap::ArgAction::Count, global = true)]
@@ -45,8 +51,12 @@ struct Cli {
     #[arg(long, global = true)]
     config: Option<PathBuf>,
 
+    /// Explain an error code (e.g. E0004) and exit
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
+
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
@@ -84,11 +94,95 @@ enum Commands {
         #[arg(long)]
         agent: Option<String>,
 
+        /// Set up the workspace and record the assignment, then print the
+        /// prompt to stdout instead of starting the agent
+        #[arg(long = "no-exec", conflicts_with = "detach")]
+        no_exec: bool,
+
+        /// Set up the workspace and record the assignment, then hand the work
+        /// off to the running toren daemon
+        #[arg(long)]
+        detach: bool,
+
+        /// Run the agent as a child process instead of exec'ing it, recording
+        /// its session ID on the assignment so the session can be resumed
+        #[arg(long, conflicts_with_all = ["no_exec", "detach"])]
+        wrap: bool,
+
+        /// Assign the same work to N new workspaces and print a summary instead
+        /// of starting an agent (with --detach, each is handed to the daemon).
+        /// Intents can use {{ variant.index }} and {{ variant.count }}.
+        #[arg(
+            short = 'n',
+            long,
+            value_parser = clap::value_parser!(u32).range(1..),
+            conflicts_with_all = ["workspace", "wrap"]
+        )]
+        count: Option<u32>,
+
+        /// Scope the assignment to a subdirectory of the repo (e.g. "crates/foo"):
+        /// the agent starts there and only changes under it count.
+        /// Intents can use {{ assignment.path }}.
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Allocate past `max_per_segment` when every pool slot is occupied
+        #[arg(long)]
+        overflow: bool,
+
+        /// Start even if another breq session or daemon work owns the
+        /// workspace's assignment, taking ownership of it
+        #[arg(long, requires = "workspace")]
+        steal: bool,
+
+        /// Wait for a task to complete first: the assignment is recorded now
+        /// and the daemon starts it (with its default agent) once a completion
+        /// record for the task lands
+        #[arg(long, value_name = "TASK", requires = "detach", conflicts_with = "agent")]
+        after: Option<String>,
+
+        /// Span a segment group (`[segments.groups]`): create a workspace in
+        /// each of its segments, starting the agent in the first one's
+        #[arg(long, conflicts_with_all = ["workspace", "segment", "count"])]
+        group: Option<String>,
+
         /// Additional arguments passed directly to the agent CLI
         #[arg(last = true)]
         passthrough: Vec<String>,
     },
 
+    /// Resume an assignment under the toren daemon, recreating its workspace
+    /// if needed, with an optional follow-up instruction
+    Resume {
+        /// Workspace or task ID reference
+        reference: String,
+
+        /// Instruction for the resumed work (default: continue the task)
+        #[arg(short, long, conflicts_with = "instruction_file")]
+        instruction: Option<String>,
+
+        /// Read the instruction from a file, or from stdin with `-`
+        #[arg(short = 'f', long = "instruction-file")]
+        instruction_file: Option<PathBuf>,
+
+        /// Write the instruction in $EDITOR, starting from the previous
+        /// prompt or instruction, with the task's description for reference
+        #[arg(short, long, conflicts_with_all = ["instruction", "instruction_file"])]
+        edit: bool,
+
+        /// Segment to use (defaults to current directory's segment)
+        #[arg(short, long)]
+        segment: Option<String>,
+
+        /// Agent to use (e.g., "claude", "codex:o3"). Uses the daemon's default if unset.
+        #[arg(long)]
+        agent: Option<String>,
+
+        /// Take the assignment over from a breq session that owns it
+        #[arg(long)]
+        steal: bool,
+    },
+
     /// Open a shell in a workspace, optionally running a command
     #[command(visible_alias = "sh")]
     Shell {
@@ -99,6 +193,11 @@ enum Commands {
         #[arg(long)]
         hook: Option<HookArg>,
 
+        /// With --hook, detect the workspace with jj/git instead of reading
+        /// its cached context (.toren/context.json)
+        #[arg(long, requires = "hook")]
+        no_cache: bool,
+
         /// Tag assignment with a task identifier
         #[arg(long = "task-id")]
         task_id: Option<String>,
@@ -115,11 +214,67 @@ enum Commands {
         #[arg(short, long)]
         segment: Option<String>,
 
+        /// Allocate past `max_per_segment` when every pool slot is occupied
+        #[arg(long)]
+        overflow: bool,
+
         /// Command to run in the workspace directory (after --)
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         cmd: Vec<String>,
     },
 
+    /// Open a shell in a workspace by name, task ID or unique prefix;
+    /// without a reference, list the segment's workspaces
+    Go {
+        /// Workspace name, task ID, or a unique prefix of either
+        reference: Option<String>,
+
+        /// List the segment's workspaces instead of entering one
+        #[arg(long)]
+        list: bool,
+
+        /// Open the workspace's .toren/layout.kdl panes in a tmux or zellij
+        /// session (attaching if it is already running)
+        #[arg(long, conflicts_with = "list")]
+        layout: bool,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Open a workspace in your editor ([ancillaries.editor] command), or
+    /// print a URI that opens it remotely
+    Open {
+        /// Workspace name, task ID, or a unique prefix of either
+        reference: String,
+
+        /// Print a URI for an editor on another machine (by default VS Code
+        /// Remote-SSH) instead of running the editor here
+        #[arg(long)]
+        uri: bool,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// List processes running in a workspace (what `destroy --kill` would
+    /// terminate)
+    Ps {
+        /// Workspace name (e.g. "one", "three")
+        workspace: String,
+
+        /// Terminate these processes (SIGTERM, then SIGKILL after 5s); each
+        /// must be running in the workspace
+        #[arg(long, value_name = "PID", num_args = 1..)]
+        kill: Vec<i32>,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
     /// List active assignments
     List {
         /// Workspace or external ID to show detail for
@@ -136,6 +291,26 @@ enum Commands {
         /// Show detailed assignment info
         #[arg(long)]
         detail: bool,
+
+        /// Comma-separated columns to show, overriding `[ui] list_columns`
+        /// (workspace, bead, activity, assignee, title, changes, age, labels)
+        #[arg(long, value_delimiter = ',')]
+        columns: Option<Vec<ListColumn>>,
+    },
+
+    /// Show completed and aborted assignments, newest first
+    History {
+        /// Only show assignments from this segment
+        #[arg(short, long)]
+        segment: Option<String>,
+
+        /// List the artifacts kept from each workspace
+        #[arg(long)]
+        artifacts: bool,
+
+        /// Maximum number of assignments to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
     },
 
     /// Set up a workspace without starting an agent
@@ -158,6 +333,10 @@ enum Commands {
         /// Segment to use (defaults to current directory's segment)
         #[arg(short, long)]
         segment: Option<String>,
+
+        /// Allocate past `max_per_segment` when every pool slot is occupied
+        #[arg(long)]
+        overflow: bool,
     },
 
     /// Teardown a workspace (bead-free), output JSON to stdout
@@ -174,12 +353,25 @@ enum Commands {
         #[arg(long)]
         push: bool,
 
+        /// Push without running the [push] checks
+        #[arg(long, requires = "push")]
+        no_verify: bool,
+
         /// Run destroy hooks and clean up workspace state even if no
         /// assignment record exists. Useful when a prior destroy failed
         /// or setup never completed.
         #[arg(long)]
         force: bool,
 
+        /// Destroy even if another breq session or daemon work owns the
+        /// assignment
+        #[arg(long)]
+        steal: bool,
+
+        /// Destroy even if the working copy has unresolved conflicts
+        #[arg(long)]
+        allow_conflicts: bool,
+
         /// Segment to use
         #[arg(short, long)]
         segment: Option<String>,
@@ -196,6 +388,27 @@ enum Commands {
         all: bool,
     },
 
+    /// Inspect workspace directories
+    Ws {
+        #[command(subcommand)]
+        cmd: WsCmd,
+    },
+
+    /// Find history, logs and artifacts of segments that no longer exist
+    Gc {
+        /// Only report what would be collected
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Move stale records and files to ~/.toren/archive without asking
+        #[arg(long, conflicts_with_all = ["dry_run", "delete"])]
+        archive: bool,
+
+        /// Delete stale records and files without asking
+        #[arg(long, conflicts_with = "dry_run")]
+        delete: bool,
+    },
+
     /// Initialize toren.kdl in the current repository
     Init {
         /// Add toren.kdl to .git/info/exclude instead of committing it
@@ -203,15 +416,28 @@ enum Commands {
         stealth: bool,
     },
 
-    /// Show a specific field from an assignment (for scripting)
+    /// Check the toren config, required tools and each segment's toren.kdl
+    Doctor,
+
+    /// Send a test notification to every `[notifications]` target
+    NotifyTest,
+
+    /// Show a specific field from an assignment (for scripting), or the
+    /// agent's session transcript
     Show {
         /// Workspace name (e.g. "one", "two")
         workspace: String,
 
         /// Field path to show (e.g., "task.id", "task.title", "task.url", "task.source",
-        /// "workspace.path", "segment", "ancillary_id", "session_id")
-        #[arg(long)]
-        field: String,
+        /// "task.auto_created", "source", "prompt", "workspace.path", "segment",
+        /// "ancillary_id", "session_id", "resume_instruction", "notes", "usage",
+        /// "usage.tokens", "usage.cost")
+        #[arg(long, required_unless_present = "transcript")]
+        field: Option<String>,
+
+        /// Print the agent's Claude session: messages, tool calls and token usage
+        #[arg(long, conflicts_with = "field")]
+        transcript: bool,
 
         /// Segment to use
         #[arg(short, long)]
@@ -224,85 +450,421 @@ enum Commands {
         reference: String,
     },
 
+    /// Undo the most recent complete, abort or clean: recreate the workspace
+    /// at its last revision, restore the assignment and reclaim its task
+    Undo {
+        /// Show what would be restored without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Terminal dashboard: assignments with their status and the selected
+    /// ancillary's live work log (r resume, c complete, a abort)
+    Ui,
+
+    /// Add a timestamped note to an assignment, or list its notes
+    Note {
+        /// Workspace or task ID reference
+        reference: String,
+
+        /// Note text (omit to list existing notes)
+        text: Option<String>,
+    },
+
+    /// Replace the prompt of an assignment created from one, and the
+    /// description of its auto-created task; the next resume includes it
+    Amend {
+        /// Workspace or task ID reference
+        reference: String,
+
+        /// The new prompt
+        #[arg(short, long, required_unless_present = "edit")]
+        prompt: Option<String>,
+
+        /// Edit the current prompt in $EDITOR
+        #[arg(short, long, conflicts_with = "prompt")]
+        edit: bool,
+
+        /// Segment to use (defaults to current directory's segment)
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Explain how an assignment's status signals were derived
+    Why {
+        /// Workspace or task ID reference
+        reference: String,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Show an assignment's changes since its workspace forked from base
+    Diff {
+        /// Workspace or task ID reference
+        reference: String,
+
+        /// Per-file change counts instead of the patch
+        #[arg(long)]
+        stat: bool,
+
+        /// Limit to one file or directory (relative to the assignment's focus)
+        #[arg(long)]
+        file: Option<String>,
+
+        /// Revision to diff from (default: the fork point)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Revision to diff to (default: the working copy)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
     /// Manage Rhai plugins under ~/.toren/plugins
     Plugin {
         #[command(subcommand)]
         cmd: PluginCmd,
     },
+
+    /// Pair with the toren daemon and save the session token for its API
+    Pair {
+        /// Pairing token printed by toren-daemon at startup
+        pairing_token: String,
+        /// Pair for viewing only: the daemon refuses this session's changes
+        #[arg(long)]
+        read_only: bool,
+    },
+
+    /// Manage station proxy routes for workspaces
+    Proxy {
+        #[command(subcommand)]
+        cmd: ProxyCmd,
+    },
+
+    /// Manage recurring assignments run by the daemon
+    Schedule {
+        #[command(subcommand)]
+        cmd: ScheduleCmd,
+    },
+
+    /// Inspect segment discovery
+    Segments {
+        #[command(subcommand)]
+        cmd: SegmentsCmd,
+    },
+
+    /// Suggest what to work on next: the segment's open, unblocked and
+    /// unassigned beads, by priority then age, offering to assign the top one
+    Ready {
+        /// How many beads to list
+        #[arg(short = 'n', long, default_value_t = 5)]
+        limit: usize,
+
+        /// Assign the top pick without asking
+        #[arg(long)]
+        assign: bool,
+
+        /// Hand the assigned bead's session off to the toren daemon
+        #[arg(long)]
+        detach: bool,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// List, show, create and close beads (`bd`) in a segment
+    Bead {
+        #[command(subcommand)]
+        cmd: BeadCmd,
+    },
+
+    /// Print a shell completion script (e.g. `breq completions zsh > ~/.zfunc/_breq`)
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
+
+    /// List workspace names and task IDs for shell completion
+    #[command(name = "__complete-references", hide = true)]
+    CompleteReferences {
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// List segment names for shell completion
+    #[command(name = "__complete-segments", hide = true)]
+    CompleteSegments,
 }
 
 #[derive(Subcommand)]
-enum PluginCmd {
-    /// List plugins available from the contrib repo (and which are installed)
-    List,
+enum WsCmd {
+    /// Show disk usage per workspace, largest first (symlinked shares excluded)
+    Du {
+        /// Only show workspaces of this segment
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+}
 
-    /// Install a plugin from a local path or the contrib repo
-    ///
-    /// TARGET is one of:
-    ///   - `commands/<name>` or `tasks/<name>` (fetched from github.com/anowell/toren)
-    ///   - a local .rhai path whose parent dir is `commands` or `tasks`
-    Install {
-        /// `commands/<name>`, `tasks/<name>`, or a local path to a .rhai file
-        target: String,
+#[derive(Subcommand)]
+enum ProxyCmd {
+    /// Re-apply proxy routes for active workspaces (e.g. after the proxy was down)
+    Reconcile {
+        /// Segment to reconcile
+        #[arg(short, long)]
+        segment: Option<String>,
+
+        /// Reconcile all segments
+        #[arg(short, long, conflicts_with = "segment")]
+        all: bool,
+    },
+
+    /// List workspace proxy routes known to the daemon
+    List {
+        /// Only show routes for this segment
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Remove all proxy routes for a workspace
+    Remove {
+        /// Workspace name or number (e.g. "one" or "1")
+        workspace: String,
+
+        /// Segment the workspace belongs to (default: current directory's segment)
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Remove routes whose workspace no longer exists (across all segments)
+    Prune {
+        /// Only report the routes that would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Write active workspaces' proxy routes as a static config snapshot
+    Export {
+        /// Snapshot format
+        #[arg(long, value_enum, default_value = "caddyfile")]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Only export routes for this segment
+        #[arg(short, long)]
+        segment: Option<String>,
     },
 }
 
 #[derive(Clone, Copy, clap::ValueEnum)]
-enum HookArg {
-    Setup,
-    Destroy,
+enum ExportFormat {
+    /// A Caddyfile with one site block per route
+    Caddyfile,
+    /// A JSON list of routes, for handing to another proxy
+    Json,
 }
 
-fn main() -> Result<()> {
-    let raw_args: Vec<String> = std::env::args().collect();
+#[derive(Subcommand)]
+enum ScheduleCmd {
+    /// Register a recurring assignment
+    Add {
+        /// Five-field cron expression, in local time (e.g. "0 3 * * *")
+        #[arg(long)]
+        cron: String,
 
-    // Pre-parse: check for alias match before clap gets involved
-    if raw_args.len() > 1 {
-        // Skip global flags to find the subcommand
-        let mut subcmd_idx = 1;
-        while subcmd_idx < raw_args.len() {
-            let arg = &raw_args[subcmd_idx];
-            if arg == "-v" || arg == "-vv" || arg == "-vvv" || arg == "--verbose" {
-                subcmd_idx += 1;
-                continue;
-            }
-            if arg == "--config" {
-                subcmd_idx += 2; // skip --config <path>
-                continue;
-            }
-            break;
-        }
+        /// Prompt for each run (a new task is created per run)
+        #[arg(short, long)]
+        prompt: String,
 
-        if subcmd_idx < raw_args.len() {
-            let subcmd = &raw_args[subcmd_idx];
-            let plugin_args: Vec<String> = raw_args[subcmd_idx + 1..].to_vec();
+        /// Segment to run in (default: current directory's segment)
+        #[arg(short, long)]
+        segment: Option<String>,
 
-            // `breq do --help`: inject available intents into help text
-            if subcmd == "do" && plugin_args.iter().any(|a| a == "--help" || a == "-h") {
-                if let Ok(config) = Config::load() {
-                    let mut intent_names: Vec<&str> = config.intents.entries.keys().map(|s| s.as_str()).collect();
-                    intent_names.sort();
-                    let intent_list = intent_names.join(", ");
-                    let section = format!("Available intents:\n  {}", intent_list);
-                    Cli::command()
-                        .find_subcommand_mut("do")
-                        .expect("do subcommand exists")
-                        .clone()
-                        .after_help(section)
-                        .print_help()
-                        .ok();
-                    std::process::exit(0);
-                }
-                // Fall through to normal clap help if config fails
-            }
+        /// Task source for the created tasks (default: tasks.sources)
+        #[arg(long)]
+        task_source: Option<String>,
 
-            // Top-level help: inject plugin descriptions
-            if subcmd == "--help" || subcmd == "-h" {
-                if let Ok(plugin_mgr) = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins")) {
-                    let plugins = plugin_mgr.list_with_descriptions();
-                    if !plugins.is_empty() {
-                        let mut section = String::from("Plugins:");
-                        for (name, desc) in &plugins {
+        /// Agent to use (e.g. "codex:o3")
+        #[arg(long)]
+        agent: Option<String>,
+    },
+
+    /// List schedules with their next and last runs
+    List,
+
+    /// Remove a schedule
+    Remove {
+        /// Schedule ID (from `breq schedule list`)
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SegmentsCmd {
+    /// List the segments found under the configured roots, and the
+    /// directories that were passed over, with the reason for each
+    List {
+        /// Only show segments
+        #[arg(long)]
+        segments_only: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BeadCmd {
+    /// List beads, with the workspace each assigned one is in
+    List {
+        /// Only show beads without an assignment
+        #[arg(long)]
+        unassigned: bool,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Show a bead and its assignment
+    Show {
+        /// Bead ID
+        id: String,
+
+        /// Claim the bead and start an agent on it (like `breq assign`)
+        #[arg(long)]
+        assign: bool,
+
+        /// With --assign, hand the session off to the toren daemon
+        #[arg(long, requires = "assign")]
+        detach: bool,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Create a bead and print its ID
+    Create {
+        /// Bead title
+        title: String,
+
+        /// Bead description
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Claim the new bead and start an agent on it
+        #[arg(long)]
+        assign: bool,
+
+        /// With --assign, hand the session off to the toren daemon
+        #[arg(long, requires = "assign")]
+        detach: bool,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+
+    /// Close a bead
+    Close {
+        /// Bead ID
+        id: String,
+
+        /// Close it even though an assignment is working on it
+        #[arg(long)]
+        force: bool,
+
+        /// Segment to use
+        #[arg(short, long)]
+        segment: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PluginCmd {
+    /// List plugins available from the contrib repo (and which are installed)
+    List,
+
+    /// Install a plugin from a local path or the contrib repo
+    ///
+    /// TARGET is one of:
+    ///   - `commands/<name>` or `tasks/<name>` (fetched from github.com/anowell/toren)
+    ///   - a local .rhai path whose parent dir is `commands` or `tasks`
+    Install {
+        /// `commands/<name>`, `tasks/<name>`, or a local path to a .rhai file
+        target: String,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum HookArg {
+    Setup,
+    Destroy,
+}
+
+fn main() -> Result<()> {
+    let raw_args: Vec<String> = std::env::args().collect();
+
+    // Pre-parse: check for alias match before clap gets involved
+    if raw_args.len() > 1 {
+        // Skip global flags to find the subcommand
+        let mut subcmd_idx = 1;
+        while subcmd_idx < raw_args.len() {
+            let arg = &raw_args[subcmd_idx];
+            if arg == "-v" || arg == "-vv" || arg == "-vvv" || arg == "--verbose" {
+                subcmd_idx += 1;
+                continue;
+            }
+            if arg == "--config" {
+                subcmd_idx += 2; // skip --config <path>
+                continue;
+            }
+            break;
+        }
+
+        if subcmd_idx < raw_args.len() {
+            let subcmd = &raw_args[subcmd_idx];
+            let plugin_args: Vec<String> = raw_args[subcmd_idx + 1..].to_vec();
+
+            // `breq do --help`: inject available intents into help text
+            if subcmd == "do" && plugin_args.iter().any(|a| a == "--help" || a == "-h") {
+                if let Ok(config) = Config::load() {
+                    // Include the current repo's toren.kdl intents when there is one
+                    let intents = SegmentManager::new(&config)
+                        .ok()
+                        .and_then(|mgr| resolve_segment(&mgr, None).ok())
+                        .and_then(|segment| config.intents_for(&segment.path).ok())
+                        .unwrap_or_else(|| config.intents.clone());
+                    let intent_list = intents.names().join(", ");
+                    let section = format!("Available intents:\n  {}", intent_list);
+                    Cli::command()
+                        .find_subcommand_mut("do")
+                        .expect("do subcommand exists")
+                        .clone()
+                        .after_help(section)
+                        .print_help()
+                        .ok();
+                    std::process::exit(0);
+                }
+                // Fall through to normal clap help if config fails
+            }
+
+            // Top-level help: inject plugin descriptions
+            if subcmd == "--help" || subcmd == "-h" {
+                if let Ok(plugin_mgr) = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins")) {
+                    let plugins = plugin_mgr.list_with_descriptions();
+                    if !plugins.is_empty() {
+                        let mut section = String::from("Plugins:");
+                        for (name, desc) in &plugins {
                             match desc {
                                 Some(d) => section.push_str(&format!("\n  {:<16}{}", name, d)),
                                 None => section.push_str(&format!("\n  {}", name)),
@@ -372,7 +934,7 @@ fn main() -> Result<()> {
                                 match execute_deferred_action(&config, action) {
                                     Ok(()) => std::process::exit(0),
                                     Err(e) => {
-                                        eprintln!("Error: {:#}", e);
+                                        report_error(&e, verbose_count > 0);
                                         std::process::exit(1);
                                     }
                                 }
@@ -415,10 +977,27 @@ fn main() -> Result<()> {
         .with_timer(ShortTime)
         .init();
 
+    if let Some(code) = cli.explain {
+        return cmd_explain(&code);
+    }
+    let Some(command) = cli.command else {
+        Cli::command().print_help().ok();
+        std::process::exit(2);
+    };
+
+    if let Err(e) = run(cli.config.as_deref(), command) {
+        report_error(&e, cli.verbose > 0);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn run(config_path: Option<&Path>, command: Commands) -> Result<()> {
     // Load config once, shared across all commands
-    let config = Config::load_from(cli.config.as_deref())?;
+    let config = Config::load_from(config_path)?;
+    toren_lib::task_comments::TaskCommenter::register(&config);
 
-    match cli.command {
+    match command {
         Commands::Do {
             workspace,
             prompt,
@@ -428,6 +1007,15 @@ fn main() -> Result<()> {
             task_url,
             segment,
             agent,
+            no_exec,
+            detach,
+            wrap,
+            count,
+            path,
+            overflow,
+            steal,
+            after,
+            group,
             passthrough,
         } => cmd_do(
             &config,
@@ -440,69 +1028,274 @@ fn main() -> Result<()> {
             None, // task_source inferred from task_id prefix or plugin resolution
             segment.as_deref(),
             agent,
+            Launch::from_flags(no_exec, detach, wrap),
+            count,
+            path,
+            overflow,
+            steal,
+            after,
+            group,
             passthrough,
         ),
+        Commands::Resume {
+            reference,
+            instruction,
+            instruction_file,
+            edit,
+            segment,
+            agent,
+            steal,
+        } => cmd_resume(
+            &config,
+            &reference,
+            instruction,
+            instruction_file.as_deref(),
+            edit,
+            segment.as_deref(),
+            agent.as_deref(),
+            steal,
+        ),
         Commands::Shell {
             workspace,
             hook,
+            no_cache,
             task_id,
             task_title,
             task_url,
             segment,
+            overflow,
             cmd,
-        } => cmd_shell(&config, workspace, hook, task_id, task_title, task_url, segment.as_deref(), cmd),
+        } => cmd_shell(&config, workspace, hook, no_cache, task_id, task_title, task_url, segment.as_deref(), overflow, cmd),
+        Commands::Go {
+            reference,
+            list,
+            layout,
+            segment,
+        } => cmd_go(&config, reference.as_deref(), list, layout, segment.as_deref()),
+        Commands::Open {
+            reference,
+            uri,
+            segment,
+        } => cmd_open(&config, &reference, uri, segment.as_deref()),
+        Commands::Ps {
+            workspace,
+            kill,
+            segment,
+        } => cmd_ps(&config, &workspace, &kill, segment.as_deref()),
         Commands::List {
             reference,
             all,
             segment,
             detail,
-        } => cmd_list(&config, reference, all, segment, detail),
+            columns,
+        } => cmd_list(&config, reference, all, segment, detail, columns),
+        Commands::History {
+            segment,
+            artifacts,
+            limit,
+        } => cmd_history(segment.as_deref(), artifacts, limit),
         Commands::Setup {
             workspace,
             task_id,
             task_title,
             task_url,
             segment,
-        } => cmd_setup(&config, workspace, task_id, task_title, task_url, segment.as_deref()),
+            overflow,
+        } => cmd_setup(&config, workspace, task_id, task_title, task_url, segment.as_deref(), overflow),
         Commands::Destroy {
             workspace,
             kill,
             push,
+            no_verify,
             force,
+            steal,
+            allow_conflicts,
             segment,
-        } => cmd_destroy(&config, &workspace, kill, push, force, segment.as_deref()),
+        } => cmd_destroy(
+            &config,
+            &workspace,
+            kill,
+            push,
+            no_verify,
+            force,
+            steal,
+            allow_conflicts,
+            segment.as_deref(),
+        ),
         Commands::Cleanup { segment, all } => cmd_cleanup(&config, all, segment),
+        Commands::Ws { cmd } => cmd_ws(&config, cmd),
+        Commands::Gc {
+            dry_run,
+            archive,
+            delete,
+        } => cmd_gc(&config, dry_run, archive, delete),
         Commands::Init { stealth } => cmd_init(stealth),
         Commands::Show {
             workspace,
             field,
+            transcript,
             segment,
-        } => cmd_show(&config, &workspace, &field, segment.as_deref()),
+        } => cmd_show(&config, &workspace, field.as_deref(), transcript, segment.as_deref()),
         Commands::Dismiss { reference } => cmd_dismiss(&config, &reference),
+        Commands::Undo { dry_run } => cmd_undo(&config, dry_run),
+        Commands::Ui => ui::run(&config),
+        Commands::Note { reference, text } => cmd_note(&config, &reference, text.as_deref()),
+        Commands::Amend { reference, prompt, edit, segment } => {
+            cmd_amend(&config, &reference, prompt, edit, segment.as_deref())
+        }
+        Commands::Why { reference, segment } => cmd_why(&config, &reference, segment.as_deref()),
+        Commands::Diff { reference, stat, file, from, to, segment } => cmd_diff(
+            &config,
+            &reference,
+            &toren_lib::DiffOptions { stat, file, from, to },
+            segment.as_deref(),
+        ),
+        Commands::Doctor => cmd_doctor(&config),
+        Commands::NotifyTest => cmd_notify_test(&config),
         Commands::Plugin { cmd } => cmd_plugin(cmd),
+        Commands::Pair {
+            pairing_token,
+            read_only,
+        } => cmd_pair(&config, &pairing_token, read_only),
+        Commands::Proxy { cmd } => cmd_proxy(&config, cmd),
+        Commands::Schedule { cmd } => cmd_schedule(&config, cmd),
+        Commands::Segments { cmd } => cmd_segments(&config, cmd),
+        Commands::Ready {
+            limit,
+            assign,
+            detach,
+            segment,
+        } => cmd_ready(&config, limit, assign, detach, segment.as_deref()),
+        Commands::Bead { cmd } => cmd_bead(&config, cmd),
+        Commands::Completions { shell } => cmd_completions(shell),
+        Commands::CompleteReferences { segment } => cmd_complete_references(&config, segment.as_deref()),
+        Commands::CompleteSegments => cmd_complete_segments(&config),
+    }
+}
+
+// ─── errors ──────────────────────────────────────────────────────────────────
+
+/// Print an error for humans: coded errors get a one-line summary and hint,
+/// everything else its context chain on one line. Verbose mode adds the full
+/// chain and, for coded errors, the `--explain` text.
+fn report_error(err: &anyhow::Error, verbose: bool) {
+    let Some(coded) = toren_lib::error::find_coded(err) else {
+        if verbose {
+            eprintln!("{} {:?}", "error:".red().bold(), err);
+        } else {
+            eprintln!("{} {:#}", "error:".red().bold(), err);
+        }
+        return;
+    };
+
+    eprintln!(
+        "{} {}",
+        format!("error[{}]:", coded.code).red().bold(),
+        coded.message
+    );
+    if let Some(ref hint) = coded.hint {
+        for (i, line) in hint.lines().enumerate() {
+            let label = if i == 0 { "hint:" } else { "     " };
+            eprintln!("  {} {}", label.cyan(), line);
+        }
+    }
+
+    if !verbose {
+        eprintln!(
+            "  {}",
+            format!("(run `breq --explain {}` or add -v for details)", coded.code).dimmed()
+        );
+        return;
+    }
+
+    // The coded layer is usually context; what follows it is the cause
+    let causes: Vec<String> = err
+        .chain()
+        .map(|e| e.to_string())
+        .filter(|e| *e != coded.to_string())
+        .collect();
+    if !causes.is_empty() {
+        eprintln!("\nCaused by:");
+        for cause in causes {
+            eprintln!("  {}", cause);
+        }
+    }
+    eprintln!();
+    print_explanation(coded.code, &mut std::io::stderr());
+}
+
+fn cmd_explain(code: &str) -> Result<()> {
+    let Some(code) = toren_lib::ErrorCode::parse(code) else {
+        let known: Vec<_> = toren_lib::ErrorCode::ALL.iter().map(|c| c.as_str()).collect();
+        anyhow::bail!("Unknown error code '{}'. Known codes: {}", code, known.join(", "));
+    };
+    print_explanation(code, &mut std::io::stdout());
+    Ok(())
+}
+
+fn print_explanation(code: toren_lib::ErrorCode, out: &mut impl std::io::Write) {
+    let explanation = code.explain();
+    let _ = writeln!(out, "{}: {}\n", code, explanation.title.bold());
+    let _ = writeln!(out, "{}", explanation.details);
+    if !explanation.files.is_empty() {
+        let _ = writeln!(out, "\nRelated files:");
+        for file in explanation.files {
+            let _ = writeln!(out, "  {}", file);
+        }
+    }
+    if !explanation.next.is_empty() {
+        let _ = writeln!(out, "\nTry:");
+        for cmd in explanation.next {
+            let _ = writeln!(out, "  {}", cmd);
+        }
     }
 }
 
 /// Helper to find segment from current directory or specified name.
 fn resolve_segment(segment_mgr: &SegmentManager, segment_name: Option<&str>) -> Result<Segment> {
     if let Some(name) = segment_name {
-        segment_mgr
-            .find_by_name(name)
-            .with_context(|| format!("Segment '{}' not found in any segment root", name))
+        segment_mgr.find_by_name(name).ok_or_else(|| {
+            TorenError::new(
+                ErrorCode::SegmentNotFound,
+                format!("Segment '{}' not found in any segment root", name),
+            )
+            .hint("Run `breq list --all` to see known segments.")
+            .into()
+        })
     } else {
         let cwd = std::env::current_dir()?;
-        segment_mgr.resolve_from_path(&cwd).with_context(|| {
-            "Current directory is not under any configured segment.\n\
-             Configure segments in ~/.toren/config.toml:\n\
-             [ancillaries]\n\
-             segments = [\"~/proj/*\"]"
+        segment_mgr.resolve_from_path(&cwd).ok_or_else(|| {
+            TorenError::new(
+                ErrorCode::NotInSegment,
+                "Current directory is not under any configured segment.",
+            )
+            .hint(
+                "Configure segments in ~/.toren/config.toml:\n\
+                 [ancillaries]\n\
+                 segments = [\"~/proj/*\"]",
+            )
+            .into()
         })
     }
 }
 
-/// Generate workspace name from ancillary number word.
-fn workspace_name_for_number(n: u32) -> String {
-    toren_lib::number_to_word(n).to_lowercase()
+/// Run the user `assign` hook for a newly recorded assignment.
+fn run_assign_hook(assignment: &toren_lib::Assignment) {
+    toren_lib::hooks::emit(&toren_lib::HookPayload::new(
+        toren_lib::HookEvent::Assign,
+        assignment,
+    ));
+}
+
+/// Write or refresh the toren context block in the workspace (best-effort).
+fn write_agent_context(
+    workspace_mgr: &WorkspaceManager,
+    segment_path: &Path,
+    assignment: &toren_lib::Assignment,
+) {
+    if let Err(e) = workspace_mgr.write_agent_context(segment_path, assignment) {
+        tracing::warn!("Failed to write agent context: {:#}", e);
+    }
 }
 
 /// Resolve segment path and name from CWD for plugin context (best-effort).
@@ -527,8 +1320,16 @@ fn execute_deferred_action(config: &Config, action: toren_lib::DeferredAction) -
             task_source,
             prompt,
             intent,
+            no_exec,
+            detach,
+            wrap,
+            count,
+            path,
+            after,
+            group,
         } => {
-            cmd_do(
+            let claim = task_id.clone().zip(task_source.clone());
+            let result = cmd_do(
                 config,
                 None,       // workspace (auto-create)
                 prompt,
@@ -539,14 +1340,89 @@ fn execute_deferred_action(config: &Config, action: toren_lib::DeferredAction) -
                 task_source,
                 None,       // segment (resolve from CWD)
                 None,       // agent (use config/auto-detect)
+                Launch::from_flags(no_exec, detach, wrap),
+                count,
+                path,
+                false,      // overflow
+                false,      // steal
+                after,
+                group,
                 Vec::new(), // passthrough
-            )
+            );
+
+            // The plugin may have claimed the task before handing off; release
+            // the claim if the session never started.
+            if let (Err(_), Some((task_id, source))) = (&result, claim) {
+                release_task_claim(config, &source, &task_id);
+            }
+            result
         }
     }
 }
 
+/// Return a claimed task to open after a failed launch (best-effort).
+fn release_task_claim(config: &Config, source: &str, task_id: &str) {
+    let Ok(plugin_mgr) = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins")) else {
+        return;
+    };
+    let (seg_path, seg_name) = resolve_segment_for_plugin(config);
+    let ctx = toren_lib::PluginContext::new(seg_path, seg_name);
+    match plugin_mgr.resolve_abort(source, task_id, ctx) {
+        Ok(()) => eprintln!("Released claim on task {}", task_id),
+        Err(e) => eprintln!(
+            "warning: failed to release claim on task {}: {:#}\n\
+             The task may still be marked in progress.",
+            task_id, e
+        ),
+    }
+}
+
+/// Re-claim an assignment's task under its ancillary's identity once the
+/// ancillary is known. Only applies with `tasks.claim_identity` set, since the
+/// task plugin has already claimed it under the default identity (best-effort).
+fn claim_as_ancillary(config: &Config, segment_path: &Path, assignment: &toren_lib::Assignment) {
+    let Some(ref template) = config.tasks.claim_identity else {
+        return;
+    };
+    let Ok(plugin_mgr) = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins")) else {
+        return;
+    };
+    if let Err(e) =
+        toren_lib::claim_for_assignment(assignment, &plugin_mgr, segment_path, Some(template))
+    {
+        eprintln!("warning: failed to claim task as {}: {:#}", assignment.ancillary_id, e);
+    }
+}
+
 // ─── do ─────────────────────────────────────────────────────────────────────
 
+/// What `breq do` does once the workspace and assignment are in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Launch {
+    /// Exec into the agent in this terminal
+    Exec,
+    /// Run the agent as a child in this terminal, capturing its session ID
+    Wrap,
+    /// Print the generated prompt to stdout and exit
+    Print,
+    /// Hand the assignment to the daemon's work manager
+    Daemon,
+}
+
+impl Launch {
+    fn from_flags(no_exec: bool, detach: bool, wrap: bool) -> Self {
+        if detach {
+            Launch::Daemon
+        } else if wrap {
+            Launch::Wrap
+        } else if no_exec {
+            Launch::Print
+        } else {
+            Launch::Exec
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn cmd_do(
     config: &Config,
@@ -559,16 +1435,72 @@ fn cmd_do(
     task_source_arg: Option<String>,
     segment_name: Option<&str>,
     agent_str: Option<String>,
+    launch: Launch,
+    count: Option<u32>,
+    focus: Option<String>,
+    overflow: bool,
+    steal: bool,
+    after: Option<String>,
+    group: Option<String>,
     passthrough: Vec<String>,
 ) -> Result<()> {
+    let fan_out = count.filter(|&n| n > 1);
+    if after.is_some() && launch != Launch::Daemon {
+        anyhow::bail!("--after hands the work to the daemon once the task completes; it needs --detach");
+    }
+    let after = after.as_deref().map(toren_lib::TaskId::parse).transpose()?;
+    if fan_out.is_some() && (workspace.is_some() || launch == Launch::Wrap) {
+        anyhow::bail!("--count creates new workspaces and can't be combined with a workspace or --wrap");
+    }
+    if group.is_some() && (workspace.is_some() || fan_out.is_some()) {
+        anyhow::bail!("--group creates one new workspace per segment and can't be combined with a workspace or --count");
+    }
+
     let agent = config.resolve_agent(agent_str.as_deref())?;
+    let logs = SessionLogs::from_config(config);
+    // Preflight: fail before creating anything if the agent CLI is missing.
+    // Detached work runs under the daemon, which resolves its own agent, and
+    // fan-out never starts an agent in this terminal.
+    if matches!(launch, Launch::Exec | Launch::Wrap) && fan_out.is_none() {
+        agent.ensure_installed()?;
+    }
     let workspace_root = config.ancillaries.workspace_root.clone();
 
-    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()));
+    // Setup can take minutes; show what it's doing when someone is watching
+    let setup_progress = std::io::stderr()
+        .is_terminal()
+        .then(|| spinner::SetupSpinner::new() as std::sync::Arc<dyn toren_lib::SetupProgress>);
+    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+        .with_proxy_backend(Some(config.proxy.backend.clone()))
+        .with_host_template(config.proxy.host_template.clone())
+        .with_segment_roots(config.segment_workspace_roots())
+        .with_context_file(config.ancillaries.context_file.clone())
+        .with_envrc(config.ancillaries.envrc)
+        .with_setup_progress(setup_progress);
     let segment_mgr = SegmentManager::new(config)?;
     let mut assignment_mgr = AssignmentManager::new()?;
 
+    // A segment group's first segment is the assignment's own
+    let group_members = group.as_deref().map(|g| config.segment_group(g)).transpose()?;
+    let segment_name = group_members.map(|members| members[0].as_str()).or(segment_name);
     let segment = resolve_segment(&segment_mgr, segment_name)?;
+    let settings = segment_mgr.settings(&segment.name);
+    let intent = intent.or(settings.intent);
+
+    let focus_path = focus
+        .as_deref()
+        .map(toren_lib::normalize_focus_path)
+        .transpose()?
+        .flatten();
+    if let Some(ref focus) = focus_path {
+        let base = match workspace {
+            Some(ref ws) => workspace_mgr.workspace_path(&segment.name, &toren_lib::naming::normalize_workspace_name(&segment.name, ws)),
+            None => segment.path.clone(),
+        };
+        if !base.join(focus).is_dir() {
+            anyhow::bail!("Path '{}' is not a directory in {}", focus.display(), base.display());
+        }
+    }
 
     // Infer task fields from CLI args
     let mut inferred = toren_lib::infer_task_fields(
@@ -602,14 +1534,26 @@ fn cmd_do(
     }
 
     // 1. System prompt from intent (optional, rendered as --append-system-prompt)
-    let system_prompt = if let Some(ref intent_name) = intent {
-        let template = config
-            .intents
-            .get(intent_name)
-            .with_context(|| format!("Unknown intent: {}", intent_name))?;
-
-        // Fetch task description if we have a task_id
-        let task_description = inferred.task_id.as_ref().and_then(|id| {
+    let intents = match intent {
+        Some(_) => config.intents_for(&segment.path)?,
+        None => config.intents.clone(),
+    };
+    let intent_template = intent
+        .as_ref()
+        .map(|intent_name| {
+            intents.get(intent_name).with_context(|| {
+                format!(
+                    "Unknown intent: {} (available: {})",
+                    intent_name,
+                    intents.names().join(", ")
+                )
+            })
+        })
+        .transpose()?;
+
+    // Fetch task description and dependencies if we have a task_id
+    let resolved_task = if intent_template.is_some() {
+        inferred.task_id.as_ref().and_then(|id| {
             let plugin_mgr = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins")).ok()?;
             let ctx = toren_lib::PluginContext::new(Some(segment.path.clone()), Some(segment.name.clone()));
             if let Some(source) = inferred.task_source.as_deref() {
@@ -619,34 +1563,74 @@ fn cmd_do(
                 // Source unknown — search across all task plugins
                 let sources = plugin_mgr.effective_sources(&config.tasks.sources);
                 plugin_mgr.resolve_info_multi(&sources, id, ctx).ok()
-            }.and_then(|t| t.description)
-        });
-
+            }
+        })
+    } else {
+        None
+    };
+    let recent_commits = workspace_mgr.recent_commits(&segment.path, None);
+
+    // The intent template, followed by the repo's toren.kdl `prompt` context.
+    // Rendered for the agent's workspace once it is known, so both can use
+    // the workspace's toren.kdl `vars.*`.
+    let render_intent = |variant: Option<toren_lib::VariantInfo>,
+                         ws: Option<toren_lib::WorkspaceInfo>|
+     -> Result<Option<String>> {
+        let vars = match ws {
+            Some(ref ws) => workspace_mgr.workspace_vars(&segment.path, Path::new(&ws.path), &ws.name, ws.num)?,
+            None => std::collections::HashMap::new(),
+        };
         // Build task context for template rendering
-        let task_id = inferred.task_id.clone().unwrap_or_default();
+        let task_id = inferred.task_id.as_deref().unwrap_or_default().to_string();
         let task_title = inferred.task_title.clone().unwrap_or_else(|| task_id.clone());
         let ctx = toren_lib::WorkspaceContext {
-            ws: toren_lib::WorkspaceInfo {
+            ws: ws.unwrap_or(toren_lib::WorkspaceInfo {
                 name: String::new(),
                 num: 0,
                 path: String::new(),
-            },
+            }),
             repo: toren_lib::RepoInfo {
                 root: segment.path.display().to_string(),
                 name: segment.name.clone(),
+                recent_commits: recent_commits.clone(),
             },
             task: Some(toren_lib::TaskInfo {
                 id: task_id,
                 title: task_title,
-                description: task_description,
+                description: resolved_task.as_ref().and_then(|t| t.description.clone()),
                 url: inferred.task_url.clone(),
                 source: inferred.task_source.clone(),
+                deps: resolved_task.as_ref().map(|t| t.deps.clone()).unwrap_or_default(),
             }),
-            vars: std::collections::HashMap::new(),
+            variant,
+            assignment: Some(toren_lib::AssignmentInfo {
+                path: focus_path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+            }),
+            vars,
         };
-        Some(toren_lib::render_template(template, &ctx)?)
-    } else {
-        None
+        let rendered = intent_template
+            .map(|template| toren_lib::render_template(template, &ctx))
+            .transpose()?;
+        toren_lib::with_repo_prompt(rendered, &segment.path, &ctx)
+    };
+    // An existing workspace is known up front. A new one is created after
+    // the prompt is read, so this render (without its vars) only catches
+    // template errors early and the prompt is rendered again once it exists.
+    let system_prompt = match workspace {
+        _ if fan_out.is_some() => None,
+        Some(ref ws_name) => {
+            let name = toren_lib::naming::normalize_workspace_name(&segment.name, ws_name);
+            let path = workspace_mgr.workspace_path(&segment.name, &name);
+            if !path.exists() {
+                anyhow::bail!("Workspace '{}' not found at {}", name, path.display());
+            }
+            let num = toren_lib::naming::slot_number(&segment.name, &name).unwrap_or(0);
+            render_intent(None, Some(workspace_info(&name, num, &path)))?
+        }
+        None => render_intent(None, None)?,
     };
 
     // 2. User message: provided prompt > stdin > $EDITOR
@@ -684,55 +1668,192 @@ fn cmd_do(
 
     // Determine workspace: reuse existing or create new
     if let Some(ref ws_name) = workspace {
-        let ws_name_lower = ws_name.to_lowercase();
+        let ws_name_lower = toren_lib::naming::normalize_workspace_name(&segment.name, ws_name);
         let ws_path = workspace_mgr.workspace_path(&segment.name, &ws_name_lower);
 
-        if !ws_path.exists() {
-            anyhow::bail!("Workspace '{}' not found at {}", ws_name_lower, ws_path.display());
-        }
-
         // Reuse workspace — update assignment fields if provided
         if inferred.task_id.is_some() || inferred.task_title.is_some() || inferred.task_url.is_some() {
-            let ancillary_num = toren_lib::word_to_number(&ws_name_lower).unwrap_or(0);
+            let ancillary_num = toren_lib::naming::slot_number(&segment.name, &ws_name_lower).unwrap_or(0);
             let ancillary_id_str = toren_lib::ancillary_id(&segment.name, ancillary_num);
 
             if let Some(assignment) = assignment_mgr.get_active_for_ancillary(&ancillary_id_str).cloned() {
                 if assignment_mgr.update_task_fields(
                     &assignment.id,
-                    inferred.task_id.as_deref(),
+                    inferred.task_id.as_ref(),
                     inferred.task_title.as_deref(),
                     inferred.task_url.as_deref(),
                     inferred.task_source.as_deref(),
                 )? {
                     eprintln!("Updated assignment for workspace '{}'", ws_name_lower);
+                    if let Some(updated) = assignment_mgr.get(&assignment.id) {
+                        write_agent_context(&workspace_mgr, &segment.path, updated);
+                    }
                 }
             } else {
                 info!("No assignment found for ancillary '{}', skipping update", ancillary_id_str);
             }
         }
 
-        // Start agent session
-        eprintln!("Starting {} session in {}\n", agent, ws_path.display());
-        let mut cmd = agent.build_command(&user_message, &ws_path, system_prompt.as_deref());
-        cmd.args(&passthrough);
-
-        let err = cmd.exec();
-        Err(err).context(format!("Failed to exec {}", agent.kind.binary_name()))
-    } else {
-        // Create new workspace
-        let existing_workspaces = workspace_mgr
-            .list_workspaces(&segment.path)
-            .unwrap_or_default();
-        let ancillary_id_str = assignment_mgr.next_available_ancillary(
-            &segment.name,
-            config.ancillaries.max_per_segment,
-            &existing_workspaces,
-        );
-        let ancillary_num = toren_lib::ancillary_number(&ancillary_id_str).unwrap_or(1);
-        eprintln!("Ancillary: {}", ancillary_id_str);
+        // A new focus replaces the assignment's; otherwise keep its existing one
+        let ancillary_num = toren_lib::naming::slot_number(&segment.name, &ws_name_lower).unwrap_or(0);
+        let ancillary_id_str = toren_lib::ancillary_id(&segment.name, ancillary_num);
+        let existing = assignment_mgr.get_active_for_ancillary(&ancillary_id_str).cloned();
+        if let (Some(ref assignment), Some(_)) = (&existing, &focus_path) {
+            if assignment_mgr.set_focus_path(&assignment.id, focus_path.clone())? {
+                if let Some(updated) = assignment_mgr.get(&assignment.id) {
+                    write_agent_context(&workspace_mgr, &segment.path, updated);
+                }
+            }
+        }
+        if let (Some(ref assignment), Some(_)) = (&existing, &intent) {
+            assignment_mgr.set_intent(&assignment.id, intent.clone())?;
+        }
+        let work_dir = match (&focus_path, &existing) {
+            (Some(focus), _) => ws_path.join(focus),
+            (None, Some(assignment)) => assignment.working_dir(),
+            (None, None) => ws_path.clone(),
+        };
+
+        match launch {
+            Launch::Print => {
+                println!("{}", user_message);
+                Ok(())
+            }
+            Launch::Daemon => {
+                let ancillary_num = toren_lib::naming::slot_number(&segment.name, &ws_name_lower).unwrap_or(0);
+                let ancillary_id_str = toren_lib::ancillary_id(&segment.name, ancillary_num);
+                let assignment = assignment_mgr
+                    .get_active_for_ancillary(&ancillary_id_str)
+                    .cloned()
+                    .with_context(|| {
+                        format!(
+                            "Workspace '{}' has no assignment to hand off. Pass --task-id to record one.",
+                            ws_name_lower
+                        )
+                    })?;
+                start_daemon_work_after(config, &mut assignment_mgr, &assignment, after.as_ref(), agent_str.as_deref(), steal)
+                    .map(drop)
+            }
+            Launch::Exec | Launch::Wrap => {
+                // Refuse to start a second agent next to a live one
+                let owner = toren_lib::AssignmentOwner::cli();
+                if let Some(ref assignment) = existing {
+                    assignment_mgr.claim_owner(&assignment.id, owner.clone(), steal)?;
+                }
+
+                // Start agent session
+                eprintln!("Starting {} session in {}\n", agent, work_dir.display());
+                let mut cmd = agent.build_command(&user_message, &work_dir, system_prompt.as_deref());
+                cmd.args(&passthrough);
+                cmd.envs(workspace_env(&ws_path));
+
+                if launch == Launch::Wrap {
+                    let assignment_id = existing.map(|a| a.id);
+                    let status = run_wrapped(&agent, &logs, &mut cmd, &work_dir, assignment_id.clone())
+                        .with_context(|| format!("Failed to launch {}", agent.binary()))?;
+                    if let Some(ref id) = assignment_id {
+                        release_owner(id, &owner);
+                    }
+                    std::process::exit(status.code().unwrap_or(1));
+                }
+
+                let err = exec(&mut cmd);
+                Err(err).context(format!("Failed to exec {}", agent.binary()))
+            }
+        }
+    } else if let Some(count) = fan_out {
+        let mut table = table::Table::new(4);
+        let mut assigned = 0;
+        for index in 1..=count {
+            let variant = toren_lib::VariantInfo { index, count };
+            let title = inferred.task_title.clone().unwrap_or_else(|| {
+                user_message.lines().next().unwrap_or_default().chars().take(80).collect()
+            });
+            // Each variant's full prompt is recorded on its assignment, since
+            // there's no agent command line to carry a system prompt.
+            let outcome = assign_new_workspace(
+                config,
+                &workspace_mgr,
+                &mut assignment_mgr,
+                &segment,
+                settings.max_per_segment,
+                &inferred,
+                |ws| {
+                    let prompt = match render_intent(Some(variant), Some(ws))? {
+                        Some(system) => format!("{}\n\n{}", system, user_message),
+                        None => user_message.clone(),
+                    };
+                    Ok(AssignmentSource::Prompt {
+                        original_prompt: prompt,
+                        auto_created_bead: false,
+                    })
+                },
+                format!("{} [{}/{}]", title, index, count),
+                focus_path.as_deref(),
+                intent.as_deref(),
+                overflow,
+            );
+
+            let row = match outcome {
+                Ok(assignment) => {
+                    assigned += 1;
+                    let status = if launch == Launch::Daemon {
+                        let started = start_daemon_work_after(
+                            config,
+                            &mut assignment_mgr,
+                            &assignment,
+                            after.as_ref(),
+                            agent_str.as_deref(),
+                            false,
+                        );
+                        match started {
+                            Ok(true) => "started".to_string(),
+                            Ok(false) => format!("waiting for {}", after.as_deref().unwrap_or_default()),
+                            Err(e) => format!("assigned; daemon handoff failed: {:#}", e),
+                        }
+                    } else {
+                        "assigned".to_string()
+                    };
+                    vec![
+                        format!("{}/{}", index, count),
+                        assignment.ancillary_id.to_string(),
+                        toren_lib::tilde_shorten(&assignment.workspace_path),
+                        status,
+                    ]
+                }
+                Err(e) => vec![
+                    format!("{}/{}", index, count),
+                    "-".to_string(),
+                    "-".to_string(),
+                    format!("failed: {:#}", e),
+                ],
+            };
+            table.push_row(row);
+        }
+
+        for line in table.render(usize::MAX) {
+            println!("{}", line);
+        }
+        if assigned == 0 {
+            anyhow::bail!("No workspaces were assigned");
+        }
+        Ok(())
+    } else {
+        // Create new workspace
+        let ancillary_id_str = toren_lib::allocate_ancillary(
+            &mut assignment_mgr,
+            &workspace_mgr,
+            &segment.name,
+            &segment.path,
+            settings.max_per_segment,
+            overflow,
+            &logs,
+        )?;
+        let ancillary_num = toren_lib::ancillary_number(&ancillary_id_str).unwrap_or(1);
+        eprintln!("Ancillary: {}", ancillary_id_str);
 
         let base_branch = workspace_mgr.active_branch(&segment.path);
-        let ws_name = workspace_name_for_number(ancillary_num);
+        let ws_name = toren_lib::naming::workspace_name(&segment.name, ancillary_num);
 
         let (ws_path, _setup_result) = workspace_mgr.create_workspace_with_setup(
             &segment.path,
@@ -741,6 +1862,26 @@ fn cmd_do(
             ancillary_num,
         )?;
         eprintln!("Workspace: {}", ws_path.display());
+        let companions = match group_members {
+            Some(members) => {
+                match create_companion_workspaces(&workspace_mgr, &segment_mgr, &members[1..], &ws_name, ancillary_num) {
+                    Ok(companions) => companions,
+                    Err(e) => {
+                        if let Err(cleanup) = workspace_mgr.cleanup_workspace(
+                            &segment.path,
+                            &segment.name,
+                            &ws_name,
+                            toren_lib::CleanupMode::Abort,
+                        ) {
+                            eprintln!("warning: failed to remove workspace: {:#}", cleanup);
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            None => Vec::new(),
+        };
+        let system_prompt = render_intent(None, Some(workspace_info(&ws_name, ancillary_num, &ws_path)))?;
 
         // Record assignment
         let source = if inferred.task_id.is_some() {
@@ -748,6 +1889,7 @@ fn cmd_do(
         } else {
             AssignmentSource::Prompt {
                 original_prompt: user_message.clone(),
+                auto_created_bead: false,
             }
         };
 
@@ -762,9 +1904,9 @@ fn cmd_do(
                 .collect(),
         ));
 
-        assignment_mgr.create(
+        let assignment = assignment_mgr.create(
             &ancillary_id_str,
-            inferred.task_id.as_deref(),
+            inferred.task_id.as_ref(),
             source,
             &segment.name,
             ws_path.clone(),
@@ -773,15 +1915,501 @@ fn cmd_do(
             inferred.task_url.as_deref(),
             inferred.task_source.as_deref(),
         )?;
+        let assignment = with_focus_path(&mut assignment_mgr, assignment, focus_path.as_deref())?;
+        let assignment = with_intent(&mut assignment_mgr, assignment, intent.as_deref())?;
+        let assignment = match group {
+            Some(ref group) if !companions.is_empty() => {
+                assignment_mgr.set_companions(&assignment.id, group, companions)?;
+                assignment_mgr.get(&assignment.id).cloned().unwrap_or(assignment)
+            }
+            _ => assignment,
+        };
+        let work_dir = assignment.working_dir();
+        write_agent_context(&workspace_mgr, &segment.path, &assignment);
+        run_assign_hook(&assignment);
+        let user_message = match assignment.group_prompt() {
+            Some(workspaces) => format!("{}\n\n{}", user_message, workspaces),
+            None => user_message,
+        };
+
+        let err = match launch {
+            Launch::Print => {
+                println!("{}", user_message);
+                return Ok(());
+            }
+            Launch::Daemon => match start_daemon_work_after(
+                config,
+                &mut assignment_mgr,
+                &assignment,
+                after.as_ref(),
+                agent_str.as_deref(),
+                false,
+            ) {
+                Ok(_) => return Ok(()),
+                Err(e) => e,
+            },
+            Launch::Wrap => {
+                let owner = toren_lib::AssignmentOwner::cli();
+                assignment_mgr.claim_owner(&assignment.id, owner.clone(), false)?;
+                eprintln!("Starting {} session in {}\n", agent, work_dir.display());
+                let mut cmd = agent.build_command(&user_message, &work_dir, system_prompt.as_deref());
+                cmd.args(&passthrough);
+                cmd.envs(workspace_env(&assignment.workspace_path));
+
+                match run_wrapped(&agent, &logs, &mut cmd, &work_dir, Some(assignment.id.clone())) {
+                    Ok(status) => {
+                        release_owner(&assignment.id, &owner);
+                        std::process::exit(status.code().unwrap_or(1))
+                    }
+                    Err(e) => anyhow::Error::new(e).context(format!(
+                        "Failed to launch {}. Check that it runs from a shell, then retry.",
+                        agent.binary()
+                    )),
+                }
+            }
+            Launch::Exec => {
+                // The agent takes over this pid, so it owns the assignment
+                assignment_mgr.claim_owner(&assignment.id, toren_lib::AssignmentOwner::cli(), false)?;
+
+                // Exec into agent
+                eprintln!("Starting {} session in {}\n", agent, work_dir.display());
+                let mut cmd = agent.build_command(&user_message, &work_dir, system_prompt.as_deref());
+                cmd.args(&passthrough);
+                cmd.envs(workspace_env(&assignment.workspace_path));
+
+                // exec only returns on failure
+                anyhow::Error::new(exec(&mut cmd)).context(format!(
+                    "Failed to exec {}. Check that it runs from a shell, then retry.",
+                    agent.binary()
+                ))
+            }
+        };
+
+        // Don't leave a workspace nobody is working in
+        eprintln!("Failed to launch {}; removing workspace '{}'", agent, ws_name);
+        if let Err(e) = workspace_mgr.cleanup_workspace(
+            &segment.path,
+            &segment.name,
+            &ws_name,
+            toren_lib::CleanupMode::Abort,
+        ) {
+            eprintln!("warning: failed to remove workspace: {:#}", e);
+        }
+        for companion in &assignment.companions {
+            if let Err(e) = workspace_mgr.cleanup_workspace(
+                &companion.segment_path,
+                &companion.segment,
+                &ws_name,
+                toren_lib::CleanupMode::Abort,
+            ) {
+                eprintln!("warning: failed to remove workspace in {}: {:#}", companion.segment, e);
+            }
+        }
+        assignment_mgr.remove(&assignment.id)?;
+
+        Err(err)
+    }
+}
+
+/// Allocate the next ancillary, create its workspace and record an assignment.
+#[allow(clippy::too_many_arguments)]
+fn assign_new_workspace(
+    config: &Config,
+    workspace_mgr: &WorkspaceManager,
+    assignment_mgr: &mut AssignmentManager,
+    segment: &toren_lib::Segment,
+    pool_size: u32,
+    inferred: &toren_lib::InferredTaskFields,
+    source: impl FnOnce(toren_lib::WorkspaceInfo) -> Result<AssignmentSource>,
+    title: String,
+    focus_path: Option<&Path>,
+    intent: Option<&str>,
+    overflow: bool,
+) -> Result<toren_lib::Assignment> {
+    let ancillary_id_str = toren_lib::allocate_ancillary(
+        assignment_mgr,
+        workspace_mgr,
+        &segment.name,
+        &segment.path,
+        pool_size,
+        overflow,
+        &SessionLogs::from_config(config),
+    )?;
+    let ancillary_num = toren_lib::ancillary_number(&ancillary_id_str).unwrap_or(1);
+    let ws_name = toren_lib::naming::workspace_name(&segment.name, ancillary_num);
+    let base_branch = workspace_mgr.active_branch(&segment.path);
+
+    let (ws_path, _) = workspace_mgr.create_workspace_with_setup(
+        &segment.path,
+        &segment.name,
+        &ws_name,
+        ancillary_num,
+    )?;
+    let source = source(workspace_info(&ws_name, ancillary_num, &ws_path))?;
+
+    let assignment = assignment_mgr.create(
+        &ancillary_id_str,
+        inferred.task_id.as_ref(),
+        source,
+        &segment.name,
+        ws_path,
+        Some(title),
+        base_branch,
+        inferred.task_url.as_deref(),
+        inferred.task_source.as_deref(),
+    )?;
+    let assignment = with_focus_path(assignment_mgr, assignment, focus_path)?;
+    let assignment = with_intent(assignment_mgr, assignment, intent)?;
+    claim_as_ancillary(config, &segment.path, &assignment);
+    write_agent_context(workspace_mgr, &segment.path, &assignment);
+    run_assign_hook(&assignment);
+    Ok(assignment)
+}
+
+/// Create the workspaces of a segment group assignment in the group's other
+/// segments, named like the primary one. Removes those already created if
+/// one fails.
+fn create_companion_workspaces(
+    workspace_mgr: &WorkspaceManager,
+    segment_mgr: &SegmentManager,
+    segments: &[String],
+    ws_name: &str,
+    ancillary_num: u32,
+) -> Result<Vec<toren_lib::CompanionWorkspace>> {
+    let mut companions: Vec<toren_lib::CompanionWorkspace> = Vec::new();
+    let result = segments.iter().try_for_each(|name| -> Result<()> {
+        let segment = segment_mgr
+            .find_by_name(name)
+            .with_context(|| format!("Segment '{}' of the group not found", name))?;
+        if workspace_mgr.workspace_path(&segment.name, ws_name).exists() {
+            anyhow::bail!("Workspace '{}' already exists in {}", ws_name, segment.name);
+        }
+        let base_branch = workspace_mgr.active_branch(&segment.path);
+        let (workspace_path, _) =
+            workspace_mgr.create_workspace_with_setup(&segment.path, &segment.name, ws_name, ancillary_num)?;
+        eprintln!("Workspace ({}): {}", segment.name, workspace_path.display());
+        companions.push(toren_lib::CompanionWorkspace {
+            segment: segment.name,
+            segment_path: segment.path,
+            workspace_path,
+            base_branch,
+        });
+        Ok(())
+    });
+    if let Err(e) = result {
+        for companion in &companions {
+            if let Err(cleanup) = workspace_mgr.cleanup_workspace(
+                &companion.segment_path,
+                &companion.segment,
+                ws_name,
+                toren_lib::CleanupMode::Abort,
+            ) {
+                eprintln!("warning: failed to remove workspace in {}: {:#}", companion.segment, cleanup);
+            }
+        }
+        return Err(e);
+    }
+    Ok(companions)
+}
+
+/// Template `ws.*` fields for a workspace.
+fn workspace_info(name: &str, num: u32, path: &Path) -> toren_lib::WorkspaceInfo {
+    toren_lib::WorkspaceInfo {
+        name: name.to_string(),
+        num,
+        path: path.display().to_string(),
+    }
+}
+
+/// Record a focus path on a newly created assignment.
+fn with_focus_path(
+    assignment_mgr: &mut AssignmentManager,
+    mut assignment: toren_lib::Assignment,
+    focus_path: Option<&Path>,
+) -> Result<toren_lib::Assignment> {
+    if let Some(focus) = focus_path {
+        assignment_mgr.set_focus_path(&assignment.id, Some(focus.to_path_buf()))?;
+        assignment.focus_path = Some(focus.to_path_buf());
+    }
+    Ok(assignment)
+}
+
+/// Record the intent a newly created assignment was given, so completion
+/// knows whether to post a report.
+fn with_intent(
+    assignment_mgr: &mut AssignmentManager,
+    mut assignment: toren_lib::Assignment,
+    intent: Option<&str>,
+) -> Result<toren_lib::Assignment> {
+    if let Some(intent) = intent {
+        assignment_mgr.set_intent(&assignment.id, Some(intent.to_string()))?;
+        assignment.intent = Some(intent.to_string());
+    }
+    Ok(assignment)
+}
+
+/// Run the agent as a child process, recording its session ID on the
+/// assignment as soon as the session log appears, and its token usage once
+/// it exits.
+fn run_wrapped(
+    agent: &toren_lib::Agent,
+    logs: &SessionLogs,
+    cmd: &mut Command,
+    ws_path: &Path,
+    assignment_id: Option<String>,
+) -> std::io::Result<std::process::ExitStatus> {
+    // Session logs are specific to Claude Code; other agents just run wrapped
+    let watcher = match (agent.kind, &assignment_id) {
+        (toren_lib::AgentKind::Claude, Some(_)) => toren_lib::session::SessionWatcher::new(logs, ws_path),
+        _ => None,
+    };
+
+    let tracks_usage = watcher.is_some();
+    let session_assignment = assignment_id.clone();
+    let status = toren_lib::session::run_tracking_session(cmd, watcher, move |session_id| {
+        let Some(assignment_id) = session_assignment else {
+            return;
+        };
+        let recorded = AssignmentManager::new()
+            .and_then(|mut mgr| mgr.update_session_id(&assignment_id, Some(session_id)));
+        if let Err(e) = recorded {
+            tracing::warn!("Failed to record session ID: {:#}", e);
+        }
+    })?;
+
+    if let (true, Some(assignment_id)) = (tracks_usage, assignment_id) {
+        if let Err(e) = record_usage(logs, &assignment_id) {
+            tracing::warn!("Failed to record token usage: {:#}", e);
+        }
+    }
+    Ok(status)
+}
+
+/// Refresh an assignment's token usage from its session logs.
+fn record_usage(logs: &SessionLogs, assignment_id: &str) -> Result<()> {
+    let mut mgr = AssignmentManager::new()?;
+    let Some(assignment) = mgr.get(assignment_id).cloned() else {
+        return Ok(());
+    };
+    if let Some(usage) = toren_lib::usage::assignment_usage(logs, &assignment) {
+        mgr.set_usage(assignment_id, usage)?;
+    }
+    Ok(())
+}
+
+/// Clear this session's ownership once its agent exits.
+fn release_owner(assignment_id: &str, owner: &toren_lib::AssignmentOwner) {
+    let released = AssignmentManager::new().and_then(|mut mgr| mgr.release_owner(assignment_id, owner));
+    if let Err(e) = released {
+        tracing::warn!("Failed to release assignment ownership: {:#}", e);
+    }
+}
+
+/// Ask the daemon's work manager to run the agent for `assignment`, taking
+/// it over from a breq session that owns it if `steal`.
+fn start_daemon_work(
+    config: &Config,
+    assignment: &toren_lib::Assignment,
+    agent: Option<&str>,
+    steal: bool,
+) -> Result<()> {
+    let url = format!(
+        "http://{}:{}/api/ancillaries/{}/start",
+        config.host(),
+        config.port(),
+        urlencoding::encode(&assignment.ancillary_id)
+    );
+    let http = ureq::Agent::new_with_config(
+        ureq::config::Config::builder()
+            .timeout_global(Some(std::time::Duration::from_secs(30)))
+            .http_status_as_error(false)
+            .build(),
+    );
+
+    let mut body = serde_json::json!({ "assignment_id": assignment.id, "steal": steal });
+    if let Some(agent) = agent {
+        body["agent"] = serde_json::Value::String(agent.to_string());
+    }
+
+    let response = with_daemon_token(http.post(&url))
+        .send_json(&body)
+        .with_context(|| daemon_unreachable(config))?;
+
+    let status: u16 = response.status().into();
+    if status == 401 {
+        return Err(daemon_unauthorized(config).into());
+    }
+    if !(200..300).contains(&status) {
+        // The daemon tags each request with a correlation ID; surface it so the
+        // failure can be found in the daemon's logs.
+        let request_id = response
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let error = response
+            .into_body()
+            .read_json::<serde_json::Value>()
+            .ok()
+            .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(String::from))
+            .unwrap_or_else(|| format!("HTTP {}", status));
+        match request_id {
+            Some(id) => anyhow::bail!(
+                "Daemon refused to start work for {}: {} (request id: {})",
+                assignment.ancillary_id,
+                error,
+                id
+            ),
+            None => anyhow::bail!("Daemon refused to start work for {}: {}", assignment.ancillary_id, error),
+        }
+    }
+
+    eprintln!(
+        "Handed off {} to the daemon ({})",
+        assignment.ancillary_id,
+        assignment.workspace_path.display()
+    );
+    Ok(())
+}
+
+/// Hand work off to the daemon, unless it waits for a task that hasn't
+/// completed yet: then record the dependency for the daemon to start it
+/// once it does. Returns whether work started now.
+fn start_daemon_work_after(
+    config: &Config,
+    assignment_mgr: &mut AssignmentManager,
+    assignment: &toren_lib::Assignment,
+    after: Option<&toren_lib::TaskId>,
+    agent: Option<&str>,
+    steal: bool,
+) -> Result<bool> {
+    if let Some(after) = after {
+        if !assignment_mgr.completed_task_ids()?.contains(after) {
+            assignment_mgr.set_after(&assignment.id, Some(after.clone()))?;
+            eprintln!(
+                "{} waits for {}; the daemon starts it once that completes",
+                assignment.ancillary_id, after
+            );
+            return Ok(false);
+        }
+    }
+    start_daemon_work(config, assignment, agent, steal)?;
+    Ok(true)
+}
+
+// ─── resume ─────────────────────────────────────────────────────────────────
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_resume(
+    config: &Config,
+    reference: &str,
+    instruction: Option<String>,
+    instruction_file: Option<&Path>,
+    edit: bool,
+    segment_name: Option<&str>,
+    agent: Option<&str>,
+    steal: bool,
+) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
+    let instruction = match instruction_file {
+        Some(path) => Some(read_instruction_file(path)?),
+        None if edit => Some(edit_resume_instruction(config, &segment, reference)?),
+        None => instruction,
+    };
+    if instruction.as_deref().is_some_and(|i| i.trim().is_empty()) {
+        anyhow::bail!("Instruction is empty");
+    }
+
+    let mut body = serde_json::json!({ "instruction": instruction, "steal": steal });
+    if let Some(agent) = agent {
+        body["agent"] = serde_json::Value::String(agent.to_string());
+    }
+    let path = format!(
+        "/api/assignments/{}/resume?segment={}",
+        urlencoding::encode(reference),
+        urlencoding::encode(&segment.name)
+    );
+    let response = daemon_json(config, "POST", &path, Some(body))?;
+
+    let ancillary_id = response["assignment"]["ancillary_id"].as_str().unwrap_or(reference);
+    if response["workspace_recreated"].as_bool() == Some(true) {
+        eprintln!("Recreated the workspace of {}", ancillary_id);
+    }
+    if response["work_started"].as_bool() == Some(true) {
+        eprintln!("Resumed {} under the daemon", ancillary_id);
+    } else {
+        eprintln!("Prepared {} for resume; the daemon is already running work for it", ancillary_id);
+    }
+    Ok(())
+}
+
+/// Open $EDITOR on the assignment's previous instruction (or its original
+/// prompt), with the task's description below it as `#` comment lines; the
+/// edited text without comment lines.
+fn edit_resume_instruction(config: &Config, segment: &Segment, reference: &str) -> Result<String> {
+    let assignment = resolve_unique(&mut AssignmentManager::new()?, reference, &segment.name)?;
+
+    let previous = match (&assignment.resume_instruction, &assignment.source) {
+        (Some(instruction), _) => instruction.as_str(),
+        (None, AssignmentSource::Prompt { original_prompt, .. }) => original_prompt.as_str(),
+        (None, AssignmentSource::Reference) => "",
+    };
+    let mut template = format!("{}\n\n", previous.trim());
+    template.push_str(&format!(
+        "# Instruction for resuming {}. Lines starting with '#' are ignored;\n# an empty instruction cancels the resume.\n",
+        assignment.ancillary_id
+    ));
+    if let Some(ref task_id) = assignment.task_id {
+        let task = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins"))
+            .ok()
+            .and_then(|plugin_mgr| {
+                let ctx = toren_lib::PluginContext::new(Some(segment.path.clone()), Some(segment.name.clone()));
+                match assignment.task_source.as_deref() {
+                    Some(source) => plugin_mgr.resolve_info(source, task_id, ctx).ok(),
+                    None => plugin_mgr
+                        .resolve_info_multi(&plugin_mgr.effective_sources(&config.tasks.sources), task_id, ctx)
+                        .ok(),
+                }
+            });
+        let title = task
+            .as_ref()
+            .map(|t| t.title.as_str())
+            .or(assignment.task_title.as_deref())
+            .unwrap_or_default();
+        template.push_str(&format!("#\n# {}: {}\n", task_id, title));
+        if let Some(description) = task.and_then(|t| t.description) {
+            template.push_str("#\n");
+            for line in description.trim().lines() {
+                template.push_str(format!("# {}", line).trim_end());
+                template.push('\n');
+            }
+        }
+    }
 
-        // Exec into agent
-        eprintln!("Starting {} session in {}\n", agent, ws_path.display());
-        let mut cmd = agent.build_command(&user_message, &ws_path, system_prompt.as_deref());
-        cmd.args(&passthrough);
+    let text = edit::edit(&template).context("Editor returned an error")?;
+    let instruction = text
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+    if instruction.is_empty() {
+        anyhow::bail!("Empty instruction from editor; not resuming {}", assignment.ancillary_id);
+    }
+    Ok(instruction)
+}
 
-        let err = cmd.exec();
-        Err(err).context(format!("Failed to exec {}", agent.kind.binary_name()))
+/// An instruction file's contents, or stdin's for `-`.
+fn read_instruction_file(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)
+            .context("Failed to read the instruction from stdin")?;
+        return Ok(text);
     }
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
 }
 
 // ─── shell ──────────────────────────────────────────────────────────────────
@@ -791,19 +2419,25 @@ fn cmd_shell(
     config: &Config,
     workspace: Option<String>,
     hook: Option<HookArg>,
+    no_cache: bool,
     task_id_arg: Option<String>,
     task_title_arg: Option<String>,
     task_url_arg: Option<String>,
     segment_name: Option<&str>,
+    overflow: bool,
     cmd: Vec<String>,
 ) -> Result<()> {
     // Hook mode: run setup/destroy from cwd
     if let Some(hook_type) = hook {
         let workspace_root = config.ancillaries.workspace_root.clone();
-        let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()));
+        let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+            .with_proxy_backend(Some(config.proxy.backend.clone()))
+            .with_host_template(config.proxy.host_template.clone())
+            .with_segment_roots(config.segment_workspace_roots());
 
-        let (segment_path, workspace_path, workspace_name) = detect_workspace_context()?;
-        let ancillary_num = toren_lib::word_to_number(&workspace_name);
+        let (segment_path, workspace_path, workspace_name) = detect_workspace_context(no_cache)?;
+        let segment_name = segment_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let ancillary_num = toren_lib::naming::slot_number(segment_name, &workspace_name);
 
         match hook_type {
             HookArg::Setup => {
@@ -812,12 +2446,13 @@ fn cmd_shell(
                     workspace_name,
                     workspace_path.display()
                 );
-                workspace_mgr.run_setup(
+                let result = workspace_mgr.run_setup(
                     &segment_path,
                     &workspace_path,
                     &workspace_name,
                     ancillary_num.unwrap_or(0),
                 )?;
+                print_failed_actions(&result.failed);
                 eprintln!("Setup complete.");
             }
             HookArg::Destroy => {
@@ -826,11 +2461,12 @@ fn cmd_shell(
                     workspace_name,
                     workspace_path.display()
                 );
-                workspace_mgr.run_destroy(
+                let result = workspace_mgr.run_destroy(
                     &segment_path,
                     &workspace_path,
                     &workspace_name,
                 )?;
+                print_failed_actions(&result.failed);
                 eprintln!("Destroy complete.");
             }
         }
@@ -839,51 +2475,55 @@ fn cmd_shell(
 
     let workspace_root = config.ancillaries.workspace_root.clone();
 
-    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()));
+    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+        .with_proxy_backend(Some(config.proxy.backend.clone()))
+        .with_host_template(config.proxy.host_template.clone())
+        .with_segment_roots(config.segment_workspace_roots())
+        .with_context_file(config.ancillaries.context_file.clone())
+        .with_envrc(config.ancillaries.envrc);
     let segment_mgr = SegmentManager::new(config)?;
     let segment = resolve_segment(&segment_mgr, segment_name)?;
 
     if let Some(ref ws_name) = workspace {
-        let ws_name_lower = ws_name.to_lowercase();
+        let ws_name_lower = toren_lib::naming::normalize_workspace_name(&segment.name, ws_name);
         let ws_path = workspace_mgr.workspace_path(&segment.name, &ws_name_lower);
 
-        if !ws_path.exists() {
-            anyhow::bail!("Workspace '{}' not found at {}", ws_name_lower, ws_path.display());
-        }
-
         let (program, args): (String, Vec<String>) = if cmd.is_empty() {
-            let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+            let shell = default_shell();
             (shell, vec![])
         } else {
             (cmd[0].clone(), cmd[1..].to_vec())
         };
 
         println!("{}", ws_path.display());
-        let err = Command::new(&program)
-            .args(&args)
-            .current_dir(&ws_path)
-            .exec();
+        let err = exec(
+            Command::new(&program)
+                .args(&args)
+                .current_dir(&ws_path)
+                .envs(workspace_env(&ws_path)),
+        );
         Err(err).with_context(|| format!("Failed to exec: {}", program))
     } else if !cmd.is_empty() {
         // No workspace, command given — run in cwd
         let (program, args) = (cmd[0].clone(), cmd[1..].to_vec());
-        let err = Command::new(&program).args(&args).exec();
+        let err = exec(Command::new(&program).args(&args));
         Err(err).with_context(|| format!("Failed to exec: {}", program))
     } else {
         // No workspace, no command — create new workspace and drop into shell
         let mut assignment_mgr = AssignmentManager::new()?;
-        let existing_workspaces = workspace_mgr
-            .list_workspaces(&segment.path)
-            .unwrap_or_default();
-        let ancillary_id_str = assignment_mgr.next_available_ancillary(
+        let ancillary_id_str = toren_lib::allocate_ancillary(
+            &mut assignment_mgr,
+            &workspace_mgr,
             &segment.name,
-            config.ancillaries.max_per_segment,
-            &existing_workspaces,
-        );
+            &segment.path,
+            segment_mgr.settings(&segment.name).max_per_segment,
+            overflow,
+            &SessionLogs::from_config(config),
+        )?;
         let ancillary_num = toren_lib::ancillary_number(&ancillary_id_str).unwrap_or(1);
 
         let base_branch = workspace_mgr.active_branch(&segment.path);
-        let ws_name = workspace_name_for_number(ancillary_num);
+        let ws_name = toren_lib::naming::workspace_name(&segment.name, ancillary_num);
 
         let (ws_path, _) = workspace_mgr.create_workspace_with_setup(
             &segment.path,
@@ -905,12 +2545,13 @@ fn cmd_shell(
         } else {
             AssignmentSource::Prompt {
                 original_prompt: "(interactive shell)".to_string(),
+                auto_created_bead: false,
             }
         };
 
-        assignment_mgr.create(
+        let assignment = assignment_mgr.create(
             &ancillary_id_str,
-            inferred.task_id.as_deref(),
+            inferred.task_id.as_ref(),
             source,
             &segment.name,
             ws_path.clone(),
@@ -919,50 +2560,410 @@ fn cmd_shell(
             inferred.task_url.as_deref(),
             inferred.task_source.as_deref(),
         )?;
+        write_agent_context(&workspace_mgr, &segment.path, &assignment);
+        run_assign_hook(&assignment);
 
         eprintln!("Created workspace: {}", ws_path.display());
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        let shell = default_shell();
         println!("{}", ws_path.display());
-        let err = Command::new(&shell).current_dir(&ws_path).exec();
+        let err = exec(
+            Command::new(&shell)
+                .current_dir(&ws_path)
+                .envs(workspace_env(&ws_path)),
+        );
         Err(err).context("Failed to exec shell")
     }
 }
 
-// ─── list ───────────────────────────────────────────────────────────────────
+/// Warn about setup or destroy actions that failed without stopping the block.
+fn print_failed_actions(failed: &[toren_lib::ActionFailure]) {
+    for action in failed {
+        let what = if action.timed_out { "timed out" } else { "failed" };
+        eprintln!("warning: action {} {}: {}", action.index, what, action.label);
+        eprintln!("  {}", action.error);
+    }
+}
 
-fn cmd_list(
+// ─── go ─────────────────────────────────────────────────────────────────────
+
+/// A workspace `breq go` can enter.
+struct GoTarget {
+    workspace: String,
+    task_id: Option<String>,
+    title: Option<String>,
+    path: PathBuf,
+    /// The active assignment holding the workspace
+    assignment: Option<toren_lib::Assignment>,
+}
+
+/// The segment's workspaces, assigned ones with their task, in slot order.
+fn go_targets(config: &Config, segment: &Segment) -> Result<Vec<GoTarget>> {
+    let workspace_mgr = WorkspaceManager::new(
+        config.ancillaries.workspace_root.clone(),
+        Some(config.proxy.domain.clone()),
+    )
+    .with_segment_roots(config.segment_workspace_roots());
+    let mut assignment_mgr = AssignmentManager::new()?;
+
+    let mut targets: Vec<GoTarget> = Vec::new();
+    for assignment in assignment_mgr.list_active_segment(&segment.name) {
+        let Some(num) = toren_lib::ancillary_number(&assignment.ancillary_id) else {
+            continue;
+        };
+        targets.push(GoTarget {
+            workspace: toren_lib::naming::workspace_name(&segment.name, num),
+            task_id: assignment.task_id.as_ref().map(|id| id.to_string()),
+            title: assignment.task_title.clone(),
+            path: assignment.workspace_path.clone(),
+            assignment: Some(assignment.clone()),
+        });
+    }
+    for ws_name in workspace_mgr.list_workspaces(&segment.path).unwrap_or_default() {
+        if !targets.iter().any(|t| t.workspace == ws_name) {
+            let path = workspace_mgr.workspace_path(&segment.name, &ws_name);
+            targets.push(GoTarget {
+                workspace: ws_name,
+                task_id: None,
+                title: None,
+                path,
+                assignment: None,
+            });
+        }
+    }
+    targets.sort_by_key(|t| toren_lib::naming::slot_number(&segment.name, &t.workspace).unwrap_or(u32::MAX));
+    Ok(targets)
+}
+
+fn cmd_go(
     config: &Config,
-    reference: Option<String>,
-    all_segments: bool,
-    segment_name: Option<String>,
-    detail: bool,
+    reference: Option<&str>,
+    list: bool,
+    layout: bool,
+    segment_name: Option<&str>,
 ) -> Result<()> {
-    let workspace_root = config.ancillaries.workspace_root.clone();
     let segment_mgr = SegmentManager::new(config)?;
-    let mut assignment_mgr = AssignmentManager::new()?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
+    let targets = go_targets(config, &segment)?;
 
-    // If a specific reference is given and --detail, show detailed info
-    if let Some(ref reference) = reference {
-        if detail {
-            return cmd_list_detail(config, &segment_mgr, &mut assignment_mgr, reference);
+    let reference = match reference {
+        Some(reference) if !list => reference,
+        _ => {
+            if targets.is_empty() {
+                println!("No workspaces in segment '{}'", segment.name);
+            } else {
+                print_go_targets(&targets);
+            }
+            return Ok(());
         }
+    };
+
+    let target = match_go_target(&targets, reference, &segment.name)?;
+    if !target.path.exists() {
+        anyhow::bail!("Workspace '{}' not found at {}", target.workspace, target.path.display());
     }
+    if layout {
+        return open_layout(&segment.name, target);
+    }
+    let shell = default_shell();
+    println!("{}", target.path.display());
+    let err = exec(
+        Command::new(&shell)
+            .current_dir(&target.path)
+            .envs(workspace_env(&target.path)),
+    );
+    Err(err).with_context(|| format!("Failed to exec: {}", shell))
+}
 
-    // Determine which segment(s) to list
-    let (assignments, segments, scope_label): (Vec<_>, Vec<Segment>, String) = if all_segments {
-        let assignments = assignment_mgr.list_active().into_iter().collect::<Vec<_>>();
-        let segments = segment_mgr.list_all();
-        (assignments, segments, "all segments".to_string())
-    } else if let Some(ref name) = segment_name {
-        let assignments = assignment_mgr
-            .list_active_segment(name)
-            .into_iter()
-            .collect::<Vec<_>>();
-        let segments = segment_mgr
-            .find_by_name(name)
-            .map(|s| vec![s])
-            .unwrap_or_default();
-        (assignments, segments, name.clone())
+/// Exec into the workspace's layout session, creating it from
+/// `.toren/layout.kdl` unless it is already running.
+fn open_layout(segment: &str, target: &GoTarget) -> Result<()> {
+    use toren_lib::layout::{Layout, Multiplexer};
+
+    let layout = Layout::load(&target.path)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No layout in workspace '{}'; add {} to the repo (see the README)",
+            target.workspace,
+            toren_lib::layout::LAYOUT_FILE
+        )
+    })?;
+    let mux = layout.multiplexer()?;
+    let session = toren_lib::layout::session_name(segment, &target.workspace);
+    let env = workspace_env(&target.path);
+
+    let mut cmd = Command::new(mux.binary());
+    match mux {
+        Multiplexer::Tmux => {
+            let nested = std::env::var_os("TMUX").is_some();
+            let running = Command::new("tmux")
+                .args(["has-session", "-t", &format!("={}", session)])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success());
+            if running {
+                cmd.args([if nested { "switch-client" } else { "attach-session" }, "-t", &session]);
+            } else {
+                cmd.args(layout.tmux_args(&session, &target.path, &env, nested));
+            }
+        }
+        Multiplexer::Zellij => {
+            if std::env::var_os("ZELLIJ").is_some() {
+                anyhow::bail!("Already inside a zellij session; run `breq go --layout` from a plain terminal");
+            }
+            let running = Command::new("zellij")
+                .args(["list-sessions", "--short"])
+                .output()
+                .map(|o| String::from_utf8_lossy(&o.stdout).lines().any(|l| l.trim() == session))
+                .unwrap_or(false);
+            if running {
+                cmd.args(["attach", &session]);
+            } else {
+                let layout_path = toren_lib::toren_root()
+                    .join("layouts")
+                    .join(format!("{}.kdl", session));
+                if let Some(parent) = layout_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&layout_path, layout.zellij_layout(&target.path))
+                    .with_context(|| format!("Failed to write {}", layout_path.display()))?;
+                cmd.arg("--session").arg(&session).arg("--layout").arg(&layout_path);
+                cmd.envs(env);
+            }
+        }
+    }
+
+    println!("{}", target.path.display());
+    let err = exec(cmd.current_dir(&target.path));
+    Err(err).with_context(|| format!("Failed to exec: {}", mux))
+}
+
+/// Replace breq with `cmd`; only returns on failure. Windows has no exec, so
+/// there the command runs as a child and breq exits with its status.
+#[cfg(unix)]
+fn exec(cmd: &mut Command) -> std::io::Error {
+    use std::os::unix::process::CommandExt;
+    cmd.exec()
+}
+
+#[cfg(not(unix))]
+fn exec(cmd: &mut Command) -> std::io::Error {
+    match cmd.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => e,
+    }
+}
+
+/// The user's shell: `$SHELL`, else `/bin/sh` (`%COMSPEC%` on Windows).
+fn default_shell() -> String {
+    if let Ok(shell) = std::env::var("SHELL") {
+        return shell;
+    }
+    if cfg!(windows) {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    } else {
+        "/bin/sh".to_string()
+    }
+}
+
+/// Variables setup exported to the workspace's `.toren-env` (toren.kdl's
+/// `env { ... }` block), for shells and agents started in it.
+fn workspace_env(workspace_path: &Path) -> Vec<(String, String)> {
+    toren_lib::read_workspace_env(workspace_path).unwrap_or_else(|e| {
+        eprintln!("Warning: ignoring {}: {:#}", toren_lib::WORKSPACE_ENV_FILE, e);
+        Vec::new()
+    })
+}
+
+/// Resolve a `breq go` reference: an exact workspace name or number, or task
+/// ID, wins; otherwise it must be a prefix of exactly one workspace name or
+/// task ID.
+fn match_go_target<'a>(targets: &'a [GoTarget], reference: &str, segment: &str) -> Result<&'a GoTarget> {
+    let needle = reference.to_lowercase();
+    let number = toren_lib::naming::slot_number(segment, reference);
+    let exact = targets.iter().find(|t| {
+        t.workspace == needle
+            || (number.is_some() && toren_lib::naming::slot_number(segment, &t.workspace) == number)
+            || t.task_id.as_deref().is_some_and(|id| id.to_lowercase() == needle)
+    });
+    if let Some(target) = exact {
+        return Ok(target);
+    }
+
+    let matches: Vec<&GoTarget> = targets
+        .iter()
+        .filter(|t| {
+            t.workspace.starts_with(&needle)
+                || t.task_id.as_deref().is_some_and(|id| id.to_lowercase().starts_with(&needle))
+        })
+        .collect();
+    match matches.as_slice() {
+        [] => Err(TorenError::new(
+            ErrorCode::AssignmentNotFound,
+            format!("No workspace or task matches '{}'; run `breq go` to list them", reference),
+        )
+        .into()),
+        [one] => Ok(*one),
+        _ => {
+            let names: Vec<String> = matches
+                .iter()
+                .map(|t| match t.task_id {
+                    Some(ref id) => format!("{} ({})", t.workspace, id),
+                    None => t.workspace.clone(),
+                })
+                .collect();
+            anyhow::bail!("'{}' matches {}: {}", reference, matches.len(), names.join(", "))
+        }
+    }
+}
+
+fn print_go_targets(targets: &[GoTarget]) {
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(4).flex(2);
+    for target in targets {
+        table.push_row(vec![
+            target.workspace.bold().to_string(),
+            target.task_id.clone().unwrap_or_else(|| "-".to_string()),
+            target.title.clone().unwrap_or_default(),
+            toren_lib::tilde_shorten(&target.path).dimmed().to_string(),
+        ]);
+    }
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
+}
+
+// ─── open ───────────────────────────────────────────────────────────────────
+
+/// Open a workspace in an editor with `[ancillaries.editor] command`, or
+/// print the URI that opens it from another machine.
+fn cmd_open(config: &Config, reference: &str, uri: bool, segment_name: Option<&str>) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
+    let targets = go_targets(config, &segment)?;
+    let target = match_go_target(&targets, reference, &segment.name)?;
+    if !target.path.exists() {
+        anyhow::bail!("Workspace '{}' not found at {}", target.workspace, target.path.display());
+    }
+
+    let editor = &config.ancillaries.editor;
+    let ctx = toren_lib::assignment_ops::workspace_context(
+        &segment.name,
+        &segment.path,
+        &target.path,
+        target.assignment.as_ref(),
+    );
+    if uri || (editor.print_uri_over_ssh && toren_lib::editor::in_ssh_session()) {
+        println!("{}", editor.uri(&ctx)?);
+        return Ok(());
+    }
+
+    let command = editor.command(&ctx)?;
+    let shell = default_shell();
+    let flag = if cfg!(windows) { "/C" } else { "-c" };
+    let status = Command::new(&shell)
+        .arg(flag)
+        .arg(&command)
+        .current_dir(&target.path)
+        .envs(workspace_env(&target.path))
+        .status()
+        .with_context(|| format!("Failed to run: {}", command))?;
+    if !status.success() {
+        anyhow::bail!("`{}` exited with {}", command, status);
+    }
+    Ok(())
+}
+
+// ─── ps ─────────────────────────────────────────────────────────────────────
+
+fn cmd_ps(config: &Config, workspace: &str, kill: &[i32], segment_name: Option<&str>) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
+    let workspace_mgr = WorkspaceManager::new(
+        config.ancillaries.workspace_root.clone(),
+        Some(config.proxy.domain.clone()),
+    )
+    .with_segment_roots(config.segment_workspace_roots());
+
+    let ws_name = toren_lib::naming::normalize_workspace_name(&segment.name, workspace);
+    let ws_path = workspace_mgr.workspace_path(&segment.name, &ws_name);
+    if !ws_path.exists() {
+        anyhow::bail!("Workspace '{}' not found at {}", ws_name, ws_path.display());
+    }
+
+    if !kill.is_empty() {
+        let processes = toren_lib::process::select_workspace_processes(&ws_path, kill)?;
+        toren_lib::process::terminate_processes(&processes, std::time::Duration::from_secs(5))?;
+        for process in &processes {
+            eprintln!("Terminated {}", process);
+        }
+        return Ok(());
+    }
+
+    let processes = toren_lib::process::find_workspace_processes(&ws_path);
+    if processes.is_empty() {
+        println!("No processes running in workspace '{}'", ws_name);
+        return Ok(());
+    }
+
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(3).flex(2);
+    for process in &processes {
+        table.push_row(vec![
+            process.pid.to_string().bold().to_string(),
+            process.started_at.as_deref().map(format_age).unwrap_or_else(|| "-".to_string()),
+            process.command.clone(),
+        ]);
+    }
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// ─── list ───────────────────────────────────────────────────────────────────
+
+fn cmd_list(
+    config: &Config,
+    reference: Option<String>,
+    all_segments: bool,
+    segment_name: Option<String>,
+    detail: bool,
+    columns: Option<Vec<ListColumn>>,
+) -> Result<()> {
+    let workspace_root = config.ancillaries.workspace_root.clone();
+    let segment_mgr = SegmentManager::new(config)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let logs = SessionLogs::from_config(config);
+
+    // If a specific reference is given and --detail, show detailed info
+    if let Some(ref reference) = reference {
+        if detail {
+            return cmd_list_detail(config, &segment_mgr, &mut assignment_mgr, reference);
+        }
+    }
+
+    // Tasks whose completion unblocks assignments recorded with `--after`
+    let completed = assignment_mgr.completed_task_ids()?;
+
+    // Determine which segment(s) to list
+    let (assignments, segments, scope_label): (Vec<_>, Vec<Segment>, String) = if all_segments {
+        let assignments = assignment_mgr.list_active().into_iter().collect::<Vec<_>>();
+        let segments = segment_mgr.list_all();
+        (assignments, segments, "all segments".to_string())
+    } else if let Some(ref name) = segment_name {
+        let assignments = assignment_mgr
+            .list_active_segment(name)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let segments = segment_mgr
+            .find_by_name(name)
+            .map(|s| vec![s])
+            .unwrap_or_default();
+        (assignments, segments, name.clone())
     } else {
         let segment = resolve_segment(&segment_mgr, None)?;
         let name = segment.name.clone();
@@ -979,89 +2980,157 @@ fn cmd_list(
         .map(|(w, _)| w.0 as usize)
         .unwrap_or(80);
 
-    // Compute workspace column width from the longest name we'll display
-    let ws_col_width = assignments
-        .iter()
-        .map(|a| {
-            let name = if all_segments {
-                a.ancillary_id.as_str()
-            } else {
-                a.ancillary_id.split_whitespace().last().unwrap_or(&a.ancillary_id)
-            };
-            // Account for " *" dirty suffix
-            name.len() + 2
-        })
-        .max()
-        .unwrap_or(10);
+    let columns = columns.unwrap_or_else(|| config.ui.list_columns.clone());
+    let plugin_mgr = if columns.contains(&ListColumn::Assignee) {
+        toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins")).ok()
+    } else {
+        None
+    };
 
-    // Fixed column widths: workspace(dynamic) + ext_id(15) + activity(6) + spaces(3)
-    let fixed_width: usize = ws_col_width + 15 + 6 + 3;
+    let mut table = table::Table::new(columns.len());
+    if let Some(title) = columns.iter().position(|c| *c == ListColumn::Title) {
+        table = table.flex(title);
+    }
 
     for assignment in &assignments {
-        // Agent activity
-        let agent_activity = toren_lib::composite_status::detect_agent_activity(
-            &assignment.workspace_path,
-        );
-
-        // Has changes
-        let has_changes = toren_lib::composite_status::workspace_has_changes(
-            &assignment.workspace_path,
-            assignment.base_branch.as_deref(),
-        );
-
-        // Workspace name — extract short name, mark dirty with *
-        // In --all mode, use full ancillary ID for disambiguation
-        let ancillary_name = if all_segments {
-            assignment.ancillary_id.as_str()
-        } else {
-            assignment
-                .ancillary_id
-                .split_whitespace()
-                .last()
-                .unwrap_or(&assignment.ancillary_id)
-        };
-
-        let ws_text = if has_changes {
-            format!("{:<width$}", format!("{} *", ancillary_name), width = ws_col_width)
-        } else {
-            format!("{:<width$}", ancillary_name, width = ws_col_width)
-        };
-        let ws_colored = if has_changes {
-            ws_text.yellow()
-        } else {
-            ws_text.normal()
-        };
+        let has_changes = (columns.contains(&ListColumn::Workspace)
+            || columns.contains(&ListColumn::Changes))
+            && toren_lib::composite_status::workspace_has_changes(
+                &assignment.workspace_path,
+                assignment.base_branch.as_deref(),
+                assignment.focus_path.as_deref(),
+            );
+        let has_conflicts = has_changes
+            && !toren_lib::composite_status::workspace_conflicts(&assignment.workspace_path).is_empty();
 
-        // Task ID (if any)
-        let task_id_display = assignment
-            .task_id
-            .as_deref()
-            .unwrap_or("-");
+        let row = columns
+            .iter()
+            .map(|column| match column {
+                ListColumn::Workspace => {
+                    // Short name, marked dirty with * or conflicted; in --all
+                    // mode use the full ancillary ID for disambiguation
+                    let ancillary_name = if all_segments {
+                        assignment.ancillary_id.as_str()
+                    } else {
+                        assignment
+                            .ancillary_id
+                            .split_whitespace()
+                            .last()
+                            .unwrap_or(&assignment.ancillary_id)
+                    };
+                    if has_conflicts {
+                        format!("{} conflict", ancillary_name).red().to_string()
+                    } else if has_changes {
+                        format!("{} *", ancillary_name).yellow().to_string()
+                    } else {
+                        ancillary_name.to_string()
+                    }
+                }
+                ListColumn::Bead => assignment.task_id.as_ref().map_or_else(|| "-".to_string(), |id| id.to_string()),
+                ListColumn::Activity if assignment.blocked_on(&completed).is_some() => {
+                    "blocked".blue().to_string()
+                }
+                ListColumn::Activity => {
+                    let activity = toren_lib::composite_status::detect_agent_activity(
+                        &logs,
+                        &assignment.working_dir(),
+                    );
+                    if activity == "busy" {
+                        activity.yellow().to_string()
+                    } else {
+                        activity.green().to_string()
+                    }
+                }
+                ListColumn::Assignee => {
+                    let seg_path = segments
+                        .iter()
+                        .find(|s| s.name == assignment.segment)
+                        .map(|s| s.path.clone());
+                    let assignee = plugin_mgr
+                        .as_ref()
+                        .zip(assignment.task_id.as_deref().zip(assignment.task_source.as_deref()))
+                        .and_then(|(mgr, (id, source))| {
+                            let ctx = toren_lib::PluginContext::new(
+                                seg_path.clone(),
+                                Some(assignment.segment.clone()),
+                            );
+                            mgr.resolve_info(source, id, ctx).ok()?.assignee
+                        })
+                        .filter(|a| !a.is_empty());
+                    match assignee {
+                        Some(assignee) => {
+                            // Flag tasks claimed by someone other than this ancillary
+                            let expected = toren_lib::render_claim_identity(
+                                config.tasks.claim_identity.as_deref(),
+                                &assignment.segment,
+                                seg_path.as_deref().unwrap_or(Path::new("")),
+                                &assignment.ancillary_id,
+                            );
+                            if assignee == expected {
+                                assignee
+                            } else {
+                                format!("{} !", assignee).red().to_string()
+                            }
+                        }
+                        None => "-".to_string(),
+                    }
+                }
+                ListColumn::Title => assignment
+                    .task_title
+                    .clone()
+                    .unwrap_or_else(|| "-".to_string()),
+                ListColumn::Changes => {
+                    if has_conflicts {
+                        "conflict".red().to_string()
+                    } else if has_changes {
+                        "yes".yellow().to_string()
+                    } else {
+                        "no".to_string()
+                    }
+                }
+                ListColumn::Age => format_age(&assignment.created_at),
+                ListColumn::Labels => {
+                    if assignment.labels.is_empty() {
+                        "-".to_string()
+                    } else {
+                        assignment.labels.join(",")
+                    }
+                }
+            })
+            .collect();
+        table.push_row(row);
+    }
 
-        let activity_text = format!("{:<6}", agent_activity);
-        let activity_colored = if agent_activity == "busy" {
-            activity_text.yellow()
-        } else {
-            activity_text.green()
-        };
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
 
-        // Always show title; in detail mode show all task fields
-        let title_max = term_width.saturating_sub(fixed_width);
-        let title = assignment
-            .task_title
-            .as_deref()
-            .map(|t| truncate_title(t, title_max))
-            .unwrap_or_else(|| "-".to_string());
+    for assignment in &assignments {
+        if let Some(after) = assignment.blocked_on(&completed) {
+            eprintln!("{} is waiting for {} to complete", assignment.ancillary_id, after);
+        }
+    }
 
-        println!(
-            "{} {:<15} {} {}",
-            ws_colored, task_id_display, activity_colored, title
-        );
+    // Same checks as the daemon's health monitor, from session logs alone
+    let stall_after = std::time::Duration::from_secs(config.ancillaries.stall_minutes * 60);
+    for assignment in &assignments {
+        let health = toren_lib::health::check_health(&logs, assignment, false, stall_after);
+        if health.state.is_warning() {
+            eprintln!(
+                "warning: {} is {}: {}",
+                assignment.ancillary_id,
+                health.state.as_str().replace('_', " "),
+                health.detail.as_deref().unwrap_or("")
+            );
+        }
     }
 
     // Detect orphaned workspace directories
     {
-        let ws_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()));
+        let ws_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+            .with_proxy_backend(Some(config.proxy.backend.clone()))
+            .with_host_template(config.proxy.host_template.clone())
+            .with_segment_roots(config.segment_workspace_roots());
         let orphans = find_orphaned_workspaces(&ws_mgr, &segments, &assignments);
 
         if !orphans.is_empty() {
@@ -1096,7 +3165,11 @@ fn cmd_list_detail(
     let assignments = assignment_mgr.resolve(&ref_);
 
     if assignments.is_empty() {
-        anyhow::bail!("No assignment found for: {}", reference);
+        return Err(TorenError::new(
+            ErrorCode::AssignmentNotFound,
+            format!("No assignment found for: {}", reference),
+        )
+        .into());
     }
 
     for assignment in assignments {
@@ -1116,7 +3189,17 @@ fn cmd_list_detail(
         }
         println!("  Segment:      {}", assignment.segment);
         println!("  Status:       {:?}", assignment.status);
-        println!("  Source:       {:?}", assignment.source);
+        match assignment.source {
+            AssignmentSource::Reference => println!("  Source:       reference"),
+            AssignmentSource::Prompt { auto_created_bead, .. } => println!(
+                "  Source:       prompt{}",
+                if auto_created_bead {
+                    " (task auto-created; `breq abort --delete-bead` deletes it)"
+                } else {
+                    ""
+                }
+            ),
+        }
         println!("  Workspace:    {}", assignment.workspace_path.display());
         if let Some(ref branch) = assignment.base_branch {
             println!("  Base:         {}", branch);
@@ -1127,6 +3210,20 @@ fn cmd_list_detail(
         println!("  Created:      {}", assignment.created_at);
         println!("  Updated:      {}", assignment.updated_at);
 
+        if let AssignmentSource::Prompt { ref original_prompt, .. } = assignment.source {
+            println!("\nPrompt:");
+            for line in original_prompt.lines() {
+                println!("  {}", line);
+            }
+        }
+
+        if !assignment.notes.is_empty() {
+            println!("\nNotes:");
+            for line in format_notes(&assignment.notes) {
+                println!("  {}", line);
+            }
+        }
+
         // Show workspace info if exists
         if assignment.workspace_path.exists() {
             println!("\nRecent changes:");
@@ -1177,10 +3274,16 @@ fn cmd_setup(
     task_title_arg: Option<String>,
     task_url_arg: Option<String>,
     segment_name: Option<&str>,
+    overflow: bool,
 ) -> Result<()> {
     let workspace_root = config.ancillaries.workspace_root.clone();
 
-    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()));
+    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+        .with_proxy_backend(Some(config.proxy.backend.clone()))
+        .with_host_template(config.proxy.host_template.clone())
+        .with_segment_roots(config.segment_workspace_roots())
+        .with_context_file(config.ancillaries.context_file.clone())
+        .with_envrc(config.ancillaries.envrc);
     let segment_mgr = SegmentManager::new(config)?;
     let mut assignment_mgr = AssignmentManager::new()?;
 
@@ -1212,18 +3315,18 @@ fn cmd_setup(
 
     // If workspace exists, just update assignment fields and return
     if let Some(ref ws_name) = workspace {
-        let ws_name_lower = ws_name.to_lowercase();
+        let ws_name_lower = toren_lib::naming::normalize_workspace_name(&segment.name, ws_name);
         let ws_path = workspace_mgr.workspace_path(&segment.name, &ws_name_lower);
 
         if ws_path.exists() {
-            let ancillary_num = toren_lib::word_to_number(&ws_name_lower).unwrap_or(0);
+            let ancillary_num = toren_lib::naming::slot_number(&segment.name, &ws_name_lower).unwrap_or(0);
             let ancillary_id_str = toren_lib::ancillary_id(&segment.name, ancillary_num);
 
             if inferred.task_id.is_some() || inferred.task_title.is_some() || inferred.task_url.is_some() {
                 if let Some(assignment) = assignment_mgr.get_active_for_ancillary(&ancillary_id_str).cloned() {
                     if assignment_mgr.update_task_fields(
                         &assignment.id,
-                        inferred.task_id.as_deref(),
+                        inferred.task_id.as_ref(),
                         inferred.task_title.as_deref(),
                         inferred.task_url.as_deref(),
                         inferred.task_source.as_deref(),
@@ -1242,22 +3345,23 @@ fn cmd_setup(
 
     // Resolve workspace name and ancillary ID
     let (ws_name, ancillary_id_str, ancillary_num) = if let Some(ref name) = workspace {
-        let ws_name = name.to_lowercase();
-        let num = toren_lib::word_to_number(&ws_name)
+        let ws_name = toren_lib::naming::normalize_workspace_name(&segment.name, name);
+        let num = toren_lib::naming::slot_number(&segment.name, &ws_name)
             .with_context(|| format!("Invalid workspace name: {}", ws_name))?;
         let id = toren_lib::ancillary_id(&segment.name, num);
         (ws_name, id, num)
     } else {
-        let existing_workspaces = workspace_mgr
-            .list_workspaces(&segment.path)
-            .unwrap_or_default();
-        let id = assignment_mgr.next_available_ancillary(
+        let id = toren_lib::allocate_ancillary(
+            &mut assignment_mgr,
+            &workspace_mgr,
             &segment.name,
-            config.ancillaries.max_per_segment,
-            &existing_workspaces,
-        );
+            &segment.path,
+            segment_mgr.settings(&segment.name).max_per_segment,
+            overflow,
+            &SessionLogs::from_config(config),
+        )?;
         let num = toren_lib::ancillary_number(&id).unwrap_or(1);
-        let ws_name = workspace_name_for_number(num);
+        let ws_name = toren_lib::naming::workspace_name(&segment.name, num);
         (ws_name, id, num)
     };
 
@@ -1275,12 +3379,13 @@ fn cmd_setup(
     } else {
         AssignmentSource::Prompt {
             original_prompt: "(setup)".to_string(),
+            auto_created_bead: false,
         }
     };
 
-    assignment_mgr.create(
+    let assignment = assignment_mgr.create(
         &ancillary_id_str,
-        inferred.task_id.as_deref(),
+        inferred.task_id.as_ref(),
         source,
         &segment.name,
         ws_path.clone(),
@@ -1289,6 +3394,8 @@ fn cmd_setup(
         inferred.task_url.as_deref(),
         inferred.task_source.as_deref(),
     )?;
+    write_agent_context(&workspace_mgr, &segment.path, &assignment);
+    run_assign_hook(&assignment);
 
     eprintln!("Created workspace: {}", ws_path.display());
     println!("{}", ws_path.display());
@@ -1297,24 +3404,33 @@ fn cmd_setup(
 
 // ─── destroy ───────────────────────────────────────────────────────────────
 
+#[allow(clippy::too_many_arguments)]
 fn cmd_destroy(
     config: &Config,
     workspace: &str,
     kill: bool,
     push: bool,
+    no_verify: bool,
     force: bool,
+    steal: bool,
+    allow_conflicts: bool,
     segment_name: Option<&str>,
 ) -> Result<()> {
     let workspace_root = config.ancillaries.workspace_root.clone();
 
     let segment_mgr = SegmentManager::new(config)?;
-    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()));
+    let workspace_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+        .with_proxy_backend(Some(config.proxy.backend.clone()))
+        .with_host_template(config.proxy.host_template.clone())
+        .with_segment_roots(config.segment_workspace_roots())
+        .with_context_file(config.ancillaries.context_file.clone())
+        .with_envrc(config.ancillaries.envrc);
     let mut assignment_mgr = AssignmentManager::new()?;
 
     let segment = resolve_segment(&segment_mgr, segment_name)?;
 
-    let ws_name = workspace.to_lowercase();
-    let ancillary_num = toren_lib::word_to_number(&ws_name).unwrap_or(0);
+    let ws_name = toren_lib::naming::normalize_workspace_name(&segment.name, workspace);
+    let ancillary_num = toren_lib::naming::slot_number(&segment.name, &ws_name).unwrap_or(0);
     let ancillary_id_str = toren_lib::ancillary_id(&segment.name, ancillary_num);
 
     let assignment = assignment_mgr
@@ -1323,8 +3439,14 @@ fn cmd_destroy(
 
     match assignment {
         Some(assignment) => {
+            assignment_mgr.check_owner(&assignment.id, steal)?;
+
             let auto_commit_message = toren_lib::render_auto_commit_message(
-                toren_lib::DEFAULT_AUTO_COMMIT_MESSAGE,
+                config
+                    .tasks
+                    .commit_message
+                    .as_deref()
+                    .unwrap_or(toren_lib::DEFAULT_AUTO_COMMIT_MESSAGE),
                 &assignment,
                 &segment.name,
                 &segment.path,
@@ -1341,6 +3463,8 @@ fn cmd_destroy(
                 segment_path: &segment.path,
                 kill,
                 auto_commit_message,
+                push_checks: (!no_verify).then_some(&config.push),
+                allow_conflicts,
             };
 
             let result = toren_lib::clean_assignment(
@@ -1384,107 +3508,1466 @@ fn cmd_destroy(
                 }
             }
 
-            workspace_mgr.cleanup_workspace(
-                &segment.path,
-                &segment.name,
-                &ws_name,
-                toren_lib::CleanupMode::Abort,
-            )?;
+            workspace_mgr.cleanup_workspace(
+                &segment.path,
+                &segment.name,
+                &ws_name,
+                toren_lib::CleanupMode::Abort,
+            )?;
+
+            let result = serde_json::json!({
+                "workspace": ws_name,
+                "segment": segment.name,
+                "forced": true,
+            });
+            println!("{}", serde_json::to_string(&result)?);
+            Ok(())
+        }
+        None => anyhow::bail!(
+            "No assignment found for workspace '{}'. Use --force to clean up workspace state anyway.",
+            ws_name
+        ),
+    }
+}
+
+// ─── history ────────────────────────────────────────────────────────────────
+
+/// Estimated cost and total tokens, e.g. "$1.24 (2.1M tok)".
+fn format_usage(usage: &toren_lib::usage::Usage) -> String {
+    let tokens = usage.total_tokens() as f64;
+    let tokens = if tokens >= 1_000_000.0 {
+        format!("{:.1}M", tokens / 1_000_000.0)
+    } else if tokens >= 1_000.0 {
+        format!("{:.0}k", tokens / 1_000.0)
+    } else {
+        format!("{}", tokens)
+    };
+    format!("${:.2} ({} tok)", usage.cost_usd, tokens)
+}
+
+fn cmd_history(segment: Option<&str>, show_artifacts: bool, limit: usize) -> Result<()> {
+    let assignment_mgr = AssignmentManager::new()?;
+    let records = assignment_mgr.recent_history(&toren_lib::assignment_store::HistoryFilter {
+        segment: segment.map(String::from),
+        limit: Some(limit),
+    })?;
+
+    if records.is_empty() {
+        println!("No completed assignments");
+        return Ok(());
+    }
+
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(6);
+    for record in &records {
+        let at = chrono::DateTime::parse_from_rfc3339(&record.completed_at)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| record.completed_at.clone());
+        let workspace = toren_lib::ancillary_number(&record.ancillary_id)
+            .map(toren_lib::workspace_name)
+            .unwrap_or_else(|| record.ancillary_id.to_string());
+        let reason = match record.reason {
+            toren_lib::CompletionReason::Completed => "completed".green().to_string(),
+            toren_lib::CompletionReason::Aborted => "aborted".yellow().to_string(),
+        };
+        let revision = record
+            .final_revision
+            .as_deref()
+            .map(|r| r.chars().take(12).collect())
+            .unwrap_or_else(|| "-".to_string());
+        table.push_row(vec![
+            at,
+            format!("{}/{}", record.segment, workspace),
+            record.task_id.as_ref().map_or_else(|| "-".to_string(), |id| id.to_string()),
+            reason,
+            revision,
+            record.usage.as_ref().map_or_else(|| "-".to_string(), format_usage),
+        ]);
+    }
+
+    for (line, record) in table.render(term_width).iter().zip(&records) {
+        println!("{}", line);
+        if show_artifacts {
+            let dir = assignment_mgr.artifacts_dir(&record.assignment_id);
+            for path in toren_lib::artifacts::paths(&dir, &record.artifacts) {
+                println!("    {}", toren_lib::tilde_shorten(&path).dimmed());
+            }
+        }
+    }
+    Ok(())
+}
+
+// ─── cleanup ────────────────────────────────────────────────────────────────
+
+fn cmd_cleanup(config: &Config, all_segments: bool, segment_name: Option<String>) -> Result<()> {
+    let workspace_root = config.ancillaries.workspace_root.clone();
+
+    let segment_mgr = SegmentManager::new(config)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let ws_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+        .with_proxy_backend(Some(config.proxy.backend.clone()))
+        .with_host_template(config.proxy.host_template.clone())
+        .with_segment_roots(config.segment_workspace_roots())
+        .with_context_file(config.ancillaries.context_file.clone())
+        .with_envrc(config.ancillaries.envrc);
+
+    let (assignments, segments): (Vec<_>, Vec<Segment>) = if all_segments {
+        let assignments = assignment_mgr.list_active().into_iter().collect();
+        let segments = segment_mgr.list_all();
+        (assignments, segments)
+    } else if let Some(ref name) = segment_name {
+        let assignments = assignment_mgr
+            .list_active_segment(name)
+            .into_iter()
+            .collect();
+        let segments = segment_mgr
+            .find_by_name(name)
+            .map(|s| vec![s])
+            .unwrap_or_default();
+        (assignments, segments)
+    } else {
+        let segment = resolve_segment(&segment_mgr, None)?;
+        let name = segment.name.clone();
+        let assignments = assignment_mgr
+            .list_active_segment(&name)
+            .into_iter()
+            .collect();
+        (assignments, vec![segment])
+    };
+
+    let orphans = find_orphaned_workspaces(&ws_mgr, &segments, &assignments);
+
+    if orphans.is_empty() {
+        println!("No orphaned workspace directories found.");
+        return Ok(());
+    }
+
+    println!("Removing {} orphaned workspace dir(s):", orphans.len());
+    for (segment_name, ws_name, path) in &orphans {
+        print!("  {}/{}...", segment_name, ws_name);
+        match std::fs::remove_dir_all(path) {
+            Ok(()) => println!(" removed"),
+            Err(e) => println!(" failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+// ─── ws ─────────────────────────────────────────────────────────────────────
+
+fn cmd_ws(config: &Config, cmd: WsCmd) -> Result<()> {
+    match cmd {
+        WsCmd::Du { segment } => cmd_ws_du(config, segment),
+    }
+}
+
+fn cmd_ws_du(config: &Config, segment: Option<String>) -> Result<()> {
+    use toren_lib::disk_usage::{format_bytes, workspace_usage};
+
+    let segment_mgr = SegmentManager::new(config)?;
+    let segments = match segment {
+        Some(ref name) => vec![resolve_segment(&segment_mgr, Some(name))?],
+        None => segment_mgr.list_all(),
+    };
+    let ws_mgr = WorkspaceManager::new(config.ancillaries.workspace_root.clone(), None)
+        .with_segment_roots(config.segment_workspace_roots());
+    let warn_bytes = config.ancillaries.disk_warn_mb * 1024 * 1024;
+
+    let usage = workspace_usage(&ws_mgr, &segments, warn_bytes);
+    if usage.is_empty() {
+        println!("No workspaces");
+        return Ok(());
+    }
+
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(3).flex(2);
+    for ws in &usage {
+        let size = format!("{:>9}", format_bytes(ws.bytes));
+        table.push_row(vec![
+            if ws.over_threshold { size.yellow().bold().to_string() } else { size },
+            format!("{}/{}", ws.segment, ws.workspace),
+            toren_lib::tilde_shorten(&ws.path).dimmed().to_string(),
+        ]);
+    }
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
+
+    let total: u64 = usage.iter().map(|u| u.bytes).sum();
+    println!("{:>9}  total", format_bytes(total).bold());
+    let over = usage.iter().filter(|u| u.over_threshold).count();
+    if over > 0 {
+        eprintln!(
+            "\n{} workspace(s) over {} (disk_warn_mb under [ancillaries])",
+            over,
+            format_bytes(warn_bytes)
+        );
+    }
+    Ok(())
+}
+
+// ─── gc ─────────────────────────────────────────────────────────────────────
+
+fn cmd_gc(config: &Config, dry_run: bool, archive: bool, delete: bool) -> Result<()> {
+    use toren_lib::gc::GcAction;
+
+    let live: Vec<String> = SegmentManager::new(config)?
+        .list_all()
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let toren_dir = toren_lib::toren_root();
+    let plan = toren_lib::gc::plan(&mut assignment_mgr, &toren_dir, &live)?;
+
+    if plan.is_empty() {
+        println!("Nothing to collect: all history and logs belong to configured segments.");
+        return Ok(());
+    }
+
+    for segment in &plan.segments {
+        println!(
+            "{}  {} history record(s), {} path(s)",
+            segment.name.bold(),
+            segment.records.len(),
+            segment.paths.len()
+        );
+        for path in &segment.paths {
+            println!("  {}", toren_lib::tilde_shorten(path).dimmed());
+        }
+    }
+
+    let action = if archive {
+        GcAction::Archive
+    } else if delete {
+        GcAction::Delete
+    } else if dry_run || !std::io::stdin().is_terminal() {
+        eprintln!("\nDry run. Rerun with --archive or --delete to collect.");
+        return Ok(());
+    } else {
+        eprint!("\nArchive to ~/.toren/archive (a), delete (d), or leave (N)? ");
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        match input.trim().to_ascii_lowercase().as_str() {
+            "a" | "archive" => GcAction::Archive,
+            "d" | "delete" => GcAction::Delete,
+            _ => return Ok(()),
+        }
+    };
+
+    let outcome = toren_lib::gc::apply(&assignment_mgr, &toren_dir, &plan, action)?;
+    match outcome.archive_dir {
+        Some(dir) => println!(
+            "Archived {} history record(s) and {} path(s) to {}",
+            outcome.records,
+            outcome.paths,
+            toren_lib::tilde_shorten(&dir)
+        ),
+        None => println!(
+            "Deleted {} history record(s) and {} path(s)",
+            outcome.records, outcome.paths
+        ),
+    }
+    Ok(())
+}
+
+// ─── proxy ──────────────────────────────────────────────────────────────────
+
+fn cmd_proxy(config: &Config, cmd: ProxyCmd) -> Result<()> {
+    match cmd {
+        ProxyCmd::Reconcile { segment, all } => cmd_proxy_reconcile(config, all, segment),
+        ProxyCmd::List { segment } => cmd_proxy_list(config, segment),
+        ProxyCmd::Remove { workspace, segment } => cmd_proxy_remove(config, &workspace, segment),
+        ProxyCmd::Prune { dry_run } => cmd_proxy_prune(config, dry_run),
+        ProxyCmd::Export { format, output, segment } => {
+            cmd_proxy_export(config, format, output.as_deref(), segment)
+        }
+    }
+}
+
+/// A workspace route as returned by the daemon's `/api/proxy/routes`.
+#[derive(serde::Deserialize)]
+struct ProxyRouteRow {
+    host: String,
+    upstream: String,
+    port: u16,
+    tls: bool,
+    segment: String,
+    workspace: String,
+}
+
+impl ProxyRouteRow {
+    fn url(&self) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        match (self.tls, self.port) {
+            (true, 443) | (false, 80) => format!("{}://{}", scheme, self.host),
+            _ => format!("{}://{}:{}", scheme, self.host, self.port),
+        }
+    }
+}
+
+fn cmd_proxy_list(config: &Config, segment: Option<String>) -> Result<()> {
+    let body = daemon_json(config, "GET", "/api/proxy/routes", None)?;
+    let mut routes: Vec<ProxyRouteRow> = serde_json::from_value(body["routes"].clone())
+        .context("Unexpected response from the daemon")?;
+    if let Some(ref segment) = segment {
+        routes.retain(|r| &r.segment == segment);
+    }
+    if routes.is_empty() {
+        println!("No proxy routes");
+        return Ok(());
+    }
+    routes.sort_by(|a, b| {
+        (&a.segment, &a.workspace, &a.host, a.port).cmp(&(&b.segment, &b.workspace, &b.host, b.port))
+    });
+
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(3).flex(2);
+    for route in &routes {
+        table.push_row(vec![
+            format!("{}/{}", route.segment, route.workspace),
+            route.url(),
+            route.upstream.clone(),
+        ]);
+    }
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn cmd_proxy_remove(config: &Config, workspace: &str, segment: Option<String>) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment.as_deref())?;
+    let workspace = toren_lib::naming::normalize_workspace_name(&segment.name, workspace);
+
+    let path = format!(
+        "/api/proxy/routes/{}/{}",
+        urlencoding::encode(&segment.name),
+        urlencoding::encode(&workspace)
+    );
+    let body = daemon_json(config, "DELETE", &path, None)?;
+    let removed: Vec<ProxyRouteRow> = serde_json::from_value(body["removed"].clone())
+        .context("Unexpected response from the daemon")?;
+    if removed.is_empty() {
+        println!("No proxy routes for {}/{}", segment.name, workspace);
+    }
+    for route in &removed {
+        println!("Removed {} -> {}", route.url(), route.upstream);
+    }
+    Ok(())
+}
+
+fn cmd_proxy_export(
+    config: &Config,
+    format: ExportFormat,
+    output: Option<&Path>,
+    segment_name: Option<String>,
+) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let ws_mgr = WorkspaceManager::new(
+        config.ancillaries.workspace_root.clone(),
+        Some(config.proxy.domain.clone()),
+    )
+    .with_proxy_backend(Some(config.proxy.backend.clone()))
+    .with_host_template(config.proxy.host_template.clone())
+    .with_segment_roots(config.segment_workspace_roots());
+
+    let mut assignments: Vec<_> = match segment_name {
+        Some(ref name) => assignment_mgr.list_active_segment(name),
+        None => assignment_mgr.list_active(),
+    }
+    .into_iter()
+    .cloned()
+    .collect();
+    assignments.sort_by(|a, b| {
+        (&a.segment, a.ancillary_num).cmp(&(&b.segment, b.ancillary_num))
+    });
+
+    let mut routes = Vec::new();
+    for assignment in &assignments {
+        let Some(segment) = segment_mgr.find_by_name(&assignment.segment) else {
+            continue;
+        };
+        let Some(ws_name) = assignment.workspace_path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let declared = ws_mgr
+            .declared_proxy_routes(
+                &segment,
+                &assignment.workspace_path,
+                ws_name,
+                assignment.ancillary_num.unwrap_or(0),
+            )
+            .with_context(|| format!("Failed to evaluate proxy routes for {}", assignment.ancillary_id))?;
+        routes.extend(declared);
+    }
+
+    let snapshot = match format {
+        ExportFormat::Caddyfile => toren_lib::proxy_routes::to_caddyfile(&routes),
+        ExportFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&serde_json::json!({ "routes": routes }))?;
+            json.push('\n');
+            json
+        }
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, snapshot)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Wrote {} route(s) to {}", routes.len(), path.display());
+        }
+        None => print!("{}", snapshot),
+    }
+    Ok(())
+}
+
+fn daemon_unreachable(config: &Config) -> TorenError {
+    TorenError::new(
+        ErrorCode::DaemonUnreachable,
+        format!("Failed to reach toren daemon at {}:{}", config.host(), config.port()),
+    )
+    .hint("Is it running? Start it with `toren-daemon`.")
+}
+
+fn daemon_unauthorized(config: &Config) -> TorenError {
+    TorenError::new(
+        ErrorCode::DaemonUnauthorized,
+        format!("Toren daemon at {}:{} rejected the session token", config.host(), config.port()),
+    )
+    .hint("Pair with `breq pair <pairing-token>` (toren-daemon logs it at startup).")
+}
+
+/// Where `breq pair` saves the daemon session token.
+fn daemon_token_path() -> PathBuf {
+    toren_lib::toren_root().join("daemon_token")
+}
+
+/// Session token for the daemon API: `TOREN_TOKEN`, or the one saved by `breq pair`.
+fn daemon_token() -> Option<String> {
+    std::env::var("TOREN_TOKEN")
+        .ok()
+        .or_else(|| std::fs::read_to_string(daemon_token_path()).ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Attach the session token, if any, to a daemon request.
+fn with_daemon_token<B>(request: ureq::RequestBuilder<B>) -> ureq::RequestBuilder<B> {
+    match daemon_token() {
+        Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+/// Call a daemon API endpoint (POSTing `body`, if any) and return its JSON response.
+fn daemon_json(
+    config: &Config,
+    method: &str,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> Result<serde_json::Value> {
+    let url = format!("http://{}:{}{}", config.host(), config.port(), path);
+    let http = ureq::Agent::new_with_config(
+        ureq::config::Config::builder()
+            .timeout_global(Some(std::time::Duration::from_secs(30)))
+            .http_status_as_error(false)
+            .build(),
+    );
+    let response = match (method, body) {
+        ("DELETE", _) => with_daemon_token(http.delete(&url)).call(),
+        (_, Some(body)) => with_daemon_token(http.post(&url)).send_json(&body),
+        _ => with_daemon_token(http.get(&url)).call(),
+    }
+    .with_context(|| daemon_unreachable(config))?;
+
+    let status: u16 = response.status().into();
+    if status == 401 && path.starts_with("/api/") {
+        return Err(daemon_unauthorized(config).into());
+    }
+    let body = response
+        .into_body()
+        .read_json::<serde_json::Value>()
+        .unwrap_or(serde_json::Value::Null);
+    if !(200..300).contains(&status) {
+        let error = body
+            .get("error")
+            .and_then(|e| e.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| format!("HTTP {}", status));
+        anyhow::bail!("{} {} failed: {}", method, path, error);
+    }
+    Ok(body)
+}
+
+fn cmd_proxy_reconcile(config: &Config, all_segments: bool, segment_name: Option<String>) -> Result<()> {
+    // Probe the proxy first; this also clears station's circuit breaker so
+    // the route operations below aren't skipped.
+    let output = Command::new("station")
+        .arg("health")
+        .env("STATION_BACKEND", &config.proxy.backend)
+        .output()
+        .context("Failed to run `station health`. Is station installed?")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Proxy backend is still unreachable: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let workspace_root = config.ancillaries.workspace_root.clone();
+    let segment_mgr = SegmentManager::new(config)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let ws_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()))
+        .with_proxy_backend(Some(config.proxy.backend.clone()))
+        .with_host_template(config.proxy.host_template.clone())
+        .with_segment_roots(config.segment_workspace_roots())
+        .with_context_file(config.ancillaries.context_file.clone())
+        .with_envrc(config.ancillaries.envrc);
+
+    let assignments: Vec<_> = if all_segments {
+        assignment_mgr.list_active().into_iter().cloned().collect()
+    } else {
+        let name = match segment_name {
+            Some(name) => name,
+            None => resolve_segment(&segment_mgr, None)?.name,
+        };
+        assignment_mgr
+            .list_active_segment(&name)
+            .into_iter()
+            .cloned()
+            .collect()
+    };
+
+    let mut failed = 0;
+    for assignment in &assignments {
+        let Some(segment) = segment_mgr.find_by_name(&assignment.segment) else {
+            continue;
+        };
+        let Some(ws_name) = assignment.workspace_path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !assignment.workspace_path.exists() {
+            continue;
+        }
+
+        match ws_mgr.reconcile_proxies(
+            &segment.path,
+            &assignment.workspace_path,
+            ws_name,
+            assignment.ancillary_num.unwrap_or(0),
+        ) {
+            Ok(0) => {}
+            Ok(n) => println!("{}: restored {} route(s)", assignment.ancillary_id, n),
+            Err(e) => {
+                failed += 1;
+                eprintln!("{}: {:#}", assignment.ancillary_id, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("Failed to reconcile routes for {} workspace(s)", failed);
+    }
+    Ok(())
+}
+
+fn cmd_proxy_prune(config: &Config, dry_run: bool) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let ws_mgr = WorkspaceManager::new(
+        config.ancillaries.workspace_root.clone(),
+        Some(config.proxy.domain.clone()),
+    )
+    .with_proxy_backend(Some(config.proxy.backend.clone()))
+    .with_host_template(config.proxy.host_template.clone())
+    .with_segment_roots(config.segment_workspace_roots());
+
+    let segments = segment_mgr.list_all();
+    let active: Vec<_> = assignment_mgr.list_active().into_iter().cloned().collect();
+    let routes = if dry_run {
+        ws_mgr.stale_proxy_routes(&segments, &active)?
+    } else {
+        ws_mgr.gc_proxy_routes(&segments, &active)?
+    };
+
+    if routes.is_empty() {
+        println!("No stale routes");
+        return Ok(());
+    }
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    for route in &routes {
+        println!("{} {} ({}/{})", verb, route.host, route.segment, route.workspace);
+    }
+    Ok(())
+}
+
+// ─── schedule ───────────────────────────────────────────────────────────────
+
+fn cmd_schedule(config: &Config, cmd: ScheduleCmd) -> Result<()> {
+    match cmd {
+        ScheduleCmd::Add {
+            cron,
+            prompt,
+            segment,
+            task_source,
+            agent,
+        } => {
+            let segment_mgr = SegmentManager::new(config)?;
+            let segment = resolve_segment(&segment_mgr, segment.as_deref())?;
+            let body = daemon_json(
+                config,
+                "POST",
+                "/api/schedules",
+                Some(serde_json::json!({
+                    "cron": cron,
+                    "segment": segment.name,
+                    "prompt": prompt,
+                    "task_source": task_source,
+                    "agent": agent,
+                })),
+            )?;
+            let schedule: ScheduleRow = serde_json::from_value(body["schedule"].clone())
+                .context("Unexpected response from the daemon")?;
+            eprintln!(
+                "Added schedule {} ({}) for {}; next run {}",
+                schedule.id,
+                schedule.cron,
+                schedule.segment,
+                schedule.next_run_display()
+            );
+            println!("{}", schedule.id);
+            Ok(())
+        }
+        ScheduleCmd::List => cmd_schedule_list(config),
+        ScheduleCmd::Remove { id } => {
+            daemon_json(
+                config,
+                "DELETE",
+                &format!("/api/schedules/{}", urlencoding::encode(&id)),
+                None,
+            )?;
+            eprintln!("Removed schedule {}", id);
+            Ok(())
+        }
+    }
+}
+
+/// A schedule as returned by the daemon's `/api/schedules`.
+#[derive(serde::Deserialize)]
+struct ScheduleRow {
+    id: String,
+    cron: String,
+    segment: String,
+    prompt: String,
+    next_run: Option<chrono::DateTime<chrono::Utc>>,
+    last_assignment_id: Option<String>,
+    last_error: Option<String>,
+}
+
+impl ScheduleRow {
+    fn next_run_display(&self) -> String {
+        self.next_run
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+fn cmd_schedule_list(config: &Config) -> Result<()> {
+    let body = daemon_json(config, "GET", "/api/schedules", None)?;
+    let schedules: Vec<ScheduleRow> = serde_json::from_value(body["schedules"].clone())
+        .context("Unexpected response from the daemon")?;
+    if schedules.is_empty() {
+        println!("No schedules");
+        return Ok(());
+    }
+
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(6).flex(5);
+    for s in &schedules {
+        let last = match (&s.last_error, &s.last_assignment_id) {
+            (Some(error), _) => format!("skipped: {}", error).red().to_string(),
+            (None, Some(id)) => id.chars().take(8).collect(),
+            (None, None) => "-".to_string(),
+        };
+        table.push_row(vec![
+            s.id.clone(),
+            s.cron.clone(),
+            s.segment.clone(),
+            s.next_run_display(),
+            last,
+            s.prompt.lines().next().unwrap_or_default().to_string(),
+        ]);
+    }
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// ─── segments ───────────────────────────────────────────────────────────────
+
+fn cmd_segments(config: &Config, cmd: SegmentsCmd) -> Result<()> {
+    match cmd {
+        SegmentsCmd::List { segments_only } => {
+            let segment_mgr = SegmentManager::new(config)?;
+            let discovered: Vec<_> = segment_mgr
+                .discover()
+                .into_iter()
+                .filter(|d| !segments_only || d.discovery.is_segment())
+                .collect();
+            if discovered.is_empty() {
+                println!("No segments; add roots or paths to [ancillaries] segments");
+                return Ok(());
+            }
+
+            let term_width = terminal_size::terminal_size()
+                .map(|(w, _)| w.0 as usize)
+                .unwrap_or(80);
+            let mut table = table::Table::new(3).flex(1);
+            for d in &discovered {
+                let row = vec![
+                    d.name.clone(),
+                    toren_lib::tilde_shorten(&d.path),
+                    d.discovery.to_string(),
+                ];
+                let row = if d.discovery.is_segment() {
+                    row
+                } else {
+                    row.into_iter().map(|cell| cell.dimmed().to_string()).collect()
+                };
+                table.push_row(row);
+            }
+            for line in table.render(term_width) {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+    }
+}
+
+// ─── bead ───────────────────────────────────────────────────────────────────
+
+/// Task source of the beads resolver plugin (`tasks/beads.rhai`).
+const BEAD_SOURCE: &str = "beads";
+
+fn cmd_bead(config: &Config, cmd: BeadCmd) -> Result<()> {
+    let plugin_mgr = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins"))?;
+    if !plugin_mgr.has_resolver(BEAD_SOURCE) {
+        anyhow::bail!("The beads task plugin isn't installed; run `breq plugin install tasks/beads`");
+    }
+    let segment_mgr = SegmentManager::new(config)?;
+
+    match cmd {
+        BeadCmd::List { unassigned, segment } => {
+            let segment = resolve_segment(&segment_mgr, segment.as_deref())?;
+            cmd_bead_list(&plugin_mgr, &segment, unassigned)
+        }
+        BeadCmd::Show { id, assign, detach, segment } => {
+            let segment = resolve_segment(&segment_mgr, segment.as_deref())?;
+            let bead = plugin_mgr.resolve_info(BEAD_SOURCE, &id, bead_context(&segment))?;
+            let assignment = bead_assignments(&segment)?.remove(&bead.id);
+
+            println!("{}  {}", bead.id.bold(), bead.title);
+            let status = bead.status.as_deref().unwrap_or("open");
+            match bead.assignee.as_deref().filter(|a| !a.is_empty()) {
+                Some(assignee) => println!("status: {} ({})", status, assignee),
+                None => println!("status: {}", status),
+            }
+            if let Some(ref assignment) = assignment {
+                println!(
+                    "assignment: {} in {}",
+                    assignment.ancillary_id.cyan(),
+                    toren_lib::tilde_shorten(&assignment.workspace_path)
+                );
+            }
+            if let Some(description) = bead.description.as_deref().filter(|d| !d.trim().is_empty()) {
+                println!("\n{}", description.trim());
+            }
+
+            if assign {
+                if let Some(assignment) = assignment {
+                    anyhow::bail!(
+                        "{} is already assigned to {}; open it with `breq go {}`",
+                        bead.id,
+                        assignment.ancillary_id,
+                        bead.id
+                    );
+                }
+                assign_bead(config, &plugin_mgr, &segment, &bead, detach)?;
+            }
+            Ok(())
+        }
+        BeadCmd::Create { title, description, assign, detach, segment } => {
+            let segment = resolve_segment(&segment_mgr, segment.as_deref())?;
+            let id = plugin_mgr.resolve_create(BEAD_SOURCE, &title, description.as_deref(), bead_context(&segment))?;
+            let id = toren_lib::TaskId::parse(id.trim()).context("bd didn't report the new bead's ID")?;
+            eprintln!("Created bead {}", id);
+            println!("{}", id);
+            if assign {
+                let bead = toren_lib::ResolvedTask {
+                    id,
+                    source: BEAD_SOURCE.to_string(),
+                    kind: None,
+                    title,
+                    status: None,
+                    assignee: None,
+                    description,
+                    created_at: None,
+                    updated_at: None,
+                    priority: None,
+                    deps: Vec::new(),
+                };
+                assign_bead(config, &plugin_mgr, &segment, &bead, detach)?;
+            }
+            Ok(())
+        }
+        BeadCmd::Close { id, force, segment } => {
+            let segment = resolve_segment(&segment_mgr, segment.as_deref())?;
+            if let Some(assignment) = bead_assignments(&segment)?.remove(id.as_str()) {
+                if !force {
+                    let ws_name = assignment
+                        .workspace_path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or_default();
+                    anyhow::bail!(
+                        "{} is assigned to {}; finish it with `breq complete {}` or pass --force",
+                        id,
+                        assignment.ancillary_id,
+                        ws_name
+                    );
+                }
+            }
+            plugin_mgr.resolve_complete(BEAD_SOURCE, &id, bead_context(&segment))?;
+            eprintln!("Closed bead {}", id);
+            Ok(())
+        }
+    }
+}
+
+fn cmd_bead_list(plugin_mgr: &toren_lib::PluginManager, segment: &Segment, unassigned: bool) -> Result<()> {
+    if !plugin_mgr.resolver_has_fn(BEAD_SOURCE, "list") {
+        anyhow::bail!("The installed beads plugin can't list beads; update it with `breq plugin install tasks/beads`");
+    }
+    let beads = plugin_mgr.resolve_list(BEAD_SOURCE, bead_context(segment))?;
+    let assignments = bead_assignments(segment)?;
+    let beads: Vec<_> = beads
+        .into_iter()
+        .filter(|b| !unassigned || !assignments.contains_key(&b.id))
+        .collect();
+    if beads.is_empty() {
+        println!("No beads");
+        return Ok(());
+    }
+
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(4).flex(3);
+    for bead in &beads {
+        let assigned = match assignments.get(&bead.id) {
+            Some(assignment) => assignment.ancillary_id.cyan().to_string(),
+            None => "-".to_string(),
+        };
+        table.push_row(vec![
+            bead.id.to_string(),
+            bead.status.clone().unwrap_or_else(|| "open".to_string()),
+            assigned,
+            bead.title.clone(),
+        ]);
+    }
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Rank the segment's ready beads and offer to assign the top one.
+fn cmd_ready(config: &Config, limit: usize, assign: bool, detach: bool, segment_name: Option<&str>) -> Result<()> {
+    let plugin_mgr = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins"))?;
+    if !plugin_mgr.has_resolver(BEAD_SOURCE) {
+        anyhow::bail!("The beads task plugin isn't installed; run `breq plugin install tasks/beads`");
+    }
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
+
+    let beads = if plugin_mgr.resolver_has_fn(BEAD_SOURCE, "ready") {
+        plugin_mgr.resolve_ready(BEAD_SOURCE, bead_context(&segment))?
+    } else if plugin_mgr.resolver_has_fn(BEAD_SOURCE, "list") {
+        plugin_mgr.resolve_list(BEAD_SOURCE, bead_context(&segment))?
+    } else {
+        anyhow::bail!("The installed beads plugin can't list beads; update it with `breq plugin install tasks/beads`");
+    };
+    let assignments = bead_assignments(&segment)?;
+    let mut beads: Vec<_> = beads
+        .into_iter()
+        .filter(|b| toren_lib::is_ready(b) && !assignments.contains_key(&b.id))
+        .collect();
+    toren_lib::rank_ready(&mut beads);
+    let Some(top) = beads.first() else {
+        println!("No ready beads in {}", segment.name);
+        return Ok(());
+    };
+
+    let term_width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let mut table = table::Table::new(4).flex(3);
+    for bead in beads.iter().take(limit.max(1)) {
+        table.push_row(vec![
+            bead.id.to_string(),
+            bead.priority.map(|p| format!("P{}", p)).unwrap_or_else(|| "-".to_string()),
+            bead.created_at.as_deref().map(format_age).unwrap_or_else(|| "-".to_string()),
+            bead.title.clone(),
+        ]);
+    }
+    for line in table.render(term_width) {
+        println!("{}", line);
+    }
+    if beads.len() > limit.max(1) {
+        println!("{}", format!("… and {} more", beads.len() - limit.max(1)).dimmed());
+    }
+
+    if !assign {
+        if !std::io::stdin().is_terminal() {
+            return Ok(());
+        }
+        eprint!("\nAssign {} to a new ancillary? [y/N] ", top.id);
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        if !matches!(input.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+            return Ok(());
+        }
+    }
+    // `ready` and `list` may leave out the description the agent's prompt needs
+    let top = plugin_mgr
+        .resolve_info(BEAD_SOURCE, &top.id, bead_context(&segment))
+        .unwrap_or_else(|_| top.clone());
+    assign_bead(config, &plugin_mgr, &segment, &top, detach)
+}
+
+/// Plugin context that runs `bd` in the segment.
+fn bead_context(segment: &Segment) -> toren_lib::PluginContext {
+    toren_lib::PluginContext::new(Some(segment.path.clone()), Some(segment.name.clone()))
+}
+
+/// The segment's active assignments on beads, keyed by bead ID.
+fn bead_assignments(segment: &Segment) -> Result<std::collections::HashMap<toren_lib::TaskId, toren_lib::Assignment>> {
+    let mut assignment_mgr = AssignmentManager::new()?;
+    Ok(assignment_mgr
+        .list_active_segment(&segment.name)
+        .into_iter()
+        .filter(|a| a.task_source.as_deref().is_none_or(|s| s == BEAD_SOURCE))
+        .filter_map(|a| Some((a.task_id.clone()?, a.clone())))
+        .collect())
+}
+
+/// Claim a bead and start an agent on it in a new workspace, as the
+/// `assign` plugin does. The claim is released if the agent doesn't start.
+fn assign_bead(
+    config: &Config,
+    plugin_mgr: &toren_lib::PluginManager,
+    segment: &Segment,
+    bead: &toren_lib::ResolvedTask,
+    detach: bool,
+) -> Result<()> {
+    plugin_mgr.resolve_claim(BEAD_SOURCE, &bead.id, toren_lib::DEFAULT_CLAIM_IDENTITY, bead_context(segment))?;
+    let mut prompt = format!("{} {}: {}", BEAD_SOURCE, bead.id, bead.title);
+    if let Some(description) = bead.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        prompt.push_str("\n\n");
+        prompt.push_str(description.trim());
+    }
+
+    let result = cmd_do(
+        config,
+        None,
+        Some(prompt),
+        None,
+        Some(bead.id.to_string()),
+        Some(bead.title.clone()),
+        None,
+        Some(BEAD_SOURCE.to_string()),
+        Some(&segment.name),
+        None,
+        Launch::from_flags(false, detach, false),
+        None,
+        None,
+        false,
+        false,
+        None,
+        None,
+        Vec::new(),
+    );
+    if result.is_err() {
+        match plugin_mgr.resolve_abort(BEAD_SOURCE, &bead.id, bead_context(segment)) {
+            Ok(()) => eprintln!("Released claim on bead {}", bead.id),
+            Err(e) => eprintln!("warning: failed to release claim on bead {}: {:#}", bead.id, e),
+        }
+    }
+    result
+}
+
+// ─── dismiss ────────────────────────────────────────────────────────────────
+
+fn cmd_dismiss(config: &Config, reference: &str) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let segment = resolve_segment(&segment_mgr, None)?;
+
+    let ref_ = AssignmentRef::parse(reference, &segment.name);
+    let assignments: Vec<_> = assignment_mgr
+        .resolve(&ref_)
+        .iter()
+        .map(|a| (*a).clone())
+        .collect();
+
+    if assignments.is_empty() {
+        return Err(TorenError::new(
+            ErrorCode::AssignmentNotFound,
+            format!("No assignment found for: {}", reference),
+        )
+        .into());
+    }
+
+    for assignment in &assignments {
+        assignment_mgr.remove(&assignment.id)?;
+        println!(
+            "Dismissed: {} ({})",
+            assignment.ancillary_id,
+            assignment.task_id.as_deref().unwrap_or("-")
+        );
+    }
+
+    Ok(())
+}
+
+// ─── undo ────────────────────────────────────────────────────────────────────
+
+fn cmd_undo(config: &Config, dry_run: bool) -> Result<()> {
+    let retention = config.ancillaries.undo_retention_minutes;
+    if retention == 0 {
+        anyhow::bail!("Undo is disabled (ancillaries.undo_retention_minutes = 0)");
+    }
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let entry = assignment_mgr
+        .undo_journal()
+        .latest()?
+        .filter(|entry| !entry.expired(chrono::Duration::minutes(retention as i64)));
+    let Some(entry) = entry else {
+        println!("Nothing to undo in the last {} minutes.", retention);
+        return Ok(());
+    };
+    let assignment = &entry.assignment;
+
+    let ago = (chrono::Utc::now() - entry.recorded_at).num_minutes();
+    println!(
+        "Undoing {} of {} ({}) from {} min ago",
+        entry.operation,
+        assignment.ancillary_id,
+        assignment.task_id.as_deref().unwrap_or("-"),
+        ago
+    );
+    if dry_run {
+        println!("  workspace: {}", assignment.workspace_path.display());
+        println!("  revision:  {}", entry.revision.as_deref().unwrap_or("-"));
+        if let Some(task) = entry.task {
+            println!("  task:      {:?}", task);
+        }
+        for url in &entry.proxy_urls {
+            println!("  route:     {}", url);
+        }
+        return Ok(());
+    }
+
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = segment_mgr
+        .find_by_name(&assignment.segment)
+        .with_context(|| format!("Segment not found: {}", assignment.segment))?;
+    let ws_mgr = WorkspaceManager::new(
+        config.ancillaries.workspace_root.clone(),
+        Some(config.proxy.domain.clone()),
+    )
+    .with_proxy_backend(Some(config.proxy.backend.clone()))
+    .with_host_template(config.proxy.host_template.clone())
+    .with_segment_roots(config.segment_workspace_roots())
+    .with_context_file(config.ancillaries.context_file.clone())
+    .with_envrc(config.ancillaries.envrc);
+    let plugin_mgr = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins"))?;
+
+    let opts = toren_lib::UndoOptions {
+        segment_path: &segment.path,
+        plugin_mgr: &plugin_mgr,
+        claim_identity: config.tasks.claim_identity.as_deref(),
+    };
+    let result = toren_lib::undo_operation(&entry, &mut assignment_mgr, &ws_mgr, &opts)?;
+
+    match result.revision {
+        Some(ref rev) => println!("Restored {} at {}", result.workspace_path.display(), rev),
+        None => println!("Restored {}", result.workspace_path.display()),
+    }
+    if result.task_claimed {
+        println!("Claimed {} again", assignment.task_id.as_deref().unwrap_or("-"));
+    } else if entry.task == Some(toren_lib::undo::TaskTransition::Deleted) {
+        eprintln!("warning: task {} was deleted and can't be restored", assignment.task_id.as_deref().unwrap_or("-"));
+    }
+    for url in &result.proxy_urls {
+        println!("  {}", url);
+    }
+    if entry.pushed {
+        eprintln!("warning: the changes were already pushed; undo doesn't unpush them");
+    }
+    Ok(())
+}
+
+// ─── note ────────────────────────────────────────────────────────────────────
 
-            let result = serde_json::json!({
-                "workspace": ws_name,
-                "segment": segment.name,
-                "forced": true,
-            });
-            println!("{}", serde_json::to_string(&result)?);
-            Ok(())
+fn cmd_note(config: &Config, reference: &str, text: Option<&str>) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+    let segment = resolve_segment(&segment_mgr, None)?;
+
+    let ref_ = AssignmentRef::parse(reference, &segment.name);
+    let assignments: Vec<_> = assignment_mgr
+        .resolve(&ref_)
+        .iter()
+        .map(|a| (*a).clone())
+        .collect();
+    let assignment = match assignments.as_slice() {
+        [] => {
+            return Err(TorenError::new(
+                ErrorCode::AssignmentNotFound,
+                format!("No assignment found for: {}", reference),
+            )
+            .into())
         }
-        None => anyhow::bail!(
-            "No assignment found for workspace '{}'. Use --force to clean up workspace state anyway.",
-            ws_name
+        [one] => one,
+        _ => anyhow::bail!(
+            "'{}' matches {} assignments; use a workspace name instead",
+            reference,
+            assignments.len()
         ),
+    };
+
+    let Some(text) = text else {
+        if assignment.notes.is_empty() {
+            println!("No notes for {}", assignment.ancillary_id);
+        }
+        for line in format_notes(&assignment.notes) {
+            println!("{}", line);
+        }
+        return Ok(());
+    };
+    if text.trim().is_empty() {
+        anyhow::bail!("Note text is empty");
     }
+    assignment_mgr.add_note(&assignment.id, text)?;
+    println!("Noted on {}", assignment.ancillary_id);
+    Ok(())
 }
 
-// ─── cleanup ────────────────────────────────────────────────────────────────
+/// One line per note: local timestamp, then the text (continuation lines indented).
+fn format_notes(notes: &[toren_lib::AssignmentNote]) -> Vec<String> {
+    notes
+        .iter()
+        .map(|note| {
+            let at = chrono::DateTime::parse_from_rfc3339(&note.at)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|_| note.at.clone());
+            format!("{}  {}", at, note.text.replace('\n', "\n                  "))
+        })
+        .collect()
+}
 
-fn cmd_cleanup(config: &Config, all_segments: bool, segment_name: Option<String>) -> Result<()> {
-    let workspace_root = config.ancillaries.workspace_root.clone();
+// ─── amend ───────────────────────────────────────────────────────────────────
 
+fn cmd_amend(
+    config: &Config,
+    reference: &str,
+    prompt: Option<String>,
+    edit: bool,
+    segment: Option<&str>,
+) -> Result<()> {
     let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment)?;
     let mut assignment_mgr = AssignmentManager::new()?;
-    let ws_mgr = WorkspaceManager::new(workspace_root, Some(config.proxy.domain.clone()));
+    let assignment = resolve_unique(&mut assignment_mgr, reference, &segment.name)?;
 
-    let (assignments, segments): (Vec<_>, Vec<Segment>) = if all_segments {
-        let assignments = assignment_mgr.list_active().into_iter().collect();
-        let segments = segment_mgr.list_all();
-        (assignments, segments)
-    } else if let Some(ref name) = segment_name {
-        let assignments = assignment_mgr
-            .list_active_segment(name)
-            .into_iter()
-            .collect();
-        let segments = segment_mgr
-            .find_by_name(name)
-            .map(|s| vec![s])
-            .unwrap_or_default();
-        (assignments, segments)
-    } else {
-        let segment = resolve_segment(&segment_mgr, None)?;
-        let name = segment.name.clone();
-        let assignments = assignment_mgr
-            .list_active_segment(&name)
-            .into_iter()
-            .collect();
-        (assignments, vec![segment])
+    let AssignmentSource::Prompt { ref original_prompt, .. } = assignment.source else {
+        anyhow::bail!(
+            "{} was created from a task, not a prompt; edit the task instead",
+            assignment.ancillary_id
+        );
     };
-
-    let orphans = find_orphaned_workspaces(&ws_mgr, &segments, &assignments);
-
-    if orphans.is_empty() {
-        println!("No orphaned workspace directories found.");
+    let prompt = match prompt {
+        Some(prompt) => prompt,
+        None if edit => {
+            let template = format!(
+                "{}\n\n# Prompt of {}. Lines starting with '#' are ignored;\n# an empty prompt cancels the amend.\n",
+                original_prompt.trim(),
+                assignment.ancillary_id
+            );
+            let text = edit::edit(&template).context("Editor returned an error")?;
+            text.lines()
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        None => anyhow::bail!("Pass the new prompt with --prompt, or --edit to write it in $EDITOR"),
+    };
+    if prompt.trim().is_empty() {
+        anyhow::bail!("Empty prompt; not amending {}", assignment.ancillary_id);
+    }
+    if prompt.trim() == original_prompt.trim() {
+        eprintln!("Prompt unchanged; not amending {}", assignment.ancillary_id);
         return Ok(());
     }
 
-    println!("Removing {} orphaned workspace dir(s):", orphans.len());
-    for (segment_name, ws_name, path) in &orphans {
-        print!("  {}/{}...", segment_name, ws_name);
-        match std::fs::remove_dir_all(path) {
-            Ok(()) => println!(" removed"),
-            Err(e) => println!(" failed: {}", e),
+    let plugin_mgr = toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins"))?;
+    let opts = toren_lib::AmendOptions {
+        prompt: &prompt,
+        segment_path: &segment.path,
+        plugin_mgr: &plugin_mgr,
+    };
+    let result = toren_lib::amend_prompt(&assignment, &mut assignment_mgr, &opts)?;
+    eprintln!("Amended the prompt of {}", assignment.ancillary_id);
+    if result.task_updated {
+        if let Some(ref task_id) = assignment.task_id {
+            eprintln!("Updated the description of {}", task_id);
         }
     }
+    eprintln!("The next resume includes the amended prompt");
+    Ok(())
+}
+
+// ─── why ─────────────────────────────────────────────────────────────────────
+
+fn cmd_diff(
+    config: &Config,
+    reference: &str,
+    opts: &toren_lib::DiffOptions,
+    segment_name: Option<&str>,
+) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
+    let assignment = resolve_unique(&mut AssignmentManager::new()?, reference, &segment.name)?;
+    if !assignment.workspace_path.exists() {
+        anyhow::bail!(
+            "Workspace {} no longer exists",
+            toren_lib::tilde_shorten(&assignment.workspace_path)
+        );
+    }
 
+    let diff = toren_lib::workspace_diff(
+        &assignment.workspace_path,
+        &assignment.working_dir(),
+        assignment.base_branch.as_deref(),
+        opts,
+    )?;
+    print!("{}", diff);
     Ok(())
 }
 
-// ─── dismiss ────────────────────────────────────────────────────────────────
+/// The one assignment a workspace or task reference names.
+fn resolve_unique(
+    assignment_mgr: &mut AssignmentManager,
+    reference: &str,
+    segment_name: &str,
+) -> Result<toren_lib::Assignment> {
+    let ref_ = AssignmentRef::parse(reference, segment_name);
+    let assignments = assignment_mgr.resolve(&ref_);
+    match assignments.as_slice() {
+        [] => Err(TorenError::new(
+            ErrorCode::AssignmentNotFound,
+            format!("No assignment found for: {}", reference),
+        )
+        .into()),
+        [one] => Ok((*one).clone()),
+        _ => anyhow::bail!(
+            "'{}' matches {} assignments; use a workspace name instead",
+            reference,
+            assignments.len()
+        ),
+    }
+}
+
+fn cmd_why(config: &Config, reference: &str, segment_name: Option<&str>) -> Result<()> {
+    use toren_lib::composite_status::{
+        explain_agent_activity, explain_has_changes, explain_task_status,
+    };
 
-fn cmd_dismiss(config: &Config, reference: &str) -> Result<()> {
     let segment_mgr = SegmentManager::new(config)?;
     let mut assignment_mgr = AssignmentManager::new()?;
-    let segment = resolve_segment(&segment_mgr, None)?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
 
     let ref_ = AssignmentRef::parse(reference, &segment.name);
-    let assignments: Vec<_> = assignment_mgr
-        .resolve(&ref_)
-        .iter()
-        .map(|a| (*a).clone())
-        .collect();
+    let assignments = assignment_mgr.resolve(&ref_);
+    let assignment = match assignments.as_slice() {
+        [] => {
+            return Err(TorenError::new(
+                ErrorCode::AssignmentNotFound,
+                format!("No assignment found for: {}", reference),
+            )
+            .into())
+        }
+        [one] => *one,
+        _ => anyhow::bail!(
+            "'{}' matches {} assignments; use a workspace name instead",
+            reference,
+            assignments.len()
+        ),
+    };
 
-    if assignments.is_empty() {
-        anyhow::bail!("No assignment found for: {}", reference);
+    println!("{}", assignment.ancillary_id.bold());
+    if let Some(ref task_id) = assignment.task_id {
+        println!("  task:      {}", task_id);
+    }
+    println!("  workspace: {}", toren_lib::tilde_shorten(&assignment.workspace_path));
+    if let Some(ref focus) = assignment.focus_path {
+        println!("  focus:     {}", focus.display());
+    }
+    if let Some(owner) = assignment.owner.as_ref().filter(|o| o.is_alive()) {
+        println!("  owner:     {}", owner);
     }
 
-    for assignment in &assignments {
-        assignment_mgr.remove(&assignment.id)?;
-        println!(
-            "Dismissed: {} ({})",
-            assignment.ancillary_id,
-            assignment.task_id.as_deref().unwrap_or("-")
-        );
+    let seg_path = segment_mgr
+        .find_by_name(&assignment.segment)
+        .map(|s| s.path.clone());
+    let task = match toren_lib::PluginManager::new(&toren_lib::toren_root().join("plugins")) {
+        Ok(plugin_mgr) => explain_task_status(
+            &plugin_mgr,
+            assignment.task_id.as_deref(),
+            assignment.task_source.as_deref(),
+            &config.tasks.sources,
+            toren_lib::PluginContext::new(seg_path, Some(assignment.segment.clone())),
+        ),
+        Err(e) => toren_lib::composite_status::SignalExplanation {
+            signal: "task",
+            value: "unknown".to_string(),
+            evidence: vec![format!("failed to load plugins: {:#}", e)],
+        },
+    };
+    let signals = [
+        explain_agent_activity(&SessionLogs::from_config(config), &assignment.working_dir()),
+        explain_has_changes(
+            &assignment.workspace_path,
+            assignment.base_branch.as_deref(),
+            assignment.focus_path.as_deref(),
+        ),
+        task,
+    ];
+    for signal in &signals {
+        println!();
+        println!("{}: {}", signal.signal.bold(), signal.value);
+        for line in &signal.evidence {
+            println!("  {}", line.dimmed());
+        }
+    }
+    Ok(())
+}
+
+// ─── pair ────────────────────────────────────────────────────────────────────
+
+fn cmd_pair(config: &Config, pairing_token: &str, read_only: bool) -> Result<()> {
+    let body = daemon_json(
+        config,
+        "POST",
+        "/pair",
+        Some(serde_json::json!({ "pairing_token": pairing_token, "read_only": read_only })),
+    )
+    .context("Pairing failed; check the pairing token toren-daemon logged at startup")?;
+    let token = body
+        .get("session_token")
+        .and_then(|t| t.as_str())
+        .context("Daemon response has no session_token")?;
+
+    let path = daemon_token_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, token).with_context(|| format!("Failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    std::fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+
+    eprintln!(
+        "Paired with toren daemon at {}:{}{}; session token saved to {}",
+        config.host(),
+        config.port(),
+        if body["read_only"].as_bool() == Some(true) {
+            " (read-only)"
+        } else {
+            ""
+        },
+        toren_lib::tilde_shorten(&path)
+    );
+    Ok(())
+}
+
+// ─── doctor ──────────────────────────────────────────────────────────────────
+
+fn cmd_doctor(config: &Config) -> Result<()> {
+    use toren_lib::doctor::CheckStatus;
+
+    println!("{} {}", "config:".bold(), toren_lib::tilde_shorten(Path::new(&config.config_path)));
+    let checks = toren_lib::doctor::run_checks(config);
+    for check in &checks {
+        let mark = match check.status {
+            CheckStatus::Ok => "ok".green(),
+            CheckStatus::Warn => "warn".yellow(),
+            CheckStatus::Fail => "FAIL".red(),
+        };
+        println!("{:>4}  {}: {}", mark, check.name.bold(), check.detail);
+        if let Some(ref fix) = check.fix {
+            for line in fix.lines() {
+                println!("      {}", line.dimmed());
+            }
+        }
     }
 
+    let failed = checks.iter().filter(|c| c.status == CheckStatus::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed", failed);
+    }
+    Ok(())
+}
+
+// ─── notify-test ─────────────────────────────────────────────────────────────
+
+fn cmd_notify_test(config: &Config) -> Result<()> {
+    use toren_lib::notifications::{Notification, Notifier, NotifyEvent};
+
+    let notifier = Notifier::new(&config.notifications);
+    if notifier.targets().is_empty() {
+        anyhow::bail!("No [notifications] targets configured in {}", config.config_path);
+    }
+    let notification = Notification::new(
+        NotifyEvent::Test,
+        "toren test notification",
+        format!("Sent by breq notify-test from {}", toren_lib::tilde_shorten(Path::new(&config.config_path))),
+    );
+    let mut failed = 0;
+    for target in notifier.targets() {
+        match notifier.send_to(target, &notification) {
+            Ok(()) => println!("{:>4}  {}", "ok".green(), target),
+            Err(e) => {
+                failed += 1;
+                println!("{:>4}  {}: {:#}", "FAIL".red(), target, e);
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} target(s) failed", failed);
+    }
     Ok(())
 }
 
@@ -1493,32 +4976,82 @@ fn cmd_dismiss(config: &Config, reference: &str) -> Result<()> {
 fn cmd_show(
     config: &Config,
     workspace: &str,
-    field: &str,
+    field: Option<&str>,
+    transcript: bool,
     segment_name: Option<&str>,
 ) -> Result<()> {
     let segment_mgr = SegmentManager::new(config)?;
     let mut assignment_mgr = AssignmentManager::new()?;
     let segment = resolve_segment(&segment_mgr, segment_name)?;
 
-    let ws_name = workspace.to_lowercase();
-    let ancillary_num = toren_lib::word_to_number(&ws_name).unwrap_or(0);
+    let ws_name = toren_lib::naming::normalize_workspace_name(&segment.name, workspace);
+    let ancillary_num = toren_lib::naming::slot_number(&segment.name, &ws_name).unwrap_or(0);
     let ancillary_id_str = toren_lib::ancillary_id(&segment.name, ancillary_num);
 
     let assignment = assignment_mgr
         .get_active_for_ancillary(&ancillary_id_str)
-        .with_context(|| format!("No assignment found for workspace '{}'", ws_name))?;
+        .ok_or_else(|| {
+            TorenError::new(
+                ErrorCode::AssignmentNotFound,
+                format!("No assignment found for workspace '{}'", ws_name),
+            )
+        })?;
+
+    let logs = SessionLogs::from_config(config);
+    // The recorded session, else the latest one in the working dir
+    let session = match assignment.session_id {
+        Some(ref id) => ClaudeSession::find(&logs, &assignment.working_dir(), id),
+        None => ClaudeSession::latest(&logs, &assignment.working_dir()),
+    };
+    if transcript {
+        let session = session.ok_or_else(|| {
+            anyhow::anyhow!("No Claude session log found for {}", toren_lib::tilde_shorten(&assignment.working_dir()))
+        })?;
+        return print_transcript(&session);
+    }
 
+    let field = field.unwrap_or_default();
     let value = match field {
         "task.id" => assignment.task_id.as_deref().unwrap_or("").to_string(),
         "task.title" => assignment.task_title.as_deref().unwrap_or("").to_string(),
         "task.url" => assignment.task_url.as_deref().unwrap_or("").to_string(),
         "task.source" => assignment.task_source.as_deref().unwrap_or("").to_string(),
+        "task.auto_created" => assignment.source.auto_created_bead().to_string(),
+        "source" => match assignment.source {
+            AssignmentSource::Reference => "reference".to_string(),
+            AssignmentSource::Prompt { .. } => "prompt".to_string(),
+        },
+        "prompt" => match assignment.source {
+            AssignmentSource::Reference => String::new(),
+            AssignmentSource::Prompt { ref original_prompt, .. } => original_prompt.clone(),
+        },
         "workspace.path" => assignment.workspace_path.display().to_string(),
         "segment" => assignment.segment.clone(),
-        "ancillary_id" => assignment.ancillary_id.clone(),
-        "session_id" => assignment.session_id.as_deref().unwrap_or("").to_string(),
+        "ancillary_id" => assignment.ancillary_id.to_string(),
+        "session_id" => assignment
+            .session_id
+            .clone()
+            .or(session.map(|s| s.id))
+            .unwrap_or_default(),
+        "resume_instruction" => assignment.resume_instruction.as_deref().unwrap_or("").to_string(),
+        "assignment.path" => assignment
+            .focus_path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default(),
+        "notes" => format_notes(&assignment.notes).join("\n"),
+        "usage" | "usage.tokens" | "usage.cost" => {
+            // Live from the session logs, else as last recorded
+            let usage = toren_lib::usage::assignment_usage(&logs, assignment).or(assignment.usage);
+            match (field, usage) {
+                (_, None) => String::new(),
+                ("usage.tokens", Some(u)) => u.total_tokens().to_string(),
+                ("usage.cost", Some(u)) => format!("{:.2}", u.cost_usd),
+                (_, Some(u)) => format_usage(&u),
+            }
+        }
         _ => anyhow::bail!(
-            "Unknown field: {}. Supported: task.id, task.title, task.url, task.source, workspace.path, segment, ancillary_id, session_id",
+            "Unknown field: {}. Supported: task.id, task.title, task.url, task.source, task.auto_created, source, prompt, workspace.path, segment, ancillary_id, session_id, resume_instruction, assignment.path, notes, usage, usage.tokens, usage.cost",
             field
         ),
     };
@@ -1527,6 +5060,206 @@ fn cmd_show(
     Ok(())
 }
 
+/// Longest tool input or result shown on one transcript line.
+const TRANSCRIPT_SNIPPET: usize = 160;
+
+fn print_transcript(session: &ClaudeSession) -> Result<()> {
+    use toren_lib::claude_session::{message_usage, token_totals, ContentBlock, EntryKind};
+
+    let entries = session.entries()?;
+    println!("{} {}", "session:".bold(), session.id);
+    println!("{}", toren_lib::tilde_shorten(&session.path).dimmed());
+    for entry in &entries {
+        let speaker = match entry.kind {
+            EntryKind::User => "user".cyan(),
+            EntryKind::Assistant => "assistant".green(),
+            _ => continue,
+        };
+        for block in &entry.content {
+            match block {
+                ContentBlock::Text { text } if !text.trim().is_empty() => {
+                    println!();
+                    println!("{}: {}", speaker.clone().bold(), text.trim());
+                }
+                ContentBlock::ToolUse { name, input, .. } => {
+                    println!("  {} {} {}", "→".yellow(), name.bold(), snippet(&input.to_string()).dimmed());
+                }
+                ContentBlock::ToolResult { content, is_error, .. } => {
+                    let mark = if *is_error { "✗".red() } else { "←".dimmed() };
+                    println!("  {} {}", mark, snippet(content).dimmed());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let tokens = token_totals(&entries);
+    let cost: f64 = message_usage(&entries)
+        .iter()
+        .map(|(model, usage)| toren_lib::usage::estimate_cost(*model, usage))
+        .sum();
+    println!();
+    println!(
+        "{} {} in, {} out, {} cache read, {} cache write (~${:.2})",
+        "tokens:".bold(),
+        tokens.input_tokens,
+        tokens.output_tokens,
+        tokens.cache_read_input_tokens,
+        tokens.cache_creation_input_tokens,
+        cost
+    );
+    Ok(())
+}
+
+/// The first line of `text`, cut to [`TRANSCRIPT_SNIPPET`] characters.
+fn snippet(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or_default();
+    let mut out: String = line.chars().take(TRANSCRIPT_SNIPPET).collect();
+    if out.len() < line.len() || text.trim().lines().nth(1).is_some() {
+        out.push('…');
+    }
+    out
+}
+
+// ─── completions ────────────────────────────────────────────────────────────
+
+/// Positional arguments that take a workspace or task reference.
+const REFERENCE_ARGS: &[&str] = &["workspace", "reference"];
+
+const ZSH_HELPERS: &str = r#"(( $+functions[_breq_references] )) ||
+_breq_references() {
+    local -a refs
+    local seg=${opt_args[-s]:-${opt_args[--segment]}}
+    refs=(${${(f)"$(breq __complete-references ${seg:+--segment $seg} 2>/dev/null)"}//$'\t'/:})
+    _describe -t references 'workspace or task' refs
+}
+(( $+functions[_breq_segments] )) ||
+_breq_segments() {
+    local -a segs
+    segs=(${${(f)"$(breq __complete-segments 2>/dev/null)"}//$'\t'/:})
+    _describe -t segments 'segment' segs
+}
+
+"#;
+
+fn cmd_completions(shell: clap_complete::Shell) -> Result<()> {
+    let mut cmd = Cli::command();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, "breq", &mut script);
+    let script = String::from_utf8(script)?;
+    print!("{}", with_dynamic_completions(shell, &cmd, &script));
+    Ok(())
+}
+
+/// Subcommands (and their aliases) whose positional argument is a reference.
+fn reference_commands(cmd: &clap::Command) -> Vec<String> {
+    cmd.get_subcommands()
+        .filter(|sub| {
+            sub.get_positionals()
+                .any(|arg| REFERENCE_ARGS.contains(&arg.get_id().as_str()))
+        })
+        .flat_map(|sub| {
+            std::iter::once(sub.get_name().to_string())
+                .chain(sub.get_visible_aliases().map(str::to_string))
+        })
+        .collect()
+}
+
+/// Patch a generated script so references and `--segment` values complete
+/// from live state (via the hidden `__complete-*` commands), and drop those
+/// hidden commands from the subcommand listing.
+fn with_dynamic_completions(shell: clap_complete::Shell, cmd: &clap::Command, script: &str) -> String {
+    use clap_complete::Shell;
+
+    match shell {
+        Shell::Zsh => {
+            let mut out = String::new();
+            for line in script.lines() {
+                if line.starts_with("'__complete-") {
+                    continue;
+                }
+                let is_reference = REFERENCE_ARGS.iter().any(|name| {
+                    line.starts_with(&format!("':{} -- ", name))
+                        || line.starts_with(&format!("'::{} -- ", name))
+                });
+                let line = if is_reference {
+                    line.replace(":_default'", ":_breq_references'")
+                } else {
+                    line.replace(":SEGMENT:_default'", ":SEGMENT:_breq_segments'")
+                };
+                if line.starts_with("if [ \"$funcstack[1]\" = \"_breq\" ]") {
+                    out.push_str(ZSH_HELPERS);
+                }
+                out.push_str(&line);
+                out.push('\n');
+            }
+            out
+        }
+        Shell::Fish => {
+            let mut out = String::new();
+            for line in script.lines() {
+                if line.contains("-a \"__complete-") {
+                    continue;
+                }
+                out.push_str(line);
+                if line.contains(" -l segment ") && line.ends_with(" -r") {
+                    out.push_str(" -f -a \"(breq __complete-segments 2>/dev/null)\"");
+                }
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "complete -c breq -n \"__fish_breq_using_subcommand {}\" -f -a \"(breq __complete-references 2>/dev/null)\"\n",
+                reference_commands(cmd).join(" ")
+            ));
+            out
+        }
+        _ => script.to_string(),
+    }
+}
+
+/// Print `<completion>\t<description>` for each workspace and task ID in the
+/// segment, for the shell completion scripts.
+fn cmd_complete_references(config: &Config, segment_name: Option<&str>) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    let segment = resolve_segment(&segment_mgr, segment_name)?;
+    let mut assignment_mgr = AssignmentManager::new()?;
+
+    let mut seen = std::collections::HashSet::new();
+    for assignment in assignment_mgr.list_active_segment(&segment.name) {
+        let Some(num) = toren_lib::ancillary_number(&assignment.ancillary_id) else {
+            continue;
+        };
+        let ws_name = toren_lib::naming::workspace_name(&segment.name, num);
+        let title = assignment.task_title.as_deref().unwrap_or("");
+        println!("{}\t{}", ws_name, assignment.task_id.as_deref().unwrap_or(title));
+        if let Some(ref task_id) = assignment.task_id {
+            println!("{}\t{}", task_id, if title.is_empty() { &ws_name } else { title });
+        }
+        seen.insert(ws_name);
+    }
+
+    let workspace_mgr = WorkspaceManager::new(
+        config.ancillaries.workspace_root.clone(),
+        Some(config.proxy.domain.clone()),
+    )
+    .with_segment_roots(config.segment_workspace_roots());
+    for ws_name in workspace_mgr.list_workspaces(&segment.path).unwrap_or_default() {
+        if !seen.contains(&ws_name) {
+            println!("{}\tno assignment", ws_name);
+        }
+    }
+    Ok(())
+}
+
+/// Print `<segment>\t<path>` for each configured segment.
+fn cmd_complete_segments(config: &Config) -> Result<()> {
+    let segment_mgr = SegmentManager::new(config)?;
+    for segment in segment_mgr.list_all() {
+        println!("{}\t{}", segment.name, toren_lib::tilde_shorten(&segment.path));
+    }
+    Ok(())
+}
+
 // ─── init ───────────────────────────────────────────────────────────────────
 
 fn cmd_init(stealth: bool) -> Result<()> {
@@ -2082,21 +5815,32 @@ fn find_orphaned_workspaces(
     orphans
 }
 
-fn truncate_title(title: &str, max_len: usize) -> String {
-    if title.chars().count() <= max_len {
-        title.to_string()
-    } else if max_len > 3 {
-        let end: String = title.chars().take(max_len - 3).collect();
-        format!("{}...", end)
-    } else {
-        title.chars().take(max_len).collect()
+/// Compact age of an RFC 3339 timestamp, e.g. "5m", "3h", "2d".
+fn format_age(timestamp: &str) -> String {
+    let Ok(created) = chrono::DateTime::parse_from_rfc3339(timestamp) else {
+        return "-".to_string();
+    };
+    let secs = (chrono::Utc::now() - created.with_timezone(&chrono::Utc))
+        .num_seconds()
+        .max(0);
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
     }
 }
 
 /// Detect workspace context from current directory.
-fn detect_workspace_context() -> Result<(std::path::PathBuf, std::path::PathBuf, String)> {
+fn detect_workspace_context(no_cache: bool) -> Result<(std::path::PathBuf, std::path::PathBuf, String)> {
     let cwd = std::env::current_dir()?;
 
+    if !no_cache {
+        if let Some(cached) = toren_lib::context_cache::CachedContext::find(&cwd) {
+            return Ok((cached.segment_path, cached.workspace_path, cached.workspace_name));
+        }
+    }
+
     if cwd.join(".jj").exists() {
         return detect_jj_workspace_context(&cwd);
     }
@@ -0,0 +1,140 @@
+//! Live progress for workspace setup.
+//!
+//! toren.kdl setup can run for minutes (`pnpm install`, builds). Instead of
+//! silence, [`SetupSpinner`] redraws one status line on stderr with the
+//! running action, its last line of output and the elapsed time, and leaves
+//! a ✓/✗ line behind for each finished action.
+
+use colored::Colorize;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use toren_lib::{SetupEvent, SetupProgress};
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const TICK: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+struct Running {
+    index: usize,
+    total: usize,
+    label: String,
+    last_line: String,
+    started: Option<Instant>,
+    frame: usize,
+}
+
+/// Draws setup progress on stderr; the ticker stops once it is dropped.
+pub struct SetupSpinner {
+    running: Arc<Mutex<Option<Running>>>,
+}
+
+impl SetupSpinner {
+    pub fn new() -> Arc<Self> {
+        let spinner = Arc::new(Self {
+            running: Arc::new(Mutex::new(None)),
+        });
+        let running = Arc::downgrade(&spinner.running);
+        std::thread::spawn(move || tick(running));
+        spinner
+    }
+}
+
+fn tick(running: Weak<Mutex<Option<Running>>>) {
+    loop {
+        std::thread::sleep(TICK);
+        let Some(running) = running.upgrade() else {
+            return;
+        };
+        let mut running = running.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(ref mut action) = *running {
+            action.frame = (action.frame + 1) % FRAMES.len();
+            draw(action);
+        }
+    }
+}
+
+fn draw(action: &Running) {
+    let elapsed = action.started.map(|s| s.elapsed().as_secs()).unwrap_or(0);
+    let mut status = format!(
+        "{} [{}/{}] {} ({}s)",
+        FRAMES[action.frame], action.index, action.total, action.label, elapsed
+    );
+    if !action.last_line.is_empty() {
+        status.push_str(" — ");
+        status.push_str(&action.last_line);
+    }
+    let width = terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80);
+    let status: String = status.chars().take(width.saturating_sub(1)).collect();
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(stderr, "\r\x1b[2K{}", status);
+    let _ = stderr.flush();
+}
+
+/// A line of command output, without control characters (color codes,
+/// carriage returns) that would break the status line.
+fn printable(line: &str) -> String {
+    let mut out = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence up to its final byte
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else if !c.is_control() {
+            out.push(c);
+        }
+    }
+    out.trim().to_string()
+}
+
+impl SetupProgress for SetupSpinner {
+    fn on_setup_event(&self, _workspace_path: &Path, event: &SetupEvent) {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        match event {
+            SetupEvent::ActionStarted { index, total, label } => {
+                let action = Running {
+                    index: *index,
+                    total: *total,
+                    label: label.clone(),
+                    started: Some(Instant::now()),
+                    ..Default::default()
+                };
+                draw(&action);
+                *running = Some(action);
+            }
+            SetupEvent::Output { line, .. } => {
+                if let Some(ref mut action) = *running {
+                    let line = printable(line);
+                    if !line.is_empty() {
+                        action.last_line = line;
+                    }
+                }
+            }
+            SetupEvent::ActionFinished {
+                index,
+                total,
+                label,
+                ok,
+                skipped,
+                elapsed_ms,
+            } => {
+                *running = None;
+                let (mark, note) = if *skipped {
+                    ("·".dimmed(), "unchanged".to_string())
+                } else if *ok {
+                    ("✓".green(), format!("{:.1}s", *elapsed_ms as f64 / 1000.0))
+                } else {
+                    ("✗".red(), "failed".to_string())
+                };
+                eprintln!("\r\x1b[2K{} [{}/{}] {} {}", mark, index, total, label, note.dimmed());
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Minimal column layout for terminal tables.
+//!
+//! Cells may carry ANSI color codes; widths are measured on the visible text
+//! so colored and plain cells line up. One column can be marked flexible: it
+//! is truncated to whatever width the other columns leave free.
+
+/// Rows of pre-formatted cells laid out in aligned columns.
+pub struct Table {
+    columns: usize,
+    flex: Option<usize>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(columns: usize) -> Self {
+        Self {
+            columns,
+            flex: None,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Mark column `index` as the one that shrinks to fit the terminal.
+    pub fn flex(mut self, index: usize) -> Self {
+        self.flex = Some(index);
+        self
+    }
+
+    pub fn push_row(&mut self, row: Vec<String>) {
+        debug_assert_eq!(row.len(), self.columns);
+        self.rows.push(row);
+    }
+
+    /// Render every row as a line no wider than `max_width` (when a flex column allows it).
+    pub fn render(&self, max_width: usize) -> Vec<String> {
+        let mut widths = vec![0; self.columns];
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(visible_width(cell));
+            }
+        }
+
+        if let Some(flex) = self.flex {
+            let fixed: usize = widths
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != flex)
+                .map(|(_, w)| w)
+                .sum();
+            let separators = self.columns.saturating_sub(1);
+            let available = max_width.saturating_sub(fixed + separators);
+            widths[flex] = widths[flex].min(available);
+        }
+
+        self.rows
+            .iter()
+            .map(|row| {
+                let last = row.len().saturating_sub(1);
+                let cells: Vec<String> = row
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| {
+                        let cell = truncate_visible(cell, widths[i]);
+                        if i == last {
+                            cell
+                        } else {
+                            pad_visible(&cell, widths[i])
+                        }
+                    })
+                    .collect();
+                cells.join(" ")
+            })
+            .collect()
+    }
+}
+
+/// Number of printable characters in `s`, ignoring ANSI escape sequences.
+pub fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            skip_escape(&mut chars);
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Right-pad `s` with spaces to `width` visible characters.
+fn pad_visible(s: &str, width: usize) -> String {
+    let pad = width.saturating_sub(visible_width(s));
+    format!("{}{}", s, " ".repeat(pad))
+}
+
+/// Truncate `s` to `max` visible characters, ending with "..." when cut.
+///
+/// Escape sequences are kept intact, and a reset is appended if any were
+/// present so a cut-off color doesn't bleed into the next cell.
+fn truncate_visible(s: &str, max: usize) -> String {
+    if visible_width(s) <= max {
+        return s.to_string();
+    }
+
+    let (keep, ellipsis) = if max > 3 { (max - 3, "...") } else { (max, "") };
+    let mut out = String::new();
+    let mut kept = 0;
+    let mut styled = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            styled = true;
+            out.push(c);
+            // Copy the rest of the sequence through the final byte
+            while let Some(&next) = chars.peek() {
+                out.push(next);
+                chars.next();
+                if next != '[' && ('\x40'..='\x7e').contains(&next) {
+                    break;
+                }
+            }
+        } else if kept < keep {
+            out.push(c);
+            kept += 1;
+        }
+    }
+    out.push_str(ellipsis);
+    if styled {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+/// Consume a CSI sequence (`ESC [ ... final`) after its leading ESC.
+fn skip_escape(chars: &mut std::str::Chars<'_>) {
+    for c in chars.by_ref() {
+        if c != '[' && ('\x40'..='\x7e').contains(&c) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_width_ignores_ansi() {
+        assert_eq!(visible_width("idle"), 4);
+        assert_eq!(visible_width("\x1b[33mbusy\x1b[0m"), 4);
+        assert_eq!(visible_width("\x1b[1;32mok\x1b[0m"), 2);
+    }
+
+    #[test]
+    fn test_truncate_visible_keeps_escapes() {
+        assert_eq!(truncate_visible("short", 10), "short");
+        assert_eq!(truncate_visible("a long title", 8), "a lon...");
+        assert_eq!(
+            truncate_visible("\x1b[33mhello world\x1b[0m", 8),
+            "\x1b[33mhello\x1b[0m...\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_render_aligns_colored_cells() {
+        let mut table = Table::new(3);
+        table.push_row(vec!["one".into(), "\x1b[33mbusy\x1b[0m".into(), "x".into()]);
+        table.push_row(vec!["three".into(), "idle".into(), "y".into()]);
+        let lines = table.render(80);
+        assert_eq!(lines[0], "one   \x1b[33mbusy\x1b[0m x");
+        assert_eq!(lines[1], "three idle y");
+    }
+
+    #[test]
+    fn test_render_truncates_flex_column() {
+        let mut table = Table::new(2).flex(1);
+        table.push_row(vec!["one".into(), "a very long task title".into()]);
+        let lines = table.render(14);
+        assert_eq!(lines[0], "one a very ...");
+        assert_eq!(visible_width(&lines[0]), 14);
+    }
+}
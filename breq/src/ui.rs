@@ -0,0 +1,418 @@
+//! `breq ui`: a terminal dashboard of the daemon's assignments.
+//!
+//! The left pane lists assignments with their composite status, refreshed
+//! from `GET /api/assignments`. The right pane tails the selected ancillary's
+//! work log over `/ws/ancillaries/<id>`, replaying it from the start. Daemon
+//! calls run on background threads so the dashboard stays responsive while
+//! a complete pushes or a resume recreates a workspace.
+
+use anyhow::{Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use toren_lib::composite_status::CompositeStatus;
+use toren_lib::health::AssignmentHealth;
+use toren_lib::{Assignment, Config};
+
+/// How often the assignment list is fetched again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Work log lines kept for the selected ancillary.
+const LOG_LINES: usize = 2000;
+
+/// An assignment as `GET /api/assignments` returns it.
+#[derive(Debug, Clone, Deserialize)]
+struct Row {
+    #[serde(flatten)]
+    assignment: Assignment,
+    #[serde(flatten)]
+    composite: CompositeStatus,
+    #[serde(default)]
+    health: Option<AssignmentHealth>,
+}
+
+/// The latest assignment fetch, or its error.
+type Fetched = Arc<Mutex<Option<Result<Vec<Row>, String>>>>;
+
+/// A daemon action waiting for `y`.
+#[derive(Debug, Clone, Copy)]
+enum Pending {
+    Complete,
+    Abort,
+}
+
+/// The selected ancillary's work log, filled by its tail thread.
+#[derive(Default)]
+struct LogPane {
+    ancillary_id: String,
+    lines: Vec<Line<'static>>,
+}
+
+struct App {
+    config: Config,
+    rows: Vec<Row>,
+    list: ListState,
+    /// Latest fetch, set by the refresh thread
+    fetched: Fetched,
+    last_refresh: Option<Instant>,
+    log: Arc<Mutex<LogPane>>,
+    /// Bumped on every selection change; a tail thread stops once its
+    /// generation is stale
+    log_generation: Arc<AtomicU64>,
+    pending: Option<Pending>,
+    /// Last error or action result, shown in the footer
+    message: Arc<Mutex<Option<String>>>,
+}
+
+pub fn run(config: &Config) -> Result<()> {
+    // Fail before taking over the terminal if the daemon isn't there
+    let rows = fetch_rows(config)?;
+
+    let mut app = App {
+        config: config.clone(),
+        rows: Vec::new(),
+        list: ListState::default(),
+        fetched: Arc::new(Mutex::new(Some(Ok(rows)))),
+        last_refresh: Some(Instant::now()),
+        log: Arc::default(),
+        log_generation: Arc::default(),
+        pending: None,
+        message: Arc::default(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal);
+    ratatui::restore();
+    app.log_generation.fetch_add(1, Ordering::SeqCst);
+    result
+}
+
+impl App {
+    fn event_loop(&mut self, terminal: &mut ratatui::DefaultTerminal) -> Result<()> {
+        loop {
+            self.apply_fetch();
+            if self.last_refresh.is_none_or(|at| at.elapsed() >= REFRESH_INTERVAL) {
+                self.refresh();
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(Duration::from_millis(200))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(pending) = self.pending.take() {
+                if key.code == KeyCode::Char('y') {
+                    self.act(match pending {
+                        Pending::Complete => Action::Complete,
+                        Pending::Abort => Action::Abort,
+                    });
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.select(1),
+                KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+                KeyCode::Char('r') => self.act(Action::Resume),
+                KeyCode::Char('c') if self.selected().is_some() => self.pending = Some(Pending::Complete),
+                KeyCode::Char('a') if self.selected().is_some() => self.pending = Some(Pending::Abort),
+                _ => {}
+            }
+        }
+    }
+
+    fn selected(&self) -> Option<&Row> {
+        self.list.selected().and_then(|i| self.rows.get(i))
+    }
+
+    fn select(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.rows.len() as isize - 1) as usize;
+        self.list.select(Some(next));
+        self.tail_selected();
+    }
+
+    /// Fetch the assignment list in the background.
+    fn refresh(&mut self) {
+        self.last_refresh = Some(Instant::now());
+        let config = self.config.clone();
+        let fetched = self.fetched.clone();
+        std::thread::spawn(move || {
+            let rows = fetch_rows(&config).map_err(|e| format!("{:#}", e));
+            *fetched.lock().unwrap() = Some(rows);
+        });
+    }
+
+    /// Take the latest fetch, keeping the same assignment selected.
+    fn apply_fetch(&mut self) {
+        let Some(fetched) = self.fetched.lock().unwrap().take() else {
+            return;
+        };
+        let rows = match fetched {
+            Ok(rows) => rows,
+            Err(e) => {
+                *self.message.lock().unwrap() = Some(e);
+                return;
+            }
+        };
+        let selected_id = self.selected().map(|row| row.assignment.id.clone());
+        self.rows = rows;
+        self.rows
+            .sort_by(|a, b| (&a.assignment.segment, a.assignment.ancillary_num).cmp(&(&b.assignment.segment, b.assignment.ancillary_num)));
+        let index = selected_id
+            .and_then(|id| self.rows.iter().position(|row| row.assignment.id == id))
+            .or((!self.rows.is_empty()).then_some(0));
+        self.list.select(index.map(|i| i.min(self.rows.len().saturating_sub(1))));
+        self.tail_selected();
+    }
+
+    /// Tail the selected ancillary's work log, unless it already is.
+    fn tail_selected(&mut self) {
+        let Some(ancillary_id) = self.selected().map(|row| row.assignment.ancillary_id.to_string()) else {
+            return;
+        };
+        if self.log.lock().unwrap().ancillary_id == ancillary_id {
+            return;
+        }
+        let generation = self.log_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.log.lock().unwrap() = LogPane {
+            ancillary_id: ancillary_id.clone(),
+            lines: Vec::new(),
+        };
+        let url = format!(
+            "ws://{}:{}/ws/ancillaries/{}",
+            self.config.host(),
+            self.config.port(),
+            urlencoding::encode(&ancillary_id)
+        );
+        let log = self.log.clone();
+        let current = self.log_generation.clone();
+        std::thread::spawn(move || {
+            let alive = || current.load(Ordering::SeqCst) == generation;
+            let push = |line: Line<'static>| {
+                let mut log = log.lock().unwrap();
+                log.lines.push(line);
+                let excess = log.lines.len().saturating_sub(LOG_LINES);
+                log.lines.drain(..excess);
+            };
+            if let Err(e) = tail_work_log(&url, &alive, &push) {
+                if alive() {
+                    push(Line::from(format!("[{:#}]", e)).dark_gray());
+                }
+            }
+        });
+    }
+
+    fn act(&mut self, action: Action) {
+        let Some(row) = self.selected() else {
+            return;
+        };
+        let id = row.assignment.id.clone();
+        let ancillary_id = row.assignment.ancillary_id.to_string();
+        let config = self.config.clone();
+        let message = self.message.clone();
+        let fetched = self.fetched.clone();
+        *message.lock().unwrap() = Some(format!("{} {}...", action.verb(), ancillary_id));
+        std::thread::spawn(move || {
+            let path = format!("/api/assignments/{}/{}", urlencoding::encode(&id), action.endpoint());
+            let body = match action {
+                Action::Resume => serde_json::json!({}),
+                Action::Complete | Action::Abort => serde_json::json!({ "kill": true }),
+            };
+            let result = match crate::daemon_json(&config, "POST", &path, Some(body)) {
+                Ok(_) => format!("{} {}: done", action.verb(), ancillary_id),
+                Err(e) => format!("{} {} failed: {:#}", action.verb(), ancillary_id, e),
+            };
+            *message.lock().unwrap() = Some(result);
+            *fetched.lock().unwrap() = Some(fetch_rows(&config).map_err(|e| format!("{:#}", e)));
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(main);
+
+        let items: Vec<ListItem> = self.rows.iter().map(row_item).collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(" Assignments ({}) ", self.rows.len())))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, left, &mut self.list);
+
+        let log = self.log.lock().unwrap();
+        let height = right.height.saturating_sub(2) as usize;
+        let start = log.lines.len().saturating_sub(height);
+        let title = if log.ancillary_id.is_empty() {
+            " Work log ".to_string()
+        } else {
+            format!(" {} ", log.ancillary_id)
+        };
+        let paragraph = Paragraph::new(log.lines[start..].to_vec())
+            .block(Block::bordered().title(title))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, right);
+        drop(log);
+
+        let footer_line = match (self.pending, self.message.lock().unwrap().as_ref()) {
+            (Some(Pending::Complete), _) => Line::from("Complete the selected assignment (closes its task)? y/n").yellow(),
+            (Some(Pending::Abort), _) => Line::from("Abort the selected assignment (reopens its task)? y/n").yellow(),
+            (None, Some(message)) => Line::from(message.clone()),
+            (None, None) => Line::from("↑/↓ select · r resume · c complete · a abort · q quit").dark_gray(),
+        };
+        frame.render_widget(Paragraph::new(footer_line), footer);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Resume,
+    Complete,
+    Abort,
+}
+
+impl Action {
+    fn endpoint(self) -> &'static str {
+        match self {
+            Action::Resume => "resume",
+            Action::Complete => "complete",
+            Action::Abort => "abort",
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            Action::Resume => "Resuming",
+            Action::Complete => "Completing",
+            Action::Abort => "Aborting",
+        }
+    }
+}
+
+fn fetch_rows(config: &Config) -> Result<Vec<Row>> {
+    let response = crate::daemon_json(config, "GET", "/api/assignments", None)?;
+    serde_json::from_value(response["assignments"].clone()).context("Unexpected /api/assignments response")
+}
+
+fn row_item(row: &Row) -> ListItem<'static> {
+    let a = &row.assignment;
+    let busy = row.composite.agent_activity == "busy";
+    let mut spans = vec![
+        Span::raw(format!("{:<18}", a.ancillary_id.as_str())),
+        if busy {
+            Span::raw("busy ").yellow()
+        } else {
+            Span::raw("idle ").green()
+        },
+        if row.composite.has_conflicts {
+            Span::raw("! ").red()
+        } else if row.composite.has_changes {
+            Span::raw("* ").yellow()
+        } else {
+            Span::raw("  ")
+        },
+        Span::raw(format!("{:<12} ", a.task_id.as_deref().unwrap_or("-"))),
+    ];
+    if let Some(health) = row.health.as_ref().filter(|h| h.state.is_warning()) {
+        spans.push(Span::raw(format!("[{}] ", health.state.as_str())).red());
+    }
+    spans.push(Span::raw(a.task_title.clone().unwrap_or_default()));
+    ListItem::new(Line::from(spans))
+}
+
+/// Stream a work log into `push` until the work ends, the connection drops
+/// or `alive` reports that nobody is watching anymore.
+fn tail_work_log(url: &str, alive: &dyn Fn() -> bool, push: &dyn Fn(Line<'static>)) -> Result<()> {
+    let uri: tungstenite::http::Uri = url.parse().context("Invalid work log URL")?;
+    let host = uri.host().unwrap_or("localhost");
+    let port = uri.port_u16().unwrap_or(80);
+    let stream = std::net::TcpStream::connect((host, port)).context("Failed to connect to the daemon")?;
+    // Wake up regularly to notice a selection change
+    stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+    let (mut socket, _) = tungstenite::client(url, stream).context("WebSocket handshake failed")?;
+
+    while alive() {
+        let message = match socket.read() {
+            Ok(message) => message,
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) =>
+            {
+                continue;
+            }
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let tungstenite::Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) else {
+            continue;
+        };
+        if !alive() {
+            break;
+        }
+        format_ws_message(&value).into_iter().for_each(push);
+    }
+    let _ = socket.close(None);
+    Ok(())
+}
+
+/// Lines for one message of the ancillary WebSocket.
+fn format_ws_message(value: &serde_json::Value) -> Vec<Line<'static>> {
+    let str_of = |v: &serde_json::Value, key: &str| v[key].as_str().unwrap_or_default().to_string();
+    match value["type"].as_str() {
+        Some("status") => vec![Line::from(format!("[{}]", str_of(value, "status"))).dark_gray()],
+        Some("error") => vec![Line::from(str_of(value, "message")).red()],
+        Some("replay_complete") => vec![Line::from("── live ──").dark_gray()],
+        Some("event") => format_op(&value["event"]["op"]),
+        _ => Vec::new(),
+    }
+}
+
+fn format_op(op: &serde_json::Value) -> Vec<Line<'static>> {
+    let str_of = |key: &str| op[key].as_str().unwrap_or_default().to_string();
+    let text_lines = |text: String, style: Style| -> Vec<Line<'static>> {
+        text.lines().map(|l| Line::styled(l.to_string(), style)).collect()
+    };
+    match op["type"].as_str().unwrap_or_default() {
+        "assistant_message" => text_lines(str_of("content"), Style::new()),
+        "user_message" => text_lines(format!("> {}", str_of("content")), Style::new().fg(Color::Cyan)),
+        "tool_call" => {
+            let input = op["input"].to_string();
+            let input: String = input.chars().take(120).collect();
+            vec![Line::from(format!("→ {} {}", str_of("name"), input)).fg(Color::Blue)]
+        }
+        "tool_result" if op["is_error"].as_bool() == Some(true) => {
+            vec![Line::from("✗ tool failed").red()]
+        }
+        "approval_requested" => vec![Line::from(format!("? approve {}", str_of("tool"))).yellow()],
+        "command_start" => {
+            let args: Vec<&str> = op["args"].as_array().into_iter().flatten().filter_map(|a| a.as_str()).collect();
+            vec![Line::from(format!("$ {} {}", str_of("command"), args.join(" "))).bold()]
+        }
+        "command_output" => {
+            let mut lines = text_lines(str_of("stdout"), Style::new());
+            lines.extend(text_lines(str_of("stderr"), Style::new().fg(Color::Red)));
+            lines
+        }
+        "command_exit" => vec![Line::from(format!("exit {}", op["code"])).dark_gray()],
+        "status_change" => vec![Line::from(format!("[{}]", str_of("status"))).dark_gray()],
+        "assignment_completed" => vec![Line::from("✓ completed").green()],
+        "assignment_failed" => vec![Line::from(format!("✗ failed: {}", str_of("error"))).red()],
+        _ => Vec::new(),
+    }
+}
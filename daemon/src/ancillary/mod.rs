@@ -5,12 +5,16 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock as TokioRwLock;
-use tracing::info;
+use tracing::{info, warn};
 
-pub use runtime::{AncillaryWork, ClientInput, WorkStatus};
-use toren_lib::{Agent, Assignment, AssignmentManager};
+pub use runtime::{AncillaryWork, ApprovalDecision, ClientInput, WorkOptions, WorkStatus};
+use crate::events::{DaemonEvent, EventBus};
+use crate::security::SecurityContext;
+use toren_lib::{Agent, AncillaryId, Assignment, AssignmentManager, AssignmentOwner};
 pub use work_log::WorkEvent;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -157,6 +161,14 @@ impl Default for AncillaryManager {
     }
 }
 
+/// How often a shutdown drain checks whether interrupted work has stopped.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Work refused because the daemon is shutting down.
+#[derive(Debug, thiserror::Error)]
+#[error("Daemon is shutting down; not starting new work")]
+pub struct ShuttingDown;
+
 /// Manages active work for ancillaries (embedded runtime).
 /// This is separate from AncillaryManager which tracks connection state.
 pub struct WorkManager {
@@ -164,6 +176,12 @@ pub struct WorkManager {
     active_work: TokioRwLock<HashMap<String, Arc<AncillaryWork>>>,
     /// Reference to assignment manager for persisting status changes
     assignments: Option<Arc<TokioRwLock<AssignmentManager>>>,
+    /// Event bus for publishing work status changes
+    events: Option<EventBus>,
+    /// Issues each session's agent token, and the daemon URL it is for
+    api_access: Option<(Arc<SecurityContext>, String)>,
+    /// Set by [`WorkManager::drain`]; no new work starts after it
+    shutting_down: AtomicBool,
 }
 
 impl WorkManager {
@@ -171,6 +189,9 @@ impl WorkManager {
         Self {
             active_work: TokioRwLock::new(HashMap::new()),
             assignments: None,
+            events: None,
+            api_access: None,
+            shutting_down: AtomicBool::new(false),
         }
     }
 
@@ -179,30 +200,93 @@ impl WorkManager {
         self.assignments = Some(assignments);
     }
 
-    /// Start work for an ancillary on an assignment
+    /// Set the event bus used to publish work status changes
+    pub fn set_events(&mut self, events: EventBus) {
+        self.events = Some(events);
+    }
+
+    /// Hand each agent an assignment-scoped token for the daemon at
+    /// `api_url`, as `TOREN_API_URL` and `TOREN_API_TOKEN`
+    pub fn set_api_access(&mut self, security: Arc<SecurityContext>, api_url: String) {
+        self.api_access = Some((security, api_url));
+    }
+
+    /// Start work for an ancillary on an assignment.
+    ///
+    /// Records the daemon as the assignment's owner for the length of the
+    /// session; fails with [`toren_lib::AssignmentOwned`] while a breq
+    /// session owns it, unless `steal`. `permission` decides how the agent
+    /// gets to use tools.
     pub async fn start_work(
         &self,
-        ancillary_id: String,
+        ancillary_id: AncillaryId,
         assignment: Assignment,
         agent: &Agent,
+        mut options: WorkOptions,
+        steal: bool,
     ) -> Result<Arc<AncillaryWork>> {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return Err(ShuttingDown.into());
+        }
         info!(
-            "Starting work for {} on {} (agent: {})",
+            "Starting work for {} on {} (agent: {}, permissions: {})",
             ancillary_id,
             assignment.task_id.as_deref().unwrap_or("-"),
             agent,
+            options.permission,
         );
 
         let assignment_id = assignment.id.clone();
-        let work = AncillaryWork::start(ancillary_id.clone(), assignment, agent.clone()).await?;
-        let work = Arc::new(work);
+        let owner = AssignmentOwner::daemon();
+        if let Some(ref assignments) = self.assignments {
+            assignments
+                .write()
+                .await
+                .claim_owner(&assignment_id, owner.clone(), steal)?;
+        }
+
+        if let Some((ref security, ref api_url)) = self.api_access {
+            if let Some(token) = security.issue_agent_token(&assignment_id) {
+                options.env.insert("TOREN_API_URL".to_string(), api_url.clone());
+                options.env.insert("TOREN_API_TOKEN".to_string(), token);
+                options.env.insert("TOREN_ASSIGNMENT_ID".to_string(), assignment_id.clone());
+            }
+        }
+
+        let started = AncillaryWork::start(ancillary_id.to_string(), assignment, agent.clone(), options).await;
+        let work = match started {
+            Ok(work) => Arc::new(work),
+            Err(e) => {
+                if let Some(ref assignments) = self.assignments {
+                    let _ = assignments.write().await.release_owner(&assignment_id, &owner);
+                }
+                if let Some((ref security, _)) = self.api_access {
+                    security.revoke_agent_tokens(&assignment_id);
+                }
+                return Err(e);
+            }
+        };
 
         let mut active = self.active_work.write().await;
-        active.insert(ancillary_id, work.clone());
+        active.insert(ancillary_id.to_string(), work.clone());
 
         // Spawn a monitor task to persist assignment status and session_id
         if let Some(ref assignments) = self.assignments {
             let assignments = assignments.clone();
+            let events = self.events.clone();
+            let security = self.api_access.as_ref().map(|(security, _)| security.clone());
+            let publish_status = {
+                let assignment_id = assignment_id.clone();
+                move |status: &str| {
+                    if let Some(ref events) = events {
+                        events.publish(DaemonEvent::WorkStatusChanged {
+                            ancillary_id: ancillary_id.clone(),
+                            assignment_id: assignment_id.clone(),
+                            status: status.to_string(),
+                        });
+                    }
+                }
+            };
             let (mut event_rx, _) = work.subscribe();
             tokio::spawn(async move {
                 let mut session_id_captured = false;
@@ -214,14 +298,16 @@ impl WorkManager {
                             match event {
                                 Ok(ev) => {
                                     // Check for session_id in status change events
-                                    if !session_id_captured {
-                                        if let work_log::WorkOp::StatusChange { ref status } = ev.op {
-                                            if let Some(sid) = status.strip_prefix("session_id:") {
+                                    if let work_log::WorkOp::StatusChange { ref status } = ev.op {
+                                        if let Some(sid) = status.strip_prefix("session_id:") {
+                                            if !session_id_captured {
                                                 let mut mgr = assignments.write().await;
                                                 let _ = mgr.update_session_id(&assignment_id, Some(sid.to_string()));
                                                 info!("Captured session_id {} for assignment {}", sid, assignment_id);
                                                 session_id_captured = true;
                                             }
+                                        } else {
+                                            publish_status(status);
                                         }
                                     }
 
@@ -230,9 +316,14 @@ impl WorkManager {
                                     // "complete" and "abort" are explicit user actions via the
                                     // lifecycle endpoints, not automatic on session end.
                                     match ev.op {
-                                        work_log::WorkOp::AssignmentCompleted
-                                        | work_log::WorkOp::AssignmentFailed { .. } => {
+                                        work_log::WorkOp::AssignmentCompleted => {
+                                            info!("Work session ended for assignment {}", assignment_id);
+                                            publish_status("completed");
+                                            break;
+                                        }
+                                        work_log::WorkOp::AssignmentFailed { .. } => {
                                             info!("Work session ended for assignment {}", assignment_id);
+                                            publish_status("failed");
                                             break;
                                         }
                                         _ => {}
@@ -252,6 +343,13 @@ impl WorkManager {
                         }
                     }
                 }
+
+                // The session is over; a breq session may now take the
+                // assignment, and its agent's token is no longer good
+                let _ = assignments.write().await.release_owner(&assignment_id, &owner);
+                if let Some(security) = security {
+                    security.revoke_agent_tokens(&assignment_id);
+                }
             });
         }
 
@@ -276,6 +374,68 @@ impl WorkManager {
         }
     }
 
+    /// Stop accepting work, interrupt all running work and wait up to
+    /// `timeout` for it to stop and release its assignments, then flush the
+    /// work logs. Returns the ancillaries whose work was still running at
+    /// the deadline.
+    pub async fn drain(&self, timeout: Duration) -> Vec<String> {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + timeout;
+
+        let active: Vec<(String, Arc<AncillaryWork>)> = self
+            .active_work
+            .read()
+            .await
+            .iter()
+            .map(|(id, work)| (id.clone(), work.clone()))
+            .collect();
+        for (id, work) in &active {
+            if !is_finished(&work.status().await) {
+                info!("Interrupting work for {}", id);
+                if let Err(e) = work.interrupt().await {
+                    warn!("Failed to interrupt work for {}: {:#}", id, e);
+                }
+            }
+        }
+
+        let mut running: Vec<&(String, Arc<AncillaryWork>)> = active.iter().collect();
+        loop {
+            let mut still_running = Vec::new();
+            for entry in running {
+                if !self.is_settled(&entry.1).await {
+                    still_running.push(entry);
+                }
+            }
+            running = still_running;
+            if running.is_empty() || Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+
+        for (id, work) in &active {
+            if let Err(e) = work.sync_log().await {
+                warn!("Failed to flush work log for {}: {:#}", id, e);
+            }
+        }
+        running.into_iter().map(|(id, _)| id.clone()).collect()
+    }
+
+    /// Whether the work has stopped and its monitor has released the
+    /// assignment.
+    async fn is_settled(&self, work: &AncillaryWork) -> bool {
+        if !is_finished(&work.status().await) {
+            return false;
+        }
+        let Some(ref assignments) = self.assignments else {
+            return true;
+        };
+        let mut assignments = assignments.write().await;
+        assignments
+            .get(&work.assignment.id)
+            .is_none_or(|a| !matches!(a.owner, Some(AssignmentOwner::Daemon { .. })))
+    }
+
     /// List all active work
     #[allow(dead_code)]
     pub async fn list_active(&self) -> Vec<(String, WorkStatus)> {
@@ -292,14 +452,17 @@ impl WorkManager {
     pub async fn has_active_work(&self, ancillary_id: &str) -> bool {
         let active = self.active_work.read().await;
         if let Some(work) = active.get(ancillary_id) {
-            let status = work.status().await;
-            !matches!(status, WorkStatus::Completed | WorkStatus::Failed { .. })
+            !is_finished(&work.status().await)
         } else {
             false
         }
     }
 }
 
+fn is_finished(status: &WorkStatus) -> bool {
+    matches!(status, WorkStatus::Completed | WorkStatus::Failed { .. })
+}
+
 impl Default for WorkManager {
     fn default() -> Self {
         Self::new()
@@ -1,14 +1,16 @@
 use anyhow::{Context, Result};
 use claude_agent_sdk_rs::{
-    query_stream, ClaudeAgentOptions, ContentBlock, Message, PermissionMode,
+    query_stream, CanUseToolCallback, ClaudeAgentOptions, ContentBlock, Message, PermissionMode,
+    PermissionResult, PermissionResultAllow, PermissionResultDeny, ToolPermissionContext,
 };
 use futures::StreamExt;
-use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, RwLock};
-use tracing::{error, info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+use tracing::{error, info, warn, Instrument};
 
 use super::work_log::{WorkLog, WorkOp};
-use toren_lib::{Agent, AgentKind, Assignment};
+use toren_lib::{Agent, AgentKind, Assignment, PermissionPolicy};
 
 /// Status of an ancillary's work execution
 #[derive(Debug, Clone, PartialEq)]
@@ -17,8 +19,7 @@ pub enum WorkStatus {
     Starting,
     /// Actively working
     Working,
-    /// Waiting for user input/approval
-    #[allow(dead_code)]
+    /// Waiting for a client to approve a tool use
     AwaitingInput,
     /// Work completed successfully
     Completed,
@@ -47,13 +48,35 @@ pub enum ClientInput {
     Interrupt,
 }
 
+/// How one work session runs its agent.
+#[derive(Debug, Clone, Default)]
+pub struct WorkOptions {
+    /// How the agent gets to use tools
+    pub permission: PermissionPolicy,
+    /// Environment for the agent on top of the workspace's `.toren-env`
+    pub env: HashMap<String, String>,
+    /// Evaluated toren.kdl vars, for the prompt template's `vars.*`
+    pub vars: HashMap<String, serde_json::Value>,
+}
+
+/// A client's answer to a tool approval request (`ask` permission mode).
+#[derive(Debug, Clone)]
+pub struct ApprovalDecision {
+    pub allow: bool,
+    /// Reason passed to the agent when denying
+    pub message: Option<String>,
+    pub client_id: String,
+}
+
+/// Tool uses waiting for a decision, keyed by approval request ID
+type PendingApprovals = Arc<Mutex<HashMap<String, oneshot::Sender<ApprovalDecision>>>>;
+
 /// An ancillary work execution context
 pub struct AncillaryWork {
     /// Ancillary identifier (e.g., "Toren One")
     #[allow(dead_code)]
     pub ancillary_id: String,
     /// The assignment being worked on
-    #[allow(dead_code)]
     pub assignment: Assignment,
     /// Current work status
     status: Arc<RwLock<WorkStatus>>,
@@ -63,13 +86,20 @@ pub struct AncillaryWork {
     event_tx: broadcast::Sender<super::work_log::WorkEvent>,
     /// Input channel (from clients)
     input_tx: mpsc::Sender<ClientInput>,
+    /// Tool approvals awaiting a client
+    approvals: PendingApprovals,
     /// Handle to the work task
     task_handle: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl AncillaryWork {
     /// Start work on an assignment
-    pub async fn start(ancillary_id: String, assignment: Assignment, agent: Agent) -> Result<Self> {
+    pub async fn start(
+        ancillary_id: String,
+        assignment: Assignment,
+        agent: Agent,
+        options: WorkOptions,
+    ) -> Result<Self> {
         let work_log =
             WorkLog::open(&ancillary_id, &assignment.id).context("Failed to open work log")?;
 
@@ -78,6 +108,7 @@ impl AncillaryWork {
 
         let status = Arc::new(RwLock::new(WorkStatus::Starting));
         let work_log = Arc::new(RwLock::new(work_log));
+        let approvals = PendingApprovals::default();
 
         let mut work = Self {
             ancillary_id: ancillary_id.clone(),
@@ -86,6 +117,7 @@ impl AncillaryWork {
             work_log: work_log.clone(),
             event_tx: event_tx.clone(),
             input_tx,
+            approvals: approvals.clone(),
             task_handle: None,
         };
 
@@ -93,39 +125,53 @@ impl AncillaryWork {
         {
             let mut log = work_log.write().await;
             let event = log.append(WorkOp::AssignmentStarted {
-                task_id: assignment.task_id.clone().unwrap_or_default(),
+                task_id: assignment.task_id.as_deref().unwrap_or_default().to_string(),
             })?;
             let _ = event_tx.send(event);
         }
 
         // Spawn the work task
-        let task_handle = tokio::spawn(Self::work_loop(
-            ancillary_id,
-            assignment,
-            agent,
-            status,
-            work_log,
-            event_tx,
-            input_rx,
-        ));
+        let span = tracing::info_span!(
+            "agent.work",
+            ancillary = %ancillary_id,
+            assignment = %assignment.id,
+            agent = %agent,
+        );
+        let task_handle = tokio::spawn(
+            Self::work_loop(
+                ancillary_id,
+                assignment,
+                agent,
+                options,
+                status,
+                work_log,
+                event_tx,
+                input_rx,
+                approvals,
+            )
+            .instrument(span),
+        );
 
         work.task_handle = Some(task_handle);
         Ok(work)
     }
 
     /// The main work loop that runs a coding agent
+    #[allow(clippy::too_many_arguments)]
     async fn work_loop(
         ancillary_id: String,
         assignment: Assignment,
         agent: Agent,
+        options: WorkOptions,
         status: Arc<RwLock<WorkStatus>>,
         work_log: Arc<RwLock<WorkLog>>,
         event_tx: broadcast::Sender<super::work_log::WorkEvent>,
         mut input_rx: mpsc::Receiver<ClientInput>,
+        approvals: PendingApprovals,
     ) {
         info!(
-            "{} starting work on {:?} via {}",
-            ancillary_id, assignment.task_id, agent
+            "{} starting work on {:?} via {} (permissions: {})",
+            ancillary_id, assignment.task_id, agent, options.permission
         );
 
         // Update status to working
@@ -137,10 +183,10 @@ impl AncillaryWork {
 
         // Build the prompt from the assignment
         let prompt = match &assignment.source {
-            toren_lib::AssignmentSource::Prompt { original_prompt } => original_prompt.clone(),
+            toren_lib::AssignmentSource::Prompt { original_prompt, .. } => original_prompt.clone(),
             toren_lib::AssignmentSource::Reference => {
                 // Fetch task info and render using the act intent template
-                let task_id = assignment.task_id.clone().unwrap_or_default();
+                let task_id = assignment.task_id.as_deref().unwrap_or_default().to_string();
                 let title = assignment
                     .task_title
                     .clone()
@@ -159,6 +205,7 @@ impl AncillaryWork {
                     repo: toren_lib::RepoInfo {
                         root: String::new(),
                         name: assignment.segment.clone(),
+                        recent_commits: Vec::new(),
                     },
                     task: Some(toren_lib::TaskInfo {
                         id: task_id.clone(),
@@ -166,8 +213,11 @@ impl AncillaryWork {
                         description: None,
                         url: assignment.task_url.clone(),
                         source: assignment.task_source.clone(),
+                        deps: Vec::new(),
                     }),
-                    vars: std::collections::HashMap::new(),
+                    variant: None,
+                    assignment: Some((&assignment).into()),
+                    vars: options.vars.clone(),
                 };
                 // TODO: read intent template from config (requires passing config to work loop)
                 let template = toren_lib::config::IntentsConfig::default()
@@ -176,6 +226,10 @@ impl AncillaryWork {
                     .unwrap_or_else(|_| format!("implement {}", task_id))
             }
         };
+        let prompt = match assignment.group_prompt() {
+            Some(workspaces) => format!("{}\n\n{}", prompt, workspaces),
+            None => prompt,
+        };
 
         match agent.kind {
             AgentKind::Claude => {
@@ -185,20 +239,29 @@ impl AncillaryWork {
                     &assignment,
                     &agent,
                     &prompt,
+                    &options,
                     &status,
                     &work_log,
                     &event_tx,
                     &mut input_rx,
+                    &approvals,
                 )
                 .await;
             }
             _ => {
                 // Subprocess path for non-Claude agents
+                if options.permission != PermissionPolicy::Bypass {
+                    warn!(
+                        "{}: permission mode '{}' only applies to Claude; {} runs with its own settings",
+                        ancillary_id, options.permission, agent
+                    );
+                }
                 Self::run_subprocess(
                     &ancillary_id,
                     &assignment,
                     &agent,
                     &prompt,
+                    &options,
                     &status,
                     &work_log,
                     &event_tx,
@@ -216,25 +279,39 @@ impl AncillaryWork {
         assignment: &Assignment,
         agent: &Agent,
         prompt: &str,
+        options: &WorkOptions,
         status: &Arc<RwLock<WorkStatus>>,
         work_log: &Arc<RwLock<WorkLog>>,
         event_tx: &broadcast::Sender<super::work_log::WorkEvent>,
         input_rx: &mut mpsc::Receiver<ClientInput>,
+        approvals: &PendingApprovals,
     ) {
-        let options = if let Some(ref model) = agent.model {
+        let permission = options.permission;
+        let mut options = if let Some(ref model) = agent.model {
             ClaudeAgentOptions::builder()
-                .cwd(assignment.workspace_path.clone())
-                .permission_mode(PermissionMode::BypassPermissions)
+                .cwd(assignment.working_dir())
+                .permission_mode(sdk_permission_mode(permission))
                 .max_turns(50u32)
+                .env(agent_env(assignment, options))
                 .model(model.clone())
                 .build()
         } else {
             ClaudeAgentOptions::builder()
-                .cwd(assignment.workspace_path.clone())
-                .permission_mode(PermissionMode::BypassPermissions)
+                .cwd(assignment.working_dir())
+                .permission_mode(sdk_permission_mode(permission))
                 .max_turns(50u32)
+                .env(agent_env(assignment, options))
                 .build()
         };
+        if permission == PermissionPolicy::Ask {
+            options.can_use_tool = Some(Self::approval_callback(
+                ancillary_id,
+                status,
+                work_log,
+                event_tx,
+                approvals,
+            ));
+        }
 
         // Run the query and stream results
         match query_stream(prompt, Some(options)).await {
@@ -326,6 +403,7 @@ impl AncillaryWork {
         assignment: &Assignment,
         agent: &Agent,
         prompt: &str,
+        options: &WorkOptions,
         status: &Arc<RwLock<WorkStatus>>,
         work_log: &Arc<RwLock<WorkLog>>,
         event_tx: &broadcast::Sender<super::work_log::WorkEvent>,
@@ -333,7 +411,8 @@ impl AncillaryWork {
     ) {
         use tokio::io::{AsyncBufReadExt, BufReader};
 
-        let mut cmd = agent.build_daemon_command(prompt, &assignment.workspace_path, None);
+        let mut cmd = agent.build_daemon_command(prompt, &assignment.working_dir(), None);
+        cmd.envs(agent_env(assignment, options));
         cmd.stdout(std::process::Stdio::piped());
         cmd.stderr(std::process::Stdio::piped());
 
@@ -394,7 +473,7 @@ impl AncillaryWork {
                                     } else {
                                         let err_msg = format!(
                                             "{} exited with {}",
-                                            agent.kind.binary_name(),
+                                            agent.binary(),
                                             exit_status
                                         );
                                         error!("{} {}", ancillary_id, err_msg);
@@ -409,7 +488,7 @@ impl AncillaryWork {
                                     }
                                 }
                                 Err(e) => {
-                                    let err_msg = format!("Failed to wait for {}: {}", agent.kind.binary_name(), e);
+                                    let err_msg = format!("Failed to wait for {}: {}", agent.binary(), e);
                                     error!("{} {}", ancillary_id, err_msg);
                                     Self::log_op(
                                         work_log,
@@ -460,7 +539,7 @@ impl AncillaryWork {
                 }
             }
             Err(e) => {
-                let err_msg = format!("Failed to spawn {}: {}", agent.kind.binary_name(), e);
+                let err_msg = format!("Failed to spawn {}: {}", agent.binary(), e);
                 error!("{} {}", ancillary_id, err_msg);
                 Self::log_op(
                     work_log,
@@ -474,6 +553,86 @@ impl AncillaryWork {
         }
     }
 
+    /// Permission callback for `ask` mode: logs an approval request, marks
+    /// the work as awaiting input and waits for a client to resolve it with
+    /// [`Self::resolve_approval`].
+    fn approval_callback(
+        ancillary_id: &str,
+        status: &Arc<RwLock<WorkStatus>>,
+        work_log: &Arc<RwLock<WorkLog>>,
+        event_tx: &broadcast::Sender<super::work_log::WorkEvent>,
+        approvals: &PendingApprovals,
+    ) -> CanUseToolCallback {
+        let ancillary_id = ancillary_id.to_string();
+        let status = status.clone();
+        let work_log = work_log.clone();
+        let event_tx = event_tx.clone();
+        let approvals = approvals.clone();
+
+        Arc::new(move |tool: String, input: serde_json::Value, _: ToolPermissionContext| {
+            let ancillary_id = ancillary_id.clone();
+            let status = status.clone();
+            let work_log = work_log.clone();
+            let event_tx = event_tx.clone();
+            let approvals = approvals.clone();
+            Box::pin(async move {
+                let request_id = uuid::Uuid::new_v4().to_string();
+                let (tx, rx) = oneshot::channel();
+                approvals.lock().unwrap().insert(request_id.clone(), tx);
+
+                info!("{} awaiting approval {} for {}", ancillary_id, request_id, tool);
+                *status.write().await = WorkStatus::AwaitingInput;
+                Self::log_status(&work_log, &event_tx, "awaiting_input").await;
+                Self::log_op(
+                    &work_log,
+                    &event_tx,
+                    WorkOp::ApprovalRequested {
+                        request_id: request_id.clone(),
+                        tool,
+                        input,
+                    },
+                )
+                .await;
+
+                // The sender is dropped only if the work goes away first
+                let decision = rx.await.unwrap_or_else(|_| ApprovalDecision {
+                    allow: false,
+                    message: Some("Work stopped".to_string()),
+                    client_id: String::new(),
+                });
+                Self::log_op(
+                    &work_log,
+                    &event_tx,
+                    WorkOp::ApprovalResolved {
+                        request_id,
+                        allow: decision.allow,
+                        message: decision.message.clone(),
+                        client_id: decision.client_id.clone(),
+                    },
+                )
+                .await;
+                if approvals.lock().unwrap().is_empty() {
+                    *status.write().await = WorkStatus::Working;
+                    Self::log_status(&work_log, &event_tx, "working").await;
+                }
+
+                if decision.allow {
+                    PermissionResult::Allow(PermissionResultAllow {
+                        updated_input: None,
+                        updated_permissions: None,
+                    })
+                } else {
+                    PermissionResult::Deny(PermissionResultDeny {
+                        message: decision
+                            .message
+                            .unwrap_or_else(|| "Denied by user".to_string()),
+                        interrupt: false,
+                    })
+                }
+            })
+        })
+    }
+
     /// Handle a message from Claude
     async fn handle_message(
         ancillary_id: &str,
@@ -600,6 +759,11 @@ impl AncillaryWork {
         self.input_tx.clone()
     }
 
+    /// Flush the work log to disk
+    pub async fn sync_log(&self) -> Result<()> {
+        self.work_log.write().await.sync()
+    }
+
     /// Read work log events from a given sequence
     pub async fn read_log_from(&self, from_seq: u64) -> Result<Vec<super::work_log::WorkEvent>> {
         let log = self.work_log.read().await;
@@ -614,8 +778,29 @@ impl AncillaryWork {
             .context("Failed to send input to ancillary")
     }
 
-    /// Interrupt the work
+    /// Answer a pending tool approval request.
+    pub fn resolve_approval(&self, request_id: &str, decision: ApprovalDecision) -> Result<()> {
+        let sender = self
+            .approvals
+            .lock()
+            .unwrap()
+            .remove(request_id)
+            .with_context(|| format!("No pending approval request: {}", request_id))?;
+        sender
+            .send(decision)
+            .map_err(|_| anyhow::anyhow!("Approval request {} is no longer waiting", request_id))
+    }
+
+    /// Interrupt the work, denying any pending tool approvals
     pub async fn interrupt(&self) -> Result<()> {
+        let pending: Vec<_> = self.approvals.lock().unwrap().drain().collect();
+        for (_, sender) in pending {
+            let _ = sender.send(ApprovalDecision {
+                allow: false,
+                message: Some("Interrupted by user".to_string()),
+                client_id: String::new(),
+            });
+        }
         self.send_input(ClientInput::Interrupt).await
     }
 }
@@ -627,3 +812,29 @@ impl Drop for AncillaryWork {
         }
     }
 }
+
+/// The SDK permission mode for a policy. `ask` runs in the default mode,
+/// where tools the agent's settings don't allow go through `can_use_tool`.
+fn sdk_permission_mode(permission: PermissionPolicy) -> PermissionMode {
+    match permission {
+        PermissionPolicy::Bypass => PermissionMode::BypassPermissions,
+        PermissionPolicy::AcceptEdits => PermissionMode::AcceptEdits,
+        PermissionPolicy::Ask => PermissionMode::Default,
+        PermissionPolicy::Plan => PermissionMode::Plan,
+    }
+}
+
+/// The agent's environment: variables setup exported to the workspace's
+/// `.toren-env` (toren.kdl's `env { ... }` block), then the session's own.
+fn agent_env(assignment: &Assignment, options: &WorkOptions) -> HashMap<String, String> {
+    let mut env: HashMap<String, String> =
+        match toren_lib::read_workspace_env(&assignment.workspace_path) {
+            Ok(vars) => vars.into_iter().collect(),
+            Err(e) => {
+                warn!("Ignoring {}: {:#}", toren_lib::WORKSPACE_ENV_FILE, e);
+                Default::default()
+            }
+        };
+    env.extend(options.env.clone());
+    env
+}
@@ -44,6 +44,19 @@ pub enum WorkOp {
         output: Value,
         is_error: bool,
     },
+    /// A tool use waiting for a client's approval (`ask` permission mode)
+    ApprovalRequested {
+        request_id: String,
+        tool: String,
+        input: Value,
+    },
+    ApprovalResolved {
+        request_id: String,
+        allow: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        client_id: String,
+    },
 
     // File operations
     FileRead {
@@ -88,6 +101,107 @@ pub enum WorkOp {
     },
 }
 
+/// Filters for searching a work log (`GET /api/ancillaries/:id/log`, and the
+/// replay of `/ws/ancillaries/:id`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogFilter {
+    /// Comma-separated op types (e.g. `tool_call,tool_result`)
+    #[serde(default)]
+    pub op: Option<String>,
+    /// Only events at or after this time
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Case-insensitive text to find in message content, tool results,
+    /// command output and errors
+    #[serde(default)]
+    pub q: Option<String>,
+    /// Only failures: tool errors, non-zero exits and failed assignments
+    #[serde(default)]
+    pub errors: bool,
+}
+
+impl LogFilter {
+    pub fn matches(&self, event: &WorkEvent) -> bool {
+        if self.since.is_some_and(|since| event.timestamp < since) {
+            return false;
+        }
+        if let Some(ref ops) = self.op {
+            let kind = event.op.kind();
+            if !ops.split(',').any(|op| op.trim() == kind) {
+                return false;
+            }
+        }
+        if self.errors && !event.op.is_error() {
+            return false;
+        }
+        if let Some(ref q) = self.q {
+            let q = q.to_lowercase();
+            if !event.op.text().iter().any(|text| text.to_lowercase().contains(&q)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A page of events matching a [`LogFilter`].
+#[derive(Debug, Serialize)]
+pub struct LogPage {
+    pub events: Vec<WorkEvent>,
+    /// `from_seq` for the next page, if there are more matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_seq: Option<u64>,
+}
+
+/// The first `limit` events from `from_seq` on that match `filter`.
+pub fn search(events: Vec<WorkEvent>, filter: &LogFilter, from_seq: u64, limit: usize) -> LogPage {
+    let mut matching = events
+        .into_iter()
+        .filter(|e| e.seq >= from_seq && filter.matches(e));
+    let events: Vec<WorkEvent> = matching.by_ref().take(limit).collect();
+    let next_seq = matching.next().map(|e| e.seq);
+    LogPage { events, next_seq }
+}
+
+impl WorkOp {
+    /// The op's type, as serialized (`tool_result`, `assistant_message`, ...).
+    pub fn kind(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|v| v["type"].as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    fn is_error(&self) -> bool {
+        match self {
+            Self::ToolResult { is_error, .. } => *is_error,
+            Self::CommandExit { code } => *code != 0,
+            Self::AssignmentFailed { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// The op's searchable text.
+    fn text(&self) -> Vec<String> {
+        match self {
+            Self::AssistantMessage { content } | Self::UserMessage { content, .. } => {
+                vec![content.clone()]
+            }
+            Self::ToolCall { name, input, .. } => vec![name.clone(), input.to_string()],
+            Self::ToolResult { output, .. } => vec![match output {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }],
+            Self::CommandStart { command, args } => vec![command.clone(), args.join(" ")],
+            Self::CommandOutput { stdout, stderr } => {
+                stdout.iter().chain(stderr.iter()).cloned().collect()
+            }
+            Self::AssignmentFailed { error } => vec![error.clone()],
+            _ => Vec::new(),
+        }
+    }
+}
+
 /// Persistent work log for an ancillary assignment.
 /// Uses a hybrid memory/disk approach:
 /// - Recent events kept in memory for fast access
@@ -108,18 +222,15 @@ pub struct WorkLog {
 impl WorkLog {
     /// Create or open a work log for the given ancillary and assignment
     pub fn open(ancillary_id: &str, assignment_id: &str) -> Result<Self> {
-        let log_dir = dirs::home_dir()
+        let toren_dir = dirs::home_dir()
             .context("Could not determine home directory")?
-            .join(".toren")
-            .join("ancillaries")
-            .join(ancillary_id.to_lowercase().replace(' ', "-"))
-            .join("work");
+            .join(".toren");
+        let log_path = toren_lib::gc::work_log_path(&toren_dir, ancillary_id, assignment_id);
+        let log_dir = log_path.parent().unwrap_or(&toren_dir);
 
-        std::fs::create_dir_all(&log_dir)
+        std::fs::create_dir_all(log_dir)
             .with_context(|| format!("Failed to create log directory: {}", log_dir.display()))?;
 
-        let log_path = log_dir.join(format!("{}.jsonl", assignment_id));
-
         // Open file for append, create if doesn't exist
         let file = OpenOptions::new()
             .create(true)
@@ -196,6 +307,13 @@ impl WorkLog {
         Ok(event)
     }
 
+    /// Flush buffered events and sync the log file to disk
+    pub fn sync(&mut self) -> Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+        Ok(())
+    }
+
     /// Get the current sequence number (next event will have this seq)
     pub fn current_seq(&self) -> u64 {
         self.next_seq
@@ -231,6 +349,16 @@ impl WorkLog {
         Ok(events)
     }
 
+    /// Every event in the on-disk log of an assignment that may no longer
+    /// be running.
+    pub fn read_all(ancillary_id: &str, assignment_id: &str) -> Result<Vec<WorkEvent>> {
+        let toren_dir = dirs::home_dir()
+            .context("Could not determine home directory")?
+            .join(".toren");
+        let log_path = toren_lib::gc::work_log_path(&toren_dir, ancillary_id, assignment_id);
+        Ok(Self::load_tail(&log_path, usize::MAX)?.into())
+    }
+
     /// Get the path to the log file
     #[allow(dead_code)]
     pub fn path(&self) -> &PathBuf {
@@ -281,4 +409,50 @@ mod tests {
         assert_eq!(events.len(), 2);
         assert_eq!(events[0].seq, 1);
     }
+
+    #[test]
+    fn test_search_filters_and_pages() {
+        let started = Utc::now();
+        let ops = vec![
+            WorkOp::AssistantMessage { content: "Running the tests".to_string() },
+            WorkOp::ToolCall {
+                id: "t1".to_string(),
+                name: "Bash".to_string(),
+                input: serde_json::json!({"command": "cargo test"}),
+            },
+            WorkOp::ToolResult {
+                id: "t1".to_string(),
+                output: Value::String("error[E0308]: mismatched types".to_string()),
+                is_error: true,
+            },
+            WorkOp::ToolResult { id: "t2".to_string(), output: Value::String("ok".to_string()), is_error: false },
+            WorkOp::CommandExit { code: 1 },
+        ];
+        let events: Vec<WorkEvent> = ops
+            .into_iter()
+            .enumerate()
+            .map(|(seq, op)| WorkEvent { seq: seq as u64, timestamp: started, op })
+            .collect();
+        let seqs = |page: LogPage| page.events.iter().map(|e| e.seq).collect::<Vec<_>>();
+
+        let all = LogFilter::default();
+        assert_eq!(seqs(search(events.clone(), &all, 0, 100)), vec![0, 1, 2, 3, 4]);
+
+        let tool_results = LogFilter { op: Some("tool_result".to_string()), ..Default::default() };
+        assert_eq!(seqs(search(events.clone(), &tool_results, 0, 100)), vec![2, 3]);
+
+        let errors = LogFilter { errors: true, ..Default::default() };
+        assert_eq!(seqs(search(events.clone(), &errors, 0, 100)), vec![2, 4]);
+
+        let text = LogFilter { q: Some("MISMATCHED".to_string()), ..Default::default() };
+        assert_eq!(seqs(search(events.clone(), &text, 0, 100)), vec![2]);
+
+        let later = LogFilter { since: Some(started + chrono::Duration::seconds(1)), ..Default::default() };
+        assert!(search(events.clone(), &later, 0, 100).events.is_empty());
+
+        let page = search(events.clone(), &all, 1, 2);
+        assert_eq!(page.next_seq, Some(3));
+        assert_eq!(seqs(page), vec![1, 2]);
+        assert_eq!(search(events, &all, 3, 2).next_seq, None);
+    }
 }
@@ -0,0 +1,162 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Deserialize;
+use toren_lib::Assignment;
+
+use super::AppState;
+use crate::security::AgentScope;
+
+// Agent callback API
+//
+// Tools an agent runs call these with the agent's TOREN_API_TOKEN; the auth
+// layer admits only that token here and attaches its scope, so each handler
+// acts on the token's assignment and nothing else.
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn error(status: StatusCode, message: impl std::fmt::Display) -> ApiError {
+    (status, Json(serde_json::json!({"error": message.to_string()})))
+}
+
+/// The scope's assignment, while it is still active.
+async fn scoped_assignment(state: &AppState, scope: &AgentScope) -> Result<Assignment, ApiError> {
+    let mut assignments = state.assignments.write().await;
+    assignments
+        .get(&scope.assignment_id)
+        .cloned()
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "The token's assignment is no longer active"))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressRequest {
+    message: String,
+}
+
+/// Record a progress report as a note on the assignment.
+pub async fn progress(
+    State(state): State<AppState>,
+    Extension(scope): Extension<AgentScope>,
+    Json(request): Json<ProgressRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if request.message.trim().is_empty() {
+        return Err(error(StatusCode::BAD_REQUEST, "Progress message is empty"));
+    }
+    let mut assignments = state.assignments.write().await;
+    let note = assignments
+        .add_note(&scope.assignment_id, &request.message)
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save note: {:#}", e)))?
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "The token's assignment is no longer active"))?;
+    Ok(Json(serde_json::json!({ "note": note })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PortRequest {
+    /// Lease name, unique within the workspace (e.g. "storybook")
+    name: String,
+    /// Lowest port to hand out
+    #[serde(default = "default_port_start")]
+    start: u16,
+}
+
+fn default_port_start() -> u16 {
+    3000
+}
+
+/// Lease a port to the assignment's workspace. The same name returns the
+/// same port; leases are released when the workspace is destroyed.
+pub async fn port(
+    State(state): State<AppState>,
+    Extension(scope): Extension<AgentScope>,
+    Json(request): Json<PortRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let assignment = scoped_assignment(&state, &scope).await?;
+    // Kept apart from toren.kdl's `(alloc-port)` vars
+    let var = format!("agent:{}", request.name);
+    let port = toren_lib::ports::PortRegistry::new()
+        .allocate(&assignment.workspace_path, &var, request.start)
+        .map_err(|e| error(StatusCode::CONFLICT, format!("{:#}", e)))?;
+    Ok(Json(serde_json::json!({ "port": port, "name": request.name })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RouteRequest {
+    /// What to route to: a port on localhost or `host:port`
+    upstream: String,
+    /// Route name; the host is `<name>.<workspace>.<station domain>`, or
+    /// `<workspace>.<station domain>` without one
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    tls: bool,
+}
+
+/// Register a proxy route for a server the agent started in its workspace.
+/// Routes live under the workspace's host like toren.kdl `proxy` routes, so
+/// they are pruned with the workspace.
+pub async fn route(
+    State(state): State<AppState>,
+    Extension(scope): Extension<AgentScope>,
+    Json(request): Json<RouteRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let assignment = scoped_assignment(&state, &scope).await?;
+    if let Some(ref name) = request.name {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(error(
+                StatusCode::BAD_REQUEST,
+                "Route name must be letters, digits and dashes",
+            ));
+        }
+    }
+    let segment_path = {
+        let segments = state.segments.read().unwrap();
+        segments
+            .find_by_name(&assignment.segment)
+            .map(|s| s.path.clone())
+    }
+    .ok_or_else(|| error(StatusCode::NOT_FOUND, format!("Segment not found: {}", assignment.segment)))?;
+
+    let proxy = &state.config.proxy;
+    let domain = toren_lib::proxy_domains::preferred_domain(
+        &segment_path,
+        &proxy.domain,
+        proxy.host_template.as_deref(),
+    )
+    .and_then(|preferred| {
+        toren_lib::proxy_domains::DomainRegistry::new().claim(&segment_path, &preferred)
+    })
+    .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+
+    let workspace = assignment
+        .workspace_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let name = match request.name {
+        Some(ref n) => format!("{}.{}", n, workspace),
+        None => workspace.to_string(),
+    };
+    let port = if request.tls { 443 } else { 80 };
+    toren_lib::proxy_routes::register(
+        &name,
+        &domain,
+        &request.upstream,
+        port,
+        request.tls,
+        Some(&proxy.backend),
+    )
+    .map_err(|e| error(StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+    {
+        let mut routes = state.agent_routes.lock().unwrap();
+        let route = (name.clone(), domain.clone());
+        if !routes.contains(&route) {
+            routes.push(route);
+        }
+    }
+
+    let host = format!("{}.{}", name, domain);
+    let scheme = if request.tls { "https" } else { "http" };
+    Ok(Json(serde_json::json!({
+        "host": host,
+        "url": format!("{}://{}", scheme, host),
+        "upstream": request.upstream,
+    })))
+}
@@ -3,7 +3,8 @@ use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use tracing::{error, info, warn};
 
-use crate::ancillary::{ClientInput, WorkEvent, WorkStatus};
+use crate::ancillary::work_log::LogFilter;
+use crate::ancillary::{ApprovalDecision, ClientInput, WorkEvent, WorkStatus};
 
 use super::AppState;
 
@@ -14,6 +15,14 @@ enum WsRequest {
     Message { content: String },
     /// Interrupt the current work
     Interrupt,
+    /// Answer an `approval_requested` event (`ask` permission mode)
+    Approval {
+        request_id: String,
+        allow: bool,
+        /// Reason given to the agent when denying
+        #[serde(default)]
+        message: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -38,6 +47,7 @@ pub async fn handle_ancillary_ws(
     state: AppState,
     ancillary_id: String,
     from_seq: Option<u64>,
+    filter: LogFilter,
 ) {
     let (mut sender, mut receiver) = socket.split();
     let client_id = uuid::Uuid::new_v4().to_string();
@@ -79,11 +89,12 @@ pub async fn handle_ancillary_ws(
         let _ = sender.send(Message::Text(json)).await;
     }
 
-    // Replay events from the requested sequence
+    // Replay events from the requested sequence (those matching the
+    // query's filters; live events that follow are unfiltered)
     let from_seq = from_seq.unwrap_or(0);
     match work.read_log_from(from_seq).await {
         Ok(events) => {
-            for event in events {
+            for event in events.into_iter().filter(|e| filter.matches(e)) {
                 let response = WsResponse::Event { event };
                 if let Ok(json) = serde_json::to_string(&response) {
                     if sender.send(Message::Text(json)).await.is_err() {
@@ -114,6 +125,15 @@ pub async fn handle_ancillary_ws(
                 match msg {
                     Some(Ok(Message::Text(text))) => {
                         match serde_json::from_str::<WsRequest>(&text) {
+                            // `[server] read_only`: watch, but don't steer the agent
+                            Ok(_) if state.config.server.read_only => {
+                                let response = WsResponse::Error {
+                                    message: "The daemon is read-only".to_string(),
+                                };
+                                if let Ok(json) = serde_json::to_string(&response) {
+                                    let _ = sender.send(Message::Text(json)).await;
+                                }
+                            }
                             Ok(WsRequest::Message { content }) => {
                                 let _ = input_sender.send(ClientInput::Message {
                                     content,
@@ -124,6 +144,19 @@ pub async fn handle_ancillary_ws(
                                 info!("Client {} requested interrupt", client_id);
                                 let _ = input_sender.send(ClientInput::Interrupt).await;
                             }
+                            Ok(WsRequest::Approval { request_id, allow, message }) => {
+                                let decision = ApprovalDecision {
+                                    allow,
+                                    message,
+                                    client_id: client_id.clone(),
+                                };
+                                if let Err(e) = work.resolve_approval(&request_id, decision) {
+                                    let response = WsResponse::Error { message: e.to_string() };
+                                    if let Ok(json) = serde_json::to_string(&response) {
+                                        let _ = sender.send(Message::Text(json)).await;
+                                    }
+                                }
+                            }
                             Err(e) => {
                                 warn!("Failed to parse client message: {}", e);
                                 let response = WsResponse::Error {
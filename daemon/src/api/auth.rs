@@ -0,0 +1,310 @@
+//! Session authentication for `/api/*` routes.
+//!
+//! Clients exchange the pairing token for a session at `POST /pair`, then
+//! send it as `Authorization: Bearer <session_token>`. Requests from
+//! `trusted_addrs` under [server] are let through without one. `/health`,
+//! `/pair` and the WebSockets (which authenticate with their first message)
+//! are not covered.
+//!
+//! Agents the daemon runs get an assignment-scoped token instead
+//! (`TOREN_API_TOKEN`). It only reaches `/api/agent/*`, whose handlers act on
+//! the token's assignment, and every call made with it is audited. Those
+//! routes take no other credentials.
+//!
+//! A read-only client, paired with `{"read_only": true}` or any client of a
+//! daemon with `[server] read_only`, gets 403 with code `read_only` from
+//! every mutating route: anything but GET, except the POST routes that only
+//! read.
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{header, Method, Response, StatusCode},
+    middleware::{self, Next},
+    response::IntoResponse,
+    Json, Router,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::security::{AgentScope, SecurityContext};
+
+/// Routes agent tokens may call
+const AGENT_PREFIX: &str = "/api/agent/";
+
+/// POST routes that only read, so read-only clients may call them
+const READ_ONLY_POSTS: &[&str] = &[
+    "/api/fs/read",
+    "/api/fs/list",
+    "/api/vcs/status",
+    "/api/vcs/diff",
+    "/api/vcs/diff/stream",
+];
+
+/// Require a session token on `router`'s `/api/*` routes.
+pub fn layer<S>(router: Router<S>, security: Arc<SecurityContext>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(middleware::from_fn_with_state(security, require_session))
+}
+
+async fn require_session(
+    State(security): State<Arc<SecurityContext>>,
+    req: Request,
+    next: Next,
+) -> Response<Body> {
+    if !req.uri().path().starts_with("/api/") {
+        return next.run(req).await;
+    }
+
+    if let Some(scope) = bearer_token(&req).and_then(|token| security.agent_scope(token)) {
+        return agent_request(&security, scope, req, next).await;
+    }
+    if req.uri().path().starts_with(AGENT_PREFIX) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "/api/agent/* takes the assignment-scoped token from the agent's TOREN_API_TOKEN",
+            })),
+        )
+            .into_response();
+    }
+
+    let trusted = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .is_some_and(|ConnectInfo(addr)| security.is_trusted(addr.ip()));
+    let authorized = bearer_token(&req).is_some_and(|token| security.validate_session(token));
+    if !(trusted || authorized) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "error": "Missing or invalid session token. Pair with the daemon's pairing token (`breq pair <token>`), or add this client to trusted_addrs under [server]",
+            })),
+        )
+            .into_response();
+    }
+
+    if is_mutation(&req) && security.is_read_only(bearer_token(&req)) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": format!("{} {} changes state, and this client is read-only", req.method(), req.uri().path()),
+                "code": "read_only",
+            })),
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
+
+/// Run an agent-token request if it stays within `/api/agent/*`, with its
+/// scope as a request extension, and audit it either way.
+async fn agent_request(
+    security: &SecurityContext,
+    scope: AgentScope,
+    mut req: Request,
+    next: Next,
+) -> Response<Body> {
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let response = if path.starts_with(AGENT_PREFIX) {
+        req.extensions_mut().insert(scope.clone());
+        next.run(req).await
+    } else {
+        (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "Agent tokens may only call /api/agent/*"})),
+        )
+            .into_response()
+    };
+    security.audit_agent_call(&scope, &method, &path, response.status().as_u16());
+    response
+}
+
+/// Whether a request may change state, and so is refused to read-only clients.
+fn is_mutation(req: &Request) -> bool {
+    match *req.method() {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => !READ_ONLY_POSTS.contains(&req.uri().path()),
+        _ => true,
+    }
+}
+
+fn bearer_token(req: &Request) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::{get, post};
+    use toren_lib::Config;
+    use tower::ServiceExt;
+
+    fn app(security: Arc<SecurityContext>) -> Router {
+        layer(
+            Router::new()
+                .route("/health", get(|| async { "ok" }))
+                .route("/api/assignments", get(|| async { "[]" })),
+            security,
+        )
+    }
+
+    async fn status(app: Router, path: &str, token: Option<&str>) -> StatusCode {
+        let mut req = Request::builder().uri(path);
+        if let Some(token) = token {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        app.oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_api_routes_require_session() {
+        let security = Arc::new(SecurityContext::new(&Config::default()).unwrap());
+        let session = security.create_session(false).unwrap();
+        let app = app(security);
+
+        assert_eq!(status(app.clone(), "/health", None).await, StatusCode::OK);
+        assert_eq!(
+            status(app.clone(), "/api/assignments", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(app.clone(), "/api/assignments", Some("wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(app, "/api/assignments", Some(&session.token)).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trusted_addrs_skip_session() {
+        let mut config = Config::default();
+        config.server.trusted_addrs = vec!["127.0.0.1".to_string()];
+        let app = app(Arc::new(SecurityContext::new(&config).unwrap()));
+
+        let from = |ip: &str| {
+            let mut req = Request::builder()
+                .uri("/api/assignments")
+                .body(Body::empty())
+                .unwrap();
+            let addr: SocketAddr = format!("{}:50000", ip).parse().unwrap();
+            req.extensions_mut().insert(ConnectInfo(addr));
+            req
+        };
+        let local = app.clone().oneshot(from("127.0.0.1")).await.unwrap();
+        assert_eq!(local.status(), StatusCode::OK);
+        let remote = app.oneshot(from("10.0.0.5")).await.unwrap();
+        assert_eq!(remote.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_sessions_cannot_mutate() {
+        let security = Arc::new(SecurityContext::new(&Config::default()).unwrap());
+        let owner = security.create_session(false).unwrap();
+        let viewer = security.create_session(true).unwrap();
+        let app = layer(
+            Router::new()
+                .route("/api/assignments", get(|| async { "[]" }).post(|| async { "{}" }))
+                .route("/api/assignments/:id/abort", post(|| async { "{}" }))
+                .route("/api/fs/read", post(|| async { "{}" })),
+            security,
+        );
+        let call = |method: &str, path: &str, token: &str| {
+            let req = Request::builder()
+                .method(method)
+                .uri(path)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            let app = app.clone();
+            async move { app.oneshot(req).await.unwrap().status() }
+        };
+
+        assert_eq!(call("GET", "/api/assignments", &viewer.token).await, StatusCode::OK);
+        assert_eq!(call("POST", "/api/fs/read", &viewer.token).await, StatusCode::OK);
+        assert_eq!(
+            call("POST", "/api/assignments", &viewer.token).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call("POST", "/api/assignments/a1/abort", &viewer.token).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call("POST", "/api/assignments/a1/abort", &owner.token).await,
+            StatusCode::OK
+        );
+
+        let mut config = Config::default();
+        config.server.read_only = true;
+        let security = Arc::new(SecurityContext::new(&config).unwrap());
+        let owner = security.create_session(false).unwrap();
+        let app = layer(
+            Router::new().route("/api/assignments", post(|| async { "{}" })),
+            security,
+        );
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/assignments")
+            .header(header::AUTHORIZATION, format!("Bearer {}", owner.token))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(app.oneshot(req).await.unwrap().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_agent_tokens_only_reach_agent_routes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let audit = tmp.path().join("audit.jsonl");
+        let security = Arc::new(
+            SecurityContext::new(&Config::default())
+                .unwrap()
+                .with_audit_file(audit.clone()),
+        );
+        let session = security.create_session(false).unwrap();
+        let token = security.issue_agent_token("a1").unwrap();
+        let app = layer(
+            Router::new()
+                .route("/api/assignments", get(|| async { "[]" }))
+                .route(
+                    "/api/agent/progress",
+                    get(|axum::Extension(scope): axum::Extension<AgentScope>| async move {
+                        scope.assignment_id
+                    }),
+                ),
+            security,
+        );
+
+        assert_eq!(
+            status(app.clone(), "/api/agent/progress", Some(&token)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(app.clone(), "/api/assignments", Some(&token)).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(app, "/api/agent/progress", Some(&session.token)).await,
+            StatusCode::FORBIDDEN
+        );
+
+        let log = std::fs::read_to_string(&audit).unwrap();
+        let entries: Vec<serde_json::Value> =
+            log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["assignment_id"], "a1");
+        assert_eq!(entries[1]["status"], 403);
+    }
+}
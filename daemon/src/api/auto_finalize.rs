@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use toren_lib::session::SessionLogs;
+
+use super::AppState;
+use crate::events::DaemonEvent;
+
+// Auto-finalization of intents with `[intents.<name>.auto]`
+
+/// How often running assignments are checked against their timeout.
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Finalize assignments whose intent has `[intents.<name>.auto]` when the
+/// daemon-run agent's work completes, or once it has been working for
+/// `timeout_minutes`: post the final report to the task, abort the
+/// assignment and publish an `assignment_auto_finalized` event.
+pub fn spawn_auto_finalizer(state: AppState) {
+    if state.config.intents.auto.is_empty() {
+        return;
+    }
+    let mut rx = state.events.subscribe();
+    tokio::spawn(async move {
+        // When each assignment's agent started working
+        let mut started: HashMap<String, Instant> = HashMap::new();
+        let mut tick = tokio::time::interval(TIMEOUT_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Ok(DaemonEvent::WorkStatusChanged { assignment_id, status, .. }) => {
+                        match status.as_str() {
+                            "working" => {
+                                started.entry(assignment_id).or_insert_with(Instant::now);
+                            }
+                            "completed" => {
+                                started.remove(&assignment_id);
+                                finalize(&state, &assignment_id, "completed").await;
+                            }
+                            _ => {}
+                        }
+                    }
+                    Ok(DaemonEvent::AssignmentCompleted { assignment_id, .. })
+                    | Ok(DaemonEvent::AssignmentAborted { assignment_id, .. }) => {
+                        started.remove(&assignment_id);
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = tick.tick() => {
+                    for assignment_id in timed_out(&state, &mut started).await {
+                        finalize(&state, &assignment_id, "timeout").await;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Assignments that have been working past their intent's timeout. Entries
+/// for assignments that are gone are dropped.
+async fn timed_out(state: &AppState, started: &mut HashMap<String, Instant>) -> Vec<String> {
+    let mut assignments = state.assignments.write().await;
+    let mut due = Vec::new();
+    started.retain(|assignment_id, since| {
+        let Some(assignment) = assignments.get(assignment_id) else {
+            return false;
+        };
+        let timeout = state
+            .config
+            .intents
+            .auto_for(assignment.intent.as_deref())
+            .and_then(|auto| auto.timeout_minutes);
+        match timeout {
+            Some(minutes) if since.elapsed() >= Duration::from_secs(minutes * 60) => {
+                due.push(assignment_id.clone());
+                false
+            }
+            _ => true,
+        }
+    });
+    due
+}
+
+async fn finalize(state: &AppState, assignment_id: &str, reason: &str) {
+    let Some(ws_mgr) = state.workspaces.as_ref() else {
+        return;
+    };
+    let mut assignments = state.assignments.write().await;
+    let Some(assignment) = assignments.get(assignment_id).cloned() else {
+        return;
+    };
+    let Some(auto) = state.config.intents.auto_for(assignment.intent.as_deref()) else {
+        return;
+    };
+    if reason == "completed" && !auto.on_complete {
+        return;
+    }
+
+    let _ = state.work_manager.stop_work(&assignment.ancillary_id).await;
+
+    let segment_path = {
+        let segments = state.segments.read().unwrap();
+        segments
+            .find_by_name(&assignment.segment)
+            .map(|s| s.path.clone())
+    };
+    let Some(segment_path) = segment_path else {
+        tracing::warn!(
+            "Can't auto-finalize {}: segment not found: {}",
+            assignment.ancillary_id,
+            assignment.segment
+        );
+        return;
+    };
+
+    let opts = toren_lib::AutoFinalizeOptions {
+        close_task: auto.close_task,
+        segment_path: &segment_path,
+        plugin_mgr: &state.rhai_plugins,
+        session_logs: &SessionLogs::from_config(&state.config),
+    };
+    match toren_lib::auto_finalize_assignment(&assignment, &mut assignments, ws_mgr, &opts) {
+        Ok(result) => {
+            tracing::info!(
+                "Auto-finalized {} ({}), report {}",
+                assignment.ancillary_id,
+                reason,
+                if result.report_posted { "posted" } else { "not posted" }
+            );
+            state.events.publish(DaemonEvent::AssignmentAutoFinalized {
+                assignment_id: assignment.id.clone(),
+                ancillary_id: assignment.ancillary_id.clone(),
+                reason: reason.to_string(),
+                report_posted: result.report_posted,
+            });
+        }
+        Err(e) => tracing::warn!("Failed to auto-finalize {}: {:#}", assignment.ancillary_id, e),
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+use tokio::sync::broadcast;
+use toren_lib::Assignment;
+
+use super::{work_options, AppState};
+use crate::events::DaemonEvent;
+
+// Assignment dependencies (`after`)
+
+/// How often waiting assignments are checked against the completion history.
+/// Completions in this daemon are picked up right away; this catches the
+/// ones `breq` records on its own.
+const DEPENDENCY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Start assignments recorded with `after` once a completion record for the
+/// task they wait for lands, publishing an `assignment_unblocked` event.
+pub fn spawn_dependency_starter(state: AppState) {
+    let mut rx = state.events.subscribe();
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(DEPENDENCY_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Ok(DaemonEvent::AssignmentCompleted { .. }) => start_unblocked(&state).await,
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = tick.tick() => start_unblocked(&state).await,
+            }
+        }
+    });
+}
+
+async fn start_unblocked(state: &AppState) {
+    let ready: Vec<Assignment> = {
+        let mut assignments = state.assignments.write().await;
+        let waiting: Vec<Assignment> = assignments
+            .list_active()
+            .into_iter()
+            .filter(|a| a.after.is_some())
+            .cloned()
+            .collect();
+        if waiting.is_empty() {
+            return;
+        }
+        let completed = match assignments.completed_task_ids() {
+            Ok(completed) => completed,
+            Err(e) => {
+                tracing::warn!("Failed to read completion history: {:#}", e);
+                return;
+            }
+        };
+        let ready: Vec<Assignment> = waiting
+            .into_iter()
+            .filter(|a| a.blocked_on(&completed).is_none())
+            .collect();
+        // Cleared before starting, so a failed start isn't retried every pass
+        for assignment in &ready {
+            if let Err(e) = assignments.set_after(&assignment.id, None) {
+                tracing::warn!("Failed to clear the dependency of {}: {:#}", assignment.ancillary_id, e);
+            }
+        }
+        ready
+    };
+
+    for mut assignment in ready {
+        let Some(after) = assignment.after.take() else {
+            continue;
+        };
+        let options = work_options(state, &assignment, state.config.ancillaries.permission_mode);
+        let started = state
+            .work_manager
+            .start_work(
+                assignment.ancillary_id.clone(),
+                assignment.clone(),
+                &state.agent,
+                options,
+                false,
+            )
+            .await;
+        match &started {
+            Ok(_) => tracing::info!("{} completed; started {}", after, assignment.ancillary_id),
+            Err(e) => tracing::warn!(
+                "{} completed but {} failed to start: {}",
+                after,
+                assignment.ancillary_id,
+                e
+            ),
+        }
+        state.events.publish(DaemonEvent::AssignmentUnblocked {
+            assignment_id: assignment.id.clone(),
+            ancillary_id: assignment.ancillary_id.clone(),
+            after,
+            work_started: started.is_ok(),
+        });
+    }
+}
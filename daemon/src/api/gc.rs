@@ -0,0 +1,84 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+use std::time::Duration;
+use toren_lib::gc::{GcAction, GcPlan};
+
+use super::AppState;
+
+// Stale segment collection
+
+/// Periodically look for history and files of segments that no longer
+/// exist, and log them. Nothing is removed until a client asks
+/// (`POST /api/gc` or `breq gc`).
+pub fn spawn_gc_monitor(state: AppState) {
+    let interval = state.config.ancillaries.gc_interval_secs;
+    if interval == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            match plan(&state).await {
+                Ok(plan) if !plan.is_empty() => {
+                    let names: Vec<&str> = plan.segments.iter().map(|s| s.name.as_str()).collect();
+                    tracing::warn!(
+                        "{} history record(s) and {} path(s) belong to segments that no longer exist ({}); run `breq gc` to archive or delete them",
+                        plan.record_count(),
+                        plan.path_count(),
+                        names.join(", ")
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Stale segment scan failed: {:#}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+async fn plan(state: &AppState) -> anyhow::Result<GcPlan> {
+    let live: Vec<String> = state
+        .segments
+        .read()
+        .unwrap()
+        .list_all()
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    let mut assignments = state.assignments.write().await;
+    toren_lib::gc::plan(&mut assignments, &toren_lib::toren_root(), &live)
+}
+
+fn internal_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({"error": format!("{:#}", e)})),
+    )
+}
+
+pub async fn gc_report(
+    State(state): State<AppState>,
+) -> Result<Json<GcPlan>, (StatusCode, Json<serde_json::Value>)> {
+    plan(&state).await.map(Json).map_err(internal_error)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GcRequest {
+    pub action: GcAction,
+}
+
+pub async fn gc_apply(
+    State(state): State<AppState>,
+    Json(request): Json<GcRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let plan = plan(&state).await.map_err(internal_error)?;
+    let assignments = state.assignments.read().await;
+    let outcome = toren_lib::gc::apply(&assignments, &toren_lib::toren_root(), &plan, request.action)
+        .map_err(internal_error)?;
+    tracing::info!(
+        "Collected {} history record(s) and {} path(s) of {} stale segment(s)",
+        outcome.records,
+        outcome.paths,
+        plan.segments.len()
+    );
+    Ok(Json(serde_json::json!({ "plan": plan, "outcome": outcome })))
+}
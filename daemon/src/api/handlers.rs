@@ -1,14 +1,35 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::path::PathBuf;
 
 use super::AppState;
-use crate::plugins::CommandSet;
-use crate::services::command::CommandRequest;
+use crate::plugins::{CommandError, CommandSet};
+use crate::services::command::CommandOutput;
+use crate::services::filesystem::FsError;
+use crate::services::vcs::DiffFilter;
 
 // Filesystem handlers
 
+type FsHandlerError = (StatusCode, Json<serde_json::Value>);
+
+fn fs_error(e: FsError) -> FsHandlerError {
+    let status = match e {
+        FsError::NotApproved(_) | FsError::OutsideWorkspace(_) => StatusCode::FORBIDDEN,
+        FsError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        FsError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        FsError::Io(_) => StatusCode::BAD_REQUEST,
+    };
+    (status, Json(serde_json::json!({"error": e.to_string()})))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FsReadRequest {
     pub path: String,
@@ -22,14 +43,14 @@ pub struct FsReadResponse {
 pub async fn fs_read(
     State(state): State<AppState>,
     Json(request): Json<FsReadRequest>,
-) -> Result<Json<FsReadResponse>, StatusCode> {
+) -> Result<Json<FsReadResponse>, FsHandlerError> {
     let path = PathBuf::from(&request.path);
 
     let content = state
         .services
         .filesystem
         .read_file(&path)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+        .map_err(fs_error)?;
 
     Ok(Json(FsReadResponse { content }))
 }
@@ -48,14 +69,14 @@ pub struct FsWriteResponse {
 pub async fn fs_write(
     State(state): State<AppState>,
     Json(request): Json<FsWriteRequest>,
-) -> Result<Json<FsWriteResponse>, StatusCode> {
+) -> Result<Json<FsWriteResponse>, FsHandlerError> {
     let path = PathBuf::from(&request.path);
 
     state
         .services
         .filesystem
         .write_file(&path, &request.content)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+        .map_err(fs_error)?;
 
     Ok(Json(FsWriteResponse { success: true }))
 }
@@ -68,14 +89,14 @@ pub struct FsListRequest {
 pub async fn fs_list(
     State(state): State<AppState>,
     Json(request): Json<FsListRequest>,
-) -> Result<Json<crate::services::filesystem::DirListing>, StatusCode> {
+) -> Result<Json<crate::services::filesystem::DirListing>, FsHandlerError> {
     let path = PathBuf::from(&request.path);
 
     let listing = state
         .services
         .filesystem
         .list_directory(&path)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+        .map_err(fs_error)?;
 
     Ok(Json(listing))
 }
@@ -105,11 +126,16 @@ pub async fn vcs_status(
 #[derive(Debug, Deserialize)]
 pub struct VcsDiffRequest {
     pub path: String,
+    #[serde(flatten)]
+    pub filter: DiffFilter,
 }
 
 #[derive(Debug, Serialize)]
 pub struct VcsDiffResponse {
     pub diff: String,
+    /// Files `max_lines_per_file` cut short
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<String>,
 }
 
 pub async fn vcs_diff(
@@ -118,13 +144,50 @@ pub async fn vcs_diff(
 ) -> Result<Json<VcsDiffResponse>, StatusCode> {
     let path = PathBuf::from(&request.path);
 
-    let diff = state
+    let (diff, truncated) = state
         .services
         .vcs
-        .diff(&path)
+        .diff(&path, &request.filter)
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    Ok(Json(VcsDiffResponse { diff }))
+    Ok(Json(VcsDiffResponse { diff, truncated }))
+}
+
+/// Stream the diff as newline-delimited JSON: one [`FileDiff`] per line as
+/// the VCS prints it, so a client can render a large diff without waiting
+/// for, or buffering, all of it. A failure partway ends the stream with an
+/// `{"error": ...}` line.
+///
+/// [`FileDiff`]: crate::services::vcs::FileDiff
+pub async fn vcs_diff_stream(
+    State(state): State<AppState>,
+    Json(request): Json<VcsDiffRequest>,
+) -> Result<Response, StatusCode> {
+    let path = PathBuf::from(&request.path);
+    let vcs = state.services.vcs.clone();
+    // Refuse unapproved paths before the response starts
+    vcs.detect_vcs(&path).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<String>(16);
+    tokio::task::spawn_blocking(move || {
+        let result = vcs.stream_diff(&path, &request.filter, |file| {
+            let line = serde_json::json!(file).to_string() + "\n";
+            tx.blocking_send(line).is_ok()
+        });
+        if let Err(e) = result {
+            let line = serde_json::json!({"error": format!("{:#}", e)}).to_string() + "\n";
+            let _ = tx.blocking_send(line);
+        }
+    });
+    let lines = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|line| (Ok::<_, Infallible>(line), rx))
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
 }
 
 // Plugin handlers
@@ -137,71 +200,107 @@ pub struct PluginsListResponse {
 pub async fn plugins_list(
     State(state): State<AppState>,
 ) -> Result<Json<PluginsListResponse>, StatusCode> {
-    let command_sets: Vec<CommandSet> = state
-        .plugins
-        .list_command_sets()
-        .into_iter()
-        .cloned()
-        .collect();
+    let command_sets: Vec<CommandSet> = state.plugins.list_command_sets();
 
     Ok(Json(PluginsListResponse { command_sets }))
 }
 
+#[derive(Debug, Serialize)]
+pub struct PluginsReloadResponse {
+    /// Number of command sets loaded
+    pub command_sets: usize,
+}
+
+/// Reload command sets from the plugin directories now, without waiting for
+/// the file watcher.
+pub async fn plugins_reload(
+    State(state): State<AppState>,
+) -> Result<Json<PluginsReloadResponse>, FsHandlerError> {
+    let plugins = state.plugins.clone();
+    let command_sets = tokio::task::spawn_blocking(move || plugins.reload())
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r.map_err(|e| format!("{:#}", e)))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e}))))?;
+    Ok(Json(PluginsReloadResponse { command_sets }))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PluginExecuteRequest {
     pub command_id: String,
+    #[serde(default)]
     pub params: HashMap<String, String>,
     pub cwd: Option<String>,
+    /// The user confirmed a command that isn't `auto_approve`
+    #[serde(default)]
+    pub confirmed: bool,
 }
 
 #[derive(Debug, Serialize)]
 pub struct PluginExecuteResponse {
     pub success: bool,
     pub message: String,
+    pub exit_code: Option<i32>,
+    pub output: Vec<CommandOutput>,
+}
+
+fn plugin_error(e: CommandError) -> FsHandlerError {
+    let status = match e {
+        CommandError::NotFound(_) => StatusCode::NOT_FOUND,
+        CommandError::InvalidParams(_) => StatusCode::BAD_REQUEST,
+    };
+    (status, Json(serde_json::json!({"error": e.to_string()})))
 }
 
+/// Run a plugin command to completion and return its output. Commands that
+/// aren't `auto_approve` return 409 with `"code": "confirmation_required"`
+/// and the command line until re-sent with `confirmed`. The WebSocket
+/// `PluginExecute` request streams the output instead.
 pub async fn plugins_execute(
     State(state): State<AppState>,
     Json(request): Json<PluginExecuteRequest>,
-) -> Result<Json<PluginExecuteResponse>, StatusCode> {
-    // Find the command
-    let (command_set, command_def) = state
-        .plugins
-        .find_command(&request.command_id)
-        .ok_or(StatusCode::NOT_FOUND)?;
-
-    // Interpolate parameters
-    let interpolated_command = state
+) -> Result<Json<PluginExecuteResponse>, FsHandlerError> {
+    let prepared = state
         .plugins
-        .interpolate_command(&command_def.command, &request.params);
-
-    // Split command into program and args
-    let parts: Vec<&str> = interpolated_command.split_whitespace().collect();
-    if parts.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+        .prepare(&request.command_id, &request.params, request.cwd)
+        .map_err(plugin_error)?;
+
+    if !prepared.auto_approve && !request.confirmed {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": format!("'{}' must be confirmed before it runs", prepared.label),
+                "code": "confirmation_required",
+                "command": prepared.display,
+            })),
+        ));
     }
 
-    let program = parts[0].to_string();
-    let args = parts[1..].iter().map(|s| s.to_string()).collect();
-
-    // Execute command
-    let cmd_request = CommandRequest {
-        command: program,
-        args,
-        cwd: request.cwd,
-    };
-
-    // For now, just validate we can create the command
-    // In a real implementation, this would use the command service
-    // and return streaming output via WebSocket
-    let _ = state.services.command.execute(cmd_request).await;
+    let mut rx = state
+        .services
+        .command
+        .execute(prepared.request)
+        .await
+        .map_err(|e| (StatusCode::FORBIDDEN, Json(serde_json::json!({"error": e.to_string()}))))?;
+
+    let mut output = Vec::new();
+    let mut exit_code = None;
+    while let Some(line) = rx.recv().await {
+        if let CommandOutput::Exit { code } = line {
+            exit_code = Some(code);
+        }
+        output.push(line);
+    }
 
     Ok(Json(PluginExecuteResponse {
-        success: true,
+        success: exit_code == Some(0),
         message: format!(
-            "Command '{}' from set '{}' queued for execution",
-            command_def.label, command_set.name
+            "Command '{}' from set '{}' exited with {}",
+            prepared.label,
+            prepared.set_name,
+            exit_code.map_or_else(|| "an error".to_string(), |c| format!("code {}", c))
         ),
+        exit_code,
+        output,
     }))
 }
-
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use toren_lib::session::SessionLogs;
+use toren_lib::AssignmentHealth;
+
+use super::AppState;
+use crate::events::DaemonEvent;
+
+// Health monitor
+
+/// Latest recorded health of an assignment.
+pub fn recorded(state: &AppState, assignment_id: &str) -> Option<AssignmentHealth> {
+    state.health.read().unwrap().get(assignment_id).cloned()
+}
+
+/// Periodically check every active assignment, record the result, and
+/// broadcast state transitions.
+pub fn spawn_health_monitor(state: AppState) {
+    let interval = state.config.ancillaries.health_interval_secs;
+    if interval == 0 {
+        return;
+    }
+    let stall_after = Duration::from_secs(state.config.ancillaries.stall_minutes * 60);
+    tokio::spawn(async move {
+        loop {
+            check_all(&state, stall_after).await;
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+async fn check_all(state: &AppState, stall_after: Duration) {
+    let active: Vec<_> = state
+        .assignments
+        .write()
+        .await
+        .list_active()
+        .into_iter()
+        .cloned()
+        .collect();
+
+    let logs = SessionLogs::from_config(&state.config);
+    let mut checks = HashMap::with_capacity(active.len());
+    for assignment in &active {
+        let running = state
+            .work_manager
+            .has_active_work(&assignment.ancillary_id)
+            .await;
+        let health = toren_lib::health::check_health(&logs, assignment, running, stall_after);
+        checks.insert(assignment.id.clone(), health);
+    }
+
+    let mut recorded = state.health.write().unwrap();
+    for assignment in &active {
+        let Some(mut health) = checks.remove(&assignment.id) else {
+            continue;
+        };
+        match recorded.get(&assignment.id) {
+            Some(previous) if previous.state == health.state => health.since = previous.since,
+            previous => {
+                if health.state.is_warning() {
+                    tracing::warn!(
+                        "{} is {}: {}",
+                        assignment.ancillary_id,
+                        health.state.as_str(),
+                        health.detail.as_deref().unwrap_or("")
+                    );
+                }
+                // Don't announce the first check of an assignment that's fine
+                if previous.is_some() || health.state.is_warning() {
+                    state.events.publish(DaemonEvent::AssignmentHealthChanged {
+                        assignment_id: assignment.id.clone(),
+                        ancillary_id: assignment.ancillary_id.clone(),
+                        health: health.clone(),
+                    });
+                }
+            }
+        }
+        recorded.insert(assignment.id.clone(), health);
+    }
+    // Forget assignments that completed or were aborted
+    recorded.retain(|id, _| active.iter().any(|a| &a.id == id));
+}
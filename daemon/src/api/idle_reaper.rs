@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime};
+use toren_lib::composite_status::{last_session_event, workspace_has_changes};
+use toren_lib::reaper::{idle_for, IdleAction};
+use toren_lib::session::SessionLogs;
+use toren_lib::Assignment;
+
+use super::AppState;
+use crate::events::DaemonEvent;
+
+// Idle assignment reaper (`[ancillaries.idle_reaper]`)
+
+/// How often active assignments are checked for idleness.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Periodically look for assignments whose workspace has no changes and
+/// whose agent hasn't been active for `idle_days`, and flag or abort them,
+/// publishing an `assignment_idle` event. Aborting journals the workspace's
+/// revision (see [`toren_lib::undo`]) and records it in the history, so the
+/// workspace can be recovered.
+pub fn spawn_idle_reaper(state: AppState) {
+    let reaper = &state.config.ancillaries.idle_reaper;
+    if reaper.idle_days == 0 {
+        return;
+    }
+    let idle_after = Duration::from_secs(reaper.idle_days * 24 * 3600);
+    tokio::spawn(async move {
+        // Assignments already flagged, so the event isn't repeated every pass
+        let mut flagged: HashSet<String> = HashSet::new();
+        loop {
+            reap_idle(&state, idle_after, &mut flagged).await;
+            tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn reap_idle(state: &AppState, idle_after: Duration, flagged: &mut HashSet<String>) {
+    let active: Vec<Assignment> = state
+        .assignments
+        .write()
+        .await
+        .list_active()
+        .into_iter()
+        // Waiting on another task is expected to be quiet
+        .filter(|a| a.after.is_none())
+        .cloned()
+        .collect();
+    flagged.retain(|id| active.iter().any(|a| &a.id == id));
+
+    let reaper = &state.config.ancillaries.idle_reaper;
+    let logs = SessionLogs::from_config(&state.config);
+    for assignment in active {
+        let running = state
+            .work_manager
+            .has_active_work(&assignment.ancillary_id)
+            .await;
+        let logs = logs.clone();
+        let candidate = assignment.clone();
+        let idle = tokio::task::spawn_blocking(move || {
+            let last_update = chrono::DateTime::parse_from_rfc3339(&candidate.updated_at)
+                .map(SystemTime::from)
+                .unwrap_or_else(|_| SystemTime::now());
+            let event = last_session_event(&logs, &candidate.working_dir());
+            let has_changes = workspace_has_changes(
+                &candidate.workspace_path,
+                candidate.base_branch.as_deref(),
+                None,
+            );
+            idle_for(last_update, event, running, has_changes, idle_after, SystemTime::now())
+        })
+        .await
+        .ok()
+        .flatten();
+
+        let Some(idle) = idle else {
+            flagged.remove(&assignment.id);
+            continue;
+        };
+        let aborting = reaper.action == IdleAction::Abort && !reaper.dry_run;
+        if !aborting && !flagged.insert(assignment.id.clone()) {
+            continue;
+        }
+        let idle_days = idle.as_secs() / (24 * 3600);
+
+        let revision = if aborting {
+            match abort_idle(state, &assignment).await {
+                Ok(revision) => {
+                    tracing::info!("Aborted {}: idle for {} days", assignment.ancillary_id, idle_days);
+                    revision
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to abort idle {}: {:#}", assignment.ancillary_id, e);
+                    continue;
+                }
+            }
+        } else {
+            tracing::warn!(
+                "{} has been idle for {} days{}",
+                assignment.ancillary_id,
+                idle_days,
+                if reaper.action == IdleAction::Abort { " (dry run: would abort)" } else { "" }
+            );
+            None
+        };
+        state.events.publish(DaemonEvent::AssignmentIdle {
+            assignment_id: assignment.id.clone(),
+            ancillary_id: assignment.ancillary_id.clone(),
+            idle_days,
+            action: reaper.action,
+            dry_run: reaper.dry_run,
+            revision,
+        });
+    }
+}
+
+/// Abort an idle assignment, returning the revision its workspace was at.
+async fn abort_idle(state: &AppState, assignment: &Assignment) -> anyhow::Result<Option<String>> {
+    let ws_mgr = state
+        .workspaces
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("workspace_root not configured"))?;
+    let segment_path = state
+        .segments
+        .read()
+        .unwrap()
+        .find_by_name(&assignment.segment)
+        .map(|s| s.path.clone())
+        .ok_or_else(|| anyhow::anyhow!("Segment not found: {}", assignment.segment))?;
+
+    let mut assignments = state.assignments.write().await;
+    if assignments.get(&assignment.id).map(|a| &a.updated_at) != Some(&assignment.updated_at) {
+        anyhow::bail!("it was resumed or removed since the check");
+    }
+    let revision = assignment
+        .workspace_path
+        .exists()
+        .then(|| ws_mgr.capture_revision(&segment_path, &assignment.workspace_path))
+        .flatten();
+    let opts = toren_lib::AbortOptions {
+        close_task: false,
+        delete_task: false,
+        segment_path: &segment_path,
+        kill: true,
+        plugin_mgr: &state.rhai_plugins,
+    };
+    toren_lib::abort_assignment(assignment, &mut assignments, ws_mgr, &opts)?;
+    Ok(revision)
+}
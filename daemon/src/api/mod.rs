@@ -6,23 +6,42 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 
-use crate::ancillary::{AncillaryManager, WorkManager};
+use crate::ancillary::work_log::{self, LogFilter, LogPage, WorkLog};
+use crate::ancillary::{AncillaryManager, WorkManager, WorkOptions};
+use crate::events::{DaemonEvent, EventBus};
 use crate::plugins::PluginManager;
 use crate::security::SecurityContext;
 use crate::services::Services;
 use tokio::sync::RwLock;
+use toren_lib::session::SessionLogs;
 use toren_lib::{
-    Agent, Assignment, AssignmentManager, CompositeStatus, Config, SegmentManager,
-    WorkspaceManager,
+    Agent, Assignment, AssignmentHealth, AssignmentManager, CompositeStatus, Config, DiffOptions,
+    ScheduleManager, SegmentManager, WorkspaceManager,
 };
 
+mod agent;
 mod ancillary_ws;
+mod auth;
+mod auto_finalize;
+mod dependencies;
+mod gc;
 mod handlers;
+mod health;
+mod idle_reaper;
+mod notifications;
+mod reaper;
+mod request_id;
+mod schedules;
+mod shutdown;
+mod status_cache;
+mod usage;
 mod ws_handler;
 
 #[derive(Clone)]
@@ -34,10 +53,18 @@ pub struct AppState {
     pub rhai_plugins: Arc<toren_lib::PluginManager>,
     pub ancillaries: Arc<AncillaryManager>,
     pub assignments: Arc<RwLock<AssignmentManager>>,
+    pub schedules: Arc<RwLock<ScheduleManager>>,
+    /// Latest health check per assignment ID, kept by the health monitor
+    pub health: Arc<std::sync::RwLock<HashMap<String, AssignmentHealth>>>,
+    pub status_cache: Arc<status_cache::StatusCache>,
     pub segments: Arc<std::sync::RwLock<SegmentManager>>,
     pub workspaces: Option<Arc<WorkspaceManager>>,
     pub work_manager: Arc<WorkManager>,
     pub agent: Arc<Agent>,
+    pub events: EventBus,
+    /// Proxy routes agents registered, as `(name, domain)`, for removal on
+    /// shutdown (`server.forget_agent_routes_on_shutdown`)
+    pub agent_routes: Arc<std::sync::Mutex<Vec<(String, String)>>>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -50,6 +77,7 @@ pub async fn serve(
     rhai_plugins: toren_lib::PluginManager,
     ancillary_manager: AncillaryManager,
     assignment_manager: AssignmentManager,
+    schedule_manager: ScheduleManager,
     segment_manager: SegmentManager,
     workspace_manager: Option<WorkspaceManager>,
     mut work_manager: WorkManager,
@@ -57,23 +85,74 @@ pub async fn serve(
 ) -> Result<()> {
     let assignments = Arc::new(RwLock::new(assignment_manager));
 
+    let events = EventBus::new();
+    toren_lib::hooks::register_observer(Arc::new(events.clone()));
+    let security = Arc::new(security_ctx);
+
     // Give work manager a reference to assignments for status persistence
     work_manager.set_assignments(assignments.clone());
+    work_manager.set_events(events.clone());
+    work_manager.set_api_access(security.clone(), api_url(addr));
+
+    let status_cache = status_cache::StatusCache::new(std::time::Duration::from_secs(
+        config.status.min_interval_secs,
+    ));
 
     let state = AppState {
         config: Arc::new(config),
         services,
-        security: Arc::new(security_ctx),
+        security,
         plugins: Arc::new(plugin_manager),
         rhai_plugins: Arc::new(rhai_plugins),
         ancillaries: Arc::new(ancillary_manager),
         assignments,
+        schedules: Arc::new(RwLock::new(schedule_manager)),
+        health: Arc::new(std::sync::RwLock::new(HashMap::new())),
+        status_cache: Arc::new(status_cache),
         segments: Arc::new(std::sync::RwLock::new(segment_manager)),
-        workspaces: workspace_manager.map(Arc::new),
+        workspaces: workspace_manager
+            .map(|m| Arc::new(m.with_setup_progress(Some(Arc::new(events.clone()))))),
         work_manager: Arc::new(work_manager),
         agent: Arc::new(agent),
+        events,
+        agent_routes: Arc::new(std::sync::Mutex::new(Vec::new())),
     };
 
+    // Clear routes left behind by workspaces removed while the daemon was
+    // down, then keep pruning periodically
+    {
+        let state = state.clone();
+        let interval = state.config.proxy.prune_interval_secs;
+        tokio::spawn(async move {
+            loop {
+                match gc_proxy_routes(&state).await {
+                    Ok(removed) if !removed.is_empty() => {
+                        tracing::info!("Removed {} stale proxy route(s)", removed.len())
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Proxy route GC skipped: {:#}", e),
+                }
+                if interval == 0 {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+            }
+        });
+    }
+
+    status_cache::spawn_invalidator(state.status_cache.clone(), &state.events);
+    schedules::spawn_scheduler(state.clone());
+    health::spawn_health_monitor(state.clone());
+    gc::spawn_gc_monitor(state.clone());
+    reaper::spawn_reaper(state.clone());
+    idle_reaper::spawn_idle_reaper(state.clone());
+    auto_finalize::spawn_auto_finalizer(state.clone());
+    dependencies::spawn_dependency_starter(state.clone());
+    notifications::spawn_notifier(state.clone());
+    usage::spawn_usage_recorder(state.clone());
+    crate::plugins::spawn_watcher(state.plugins.clone());
+    crate::telemetry::spawn_metrics(&state.events);
+
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/pair", post(pair_device))
@@ -84,11 +163,14 @@ pub async fn serve(
         .route("/api/fs/list", post(handlers::fs_list))
         .route("/api/vcs/status", post(handlers::vcs_status))
         .route("/api/vcs/diff", post(handlers::vcs_diff))
+        .route("/api/vcs/diff/stream", post(handlers::vcs_diff_stream))
         .route("/api/plugins/list", get(handlers::plugins_list))
         .route("/api/plugins/execute", post(handlers::plugins_execute))
+        .route("/api/plugins/reload", post(handlers::plugins_reload))
         .route("/api/ancillaries/list", get(ancillaries_list))
         .route("/api/ancillaries/:id/start", post(ancillary_start_work))
         .route("/api/ancillaries/:id/stop", post(ancillary_stop_work))
+        .route("/api/ancillaries/:id/log", get(ancillary_log))
         .route("/api/assignments", get(assignments_list))
         .route("/api/assignments", post(assignments_create))
         .route("/api/assignments/:id", get(assignments_get))
@@ -106,23 +188,69 @@ pub async fn serve(
         )
         .route("/api/assignments/:id/abort", post(assignments_abort))
         .route("/api/assignments/:id/resume", post(assignments_resume))
+        .route("/api/assignments/:id/diff", get(assignment_diff))
+        .route(
+            "/api/assignments/:id/notes",
+            get(assignment_notes_list).post(assignment_notes_add),
+        )
         .route(
             "/api/assignments/:id/action/:name",
             post(assignment_action),
         )
+        .route("/api/schedules", get(schedules::schedules_list))
+        .route("/api/schedules", post(schedules::schedules_create))
+        .route(
+            "/api/schedules/:id",
+            axum::routing::delete(schedules::schedules_remove),
+        )
         .route("/api/segments/list", get(segments_list))
         .route("/api/segments/create", post(segments_create))
         .route("/api/workspaces/list/:segment", get(workspaces_list))
         .route("/api/workspaces/cleanup", post(workspaces_cleanup))
-        .layer(CorsLayer::permissive())
-        .with_state(state);
+        .route("/api/workspaces/setup", post(workspaces_setup))
+        .route("/api/workspaces/destroy", post(workspaces_destroy))
+        .route("/api/workspaces/usage", get(workspaces_usage))
+        .route(
+            "/api/workspaces/:segment/:workspace/processes",
+            get(workspace_processes),
+        )
+        .route("/api/ports", get(ports_list))
+        .route("/api/doctor", get(doctor))
+        .route("/api/history", get(history))
+        .route("/api/usage", get(usage::usage_summary))
+        .route("/api/gc", get(gc::gc_report).post(gc::gc_apply))
+        .route("/api/proxy/routes", get(proxy_routes_list))
+        .route(
+            "/api/proxy/routes/:segment/:workspace",
+            axum::routing::delete(proxy_routes_remove),
+        )
+        .route("/api/proxy/reconcile", post(proxy_reconcile))
+        .route("/api/agent/progress", post(agent::progress))
+        .route("/api/agent/ports", post(agent::port))
+        .route("/api/agent/routes", post(agent::route));
+    let app = auth::layer(app, state.security.clone()).layer(CorsLayer::permissive());
+    let app = request_id::layer(app).with_state(state.clone());
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown::drain_on_signal(state))
+    .await?;
 
     Ok(())
 }
 
+/// URL agents reach the daemon at: the listen address, with an unspecified
+/// host (`0.0.0.0`, `::`) replaced by loopback.
+fn api_url(addr: &str) -> String {
+    match addr.parse::<std::net::SocketAddr>() {
+        Ok(sock) if sock.ip().is_unspecified() => format!("http://127.0.0.1:{}", sock.port()),
+        _ => format!("http://{}", addr),
+    }
+}
+
 async fn health_check() -> impl IntoResponse {
     Json(serde_json::json!({
         "status": "ok",
@@ -133,12 +261,18 @@ async fn health_check() -> impl IntoResponse {
 #[derive(Debug, Deserialize)]
 struct PairRequest {
     pairing_token: String,
+    /// Pair for viewing only: the session can't call mutating endpoints
+    #[serde(default)]
+    read_only: bool,
 }
 
 #[derive(Debug, Serialize)]
 struct PairResponse {
     session_token: String,
     session_id: String,
+    /// Whether the session may only read, by its own scope or the daemon's
+    /// `[server] read_only`
+    read_only: bool,
 }
 
 async fn pair_device(
@@ -154,10 +288,11 @@ async fn pair_device(
 
     let session = state
         .security
-        .create_session()
+        .create_session(request.read_only)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(PairResponse {
+        read_only: state.security.is_read_only(Some(&session.token)),
         session_token: session.token,
         session_id: session.id,
     }))
@@ -177,6 +312,7 @@ async fn ancillary_ws_handler(
     State(state): State<AppState>,
     Path(ancillary_id): Path<String>,
     Query(query): Query<AncillaryWsQuery>,
+    Query(filter): Query<LogFilter>,
 ) -> impl IntoResponse {
     // URL decode the ancillary ID (spaces become %20)
     let ancillary_id = urlencoding::decode(&ancillary_id)
@@ -184,7 +320,7 @@ async fn ancillary_ws_handler(
         .unwrap_or(ancillary_id);
 
     ws.on_upgrade(move |socket| {
-        ancillary_ws::handle_ancillary_ws(socket, state, ancillary_id, query.from_seq)
+        ancillary_ws::handle_ancillary_ws(socket, state, ancillary_id, query.from_seq, filter)
     })
 }
 
@@ -195,6 +331,56 @@ struct StartWorkRequest {
     /// Optional agent override (e.g., "claude", "codex:o3"). Uses daemon default if unset.
     #[serde(default)]
     agent: Option<String>,
+    /// Tool permissions for this session. Uses `[ancillaries] permission_mode` if unset.
+    #[serde(default)]
+    permission_mode: Option<toren_lib::PermissionPolicy>,
+    /// Take the assignment over from a breq session that owns it
+    #[serde(default)]
+    steal: bool,
+}
+
+/// Options for starting an agent on `assignment`, with its workspace's
+/// toren.kdl vars for the prompt.
+fn work_options(
+    state: &AppState,
+    assignment: &Assignment,
+    permission: toren_lib::PermissionPolicy,
+) -> WorkOptions {
+    let segment_path = {
+        let segments = state.segments.read().unwrap();
+        segments
+            .find_by_name(&assignment.segment)
+            .map(|s| s.path.clone())
+    };
+    let vars = match (segment_path, &state.workspaces) {
+        (Some(segment_path), Some(ws_mgr)) => {
+            let ws_name = assignment
+                .workspace_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            ws_mgr
+                .workspace_vars(
+                    &segment_path,
+                    &assignment.workspace_path,
+                    ws_name,
+                    assignment
+                        .ancillary_num
+                        .or_else(|| toren_lib::ancillary_number(&assignment.ancillary_id))
+                        .unwrap_or(0),
+                )
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to evaluate toren.kdl vars for {}: {:#}", assignment.id, e);
+                    HashMap::new()
+                })
+        }
+        _ => HashMap::new(),
+    };
+    WorkOptions {
+        permission,
+        vars,
+        ..WorkOptions::default()
+    }
 }
 
 async fn ancillary_start_work(
@@ -206,6 +392,12 @@ async fn ancillary_start_work(
     let ancillary_id = urlencoding::decode(&ancillary_id)
         .map(|s| s.into_owned())
         .unwrap_or(ancillary_id);
+    let ancillary_id = toren_lib::AncillaryId::parse(&ancillary_id).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+    })?;
 
     // Get the assignment
     let assignment = {
@@ -220,6 +412,22 @@ async fn ancillary_start_work(
         )
     })?;
 
+    // Work waiting on another task is started by the dependency starter
+    if let Some(after) = assignment.after.as_ref() {
+        let completed = state.assignments.read().await.completed_task_ids().unwrap_or_default();
+        if assignment.blocked_on(&completed).is_some() {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": format!("Assignment is waiting for {} to complete", after),
+                    "code": "blocked",
+                    "after": after,
+                })),
+            ));
+        }
+        let _ = state.assignments.write().await.set_after(&assignment.id, None);
+    }
+
     // Check if ancillary already has active work
     if state.work_manager.has_active_work(&ancillary_id).await {
         return Err((
@@ -230,7 +438,7 @@ async fn ancillary_start_work(
 
     // Resolve agent: per-request override or daemon default
     let agent = if let Some(ref agent_str) = request.agent {
-        Agent::parse(agent_str).map_err(|e| {
+        state.config.resolve_agent(Some(agent_str)).map_err(|e| {
             (
                 StatusCode::BAD_REQUEST,
                 Json(serde_json::json!({"error": e.to_string()})),
@@ -239,11 +447,15 @@ async fn ancillary_start_work(
     } else {
         (*state.agent).clone()
     };
+    let permission = request
+        .permission_mode
+        .unwrap_or(state.config.ancillaries.permission_mode);
+    let options = work_options(&state, &assignment, permission);
 
     // Start work
     match state
         .work_manager
-        .start_work(ancillary_id.clone(), assignment, &agent)
+        .start_work(ancillary_id.clone(), assignment, &agent, options, request.steal)
         .await
     {
         Ok(work) => {
@@ -251,13 +463,14 @@ async fn ancillary_start_work(
             Ok(Json(serde_json::json!({
                 "success": true,
                 "ancillary_id": ancillary_id,
-                "status": status.to_string()
+                "status": status.to_string(),
+                "permission_mode": permission,
             })))
         }
-        Err(e) => Err((
+        Err(e) => Err(work_refused(&e).unwrap_or((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({"error": e.to_string()})),
-        )),
+        ))),
     }
 }
 
@@ -279,6 +492,67 @@ async fn ancillary_stop_work(
     }
 }
 
+/// Events per page of `GET /api/ancillaries/:id/log`, unless `limit` says otherwise
+const LOG_PAGE_DEFAULT: usize = 200;
+const LOG_PAGE_MAX: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+struct LogPageQuery {
+    #[serde(default)]
+    from_seq: u64,
+    limit: Option<usize>,
+}
+
+/// Search an ancillary's work log: its running work's, or else the on-disk
+/// log of its active assignment. Filters are [`LogFilter`]'s; pages continue
+/// from the returned `next_seq`.
+async fn ancillary_log(
+    State(state): State<AppState>,
+    Path(ancillary_id): Path<String>,
+    Query(page): Query<LogPageQuery>,
+    Query(filter): Query<LogFilter>,
+) -> Result<Json<LogPage>, (StatusCode, Json<serde_json::Value>)> {
+    let ancillary_id = urlencoding::decode(&ancillary_id)
+        .map(|s| s.into_owned())
+        .unwrap_or(ancillary_id);
+    let internal = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": format!("{:#}", e)})),
+        )
+    };
+
+    let events = match state.work_manager.get_work(&ancillary_id).await {
+        Some(work) => work.read_log_from(0).await.map_err(internal)?,
+        None => {
+            let id = toren_lib::AncillaryId::parse(&ancillary_id).map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": e.to_string()})),
+                )
+            })?;
+            let assignment_id = state
+                .assignments
+                .write()
+                .await
+                .get_active_for_ancillary(&id)
+                .map(|a| a.id.clone())
+                .ok_or_else(|| {
+                    (
+                        StatusCode::NOT_FOUND,
+                        Json(serde_json::json!({
+                            "error": format!("No work log for ancillary: {}", ancillary_id)
+                        })),
+                    )
+                })?;
+            WorkLog::read_all(&ancillary_id, &assignment_id).map_err(internal)?
+        }
+    };
+
+    let limit = page.limit.unwrap_or(LOG_PAGE_DEFAULT).clamp(1, LOG_PAGE_MAX);
+    Ok(Json(work_log::search(events, &filter, page.from_seq, limit)))
+}
+
 async fn ancillaries_list(State(state): State<AppState>) -> impl IntoResponse {
     let ancillaries = state.ancillaries.list();
     Json(serde_json::json!({
@@ -299,6 +573,82 @@ async fn segments_list(State(state): State<AppState>) -> impl IntoResponse {
     }))
 }
 
+async fn doctor(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let config = state.config.clone();
+    let checks = tokio::task::spawn_blocking(move || toren_lib::doctor::run_checks(&config))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    Ok(Json(serde_json::json!({
+        "ok": !toren_lib::doctor::has_failures(&checks),
+        "checks": checks,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    segment: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Completed and aborted assignments, newest first, with the directory their
+/// artifacts were kept in.
+async fn history(
+    State(state): State<AppState>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let assignments = state.assignments.read().await;
+    let filter = toren_lib::assignment_store::HistoryFilter {
+        segment: query.segment,
+        limit: query.limit,
+    };
+    let records = assignments.recent_history(&filter).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+    })?;
+
+    let records: Vec<serde_json::Value> = records
+        .into_iter()
+        .map(|r| {
+            let dir = (!r.artifacts.is_empty()).then(|| assignments.artifacts_dir(&r.assignment_id));
+            let mut value = serde_json::to_value(&r).unwrap_or_default();
+            if let Some(dir) = dir {
+                value["artifact_dir"] = serde_json::json!(dir);
+            }
+            value
+        })
+        .collect();
+    Ok(Json(serde_json::json!({ "records": records })))
+}
+
+async fn ports_list() -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let leases = toren_lib::ports::PortRegistry::new().list().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+    })?;
+    let ports: Vec<_> = leases
+        .into_iter()
+        .map(|(port, lease)| {
+            serde_json::json!({
+                "port": port,
+                "workspace": lease.workspace,
+                "var": lease.var,
+            })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({ "ports": ports })))
+}
+
 #[derive(Debug, Deserialize)]
 struct CreateSegmentRequest {
     name: String,
@@ -352,6 +702,147 @@ async fn workspaces_list(
     }
 }
 
+/// Disk usage of every workspace (or those of `?segment=`), largest first.
+async fn workspaces_usage(
+    State(state): State<AppState>,
+    Query(hint): Query<SegmentHint>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let ws_mgr = state
+        .workspaces
+        .clone()
+        .ok_or(StatusCode::NOT_IMPLEMENTED)?;
+    let mut segments = state.segments.read().unwrap().list_all();
+    if let Some(ref name) = hint.segment {
+        segments.retain(|s| &s.name == name);
+        if segments.is_empty() {
+            return Err(StatusCode::NOT_FOUND);
+        }
+    }
+
+    let warn_bytes = state.config.ancillaries.disk_warn_mb * 1024 * 1024;
+    let usage = tokio::task::spawn_blocking(move || {
+        toren_lib::disk_usage::workspace_usage(&ws_mgr, &segments, warn_bytes)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let total: u64 = usage.iter().map(|u| u.bytes).sum();
+    Ok(Json(serde_json::json!({
+        "workspaces": usage,
+        "total_bytes": total,
+        "warn_bytes": warn_bytes,
+    })))
+}
+
+/// Processes whose working directory is inside a workspace (what a
+/// complete or abort with `kill` would terminate).
+async fn workspace_processes(
+    State(state): State<AppState>,
+    Path((segment, workspace)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let ws_mgr = state.workspaces.clone().ok_or((
+        StatusCode::NOT_IMPLEMENTED,
+        Json(serde_json::json!({"error": "workspace_root not configured"})),
+    ))?;
+    let workspace = toren_lib::naming::normalize_workspace_name(&segment, &workspace);
+    let ws_path = ws_mgr.workspace_path(&segment, &workspace);
+    if !ws_path.exists() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("Workspace not found: {}/{}", segment, workspace)})),
+        ));
+    }
+    let processes = tokio::task::spawn_blocking(move || {
+        toren_lib::process::find_workspace_processes(&ws_path)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+    })?;
+    Ok(Json(serde_json::json!({ "processes": processes })))
+}
+
+/// Remove station routes whose workspace no longer exists.
+async fn gc_proxy_routes(state: &AppState) -> Result<Vec<toren_lib::proxy_routes::StaleRoute>> {
+    let Some(ws_mgr) = state.workspaces.clone() else {
+        return Ok(Vec::new());
+    };
+    let segments = state.segments.read().unwrap().list_all();
+    let active: Vec<Assignment> = state
+        .assignments
+        .write()
+        .await
+        .list_active()
+        .into_iter()
+        .cloned()
+        .collect();
+    tokio::task::spawn_blocking(move || ws_mgr.gc_proxy_routes(&segments, &active)).await?
+}
+
+async fn proxy_reconcile(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let removed = gc_proxy_routes(&state).await.map_err(|e| {
+        (
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!({"error": format!("{:#}", e)})),
+        )
+    })?;
+    Ok(Json(serde_json::json!({ "removed": removed })))
+}
+
+async fn proxy_routes_list(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let Some(ws_mgr) = state.workspaces.clone() else {
+        return Ok(Json(serde_json::json!({ "routes": [] })));
+    };
+    let segments = state.segments.read().unwrap().list_all();
+    let routes = tokio::task::spawn_blocking(move || ws_mgr.proxy_routes(&segments))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r)
+        .map_err(|e| {
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({"error": format!("{:#}", e)})),
+            )
+        })?;
+    Ok(Json(serde_json::json!({ "routes": routes })))
+}
+
+async fn proxy_routes_remove(
+    State(state): State<AppState>,
+    Path((segment, workspace)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let ws_mgr = state.workspaces.clone().ok_or((
+        StatusCode::NOT_IMPLEMENTED,
+        Json(serde_json::json!({"error": "workspace_root not configured"})),
+    ))?;
+    let segments = state.segments.read().unwrap().list_all();
+    if !segments.iter().any(|s| s.name == segment) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("Segment not found: {}", segment)})),
+        ));
+    }
+    let removed = tokio::task::spawn_blocking(move || {
+        ws_mgr.remove_workspace_routes(&segments, &segment, &workspace)
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|r| r)
+    .map_err(|e| {
+        (
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!({"error": format!("{:#}", e)})),
+        )
+    })?;
+    Ok(Json(serde_json::json!({ "removed": removed })))
+}
+
 #[derive(Debug, Deserialize)]
 struct WorkspaceCleanupRequest {
     segment: String,
@@ -391,10 +882,16 @@ async fn workspaces_cleanup(
         &request.workspace,
         toren_lib::workspace::CleanupMode::Abort,
     ) {
-        Ok(_result) => Ok(Json(serde_json::json!({
-            "success": true,
-            "message": format!("Workspace {} cleaned up", request.workspace)
-        }))),
+        Ok(_result) => {
+            state.events.publish(DaemonEvent::WorkspaceCleaned {
+                segment: request.segment.clone(),
+                workspace: request.workspace.clone(),
+            });
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "message": format!("Workspace {} cleaned up", request.workspace)
+            })))
+        }
         Err(e) => {
             eprintln!("Failed to cleanup workspace: {}", e);
             Ok(Json(serde_json::json!({
@@ -405,6 +902,119 @@ async fn workspaces_cleanup(
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct WorkspaceHookRequest {
+    segment: String,
+    workspace: String,
+}
+
+/// Re-run a workspace's toren.kdl setup, as `breq shell --hook setup` does,
+/// returning its vars and proxy routes. Progress is broadcast as
+/// `workspace_setup_progress` events.
+async fn workspaces_setup(
+    State(state): State<AppState>,
+    Json(request): Json<WorkspaceHookRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let (ws_mgr, segment_path, ws_path, ws_name) = workspace_hook_target(&state, &request)?;
+    let ancillary_num = toren_lib::naming::slot_number(&request.segment, &ws_name).unwrap_or(0);
+    let (result, facts) = tokio::task::spawn_blocking(move || {
+        let result = ws_mgr.run_setup(&segment_path, &ws_path, &ws_name, ancillary_num)?;
+        let facts = ws_mgr.describe_workspace(&segment_path, &ws_path, &ws_name, ancillary_num)?;
+        anyhow::Ok((result, facts))
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|r| r)
+    .map_err(|e| workspace_hook_error(&e))?;
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "failed_actions": result.failed,
+        "vars": facts.vars,
+        "proxy_urls": facts.proxy_urls,
+        "proxy_routes": facts.proxy_routes,
+    })))
+}
+
+/// Run a workspace's toren.kdl destroy actions, as `breq shell --hook
+/// destroy` does. The workspace itself is left in place. Actions that
+/// failed or timed out without stopping destroy are listed in
+/// `failed_actions`.
+async fn workspaces_destroy(
+    State(state): State<AppState>,
+    Json(request): Json<WorkspaceHookRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let (ws_mgr, segment_path, ws_path, ws_name) = workspace_hook_target(&state, &request)?;
+    let result = tokio::task::spawn_blocking(move || ws_mgr.run_destroy(&segment_path, &ws_path, &ws_name))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r)
+        .map_err(|e| workspace_hook_error(&e))?;
+    Ok(Json(serde_json::json!({ "success": true, "failed_actions": result.failed })))
+}
+
+/// The workspace manager, segment path, workspace path and workspace name
+/// a hook request targets; 404 if the segment or workspace doesn't exist.
+#[allow(clippy::type_complexity)]
+fn workspace_hook_target(
+    state: &AppState,
+    request: &WorkspaceHookRequest,
+) -> Result<(Arc<WorkspaceManager>, PathBuf, PathBuf, String), (StatusCode, Json<serde_json::Value>)> {
+    let ws_mgr = state.workspaces.clone().ok_or((
+        StatusCode::NOT_IMPLEMENTED,
+        Json(serde_json::json!({"error": "workspace_root not configured"})),
+    ))?;
+    let segment_path = state
+        .segments
+        .read()
+        .unwrap()
+        .find_by_name(&request.segment)
+        .map(|s| s.path.clone())
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("Segment not found: {}", request.segment)})),
+        ))?;
+    let ws_name = toren_lib::naming::normalize_workspace_name(&request.segment, &request.workspace);
+    if ws_name.is_empty() || ws_name.starts_with('.') || ws_name.contains(['/', '\\']) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": format!("Invalid workspace name: {}", request.workspace)})),
+        ));
+    }
+    let ws_path = ws_mgr.workspace_path(&request.segment, &ws_name);
+    if !ws_path.exists() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("Workspace not found: {}", ws_path.display())})),
+        ));
+    }
+    Ok((ws_mgr, segment_path, ws_path, ws_name))
+}
+
+/// A failed setup or destroy action is 422 with the action and, for a
+/// `run` command, its exit code and stderr.
+fn workspace_hook_error(e: &anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    use toren_lib::workspace_setup::{ActionFailed, CommandFailed};
+    let Some(action) = e.downcast_ref::<ActionFailed>() else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": format!("{:#}", e)})),
+        );
+    };
+    let command = e.downcast_ref::<CommandFailed>();
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(serde_json::json!({
+            "error": format!("{} failed", action.label),
+            "code": "setup_action_failed",
+            "action": action.index,
+            "action_label": action.label,
+            "exit_code": command.map(|c| c.exit_code),
+            "output": command.map(|c| c.stderr.as_str()),
+            "detail": format!("{:#}", e),
+        })),
+    )
+}
+
 // ==================== Composite Status Helper ====================
 
 /// Enriched assignment with composite status signals
@@ -415,26 +1025,70 @@ struct EnrichedAssignment {
     /// Composite status signals derived from observable state
     #[serde(flatten)]
     composite: CompositeStatus,
+    /// Latest health check, once the monitor has run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    health: Option<AssignmentHealth>,
 }
 
-/// Compute composite status for an assignment
+/// Composite statuses `GET /api/assignments` computes at once
+const STATUS_CONCURRENCY: usize = 8;
+
+/// Composite status for an assignment, reusing a recent computation
 async fn compute_composite_status(
     assignment: &Assignment,
     state: &AppState,
 ) -> CompositeStatus {
-    // 1. Agent activity — check work manager first, then Claude session logs
-    let agent_activity = if state.work_manager.has_active_work(&assignment.ancillary_id).await {
-        "busy".to_string()
-    } else {
-        // Fall back to Claude session log recency check
-        toren_lib::composite_status::detect_agent_activity(&assignment.workspace_path)
-    };
+    let mut status = state
+        .status_cache
+        .get_or_compute(&assignment.id, || observe_composite_status(assignment, state))
+        .await;
+    // Work manager state is in memory, so it is always current
+    if state.work_manager.has_active_work(&assignment.ancillary_id).await {
+        status.agent_activity = "busy".to_string();
+    }
+    status
+}
+
+/// Compute composite status for an assignment. The checks shell out and read
+/// logs, so they run on the blocking pool, letting several assignments'
+/// statuses compute at once.
+async fn observe_composite_status(
+    assignment: &Assignment,
+    state: &AppState,
+) -> CompositeStatus {
+    let assignment = assignment.clone();
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || observe_composite_status_blocking(&assignment, &state))
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Composite status computation failed: {}", e);
+            CompositeStatus {
+                agent_activity: "idle".to_string(),
+                has_changes: false,
+                has_conflicts: false,
+                task_status: "unknown".to_string(),
+                task_assignee: String::new(),
+            }
+        })
+}
+
+fn observe_composite_status_blocking(assignment: &Assignment, state: &AppState) -> CompositeStatus {
+    // 1. Agent activity — from Claude session log recency (the work manager
+    // is checked by the caller)
+    let agent_activity = toren_lib::composite_status::detect_agent_activity(
+        &SessionLogs::from_config(&state.config),
+        &assignment.working_dir(),
+    );
 
     // 2. Has changes (VCS-agnostic)
     let has_changes = toren_lib::composite_status::workspace_has_changes(
         &assignment.workspace_path,
         assignment.base_branch.as_deref(),
+        assignment.focus_path.as_deref(),
     );
+    // Conflicts only arise from changes, so a clean workspace skips the check
+    let has_conflicts = has_changes
+        && !toren_lib::composite_status::workspace_conflicts(&assignment.workspace_path).is_empty();
 
     // 3. Task status + assignee — from task resolver
     let segment_path = {
@@ -466,6 +1120,7 @@ async fn compute_composite_status(
     CompositeStatus {
         agent_activity,
         has_changes,
+        has_conflicts,
         task_status,
         task_assignee,
     }
@@ -477,7 +1132,7 @@ async fn compute_composite_status(
 struct CreateAssignmentRequest {
     /// Create from existing task ID
     #[serde(default, alias = "bead_id")]
-    task_id: Option<String>,
+    task_id: Option<toren_lib::TaskId>,
     /// Create from prompt (auto-creates task)
     #[serde(default)]
     prompt: Option<String>,
@@ -492,6 +1147,16 @@ struct CreateAssignmentRequest {
     task_source: Option<String>,
     /// Segment name
     segment: String,
+    /// Allocate past `max_per_segment` when every pool slot is occupied
+    #[serde(default)]
+    overflow: bool,
+    /// Intent to record on the assignment (default: the segment's intent)
+    #[serde(default)]
+    intent: Option<String>,
+    /// Task to wait for: the daemon starts work on the assignment once a
+    /// completion record for it lands
+    #[serde(default)]
+    after: Option<toren_lib::TaskId>,
 }
 
 async fn assignments_list(State(state): State<AppState>) -> impl IntoResponse {
@@ -499,15 +1164,31 @@ async fn assignments_list(State(state): State<AppState>) -> impl IntoResponse {
     let all: Vec<Assignment> = assignments.list().into_iter().cloned().collect();
     drop(assignments); // Release lock before async work
 
-    // Enrich each assignment with composite status
-    let mut enriched = Vec::with_capacity(all.len());
-    for assignment in all {
-        let composite = compute_composite_status(&assignment, &state).await;
-        enriched.push(EnrichedAssignment {
-            assignment,
-            composite,
-        });
-    }
+    let ids: Vec<&str> = all.iter().map(|a| a.id.as_str()).collect();
+    state.status_cache.retain(&ids);
+
+    // Enrich each assignment with composite status, computing stale ones
+    // a few at a time
+    let composites: Vec<CompositeStatus> = futures::stream::iter(all.clone())
+        .map(|assignment| {
+            let state = state.clone();
+            async move { compute_composite_status(&assignment, &state).await }
+        })
+        .buffered(STATUS_CONCURRENCY)
+        .collect()
+        .await;
+    let enriched: Vec<EnrichedAssignment> = all
+        .into_iter()
+        .zip(composites)
+        .map(|(assignment, composite)| {
+            let health = health::recorded(&state, &assignment.id);
+            EnrichedAssignment {
+                assignment,
+                composite,
+                health,
+            }
+        })
+        .collect();
 
     Json(serde_json::json!({
         "assignments": enriched,
@@ -518,30 +1199,119 @@ async fn assignments_list(State(state): State<AppState>) -> impl IntoResponse {
 async fn assignments_get(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(hint): Query<SegmentHint>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let mut assignments = state.assignments.write().await;
-
-    // Try to find by assignment ID, ancillary ID, or bead ID
-    let assignment = assignments
-        .get(&id)
-        .cloned()
-        .or_else(|| assignments.get_active_for_ancillary(&id).cloned())
-        .or_else(|| assignments.get_by_task_id(&id).into_iter().next().cloned());
-
+    let assignment = resolve_assignment(&mut assignments, &id, &hint)?;
     drop(assignments);
 
-    let assignment = assignment.ok_or(StatusCode::NOT_FOUND)?;
     let composite = compute_composite_status(&assignment, &state).await;
+    let health = health::recorded(&state, &assignment.id);
 
     Ok(Json(serde_json::json!({
-        "assignment": EnrichedAssignment { assignment, composite }
+        "assignment": EnrichedAssignment { assignment, composite, health }
     })))
 }
 
+/// `GET /api/assignments/:id/diff`: the assignment's changes. Query
+/// parameters `stat`, `file`, `from` and `to` are [`DiffOptions`].
+async fn assignment_diff(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(hint): Query<SegmentHint>,
+    Query(opts): Query<DiffOptions>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let mut assignments = state.assignments.write().await;
+    let assignment = resolve_assignment(&mut assignments, &id, &hint)?;
+    drop(assignments);
+
+    if !assignment.workspace_path.exists() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": format!("Workspace {} no longer exists", assignment.workspace_path.display()),
+            })),
+        ));
+    }
+    let vcs = state.services.vcs.clone();
+    let diff = tokio::task::spawn_blocking(move || vcs.assignment_diff(&assignment, &opts))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+        })?
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": format!("{:#}", e) })),
+            )
+        })?;
+
+    Ok(Json(serde_json::json!({ "diff": diff })))
+}
+
+#[derive(Debug, Deserialize)]
+struct AddNoteRequest {
+    text: String,
+}
+
+async fn assignment_notes_list(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(hint): Query<SegmentHint>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let mut assignments = state.assignments.write().await;
+    let assignment = resolve_assignment(&mut assignments, &id, &hint)?;
+    Ok(Json(serde_json::json!({ "notes": assignment.notes })))
+}
+
+async fn assignment_notes_add(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(hint): Query<SegmentHint>,
+    Json(request): Json<AddNoteRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if request.text.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Note text is empty"})),
+        ));
+    }
+    let mut assignments = state.assignments.write().await;
+    let id = resolve_assignment(&mut assignments, &id, &hint)?.id;
+    let note = assignments
+        .add_note(&id, &request.text)
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": format!("Failed to save note: {:#}", e)})),
+            )
+        })?;
+    Ok(Json(serde_json::json!({ "note": note })))
+}
+
 async fn assignments_create(
     State(state): State<AppState>,
     Json(request): Json<CreateAssignmentRequest>,
 ) -> Result<Json<EnrichedAssignment>, (StatusCode, Json<serde_json::Value>)> {
+    let assignment = create_assignment(&state, request).await?;
+    let composite = compute_composite_status(&assignment, &state).await;
+    Ok(Json(EnrichedAssignment {
+        assignment,
+        composite,
+        health: None,
+    }))
+}
+
+/// The create-assignment pipeline: create or claim the task, allocate an
+/// ancillary, set up its workspace and record the assignment. Shared by
+/// `POST /api/assignments` and the scheduler.
+async fn create_assignment(
+    state: &AppState,
+    request: CreateAssignmentRequest,
+) -> Result<Assignment, (StatusCode, Json<serde_json::Value>)> {
     let ws_mgr = state.workspaces.as_ref().ok_or((
         StatusCode::NOT_IMPLEMENTED,
         Json(serde_json::json!({"error": "workspace_root not configured"})),
@@ -593,11 +1363,17 @@ async fn assignments_create(
                     Json(serde_json::json!({"error": format!("Failed to create task: {}", e)})),
                 )
             })?;
+        let new_task_id = toren_lib::TaskId::parse(new_task_id.trim()).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": format!("Task source returned an invalid ID: {}", e)})),
+            )
+        })?;
 
         // Claim the newly created task
         let ctx = toren_lib::PluginContext::new(Some(segment_path.clone()), None);
         plugin_mgr
-            .resolve_claim(&create_source, &new_task_id, "claude", ctx)
+            .resolve_claim(&create_source, &new_task_id, toren_lib::DEFAULT_CLAIM_IDENTITY, ctx)
             .map_err(|e| {
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -613,7 +1389,7 @@ async fn assignments_create(
         if let Some(source) = task_source {
             let ctx = toren_lib::PluginContext::new(Some(segment_path.clone()), None);
             plugin_mgr
-                .resolve_claim(source, &task_id, "claude", ctx)
+                .resolve_claim(source, &task_id, toren_lib::DEFAULT_CLAIM_IDENTITY, ctx)
                 .map_err(|e| {
                     (
                         StatusCode::BAD_REQUEST,
@@ -644,21 +1420,40 @@ async fn assignments_create(
     };
 
     // Find next available ancillary, accounting for existing workspaces on disk
-    let existing_workspaces = ws_mgr
-        .list_workspaces(&segment_path)
-        .unwrap_or_default();
-    let ancillary_id = assignments.next_available_ancillary(
+    let settings = state.segments.read().unwrap().settings(&request.segment);
+    let pool_size = settings.max_per_segment;
+    let ancillary_id = toren_lib::allocate_ancillary(
+        &mut assignments,
+        ws_mgr,
         &request.segment,
-        state.config.ancillaries.max_per_segment,
-        &existing_workspaces,
-    );
+        &segment_path,
+        pool_size,
+        request.overflow,
+        &SessionLogs::from_config(&state.config),
+    )
+    .map_err(|e| match e.downcast_ref::<toren_lib::PoolExhausted>() {
+        Some(exhausted) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": exhausted.to_string(),
+                "code": "pool_exhausted",
+                "segment": exhausted.segment,
+                "pool_size": exhausted.pool_size,
+                "slots": exhausted.slots,
+            })),
+        ),
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    })?;
     let ancillary_num = toren_lib::ancillary_number(&ancillary_id).unwrap_or(1);
 
     // Record base branch (for git worktrees; None for jj)
     let base_branch = ws_mgr.active_branch(&segment_path);
 
-    // Generate workspace name from ancillary number word
-    let ws_name = toren_lib::number_to_word(ancillary_num).to_lowercase();
+    // Generate workspace name from the ancillary's slot name
+    let ws_name = toren_lib::naming::workspace_name(&request.segment, ancillary_num);
 
     // Create workspace (with setup hooks)
     let (ws_path, _setup_result) = ws_mgr
@@ -668,23 +1463,19 @@ async fn assignments_create(
             &ws_name,
             ancillary_num,
         )
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": format!("Failed to create workspace: {}", e)})),
-            )
-        })?;
+        .map_err(|e| workspace_create_error(&e))?;
 
     // Create assignment
     let source = if let Some(prompt) = original_prompt {
         toren_lib::AssignmentSource::Prompt {
             original_prompt: prompt,
+            auto_created_bead: true,
         }
     } else {
         toren_lib::AssignmentSource::Reference
     };
 
-    let assignment = assignments
+    let mut assignment = assignments
         .create(
             &ancillary_id,
             Some(&task_id),
@@ -704,9 +1495,49 @@ async fn assignments_create(
                 ),
             )
         })?;
+    let intent = request.intent.clone().or(settings.intent);
+    if intent.is_some() {
+        if let Err(e) = assignments.set_intent(&assignment.id, intent.clone()) {
+            tracing::warn!("Failed to record intent for {}: {:#}", assignment.id, e);
+        }
+        assignment.intent = intent;
+    }
+    if request.after.is_some() {
+        if let Err(e) = assignments.set_after(&assignment.id, request.after.clone()) {
+            tracing::warn!("Failed to record the dependency of {}: {:#}", assignment.id, e);
+        }
+        assignment.after = request.after;
+    }
+    drop(assignments);
 
-    let composite = compute_composite_status(&assignment, &state).await;
-    Ok(Json(EnrichedAssignment { assignment, composite }))
+    // The task was claimed before the ancillary was allocated; re-claim it
+    // under the ancillary's identity if one is configured.
+    if let Some(ref template) = state.config.tasks.claim_identity {
+        if let Err(e) = toren_lib::claim_for_assignment(
+            &assignment,
+            plugin_mgr,
+            &segment_path,
+            Some(template),
+        ) {
+            tracing::warn!("Failed to claim task as {}: {:#}", assignment.ancillary_id, e);
+        }
+    }
+
+    if let Err(e) = ws_mgr.write_agent_context(&segment_path, &assignment) {
+        tracing::warn!("Failed to write agent context: {:#}", e);
+    }
+    state.events.publish(DaemonEvent::WorkspaceCreated {
+        segment: request.segment.clone(),
+        workspace: ws_name,
+        path: assignment.workspace_path.display().to_string(),
+    });
+    // Publishes AssignmentCreated via the event bus observer
+    toren_lib::hooks::emit(&toren_lib::HookPayload::new(
+        toren_lib::HookEvent::Assign,
+        &assignment,
+    ));
+
+    Ok(assignment)
 }
 
 #[derive(Debug, Deserialize)]
@@ -719,80 +1550,172 @@ struct UpdateStatusRequest {
 async fn assignments_update_status(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(hint): Query<SegmentHint>,
     Json(_request): Json<UpdateStatusRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // All assignments are active — status updates are no-ops.
     // Terminal transitions happen via complete/abort endpoints.
     let mut assignments = state.assignments.write().await;
 
     // Verify the assignment exists
-    let exists = assignments.get(&id).is_some()
-        || assignments.get_active_for_ancillary(&id).is_some();
-
-    if !exists {
-        return Err(StatusCode::NOT_FOUND);
-    }
+    resolve_assignment(&mut assignments, &id, &hint)?;
 
     Ok(Json(serde_json::json!({"success": true})))
 }
 
+/// Dismiss every assignment `:id` matches (e.g. all assignments of a task).
 async fn assignments_delete(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(hint): Query<SegmentHint>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let mut assignments = state.assignments.write().await;
 
-    // Try to find by assignment ID first
-    if assignments.get(&id).is_some() {
-        assignments
-            .remove(&id)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        return Ok(Json(serde_json::json!({"success": true, "removed": 1})));
+    let ids: Vec<String> = assignments
+        .resolve_id(&id, hint.segment.as_deref())
+        .into_iter()
+        .map(|a| a.id.clone())
+        .collect();
+    if ids.is_empty() {
+        return Err(assignment_not_found(&id));
     }
 
-    // Try by ancillary ID
-    let dismissed = assignments
-        .dismiss_ancillary(&id)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    if !dismissed.is_empty() {
-        return Ok(Json(
-            serde_json::json!({"success": true, "removed": dismissed.len()}),
-        ));
+    for id in &ids {
+        assignments.remove(id).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": format!("Failed to remove assignment: {:#}", e)})),
+            )
+        })?;
     }
+    Ok(Json(serde_json::json!({"success": true, "removed": ids.len()})))
+}
 
-    // Try by task ID
-    let dismissed = assignments
-        .dismiss_task_id(&id)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+// ==================== Assignment Lifecycle Endpoints ====================
 
-    if !dismissed.is_empty() {
-        return Ok(Json(
-            serde_json::json!({"success": true, "removed": dismissed.len()}),
+/// Error response for a failed workspace creation (or any other error
+/// from the same call). Setup failures are 422 with the structured cause
+/// under `failure`, so clients can show e.g. "run pnpm install failed
+/// (exit 1)" with its stderr rather than a generic 500.
+fn workspace_create_error(e: &anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    let Some(failure) = e.downcast_ref::<toren_lib::WorkspaceCreateFailure>() else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": format!("{:#}", e)})),
+        );
+    };
+    let status = match failure.stage {
+        toren_lib::CreateStage::Vcs => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::UNPROCESSABLE_ENTITY,
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "error": failure.summary(),
+            "code": "workspace_create_failed",
+            "failure": failure,
+        })),
+    )
+}
+
+/// 409 with the owner when another live process owns the assignment.
+fn owned_error(e: &anyhow::Error) -> Option<(StatusCode, Json<serde_json::Value>)> {
+    let owned = e.downcast_ref::<toren_lib::AssignmentOwned>()?;
+    Some((
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "error": owned.to_string(),
+            "code": "assignment_owned",
+            "owner": owned.owner,
+        })),
+    ))
+}
+
+/// Why the work manager refused to start work: another process owns the
+/// assignment, or the daemon is shutting down.
+fn work_refused(e: &anyhow::Error) -> Option<(StatusCode, Json<serde_json::Value>)> {
+    if let Some(refused) = e.downcast_ref::<crate::ancillary::ShuttingDown>() {
+        return Some((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({"error": refused.to_string(), "code": "shutting_down"})),
         ));
     }
+    owned_error(e)
+}
 
-    Err(StatusCode::NOT_FOUND)
+/// Refuse to act on an assignment another live process owns, unless `steal`.
+fn check_owner(
+    assignments: &mut toren_lib::AssignmentManager,
+    assignment: &toren_lib::Assignment,
+    steal: bool,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    assignments.check_owner(&assignment.id, steal).map_err(|e| {
+        owned_error(&e).unwrap_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ))
+    })
 }
 
-// ==================== Assignment Lifecycle Endpoints ====================
+/// Optional `?segment=` hint for resolving an assignment's `:id`
+#[derive(Debug, Default, Deserialize)]
+struct SegmentHint {
+    segment: Option<String>,
+}
 
-/// Helper to resolve an assignment by ID, ancillary ID, or bead ID
-fn resolve_assignment(assignments: &mut AssignmentManager, id: &str) -> Option<Assignment> {
-    // Try by assignment ID
-    if let Some(a) = assignments.get(id) {
-        return Some(a.clone());
-    }
-    // Try by ancillary ID (active assignment)
-    if let Some(a) = assignments.get_active_for_ancillary(id) {
-        return Some(a.clone());
-    }
-    // Try by task ID (first match)
-    let by_task = assignments.get_by_task_id(id);
-    if let Some(a) = by_task.into_iter().next() {
-        return Some(a.clone());
+/// Resolve the `:id` of an assignment route, the way breq resolves a
+/// workspace reference.
+///
+/// Precedence:
+/// 1. Assignment ID (UUID)
+/// 2. Full ancillary name: "Toren One", "toren 21"
+/// 3. Ancillary number word or digits ("one", "21"), in the `?segment=`
+///    segment, or in any segment without a hint
+/// 4. Task ID, narrowed to `?segment=` when given
+///
+/// More than one match (e.g. "one" across segments, or a task assigned in
+/// two segments) is a 409 listing the candidates.
+fn resolve_assignment(
+    assignments: &mut AssignmentManager,
+    id: &str,
+    hint: &SegmentHint,
+) -> Result<Assignment, (StatusCode, Json<serde_json::Value>)> {
+    match assignments.resolve_id(id, hint.segment.as_deref()).as_slice() {
+        [] => Err(assignment_not_found(id)),
+        [assignment] => Ok((*assignment).clone()),
+        matches => {
+            let mut candidates: Vec<&str> = matches.iter().map(|a| a.ancillary_id.as_str()).collect();
+            candidates.sort();
+            Err((
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": format!(
+                        "'{}' matches {} assignments; use an assignment ID or add ?segment=",
+                        id,
+                        matches.len()
+                    ),
+                    "candidates": candidates,
+                })),
+            ))
+        }
     }
-    None
+}
+
+fn assignment_not_found(id: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({"error": format!("Assignment not found: {}", id)})),
+    )
+}
+
+/// Workspace directory name for an assignment (e.g., "one")
+fn workspace_name(assignment: &Assignment) -> String {
+    assignment
+        .workspace_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string()
 }
 
 #[derive(Debug, Deserialize)]
@@ -806,11 +1729,21 @@ struct CompleteRequest {
     /// Whether to kill processes running in the workspace before cleanup
     #[serde(default)]
     kill: bool,
+    /// Skip the `[push]` checks
+    #[serde(default)]
+    no_verify: bool,
+    /// Complete even if a breq session is still running the agent
+    #[serde(default)]
+    steal: bool,
+    /// Complete even if the working copy has unresolved conflicts
+    #[serde(default)]
+    allow_conflicts: bool,
 }
 
 async fn assignments_complete(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(hint): Query<SegmentHint>,
     Json(request): Json<CompleteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let ws_mgr = state.workspaces.as_ref().ok_or((
@@ -820,10 +1753,8 @@ async fn assignments_complete(
 
     let mut assignments = state.assignments.write().await;
 
-    let assignment = resolve_assignment(&mut assignments, &id).ok_or((
-        StatusCode::NOT_FOUND,
-        Json(serde_json::json!({"error": "Assignment not found"})),
-    ))?;
+    let assignment = resolve_assignment(&mut assignments, &id, &hint)?;
+    check_owner(&mut assignments, &assignment, request.steal)?;
 
     // Stop active work if running
     let _ = state
@@ -846,9 +1777,14 @@ async fn assignments_complete(
         ),
     ))?;
 
-    // Render auto-commit message from hardcoded template
+    // Render auto-commit message from the configured template
     let auto_commit_message = toren_lib::render_auto_commit_message(
-        toren_lib::DEFAULT_AUTO_COMMIT_MESSAGE,
+        state
+            .config
+            .tasks
+            .commit_message
+            .as_deref()
+            .unwrap_or(toren_lib::DEFAULT_AUTO_COMMIT_MESSAGE),
         &assignment,
         &assignment.segment,
         &segment_path,
@@ -861,11 +1797,35 @@ async fn assignments_complete(
         kill: request.kill,
         auto_commit_message,
         plugin_mgr: &state.rhai_plugins,
+        report_intents: &state.config.tasks.report_intents,
+        session_logs: &SessionLogs::from_config(&state.config),
+        push_checks: (!request.no_verify).then_some(&state.config.push),
+        allow_conflicts: request.allow_conflicts,
     };
 
     let result =
         toren_lib::complete_assignment(&assignment, &mut assignments, ws_mgr, &opts).map_err(
             |e| {
+                if let Some(failed) = e.downcast_ref::<toren_lib::push_checks::PushChecksFailed>() {
+                    return (
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        Json(serde_json::json!({
+                            "error": failed.to_string(),
+                            "code": "push_checks_failed",
+                            "findings": failed.findings,
+                        })),
+                    );
+                }
+                if let Some(conflicted) = e.downcast_ref::<toren_lib::WorkspaceConflicted>() {
+                    return (
+                        StatusCode::CONFLICT,
+                        Json(serde_json::json!({
+                            "error": conflicted.to_string(),
+                            "code": "conflicts",
+                            "paths": conflicted.paths,
+                        })),
+                    );
+                }
                 let status = if e.downcast_ref::<toren_lib::WorkspaceProcessesRunning>().is_some() {
                     StatusCode::CONFLICT
                 } else {
@@ -879,6 +1839,9 @@ async fn assignments_complete(
         "success": true,
         "revision": result.revision,
         "pushed": result.pushed,
+        "artifacts": result.artifacts,
+        "report": result.report,
+        "report_posted": result.report_posted,
     })))
 }
 
@@ -887,14 +1850,21 @@ struct AbortRequest {
     /// Whether to close the bead (default: reopen it)
     #[serde(default)]
     close_bead: bool,
+    /// Delete the bead instead if it was auto-created from the prompt
+    #[serde(default)]
+    delete_bead: bool,
     /// Whether to kill processes running in the workspace before cleanup
     #[serde(default)]
     kill: bool,
+    /// Abort even if a breq session is still running the agent
+    #[serde(default)]
+    steal: bool,
 }
 
 async fn assignments_abort(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(hint): Query<SegmentHint>,
     Json(request): Json<AbortRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let ws_mgr = state.workspaces.as_ref().ok_or((
@@ -904,10 +1874,8 @@ async fn assignments_abort(
 
     let mut assignments = state.assignments.write().await;
 
-    let assignment = resolve_assignment(&mut assignments, &id).ok_or((
-        StatusCode::NOT_FOUND,
-        Json(serde_json::json!({"error": "Assignment not found"})),
-    ))?;
+    let assignment = resolve_assignment(&mut assignments, &id, &hint)?;
+    check_owner(&mut assignments, &assignment, request.steal)?;
 
     // Stop active work if running
     let _ = state
@@ -932,6 +1900,7 @@ async fn assignments_abort(
 
     let opts = toren_lib::AbortOptions {
         close_task: request.close_bead,
+        delete_task: request.delete_bead,
         segment_path: &segment_path,
         kill: request.kill,
         plugin_mgr: &state.rhai_plugins,
@@ -949,6 +1918,7 @@ async fn assignments_abort(
     Ok(Json(serde_json::json!({
         "success": true,
         "bead_closed": request.close_bead,
+        "bead_deleted": request.delete_bead && assignment.source.auto_created_bead(),
     })))
 }
 
@@ -963,6 +1933,12 @@ struct ResumeRequest {
     /// Optional agent override (e.g., "claude", "codex:o3"). Uses daemon default if unset.
     #[serde(default)]
     agent: Option<String>,
+    /// Tool permissions for this session. Uses `[ancillaries] permission_mode` if unset.
+    #[serde(default)]
+    permission_mode: Option<toren_lib::PermissionPolicy>,
+    /// Take the assignment over from a breq session that owns it
+    #[serde(default)]
+    steal: bool,
 }
 
 fn default_true() -> bool {
@@ -972,6 +1948,7 @@ fn default_true() -> bool {
 async fn assignments_resume(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(hint): Query<SegmentHint>,
     Json(request): Json<ResumeRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let ws_mgr = state.workspaces.as_ref().ok_or((
@@ -981,10 +1958,10 @@ async fn assignments_resume(
 
     let mut assignments = state.assignments.write().await;
 
-    let assignment = resolve_assignment(&mut assignments, &id).ok_or((
-        StatusCode::NOT_FOUND,
-        Json(serde_json::json!({"error": "Assignment not found"})),
-    ))?;
+    let assignment = resolve_assignment(&mut assignments, &id, &hint)?;
+    if request.start_work {
+        check_owner(&mut assignments, &assignment, request.steal)?;
+    }
 
     // Get segment path
     let segment_path = {
@@ -1006,15 +1983,12 @@ async fn assignments_resume(
         segment_path: &segment_path,
         segment_name: &assignment.segment,
         plugin_mgr: &state.rhai_plugins,
+        claim_identity: state.config.tasks.claim_identity.as_deref(),
     };
 
     let resume_result =
-        toren_lib::prepare_resume(&assignment, &mut assignments, ws_mgr, &opts).map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": e.to_string()})),
-            )
-        })?;
+        toren_lib::prepare_resume(&assignment, &mut assignments, ws_mgr, &opts)
+            .map_err(|e| workspace_create_error(&e))?;
 
     // Re-read the updated assignment (status may have changed)
     let updated_assignment = assignments.get(&assignment.id).cloned().ok_or((
@@ -1024,7 +1998,7 @@ async fn assignments_resume(
 
     // Resolve agent: per-request override or daemon default
     let agent = if let Some(ref agent_str) = request.agent {
-        Agent::parse(agent_str).map_err(|e| {
+        state.config.resolve_agent(Some(agent_str)).map_err(|e| {
             (
                 StatusCode::BAD_REQUEST,
                 Json(serde_json::json!({"error": e.to_string()})),
@@ -1033,6 +2007,12 @@ async fn assignments_resume(
     } else {
         (*state.agent).clone()
     };
+    let permission = request
+        .permission_mode
+        .unwrap_or(state.config.ancillaries.permission_mode);
+
+    // Starting work claims the assignment, which takes the lock again
+    drop(assignments);
 
     // Optionally start SDK work
     let work_started = if request.start_work {
@@ -1048,21 +2028,23 @@ async fn assignments_resume(
             let mut resume_assignment = updated_assignment.clone();
             resume_assignment.source = toren_lib::AssignmentSource::Prompt {
                 original_prompt: resume_result.prompt.clone(),
+                auto_created_bead: updated_assignment.source.auto_created_bead(),
             };
 
+            let options = work_options(&state, &resume_assignment, permission);
             match state
                 .work_manager
-                .start_work(assignment.ancillary_id.clone(), resume_assignment, &agent)
+                .start_work(assignment.ancillary_id.clone(), resume_assignment, &agent, options, request.steal)
                 .await
             {
                 Ok(_) => true,
                 Err(e) => {
-                    return Err((
+                    return Err(work_refused(&e).unwrap_or((
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Json(
                             serde_json::json!({"error": format!("Failed to start work: {}", e)}),
                         ),
-                    ));
+                    )));
                 }
             }
         }
@@ -1070,6 +2052,20 @@ async fn assignments_resume(
         false
     };
 
+    if resume_result.workspace_recreated {
+        state.events.publish(DaemonEvent::WorkspaceCreated {
+            segment: assignment.segment.clone(),
+            workspace: workspace_name(&assignment),
+            path: assignment.workspace_path.display().to_string(),
+        });
+    }
+    state.events.publish(DaemonEvent::AssignmentResumed {
+        assignment_id: assignment.id.clone(),
+        ancillary_id: assignment.ancillary_id.clone(),
+        workspace_recreated: resume_result.workspace_recreated,
+        work_started,
+    });
+
     Ok(Json(serde_json::json!({
         "success": true,
         "workspace_recreated": resume_result.workspace_recreated,
@@ -1090,14 +2086,12 @@ struct AssignmentActionRequest {
 async fn assignment_action(
     State(state): State<AppState>,
     Path((id, name)): Path<(String, String)>,
+    Query(hint): Query<SegmentHint>,
     Json(request): Json<AssignmentActionRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Resolve assignment to get segment context
     let mut assignments = state.assignments.write().await;
-    let assignment = resolve_assignment(&mut assignments, &id).ok_or((
-        StatusCode::NOT_FOUND,
-        Json(serde_json::json!({"error": "Assignment not found"})),
-    ))?;
+    let assignment = resolve_assignment(&mut assignments, &id, &hint)?;
     drop(assignments);
 
     // Check plugin exists
@@ -1148,6 +2142,9 @@ async fn assignment_action(
                     task_source,
                     prompt,
                     intent,
+                    after,
+                    group,
+                    ..
                 } => serde_json::json!({
                     "type": "do",
                     "task_id": task_id,
@@ -1156,6 +2153,8 @@ async fn assignment_action(
                     "task_source": task_source,
                     "prompt": prompt,
                     "intent": intent,
+                    "after": after,
+                    "group": group,
                 }),
             };
             Ok(Json(serde_json::json!({
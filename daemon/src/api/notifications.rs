@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use toren_lib::notifications::{Notification, Notifier, NotifyEvent};
+use toren_lib::SetupEvent;
+
+use super::AppState;
+use crate::events::DaemonEvent;
+
+// Notifications (`[notifications]`)
+
+/// Send a notification to the configured targets when work completes,
+/// fails or waits for input, and when a workspace setup action fails.
+/// Deliveries (with their retries) run off the event loop, so a slow
+/// target never holds up the others.
+pub fn spawn_notifier(state: AppState) {
+    if state.config.notifications.targets.is_empty() {
+        return;
+    }
+    let notifier = Arc::new(Notifier::new(&state.config.notifications));
+    let mut rx = state.events.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let Some(notification) = notification_for(&state, &event).await else {
+                continue;
+            };
+            if !notifier.wants(notification.event) {
+                continue;
+            }
+            let notifier = notifier.clone();
+            tokio::task::spawn_blocking(move || notifier.send(&notification));
+        }
+    });
+}
+
+async fn notification_for(state: &AppState, event: &DaemonEvent) -> Option<Notification> {
+    match event {
+        DaemonEvent::WorkStatusChanged { assignment_id, status, .. } => {
+            let event = match status.as_str() {
+                "completed" => NotifyEvent::WorkCompleted,
+                "failed" => NotifyEvent::WorkFailed,
+                "awaiting_input" => NotifyEvent::AwaitingInput,
+                _ => return None,
+            };
+            let assignment = state.assignments.write().await.get(assignment_id).cloned()?;
+            Some(Notification::for_assignment(event, &assignment))
+        }
+        DaemonEvent::WorkspaceSetupProgress {
+            segment,
+            workspace,
+            event:
+                SetupEvent::ActionFinished {
+                    label,
+                    ok: false,
+                    skipped: false,
+                    elapsed_ms,
+                    ..
+                },
+        } => Some(Notification {
+            segment: Some(segment.clone()),
+            ..Notification::new(
+                NotifyEvent::SetupFailed,
+                format!("Setup failed in {}/{}", segment, workspace),
+                format!("{} failed after {}s", label, elapsed_ms / 1000),
+            )
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use toren_lib::composite_status::last_session_event;
+use toren_lib::reaper::{agent_process_names, leftover_processes, ReapPolicy};
+use toren_lib::session::SessionLogs;
+use toren_lib::Assignment;
+
+use super::AppState;
+use crate::events::DaemonEvent;
+
+// Leftover process reaper
+
+/// How long terminated processes get to exit before SIGKILL.
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Periodically look for processes left running in the workspaces of agent
+/// sessions that ended (see [`toren_lib::reaper`]) and report or terminate
+/// them per `reap_policy`.
+pub fn spawn_reaper(state: AppState) {
+    let ancillaries = &state.config.ancillaries;
+    let interval = ancillaries.reap_interval_secs;
+    if interval == 0 || ancillaries.reap_policy == ReapPolicy::Off {
+        return;
+    }
+    let idle_after = Duration::from_secs(ancillaries.reap_idle_minutes * 60);
+    tokio::spawn(async move {
+        // Pids last reported per assignment, so a notification isn't repeated
+        // every pass while nothing changes
+        let mut reported: HashMap<String, Vec<i32>> = HashMap::new();
+        loop {
+            reap_all(&state, idle_after, &mut reported).await;
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+async fn reap_all(state: &AppState, idle_after: Duration, reported: &mut HashMap<String, Vec<i32>>) {
+    let active: Vec<Assignment> = state
+        .assignments
+        .write()
+        .await
+        .list_active()
+        .into_iter()
+        .cloned()
+        .collect();
+    reported.retain(|id, _| active.iter().any(|a| &a.id == id));
+
+    let policy = state.config.ancillaries.reap_policy;
+    let agent_names = agent_process_names(&state.config.ancillaries);
+    let logs = SessionLogs::from_config(&state.config);
+    for assignment in active {
+        let running = state
+            .work_manager
+            .has_active_work(&assignment.ancillary_id)
+            .await;
+        let (logs, agent_names) = (logs.clone(), agent_names.clone());
+        let workspace = assignment.workspace_path.clone();
+        let working_dir = assignment.working_dir();
+        let leftovers = tokio::task::spawn_blocking(move || {
+            if running || !workspace.exists() {
+                return None;
+            }
+            let event = last_session_event(&logs, &working_dir);
+            let processes = toren_lib::process::find_workspace_processes(&workspace);
+            leftover_processes(&processes, event, running, &agent_names, idle_after, SystemTime::now())
+        })
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+        if leftovers.is_empty() {
+            reported.remove(&assignment.id);
+            continue;
+        }
+        let pids: Vec<i32> = leftovers.iter().map(|p| p.pid).collect();
+        if reported.get(&assignment.id) == Some(&pids) {
+            continue;
+        }
+        let names: Vec<String> = leftovers.iter().map(|p| p.to_string()).collect();
+
+        let terminated = policy == ReapPolicy::Terminate;
+        if terminated {
+            let processes = leftovers.clone();
+            match tokio::task::spawn_blocking(move || {
+                toren_lib::process::terminate_processes(&processes, TERMINATE_TIMEOUT)
+            })
+            .await
+            {
+                Ok(Ok(())) => tracing::info!(
+                    "Terminated processes left by {}'s ended session: {}",
+                    assignment.ancillary_id,
+                    names.join(", ")
+                ),
+                Ok(Err(e)) => {
+                    tracing::warn!("Failed to terminate processes left by {}: {:#}", assignment.ancillary_id, e);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to terminate processes left by {}: {}", assignment.ancillary_id, e);
+                    continue;
+                }
+            }
+        } else {
+            tracing::warn!(
+                "{}'s agent session ended but left processes running: {}",
+                assignment.ancillary_id,
+                names.join(", ")
+            );
+            reported.insert(assignment.id.clone(), pids);
+        }
+        state.events.publish(DaemonEvent::LeftoverProcesses {
+            assignment_id: assignment.id.clone(),
+            ancillary_id: assignment.ancillary_id.clone(),
+            processes: leftovers,
+            terminated,
+        });
+    }
+}
@@ -0,0 +1,141 @@
+//! Request correlation IDs.
+//!
+//! Every API request carries an `x-request-id`: a client-supplied ID is kept,
+//! otherwise the daemon generates one. The ID is recorded on the request's
+//! tracing span (so every log line emitted while handling it is tagged),
+//! echoed in the response header, and added to JSON error bodies, letting a
+//! failure reported by a client be matched to the daemon's logs.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, Response},
+    middleware::{self, Next},
+    Router,
+};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::Level;
+
+/// Header carrying the correlation ID on requests and responses.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Wrap `router` with ID assignment, request tracing and error annotation.
+pub fn layer<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(|req: &Request| {
+                        tracing::info_span!(
+                            "request",
+                            method = %req.method(),
+                            path = %req.uri().path(),
+                            request_id = %request_id(req.headers()).unwrap_or_default(),
+                        )
+                    })
+                    // One line per request at INFO doubles as the audit log
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(middleware::from_fn(annotate_errors)),
+    )
+}
+
+fn request_id(headers: &axum::http::HeaderMap) -> Option<&str> {
+    headers.get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok())
+}
+
+/// Add `request_id` to JSON object bodies of 4xx/5xx responses.
+async fn annotate_errors(req: Request, next: Next) -> Response<Body> {
+    let id = request_id(req.headers()).map(String::from);
+    let response = next.run(req).await;
+
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    let Some(id) = id.filter(|_| is_json && (status.is_client_error() || status.is_server_error()))
+    else {
+        return response;
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert("request_id".to_string(), serde_json::Value::String(id));
+            let json = serde_json::to_vec(&map).unwrap_or_else(|_| bytes.to_vec());
+            if let Ok(len) = HeaderValue::from_str(&json.len().to_string()) {
+                parts.headers.insert(header::CONTENT_LENGTH, len);
+            }
+            Body::from(json)
+        }
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::get, Json};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        layer(
+            Router::new()
+                .route("/ok", get(|| async { "ok" }))
+                .route(
+                    "/fail",
+                    get(|| async {
+                        (
+                            StatusCode::NOT_FOUND,
+                            Json(serde_json::json!({"error": "Assignment not found"})),
+                        )
+                    }),
+                ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_generates_and_echoes_request_id() {
+        let response = app()
+            .oneshot(Request::get("/ok").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(!id.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_error_body_includes_client_request_id() {
+        let response = app()
+            .oneshot(
+                Request::get("/fail")
+                    .header(REQUEST_ID_HEADER, "breq-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "breq-123");
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "Assignment not found");
+        assert_eq!(body["request_id"], "breq-123");
+    }
+}
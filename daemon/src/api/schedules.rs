@@ -0,0 +1,243 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use toren_lib::Schedule;
+
+use super::{create_assignment, work_options, AppState, CreateAssignmentRequest};
+
+// Schedule API
+
+#[derive(Debug, Deserialize)]
+pub struct CreateScheduleRequest {
+    pub cron: String,
+    pub segment: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub task_source: Option<String>,
+    #[serde(default)]
+    pub agent: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleView {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+impl From<Schedule> for ScheduleView {
+    fn from(schedule: Schedule) -> Self {
+        let next_run = schedule.next_run();
+        Self { schedule, next_run }
+    }
+}
+
+pub async fn schedules_list(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let schedules: Vec<ScheduleView> = state
+        .schedules
+        .read()
+        .await
+        .list()
+        .iter()
+        .cloned()
+        .map(ScheduleView::from)
+        .collect();
+    Json(serde_json::json!({ "schedules": schedules }))
+}
+
+pub async fn schedules_create(
+    State(state): State<AppState>,
+    Json(request): Json<CreateScheduleRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if request.prompt.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Schedule prompt is empty"})),
+        ));
+    }
+    let segment_exists = state
+        .segments
+        .read()
+        .unwrap()
+        .find_by_name(&request.segment)
+        .is_some();
+    if !segment_exists {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("Segment not found: {}", request.segment)})),
+        ));
+    }
+    if let Some(ref agent) = request.agent {
+        state.config.resolve_agent(Some(agent)).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    }
+
+    let schedule = state
+        .schedules
+        .write()
+        .await
+        .add(
+            &request.cron,
+            &request.segment,
+            &request.prompt,
+            request.task_source,
+            request.agent,
+        )
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("{:#}", e)})),
+            )
+        })?;
+    tracing::info!("Added schedule {} ({}) for {}", schedule.id, schedule.cron, schedule.segment);
+    Ok(Json(serde_json::json!({ "schedule": ScheduleView::from(schedule) })))
+}
+
+pub async fn schedules_remove(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let removed = state.schedules.write().await.remove(&id).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": format!("{:#}", e)})),
+        )
+    })?;
+    if !removed {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("Schedule not found: {}", id)})),
+        ));
+    }
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+// Scheduler
+
+/// Check schedules at the top of every minute and run the due ones.
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            let wait = 60 - u64::from(Utc::now().second());
+            tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+
+            let now = Utc::now();
+            let due = state.schedules.read().await.due(now);
+            for schedule in due {
+                let outcome = run_schedule(&state, &schedule).await;
+                match outcome {
+                    Ok(ref id) => {
+                        tracing::info!("Schedule {} started assignment {}", schedule.id, id)
+                    }
+                    Err(ref e) => tracing::warn!("Schedule {} skipped: {}", schedule.id, e),
+                }
+                if let Err(e) = state
+                    .schedules
+                    .write()
+                    .await
+                    .record_run(&schedule.id, now, outcome)
+                {
+                    tracing::warn!("Failed to record run of schedule {}: {:#}", schedule.id, e);
+                }
+            }
+        }
+    });
+}
+
+/// Create and start one scheduled assignment, unless a concurrency cap says
+/// not to. Returns the assignment ID, or why no assignment was started.
+async fn run_schedule(state: &AppState, schedule: &Schedule) -> Result<String, String> {
+    let ws_mgr = state
+        .workspaces
+        .as_ref()
+        .ok_or("workspace_root not configured")?;
+    let segment_path = state
+        .segments
+        .read()
+        .unwrap()
+        .find_by_name(&schedule.segment)
+        .map(|s| s.path.clone())
+        .ok_or_else(|| format!("Segment not found: {}", schedule.segment))?;
+
+    {
+        let mut assignments = state.assignments.write().await;
+
+        // One run at a time: overlapping runs of a slow job pile up
+        if let Some(ref previous) = schedule.last_assignment_id {
+            if assignments.get(previous).is_some() {
+                return Err(format!("previous run ({}) is still active", previous));
+            }
+        }
+
+        // Don't grow the segment past its ancillary pool
+        let max = state
+            .segments
+            .read()
+            .unwrap()
+            .settings(&schedule.segment)
+            .max_per_segment;
+        let existing = ws_mgr.list_workspaces(&segment_path).unwrap_or_default();
+        let next = assignments.next_available_ancillary(&schedule.segment, max, &existing);
+        if toren_lib::ancillary_number(&next).is_some_and(|n| n > max) {
+            return Err(format!(
+                "segment {} has no free ancillary (max_per_segment = {})",
+                schedule.segment, max
+            ));
+        }
+    }
+
+    let agent = match schedule.agent {
+        Some(ref agent) => state
+            .config
+            .resolve_agent(Some(agent))
+            .map_err(|e| e.to_string())?,
+        None => (*state.agent).clone(),
+    };
+
+    let request = CreateAssignmentRequest {
+        task_id: None,
+        prompt: Some(schedule.prompt.clone()),
+        task_title: None,
+        task_url: None,
+        task_source: schedule.task_source.clone(),
+        segment: schedule.segment.clone(),
+        overflow: false,
+        intent: None,
+        after: None,
+    };
+    let assignment = create_assignment(state, request)
+        .await
+        .map_err(|(_, Json(body))| {
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("Failed to create assignment")
+                .to_string()
+        })?;
+
+    let options = work_options(state, &assignment, state.config.ancillaries.permission_mode);
+    state
+        .work_manager
+        .start_work(
+            assignment.ancillary_id.clone(),
+            assignment.clone(),
+            &agent,
+            options,
+            false,
+        )
+        .await
+        .map_err(|e| {
+            format!(
+                "assignment {} created but the agent failed to start: {}",
+                assignment.ancillary_id, e
+            )
+        })?;
+    Ok(assignment.id)
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use super::AppState;
+
+// Graceful shutdown
+
+/// Wait for Ctrl-C or SIGTERM, then drain before the server stops: refuse
+/// new work, interrupt running work and wait up to
+/// `server.shutdown_timeout_secs` for it to stop, remove agent-registered
+/// proxy routes if `server.forget_agent_routes_on_shutdown`, and save the
+/// assignment state. A second signal stops waiting.
+pub async fn drain_on_signal(state: AppState) {
+    wait_for_signal().await;
+    let timeout = Duration::from_secs(state.config.server.shutdown_timeout_secs);
+    tracing::info!("Shutting down; draining active work (timeout {}s)", timeout.as_secs());
+
+    tokio::select! {
+        _ = drain(&state, timeout) => tracing::info!("Shutdown drain complete"),
+        _ = wait_for_signal() => tracing::warn!("Second signal received; exiting without draining"),
+    }
+}
+
+async fn drain(state: &AppState, timeout: Duration) {
+    let still_running = state.work_manager.drain(timeout).await;
+    if !still_running.is_empty() {
+        tracing::warn!(
+            "Work still running after {}s, exiting anyway: {}",
+            timeout.as_secs(),
+            still_running.join(", ")
+        );
+    }
+
+    if state.config.server.forget_agent_routes_on_shutdown {
+        let routes: Vec<(String, String)> = state.agent_routes.lock().unwrap().drain(..).collect();
+        let backend = state.config.proxy.backend.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            for (name, domain) in routes {
+                match toren_lib::proxy_routes::forget(&name, &domain, Some(&backend)) {
+                    Ok(()) => tracing::info!("Removed agent route {}.{}", name, domain),
+                    Err(e) => tracing::warn!("Failed to remove agent route {}.{}: {:#}", name, domain, e),
+                }
+            }
+        })
+        .await;
+    }
+
+    if let Err(e) = state.assignments.write().await.save() {
+        tracing::warn!("Failed to save assignments: {:#}", e);
+    }
+}
+
+async fn wait_for_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use toren_lib::CompositeStatus;
+
+use crate::events::EventBus;
+
+// Composite status cache
+
+type Slot = Arc<tokio::sync::Mutex<Option<(Instant, CompositeStatus)>>>;
+
+/// Composite status per assignment, shared by concurrent requests.
+///
+/// Computing a status shells out to jj and the task tracker, so a client
+/// polling `/api/assignments` can spawn dozens of processes per request.
+/// Requests for the same assignment wait on a single in-flight computation,
+/// and its result is reused for `min_interval` or until a lifecycle event
+/// about the assignment (see [`spawn_invalidator`]).
+pub struct StatusCache {
+    min_interval: Duration,
+    slots: Mutex<HashMap<String, Slot>>,
+}
+
+impl StatusCache {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The cached status of an assignment, or the result of `compute` when
+    /// it is missing or older than `min_interval`.
+    pub async fn get_or_compute<F, Fut>(&self, assignment_id: &str, compute: F) -> CompositeStatus
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = CompositeStatus>,
+    {
+        let slot = self
+            .slots
+            .lock()
+            .unwrap()
+            .entry(assignment_id.to_string())
+            .or_default()
+            .clone();
+
+        // Held across the computation so concurrent callers wait for it
+        let mut cached = slot.lock().await;
+        if let Some((at, status)) = cached.as_ref() {
+            if at.elapsed() < self.min_interval {
+                return status.clone();
+            }
+        }
+        let status = compute().await;
+        *cached = Some((Instant::now(), status.clone()));
+        status
+    }
+
+    /// Forget an assignment's cached status, so the next request recomputes it.
+    pub fn invalidate(&self, assignment_id: &str) {
+        self.slots.lock().unwrap().remove(assignment_id);
+    }
+
+    /// Forget every cached status.
+    pub fn clear(&self) {
+        self.slots.lock().unwrap().clear();
+    }
+
+    /// Drop cached statuses of assignments not in `active_ids`.
+    pub fn retain(&self, active_ids: &[&str]) {
+        self.slots
+            .lock()
+            .unwrap()
+            .retain(|id, _| active_ids.contains(&id.as_str()));
+    }
+}
+
+/// Invalidate an assignment's cached status whenever an event about it is
+/// published (create, complete, abort, resume, work status, health), so a
+/// long `min_interval` never hides a lifecycle change.
+pub fn spawn_invalidator(cache: Arc<StatusCache>, events: &EventBus) {
+    let mut rx = events.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if let Some(id) = event.assignment_id() {
+                        cache.invalidate(id);
+                    }
+                }
+                // Missed events may have been about any assignment
+                Err(RecvError::Lagged(_)) => cache.clear(),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn status(activity: &str) -> CompositeStatus {
+        CompositeStatus {
+            agent_activity: activity.to_string(),
+            has_changes: false,
+            has_conflicts: false,
+            task_status: "open".to_string(),
+            task_assignee: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_share_one_computation() {
+        let cache = Arc::new(StatusCache::new(Duration::from_secs(60)));
+        let computed = Arc::new(AtomicUsize::new(0));
+
+        let requests = (0..8).map(|_| {
+            let cache = cache.clone();
+            let computed = computed.clone();
+            tokio::spawn(async move {
+                cache
+                    .get_or_compute("a1", || async move {
+                        computed.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        status("idle")
+                    })
+                    .await
+            })
+        });
+        for request in futures::future::join_all(requests).await {
+            assert_eq!(request.unwrap().agent_activity, "idle");
+        }
+        assert_eq!(computed.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recomputes_after_interval() {
+        let cache = StatusCache::new(Duration::ZERO);
+        cache.get_or_compute("a1", || async { status("idle") }).await;
+        let second = cache.get_or_compute("a1", || async { status("busy") }).await;
+        assert_eq!(second.agent_activity, "busy");
+
+        cache.retain(&[]);
+        assert!(cache.slots.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_events_invalidate() {
+        let cache = Arc::new(StatusCache::new(Duration::from_secs(60)));
+        let events = EventBus::new();
+        spawn_invalidator(cache.clone(), &events);
+
+        cache.get_or_compute("a1", || async { status("idle") }).await;
+        cache.get_or_compute("a2", || async { status("idle") }).await;
+        events.publish(crate::events::DaemonEvent::WorkStatusChanged {
+            ancillary_id: "Toren One".parse().unwrap(),
+            assignment_id: "a1".to_string(),
+            status: "working".to_string(),
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let a1 = cache.get_or_compute("a1", || async { status("busy") }).await;
+        let a2 = cache.get_or_compute("a2", || async { status("busy") }).await;
+        assert_eq!(a1.agent_activity, "busy");
+        assert_eq!(a2.agent_activity, "idle");
+    }
+}
@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use toren_lib::assignment_store::HistoryFilter;
+use toren_lib::session::SessionLogs;
+use toren_lib::usage::{assignment_usage, UsageSummary};
+
+use super::AppState;
+use crate::events::DaemonEvent;
+
+// Token usage and cost accounting
+
+/// Record an assignment's token usage from its session logs whenever the
+/// daemon-run agent's work completes or fails, so `breq history` and
+/// `GET /api/usage` include it once the assignment is finished.
+pub fn spawn_usage_recorder(state: AppState) {
+    let mut rx = state.events.subscribe();
+    let logs = SessionLogs::from_config(&state.config);
+    tokio::spawn(async move {
+        loop {
+            let assignment_id = match rx.recv().await {
+                Ok(DaemonEvent::WorkStatusChanged { assignment_id, status, .. })
+                    if status == "completed" || status.starts_with("failed") =>
+                {
+                    assignment_id
+                }
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let Some(assignment) = state.assignments.write().await.get(&assignment_id).cloned() else {
+                continue;
+            };
+            let logs = logs.clone();
+            let usage = tokio::task::spawn_blocking(move || assignment_usage(&logs, &assignment))
+                .await
+                .ok()
+                .flatten();
+            if let Some(usage) = usage {
+                if let Err(e) = state.assignments.write().await.set_usage(&assignment_id, usage) {
+                    tracing::warn!("Failed to record token usage for {}: {:#}", assignment_id, e);
+                }
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    segment: Option<String>,
+}
+
+/// Token usage and estimated cost summed over finished and active
+/// assignments, with a breakdown per segment.
+pub async fn usage_summary(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageSummary>, (StatusCode, Json<serde_json::Value>)> {
+    let mut assignments = state.assignments.write().await;
+    let filter = HistoryFilter {
+        segment: query.segment.clone(),
+        limit: None,
+    };
+    let history = assignments.recent_history(&filter).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+    })?;
+    let active: Vec<_> = assignments
+        .list_active()
+        .into_iter()
+        .filter(|a| query.segment.as_ref().is_none_or(|s| &a.segment == s))
+        .collect();
+    Ok(Json(UsageSummary::from_records(&history, &active)))
+}
@@ -1,11 +1,14 @@
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, StreamExt};
+use tokio::sync::broadcast;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use tracing::{error, info, warn};
 
 use super::AppState;
 use crate::ancillary::AncillaryStatus;
+use crate::events::DaemonEvent;
 use crate::services::command::CommandRequest;
 use toren_lib::tasks;
 
@@ -31,6 +34,18 @@ enum WsRequest {
     Command {
         request: CommandRequest,
     },
+    /// Run a command plugin command, streaming its output. Commands that
+    /// aren't `auto_approve` answer `ConfirmationRequired` until re-sent with
+    /// `confirmed: true`.
+    PluginExecute {
+        command_id: String,
+        #[serde(default)]
+        params: HashMap<String, String>,
+        #[serde(default)]
+        cwd: Option<String>,
+        #[serde(default)]
+        confirmed: bool,
+    },
     FileRead {
         path: String,
     },
@@ -49,7 +64,7 @@ enum WsResponse {
         #[serde(skip_serializing_if = "Option::is_none")]
         assignment_id: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
-        task_id: Option<String>,
+        task_id: Option<toren_lib::TaskId>,
         #[serde(skip_serializing_if = "Option::is_none")]
         working_dir: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -61,6 +76,12 @@ enum WsResponse {
     CommandOutput {
         output: crate::services::command::CommandOutput,
     },
+    ConfirmationRequired {
+        command_id: String,
+        label: String,
+        /// The command line that would run
+        command: String,
+    },
     FileContent {
         content: String,
     },
@@ -70,17 +91,40 @@ enum WsResponse {
     Error {
         message: String,
     },
+    /// Daemon-wide lifecycle event, pushed to every authenticated client
+    Event {
+        event: DaemonEvent,
+    },
 }
 
 pub async fn handle_websocket(socket: WebSocket, state: AppState) {
     let (mut sender, mut receiver) = socket.split();
     let mut authenticated = false;
+    // Read-only sessions observe but can't run commands
+    let mut read_only = false;
     let mut ancillary_id: Option<String> = None;
     let mut _assignment_id: Option<String> = None;
+    // Subscribed to daemon events once the client authenticates
+    let mut events_rx: Option<broadcast::Receiver<DaemonEvent>> = None;
 
     info!("New WebSocket connection");
 
-    while let Some(msg) = receiver.next().await {
+    loop {
+        let msg = tokio::select! {
+            msg = receiver.next() => msg,
+            Some(event) = next_event(&mut events_rx) => {
+                let response = WsResponse::Event { event };
+                if let Ok(json) = serde_json::to_string(&response) {
+                    if sender.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                continue;
+            }
+        };
+        let Some(msg) = msg else {
+            break;
+        };
         let msg = match msg {
             Ok(msg) => msg,
             Err(e) => {
@@ -112,6 +156,8 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
                     }
 
                     authenticated = true;
+                    read_only = state.security.is_read_only(Some(&token));
+                    events_rx = Some(state.events.subscribe());
 
                     // Try to connect via assignment first
                     if let Some(ref id) = aid {
@@ -276,6 +322,16 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
 
                     info!("WebSocket authenticated (legacy mode)");
                 }
+                Ok(WsRequest::Command { .. } | WsRequest::PluginExecute { .. })
+                    if authenticated && read_only =>
+                {
+                    let response = WsResponse::Error {
+                        message: "Read-only session: commands are not allowed".to_string(),
+                    };
+                    if let Ok(json) = serde_json::to_string(&response) {
+                        let _ = sender.send(Message::Text(json)).await;
+                    }
+                }
                 Ok(req) if authenticated => {
                     handle_authenticated_request(req, &state, &mut sender, ancillary_id.as_deref())
                         .await;
@@ -311,17 +367,36 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
     info!("WebSocket connection closed");
 }
 
+/// Wait for the next daemon event. Pends forever before authentication,
+/// and returns None once the bus is closed.
+async fn next_event(rx: &mut Option<broadcast::Receiver<DaemonEvent>>) -> Option<DaemonEvent> {
+    let Some(rx) = rx else {
+        return std::future::pending().await;
+    };
+    loop {
+        match rx.recv().await {
+            Ok(event) => return Some(event),
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!("WebSocket client lagged, skipped {} events", n);
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
 /// Connect using an existing assignment
 async fn connect_via_assignment(
     state: &AppState,
     ancillary_id: &str,
     session_token: &str,
 ) -> Result<(WsResponse, String, String), Option<String>> {
+    // An ID that isn't an ancillary name can't have an assignment
+    let parsed_id = toren_lib::AncillaryId::parse(ancillary_id).map_err(|_| None)?;
     let mut assignments = state.assignments.write().await;
 
     // Look up active assignment for this ancillary
     let assignment = assignments
-        .get_active_for_ancillary(ancillary_id)
+        .get_active_for_ancillary(&parsed_id)
         .cloned()
         .ok_or(None)?; // None = no assignment found, fall through to legacy
 
@@ -364,7 +439,7 @@ async fn connect_via_assignment(
         ancillary_id.to_string(),
         assignment.segment.clone(),
         session_token.to_string(),
-        assignment.task_id.clone(),
+        assignment.task_id.as_ref().map(|id| id.to_string()),
         working_dir.clone(),
     );
 
@@ -420,37 +495,31 @@ async fn handle_authenticated_request(
         WsRequest::Auth { .. } => unreachable!(),
 
         WsRequest::Command { request } => {
-            // Update status to Executing
-            if let Some(id) = ancillary_id {
-                state
-                    .ancillaries
-                    .update_status(id, AncillaryStatus::Executing);
-            }
+            stream_command(request, state, sender, ancillary_id).await;
+        }
 
-            match state.services.command.execute(request).await {
-                Ok(mut rx) => {
-                    while let Some(output) = rx.recv().await {
-                        let response = WsResponse::CommandOutput { output };
-                        if let Ok(json) = serde_json::to_string(&response) {
-                            if sender.send(Message::Text(json)).await.is_err() {
-                                break;
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    let response = WsResponse::Error {
-                        message: e.to_string(),
-                    };
-                    if let Ok(json) = serde_json::to_string(&response) {
-                        let _ = sender.send(Message::Text(json)).await;
-                    }
+        WsRequest::PluginExecute {
+            command_id,
+            params,
+            cwd,
+            confirmed,
+        } => {
+            let response = match state.plugins.prepare(&command_id, &params, cwd) {
+                Ok(prepared) if prepared.auto_approve || confirmed => {
+                    stream_command(prepared.request, state, sender, ancillary_id).await;
+                    return;
                 }
-            }
-
-            // Update status back to Idle
-            if let Some(id) = ancillary_id {
-                state.ancillaries.update_status(id, AncillaryStatus::Idle);
+                Ok(prepared) => WsResponse::ConfirmationRequired {
+                    command_id,
+                    label: prepared.label,
+                    command: prepared.display,
+                },
+                Err(e) => WsResponse::Error {
+                    message: e.to_string(),
+                },
+            };
+            if let Ok(json) = serde_json::to_string(&response) {
+                let _ = sender.send(Message::Text(json)).await;
             }
         }
 
@@ -495,3 +564,44 @@ async fn handle_authenticated_request(
         }
     }
 }
+
+/// Run a command, streaming its output as `CommandOutput` messages.
+async fn stream_command(
+    request: CommandRequest,
+    state: &AppState,
+    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    ancillary_id: Option<&str>,
+) {
+    // Update status to Executing
+    if let Some(id) = ancillary_id {
+        state
+            .ancillaries
+            .update_status(id, AncillaryStatus::Executing);
+    }
+
+    match state.services.command.execute(request).await {
+        Ok(mut rx) => {
+            while let Some(output) = rx.recv().await {
+                let response = WsResponse::CommandOutput { output };
+                if let Ok(json) = serde_json::to_string(&response) {
+                    if sender.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            let response = WsResponse::Error {
+                message: e.to_string(),
+            };
+            if let Ok(json) = serde_json::to_string(&response) {
+                let _ = sender.send(Message::Text(json)).await;
+            }
+        }
+    }
+
+    // Update status back to Idle
+    if let Some(id) = ancillary_id {
+        state.ancillaries.update_status(id, AncillaryStatus::Idle);
+    }
+}
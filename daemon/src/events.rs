@@ -0,0 +1,243 @@
+//! Daemon-wide event bus.
+//!
+//! Lifecycle changes (assignments, workspaces, work status) are published here
+//! and fanned out to every authenticated `/ws` client so UIs can live-update
+//! without polling. Assignment create/complete/abort events come from
+//! toren_lib's lifecycle observers, so they're published whichever code path
+//! (API handler, scheduler, plugin) performed the operation. Workspace setup
+//! reports its progress here too, action by action.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use toren_lib::hooks::LifecycleObserver;
+use std::path::Path;
+use toren_lib::reaper::IdleAction;
+use toren_lib::{
+    AncillaryId, Assignment, AssignmentHealth, HookPayload, ProcessInfo, SetupEvent, SetupProgress, TaskId,
+};
+
+/// Number of events buffered per subscriber before slow clients start lagging
+const EVENT_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    AssignmentCreated {
+        assignment: Box<Assignment>,
+    },
+    AssignmentCompleted {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revision: Option<String>,
+        pushed: bool,
+    },
+    AssignmentAborted {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+    },
+    AssignmentResumed {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+        workspace_recreated: bool,
+        work_started: bool,
+    },
+    WorkspaceCreated {
+        segment: String,
+        workspace: String,
+        path: String,
+    },
+    WorkspaceCleaned {
+        segment: String,
+        workspace: String,
+    },
+    /// A step of a workspace's setup (toren.kdl actions)
+    WorkspaceSetupProgress {
+        segment: String,
+        workspace: String,
+        event: SetupEvent,
+    },
+    WorkStatusChanged {
+        ancillary_id: AncillaryId,
+        assignment_id: String,
+        status: String,
+    },
+    AssignmentHealthChanged {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+        health: AssignmentHealth,
+    },
+    LeftoverProcesses {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+        processes: Vec<ProcessInfo>,
+        terminated: bool,
+    },
+    AssignmentAutoFinalized {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+        /// `completed` or `timeout`
+        reason: String,
+        report_posted: bool,
+    },
+    AssignmentUnblocked {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+        /// The task whose completion unblocked it
+        after: TaskId,
+        work_started: bool,
+    },
+    AssignmentIdle {
+        assignment_id: String,
+        ancillary_id: AncillaryId,
+        idle_days: u64,
+        /// `flag` or `abort`; with `dry_run`, the abort didn't happen
+        action: IdleAction,
+        dry_run: bool,
+        /// The aborted workspace's revision
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revision: Option<String>,
+    },
+}
+
+impl DaemonEvent {
+    /// The assignment the event is about, if any.
+    pub fn assignment_id(&self) -> Option<&str> {
+        match self {
+            DaemonEvent::AssignmentCreated { assignment } => Some(&assignment.id),
+            DaemonEvent::AssignmentCompleted { assignment_id, .. }
+            | DaemonEvent::AssignmentAborted { assignment_id, .. }
+            | DaemonEvent::AssignmentResumed { assignment_id, .. }
+            | DaemonEvent::WorkStatusChanged { assignment_id, .. }
+            | DaemonEvent::AssignmentHealthChanged { assignment_id, .. }
+            | DaemonEvent::LeftoverProcesses { assignment_id, .. }
+            | DaemonEvent::AssignmentAutoFinalized { assignment_id, .. }
+            | DaemonEvent::AssignmentUnblocked { assignment_id, .. }
+            | DaemonEvent::AssignmentIdle { assignment_id, .. } => Some(assignment_id),
+            DaemonEvent::WorkspaceCreated { .. }
+            | DaemonEvent::WorkspaceCleaned { .. }
+            | DaemonEvent::WorkspaceSetupProgress { .. } => None,
+        }
+    }
+}
+
+/// Broadcast channel for [`DaemonEvent`]s. Cheap to clone.
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<DaemonEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { tx }
+    }
+
+    /// Publish an event. Dropped silently when nobody is listening.
+    pub fn publish(&self, event: DaemonEvent) {
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DaemonEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl EventBus {
+    fn publish_workspace_cleaned(&self, assignment: &Assignment) {
+        let workspace = assignment
+            .workspace_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string();
+        self.publish(DaemonEvent::WorkspaceCleaned {
+            segment: assignment.segment.clone(),
+            workspace,
+        });
+    }
+}
+
+impl SetupProgress for EventBus {
+    fn on_setup_event(&self, workspace_path: &Path, event: &SetupEvent) {
+        let name = |p: Option<&Path>| {
+            p.and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        self.publish(DaemonEvent::WorkspaceSetupProgress {
+            segment: name(workspace_path.parent()),
+            workspace: name(Some(workspace_path)),
+            event: event.clone(),
+        });
+    }
+}
+
+impl LifecycleObserver for EventBus {
+    fn on_create(&self, payload: &HookPayload) {
+        self.publish(DaemonEvent::AssignmentCreated {
+            assignment: Box::new(payload.assignment.clone()),
+        });
+    }
+
+    fn on_complete(&self, payload: &HookPayload) {
+        self.publish_workspace_cleaned(payload.assignment);
+        self.publish(DaemonEvent::AssignmentCompleted {
+            assignment_id: payload.assignment.id.clone(),
+            ancillary_id: payload.assignment.ancillary_id.clone(),
+            revision: payload.revision.clone(),
+            pushed: payload.pushed.unwrap_or(false),
+        });
+    }
+
+    fn on_abort(&self, payload: &HookPayload) {
+        self.publish_workspace_cleaned(payload.assignment);
+        self.publish(DaemonEvent::AssignmentAborted {
+            assignment_id: payload.assignment.id.clone(),
+            ancillary_id: payload.assignment.ancillary_id.clone(),
+        });
+    }
+
+    // Resume is published by the resume handler, which also knows whether
+    // work was started
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_fans_out_to_subscribers() {
+        let bus = EventBus::new();
+        let mut a = bus.subscribe();
+        let mut b = bus.subscribe();
+
+        bus.publish(DaemonEvent::WorkspaceCleaned {
+            segment: "toren".to_string(),
+            workspace: "one".to_string(),
+        });
+
+        for rx in [&mut a, &mut b] {
+            let event = rx.recv().await.unwrap();
+            let json = serde_json::to_value(&event).unwrap();
+            assert_eq!(json["kind"], "workspace_cleaned");
+            assert_eq!(json["workspace"], "one");
+        }
+    }
+
+    #[test]
+    fn test_publish_without_subscribers_is_noop() {
+        let bus = EventBus::new();
+        bus.publish(DaemonEvent::AssignmentAborted {
+            assignment_id: "a1".to_string(),
+            ancillary_id: "Toren One".parse().unwrap(),
+        });
+    }
+}
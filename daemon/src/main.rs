@@ -1,16 +1,18 @@
 use anyhow::Result;
 use clap::Parser;
 use std::path::PathBuf;
-use tracing::{info, Level};
+use tracing::info;
 
 mod ancillary;
 mod api;
+mod events;
 mod plugins;
 mod security;
 mod services;
+mod telemetry;
 
 // Re-export from toren-lib for internal use
-use toren_lib::{AssignmentManager, Config, SegmentManager, WorkspaceManager};
+use toren_lib::{AssignmentManager, Config, ScheduleManager, SegmentManager, WorkspaceManager};
 
 #[derive(Parser)]
 #[command(name = "toren-daemon")]
@@ -23,8 +25,8 @@ struct Cli {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+    // Initialize tracing (and OpenTelemetry export, if enabled)
+    let telemetry = telemetry::init();
 
     let cli = Cli::parse();
 
@@ -33,6 +35,7 @@ async fn main() -> Result<()> {
     // Load configuration
     let config = Config::load_from(cli.config.as_deref())?;
     info!("Loaded configuration from: {}", config.config_path);
+    toren_lib::task_comments::TaskCommenter::register(&config);
 
     // Initialize security context
     let security_ctx = security::SecurityContext::new(&config)?;
@@ -78,6 +81,10 @@ async fn main() -> Result<()> {
     let assignment_manager = AssignmentManager::new()?;
     info!("Assignment manager initialized");
 
+    // Initialize schedule manager
+    let schedule_manager = ScheduleManager::new()?;
+    info!("Schedule manager initialized ({} schedules)", schedule_manager.list().len());
+
     // Initialize segment manager
     let segment_manager = SegmentManager::new(&config)?;
     info!("Segment manager initialized");
@@ -89,7 +96,14 @@ async fn main() -> Result<()> {
         "Workspace manager initialized with root: {}",
         workspace_root.display()
     );
-    let workspace_manager = Some(WorkspaceManager::new(workspace_root, local_domain));
+    let workspace_manager = Some(
+        WorkspaceManager::new(workspace_root, local_domain)
+            .with_proxy_backend(Some(config.proxy.backend.clone()))
+            .with_host_template(config.proxy.host_template.clone())
+            .with_segment_roots(config.segment_workspace_roots())
+            .with_context_file(config.ancillaries.context_file.clone())
+            .with_envrc(config.ancillaries.envrc),
+    );
 
     // Initialize work manager (for embedded ancillary runtime)
     let work_manager = ancillary::WorkManager::new();
@@ -112,6 +126,7 @@ async fn main() -> Result<()> {
         rhai_plugins,
         ancillary_manager,
         assignment_manager,
+        schedule_manager,
         segment_manager,
         workspace_manager,
         work_manager,
@@ -119,5 +134,6 @@ async fn main() -> Result<()> {
     )
     .await?;
 
+    telemetry.shutdown();
     Ok(())
 }
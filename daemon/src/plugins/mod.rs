@@ -2,7 +2,11 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use tracing::{info, warn};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::services::command::CommandRequest;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandSet {
@@ -28,25 +32,83 @@ pub struct CommandDef {
     pub requires_vcs: bool,
 }
 
+/// A command parameter. Without a default, a value must be given.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandParam {
     pub name: String,
+    /// `string`, `number` or `enum`
     #[serde(rename = "type")]
     pub param_type: String,
     pub prompt: String,
     #[serde(default)]
     pub default: Option<String>,
+    /// Allowed values of an `enum` parameter
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+impl CommandParam {
+    fn validate(&self, value: &str) -> Result<(), String> {
+        match self.param_type.as_str() {
+            "string" => Ok(()),
+            "number" => value
+                .trim()
+                .parse::<f64>()
+                .map(|_| ())
+                .map_err(|_| format!("'{}' must be a number, got '{}'", self.name, value)),
+            "enum" if self.options.iter().any(|o| o == value) => Ok(()),
+            "enum" => Err(format!(
+                "'{}' must be one of {}, got '{}'",
+                self.name,
+                self.options.join(", "),
+                value
+            )),
+            other => Err(format!("'{}' has unknown type '{}'", self.name, other)),
+        }
+    }
+}
+
+/// Why a plugin command can't be run.
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("Unknown command: {0}")]
+    NotFound(String),
+    #[error("Invalid parameters: {0}")]
+    InvalidParams(String),
 }
 
+/// A command with validated parameters, ready for the command service.
+#[derive(Debug)]
+pub struct PreparedCommand {
+    pub label: String,
+    pub set_name: String,
+    pub auto_approve: bool,
+    /// The command line, for confirmation prompts
+    pub display: String,
+    pub request: CommandRequest,
+}
+
+/// Command sets as loaded from the plugin directories.
+#[derive(Default)]
+struct Loaded {
+    sets: HashMap<String, CommandSet>,
+    /// Which file each set was loaded from
+    files: HashMap<PathBuf, String>,
+}
+
+/// YAML command sets from the plugin directories. [`Self::reload`] swaps in
+/// a freshly loaded generation at once, so requests never see a partial one;
+/// the daemon reloads when a file changes (see [`spawn_watcher`]) and on
+/// `POST /api/plugins/reload`.
 pub struct PluginManager {
-    command_sets: HashMap<String, CommandSet>,
+    loaded: RwLock<Loaded>,
     plugin_dirs: Vec<PathBuf>,
 }
 
 impl PluginManager {
     pub fn new() -> Self {
         Self {
-            command_sets: HashMap::new(),
+            loaded: RwLock::new(Loaded::default()),
             plugin_dirs: Vec::new(),
         }
     }
@@ -55,83 +117,288 @@ impl PluginManager {
         self.plugin_dirs.push(dir);
     }
 
-    pub fn load_all(&mut self) -> Result<()> {
-        for dir in &self.plugin_dirs.clone() {
+    pub fn load_all(&self) -> Result<()> {
+        for dir in &self.plugin_dirs {
             if !dir.exists() {
                 std::fs::create_dir_all(dir).context("Failed to create plugin directory")?;
-                continue;
             }
-
-            self.load_from_dir(dir)?;
         }
-
-        info!("Loaded {} command sets", self.command_sets.len());
+        let count = self.reload()?;
+        info!("Loaded {} command sets", count);
         Ok(())
     }
 
-    fn load_from_dir(&mut self, dir: &Path) -> Result<()> {
-        for entry in std::fs::read_dir(dir).context("Failed to read plugin directory")? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if path.extension().and_then(|s| s.to_str()) == Some("yaml")
-                || path.extension().and_then(|s| s.to_str()) == Some("yml")
-            {
-                match self.load_command_set(&path) {
+    /// Load every command set again and swap them in. A file that no longer
+    /// parses keeps its previous command set, so a half-saved edit doesn't
+    /// take commands away. Returns the number of command sets.
+    pub fn reload(&self) -> Result<usize> {
+        let previous = self.loaded.read().unwrap();
+        let mut next = Loaded::default();
+        for dir in self.plugin_dirs.iter().filter(|dir| dir.exists()) {
+            for path in command_set_files(dir)? {
+                let command_set = match load_command_set(&path) {
                     Ok(command_set) => {
-                        info!(
-                            "Loaded command set: {} from {}",
-                            command_set.name,
-                            path.display()
-                        );
-                        self.command_sets
-                            .insert(command_set.id.clone(), command_set);
+                        debug!("Loaded command set: {} from {}", command_set.name, path.display());
+                        command_set
                     }
                     Err(e) => {
-                        warn!("Failed to load command set from {}: {}", path.display(), e);
+                        warn!("Failed to load command set from {}: {:#}", path.display(), e);
+                        let kept = previous.files.get(&path).and_then(|id| previous.sets.get(id));
+                        match kept {
+                            Some(command_set) => command_set.clone(),
+                            None => continue,
+                        }
                     }
-                }
+                };
+                next.files.insert(path, command_set.id.clone());
+                next.sets.insert(command_set.id.clone(), command_set);
             }
         }
+        drop(previous);
 
-        Ok(())
+        let count = next.sets.len();
+        *self.loaded.write().unwrap() = next;
+        Ok(count)
     }
 
-    fn load_command_set(&self, path: &Path) -> Result<CommandSet> {
-        let content = std::fs::read_to_string(path).context("Failed to read command set file")?;
+    #[allow(dead_code)]
+    pub fn get_command_set(&self, id: &str) -> Option<CommandSet> {
+        self.loaded.read().unwrap().sets.get(id).cloned()
+    }
 
-        let command_set: CommandSet =
-            serde_yaml::from_str(&content).context("Failed to parse command set YAML")?;
+    pub fn list_command_sets(&self) -> Vec<CommandSet> {
+        self.loaded.read().unwrap().sets.values().cloned().collect()
+    }
 
-        Ok(command_set)
+    pub fn find_command(&self, command_id: &str) -> Option<(CommandSet, CommandDef)> {
+        let loaded = self.loaded.read().unwrap();
+        loaded.sets.values().find_map(|command_set| {
+            let cmd = command_set.commands.iter().find(|c| c.id == command_id)?;
+            Some((command_set.clone(), cmd.clone()))
+        })
     }
 
-    #[allow(dead_code)]
-    pub fn get_command_set(&self, id: &str) -> Option<&CommandSet> {
-        self.command_sets.get(id)
+    /// Validate `params` against the command's declared parameters (filling
+    /// in defaults) and build its request. The command is split into words
+    /// before parameters are substituted, so a value is always passed as part
+    /// of one argument, whatever it contains.
+    pub fn prepare(
+        &self,
+        command_id: &str,
+        params: &HashMap<String, String>,
+        cwd: Option<String>,
+    ) -> Result<PreparedCommand, CommandError> {
+        let (command_set, command_def) = self
+            .find_command(command_id)
+            .ok_or_else(|| CommandError::NotFound(command_id.to_string()))?;
+        let params = validate_params(&command_def.params, params).map_err(CommandError::InvalidParams)?;
+
+        let words = shlex::split(&command_def.command)
+            .filter(|words| !words.is_empty())
+            .ok_or_else(|| CommandError::InvalidParams(format!("'{}' has no valid command line", command_id)))?;
+        let mut words = words.into_iter().map(|word| interpolate(&word, &params));
+        let command = words.next().unwrap_or_default();
+        let args: Vec<String> = words.collect();
+        let display = shlex::try_join(std::iter::once(command.as_str()).chain(args.iter().map(String::as_str)))
+            .unwrap_or_else(|_| format!("{} {}", command, args.join(" ")));
+
+        Ok(PreparedCommand {
+            label: command_def.label.clone(),
+            set_name: command_set.name.clone(),
+            auto_approve: command_def.auto_approve,
+            display,
+            request: CommandRequest { command, args, cwd },
+        })
+    }
+}
+
+/// YAML files in a plugin directory, in path order.
+fn command_set_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).context("Failed to read plugin directory")? {
+        let path = entry?.path();
+        if is_command_set_file(&path) {
+            files.push(path);
+        }
     }
+    files.sort();
+    Ok(files)
+}
+
+fn is_command_set_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|s| s.to_str()), Some("yaml" | "yml"))
+}
+
+fn load_command_set(path: &Path) -> Result<CommandSet> {
+    let content = std::fs::read_to_string(path).context("Failed to read command set file")?;
+    serde_yaml::from_str(&content).context("Failed to parse command set YAML")
+}
+
+/// How long file events must settle before command sets are reloaded;
+/// editors write a file in several steps.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Reload command sets whenever a YAML file in a plugin directory is
+/// created, changed or removed. If the directories can't be watched, only
+/// `POST /api/plugins/reload` picks up changes.
+pub fn spawn_watcher(manager: Arc<PluginManager>) {
+    use notify::Watcher;
 
-    pub fn list_command_sets(&self) -> Vec<&CommandSet> {
-        self.command_sets.values().collect()
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let relevant = !event.kind.is_access() && event.paths.iter().any(|p| is_command_set_file(p));
+            if relevant {
+                let _ = tx.send(());
+            }
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Command set hot-reload disabled: {}", e);
+            return;
+        }
+    };
+    for dir in manager.plugin_dirs.iter().filter(|dir| dir.exists()) {
+        if let Err(e) = watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+            warn!("Failed to watch {} for command set changes: {}", dir.display(), e);
+        }
     }
 
-    pub fn find_command(&self, command_id: &str) -> Option<(&CommandSet, &CommandDef)> {
-        for command_set in self.command_sets.values() {
-            if let Some(cmd) = command_set.commands.iter().find(|c| c.id == command_id) {
-                return Some((command_set, cmd));
+    tokio::spawn(async move {
+        // Dropping the watcher stops it
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            let manager = manager.clone();
+            match tokio::task::spawn_blocking(move || manager.reload()).await {
+                Ok(Ok(count)) => info!("Reloaded {} command sets", count),
+                Ok(Err(e)) => warn!("Failed to reload command sets: {:#}", e),
+                Err(e) => warn!("Command set reload panicked: {}", e),
             }
         }
-        None
+    });
+}
+
+/// The parameter values to substitute: the given ones, validated, plus
+/// defaults for the rest.
+fn validate_params(
+    declared: &[CommandParam],
+    given: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    if let Some(unknown) = given.keys().find(|k| !declared.iter().any(|p| &p.name == *k)) {
+        return Err(format!("unknown parameter '{}'", unknown));
+    }
+    let mut values = HashMap::new();
+    for param in declared {
+        let value = given
+            .get(&param.name)
+            .or(param.default.as_ref())
+            .ok_or_else(|| format!("missing parameter '{}'", param.name))?;
+        param.validate(value)?;
+        values.insert(param.name.clone(), value.clone());
     }
+    Ok(values)
+}
+
+fn interpolate(word: &str, params: &HashMap<String, String>) -> String {
+    let mut result = word.to_string();
+    for (key, value) in params {
+        result = result.replace(&format!("{{{}}}", key), value);
+    }
+    result
+}
 
-    pub fn interpolate_command(&self, command: &str, params: &HashMap<String, String>) -> String {
-        let mut result = command.to_string();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for (key, value) in params {
-            let placeholder = format!("{{{}}}", key);
-            result = result.replace(&placeholder, value);
+    fn param(name: &str, param_type: &str, default: Option<&str>, options: &[&str]) -> CommandParam {
+        CommandParam {
+            name: name.to_string(),
+            param_type: param_type.to_string(),
+            prompt: String::new(),
+            default: default.map(String::from),
+            options: options.iter().map(|o| o.to_string()).collect(),
         }
+    }
+
+    #[test]
+    fn test_prepare_validates_and_keeps_values_in_one_argument() {
+        let manager = PluginManager::new();
+        let commit = CommandDef {
+            id: "commit".to_string(),
+            label: "Commit".to_string(),
+            command: "git commit -m \"{message}\" -n {count} --mode {mode}".to_string(),
+            params: vec![
+                param("message", "string", None, &[]),
+                param("count", "number", Some("1"), &[]),
+                param("mode", "enum", Some("fast"), &["fast", "slow"]),
+            ],
+            category: "commit".to_string(),
+            icon: None,
+            auto_approve: false,
+            requires_vcs: true,
+        };
+        manager.loaded.write().unwrap().sets.insert(
+            "git".to_string(),
+            CommandSet {
+                id: "git".to_string(),
+                name: "Git".to_string(),
+                vcs: None,
+                commands: vec![commit],
+            },
+        );
+
+        let params = HashMap::from([("message".to_string(), "fix it; rm -rf /".to_string())]);
+        let prepared = manager.prepare("commit", &params, None).unwrap();
+        assert!(!prepared.auto_approve);
+        assert_eq!(prepared.request.command, "git");
+        assert_eq!(
+            prepared.request.args,
+            vec!["commit", "-m", "fix it; rm -rf /", "-n", "1", "--mode", "fast"]
+        );
+        assert_eq!(prepared.display, "git commit -m 'fix it; rm -rf /' -n 1 --mode fast");
+
+        let invalid = |extra: &[(&str, &str)]| {
+            let mut params = params.clone();
+            params.extend(extra.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+            matches!(manager.prepare("commit", &params, None), Err(CommandError::InvalidParams(_)))
+        };
+        assert!(invalid(&[("count", "ten")]));
+        assert!(invalid(&[("mode", "medium")]));
+        assert!(invalid(&[("other", "x")]));
+        assert!(matches!(
+            manager.prepare("commit", &HashMap::new(), None),
+            Err(CommandError::InvalidParams(_))
+        ));
+        assert!(matches!(manager.prepare("push", &params, None), Err(CommandError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_reload_keeps_command_sets_that_stop_parsing() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = dir.path().join("git.yaml");
+        let set = |id: &str| format!("id: {}\nname: Git\ncommands:\n  - {{ id: status, label: Status, command: git status, category: vcs }}\n", id);
+        std::fs::write(&git, set("git")).unwrap();
+
+        let mut manager = PluginManager::new();
+        manager.add_plugin_dir(dir.path().to_path_buf());
+        manager.load_all().unwrap();
+        assert!(manager.find_command("status").is_some());
+
+        std::fs::write(&git, "id: [unterminated").unwrap();
+        assert_eq!(manager.reload().unwrap(), 1);
+        assert!(manager.get_command_set("git").is_some());
+
+        std::fs::write(&git, set("vcs")).unwrap();
+        manager.reload().unwrap();
+        assert!(manager.get_command_set("git").is_none());
+        assert!(manager.get_command_set("vcs").is_some());
 
-        result
+        std::fs::remove_file(&git).unwrap();
+        assert_eq!(manager.reload().unwrap(), 0);
     }
 }
@@ -3,6 +3,8 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::io::Write;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
@@ -15,16 +17,42 @@ pub struct Session {
     pub id: String,
     pub token: String,
     pub created_at: String, // ISO 8601 timestamp
+    /// Paired for viewing only: mutating API calls are refused
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// What an agent token may act on: the one assignment it was issued for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentScope {
+    pub assignment_id: String,
+}
+
+/// A short-lived token handed to an agent the daemon runs, so tools it
+/// starts can call `/api/agent/*` for its own assignment. Kept in memory
+/// only: a daemon restart ends every work session anyway.
+struct AgentToken {
+    scope: AgentScope,
+    expires_at: chrono::DateTime<chrono::Utc>,
 }
 
 pub struct SecurityContext {
     pairing_token: String,
     sessions: Arc<RwLock<HashMap<String, Session>>>,
     session_file: PathBuf,
+    /// Client addresses exempt from API authentication
+    trusted_addrs: Vec<IpAddr>,
+    /// Agent tokens by token value
+    agent_tokens: RwLock<HashMap<String, AgentToken>>,
+    agent_token_ttl: chrono::Duration,
+    /// JSON lines log of every call made with an agent token
+    audit_file: PathBuf,
+    /// `[server] read_only`: every session may only read
+    read_only: bool,
 }
 
 impl SecurityContext {
-    pub fn new(_config: &Config) -> Result<Self> {
+    pub fn new(config: &Config) -> Result<Self> {
         // Check for PAIRING_TOKEN env var, otherwise generate random
         let pairing_token = std::env::var("PAIRING_TOKEN")
             .ok()
@@ -35,10 +63,25 @@ impl SecurityContext {
             .unwrap_or_else(|| PathBuf::from("."))
             .join(SESSION_FILE);
 
+        let trusted_addrs = config
+            .server
+            .trusted_addrs
+            .iter()
+            .map(|addr| {
+                addr.parse::<IpAddr>()
+                    .with_context(|| format!("Invalid address in server.trusted_addrs: {}", addr))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         let context = Self {
             pairing_token,
             sessions: Arc::new(RwLock::new(HashMap::new())),
             session_file,
+            trusted_addrs,
+            agent_tokens: RwLock::new(HashMap::new()),
+            agent_token_ttl: chrono::Duration::seconds(config.server.agent_token_ttl_secs as i64),
+            audit_file: toren_lib::toren_root().join("agent-audit.jsonl"),
+            read_only: config.server.read_only,
         };
 
         // Load persisted sessions
@@ -49,6 +92,13 @@ impl SecurityContext {
         Ok(context)
     }
 
+    /// Write the agent call audit log to `path` instead.
+    #[allow(dead_code)]
+    pub fn with_audit_file(mut self, path: PathBuf) -> Self {
+        self.audit_file = path;
+        self
+    }
+
     pub fn pairing_token(&self) -> String {
         self.pairing_token.clone()
     }
@@ -62,7 +112,22 @@ impl SecurityContext {
         sessions.values().any(|s| s.token == token)
     }
 
-    pub fn create_session(&self) -> Result<Session> {
+    /// Whether a client may only read: always under `[server] read_only`,
+    /// otherwise when `token` belongs to a read-only session.
+    pub fn is_read_only(&self, token: Option<&str>) -> bool {
+        self.read_only
+            || token.is_some_and(|token| {
+                let sessions = self.sessions.read().unwrap();
+                sessions.values().any(|s| s.token == token && s.read_only)
+            })
+    }
+
+    /// Whether requests from `addr` may skip the session token.
+    pub fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.trusted_addrs.contains(&addr.to_canonical())
+    }
+
+    pub fn create_session(&self, read_only: bool) -> Result<Session> {
         let session_id = Self::generate_session_id();
         let session_token = Self::generate_session_token();
 
@@ -70,6 +135,7 @@ impl SecurityContext {
             id: session_id.clone(),
             token: session_token,
             created_at: chrono::Utc::now().to_rfc3339(),
+            read_only,
         };
 
         // Store session
@@ -86,6 +152,75 @@ impl SecurityContext {
         Ok(session)
     }
 
+    /// Issue a token scoped to `assignment_id`, valid for
+    /// `[server] agent_token_ttl_secs`. None when agent tokens are disabled.
+    pub fn issue_agent_token(&self, assignment_id: &str) -> Option<String> {
+        if self.agent_token_ttl <= chrono::Duration::zero() {
+            return None;
+        }
+        let token = Self::generate_session_token();
+        let mut tokens = self.agent_tokens.write().unwrap();
+        let now = chrono::Utc::now();
+        tokens.retain(|_, t| t.expires_at > now);
+        tokens.insert(
+            token.clone(),
+            AgentToken {
+                scope: AgentScope {
+                    assignment_id: assignment_id.to_string(),
+                },
+                expires_at: now + self.agent_token_ttl,
+            },
+        );
+        Some(token)
+    }
+
+    /// The scope of an unexpired agent token.
+    pub fn agent_scope(&self, token: &str) -> Option<AgentScope> {
+        let tokens = self.agent_tokens.read().unwrap();
+        tokens
+            .get(token)
+            .filter(|t| t.expires_at > chrono::Utc::now())
+            .map(|t| t.scope.clone())
+    }
+
+    /// Invalidate every token issued for `assignment_id`.
+    pub fn revoke_agent_tokens(&self, assignment_id: &str) {
+        let mut tokens = self.agent_tokens.write().unwrap();
+        tokens.retain(|_, t| t.scope.assignment_id != assignment_id);
+    }
+
+    /// Record a call made with an agent token, allowed or not.
+    pub fn audit_agent_call(&self, scope: &AgentScope, method: &str, path: &str, status: u16) {
+        tracing::info!(
+            "Agent call for assignment {}: {} {} -> {}",
+            scope.assignment_id,
+            method,
+            path,
+            status
+        );
+        let entry = serde_json::json!({
+            "at": chrono::Utc::now().to_rfc3339(),
+            "assignment_id": scope.assignment_id,
+            "method": method,
+            "path": path,
+            "status": status,
+        });
+        let result = (|| -> Result<()> {
+            if let Some(parent) = self.audit_file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.audit_file)?;
+            writeln!(file, "{}", entry)?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            tracing::warn!("Failed to write {}: {:#}", self.audit_file.display(), e);
+        }
+    }
+
     fn load_sessions(&self) -> Result<()> {
         if !self.session_file.exists() {
             return Ok(());
@@ -159,7 +294,46 @@ mod tests {
         let config = Config::default();
         let ctx = SecurityContext::new(&config).unwrap();
 
-        let session = ctx.create_session().unwrap();
+        let session = ctx.create_session(false).unwrap();
         assert!(ctx.validate_session(&session.token));
+        assert!(!ctx.is_read_only(Some(&session.token)));
+
+        let viewer = ctx.create_session(true).unwrap();
+        assert!(ctx.validate_session(&viewer.token));
+        assert!(ctx.is_read_only(Some(&viewer.token)));
+    }
+
+    #[test]
+    fn test_trusted_addrs() {
+        let mut config = Config::default();
+        let ctx = SecurityContext::new(&config).unwrap();
+        assert!(!ctx.is_trusted("127.0.0.1".parse().unwrap()));
+
+        config.server.trusted_addrs = vec!["127.0.0.1".to_string(), "::1".to_string()];
+        let ctx = SecurityContext::new(&config).unwrap();
+        assert!(ctx.is_trusted("127.0.0.1".parse().unwrap()));
+        assert!(ctx.is_trusted("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(ctx.is_trusted("::1".parse().unwrap()));
+        assert!(!ctx.is_trusted("192.168.1.20".parse().unwrap()));
+
+        config.server.trusted_addrs = vec!["localhost".to_string()];
+        assert!(SecurityContext::new(&config).is_err());
+    }
+
+    #[test]
+    fn test_agent_tokens_are_scoped_and_revocable() {
+        let ctx = SecurityContext::new(&Config::default()).unwrap();
+        let token = ctx.issue_agent_token("a1").unwrap();
+        let other = ctx.issue_agent_token("a2").unwrap();
+        assert_eq!(ctx.agent_scope(&token).unwrap().assignment_id, "a1");
+        assert!(!ctx.validate_session(&token));
+
+        ctx.revoke_agent_tokens("a1");
+        assert!(ctx.agent_scope(&token).is_none());
+        assert!(ctx.agent_scope(&other).is_some());
+
+        let mut config = Config::default();
+        config.server.agent_token_ttl_secs = 0;
+        assert!(SecurityContext::new(&config).unwrap().issue_agent_token("a1").is_none());
     }
 }
@@ -1,12 +1,36 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use super::derive_approved_directories;
 use toren_lib::Config;
 
+/// Reads and lists are allowed under segment paths and workspace roots;
+/// writes only inside a workspace (`<workspace_root>/<segment>/<workspace>/`).
+/// Files over `[filesystem] max_file_bytes` are refused both ways, and all
+/// operations share a per-minute request budget.
 pub struct FilesystemService {
     approved_directories: Vec<PathBuf>,
+    workspace_roots: Vec<PathBuf>,
+    max_file_bytes: u64,
+    limiter: RateLimiter,
+}
+
+/// Why a filesystem request was refused.
+#[derive(Debug, thiserror::Error)]
+pub enum FsError {
+    #[error("Path not in approved directories: {0}")]
+    NotApproved(PathBuf),
+    #[error("Writes are only allowed inside managed workspaces: {0}")]
+    OutsideWorkspace(PathBuf),
+    #[error("{path} is {size} bytes, over the {limit} byte limit")]
+    TooLarge { path: PathBuf, size: u64, limit: u64 },
+    #[error("Too many filesystem requests; try again in a minute")]
+    RateLimited,
+    #[error("{0:#}")]
+    Io(#[from] anyhow::Error),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,31 +49,50 @@ pub struct DirListing {
 
 impl FilesystemService {
     pub fn new(config: &Config) -> Result<Self> {
+        let workspace_roots = std::iter::once(config.ancillaries.workspace_root.clone())
+            .chain(config.segment_workspace_roots().into_values())
+            .map(|root| root.canonicalize().unwrap_or(root))
+            .collect();
         Ok(Self {
             approved_directories: derive_approved_directories(config),
+            workspace_roots,
+            max_file_bytes: config.filesystem.max_file_bytes,
+            limiter: RateLimiter::new(config.filesystem.max_requests_per_minute),
         })
     }
 
-    pub fn read_file(&self, path: &Path) -> Result<String> {
+    pub fn read_file(&self, path: &Path) -> Result<String, FsError> {
+        self.limiter.check()?;
         self.validate_path(path)?;
 
-        std::fs::read_to_string(path)
-            .with_context(|| format!("Failed to read file: {}", path.display()))
+        let size = std::fs::metadata(path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?
+            .len();
+        self.check_size(path, size)?;
+
+        Ok(std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?)
     }
 
-    pub fn write_file(&self, path: &Path, content: &str) -> Result<()> {
-        self.validate_path(path)?;
+    pub fn write_file(&self, path: &Path, content: &str) -> Result<(), FsError> {
+        self.limiter.check()?;
+        let resolved = self.validate_path(path)?;
+        if !self.in_workspace(&resolved) {
+            return Err(FsError::OutsideWorkspace(path.to_path_buf()));
+        }
+        self.check_size(path, content.len() as u64)?;
 
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent).context("Failed to create parent directory")?;
         }
 
-        std::fs::write(path, content)
-            .with_context(|| format!("Failed to write file: {}", path.display()))
+        Ok(std::fs::write(path, content)
+            .with_context(|| format!("Failed to write file: {}", path.display()))?)
     }
 
-    pub fn list_directory(&self, path: &Path) -> Result<DirListing> {
+    pub fn list_directory(&self, path: &Path) -> Result<DirListing, FsError> {
+        self.limiter.check()?;
         self.validate_path(path)?;
 
         let entries: Result<Vec<_>> = std::fs::read_dir(path)
@@ -79,39 +122,147 @@ impl FilesystemService {
     }
 
     #[allow(dead_code)]
-    pub fn file_exists(&self, path: &Path) -> Result<bool> {
+    pub fn file_exists(&self, path: &Path) -> Result<bool, FsError> {
         self.validate_path(path)?;
         Ok(path.exists())
     }
 
-    fn validate_path(&self, path: &Path) -> Result<()> {
-        let canonical = match path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => {
-                // If path doesn't exist, try to canonicalize parent
-                if let Some(parent) = path.parent() {
-                    parent
-                        .canonicalize()
-                        .context("Failed to canonicalize parent directory")?
-                } else {
-                    return Err(anyhow!("Invalid path"));
-                }
-            }
-        };
+    fn check_size(&self, path: &Path, size: u64) -> Result<(), FsError> {
+        if size > self.max_file_bytes {
+            return Err(FsError::TooLarge {
+                path: path.to_path_buf(),
+                size,
+                limit: self.max_file_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether `resolved` lies inside a workspace directory, not just under
+    /// a workspace root.
+    fn in_workspace(&self, resolved: &Path) -> bool {
+        self.workspace_roots.iter().any(|root| {
+            resolved
+                .strip_prefix(root)
+                .is_ok_and(|rest| rest.components().count() >= 3)
+        })
+    }
+
+    /// Canonicalize `path` and check it is under an approved directory.
+    fn validate_path(&self, path: &Path) -> Result<PathBuf, FsError> {
+        let canonical = resolve(path).ok_or_else(|| FsError::NotApproved(path.to_path_buf()))?;
 
         for approved in &self.approved_directories {
-            let approved_canonical = approved
-                .canonicalize()
-                .context("Failed to canonicalize approved directory")?;
+            // Workspace roots may not exist until the first workspace is created
+            let Ok(approved_canonical) = approved.canonicalize() else {
+                continue;
+            };
 
             if canonical.starts_with(&approved_canonical) {
-                return Ok(());
+                return Ok(canonical);
             }
         }
 
-        Err(anyhow!(
-            "Path not in approved directories: {}",
-            path.display()
-        ))
+        Err(FsError::NotApproved(path.to_path_buf()))
+    }
+}
+
+/// Canonicalize the longest existing prefix of `path` and append the rest,
+/// so paths about to be created resolve too. `None` when the missing part
+/// contains `..`.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(mut canonical) = existing.canonicalize() {
+            canonical.extend(missing.iter().rev());
+            return Some(canonical);
+        }
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+}
+
+/// Fixed one-minute window request budget.
+struct RateLimiter {
+    per_minute: u32,
+    window: Mutex<(Instant, u32)>,
+}
+
+impl RateLimiter {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    fn check(&self) -> Result<(), FsError> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let mut window = self.window.lock().unwrap();
+        if window.0.elapsed() >= Duration::from_secs(60) {
+            *window = (Instant::now(), 0);
+        }
+        if window.1 >= self.per_minute {
+            return Err(FsError::RateLimited);
+        }
+        window.1 += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(dir: &Path, max_file_bytes: u64, per_minute: u32) -> FilesystemService {
+        let mut config = Config {
+            segment_paths: (Vec::new(), vec![dir.join("repo")]),
+            ..Config::default()
+        };
+        config.ancillaries.workspace_root = dir.join("workspaces");
+        config.filesystem.max_file_bytes = max_file_bytes;
+        config.filesystem.max_requests_per_minute = per_minute;
+        FilesystemService::new(&config).unwrap()
+    }
+
+    #[test]
+    fn test_writes_only_inside_workspaces() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("repo")).unwrap();
+        std::fs::create_dir_all(dir.path().join("workspaces/repo/one")).unwrap();
+        let fs = service(dir.path(), 1024, 0);
+
+        let in_ws = dir.path().join("workspaces/repo/one/src/new.rs");
+        fs.write_file(&in_ws, "fn main() {}").unwrap();
+        assert_eq!(fs.read_file(&in_ws).unwrap(), "fn main() {}");
+
+        let in_repo = dir.path().join("repo/README.md");
+        assert!(matches!(fs.write_file(&in_repo, "hi"), Err(FsError::OutsideWorkspace(_))));
+        let in_root = dir.path().join("workspaces/stray.txt");
+        assert!(matches!(fs.write_file(&in_root, "hi"), Err(FsError::OutsideWorkspace(_))));
+        let escape = dir.path().join("workspaces/repo/one/missing/../../../../outside.txt");
+        assert!(matches!(fs.write_file(&escape, "hi"), Err(FsError::NotApproved(_))));
+        let outside = dir.path().join("outside.txt");
+        assert!(matches!(fs.read_file(&outside), Err(FsError::NotApproved(_))));
+    }
+
+    #[test]
+    fn test_size_and_rate_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("workspaces/repo/one")).unwrap();
+        let big = dir.path().join("workspaces/repo/one/big.txt");
+        std::fs::write(&big, "x".repeat(64)).unwrap();
+
+        let fs = service(dir.path(), 16, 0);
+        assert!(matches!(fs.read_file(&big), Err(FsError::TooLarge { size: 64, .. })));
+        assert!(matches!(fs.write_file(&big, &"y".repeat(17)), Err(FsError::TooLarge { .. })));
+
+        let fs = service(dir.path(), 1024, 2);
+        fs.read_file(&big).unwrap();
+        fs.read_file(&big).unwrap();
+        assert!(matches!(fs.read_file(&big), Err(FsError::RateLimited)));
     }
 }
@@ -10,15 +10,19 @@ pub mod filesystem;
 pub mod vcs;
 
 /// Derive approved directories from config segments and workspace root.
-/// Expands segment globs/paths and includes the workspace root.
+/// Expands segment globs/paths and includes the workspace roots (the global
+/// one plus any per-segment overrides).
 pub fn derive_approved_directories(config: &Config) -> Vec<PathBuf> {
     let (roots, literals) = config.resolve_segment_paths();
     let mut dirs: Vec<PathBuf> = roots.clone();
     dirs.extend(literals.iter().cloned());
-    let ws_root = &config.ancillaries.workspace_root;
-    let canonical = ws_root.canonicalize().unwrap_or_else(|_| ws_root.clone());
-    if !dirs.contains(&canonical) {
-        dirs.push(canonical);
+    let ws_roots = std::iter::once(config.ancillaries.workspace_root.clone())
+        .chain(config.segment_workspace_roots().into_values());
+    for ws_root in ws_roots {
+        let canonical = ws_root.canonicalize().unwrap_or(ws_root);
+        if !dirs.contains(&canonical) {
+            dirs.push(canonical);
+        }
     }
     dirs
 }
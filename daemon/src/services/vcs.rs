@@ -1,10 +1,11 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use super::derive_approved_directories;
-use toren_lib::Config;
+use toren_lib::{Assignment, Config, DiffOptions};
 
 pub struct VcsService {
     approved_directories: Vec<PathBuf>,
@@ -26,6 +27,89 @@ pub struct VcsStatus {
     pub deleted: Vec<String>,
 }
 
+/// Server-side limits on a working copy diff.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DiffFilter {
+    /// Limit to these files or directories, relative to the directory diffed
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Keep at most this many lines of each file's patch
+    #[serde(default)]
+    pub max_lines_per_file: Option<usize>,
+}
+
+impl DiffFilter {
+    fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.max_lines_per_file.is_none()
+    }
+}
+
+/// One file's part of a diff, in git's unified format.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub diff: String,
+    /// Lines in the file's full patch, including any cut off
+    pub lines: usize,
+    /// Whether `max_lines_per_file` cut the patch short
+    pub truncated: bool,
+}
+
+/// Splits diff output into [`FileDiff`]s as its lines arrive, so a large
+/// diff is never held whole.
+struct FileDiffSplitter {
+    max_lines: Option<usize>,
+    current: Option<FileDiff>,
+}
+
+impl FileDiffSplitter {
+    fn new(max_lines: Option<usize>) -> Self {
+        Self {
+            max_lines,
+            current: None,
+        }
+    }
+
+    /// Add a line, returning the previous file's diff once a new one starts.
+    fn push(&mut self, line: &str) -> Option<FileDiff> {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            let path = header
+                .rsplit_once(" b/")
+                .map(|(_, b)| b)
+                .unwrap_or(header)
+                .to_string();
+            let finished = self.current.replace(FileDiff {
+                path,
+                diff: String::new(),
+                lines: 0,
+                truncated: false,
+            });
+            self.append(line);
+            return finished;
+        }
+        self.append(line);
+        None
+    }
+
+    fn append(&mut self, line: &str) {
+        // Output before the first file header (none with --git) is dropped
+        let Some(file) = self.current.as_mut() else {
+            return;
+        };
+        file.lines += 1;
+        if self.max_lines.is_none_or(|max| file.lines <= max) {
+            file.diff.push_str(line);
+            file.diff.push('\n');
+        } else {
+            file.truncated = true;
+        }
+    }
+
+    fn finish(self) -> Option<FileDiff> {
+        self.current
+    }
+}
+
 impl VcsService {
     pub fn new(config: &Config) -> Result<Self> {
         Ok(Self {
@@ -79,16 +163,106 @@ impl VcsService {
         }
     }
 
-    pub fn diff(&self, path: &Path) -> Result<String> {
+    /// Working copy diff of `path`. A non-empty `filter` diffs through
+    /// [`Self::stream_diff`] and also returns the files it truncated.
+    pub fn diff(&self, path: &Path, filter: &DiffFilter) -> Result<(String, Vec<String>)> {
+        if !filter.is_empty() {
+            let mut diff = String::new();
+            let mut truncated = Vec::new();
+            self.stream_diff(path, filter, |file| {
+                diff.push_str(&file.diff);
+                if file.truncated {
+                    truncated.push(file.path);
+                }
+                true
+            })?;
+            return Ok((diff, truncated));
+        }
         self.validate_directory(path)?;
 
         let vcs_type = self.detect_vcs(path)?;
 
-        match vcs_type {
-            VcsType::Git => self.git_diff(path),
-            VcsType::Jj => self.jj_diff(path),
-            VcsType::None => Ok(String::new()),
+        let diff = match vcs_type {
+            VcsType::Git => self.git_diff(path)?,
+            VcsType::Jj => self.jj_diff(path)?,
+            VcsType::None => String::new(),
+        };
+        Ok((diff, Vec::new()))
+    }
+
+    /// Run the working copy diff of `path`, handing `on_file` each file's
+    /// diff as soon as the VCS has printed it. Stops early, killing the diff,
+    /// when `on_file` returns false.
+    pub fn stream_diff(
+        &self,
+        path: &Path,
+        filter: &DiffFilter,
+        mut on_file: impl FnMut(FileDiff) -> bool,
+    ) -> Result<()> {
+        let mut cmd = match self.detect_vcs(path)? {
+            VcsType::Git => {
+                let mut cmd = Command::new("git");
+                cmd.args(["diff", "HEAD", "--"]);
+                cmd
+            }
+            VcsType::Jj => {
+                let mut cmd = Command::new("jj");
+                cmd.args(["diff", "--git"]);
+                cmd
+            }
+            VcsType::None => return Ok(()),
+        };
+        if filter.paths.is_empty() {
+            cmd.arg(".");
+        } else {
+            cmd.args(&filter.paths);
+        }
+        let mut child = cmd
+            .current_dir(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run diff")?;
+        let stdout = child.stdout.take().context("Diff has no stdout")?;
+
+        let mut splitter = FileDiffSplitter::new(filter.max_lines_per_file);
+        let mut reader = BufReader::new(stdout);
+        let mut buf = Vec::new();
+        let mut stopped = false;
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&buf);
+            if let Some(file) = splitter.push(line.trim_end_matches(['\n', '\r'])) {
+                if !on_file(file) {
+                    stopped = true;
+                    break;
+                }
+            }
+        }
+        if stopped {
+            let _ = child.kill();
+        } else if let Some(file) = splitter.finish() {
+            on_file(file);
         }
+        let _ = child.wait();
+        Ok(())
+    }
+
+    /// Diff of an assignment's changes in its workspace (scoped to its focus
+    /// directory), from where it forked from its base branch unless `opts`
+    /// names a range.
+    pub fn assignment_diff(&self, assignment: &Assignment, opts: &DiffOptions) -> Result<String> {
+        let dir = assignment.working_dir();
+        self.validate_directory(&dir)?;
+        toren_lib::workspace_diff(
+            &assignment.workspace_path,
+            &dir,
+            assignment.base_branch.as_deref(),
+            opts,
+        )
     }
 
     fn git_status(&self, path: &Path) -> Result<VcsStatus> {
@@ -132,7 +306,8 @@ impl VcsService {
 
     fn git_diff(&self, path: &Path) -> Result<String> {
         let output = Command::new("git")
-            .args(["diff", "HEAD"])
+            // Limit to `path` so a subdirectory (an assignment's focus) gets a scoped diff
+            .args(["diff", "HEAD", "--", "."])
             .current_dir(path)
             .output()
             .context("Failed to run git diff")?;
@@ -185,7 +360,7 @@ impl VcsService {
 
     fn jj_diff(&self, path: &Path) -> Result<String> {
         let output = Command::new("jj")
-            .args(["diff"])
+            .args(["diff", "."])
             .current_dir(path)
             .output()
             .context("Failed to run jj diff")?;
@@ -209,3 +384,50 @@ impl VcsService {
         anyhow::bail!("Directory not in approved list: {}", path.display())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1,2 @@
+ fn a() {}
++fn b() {}
+diff --git a/gen/schema.rs b/gen/schema.rs
+new file mode 100644
+--- /dev/null
++++ b/gen/schema.rs
+@@ -0,0 +1,3 @@
++// generated
++pub struct A;
++pub struct B;
+";
+
+    fn split(diff: &str, max_lines: Option<usize>) -> Vec<FileDiff> {
+        let mut splitter = FileDiffSplitter::new(max_lines);
+        let mut files: Vec<FileDiff> = diff.lines().filter_map(|l| splitter.push(l)).collect();
+        files.extend(splitter.finish());
+        files
+    }
+
+    #[test]
+    fn test_splits_diff_per_file() {
+        let files = split(DIFF, None);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/lib.rs");
+        assert_eq!(files[1].path, "gen/schema.rs");
+        assert_eq!(files[0].lines, 7);
+        assert!(!files[0].truncated);
+        assert_eq!(files.iter().map(|f| f.diff.as_str()).collect::<String>(), DIFF);
+
+        let files = split(DIFF, Some(5));
+        assert_eq!(files[0].diff.lines().count(), 5);
+        assert!(files[0].truncated);
+        assert_eq!(files[1].lines, 8);
+        assert!(files[1].diff.ends_with("@@ -0,0 +1,3 @@\n"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Logging, and OpenTelemetry export when built with the `otel` feature.
+//!
+//! Logs always go to stdout. With the `otel` feature and
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` set, spans and metrics are also exported
+//! over OTLP/HTTP; the standard `OTEL_*` variables configure the exporter.
+//! Spans cover workspace creation, setup and destroy hooks, agent work loops
+//! and proxy (Caddy) route calls. Metrics count assignments created,
+//! completed and failed, and gauge the ancillaries whose agent is working.
+
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+use crate::events::EventBus;
+
+/// Installed telemetry; flushes exporters on [`Telemetry::shutdown`].
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    otel: Option<otel::Providers>,
+}
+
+/// Install the tracing subscriber, with OTLP export if enabled.
+pub fn init() -> Telemetry {
+    let registry = tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(tracing_subscriber::fmt::layer());
+
+    #[cfg(feature = "otel")]
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() {
+        return match otel::Providers::install() {
+            Ok(providers) => {
+                registry
+                    .with(tracing_opentelemetry::layer().with_tracer(providers.tracer()))
+                    .init();
+                tracing::info!("Exporting OpenTelemetry traces and metrics");
+                Telemetry { otel: Some(providers) }
+            }
+            Err(e) => {
+                registry.init();
+                tracing::warn!("OpenTelemetry export disabled: {:#}", e);
+                Telemetry { otel: None }
+            }
+        };
+    }
+
+    registry.init();
+    Telemetry {
+        #[cfg(feature = "otel")]
+        otel: None,
+    }
+}
+
+impl Telemetry {
+    /// Flush pending spans and metrics.
+    pub fn shutdown(self) {
+        #[cfg(feature = "otel")]
+        if let Some(providers) = self.otel {
+            providers.shutdown();
+        }
+    }
+}
+
+/// Record assignment and work metrics from daemon events. Without an
+/// exporter the instruments are no-ops.
+#[cfg(feature = "otel")]
+pub fn spawn_metrics(events: &EventBus) {
+    tokio::spawn(otel::record_metrics(events.subscribe()));
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn spawn_metrics(_events: &EventBus) {}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+    use opentelemetry_sdk::Resource;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::broadcast;
+
+    use crate::events::DaemonEvent;
+
+    const SERVICE_NAME: &str = "toren-daemon";
+
+    pub struct Providers {
+        tracer: SdkTracerProvider,
+        meter: SdkMeterProvider,
+    }
+
+    impl Providers {
+        /// Build the OTLP exporters and make the meter provider global.
+        pub fn install() -> anyhow::Result<Self> {
+            let resource = Resource::builder().with_service_name(SERVICE_NAME).build();
+            let spans = opentelemetry_otlp::SpanExporter::builder().with_http().build()?;
+            let tracer = SdkTracerProvider::builder()
+                .with_batch_exporter(spans)
+                .with_resource(resource.clone())
+                .build();
+            let metrics = opentelemetry_otlp::MetricExporter::builder().with_http().build()?;
+            let meter = SdkMeterProvider::builder()
+                .with_periodic_exporter(metrics)
+                .with_resource(resource)
+                .build();
+            opentelemetry::global::set_meter_provider(meter.clone());
+            Ok(Self { tracer, meter })
+        }
+
+        pub fn tracer(&self) -> Tracer {
+            self.tracer.tracer(SERVICE_NAME)
+        }
+
+        pub fn shutdown(self) {
+            if let Err(e) = self.tracer.shutdown() {
+                tracing::warn!("Failed to flush spans: {}", e);
+            }
+            if let Err(e) = self.meter.shutdown() {
+                tracing::warn!("Failed to flush metrics: {}", e);
+            }
+        }
+    }
+
+    pub async fn record_metrics(mut rx: broadcast::Receiver<DaemonEvent>) {
+        let meter = opentelemetry::global::meter(SERVICE_NAME);
+        let created = meter
+            .u64_counter("toren.assignments.created")
+            .with_description("Assignments created")
+            .build();
+        let completed = meter
+            .u64_counter("toren.assignments.completed")
+            .with_description("Assignments completed")
+            .build();
+        let failed = meter
+            .u64_counter("toren.assignments.failed")
+            .with_description("Agent work sessions that failed")
+            .build();
+
+        // Ancillaries whose agent is working, by latest work status
+        let busy: Arc<Mutex<HashSet<String>>> = Arc::default();
+        let _busy_gauge = {
+            let busy = busy.clone();
+            meter
+                .u64_observable_gauge("toren.ancillaries.busy")
+                .with_description("Ancillaries with a working agent")
+                .with_callback(move |gauge| gauge.observe(busy.lock().unwrap().len() as u64, &[]))
+                .build()
+        };
+
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            match event {
+                DaemonEvent::AssignmentCreated { .. } => created.add(1, &[]),
+                DaemonEvent::AssignmentCompleted { ancillary_id, .. } => {
+                    completed.add(1, &[]);
+                    busy.lock().unwrap().remove(ancillary_id.as_str());
+                }
+                DaemonEvent::AssignmentAborted { ancillary_id, .. } => {
+                    busy.lock().unwrap().remove(ancillary_id.as_str());
+                }
+                DaemonEvent::WorkStatusChanged { ancillary_id, status, .. } => {
+                    if status == "failed" {
+                        failed.add(1, &[]);
+                    }
+                    let mut busy = busy.lock().unwrap();
+                    if status == "working" {
+                        busy.insert(ancillary_id.to_string());
+                    } else {
+                        busy.remove(ancillary_id.as_str());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
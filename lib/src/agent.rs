@@ -1,9 +1,12 @@
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 use std::path::Path;
 use std::process::Command;
 
+use crate::error::{ErrorCode, TorenError};
+
 /// Supported coding agent backends.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -45,6 +48,16 @@ impl AgentKind {
         ]
     }
 
+    /// How to install this agent's CLI, shown when the binary is missing.
+    pub fn install_hint(self) -> &'static str {
+        match self {
+            AgentKind::Claude => "npm install -g @anthropic-ai/claude-code",
+            AgentKind::Codex => "npm install -g @openai/codex",
+            AgentKind::Gemini => "npm install -g @google/gemini-cli",
+            AgentKind::Opencode => "npm install -g opencode-ai",
+        }
+    }
+
     /// The CLI flag used to specify a model override.
     fn model_flag(self) -> &'static str {
         match self {
@@ -72,11 +85,40 @@ impl fmt::Display for AgentKind {
     }
 }
 
+/// Per-agent command overrides, configured under `[ancillaries.<agent>]`.
+///
+/// Lets users run forks or wrappers, or always pass extra flags, without
+/// shell-aliasing around breq.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentCommandConfig {
+    /// Binary to run instead of the agent's default (name on PATH or a path)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+
+    /// Arguments passed on every invocation, before breq's own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// Extra environment variables for the agent process
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+
+    /// Default model when none is given as `<agent>:<model>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Flag used to select the model (defaults to the agent's own, e.g. `--model`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_flag: Option<String>,
+}
+
 /// A resolved agent: kind + optional model override.
 #[derive(Debug, Clone)]
 pub struct Agent {
     pub kind: AgentKind,
     pub model: Option<String>,
+    /// Command overrides from config
+    pub command: AgentCommandConfig,
 }
 
 impl Agent {
@@ -95,14 +137,29 @@ impl Agent {
             _ => bail!("Unknown agent: '{}'. Expected one of: claude, codex, gemini, opencode", name),
         };
 
-        Ok(Agent { kind, model })
+        Ok(Agent {
+            kind,
+            model,
+            command: AgentCommandConfig::default(),
+        })
     }
 
     /// Auto-detect the first available agent on PATH.
     pub fn detect() -> Result<Self> {
+        Self::detect_with(|_| AgentCommandConfig::default())
+    }
+
+    /// Auto-detect the first available agent, honoring per-agent command overrides.
+    pub fn detect_with(command_for: impl Fn(AgentKind) -> AgentCommandConfig) -> Result<Self> {
         for &kind in AgentKind::all() {
-            if which::which(kind.binary_name()).is_ok() {
-                return Ok(Agent { kind, model: None });
+            let agent = Agent {
+                kind,
+                model: None,
+                command: AgentCommandConfig::default(),
+            }
+            .with_command(command_for(kind));
+            if which::which(agent.binary()).is_ok() {
+                return Ok(agent);
             }
         }
         bail!(
@@ -110,9 +167,59 @@ impl Agent {
         )
     }
 
+    /// Apply command overrides. An explicit model (`claude:opus`) wins over the configured default.
+    pub fn with_command(mut self, command: AgentCommandConfig) -> Self {
+        if self.model.is_none() {
+            self.model = command.model.clone();
+        }
+        self.command = command;
+        self
+    }
+
+    /// Binary to execute: the configured override or the agent's default name.
+    pub fn binary(&self) -> &str {
+        self.command
+            .binary
+            .as_deref()
+            .unwrap_or(self.kind.binary_name())
+    }
+
+    fn model_flag(&self) -> &str {
+        self.command
+            .model_flag
+            .as_deref()
+            .unwrap_or(self.kind.model_flag())
+    }
+
+    /// Verify the agent's binary is on PATH, returning its resolved path.
+    ///
+    /// Call this before any side effects (claiming a task, creating a workspace)
+    /// so a missing agent fails fast with install guidance instead of leaving
+    /// half-finished state behind.
+    pub fn ensure_installed(&self) -> Result<std::path::PathBuf> {
+        let binary = self.binary();
+        which::which(binary).map_err(|_| {
+            if self.command.binary.is_some() {
+                return TorenError::new(ErrorCode::AgentNotInstalled, format!("`{}` not found.", binary))
+                    .hint(format!(
+                        "Check `binary` under [ancillaries.{}] in ~/.toren/config.toml.",
+                        self.kind.binary_name()
+                    ))
+                    .into();
+            }
+            TorenError::new(ErrorCode::AgentNotInstalled, format!("`{}` not found on PATH.", binary))
+                .hint(format!(
+                    "Install it with: {}\n\
+                     Or choose another agent with --agent <name> or `agent = \"...\"` under [ancillaries] in ~/.toren/config.toml.",
+                    self.kind.install_hint()
+                ))
+                .into()
+        })
+    }
+
     /// Build a `std::process::Command` for interactive (breq) use.
     ///
-    /// - Sets the binary and working directory
+    /// - Sets the binary, working directory, and configured args/env
     /// - Adds model flag if a model override is set
     /// - For Claude: adds `--append-system-prompt` for intent text
     /// - For others: prepends intent text to the prompt
@@ -123,11 +230,13 @@ impl Agent {
         cwd: &Path,
         system_prompt: Option<&str>,
     ) -> Command {
-        let mut cmd = Command::new(self.kind.binary_name());
-        cmd.current_dir(cwd);
+        let mut cmd = Command::new(self.binary());
+        cmd.current_dir(cwd)
+            .args(&self.command.args)
+            .envs(&self.command.env);
 
         if let Some(ref model) = self.model {
-            cmd.arg(self.kind.model_flag()).arg(model);
+            cmd.arg(self.model_flag()).arg(model);
         }
 
         match self.kind {
@@ -159,11 +268,13 @@ impl Agent {
         cwd: &Path,
         system_prompt: Option<&str>,
     ) -> tokio::process::Command {
-        let mut cmd = tokio::process::Command::new(self.kind.binary_name());
-        cmd.current_dir(cwd);
+        let mut cmd = tokio::process::Command::new(self.binary());
+        cmd.current_dir(cwd)
+            .args(&self.command.args)
+            .envs(&self.command.env);
 
         if let Some(ref model) = self.model {
-            cmd.arg(self.kind.model_flag()).arg(model);
+            cmd.arg(self.model_flag()).arg(model);
         }
 
         // Auto-approve flag for daemon mode
@@ -291,6 +402,36 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn build_command_applies_command_config() {
+        let agent = Agent::parse("claude").unwrap().with_command(AgentCommandConfig {
+            binary: Some("/opt/claude-fork/bin/claude".to_string()),
+            args: vec!["--verbose".to_string()],
+            env: HashMap::from([("CLAUDE_CONFIG_DIR".to_string(), "/tmp/cc".to_string())]),
+            model: Some("opus".to_string()),
+            model_flag: None,
+        });
+        let cmd = agent.build_command("fix the bug", Path::new("/tmp"), None);
+        assert_eq!(cmd.get_program(), "/opt/claude-fork/bin/claude");
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().to_string()).collect();
+        assert_eq!(args, vec!["--verbose", "--model", "opus", "fix the bug"]);
+        let envs: Vec<_> = cmd.get_envs().collect();
+        assert_eq!(envs, vec![(std::ffi::OsStr::new("CLAUDE_CONFIG_DIR"), Some(std::ffi::OsStr::new("/tmp/cc")))]);
+    }
+
+    #[test]
+    fn explicit_model_overrides_configured_default() {
+        let agent = Agent::parse("codex:o3").unwrap().with_command(AgentCommandConfig {
+            model: Some("gpt-5".to_string()),
+            model_flag: Some("--model".to_string()),
+            ..Default::default()
+        });
+        let cmd = agent.build_command("go", Path::new("/tmp"), None);
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().to_string()).collect();
+        assert_eq!(args, vec!["--model", "o3", "go"]);
+        assert_eq!(agent.binary(), "codex");
+    }
+
     #[test]
     fn agent_display() {
         let a1 = Agent::parse("claude").unwrap();
@@ -0,0 +1,288 @@
+//! Toren section in the workspace's agent memory file.
+//!
+//! When `ancillaries.context_file` is set (e.g. `CLAUDE.local.md`), toren
+//! writes a marked block of workspace facts into that file: ancillary,
+//! task, evaluated `var`s (ports), proxy URLs and a scratch directory. Agents
+//! load the file on every session start, so the facts survive context
+//! compaction and resumed sessions, unlike the initial prompt.
+//!
+//! The block is delimited by HTML comments so it can be rewritten in place
+//! on resume and stripped before changes are committed, leaving any
+//! user-authored content in the same file untouched.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::workspace_setup::WorkspaceFacts;
+use crate::Assignment;
+
+const BEGIN_MARKER: &str = "<!-- toren:begin -->";
+const END_MARKER: &str = "<!-- toren:end -->";
+
+/// Lines delimiting a toren-maintained block inside a file that may also
+/// hold user content (see also [`crate::envrc`]).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Markers {
+    pub begin: &'static str,
+    pub end: &'static str,
+}
+
+const MARKDOWN: Markers = Markers {
+    begin: BEGIN_MARKER,
+    end: END_MARKER,
+};
+
+/// Per-workspace scratch directory outside the repository:
+/// `~/.toren/scratch/{segment}/{workspace}`.
+pub fn scratch_dir(segment: &str, workspace_name: &str) -> PathBuf {
+    crate::toren_root()
+        .join("scratch")
+        .join(segment)
+        .join(workspace_name)
+}
+
+/// Render the toren block (including markers) for an assignment.
+pub fn render_section(assignment: &Assignment, facts: &WorkspaceFacts, scratch: &Path) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", BEGIN_MARKER);
+    let _ = writeln!(out, "## Toren workspace");
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "Maintained by toren; rewritten on resume and removed before changes are committed."
+    );
+    let _ = writeln!(out);
+    let _ = writeln!(out, "- Ancillary: {}", assignment.ancillary_id);
+    let _ = writeln!(out, "- Segment: {}", assignment.segment);
+    let _ = writeln!(out, "- Workspace: {}", assignment.workspace_path.display());
+    if let Some(ref focus) = assignment.focus_path {
+        let _ = writeln!(out, "- Focus: {} (keep changes within this directory)", focus.display());
+    }
+    if let Some(ref task_id) = assignment.task_id {
+        let title = assignment.task_title.as_deref().unwrap_or_default();
+        let _ = writeln!(out, "- Task: {} {}", task_id, title);
+    } else if let Some(ref title) = assignment.task_title {
+        let _ = writeln!(out, "- Task: {}", title);
+    }
+    if let Some(ref url) = assignment.task_url {
+        let _ = writeln!(out, "- Task URL: {}", url);
+    }
+    let _ = writeln!(
+        out,
+        "- Scratch dir: {} (outside the repo; use it for notes and temporary files)",
+        scratch.display()
+    );
+
+    if !facts.vars.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "### Vars");
+        let _ = writeln!(out);
+        for (name, value) in &facts.vars {
+            let _ = writeln!(out, "- `{}` = `{}`", name, value);
+        }
+    }
+
+    if !facts.proxy_urls.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "### Proxy routes");
+        let _ = writeln!(out);
+        for url in &facts.proxy_urls {
+            let _ = writeln!(out, "- {}", url);
+        }
+    }
+
+    out.push_str(END_MARKER);
+    out.push('\n');
+    out
+}
+
+/// Insert or replace the toren block in `content`.
+///
+/// An existing block is replaced in place; otherwise the block is appended.
+pub fn upsert_section(content: &str, section: &str) -> String {
+    upsert_block(content, section, MARKDOWN)
+}
+
+pub(crate) fn upsert_block(content: &str, section: &str, markers: Markers) -> String {
+    if let Some((before, after)) = split_block(content, markers) {
+        return format!("{}{}{}", before, section, after);
+    }
+    if content.trim().is_empty() {
+        return section.to_string();
+    }
+    let separator = if content.ends_with('\n') { "\n" } else { "\n\n" };
+    format!("{}{}{}", content, separator, section)
+}
+
+/// Remove the toren block from `content`. Returns `None` if there is none.
+pub fn remove_section(content: &str) -> Option<String> {
+    remove_block(content, MARKDOWN)
+}
+
+pub(crate) fn remove_block(content: &str, markers: Markers) -> Option<String> {
+    let (before, after) = split_block(content, markers)?;
+    let before = before.trim_end_matches('\n');
+    let after = after.trim_start_matches('\n');
+    Some(match (before.is_empty(), after.is_empty()) {
+        (true, true) => String::new(),
+        (true, false) => after.to_string(),
+        (false, true) => format!("{}\n", before),
+        (false, false) => format!("{}\n\n{}", before, after),
+    })
+}
+
+/// Text before the block's begin marker and after its end marker (and newline).
+fn split_block(content: &str, markers: Markers) -> Option<(&str, &str)> {
+    let start = content.find(markers.begin)?;
+    let end = start + content[start..].find(markers.end)? + markers.end.len();
+    let after = &content[end..];
+    let after = after.strip_prefix('\n').unwrap_or(after);
+    Some((&content[..start], after))
+}
+
+/// Write (or rewrite) the toren block into `workspace_path/file_name`.
+pub fn write_file(workspace_path: &Path, file_name: &str, section: &str) -> Result<PathBuf> {
+    write_block(workspace_path, file_name, section, MARKDOWN).map(|(path, _)| path)
+}
+
+/// Like [`write_file`], also returning whether the file changed.
+pub(crate) fn write_block(
+    workspace_path: &Path,
+    file_name: &str,
+    section: &str,
+    markers: Markers,
+) -> Result<(PathBuf, bool)> {
+    let path = workspace_path.join(file_name);
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let updated = upsert_block(&existing, section, markers);
+    let changed = updated != existing;
+    if changed {
+        std::fs::write(&path, updated)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok((path, changed))
+}
+
+/// Strip the toren block from `workspace_path/file_name`, deleting the file
+/// if nothing else is left in it. Returns whether a block was removed.
+pub fn remove_from_file(workspace_path: &Path, file_name: &str) -> Result<bool> {
+    remove_block_from_file(workspace_path, file_name, MARKDOWN)
+}
+
+pub(crate) fn remove_block_from_file(
+    workspace_path: &Path,
+    file_name: &str,
+    markers: Markers,
+) -> Result<bool> {
+    let path = workspace_path.join(file_name);
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Ok(false);
+    };
+    let Some(stripped) = remove_block(&content, markers) else {
+        return Ok(false);
+    };
+    if stripped.trim().is_empty() {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+    } else {
+        std::fs::write(&path, stripped)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssignmentSource, AssignmentStatus};
+
+    fn test_assignment() -> Assignment {
+        let now = chrono::Utc::now().to_rfc3339();
+        Assignment {
+            id: "abc123".to_string(),
+            ancillary_id: "Toren One".parse().unwrap(),
+            task_id: Some("bd-42".parse().unwrap()),
+            segment: "toren".to_string(),
+            workspace_path: PathBuf::from("/ws/toren/one"),
+            source: AssignmentSource::Reference,
+            status: AssignmentStatus::Active,
+            created_at: now.clone(),
+            updated_at: now,
+            task_title: Some("Fix the thing".to_string()),
+            task_url: None,
+            task_source: Some("beads".to_string()),
+            session_id: None,
+            ancillary_num: Some(1),
+            base_branch: None,
+            labels: Vec::new(),
+            focus_path: None,
+            intent: None,
+            resume_instruction: None,
+            owner: None,
+            notes: Vec::new(),
+            after: None,
+            group: None,
+            companions: Vec::new(),
+            prompt_amended_at: None,
+            usage: None,
+        }
+    }
+
+    fn section(body: &str) -> String {
+        format!("{}\n{}\n{}\n", BEGIN_MARKER, body, END_MARKER)
+    }
+
+    #[test]
+    fn test_render_section_lists_facts() {
+        let mut facts = WorkspaceFacts::default();
+        facts.vars.insert("port".to_string(), "5171".to_string());
+        facts.proxy_urls.push("https://one.toren.lvh.me".to_string());
+
+        let rendered = render_section(&test_assignment(), &facts, Path::new("/scratch/one"));
+        assert!(rendered.starts_with(BEGIN_MARKER));
+        assert!(rendered.ends_with(&format!("{}\n", END_MARKER)));
+        assert!(rendered.contains("- Ancillary: Toren One"));
+        assert!(rendered.contains("- Task: bd-42 Fix the thing"));
+        assert!(rendered.contains("- `port` = `5171`"));
+        assert!(rendered.contains("- https://one.toren.lvh.me"));
+        assert!(rendered.contains("/scratch/one"));
+    }
+
+    #[test]
+    fn test_upsert_appends_then_replaces() {
+        let user = "# Notes\n\nKeep tests fast.\n";
+        let first = upsert_section(user, &section("v1"));
+        assert_eq!(first, format!("{}\n{}", user, section("v1")));
+
+        let second = upsert_section(&first, &section("v2"));
+        assert_eq!(second, format!("{}\n{}", user, section("v2")));
+        assert_eq!(upsert_section("", &section("v1")), section("v1"));
+    }
+
+    #[test]
+    fn test_remove_section_preserves_user_content() {
+        let content = format!("# Notes\n\n{}\nMore notes\n", section("facts"));
+        assert_eq!(
+            remove_section(&content).unwrap(),
+            "# Notes\n\nMore notes\n"
+        );
+        assert_eq!(remove_section(&section("facts")).unwrap(), "");
+        assert!(remove_section("# Notes\n").is_none());
+    }
+
+    #[test]
+    fn test_file_roundtrip_deletes_toren_only_file() {
+        let ws = tempfile::tempdir().unwrap();
+        let path = write_file(ws.path(), "CLAUDE.local.md", &section("facts")).unwrap();
+        assert!(path.exists());
+
+        assert!(remove_from_file(ws.path(), "CLAUDE.local.md").unwrap());
+        assert!(!path.exists());
+        assert!(!remove_from_file(ws.path(), "CLAUDE.local.md").unwrap());
+    }
+}
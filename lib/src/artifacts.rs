@@ -0,0 +1,91 @@
+//! Completion artifacts.
+//!
+//! Files matched by the `artifacts` block of toren.kdl (e.g. `keep "PLAN.md"`,
+//! `keep "coverage/**"`) are copied to `~/.toren/artifacts/<assignment>/` when
+//! an assignment completes, so reports and plans outlive the workspace. The
+//! copied paths are listed on the assignment's [`CompletionRecord`].
+//!
+//! [`CompletionRecord`]: crate::CompletionRecord
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Copy the files in `workspace_path` matching `patterns` into `dest`,
+/// keeping their relative layout. A pattern matching a directory (such as
+/// `coverage/**`) keeps every file under it. Returns the copied paths,
+/// relative to the workspace and sorted.
+pub fn capture(workspace_path: &Path, patterns: &[String], dest: &Path) -> Result<Vec<String>> {
+    let mut matched = BTreeSet::new();
+    for pattern in patterns {
+        // A trailing `**` only yields subdirectories; match the directory
+        // itself and keep everything beneath it instead
+        let pattern_dir = pattern.strip_suffix("/**").unwrap_or(pattern);
+        let full = workspace_path.join(pattern_dir);
+        let entries = glob::glob(&full.to_string_lossy())
+            .with_context(|| format!("Invalid artifact pattern '{}'", pattern))?;
+        for path in entries.filter_map(|p| p.ok()) {
+            let mut files = Vec::new();
+            collect_files(&path, &mut files);
+            for file in files {
+                if let Ok(relative) = file.strip_prefix(workspace_path) {
+                    matched.insert(relative.to_path_buf());
+                }
+            }
+        }
+    }
+
+    let mut kept = Vec::with_capacity(matched.len());
+    for relative in matched {
+        let target = dest.join(&relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::copy(workspace_path.join(&relative), &target)
+            .with_context(|| format!("Failed to copy artifact {}", relative.display()))?;
+        kept.push(relative.display().to_string());
+    }
+    Ok(kept)
+}
+
+/// `path` itself if it is a file, or every file beneath it if a directory.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.flatten() {
+            collect_files(&entry.path(), files);
+        }
+    }
+}
+
+/// Absolute paths of an assignment's kept artifacts.
+pub fn paths(dir: &Path, artifacts: &[String]) -> Vec<PathBuf> {
+    artifacts.iter().map(|a| dir.join(a)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_copies_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = dir.path().join("ws");
+        std::fs::create_dir_all(ws.join("coverage/html")).unwrap();
+        std::fs::write(ws.join("PLAN.md"), "plan").unwrap();
+        std::fs::write(ws.join("coverage/lcov.info"), "lcov").unwrap();
+        std::fs::write(ws.join("coverage/html/index.html"), "<html>").unwrap();
+        std::fs::write(ws.join("main.rs"), "fn main() {}").unwrap();
+
+        let dest = dir.path().join("artifacts/a1");
+        let patterns = vec!["PLAN.md".to_string(), "coverage/**".to_string(), "missing.txt".to_string()];
+        let kept = capture(&ws, &patterns, &dest).unwrap();
+
+        assert_eq!(kept, vec!["PLAN.md", "coverage/html/index.html", "coverage/lcov.info"]);
+        assert_eq!(std::fs::read_to_string(dest.join("coverage/html/index.html")).unwrap(), "<html>");
+        assert!(!dest.join("main.rs").exists());
+        assert_eq!(paths(&dest, &kept)[0], dest.join("PLAN.md"));
+    }
+}
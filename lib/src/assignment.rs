@@ -1,11 +1,15 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
-use std::time::SystemTime;
 use tracing::{debug, info};
 
+use crate::assignment_store::{AssignmentStore, HistoryFilter, JsonStore};
+use crate::ids::{AncillaryId, TaskId};
+use crate::ownership::{AssignmentOwned, AssignmentOwner};
+use crate::usage::Usage;
+
 /// How the assignment was created
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type")]
@@ -14,7 +18,13 @@ pub enum AssignmentSource {
     #[serde(alias = "Bead")]
     Reference,
     /// Created from a prompt (task may have been auto-created)
-    Prompt { original_prompt: String },
+    Prompt {
+        original_prompt: String,
+        /// The task was created for this prompt, so it has no value of its
+        /// own; abort can delete it instead of reopening it
+        #[serde(default)]
+        auto_created_bead: bool,
+    },
 }
 
 /// Current status of an assignment.
@@ -50,10 +60,10 @@ pub struct CompletionRecord {
     /// Original assignment ID
     pub assignment_id: String,
     /// Ancillary that worked on it
-    pub ancillary_id: String,
+    pub ancillary_id: AncillaryId,
     /// Task identifier (e.g., bead ID)
     #[serde(alias = "external_id", alias = "bead_id", default, skip_serializing_if = "Option::is_none")]
-    pub task_id: Option<String>,
+    pub task_id: Option<TaskId>,
     /// Segment name
     pub segment: String,
     /// When the assignment was completed/aborted (RFC 3339)
@@ -63,6 +73,12 @@ pub struct CompletionRecord {
     /// Final jj revision hash (if available)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub final_revision: Option<String>,
+    /// Files kept from the workspace, relative to the assignment's artifact dir
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+    /// Tokens the agent used and their estimated cost (see [`crate::usage`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,10 +95,10 @@ pub struct Assignment {
     /// Unique identifier for this assignment
     pub id: String,
     /// Ancillary identifier (e.g., "Toren One")
-    pub ancillary_id: String,
+    pub ancillary_id: AncillaryId,
     /// Task identifier (e.g., bead ID "breq-a1b2") — optional
     #[serde(alias = "external_id", alias = "bead_id", default, skip_serializing_if = "Option::is_none")]
-    pub task_id: Option<String>,
+    pub task_id: Option<TaskId>,
     /// Segment name (e.g., "toren")
     pub segment: String,
     /// Absolute path to the workspace
@@ -114,6 +130,328 @@ pub struct Assignment {
     /// Used as the comparison reference for has_changes and workspace_info.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub base_branch: Option<String>,
+    /// Free-form labels for filtering and grouping
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    /// Subdirectory of the workspace the work is scoped to (e.g. "crates/foo").
+    /// The agent session starts there and change detection only looks inside it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_path: Option<PathBuf>,
+    /// Intent the work was assigned with (e.g. "implement", "review")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<String>,
+    /// Instruction the assignment was last resumed with, if one was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_instruction: Option<String>,
+    /// When `breq amend` last replaced the original prompt; resume then
+    /// repeats the amended prompt to the agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_amended_at: Option<String>,
+    /// Process running the agent (breq session or daemon work), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<AssignmentOwner>,
+    /// Append-only journal of free-form notes, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<AssignmentNote>,
+    /// Task this assignment waits for: work doesn't start until a completion
+    /// record for it lands in the history. Cleared once the work starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<TaskId>,
+    /// Segment group (`[segments.groups]`) the assignment spans, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Workspaces in the group's other segments, beside `workspace_path`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub companions: Vec<CompanionWorkspace>,
+    /// Tokens the agent has used so far and their estimated cost, refreshed
+    /// from the session logs when an agent session ends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+/// A workspace of a segment group assignment in one of the group's other
+/// segments. It shares the ancillary's workspace name and is completed and
+/// cleaned up with the assignment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompanionWorkspace {
+    pub segment: String,
+    pub segment_path: PathBuf,
+    pub workspace_path: PathBuf,
+    /// Base branch at the time of assignment (for git worktrees)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_branch: Option<String>,
+}
+
+/// A timestamped note attached to an assignment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignmentNote {
+    /// When the note was added (RFC 3339)
+    pub at: String,
+    pub text: String,
+}
+
+impl Assignment {
+    /// Directory the agent works in: the focus path inside the workspace, or
+    /// the workspace root when unscoped.
+    pub fn working_dir(&self) -> PathBuf {
+        match self.focus_path {
+            Some(ref focus) => self.workspace_path.join(focus),
+            None => self.workspace_path.clone(),
+        }
+    }
+
+    /// Prompt section listing every workspace of a segment group
+    /// assignment, so the agent knows where each repo's work goes.
+    pub fn group_prompt(&self) -> Option<String> {
+        if self.companions.is_empty() {
+            return None;
+        }
+        let mut prompt = format!(
+            "This task spans the {} segment group; each repository has its own workspace:\n- {}: {} (you start here)",
+            self.group.as_deref().unwrap_or_default(),
+            self.segment,
+            self.workspace_path.display()
+        );
+        for companion in &self.companions {
+            prompt.push_str(&format!("\n- {}: {}", companion.segment, companion.workspace_path.display()));
+        }
+        Some(prompt)
+    }
+
+    /// The task this assignment is still waiting for, given the IDs of
+    /// completed tasks (see [`AssignmentManager::completed_task_ids`]).
+    pub fn blocked_on(&self, completed: &HashSet<TaskId>) -> Option<&TaskId> {
+        self.after.as_ref().filter(|task| !completed.contains(*task))
+    }
+}
+
+/// Normalize a `--path` focus argument to a relative path inside the repo.
+///
+/// Rejects absolute paths and `..` components; strips `./` and trailing
+/// separators. Returns `None` for paths that point at the repo root.
+pub fn normalize_focus_path(path: &str) -> Result<Option<PathBuf>> {
+    use std::path::Component;
+    let mut normalized = PathBuf::new();
+    for component in std::path::Path::new(path).components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            _ => anyhow::bail!(
+                "Focus path must be relative to the repo root and stay inside it: {}",
+                path
+            ),
+        }
+    }
+    Ok(Some(normalized).filter(|p| !p.as_os_str().is_empty()))
+}
+
+impl AssignmentSource {
+    /// The kind of source, without its payload.
+    pub fn kind(&self) -> AssignmentSourceKind {
+        match self {
+            AssignmentSource::Reference => AssignmentSourceKind::Reference,
+            AssignmentSource::Prompt { .. } => AssignmentSourceKind::Prompt,
+        }
+    }
+
+    /// Whether the assignment's task was created from its prompt.
+    pub fn auto_created_bead(&self) -> bool {
+        matches!(self, AssignmentSource::Prompt { auto_created_bead: true, .. })
+    }
+}
+
+/// Payload-free discriminant of [`AssignmentSource`], used for filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignmentSourceKind {
+    Reference,
+    Prompt,
+}
+
+/// Sort order for [`AssignmentQuery`] results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AssignmentSort {
+    /// Segment name, then ancillary number
+    #[default]
+    Ancillary,
+    /// Creation time
+    Created,
+    /// Last update time
+    Updated,
+}
+
+/// Typed query over assignments.
+///
+/// Built with chained methods and executed with [`AssignmentManager::query`]:
+///
+/// ```ignore
+/// let recent = mgr.query(
+///     &AssignmentQuery::new()
+///         .segment("toren")
+///         .label("frontend")
+///         .sort(AssignmentSort::Created)
+///         .descending()
+///         .limit(5),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AssignmentQuery {
+    segment: Option<String>,
+    ancillary_min: Option<u32>,
+    ancillary_max: Option<u32>,
+    labels: Vec<String>,
+    created_after: Option<chrono::DateTime<chrono::Utc>>,
+    created_before: Option<chrono::DateTime<chrono::Utc>>,
+    source: Option<AssignmentSourceKind>,
+    sort: AssignmentSort,
+    descending: bool,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl AssignmentQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only assignments in this segment (case-insensitive)
+    pub fn segment(mut self, segment: impl Into<String>) -> Self {
+        self.segment = Some(segment.into());
+        self
+    }
+
+    /// Only assignments whose ancillary number falls within `range`
+    pub fn ancillary_range(mut self, range: std::ops::RangeInclusive<u32>) -> Self {
+        self.ancillary_min = Some(*range.start());
+        self.ancillary_max = Some(*range.end());
+        self
+    }
+
+    /// Only assignments carrying this label. Repeated calls require all labels.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.labels.push(label.into());
+        self
+    }
+
+    /// Only assignments created at or after `t`
+    pub fn created_after(mut self, t: chrono::DateTime<chrono::Utc>) -> Self {
+        self.created_after = Some(t);
+        self
+    }
+
+    /// Only assignments created before `t`
+    pub fn created_before(mut self, t: chrono::DateTime<chrono::Utc>) -> Self {
+        self.created_before = Some(t);
+        self
+    }
+
+    /// Only assignments created from this kind of source
+    pub fn source(mut self, kind: AssignmentSourceKind) -> Self {
+        self.source = Some(kind);
+        self
+    }
+
+    pub fn sort(mut self, sort: AssignmentSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Reverse the sort order
+    pub fn descending(mut self) -> Self {
+        self.descending = true;
+        self
+    }
+
+    /// Skip the first `offset` results (applied after sorting)
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Return at most `limit` results (applied after sorting and offset)
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether a single assignment passes every filter in this query.
+    pub fn matches(&self, a: &Assignment) -> bool {
+        if let Some(ref segment) = self.segment {
+            if !a.segment.eq_ignore_ascii_case(segment) {
+                return false;
+            }
+        }
+        if self.ancillary_min.is_some() || self.ancillary_max.is_some() {
+            let Some(n) = a.ancillary_num.or_else(|| ancillary_number(&a.ancillary_id)) else {
+                return false;
+            };
+            if self.ancillary_min.is_some_and(|min| n < min)
+                || self.ancillary_max.is_some_and(|max| n > max)
+            {
+                return false;
+            }
+        }
+        if !self.labels.iter().all(|l| a.labels.contains(l)) {
+            return false;
+        }
+        if self.created_after.is_some() || self.created_before.is_some() {
+            let Some(created) = parse_timestamp(&a.created_at) else {
+                return false;
+            };
+            if self.created_after.is_some_and(|t| created < t)
+                || self.created_before.is_some_and(|t| created >= t)
+            {
+                return false;
+            }
+        }
+        if let Some(kind) = self.source {
+            if a.source.kind() != kind {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Filter, sort, and paginate a set of assignments.
+    pub fn apply<'a>(&self, assignments: impl IntoIterator<Item = &'a Assignment>) -> Vec<&'a Assignment> {
+        let mut results: Vec<&Assignment> = assignments
+            .into_iter()
+            .filter(|a| self.matches(a))
+            .collect();
+
+        match self.sort {
+            AssignmentSort::Ancillary => results.sort_by(|a, b| {
+                a.segment
+                    .to_lowercase()
+                    .cmp(&b.segment.to_lowercase())
+                    .then_with(|| {
+                        let na = ancillary_number(&a.ancillary_id).unwrap_or(u32::MAX);
+                        let nb = ancillary_number(&b.ancillary_id).unwrap_or(u32::MAX);
+                        na.cmp(&nb)
+                    })
+            }),
+            AssignmentSort::Created => {
+                results.sort_by_key(|a| parse_timestamp(&a.created_at))
+            }
+            AssignmentSort::Updated => {
+                results.sort_by_key(|a| parse_timestamp(&a.updated_at))
+            }
+        }
+        if self.descending {
+            results.reverse();
+        }
+
+        results
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+fn parse_timestamp(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Utc))
 }
 
 /// Max number that gets a word name (1-99 use English words, 100+ use digits)
@@ -151,126 +489,140 @@ pub fn word_to_number(word: &str) -> Option<u32> {
     if let Some(&n) = word_to_number_map().get(&word.to_lowercase()) {
         return Some(n);
     }
-    // Fall back to plain number parsing (handles "100", "101", etc.)
-    word.parse::<u32>().ok()
+    // Fall back to plain digits (handles "100", "101", etc.). Only the
+    // canonical form: "007" or "+7" would name a different workspace directory.
+    let canonical = !word.is_empty()
+        && word.bytes().all(|b| b.is_ascii_digit())
+        && (word.len() == 1 || !word.starts_with('0'));
+    if canonical {
+        word.parse::<u32>().ok()
+    } else {
+        None
+    }
+}
+
+/// Workspace directory name for an ancillary number ("one", "twenty-one", "101")
+pub fn workspace_name(number: u32) -> String {
+    number_to_word(number).to_lowercase()
+}
+
+/// Canonical workspace name for a user-supplied name or number: "21",
+/// "Twenty-One" and "twenty-one" all give "twenty-one". Other names are
+/// lowercased.
+pub fn normalize_workspace_name(name: &str) -> String {
+    match word_to_number(name) {
+        Some(n) => workspace_name(n),
+        None => name.to_lowercase(),
+    }
 }
 
 /// Generate an ancillary ID from segment name and number
-pub fn ancillary_id(segment: &str, number: u32) -> String {
-    let segment_cap = capitalize(segment);
-    format!("{} {}", segment_cap, number_to_word(number))
+pub fn ancillary_id(segment: &str, number: u32) -> AncillaryId {
+    AncillaryId::new(segment, number)
 }
 
 /// Extract the number from an ancillary ID
-pub fn ancillary_number(ancillary_id: &str) -> Option<u32> {
-    ancillary_id
-        .split_whitespace()
-        .last()
-        .and_then(word_to_number)
+pub fn ancillary_number(ancillary_id: &AncillaryId) -> Option<u32> {
+    ancillary_id.number()
 }
 
 /// Extract the segment from an ancillary ID (lowercased)
-pub fn ancillary_segment(ancillary_id: &str) -> Option<String> {
-    ancillary_id
-        .split_whitespace()
-        .next()
-        .map(|s| s.to_lowercase())
-}
-
-fn capitalize(s: &str) -> String {
-    let mut chars = s.chars();
-    match chars.next() {
-        None => String::new(),
-        Some(c) => c.to_uppercase().chain(chars).collect(),
-    }
+pub fn ancillary_segment(ancillary_id: &AncillaryId) -> Option<String> {
+    ancillary_id.segment()
 }
 
 /// Reference type for command disambiguation
 #[derive(Debug, Clone, PartialEq)]
 pub enum AssignmentRef {
     /// Reference by task ID (e.g., bead ID "breq-a1b2")
-    TaskId(String),
+    TaskId(TaskId),
     /// Reference by ancillary ID (e.g., "Toren One" or just "One")
-    Ancillary(String),
+    Ancillary(AncillaryId),
 }
 
 impl AssignmentRef {
     /// Parse a reference string into an AssignmentRef.
     ///
     /// Rules:
-    /// - Contains hyphen -> treat as task ID
     /// - Contains space -> treat as ancillary name
-    /// - Otherwise -> try ancillary name first, then task ID
+    /// - Number word, digits or a name from the segment's vocabulary ->
+    ///   ancillary in `segment`
+    /// - Otherwise -> task ID
     pub fn parse(s: &str, segment: &str) -> Self {
-        if s.contains('-') {
-            AssignmentRef::TaskId(s.to_string())
-        } else if s.contains(' ') {
-            AssignmentRef::Ancillary(s.to_string())
-        } else {
-            // Try to interpret as ancillary number word
-            if word_to_number(s).is_some() {
-                let full_id = format!("{} {}", capitalize(segment), capitalize(s));
-                AssignmentRef::Ancillary(full_id)
-            } else {
-                AssignmentRef::TaskId(s.to_string())
+        if let Some((prefix, last)) = s.rsplit_once(' ') {
+            // "Toren Twenty-One", "toren 21": canonicalize the number so it
+            // matches the stored ancillary ID
+            match crate::naming::slot_number(prefix.trim(), last) {
+                Some(n) => AssignmentRef::Ancillary(ancillary_id(prefix.trim(), n)),
+                None => AssignmentRef::Ancillary(AncillaryId::unchecked(s)),
             }
+        } else if let Some(n) = crate::naming::slot_number(segment, s) {
+            // Ancillary number word ("one", "twenty-one"), digits ("101") or
+            // name; checked before the hyphen rule since number words contain hyphens
+            AssignmentRef::Ancillary(ancillary_id(segment, n))
+        } else {
+            AssignmentRef::TaskId(TaskId::unchecked(s))
         }
     }
 }
 
 /// Manages assignments between ancillaries.
-/// Persistent storage in ~/.toren/assignments.json.
+/// Persistent storage in ~/.toren/assignments.json, or ~/.toren/toren.db with
+/// the `sqlite` feature (see [`crate::assignment_store`]).
 /// Used by both CLI (breq) and daemon (toren).
 ///
-/// Automatically reloads from disk when the file has been modified externally
-/// (e.g., by breq while the daemon is running).
+/// Automatically reloads from storage when another process has written to it
+/// (e.g., breq while the daemon is running).
 pub struct AssignmentManager {
-    /// Path to the assignments.json file
-    storage_path: PathBuf,
+    /// Where assignments and completion history are persisted
+    store: Box<dyn AssignmentStore>,
     /// Assignments keyed by assignment ID
     assignments: HashMap<String, Assignment>,
-    /// Last known modification time of the assignments file
-    last_mtime: Option<SystemTime>,
 }
 
 impl AssignmentManager {
     /// Create a new AssignmentManager with persistent storage in ~/.toren/
     pub fn new() -> Result<Self> {
-        let storage_path = dirs::home_dir()
+        let toren_dir = dirs::home_dir()
             .context("Could not determine home directory")?
-            .join(".toren")
-            .join("assignments.json");
+            .join(".toren");
+        #[cfg(feature = "sqlite")]
+        {
+            let store = crate::assignment_store::SqliteStore::open(toren_dir.join("toren.db"))?;
+            Self::with_store(Box::new(store))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        {
+            let storage_path = toren_dir.join("assignments.json");
+            if !storage_path.exists() && toren_dir.join("toren.db").exists() {
+                anyhow::bail!(
+                    "Assignments are stored in {}, but this build lacks the `sqlite` feature",
+                    toren_dir.join("toren.db").display()
+                );
+            }
+            Self::with_storage_path(storage_path)
+        }
+    }
+
+    /// Create an AssignmentManager backed by a specific assignments.json file.
+    /// Completion history is written alongside it.
+    pub fn with_storage_path(storage_path: PathBuf) -> Result<Self> {
+        Self::with_store(Box::new(JsonStore::new(storage_path)))
+    }
 
+    /// Create an AssignmentManager over any store.
+    pub fn with_store(store: Box<dyn AssignmentStore>) -> Result<Self> {
         let mut mgr = Self {
-            storage_path,
+            store,
             assignments: HashMap::new(),
-            last_mtime: None,
         };
         mgr.load()?;
         Ok(mgr)
     }
 
-    /// Load assignments from disk
+    /// Load assignments from storage
     fn load(&mut self) -> Result<()> {
-        if !self.storage_path.exists() {
-            debug!(
-                "No existing assignments file at {}",
-                self.storage_path.display()
-            );
-            self.last_mtime = None;
-            return Ok(());
-        }
-
-        let metadata = std::fs::metadata(&self.storage_path)
-            .with_context(|| format!("Failed to stat {}", self.storage_path.display()))?;
-        let mtime = metadata.modified().ok();
-
-        let content = std::fs::read_to_string(&self.storage_path)
-            .with_context(|| format!("Failed to read {}", self.storage_path.display()))?;
-
-        let assignments: Vec<Assignment> =
-            serde_json::from_str(&content).with_context(|| "Failed to parse assignments.json")?;
-
+        let assignments = self.store.load()?;
         self.assignments.clear();
         for mut a in assignments {
             // Backfill ancillary_num for assignments created before this field existed
@@ -279,51 +631,27 @@ impl AssignmentManager {
             }
             self.assignments.insert(a.id.clone(), a);
         }
-        self.last_mtime = mtime;
 
-        debug!("Loaded {} assignments from disk", self.assignments.len());
+        debug!("Loaded {} assignments from {}", self.assignments.len(), self.store.path().display());
         Ok(())
     }
 
-    /// Reload from disk if the file has been modified externally.
+    /// Reload from storage if another process has written to it.
     /// Called automatically before read operations to stay in sync
-    /// when another process (e.g., breq) modifies assignments.json.
+    /// when another process (e.g., breq) modifies assignments.
     fn reload_if_changed(&mut self) {
-        let current_mtime = self
-            .storage_path
-            .metadata()
-            .ok()
-            .and_then(|m| m.modified().ok());
-
-        if current_mtime != self.last_mtime {
-            debug!("assignments.json changed on disk, reloading");
+        if self.store.changed() {
+            debug!("{} changed on disk, reloading", self.store.path().display());
             if let Err(e) = self.load() {
                 tracing::warn!("Failed to reload assignments from disk: {}", e);
             }
         }
     }
 
-    /// Save assignments to disk
+    /// Save assignments to storage
     pub fn save(&mut self) -> Result<()> {
         let assignments: Vec<&Assignment> = self.assignments.values().collect();
-        let content = serde_json::to_string_pretty(&assignments)
-            .with_context(|| "Failed to serialize assignments")?;
-
-        // Ensure parent directory exists
-        if let Some(parent) = self.storage_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-
-        std::fs::write(&self.storage_path, content)
-            .with_context(|| format!("Failed to write {}", self.storage_path.display()))?;
-
-        // Update tracked mtime so we don't reload our own writes
-        self.last_mtime = self
-            .storage_path
-            .metadata()
-            .ok()
-            .and_then(|m| m.modified().ok());
-
+        self.store.save(&assignments)?;
         debug!("Saved {} assignments to disk", self.assignments.len());
         Ok(())
     }
@@ -335,8 +663,8 @@ impl AssignmentManager {
     #[allow(clippy::too_many_arguments)]
     pub fn create(
         &mut self,
-        ancillary_id: &str,
-        task_id: Option<&str>,
+        ancillary_id: &AncillaryId,
+        task_id: Option<&TaskId>,
         source: AssignmentSource,
         segment: &str,
         workspace_path: PathBuf,
@@ -351,8 +679,8 @@ impl AssignmentManager {
         let assignment = Assignment {
             ancillary_num: ancillary_number(ancillary_id),
             id,
-            ancillary_id: ancillary_id.to_string(),
-            task_id: task_id.map(|s| s.to_string()),
+            ancillary_id: ancillary_id.clone(),
+            task_id: task_id.cloned(),
             segment: segment.to_string(),
             workspace_path,
             source,
@@ -364,6 +692,17 @@ impl AssignmentManager {
             task_source: task_source.map(|s| s.to_string()),
             session_id: None,
             base_branch,
+            labels: Vec::new(),
+            focus_path: None,
+            intent: None,
+            resume_instruction: None,
+            owner: None,
+            notes: Vec::new(),
+            after: None,
+            group: None,
+            companions: Vec::new(),
+            prompt_amended_at: None,
+            usage: None,
         };
 
         self.assignments
@@ -380,8 +719,8 @@ impl AssignmentManager {
     /// Create a new assignment from an existing bead (backward-compat wrapper).
     pub fn create_from_bead(
         &mut self,
-        ancillary_id: &str,
-        bead_id: &str,
+        ancillary_id: &AncillaryId,
+        bead_id: &TaskId,
         segment: &str,
         workspace_path: PathBuf,
         bead_title: Option<String>,
@@ -404,8 +743,8 @@ impl AssignmentManager {
     #[allow(clippy::too_many_arguments)]
     pub fn create_from_prompt(
         &mut self,
-        ancillary_id: &str,
-        bead_id: &str,
+        ancillary_id: &AncillaryId,
+        bead_id: &TaskId,
         original_prompt: &str,
         segment: &str,
         workspace_path: PathBuf,
@@ -417,6 +756,7 @@ impl AssignmentManager {
             Some(bead_id),
             AssignmentSource::Prompt {
                 original_prompt: original_prompt.to_string(),
+                auto_created_bead: true,
             },
             segment,
             workspace_path,
@@ -443,19 +783,63 @@ impl AssignmentManager {
         }
     }
 
+    /// Record an assignment's token usage. Leaves `updated_at` alone: usage
+    /// is derived from the session logs, not a change to the assignment.
+    pub fn set_usage(&mut self, assignment_id: &str, usage: Usage) -> Result<bool> {
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.usage = Some(usage);
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Record the instruction an assignment was resumed with.
+    pub fn update_resume_instruction(&mut self, assignment_id: &str, instruction: &str) -> Result<bool> {
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.resume_instruction = Some(instruction.to_string());
+            assignment.updated_at = chrono::Utc::now().to_rfc3339();
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Replace the prompt of an assignment created from one. Errors for an
+    /// assignment created from a task reference, which has no prompt.
+    pub fn amend_prompt(&mut self, assignment_id: &str, prompt: &str) -> Result<bool> {
+        let Some(assignment) = self.assignments.get_mut(assignment_id) else {
+            return Ok(false);
+        };
+        let AssignmentSource::Prompt { ref mut original_prompt, .. } = assignment.source else {
+            anyhow::bail!(
+                "{} was created from a task, not a prompt; edit the task instead",
+                assignment.ancillary_id
+            );
+        };
+        *original_prompt = prompt.to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        assignment.prompt_amended_at = Some(now.clone());
+        assignment.updated_at = now;
+        self.save()?;
+        Ok(true)
+    }
+
     /// Update task fields on an existing assignment.
     /// Only overwrites fields that are Some.
     pub fn update_task_fields(
         &mut self,
         assignment_id: &str,
-        task_id: Option<&str>,
+        task_id: Option<&TaskId>,
         task_title: Option<&str>,
         task_url: Option<&str>,
         task_source: Option<&str>,
     ) -> Result<bool> {
         if let Some(assignment) = self.assignments.get_mut(assignment_id) {
             if let Some(id) = task_id {
-                assignment.task_id = Some(id.to_string());
+                assignment.task_id = Some(id.clone());
             }
             if let Some(title) = task_title {
                 assignment.task_title = Some(title.to_string());
@@ -467,10 +851,8 @@ impl AssignmentManager {
                 assignment.task_source = Some(source.to_string());
             }
             // Update source to Reference if task_id was set and source was Prompt
-            if task_id.is_some() {
-                if matches!(assignment.source, AssignmentSource::Prompt { .. }) {
-                    assignment.source = AssignmentSource::Reference;
-                }
+            if task_id.is_some() && matches!(assignment.source, AssignmentSource::Prompt { .. }) {
+                assignment.source = AssignmentSource::Reference;
             }
             assignment.updated_at = chrono::Utc::now().to_rfc3339();
             self.save()?;
@@ -498,6 +880,7 @@ impl AssignmentManager {
         assignment: &Assignment,
         reason: CompletionReason,
         final_revision: Option<String>,
+        artifacts: Vec<String>,
     ) -> Result<()> {
         let record = CompletionRecord {
             assignment_id: assignment.id.clone(),
@@ -507,26 +890,11 @@ impl AssignmentManager {
             completed_at: chrono::Utc::now().to_rfc3339(),
             reason,
             final_revision,
+            artifacts,
+            usage: assignment.usage,
         };
 
-        // Append to completion history file
-        let history_path = self
-            .storage_path
-            .parent()
-            .unwrap_or(std::path::Path::new("."))
-            .join("completion_history.jsonl");
-
-        let mut line = serde_json::to_string(&record)
-            .with_context(|| "Failed to serialize completion record")?;
-        line.push('\n');
-
-        use std::io::Write;
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&history_path)
-            .with_context(|| format!("Failed to open {}", history_path.display()))?;
-        file.write_all(line.as_bytes())?;
+        self.store.append_history(&record)?;
 
         debug!(
             "Recorded completion for assignment {} ({})",
@@ -540,6 +908,51 @@ impl AssignmentManager {
         Ok(())
     }
 
+    /// Completion records, oldest first. Unparseable records are skipped.
+    pub fn history(&self) -> Result<Vec<CompletionRecord>> {
+        self.store.history()
+    }
+
+    /// Completion records matching `filter`, newest first.
+    pub fn recent_history(&self, filter: &HistoryFilter) -> Result<Vec<CompletionRecord>> {
+        self.store.recent_history(filter)
+    }
+
+    /// Tasks with a `completed` record in the history. Aborts don't count:
+    /// an assignment waiting on an aborted task keeps waiting.
+    pub fn completed_task_ids(&self) -> Result<HashSet<TaskId>> {
+        Ok(self
+            .history()?
+            .into_iter()
+            .filter(|record| matches!(record.reason, CompletionReason::Completed))
+            .filter_map(|record| record.task_id)
+            .collect())
+    }
+
+    /// Remove completion records matching `remove` from the history,
+    /// returning them.
+    pub fn prune_history(
+        &self,
+        remove: impl Fn(&CompletionRecord) -> bool,
+    ) -> Result<Vec<CompletionRecord>> {
+        self.store.prune_history(&remove)
+    }
+
+    /// Where an assignment's completion artifacts are kept
+    /// (`~/.toren/artifacts/<assignment_id>`).
+    pub fn artifacts_dir(&self, assignment_id: &str) -> PathBuf {
+        self.toren_dir().join("artifacts").join(assignment_id)
+    }
+
+    /// The undo journal kept alongside the assignments (`~/.toren/undo.jsonl`).
+    pub fn undo_journal(&self) -> crate::undo::UndoJournal {
+        crate::undo::UndoJournal::new(self.toren_dir().join("undo.jsonl"))
+    }
+
+    fn toren_dir(&self) -> &Path {
+        self.store.path().parent().unwrap_or(Path::new("."))
+    }
+
     /// Get assignment by ID
     pub fn get(&mut self, assignment_id: &str) -> Option<&Assignment> {
         self.reload_if_changed();
@@ -547,30 +960,40 @@ impl AssignmentManager {
     }
 
     /// Get all assignments for a task ID
-    pub fn get_by_task_id(&mut self, task_id: &str) -> Vec<&Assignment> {
+    pub fn get_by_task_id(&mut self, task_id: &TaskId) -> Vec<&Assignment> {
         self.reload_if_changed();
         self.assignments
             .values()
-            .filter(|a| a.task_id.as_deref() == Some(task_id))
+            .filter(|a| a.task_id.as_ref() == Some(task_id))
             .collect()
     }
 
     /// Get all assignments for an ancillary
-    pub fn get_by_ancillary(&mut self, ancillary_id: &str) -> Vec<&Assignment> {
+    pub fn get_by_ancillary(&mut self, ancillary_id: &AncillaryId) -> Vec<&Assignment> {
         self.reload_if_changed();
         self.assignments
             .values()
-            .filter(|a| a.ancillary_id.to_lowercase() == ancillary_id.to_lowercase())
+            .filter(|a| a.ancillary_id.matches(ancillary_id))
             .collect()
     }
 
     /// Get active assignment for an ancillary (should be at most one).
     /// All assignments are active (terminal actions remove the record).
-    pub fn get_active_for_ancillary(&mut self, ancillary_id: &str) -> Option<&Assignment> {
+    pub fn get_active_for_ancillary(&mut self, ancillary_id: &AncillaryId) -> Option<&Assignment> {
         self.reload_if_changed();
         self.assignments
             .values()
-            .find(|a| a.ancillary_id.to_lowercase() == ancillary_id.to_lowercase())
+            .find(|a| a.ancillary_id.matches(ancillary_id))
+    }
+
+    /// Put back an assignment removed by complete, abort or clean (see
+    /// [`crate::undo`]). Its owner is dropped: that process is gone.
+    pub fn restore(&mut self, mut assignment: Assignment) -> Result<()> {
+        self.reload_if_changed();
+        assignment.owner = None;
+        assignment.updated_at = chrono::Utc::now().to_rfc3339();
+        self.assignments.insert(assignment.id.clone(), assignment);
+        self.save()
     }
 
     /// Remove assignment by ID
@@ -583,11 +1006,11 @@ impl AssignmentManager {
     }
 
     /// Remove all assignments for an ancillary
-    pub fn dismiss_ancillary(&mut self, ancillary_id: &str) -> Result<Vec<Assignment>> {
+    pub fn dismiss_ancillary(&mut self, ancillary_id: &AncillaryId) -> Result<Vec<Assignment>> {
         let ids: Vec<_> = self
             .assignments
             .values()
-            .filter(|a| a.ancillary_id.to_lowercase() == ancillary_id.to_lowercase())
+            .filter(|a| a.ancillary_id.matches(ancillary_id))
             .map(|a| a.id.clone())
             .collect();
 
@@ -609,11 +1032,11 @@ impl AssignmentManager {
     }
 
     /// Remove all assignments for a task ID
-    pub fn dismiss_task_id(&mut self, task_id: &str) -> Result<Vec<Assignment>> {
+    pub fn dismiss_task_id(&mut self, task_id: &TaskId) -> Result<Vec<Assignment>> {
         let ids: Vec<_> = self
             .assignments
             .values()
-            .filter(|a| a.task_id.as_deref() == Some(task_id))
+            .filter(|a| a.task_id.as_ref() == Some(task_id))
             .map(|a| a.id.clone())
             .collect();
 
@@ -652,30 +1075,159 @@ impl AssignmentManager {
     /// List active assignments (all assignments are active — terminal actions remove them).
     /// Sorted by segment name, then ancillary number.
     pub fn list_active(&mut self) -> Vec<&Assignment> {
-        self.reload_if_changed();
-        let mut assignments: Vec<&Assignment> = self.assignments.values().collect();
-        assignments.sort_by(|a, b| {
-            a.segment
-                .to_lowercase()
-                .cmp(&b.segment.to_lowercase())
-                .then_with(|| {
-                    let na = ancillary_number(&a.ancillary_id).unwrap_or(u32::MAX);
-                    let nb = ancillary_number(&b.ancillary_id).unwrap_or(u32::MAX);
-                    na.cmp(&nb)
-                })
-        });
-        assignments
+        self.query(&AssignmentQuery::new())
     }
 
     /// List active assignments for a specific segment, sorted by ancillary number.
     pub fn list_active_segment(&mut self, segment: &str) -> Vec<&Assignment> {
+        self.query(&AssignmentQuery::new().segment(segment))
+    }
+
+    /// Run a typed query over all assignments.
+    pub fn query(&mut self, query: &AssignmentQuery) -> Vec<&Assignment> {
         self.reload_if_changed();
-        let mut assignments: Vec<&Assignment> = self.assignments
-            .values()
-            .filter(|a| a.segment.to_lowercase() == segment.to_lowercase())
-            .collect();
-        assignments.sort_by_key(|a| ancillary_number(&a.ancillary_id).unwrap_or(u32::MAX));
-        assignments
+        query.apply(self.assignments.values())
+    }
+
+    /// Replace the labels on an assignment
+    pub fn set_labels(&mut self, assignment_id: &str, labels: Vec<String>) -> Result<bool> {
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.labels = labels;
+            assignment.updated_at = chrono::Utc::now().to_rfc3339();
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Append a note to an assignment. Returns the note, or None if the
+    /// assignment doesn't exist.
+    pub fn add_note(&mut self, assignment_id: &str, text: &str) -> Result<Option<AssignmentNote>> {
+        // Notes may be added from breq while the daemon holds its own copy
+        self.reload_if_changed();
+        let Some(assignment) = self.assignments.get_mut(assignment_id) else {
+            return Ok(None);
+        };
+        let now = chrono::Utc::now().to_rfc3339();
+        let note = AssignmentNote {
+            at: now.clone(),
+            text: text.trim().to_string(),
+        };
+        assignment.notes.push(note.clone());
+        assignment.updated_at = now;
+        self.save()?;
+        Ok(Some(note))
+    }
+
+    /// Set (or clear) the subdirectory an assignment is scoped to.
+    pub fn set_focus_path(&mut self, assignment_id: &str, focus_path: Option<PathBuf>) -> Result<bool> {
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.focus_path = focus_path;
+            assignment.updated_at = chrono::Utc::now().to_rfc3339();
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Set (or clear) the task an assignment waits for.
+    pub fn set_after(&mut self, assignment_id: &str, after: Option<TaskId>) -> Result<bool> {
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.after = after;
+            assignment.updated_at = chrono::Utc::now().to_rfc3339();
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Record the segment group an assignment spans and its workspaces in
+    /// the group's other segments.
+    pub fn set_companions(
+        &mut self,
+        assignment_id: &str,
+        group: &str,
+        companions: Vec<CompanionWorkspace>,
+    ) -> Result<bool> {
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.group = Some(group.to_string());
+            assignment.companions = companions;
+            assignment.updated_at = chrono::Utc::now().to_rfc3339();
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Record the intent an assignment was (re)assigned with.
+    pub fn set_intent(&mut self, assignment_id: &str, intent: Option<String>) -> Result<bool> {
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.intent = intent;
+            assignment.updated_at = chrono::Utc::now().to_rfc3339();
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Record `owner` as running the agent for an assignment.
+    ///
+    /// Fails with [`AssignmentOwned`] while another live process owns it,
+    /// unless `steal`. Returns `false` if the assignment doesn't exist.
+    pub fn claim_owner(&mut self, assignment_id: &str, owner: AssignmentOwner, steal: bool) -> Result<bool> {
+        self.check_owner(assignment_id, steal)?;
+        if let Some(assignment) = self.assignments.get_mut(assignment_id) {
+            assignment.owner = Some(owner);
+            assignment.updated_at = chrono::Utc::now().to_rfc3339();
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Fail with [`AssignmentOwned`] if another live process owns the
+    /// assignment, unless `steal`.
+    pub fn check_owner(&mut self, assignment_id: &str, steal: bool) -> Result<()> {
+        self.reload_if_changed();
+        let Some(assignment) = self.assignments.get(assignment_id) else {
+            return Ok(());
+        };
+        match assignment.owner {
+            Some(ref owner) if owner.blocks_current() => {
+                if !steal {
+                    return Err(AssignmentOwned {
+                        assignment_id: assignment.id.clone(),
+                        ancillary_id: assignment.ancillary_id.clone(),
+                        owner: owner.clone(),
+                    }
+                    .into_error());
+                }
+                info!("Taking over {} from {}", assignment.ancillary_id, owner);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Clear the owner if it is still `owner`; a later claim may have
+    /// replaced it.
+    pub fn release_owner(&mut self, assignment_id: &str, owner: &AssignmentOwner) -> Result<bool> {
+        self.reload_if_changed();
+        match self.assignments.get_mut(assignment_id) {
+            Some(assignment) if assignment.owner.as_ref() == Some(owner) => {
+                assignment.owner = None;
+                assignment.updated_at = chrono::Utc::now().to_rfc3339();
+                self.save()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 
     /// Find the next available ancillary for a segment.
@@ -690,7 +1242,7 @@ impl AssignmentManager {
         segment: &str,
         pool_size: u32,
         existing_workspaces: &[String],
-    ) -> String {
+    ) -> AncillaryId {
         self.reload_if_changed();
 
         // Any assignment record (regardless of status) means the number is occupied.
@@ -707,7 +1259,7 @@ impl AssignmentManager {
         // Also mark numbers for workspaces that exist on disk (e.g. a workspace
         // kept after its assignment was manually dismissed).
         for ws_name in existing_workspaces {
-            if let Some(n) = word_to_number(ws_name) {
+            if let Some(n) = crate::naming::slot_number(segment, ws_name) {
                 occupied.insert(n);
             }
         }
@@ -721,7 +1273,7 @@ impl AssignmentManager {
 
         // All pool slots used, find next available beyond pool
         let max = occupied.iter().max().copied().unwrap_or(0);
-        ancillary_id(segment, max + 1)
+        ancillary_id(segment, max.saturating_add(1))
     }
 
     /// Resolve an AssignmentRef to matching assignments
@@ -730,11 +1282,11 @@ impl AssignmentManager {
         match ref_ {
             AssignmentRef::TaskId(task_id) => self.assignments
                 .values()
-                .filter(|a| a.task_id.as_deref() == Some(task_id.as_str()))
+                .filter(|a| a.task_id.as_ref() == Some(task_id))
                 .collect(),
             AssignmentRef::Ancillary(ancillary_id) => self.assignments
                 .values()
-                .filter(|a| a.ancillary_id.to_lowercase() == ancillary_id.to_lowercase())
+                .filter(|a| a.ancillary_id.matches(ancillary_id))
                 .collect(),
         }
     }
@@ -743,6 +1295,42 @@ impl AssignmentManager {
     pub fn resolve_active(&mut self, ref_: &AssignmentRef) -> Vec<&Assignment> {
         self.resolve(ref_)
     }
+
+    /// Resolve an identifier from an API path to matching assignments.
+    ///
+    /// Precedence:
+    /// 1. Assignment ID (UUID), exact
+    /// 2. [`AssignmentRef::parse`], as the CLI does: a full ancillary name
+    ///    ("Toren One", "toren 21"), an ancillary number word or digits
+    ///    ("one", "21") in `segment`, or otherwise a task ID
+    ///
+    /// Without a segment hint, a bare number matches that ancillary number in
+    /// every segment (and a vocabulary name the slot it names in its segment), and task IDs aren't narrowed to a segment, so callers
+    /// should treat more than one match as ambiguous.
+    pub fn resolve_id(&mut self, id: &str, segment: Option<&str>) -> Vec<&Assignment> {
+        self.reload_if_changed();
+        if self.assignments.contains_key(id) {
+            return self.assignments.get(id).into_iter().collect();
+        }
+
+        let Some(segment) = segment else {
+            // A number, or a name from some segment's vocabulary
+            let names_slot =
+                |a: &Assignment| a.ancillary_num.is_some() && crate::naming::slot_number(&a.segment, id) == a.ancillary_num;
+            if !id.contains(' ') && (word_to_number(id).is_some() || self.assignments.values().any(names_slot)) {
+                return self.assignments.values().filter(|a| names_slot(a)).collect();
+            }
+            return self.resolve(&AssignmentRef::parse(id, ""));
+        };
+
+        let ref_ = AssignmentRef::parse(id, segment);
+        let in_segment = matches!(ref_, AssignmentRef::TaskId(_));
+        let mut matches = self.resolve(&ref_);
+        if in_segment {
+            matches.retain(|a| a.segment == segment);
+        }
+        matches
+    }
 }
 
 #[cfg(test)]
@@ -781,32 +1369,189 @@ mod tests {
 
     #[test]
     fn test_ancillary_number() {
-        assert_eq!(ancillary_number("Toren One"), Some(1));
-        assert_eq!(ancillary_number("Toren Five"), Some(5));
-        assert_eq!(ancillary_number("Toren Twenty-One"), Some(21));
-        assert_eq!(ancillary_number("Toren 100"), Some(100));
+        assert_eq!(ancillary_number(&"Toren One".parse().unwrap()), Some(1));
+        assert_eq!(ancillary_number(&"Toren Five".parse().unwrap()), Some(5));
+        assert_eq!(ancillary_number(&"Toren Twenty-One".parse().unwrap()), Some(21));
+        assert_eq!(ancillary_number(&"Toren 100".parse().unwrap()), Some(100));
     }
 
     #[test]
     fn test_assignment_ref_parse() {
         assert_eq!(
             AssignmentRef::parse("breq-a1b2", "toren"),
-            AssignmentRef::TaskId("breq-a1b2".to_string())
+            AssignmentRef::TaskId("breq-a1b2".parse().unwrap())
         );
         assert_eq!(
             AssignmentRef::parse("Toren One", "toren"),
-            AssignmentRef::Ancillary("Toren One".to_string())
+            AssignmentRef::Ancillary("Toren One".parse().unwrap())
         );
         assert_eq!(
             AssignmentRef::parse("one", "toren"),
-            AssignmentRef::Ancillary("Toren One".to_string())
+            AssignmentRef::Ancillary("Toren One".parse().unwrap())
         );
         assert_eq!(
             AssignmentRef::parse("a1b2", "toren"),
-            AssignmentRef::TaskId("a1b2".to_string())
+            AssignmentRef::TaskId("a1b2".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_assignment_ref_parse_canonicalizes_numbers() {
+        let twenty_one = AssignmentRef::Ancillary("Toren Twenty-One".parse().unwrap());
+        assert_eq!(AssignmentRef::parse("twenty-one", "toren"), twenty_one);
+        assert_eq!(AssignmentRef::parse("21", "toren"), twenty_one);
+        assert_eq!(AssignmentRef::parse("toren 21", "toren"), twenty_one);
+        assert_eq!(
+            AssignmentRef::parse("101", "toren"),
+            AssignmentRef::Ancillary("Toren 101".parse().unwrap())
+        );
+        assert_eq!(AssignmentRef::parse("007", "toren"), AssignmentRef::TaskId("007".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_next_available_ancillary_beyond_words() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let existing: Vec<String> = (1..=100).map(workspace_name).collect();
+        assert_eq!(existing[98], "ninety-nine");
+        assert_eq!(existing[99], "100");
+        assert_eq!(mgr.next_available_ancillary("toren", 150, &existing), "Toren 101");
+        assert_eq!(mgr.next_available_ancillary("toren", 10, &existing), "Toren 101");
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        assert!(mgr.history().unwrap().is_empty());
+
+        let a = mgr
+            .create(&"Toren One".parse().unwrap(), Some(&"t-1".parse().unwrap()), AssignmentSource::Reference, "toren", tmp.path().join("one"), None, None, None, None)
+            .unwrap();
+        mgr.record_completion(&a, CompletionReason::Completed, Some("abc".to_string()), vec!["PLAN.md".to_string()])
+            .unwrap();
+        mgr.record_completion(&a, CompletionReason::Aborted, None, Vec::new()).unwrap();
+
+        let history = mgr.history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].artifacts, vec!["PLAN.md"]);
+        assert!(history[1].artifacts.is_empty());
+        assert_eq!(mgr.artifacts_dir(&a.id), tmp.path().join("artifacts").join(&a.id));
+    }
+
+    #[test]
+    fn test_after_blocks_until_the_task_completes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let first = mgr
+            .create(&"Toren One".parse().unwrap(), Some(&"t-1".parse().unwrap()), AssignmentSource::Reference, "toren", tmp.path().join("one"), None, None, None, None)
+            .unwrap();
+        let second = mgr
+            .create(&"Toren Two".parse().unwrap(), Some(&"t-2".parse().unwrap()), AssignmentSource::Reference, "toren", tmp.path().join("two"), None, None, None, None)
+            .unwrap();
+        assert!(mgr.set_after(&second.id, Some("t-1".parse().unwrap())).unwrap());
+        let second = mgr.get(&second.id).cloned().unwrap();
+        assert_eq!(second.blocked_on(&mgr.completed_task_ids().unwrap()).map(|t| t.as_str()), Some("t-1"));
+
+        // An abort doesn't unblock it; a completion does
+        mgr.record_completion(&first, CompletionReason::Aborted, None, Vec::new()).unwrap();
+        assert!(second.blocked_on(&mgr.completed_task_ids().unwrap()).is_some());
+        mgr.record_completion(&first, CompletionReason::Completed, None, Vec::new()).unwrap();
+        assert!(second.blocked_on(&mgr.completed_task_ids().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_companions_are_recorded_and_listed_in_the_prompt() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let assignment = mgr
+            .create(&"Api One".parse().unwrap(), None, AssignmentSource::Reference, "api", PathBuf::from("/ws/api/one"), None, None, None, None)
+            .unwrap();
+        assert!(assignment.group_prompt().is_none());
+
+        let web = CompanionWorkspace {
+            segment: "web".to_string(),
+            segment_path: PathBuf::from("/src/web"),
+            workspace_path: PathBuf::from("/ws/web/one"),
+            base_branch: Some("main".to_string()),
+        };
+        assert!(mgr.set_companions(&assignment.id, "shop", vec![web.clone()]).unwrap());
+        let mut reloaded = AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let assignment = reloaded.get(&assignment.id).cloned().unwrap();
+        assert_eq!(assignment.group.as_deref(), Some("shop"));
+        assert_eq!(assignment.companions, vec![web]);
+        assert_eq!(
+            assignment.group_prompt().unwrap(),
+            "This task spans the shop segment group; each repository has its own workspace:\n\
+             - api: /ws/api/one (you start here)\n\
+             - web: /ws/web/one"
         );
     }
 
+    #[test]
+    fn test_resolve_id_precedence() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let create = |mgr: &mut AssignmentManager, ancillary: &str, task: &str, segment: &str| {
+            mgr.create(&ancillary.parse().unwrap(), Some(&task.parse().unwrap()), AssignmentSource::Reference, segment, tmp.path().join(task), None, None, None, None)
+                .unwrap()
+        };
+        let one = create(&mut mgr, "Toren One", "breq-a1b2", "toren");
+        let twenty_one = create(&mut mgr, "Toren Twenty-One", "breq-c3d4", "toren");
+        let other_one = create(&mut mgr, "Web One", "breq-a1b2", "web");
+
+        let ids = |found: Vec<&Assignment>| {
+            let mut ids: Vec<String> = found.into_iter().map(|a| a.id.clone()).collect();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut v: Vec<String>| {
+            v.sort();
+            v
+        };
+
+        // UUID
+        assert_eq!(ids(mgr.resolve_id(&one.id, None)), vec![one.id.clone()]);
+        assert_eq!(ids(mgr.resolve_id(&one.id, Some("web"))), vec![one.id.clone()]);
+        // Full ancillary names, with words or digits
+        assert_eq!(ids(mgr.resolve_id("Toren One", None)), vec![one.id.clone()]);
+        assert_eq!(ids(mgr.resolve_id("toren 21", None)), vec![twenty_one.id.clone()]);
+        // Ancillary words, narrowed by the segment hint
+        assert_eq!(ids(mgr.resolve_id("twenty-one", Some("toren"))), vec![twenty_one.id.clone()]);
+        assert_eq!(ids(mgr.resolve_id("1", Some("web"))), vec![other_one.id.clone()]);
+        assert_eq!(
+            ids(mgr.resolve_id("one", None)),
+            sorted(vec![one.id.clone(), other_one.id.clone()])
+        );
+        // Bead IDs
+        assert_eq!(ids(mgr.resolve_id("breq-c3d4", None)), vec![twenty_one.id.clone()]);
+        assert_eq!(ids(mgr.resolve_id("breq-a1b2", Some("web"))), vec![other_one.id.clone()]);
+        assert_eq!(ids(mgr.resolve_id("breq-a1b2", None)).len(), 2);
+        assert!(mgr.resolve_id("breq-zzzz", None).is_empty());
+        assert!(mgr.resolve_id("two", Some("toren")).is_empty());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_word_round_trip(n in 1u32..100_000) {
+            let word = number_to_word(n);
+            proptest::prop_assert_eq!(word_to_number(&word), Some(n));
+            proptest::prop_assert_eq!(word_to_number(&workspace_name(n)), Some(n));
+            proptest::prop_assert_eq!(normalize_workspace_name(&n.to_string()), workspace_name(n));
+        }
+
+        #[test]
+        fn prop_ancillary_id_round_trip(n in 1u32..100_000, segment in "[a-z][a-z0-9_]{0,12}") {
+            let id = ancillary_id(&segment, n);
+            proptest::prop_assert_eq!(ancillary_number(&id), Some(n));
+            proptest::prop_assert_eq!(ancillary_segment(&id), Some(segment.clone()));
+            let expected = AssignmentRef::Ancillary(id.clone());
+            proptest::prop_assert_eq!(&AssignmentRef::parse(&workspace_name(n), &segment), &expected);
+            proptest::prop_assert_eq!(&AssignmentRef::parse(&n.to_string(), &segment), &expected);
+            proptest::prop_assert_eq!(&AssignmentRef::parse(&id.to_lowercase(), &segment), &expected);
+        }
+    }
+
     #[test]
     fn test_serde_backward_compat() {
         // Old format with external_id/title should deserialize into task_id/task_title
@@ -855,4 +1600,175 @@ mod tests {
         let record: CompletionRecord = serde_json::from_str(cr_json).unwrap();
         assert_eq!(record.task_id.as_deref(), Some("breq-xyz"));
     }
+
+    /// Write a fixture assignments.json and open a manager over it.
+    fn fixture_store(dir: &std::path::Path) -> AssignmentManager {
+        let json = r#"[
+            {"id": "a1", "ancillary_id": "Toren One", "task_id": "bd-1", "segment": "toren",
+             "workspace_path": "/ws/toren/one", "source": {"type": "Reference"}, "status": "active",
+             "created_at": "2025-01-01T00:00:00Z", "updated_at": "2025-01-05T00:00:00Z",
+             "labels": ["frontend"]},
+            {"id": "a2", "ancillary_id": "Toren Two", "segment": "toren",
+             "workspace_path": "/ws/toren/two", "source": {"type": "Prompt", "original_prompt": "hi"},
+             "status": "active", "created_at": "2025-01-03T00:00:00Z", "updated_at": "2025-01-03T00:00:00Z",
+             "labels": ["frontend", "urgent"]},
+            {"id": "a3", "ancillary_id": "Toren Five", "task_id": "bd-5", "segment": "toren",
+             "workspace_path": "/ws/toren/five", "source": {"type": "Reference"}, "status": "active",
+             "created_at": "2025-01-02T00:00:00Z", "updated_at": "2025-01-02T00:00:00Z"},
+            {"id": "b1", "ancillary_id": "Other One", "segment": "other",
+             "workspace_path": "/ws/other/one", "source": {"type": "Prompt", "original_prompt": "yo"},
+             "status": "active", "created_at": "2025-01-04T00:00:00Z", "updated_at": "2025-01-04T00:00:00Z"}
+        ]"#;
+        let path = dir.join("assignments.json");
+        std::fs::write(&path, json).unwrap();
+        AssignmentManager::with_storage_path(path).unwrap()
+    }
+
+    fn ids(assignments: Vec<&Assignment>) -> Vec<&str> {
+        assignments.into_iter().map(|a| a.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_query_default_sorts_by_segment_then_number() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+        assert_eq!(ids(mgr.query(&AssignmentQuery::new())), ["b1", "a1", "a2", "a3"]);
+        assert_eq!(ids(mgr.list_active_segment("Toren")), ["a1", "a2", "a3"]);
+    }
+
+    #[test]
+    fn test_query_filters() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+
+        let q = AssignmentQuery::new().segment("toren").ancillary_range(2..=5);
+        assert_eq!(ids(mgr.query(&q)), ["a2", "a3"]);
+
+        let q = AssignmentQuery::new().label("frontend");
+        assert_eq!(ids(mgr.query(&q)), ["a1", "a2"]);
+        let q = AssignmentQuery::new().label("frontend").label("urgent");
+        assert_eq!(ids(mgr.query(&q)), ["a2"]);
+
+        let q = AssignmentQuery::new().source(AssignmentSourceKind::Prompt);
+        assert_eq!(ids(mgr.query(&q)), ["b1", "a2"]);
+
+        let after = parse_timestamp("2025-01-02T00:00:00Z").unwrap();
+        let before = parse_timestamp("2025-01-04T00:00:00Z").unwrap();
+        let q = AssignmentQuery::new().created_after(after).created_before(before);
+        assert_eq!(ids(mgr.query(&q)), ["a2", "a3"]);
+    }
+
+    #[test]
+    fn test_query_sort_and_paginate() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+
+        let q = AssignmentQuery::new().sort(AssignmentSort::Created);
+        assert_eq!(ids(mgr.query(&q)), ["a1", "a3", "a2", "b1"]);
+
+        let q = AssignmentQuery::new()
+            .sort(AssignmentSort::Updated)
+            .descending()
+            .limit(2);
+        assert_eq!(ids(mgr.query(&q)), ["a1", "b1"]);
+
+        let q = AssignmentQuery::new()
+            .sort(AssignmentSort::Created)
+            .offset(1)
+            .limit(2);
+        assert_eq!(ids(mgr.query(&q)), ["a3", "a2"]);
+    }
+
+    #[test]
+    fn test_set_labels_persists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+        assert!(mgr.set_labels("a3", vec!["backend".to_string()]).unwrap());
+        assert!(!mgr.set_labels("missing", vec![]).unwrap());
+
+        let mut reopened =
+            AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        assert_eq!(ids(reopened.query(&AssignmentQuery::new().label("backend"))), ["a3"]);
+    }
+
+    #[test]
+    fn test_add_note_appends_and_persists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+        mgr.add_note("a3", "tried approach X").unwrap().unwrap();
+        mgr.add_note("a3", "  blocked on Y\n").unwrap().unwrap();
+        assert!(mgr.add_note("missing", "nope").unwrap().is_none());
+
+        let mut reopened =
+            AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let notes: Vec<_> = reopened
+            .get("a3")
+            .unwrap()
+            .notes
+            .iter()
+            .map(|n| n.text.as_str())
+            .collect();
+        assert_eq!(notes, ["tried approach X", "blocked on Y"]);
+    }
+
+    #[test]
+    fn test_amend_prompt() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+        assert!(mgr.amend_prompt("a2", "hi, and also add tests").unwrap());
+        assert!(!mgr.amend_prompt("missing", "nope").unwrap());
+        // Created from a task reference: there's no prompt to amend
+        assert!(mgr.amend_prompt("a1", "nope").is_err());
+
+        let mut reopened =
+            AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let a2 = reopened.get("a2").unwrap();
+        assert!(matches!(
+            &a2.source,
+            AssignmentSource::Prompt { original_prompt, .. } if original_prompt == "hi, and also add tests"
+        ));
+        assert!(a2.prompt_amended_at.is_some());
+        assert!(reopened.get("a1").unwrap().prompt_amended_at.is_none());
+    }
+
+    #[test]
+    fn test_owner_claim_steal_release() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+        // pid 1 is always running and never this process
+        let other = AssignmentOwner::Cli { pid: 1 };
+        assert!(mgr.claim_owner("a3", other.clone(), false).unwrap());
+
+        let err = mgr.claim_owner("a3", AssignmentOwner::cli(), false).unwrap_err();
+        assert_eq!(err.downcast_ref::<AssignmentOwned>().unwrap().owner, other);
+        assert!(mgr.check_owner("a3", true).is_ok());
+
+        assert!(mgr.claim_owner("a3", AssignmentOwner::cli(), true).unwrap());
+        assert!(!mgr.release_owner("a3", &other).unwrap());
+        assert!(mgr.release_owner("a3", &AssignmentOwner::cli()).unwrap());
+        assert!(mgr.get("a3").unwrap().owner.is_none());
+    }
+
+    #[test]
+    fn test_normalize_focus_path() {
+        assert_eq!(
+            normalize_focus_path("./crates/foo/").unwrap(),
+            Some(PathBuf::from("crates/foo"))
+        );
+        assert_eq!(normalize_focus_path(".").unwrap(), None);
+        assert!(normalize_focus_path("/abs/path").is_err());
+        assert!(normalize_focus_path("crates/../..").is_err());
+    }
+
+    #[test]
+    fn test_focus_path_sets_working_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mgr = fixture_store(tmp.path());
+        assert!(mgr.set_focus_path("a3", Some(PathBuf::from("crates/foo"))).unwrap());
+
+        let mut reopened =
+            AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+        let a = reopened.get("a3").unwrap();
+        assert_eq!(a.working_dir(), a.workspace_path.join("crates/foo"));
+    }
 }
@@ -5,13 +5,16 @@
 
 use anyhow::{Context, Result};
 use serde::Serialize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::info;
 
 use crate::assignment::{AssignmentManager, CompletionReason};
+use crate::hooks::{HookEvent, HookPayload};
+use crate::ids::{AncillaryId, TaskId};
+use crate::undo::{TaskTransition, UndoEntry, UndoOperation};
 use crate::workspace::{CleanupMode, CommitInfo, WorkspaceManager};
-use crate::workspace_setup::{SetupResult, WorkspaceContext, WorkspaceInfo, RepoInfo, TaskInfo};
-use crate::Assignment;
+use crate::workspace_setup::{BreqConfig, SetupResult, WorkspaceContext, WorkspaceInfo, RepoInfo, TaskInfo};
+use crate::{Assignment, AssignmentSource};
 
 /// Options for completing an assignment
 pub struct CompleteOptions<'a> {
@@ -27,6 +30,14 @@ pub struct CompleteOptions<'a> {
     pub auto_commit_message: Option<String>,
     /// Plugin manager for resolver-based task operations
     pub plugin_mgr: &'a crate::plugins::PluginManager,
+    /// Intents whose final report is posted as a task comment (`tasks.report_intents`)
+    pub report_intents: &'a [String],
+    /// Session logs to take the final report from
+    pub session_logs: &'a crate::session::SessionLogs,
+    /// Checks run before pushing (`[push]`); None skips them (`--no-verify`)
+    pub push_checks: Option<&'a crate::config::PushConfig>,
+    /// Complete even if the working copy has unresolved conflicts
+    pub allow_conflicts: bool,
 }
 
 /// Result from completing an assignment
@@ -37,12 +48,21 @@ pub struct CompleteResult {
     pub pushed: bool,
     /// Commits exclusive to this workspace (captured before cleanup)
     pub workspace_info: Vec<CommitInfo>,
+    /// Artifacts kept from the workspace (see [`crate::artifacts`])
+    pub artifacts: Vec<String>,
+    /// Final report, for assignments with a report intent
+    pub report: Option<crate::report::FinalReport>,
+    /// Whether the report was posted as a task comment
+    pub report_posted: bool,
 }
 
 /// Options for aborting an assignment
 pub struct AbortOptions<'a> {
     /// Whether to close the task (default: reopen it)
     pub close_task: bool,
+    /// Delete the task instead when it was auto-created from the assignment's
+    /// prompt (falls back to closing it if the resolver has no `delete`)
+    pub delete_task: bool,
     /// Segment path for running workspace hooks and task commands
     pub segment_path: &'a Path,
     /// Whether to kill processes running in the workspace
@@ -51,6 +71,26 @@ pub struct AbortOptions<'a> {
     pub plugin_mgr: &'a crate::plugins::PluginManager,
 }
 
+/// Options for auto-finalizing an assignment (`[intents.<name>.auto]`)
+pub struct AutoFinalizeOptions<'a> {
+    /// Whether to close the task (default: return it to open)
+    pub close_task: bool,
+    /// Segment path for running workspace hooks and task commands
+    pub segment_path: &'a Path,
+    /// Plugin manager for resolver-based task operations
+    pub plugin_mgr: &'a crate::plugins::PluginManager,
+    /// Session logs to take the final report from
+    pub session_logs: &'a crate::session::SessionLogs,
+}
+
+/// Result from auto-finalizing an assignment
+pub struct AutoFinalizeResult {
+    /// The agent's final report, if one was found
+    pub report: Option<crate::report::FinalReport>,
+    /// Whether the report was posted as a task comment
+    pub report_posted: bool,
+}
+
 /// Options for preparing a resume
 pub struct ResumeOptions<'a> {
     /// Custom instruction/prompt for the resumed work
@@ -61,6 +101,8 @@ pub struct ResumeOptions<'a> {
     pub segment_name: &'a str,
     /// Plugin manager for resolver-based task operations
     pub plugin_mgr: &'a crate::plugins::PluginManager,
+    /// Claim identity template (`tasks.claim_identity`) used if the task must be reclaimed
+    pub claim_identity: Option<&'a str>,
 }
 
 /// Result from preparing a resume
@@ -73,6 +115,22 @@ pub struct ResumeResult {
     pub setup_result: SetupResult,
 }
 
+/// Options for amending a prompt-created assignment's prompt
+pub struct AmendOptions<'a> {
+    /// The new prompt, replacing the original
+    pub prompt: &'a str,
+    /// Segment path for task commands
+    pub segment_path: &'a Path,
+    /// Plugin manager for resolver-based task operations
+    pub plugin_mgr: &'a crate::plugins::PluginManager,
+}
+
+/// Result from amending an assignment's prompt
+pub struct AmendResult {
+    /// Whether the auto-created task's description was updated too
+    pub task_updated: bool,
+}
+
 /// Options for cleaning an assignment (bead-free workspace teardown)
 pub struct CleanOptions<'a> {
     /// Whether to push changes before cleanup
@@ -83,6 +141,10 @@ pub struct CleanOptions<'a> {
     pub kill: bool,
     /// Auto-commit message (rendered template). If Some, auto-commit before capture.
     pub auto_commit_message: Option<String>,
+    /// Checks run before pushing (`[push]`); None skips them (`--no-verify`)
+    pub push_checks: Option<&'a crate::config::PushConfig>,
+    /// Complete even if the working copy has unresolved conflicts
+    pub allow_conflicts: bool,
 }
 
 /// JSON-serializable result from cleaning an assignment
@@ -92,12 +154,37 @@ pub struct CleanResult {
     pub workspace: String,
     /// External ID if present (e.g., bead ID)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<String>,
+    pub id: Option<TaskId>,
     /// VCS revision hash captured before cleanup
     #[serde(skip_serializing_if = "Option::is_none")]
     pub revision: Option<String>,
     /// Segment name
     pub segment: String,
+    /// Artifacts kept from the workspace
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+}
+
+/// Options for undoing a complete, abort or clean (see [`crate::undo`])
+pub struct UndoOptions<'a> {
+    /// Segment path for running workspace hooks and task commands
+    pub segment_path: &'a Path,
+    /// Plugin manager for resolver-based task operations
+    pub plugin_mgr: &'a crate::plugins::PluginManager,
+    /// Claim identity template (`tasks.claim_identity`) used to reclaim the task
+    pub claim_identity: Option<&'a str>,
+}
+
+/// Result from undoing an operation
+pub struct UndoResult {
+    /// The recreated workspace
+    pub workspace_path: PathBuf,
+    /// The revision it was recreated at
+    pub revision: Option<String>,
+    /// Whether the task was claimed again
+    pub task_claimed: bool,
+    /// URLs of the workspace's proxy routes, re-added by its setup
+    pub proxy_urls: Vec<String>,
 }
 
 /// Default auto-commit message template.
@@ -113,7 +200,14 @@ pub fn render_auto_commit_message(
     segment_name: &str,
     segment_path: &std::path::Path,
 ) -> Option<String> {
-    let task_id = assignment.task_id.clone().unwrap_or_default();
+    let ctx = assignment_context(assignment, segment_name, segment_path);
+    crate::workspace_setup::render_template(template, &ctx).ok()
+}
+
+/// Template context for an assignment: its workspace, repo, task and focus.
+/// `vars` is left empty.
+fn assignment_context(assignment: &Assignment, segment_name: &str, segment_path: &Path) -> WorkspaceContext {
+    let task_id = assignment.task_id.as_deref().unwrap_or_default().to_string();
     let task_title = assignment
         .task_title
         .clone()
@@ -125,7 +219,7 @@ pub fn render_auto_commit_message(
         .unwrap_or("")
         .to_string();
     let ancillary_num = crate::ancillary_number(&assignment.ancillary_id).unwrap_or(0);
-    let ctx = WorkspaceContext {
+    WorkspaceContext {
         ws: WorkspaceInfo {
             name: ws_name,
             num: ancillary_num,
@@ -134,6 +228,7 @@ pub fn render_auto_commit_message(
         repo: RepoInfo {
             root: segment_path.display().to_string(),
             name: segment_name.to_string(),
+            recent_commits: Vec::new(),
         },
         task: Some(TaskInfo {
             id: task_id,
@@ -141,10 +236,113 @@ pub fn render_auto_commit_message(
             description: None,
             url: assignment.task_url.clone(),
             source: assignment.task_source.clone(),
+            deps: Vec::new(),
         }),
+        variant: None,
+        assignment: Some(assignment.into()),
         vars: std::collections::HashMap::new(),
+    }
+}
+
+/// Template context for a workspace, with its assignment's task when it
+/// has one.
+pub fn workspace_context(
+    segment_name: &str,
+    segment_path: &Path,
+    workspace_path: &Path,
+    assignment: Option<&Assignment>,
+) -> WorkspaceContext {
+    if let Some(assignment) = assignment {
+        return assignment_context(assignment, segment_name, segment_path);
+    }
+    let name = workspace_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_string();
+    WorkspaceContext {
+        ws: WorkspaceInfo {
+            num: crate::naming::slot_number(segment_name, &name).unwrap_or(0),
+            name,
+            path: workspace_path.display().to_string(),
+        },
+        repo: RepoInfo {
+            root: segment_path.display().to_string(),
+            name: segment_name.to_string(),
+            recent_commits: Vec::new(),
+        },
+        task: None,
+        variant: None,
+        assignment: None,
+        vars: std::collections::HashMap::new(),
+    }
+}
+
+/// Assignee recorded on task claims when no `tasks.claim_identity` is configured.
+pub const DEFAULT_CLAIM_IDENTITY: &str = "claude";
+
+/// Render the identity an ancillary claims tasks under.
+///
+/// The template sees `ws.name`, `ws.num` and `repo.name`. Without a template,
+/// or if it fails to render, this is [`DEFAULT_CLAIM_IDENTITY`].
+pub fn render_claim_identity(
+    template: Option<&str>,
+    segment_name: &str,
+    segment_path: &Path,
+    ancillary_id: &AncillaryId,
+) -> String {
+    let Some(template) = template else {
+        return DEFAULT_CLAIM_IDENTITY.to_string();
     };
-    crate::workspace_setup::render_template(template, &ctx).ok()
+    let num = crate::ancillary_number(ancillary_id).unwrap_or(0);
+    let ctx = WorkspaceContext {
+        ws: WorkspaceInfo {
+            name: crate::naming::workspace_name(segment_name, num),
+            num,
+            path: String::new(),
+        },
+        repo: RepoInfo {
+            root: segment_path.display().to_string(),
+            name: segment_name.to_string(),
+            recent_commits: Vec::new(),
+        },
+        task: None,
+        variant: None,
+        assignment: None,
+        vars: std::collections::HashMap::new(),
+    };
+    match crate::workspace_setup::render_template(template, &ctx) {
+        Ok(identity) if !identity.trim().is_empty() => identity.trim().to_string(),
+        Ok(_) => DEFAULT_CLAIM_IDENTITY.to_string(),
+        Err(e) => {
+            tracing::warn!("Failed to render tasks.claim_identity: {:#}", e);
+            DEFAULT_CLAIM_IDENTITY.to_string()
+        }
+    }
+}
+
+/// Claim an assignment's task under its ancillary's identity.
+/// Does nothing for assignments without a task source.
+pub fn claim_for_assignment(
+    assignment: &Assignment,
+    plugin_mgr: &crate::plugins::PluginManager,
+    segment_path: &Path,
+    claim_identity: Option<&str>,
+) -> Result<()> {
+    let (Some(task_id), Some(source)) = (&assignment.task_id, &assignment.task_source) else {
+        return Ok(());
+    };
+    let identity = render_claim_identity(
+        claim_identity,
+        &assignment.segment,
+        segment_path,
+        &assignment.ancillary_id,
+    );
+    let ctx = crate::PluginContext::new(
+        Some(segment_path.to_path_buf()),
+        Some(assignment.segment.clone()),
+    );
+    plugin_mgr.resolve_claim(source, task_id, &identity, ctx)
 }
 
 /// Complete an assignment: auto-commit, capture revision, optionally push,
@@ -161,9 +359,28 @@ pub fn complete_assignment(
         revision: None,
         pushed: false,
         workspace_info: Vec::new(),
+        artifacts: Vec::new(),
+        report: None,
+        report_posted: false,
     };
 
+    let reports = assignment
+        .intent
+        .as_ref()
+        .is_some_and(|intent| opts.report_intents.contains(intent));
+    if reports {
+        result.report =
+            crate::report::final_report(assignment, &crate::toren_root(), opts.session_logs);
+    }
+
     if assignment.workspace_path.exists() {
+        check_conflicts(assignment, ws_mgr, opts.segment_path, opts.allow_conflicts)?;
+
+        // Strip the toren context block so it never lands in the committed changes
+        if let Err(e) = ws_mgr.remove_agent_context(&assignment.workspace_path) {
+            tracing::warn!("Failed to remove agent context: {:#}", e);
+        }
+
         // Auto-commit if message provided
         if let Some(ref message) = opts.auto_commit_message {
             match ws_mgr.auto_commit(opts.segment_path, &assignment.workspace_path, message) {
@@ -194,11 +411,23 @@ pub fn complete_assignment(
 
         // Push if requested
         if opts.push && result.revision.is_some() {
+            verify_push(assignment, ws_mgr, opts.segment_path, opts.push_checks)?;
             info!("Pushing changes for assignment {}", assignment.id);
             ws_mgr.push(opts.segment_path, &assignment.workspace_path)?;
             result.pushed = true;
         }
+
+        result.artifacts = keep_artifacts(assignment, assignment_mgr, opts.segment_path);
     }
+    complete_companions(
+        assignment,
+        ws_mgr,
+        opts.auto_commit_message.as_deref(),
+        opts.push,
+        opts.push_checks,
+    )?;
+
+    let proxy_urls = workspace_proxy_urls(assignment, ws_mgr, opts.segment_path);
 
     // Cleanup workspace if it exists
     let cleanup_mode = CleanupMode::Complete {
@@ -214,13 +443,24 @@ pub fn complete_assignment(
 
     // Record completion history and remove assignment from active storage
     assignment_mgr.record_completion(
-        assignment,
+        &crate::usage::with_usage(opts.session_logs, assignment),
         CompletionReason::Completed,
         result.revision.clone(),
+        result.artifacts.clone(),
     )?;
     assignment_mgr.remove(&assignment.id)?;
 
-    // Close task unless keep_task_open (only if task_id and task_source are present)
+    // Post the report, then close task unless keep_task_open (only if
+    // task_id and task_source are present)
+    if let (Some(ref task_id), Some(ref source)) = (&assignment.task_id, &assignment.task_source) {
+        if let Some(ref report) = result.report {
+            let ctx = crate::PluginContext::new(Some(opts.segment_path.to_path_buf()), None);
+            match opts.plugin_mgr.resolve_comment(source, task_id, &report.text, ctx) {
+                Ok(()) => result.report_posted = true,
+                Err(e) => tracing::warn!("Failed to post report to task {}: {:#}", task_id, e),
+            }
+        }
+    }
     if !opts.keep_task_open {
         if let (Some(ref task_id), Some(ref source)) = (&assignment.task_id, &assignment.task_source) {
             let ctx = crate::PluginContext::new(
@@ -232,9 +472,70 @@ pub fn complete_assignment(
         }
     }
 
+    journal_undo(
+        assignment_mgr,
+        UndoEntry {
+            operation: UndoOperation::Complete,
+            recorded_at: chrono::Utc::now(),
+            assignment: assignment.clone(),
+            revision: result.revision.clone(),
+            pushed: result.pushed,
+            task: (!opts.keep_task_open && assignment.task_id.is_some() && assignment.task_source.is_some())
+                .then_some(TaskTransition::Closed),
+            proxy_urls,
+        },
+    );
+
+    let mut payload = HookPayload::new(HookEvent::Complete, assignment);
+    payload.revision = result.revision.clone();
+    payload.pushed = Some(result.pushed);
+    crate::hooks::emit(&payload);
+
     Ok(result)
 }
 
+/// Refuse to complete a workspace with unresolved conflicts, which would
+/// otherwise be committed and pushed as conflict markers.
+/// Fails with [`crate::workspace::WorkspaceConflicted`] unless `allow` is set.
+fn check_conflicts(
+    assignment: &Assignment,
+    ws_mgr: &WorkspaceManager,
+    segment_path: &Path,
+    allow: bool,
+) -> Result<()> {
+    if allow {
+        return Ok(());
+    }
+    let paths = ws_mgr.conflicted_paths(segment_path, &assignment.workspace_path);
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let workspace = assignment
+        .workspace_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    Err(crate::workspace::WorkspaceConflicted { workspace, paths }.into())
+}
+
+/// Run the `[push]` checks on what pushing the workspace would publish.
+/// Fails with [`crate::push_checks::PushChecksFailed`] before anything is pushed.
+fn verify_push(
+    assignment: &Assignment,
+    ws_mgr: &WorkspaceManager,
+    segment_path: &Path,
+    checks: Option<&crate::config::PushConfig>,
+) -> Result<()> {
+    let Some(config) = checks else {
+        return Ok(());
+    };
+    let diff = ws_mgr
+        .push_diff(segment_path, &assignment.workspace_path, assignment.base_branch.as_deref())
+        .context("Failed to diff the workspace for push checks")?;
+    crate::push_checks::verify(config, &assignment.workspace_path, &diff)
+}
+
 /// Abort an assignment: cleanup workspace, remove assignment, and handle bead status.
 ///
 /// This mirrors `breq abort` behavior.
@@ -244,6 +545,14 @@ pub fn abort_assignment(
     ws_mgr: &WorkspaceManager,
     opts: &AbortOptions,
 ) -> Result<()> {
+    // Kept for undo: jj snapshots the working copy, so its changes survive
+    let revision = assignment
+        .workspace_path
+        .exists()
+        .then(|| ws_mgr.capture_revision(opts.segment_path, &assignment.workspace_path))
+        .flatten();
+    let proxy_urls = workspace_proxy_urls(assignment, ws_mgr, opts.segment_path);
+
     // Cleanup workspace if it exists
     cleanup_workspace(
         assignment,
@@ -253,28 +562,89 @@ pub fn abort_assignment(
         CleanupMode::Abort,
     )?;
 
-    // Record abort history and remove assignment from active storage
-    assignment_mgr.record_completion(assignment, CompletionReason::Aborted, None)?;
+    // Record abort history, with the revision to recover from, and remove
+    // assignment from active storage
+    assignment_mgr.record_completion(assignment, CompletionReason::Aborted, revision.clone(), Vec::new())?;
     assignment_mgr.remove(&assignment.id)?;
 
+    let mut entry = UndoEntry {
+        operation: UndoOperation::Abort,
+        recorded_at: chrono::Utc::now(),
+        assignment: assignment.clone(),
+        revision,
+        pushed: false,
+        task: None,
+        proxy_urls,
+    };
+
     // Handle task status (only if task_id and task_source are present)
     if let (Some(ref task_id), Some(ref source)) = (&assignment.task_id, &assignment.task_source) {
         let ctx = crate::PluginContext::new(
             Some(opts.segment_path.to_path_buf()),
             None,
         );
-        if opts.close_task {
+        let transition = if opts.delete_task && assignment.source.auto_created_bead() {
+            if opts.plugin_mgr.resolver_has_fn(source, "delete") {
+                opts.plugin_mgr.resolve_delete(source, task_id, ctx)?;
+                info!("Task {} deleted", task_id);
+                TaskTransition::Deleted
+            } else {
+                opts.plugin_mgr.resolve_complete(source, task_id, ctx)?;
+                info!("Task {} closed ({} can't delete tasks)", task_id, source);
+                TaskTransition::Closed
+            }
+        } else if opts.close_task {
             opts.plugin_mgr.resolve_complete(source, task_id, ctx)?;
             info!("Task {} closed", task_id);
+            TaskTransition::Closed
         } else {
             opts.plugin_mgr.resolve_abort(source, task_id, ctx)?;
             info!("Task {} unassigned and returned to open", task_id);
-        }
+            TaskTransition::Reopened
+        };
+        entry.task = Some(transition);
     }
+    journal_undo(assignment_mgr, entry);
+
+    crate::hooks::emit(&HookPayload::new(HookEvent::Abort, assignment));
 
     Ok(())
 }
 
+/// Post the assignment's final report to its task, then abort it, as
+/// `[intents.<name>.auto]` does for fire-and-forget runs. Processes left in
+/// the workspace are killed, since nobody is watching the run.
+pub fn auto_finalize_assignment(
+    assignment: &Assignment,
+    assignment_mgr: &mut AssignmentManager,
+    ws_mgr: &WorkspaceManager,
+    opts: &AutoFinalizeOptions,
+) -> Result<AutoFinalizeResult> {
+    let report = crate::report::final_report(assignment, &crate::toren_root(), opts.session_logs);
+    let mut report_posted = false;
+    if let (Some(ref report), Some(ref task_id), Some(ref source)) =
+        (&report, &assignment.task_id, &assignment.task_source)
+    {
+        let ctx = crate::PluginContext::new(Some(opts.segment_path.to_path_buf()), None);
+        match opts.plugin_mgr.resolve_comment(source, task_id, &report.text, ctx) {
+            Ok(()) => report_posted = true,
+            Err(e) => tracing::warn!("Failed to post report to task {}: {:#}", task_id, e),
+        }
+    }
+
+    let abort = AbortOptions {
+        close_task: opts.close_task,
+        delete_task: false,
+        segment_path: opts.segment_path,
+        kill: true,
+        plugin_mgr: opts.plugin_mgr,
+    };
+    let assignment = &crate::usage::with_usage(opts.session_logs, assignment);
+    abort_assignment(assignment, assignment_mgr, ws_mgr, &abort)?;
+
+    Ok(AutoFinalizeResult { report, report_posted })
+}
+
 /// Prepare an assignment for resuming: recreate workspace if missing,
 /// update status to Active, ensure bead is claimed.
 ///
@@ -287,7 +657,7 @@ pub fn prepare_resume(
     opts: &ResumeOptions,
 ) -> Result<ResumeResult> {
     let mut workspace_recreated = false;
-    let mut setup_result = SetupResult;
+    let mut setup_result = SetupResult::default();
 
     // Recreate workspace if missing
     if !assignment.workspace_path.exists() {
@@ -313,28 +683,39 @@ pub fn prepare_resume(
         info!("Workspace recreated: {}", assignment.workspace_path.display());
     }
 
-    // Touch updated_at timestamp (assignment is always Active)
-    assignment_mgr.touch(&assignment.id)?;
+    // Touch updated_at timestamp (assignment is always Active), keeping the
+    // instruction for later inspection
+    match opts.instruction {
+        Some(instruction) => assignment_mgr.update_resume_instruction(&assignment.id, instruction)?,
+        None => assignment_mgr.touch(&assignment.id)?,
+    };
 
-    // Ensure task is in_progress and assigned to claude (if task_id and task_source present)
+    // Refresh the toren context block (task title or vars may have changed)
+    if let Err(e) = ws_mgr.write_agent_context(opts.segment_path, assignment) {
+        tracing::warn!("Failed to write agent context: {:#}", e);
+    }
+
+    // Ensure task is in_progress and assigned to this ancillary (if task_id and task_source present)
+    let mut resolved = None;
     let task_title = if let (Some(ref task_id), Some(ref source)) = (&assignment.task_id, &assignment.task_source) {
         let ctx = crate::PluginContext::new(
             Some(opts.segment_path.to_path_buf()),
             Some(opts.segment_name.to_string()),
         );
         match opts.plugin_mgr.resolve_info(source, task_id, ctx) {
-            Ok(task) => task.title,
+            Ok(task) => resolved.insert(task).title.clone(),
             Err(_) => {
                 // Task might be closed or not found, try to reclaim
-                let ctx = crate::PluginContext::new(
-                    Some(opts.segment_path.to_path_buf()),
-                    Some(opts.segment_name.to_string()),
-                );
-                opts.plugin_mgr.resolve_claim(source, task_id, "claude", ctx)?;
+                claim_for_assignment(
+                    assignment,
+                    opts.plugin_mgr,
+                    opts.segment_path,
+                    opts.claim_identity,
+                )?;
                 assignment
                     .task_title
                     .clone()
-                    .unwrap_or_else(|| task_id.clone())
+                    .unwrap_or_else(|| task_id.to_string())
             }
         }
     } else {
@@ -357,6 +738,38 @@ pub fn prepare_resume(
                 )
             }
         });
+    // An amended prompt supersedes what the agent was first told
+    let prompt = match (&assignment.source, &assignment.prompt_amended_at) {
+        (AssignmentSource::Prompt { original_prompt, .. }, Some(_)) => format!(
+            "{}\n\nThe task has been amended since work started. The current task:\n\n{}",
+            prompt, original_prompt
+        ),
+        _ => prompt,
+    };
+    // A resumed session starts fresh, so it needs the repo's prompt context too
+    let mut ctx = assignment_context(assignment, opts.segment_name, opts.segment_path);
+    if let (Some(task), Some(resolved)) = (ctx.task.as_mut(), resolved) {
+        task.description = resolved.description;
+        task.deps = resolved.deps;
+    }
+    ctx.repo.recent_commits = ws_mgr.recent_commits(opts.segment_path, assignment.base_branch.as_deref());
+    ctx.vars = ws_mgr
+        .workspace_vars(opts.segment_path, &assignment.workspace_path, &ctx.ws.name, ctx.ws.num)
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to evaluate toren.kdl vars: {:#}", e);
+            Default::default()
+        });
+    let prompt = match crate::workspace_setup::with_repo_prompt(Some(prompt.clone()), opts.segment_path, &ctx) {
+        Ok(merged) => merged.unwrap_or(prompt),
+        Err(e) => {
+            tracing::warn!("Failed to add toren.kdl prompt context: {:#}", e);
+            prompt
+        }
+    };
+
+    let mut payload = HookPayload::new(HookEvent::Resume, assignment);
+    payload.workspace_recreated = Some(workspace_recreated);
+    crate::hooks::emit(&payload);
 
     Ok(ResumeResult {
         prompt,
@@ -365,6 +778,41 @@ pub fn prepare_resume(
     })
 }
 
+/// Amend the prompt of an assignment created from one: store the new prompt
+/// on the assignment, and, when its task was auto-created from the prompt,
+/// make it the task's description too (if the resolver can update
+/// descriptions). The next resume includes the amended prompt.
+pub fn amend_prompt(
+    assignment: &Assignment,
+    assignment_mgr: &mut AssignmentManager,
+    opts: &AmendOptions,
+) -> Result<AmendResult> {
+    let prompt = opts.prompt.trim();
+    if prompt.is_empty() {
+        anyhow::bail!("The amended prompt is empty");
+    }
+    assignment_mgr.amend_prompt(&assignment.id, prompt)?;
+
+    let mut task_updated = false;
+    if let (Some(task_id), Some(source), true) = (
+        &assignment.task_id,
+        &assignment.task_source,
+        assignment.source.auto_created_bead(),
+    ) {
+        if opts.plugin_mgr.resolver_has_fn(source, "update_description") {
+            let ctx = crate::PluginContext::new(Some(opts.segment_path.to_path_buf()), None);
+            opts.plugin_mgr
+                .resolve_update_description(source, task_id, prompt, ctx)
+                .with_context(|| format!("Failed to update the description of {}", task_id))?;
+            info!("Task {} description updated", task_id);
+            task_updated = true;
+        } else {
+            info!("{} can't update task descriptions; {} keeps the original prompt", source, task_id);
+        }
+    }
+    Ok(AmendResult { task_updated })
+}
+
 /// Clean an assignment: auto-commit, capture revision, push (if requested),
 /// cleanup workspace, record completion, remove assignment.
 ///
@@ -384,8 +832,16 @@ pub fn clean_assignment(
         .to_string();
 
     let mut revision = None;
+    let mut artifacts = Vec::new();
 
     if assignment.workspace_path.exists() {
+        check_conflicts(assignment, ws_mgr, opts.segment_path, opts.allow_conflicts)?;
+
+        // Strip the toren context block so it never lands in the committed changes
+        if let Err(e) = ws_mgr.remove_agent_context(&assignment.workspace_path) {
+            tracing::warn!("Failed to remove agent context: {:#}", e);
+        }
+
         // Auto-commit if message provided
         if let Some(ref message) = opts.auto_commit_message {
             match ws_mgr.auto_commit(opts.segment_path, &assignment.workspace_path, message) {
@@ -405,10 +861,22 @@ pub fn clean_assignment(
 
         // Push if requested
         if opts.push && revision.is_some() {
+            verify_push(assignment, ws_mgr, opts.segment_path, opts.push_checks)?;
             info!("Pushing changes for assignment {}", assignment.id);
             ws_mgr.push(opts.segment_path, &assignment.workspace_path)?;
         }
+
+        artifacts = keep_artifacts(assignment, assignment_mgr, opts.segment_path);
     }
+    complete_companions(
+        assignment,
+        ws_mgr,
+        opts.auto_commit_message.as_deref(),
+        opts.push,
+        opts.push_checks,
+    )?;
+
+    let proxy_urls = workspace_proxy_urls(assignment, ws_mgr, opts.segment_path);
 
     // Cleanup workspace
     let cleanup_mode = CleanupMode::Complete {
@@ -427,17 +895,195 @@ pub fn clean_assignment(
         assignment,
         CompletionReason::Completed,
         revision.clone(),
+        artifacts.clone(),
     )?;
     assignment_mgr.remove(&assignment.id)?;
 
+    journal_undo(
+        assignment_mgr,
+        UndoEntry {
+            operation: UndoOperation::Clean,
+            recorded_at: chrono::Utc::now(),
+            assignment: assignment.clone(),
+            revision: revision.clone(),
+            pushed: opts.push && revision.is_some(),
+            task: None,
+            proxy_urls,
+        },
+    );
+
+    let mut payload = HookPayload::new(HookEvent::Complete, assignment);
+    payload.revision = revision.clone();
+    payload.pushed = Some(opts.push && revision.is_some());
+    crate::hooks::emit(&payload);
+
     Ok(CleanResult {
         workspace: ws_name,
         id: assignment.task_id.clone(),
         revision,
         segment: assignment.segment.clone(),
+        artifacts,
+    })
+}
+
+/// Undo a complete, abort or clean from the undo journal: recreate the
+/// workspace at its preserved revision (setup re-adds its proxy routes),
+/// restore the assignment record and claim its task again. Refuses if the
+/// ancillary has been reassigned or the workspace recreated since.
+///
+/// Pushed changes stay pushed, and a deleted task stays deleted.
+pub fn undo_operation(
+    entry: &UndoEntry,
+    assignment_mgr: &mut AssignmentManager,
+    ws_mgr: &WorkspaceManager,
+    opts: &UndoOptions,
+) -> Result<UndoResult> {
+    let assignment = &entry.assignment;
+    if let Some(active) = assignment_mgr.get_active_for_ancillary(&assignment.ancillary_id) {
+        anyhow::bail!(
+            "Can't undo the {} of {}: it has been assigned again ({})",
+            entry.operation,
+            assignment.ancillary_id,
+            active.id
+        );
+    }
+    if assignment.workspace_path.exists() {
+        anyhow::bail!(
+            "Can't undo the {} of {}: {} exists again",
+            entry.operation,
+            assignment.ancillary_id,
+            assignment.workspace_path.display()
+        );
+    }
+
+    let ws_name = assignment
+        .workspace_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("Invalid workspace path")?;
+    let segment_name = crate::ancillary_segment(&assignment.ancillary_id)
+        .unwrap_or_else(|| assignment.segment.clone());
+    let ancillary_num = crate::ancillary_number(&assignment.ancillary_id).unwrap_or(0);
+    let (workspace_path, _) = ws_mgr.restore_workspace(
+        opts.segment_path,
+        &segment_name,
+        ws_name,
+        ancillary_num,
+        entry.revision.as_deref(),
+    )?;
+    info!("Workspace restored: {}", workspace_path.display());
+
+    assignment_mgr.restore(assignment.clone())?;
+    assignment_mgr.prune_history(|record| record.assignment_id == assignment.id)?;
+    assignment_mgr.undo_journal().remove(&assignment.id)?;
+
+    if let Err(e) = ws_mgr.write_agent_context(opts.segment_path, assignment) {
+        tracing::warn!("Failed to write agent context: {:#}", e);
+    }
+
+    // Reopen and reclaim the task; claiming is harmless if it was left alone
+    let mut task_claimed = false;
+    let has_task = assignment.task_id.is_some() && assignment.task_source.is_some();
+    if has_task && entry.task != Some(TaskTransition::Deleted) {
+        match claim_for_assignment(assignment, opts.plugin_mgr, opts.segment_path, opts.claim_identity) {
+            Ok(()) => task_claimed = true,
+            Err(e) => tracing::warn!("Failed to claim task again: {:#}", e),
+        }
+    }
+
+    Ok(UndoResult {
+        workspace_path,
+        revision: entry.revision.clone(),
+        task_claimed,
+        proxy_urls: entry.proxy_urls.clone(),
     })
 }
 
+/// Proxy route URLs of an assignment's workspace, for the undo journal.
+/// Evaluated before cleanup releases the ports the routes point at.
+fn workspace_proxy_urls(assignment: &Assignment, ws_mgr: &WorkspaceManager, segment_path: &Path) -> Vec<String> {
+    let Some(ws_name) = assignment.workspace_path.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    let ancillary_num = crate::ancillary_number(&assignment.ancillary_id).unwrap_or(0);
+    ws_mgr
+        .describe_workspace(segment_path, &assignment.workspace_path, ws_name, ancillary_num)
+        .map(|facts| facts.proxy_urls)
+        .unwrap_or_default()
+}
+
+/// Record an operation in the undo journal. A failure only costs the undo.
+fn journal_undo(assignment_mgr: &AssignmentManager, entry: UndoEntry) {
+    if let Err(e) = assignment_mgr.undo_journal().record(&entry) {
+        tracing::warn!("Failed to record undo entry: {:#}", e);
+    }
+}
+
+/// Copy the artifacts declared in the segment's toren.kdl out of the
+/// workspace before it is destroyed. Failures are logged, never fatal: a
+/// missing report shouldn't block completing the work.
+fn keep_artifacts(
+    assignment: &Assignment,
+    assignment_mgr: &AssignmentManager,
+    segment_path: &Path,
+) -> Vec<String> {
+    let patterns = match BreqConfig::parse(segment_path) {
+        Ok(config) => config.artifacts,
+        Err(e) => {
+            tracing::warn!("Skipping artifacts: {:#}", e);
+            return Vec::new();
+        }
+    };
+    if patterns.is_empty() {
+        return Vec::new();
+    }
+    let dest = assignment_mgr.artifacts_dir(&assignment.id);
+    match crate::artifacts::capture(&assignment.workspace_path, &patterns, &dest) {
+        Ok(kept) => {
+            if !kept.is_empty() {
+                info!("Kept {} artifact(s) in {}", kept.len(), dest.display());
+            }
+            kept
+        }
+        Err(e) => {
+            tracing::warn!("Failed to keep artifacts: {:#}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Auto-commit the companion workspaces of a segment group assignment and,
+/// when pushing, check and push each one that has a revision.
+fn complete_companions(
+    assignment: &Assignment,
+    ws_mgr: &WorkspaceManager,
+    auto_commit_message: Option<&str>,
+    push: bool,
+    push_checks: Option<&crate::config::PushConfig>,
+) -> Result<()> {
+    for companion in assignment.companions.iter().filter(|c| c.workspace_path.exists()) {
+        if let Err(e) = ws_mgr.remove_agent_context(&companion.workspace_path) {
+            tracing::warn!("Failed to remove agent context: {:#}", e);
+        }
+        if let Some(message) = auto_commit_message {
+            if let Err(e) = ws_mgr.auto_commit(&companion.segment_path, &companion.workspace_path, message) {
+                tracing::warn!("Auto-commit failed in {}: {:#}", companion.segment, e);
+            }
+        }
+        if push && ws_mgr.capture_revision(&companion.segment_path, &companion.workspace_path).is_some() {
+            if let Some(config) = push_checks {
+                let diff = ws_mgr
+                    .push_diff(&companion.segment_path, &companion.workspace_path, companion.base_branch.as_deref())
+                    .with_context(|| format!("Failed to diff the {} workspace for push checks", companion.segment))?;
+                crate::push_checks::verify(config, &companion.workspace_path, &diff)?;
+            }
+            info!("Pushing changes in {} for assignment {}", companion.segment, assignment.id);
+            ws_mgr.push(&companion.segment_path, &companion.workspace_path)?;
+        }
+    }
+    Ok(())
+}
+
 /// Cleanup workspace for an assignment (process check + destroy hooks + VCS tracking removal + delete)
 fn cleanup_workspace(
     assignment: &Assignment,
@@ -484,5 +1130,43 @@ fn cleanup_workspace(
     let result =
         ws_mgr.cleanup_workspace(segment_path, &segment_name, ws_name, mode)?;
     info!("Workspace cleaned up for assignment {}", assignment.id);
+
+    // A segment group's other workspaces go with it
+    for companion in &assignment.companions {
+        if companion.workspace_path.exists() {
+            let processes = crate::process::find_workspace_processes(&companion.workspace_path);
+            if !processes.is_empty() {
+                if kill {
+                    crate::process::terminate_processes(&processes, std::time::Duration::from_secs(5))?;
+                } else {
+                    return Err(crate::process::WorkspaceProcessesRunning { processes }.into());
+                }
+            }
+        }
+        ws_mgr.cleanup_workspace(&companion.segment_path, &companion.segment, ws_name, mode)?;
+        info!("Workspace in {} cleaned up for assignment {}", companion.segment, assignment.id);
+    }
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_claim_identity() {
+        let seg = Path::new("/repos/toren");
+        assert_eq!(render_claim_identity(None, "toren", seg, &"Toren One".parse().unwrap()), "claude");
+        assert_eq!(
+            render_claim_identity(Some("{{ repo.name }}-{{ ws.name }}"), "toren", seg, &"Toren Twenty-One".parse().unwrap()),
+            "toren-twenty-one"
+        );
+        assert_eq!(
+            render_claim_identity(Some("anc{{ ws.num }}"), "toren", seg, &"Toren Three".parse().unwrap()),
+            "anc3"
+        );
+        // Unrenderable or empty templates fall back to the default
+        assert_eq!(render_claim_identity(Some("{{ oops"), "toren", seg, &"Toren One".parse().unwrap()), "claude");
+        assert_eq!(render_claim_identity(Some(" "), "toren", seg, &"Toren One".parse().unwrap()), "claude");
+    }
+}
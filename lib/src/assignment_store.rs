@@ -0,0 +1,535 @@
+//! Persistence for assignments and their completion history.
+//!
+//! [`AssignmentManager`](crate::AssignmentManager) keeps assignments in
+//! memory and writes them through an [`AssignmentStore`]:
+//!
+//! - [`JsonStore`] (default): `assignments.json`, rewritten whole on every
+//!   change, and the append-only `completion_history.jsonl`.
+//! - `SqliteStore` (feature `sqlite`): both in `toren.db`. Each save is one
+//!   transaction that writes only the assignments this process changed, so
+//!   breq and the daemon saving at the same time don't undo each other's
+//!   changes, and history queries by segment use an index. On first open it
+//!   imports the JSON files and renames them to `*.migrated`.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::debug;
+
+use crate::assignment::{Assignment, CompletionRecord};
+
+/// Which completion records to read, newest first.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub segment: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, record: &CompletionRecord) -> bool {
+        self.segment.as_ref().is_none_or(|s| &record.segment == s)
+    }
+}
+
+/// Backing storage for an [`AssignmentManager`](crate::AssignmentManager).
+pub trait AssignmentStore: Send + Sync {
+    /// File the store lives in; artifacts and the undo journal are kept
+    /// beside it.
+    fn path(&self) -> &Path;
+
+    /// Read all assignments.
+    fn load(&mut self) -> Result<Vec<Assignment>>;
+
+    /// Whether another process has written since the last load or save.
+    fn changed(&self) -> bool;
+
+    /// Persist the assignments, replacing what was loaded.
+    fn save(&mut self, assignments: &[&Assignment]) -> Result<()>;
+
+    /// Append a completion record to the history.
+    fn append_history(&self, record: &CompletionRecord) -> Result<()>;
+
+    /// Completion records, oldest first. Unreadable records are skipped.
+    fn history(&self) -> Result<Vec<CompletionRecord>>;
+
+    /// Completion records matching `filter`, newest first.
+    fn recent_history(&self, filter: &HistoryFilter) -> Result<Vec<CompletionRecord>> {
+        Ok(self
+            .history()?
+            .into_iter()
+            .rev()
+            .filter(|r| filter.matches(r))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Remove the completion records matching `remove`, returning them.
+    fn prune_history(&self, remove: &dyn Fn(&CompletionRecord) -> bool) -> Result<Vec<CompletionRecord>>;
+}
+
+/// `assignments.json` with `completion_history.jsonl` beside it.
+pub struct JsonStore {
+    storage_path: PathBuf,
+    /// Last known modification time of the assignments file
+    last_mtime: Option<SystemTime>,
+}
+
+impl JsonStore {
+    pub fn new(storage_path: PathBuf) -> Self {
+        Self {
+            storage_path,
+            last_mtime: None,
+        }
+    }
+
+    fn mtime(&self) -> Option<SystemTime> {
+        self.storage_path.metadata().ok().and_then(|m| m.modified().ok())
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.storage_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("completion_history.jsonl")
+    }
+}
+
+impl AssignmentStore for JsonStore {
+    fn path(&self) -> &Path {
+        &self.storage_path
+    }
+
+    fn load(&mut self) -> Result<Vec<Assignment>> {
+        if !self.storage_path.exists() {
+            debug!("No existing assignments file at {}", self.storage_path.display());
+            self.last_mtime = None;
+            return Ok(Vec::new());
+        }
+
+        let mtime = self.mtime();
+        let content = std::fs::read_to_string(&self.storage_path)
+            .with_context(|| format!("Failed to read {}", self.storage_path.display()))?;
+        let assignments: Vec<Assignment> =
+            serde_json::from_str(&content).with_context(|| "Failed to parse assignments.json")?;
+        self.last_mtime = mtime;
+        Ok(assignments)
+    }
+
+    fn changed(&self) -> bool {
+        self.mtime() != self.last_mtime
+    }
+
+    fn save(&mut self, assignments: &[&Assignment]) -> Result<()> {
+        let content = serde_json::to_string_pretty(assignments)
+            .with_context(|| "Failed to serialize assignments")?;
+
+        // Ensure parent directory exists
+        if let Some(parent) = self.storage_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(&self.storage_path, content)
+            .with_context(|| format!("Failed to write {}", self.storage_path.display()))?;
+
+        // Update tracked mtime so we don't reload our own writes
+        self.last_mtime = self.mtime();
+        Ok(())
+    }
+
+    fn append_history(&self, record: &CompletionRecord) -> Result<()> {
+        let history_path = self.history_path();
+        let mut line = serde_json::to_string(record)
+            .with_context(|| "Failed to serialize completion record")?;
+        line.push('\n');
+
+        use std::io::Write;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&history_path)
+            .with_context(|| format!("Failed to open {}", history_path.display()))?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn history(&self) -> Result<Vec<CompletionRecord>> {
+        let path = self.history_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Unparseable lines are kept as they are.
+    fn prune_history(&self, remove: &dyn Fn(&CompletionRecord) -> bool) -> Result<Vec<CompletionRecord>> {
+        let path = self.history_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let mut kept = String::with_capacity(content.len());
+        let mut removed = Vec::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<CompletionRecord>(line) {
+                Ok(record) if remove(&record) => removed.push(record),
+                _ => {
+                    kept.push_str(line);
+                    kept.push('\n');
+                }
+            }
+        }
+        if !removed.is_empty() {
+            std::fs::write(&path, kept)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use rusqlite::{params, Connection, OptionalExtension};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS assignments (
+            id TEXT PRIMARY KEY,
+            segment TEXT NOT NULL,
+            ancillary_id TEXT NOT NULL,
+            task_id TEXT,
+            created_at TEXT NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS assignments_segment ON assignments (segment);
+        CREATE TABLE IF NOT EXISTS completion_history (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            assignment_id TEXT NOT NULL,
+            segment TEXT NOT NULL,
+            task_id TEXT,
+            reason TEXT NOT NULL,
+            completed_at TEXT NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS history_segment ON completion_history (segment, seq);
+        CREATE INDEX IF NOT EXISTS history_task ON completion_history (task_id);
+    ";
+
+    /// Assignments and completion history in one SQLite database.
+    pub struct SqliteStore {
+        path: PathBuf,
+        conn: Mutex<Connection>,
+        /// Each assignment as last loaded or saved, serialized, so a save
+        /// writes only what this process changed
+        known: HashMap<String, String>,
+        /// `PRAGMA data_version` at the last load; it changes when another
+        /// connection commits
+        data_version: i64,
+    }
+
+    impl SqliteStore {
+        /// Open (or create) the database at `path`. If it is new, import
+        /// `assignments.json` and `completion_history.jsonl` from beside it
+        /// and rename them to `*.migrated`.
+        pub fn open(path: PathBuf) -> Result<Self> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let conn = Connection::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            conn.busy_timeout(std::time::Duration::from_secs(5))?;
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            conn.execute_batch(SCHEMA)
+                .with_context(|| format!("Failed to create tables in {}", path.display()))?;
+            let store = Self {
+                path,
+                conn: Mutex::new(conn),
+                known: HashMap::new(),
+                data_version: 0,
+            };
+            store.migrate_json()?;
+            Ok(store)
+        }
+
+        fn data_version(conn: &Connection) -> i64 {
+            conn.query_row("PRAGMA data_version", [], |row| row.get(0)).unwrap_or(0)
+        }
+
+        fn migrate_json(&self) -> Result<()> {
+            let dir = self.path.parent().unwrap_or(Path::new("."));
+            let json_path = dir.join("assignments.json");
+            let mut json = JsonStore::new(json_path.clone());
+            let history_path = json.history_path();
+            if !json_path.exists() && !history_path.exists() {
+                return Ok(());
+            }
+
+            let mut conn = self.conn.lock().unwrap();
+            let populated: Option<i64> = conn
+                .query_row(
+                    "SELECT 1 FROM assignments UNION ALL SELECT 1 FROM completion_history LIMIT 1",
+                    [],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if populated.is_some() {
+                return Ok(());
+            }
+
+            let assignments = json.load()?;
+            let history = json.history()?;
+            let tx = conn.transaction()?;
+            for assignment in &assignments {
+                upsert(&tx, assignment, &serde_json::to_string(assignment)?)?;
+            }
+            for record in &history {
+                insert_history(&tx, record)?;
+            }
+            tx.commit()?;
+
+            for path in [json_path, history_path] {
+                if path.exists() {
+                    let mut migrated = path.clone().into_os_string();
+                    migrated.push(".migrated");
+                    std::fs::rename(&path, &migrated)
+                        .with_context(|| format!("Failed to rename {}", path.display()))?;
+                }
+            }
+            tracing::info!(
+                "Migrated {} assignments and {} history records to {}",
+                assignments.len(),
+                history.len(),
+                self.path.display()
+            );
+            Ok(())
+        }
+
+        fn read_history(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<CompletionRecord>> {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt.query_map(params, |row| row.get::<_, String>(0))?;
+            Ok(rows
+                .filter_map(|data| data.ok())
+                .filter_map(|data| serde_json::from_str(&data).ok())
+                .collect())
+        }
+    }
+
+    fn upsert(conn: &Connection, assignment: &Assignment, data: &str) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO assignments (id, segment, ancillary_id, task_id, created_at, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                assignment.id,
+                assignment.segment,
+                assignment.ancillary_id.as_ref(),
+                assignment.task_id.as_deref(),
+                assignment.created_at,
+                data,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn insert_history(conn: &Connection, record: &CompletionRecord) -> Result<()> {
+        let reason = serde_json::to_value(&record.reason)?;
+        conn.execute(
+            "INSERT INTO completion_history (assignment_id, segment, task_id, reason, completed_at, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                record.assignment_id,
+                record.segment,
+                record.task_id.as_deref(),
+                reason.as_str().unwrap_or_default(),
+                record.completed_at,
+                serde_json::to_string(record)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    impl AssignmentStore for SqliteStore {
+        fn path(&self) -> &Path {
+            &self.path
+        }
+
+        fn load(&mut self) -> Result<Vec<Assignment>> {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare("SELECT data FROM assignments")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            let mut assignments = Vec::new();
+            self.known.clear();
+            for data in rows {
+                let assignment: Assignment = serde_json::from_str(&data?)
+                    .with_context(|| format!("Failed to parse an assignment in {}", self.path.display()))?;
+                self.known
+                    .insert(assignment.id.clone(), serde_json::to_string(&assignment)?);
+                assignments.push(assignment);
+            }
+            self.data_version = Self::data_version(&conn);
+            Ok(assignments)
+        }
+
+        fn changed(&self) -> bool {
+            Self::data_version(&self.conn.lock().unwrap()) != self.data_version
+        }
+
+        fn save(&mut self, assignments: &[&Assignment]) -> Result<()> {
+            let mut conn = self.conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            let mut saved = HashMap::with_capacity(assignments.len());
+            for assignment in assignments {
+                let data = serde_json::to_string(assignment)?;
+                if self.known.get(&assignment.id) != Some(&data) {
+                    upsert(&tx, assignment, &data)?;
+                }
+                saved.insert(assignment.id.clone(), data);
+            }
+            // Only rows this process removed; rows another process added
+            // since the last load are left alone
+            for id in self.known.keys().filter(|id| !saved.contains_key(*id)) {
+                tx.execute("DELETE FROM assignments WHERE id = ?1", params![id])?;
+            }
+            tx.commit()
+                .with_context(|| format!("Failed to write {}", self.path.display()))?;
+            self.known = saved;
+            Ok(())
+        }
+
+        fn append_history(&self, record: &CompletionRecord) -> Result<()> {
+            insert_history(&self.conn.lock().unwrap(), record)
+        }
+
+        fn history(&self) -> Result<Vec<CompletionRecord>> {
+            self.read_history("SELECT data FROM completion_history ORDER BY seq", [])
+        }
+
+        fn recent_history(&self, filter: &HistoryFilter) -> Result<Vec<CompletionRecord>> {
+            let limit = filter.limit.map_or(-1, |l| l as i64);
+            match filter.segment {
+                Some(ref segment) => self.read_history(
+                    "SELECT data FROM completion_history WHERE segment = ?1 ORDER BY seq DESC LIMIT ?2",
+                    params![segment, limit],
+                ),
+                None => self.read_history(
+                    "SELECT data FROM completion_history ORDER BY seq DESC LIMIT ?1",
+                    params![limit],
+                ),
+            }
+        }
+
+        fn prune_history(&self, remove: &dyn Fn(&CompletionRecord) -> bool) -> Result<Vec<CompletionRecord>> {
+            let mut conn = self.conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            let rows: Vec<(i64, String)> = {
+                let mut stmt = tx.prepare("SELECT seq, data FROM completion_history ORDER BY seq")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            let mut removed = Vec::new();
+            for (seq, data) in rows {
+                let Ok(record) = serde_json::from_str::<CompletionRecord>(&data) else {
+                    continue;
+                };
+                if remove(&record) {
+                    tx.execute("DELETE FROM completion_history WHERE seq = ?1", params![seq])?;
+                    removed.push(record);
+                }
+            }
+            tx.commit()?;
+            Ok(removed)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::assignment::{AssignmentManager, AssignmentSource, CompletionReason};
+
+        fn create(mgr: &mut AssignmentManager, name: &str, dir: &Path) -> Assignment {
+            mgr.create(&name.parse().unwrap(), None, AssignmentSource::Reference, "toren", dir.join(name), None, None, None, None)
+                .unwrap()
+        }
+
+        #[test]
+        fn test_migrates_json_files() {
+            let tmp = tempfile::tempdir().unwrap();
+            let mut json = AssignmentManager::with_storage_path(tmp.path().join("assignments.json")).unwrap();
+            let a = create(&mut json, "Toren One", tmp.path());
+            json.record_completion(&a, CompletionReason::Aborted, None, Vec::new()).unwrap();
+
+            let store = SqliteStore::open(tmp.path().join("toren.db")).unwrap();
+            let mut mgr = AssignmentManager::with_store(Box::new(store)).unwrap();
+            assert_eq!(mgr.list().len(), 1);
+            assert_eq!(mgr.history().unwrap().len(), 1);
+            assert!(!tmp.path().join("assignments.json").exists());
+            assert!(tmp.path().join("assignments.json.migrated").exists());
+            assert!(tmp.path().join("completion_history.jsonl.migrated").exists());
+        }
+
+        #[test]
+        fn test_concurrent_writers_keep_each_others_changes() {
+            let tmp = tempfile::tempdir().unwrap();
+            let db = tmp.path().join("toren.db");
+            let mut first = AssignmentManager::with_store(Box::new(SqliteStore::open(db.clone()).unwrap())).unwrap();
+            let a = create(&mut first, "Toren One", tmp.path());
+            let b = create(&mut first, "Toren Two", tmp.path());
+
+            // A second process removes one assignment and adds another...
+            let mut second = AssignmentManager::with_store(Box::new(SqliteStore::open(db.clone()).unwrap())).unwrap();
+            second.remove(&b.id).unwrap();
+            let c = create(&mut second, "Toren Three", tmp.path());
+
+            // ...while the first, not having reloaded, changes another
+            first.set_labels(&a.id, vec!["x".to_string()]).unwrap();
+
+            let mut reopened = AssignmentManager::with_store(Box::new(SqliteStore::open(db).unwrap())).unwrap();
+            let mut ids: Vec<String> = reopened.list().into_iter().map(|a| a.id.clone()).collect();
+            ids.sort();
+            let mut expected = vec![a.id.clone(), c.id.clone()];
+            expected.sort();
+            assert_eq!(ids, expected);
+            assert_eq!(reopened.get(&a.id).unwrap().labels, vec!["x"]);
+        }
+
+        #[test]
+        fn test_recent_history_by_segment() {
+            let tmp = tempfile::tempdir().unwrap();
+            let store = SqliteStore::open(tmp.path().join("toren.db")).unwrap();
+            let mut mgr = AssignmentManager::with_store(Box::new(store)).unwrap();
+            let a = create(&mut mgr, "Toren One", tmp.path());
+            for reason in [CompletionReason::Aborted, CompletionReason::Completed] {
+                mgr.record_completion(&a, reason, None, Vec::new()).unwrap();
+            }
+
+            let filter = HistoryFilter {
+                segment: Some("toren".to_string()),
+                limit: Some(1),
+            };
+            let recent = mgr.recent_history(&filter).unwrap();
+            assert_eq!(recent.len(), 1);
+            assert!(matches!(recent[0].reason, CompletionReason::Completed));
+            let other = HistoryFilter {
+                segment: Some("other".to_string()),
+                limit: None,
+            };
+            assert!(mgr.recent_history(&other).unwrap().is_empty());
+
+            let pruned = mgr.prune_history(|r| matches!(r.reason, CompletionReason::Aborted)).unwrap();
+            assert_eq!(pruned.len(), 1);
+            assert_eq!(mgr.history().unwrap().len(), 1);
+        }
+    }
+}
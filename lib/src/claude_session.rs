@@ -0,0 +1,416 @@
+//! Typed entries of Claude Code session logs.
+//!
+//! [`SessionLogs`] knows where a workspace's session logs live; this module
+//! reads them. Each JSONL line becomes a [`SessionEntry`] with its content
+//! blocks (text, thinking, tool use, tool result) and token usage, so
+//! activity detection, final reports and `breq show --transcript` don't
+//! each pick through raw JSON. Lines that aren't valid JSON are skipped;
+//! entry types Claude Code adds later parse as [`EntryKind::Other`].
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read as _, Seek, SeekFrom};
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::session::SessionLogs;
+
+/// How much of a log's tail is read to find its last entry. Lines can be
+/// large, since tool results carry file contents and command output.
+const TAIL_BYTES: u64 = 262144;
+
+/// The `type` of a session log entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    User,
+    Assistant,
+    Progress,
+    System,
+    Summary,
+    Other(String),
+}
+
+/// One block of an entry's message content.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        /// Text of the result; other content (images) is left out
+        content: String,
+        is_error: bool,
+    },
+    /// A block type this parser doesn't know
+    Other {
+        kind: String,
+    },
+}
+
+/// Tokens an assistant message used, as reported by the API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+/// One line of a session log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionEntry {
+    pub kind: EntryKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// RFC 3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// API message ID; Claude Code writes one entry per content block, all
+    /// with the same ID and usage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    /// Model that wrote an assistant message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub content: Vec<ContentBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+impl SessionEntry {
+    /// Parse one JSONL line. None if it isn't a JSON object.
+    pub fn parse(line: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let str_field = |v: &serde_json::Value, key: &str| v.get(key).and_then(|s| s.as_str()).map(String::from);
+        let kind = match value.as_object()?.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "user" => EntryKind::User,
+            "assistant" => EntryKind::Assistant,
+            "progress" => EntryKind::Progress,
+            "system" => EntryKind::System,
+            "summary" => EntryKind::Summary,
+            other => EntryKind::Other(other.to_string()),
+        };
+        let message = value.get("message");
+        let content = match message.and_then(|m| m.get("content")) {
+            Some(serde_json::Value::String(text)) => vec![ContentBlock::Text { text: text.clone() }],
+            Some(serde_json::Value::Array(blocks)) => blocks.iter().map(content_block).collect(),
+            _ => Vec::new(),
+        };
+        let usage = message.and_then(|m| m.get("usage")).map(|u| {
+            let count = |key: &str| u.get(key).and_then(|n| n.as_u64()).unwrap_or(0);
+            TokenUsage {
+                input_tokens: count("input_tokens"),
+                output_tokens: count("output_tokens"),
+                cache_creation_input_tokens: count("cache_creation_input_tokens"),
+                cache_read_input_tokens: count("cache_read_input_tokens"),
+            }
+        });
+        Some(Self {
+            kind,
+            session_id: str_field(&value, "sessionId"),
+            timestamp: str_field(&value, "timestamp"),
+            message_id: message.and_then(|m| str_field(m, "id")),
+            model: message.and_then(|m| str_field(m, "model")),
+            content,
+            stop_reason: message.and_then(|m| str_field(m, "stop_reason")),
+            usage,
+        })
+    }
+
+    /// Whether the agent is mid-turn after this entry:
+    /// - an assistant entry calling a tool → the tool is executing
+    /// - a user prompt or tool result → the agent owes a response
+    /// - progress or thinking → streaming
+    ///
+    /// An assistant entry with only text, and system or summary entries,
+    /// end the turn.
+    pub fn is_mid_turn(&self) -> bool {
+        match &self.kind {
+            EntryKind::Assistant => {
+                self.tool_uses().next().is_some()
+                    || (self.content.is_empty() && self.stop_reason.as_deref() == Some("tool_use"))
+            }
+            EntryKind::User => self
+                .content
+                .iter()
+                .any(|block| matches!(block, ContentBlock::Text { .. } | ContentBlock::ToolResult { .. })),
+            EntryKind::Progress => true,
+            EntryKind::Other(kind) => kind.contains("thinking"),
+            EntryKind::System | EntryKind::Summary => false,
+        }
+    }
+
+    /// The entry's text blocks, joined by newlines. None if it has none.
+    pub fn text(&self) -> Option<String> {
+        let text: Vec<&str> = self
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        (!text.is_empty()).then(|| text.join("\n"))
+    }
+
+    /// Tool calls in the entry: `(name, input)`.
+    pub fn tool_uses(&self) -> impl Iterator<Item = (&str, &serde_json::Value)> {
+        self.content.iter().filter_map(|block| match block {
+            ContentBlock::ToolUse { name, input, .. } => Some((name.as_str(), input)),
+            _ => None,
+        })
+    }
+}
+
+fn content_block(block: &serde_json::Value) -> ContentBlock {
+    let text = |key: &str| block.get(key).and_then(|t| t.as_str()).unwrap_or_default().to_string();
+    match block.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+        "text" => ContentBlock::Text { text: text("text") },
+        "thinking" => ContentBlock::Thinking {
+            thinking: text("thinking"),
+        },
+        "tool_use" => ContentBlock::ToolUse {
+            id: text("id"),
+            name: text("name"),
+            input: block.get("input").cloned().unwrap_or_default(),
+        },
+        "tool_result" => ContentBlock::ToolResult {
+            tool_use_id: text("tool_use_id"),
+            content: match block.get("content") {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(serde_json::Value::Array(parts)) => parts
+                    .iter()
+                    .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            },
+            is_error: block.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false),
+        },
+        other => ContentBlock::Other {
+            kind: other.to_string(),
+        },
+    }
+}
+
+/// Token usage of each API message in `entries`, with the model that
+/// wrote it. Entries of one message repeat its usage, so each message is
+/// counted once, with its last (final) count.
+pub fn message_usage(entries: &[SessionEntry]) -> Vec<(Option<&str>, TokenUsage)> {
+    let mut by_message: HashMap<&str, usize> = HashMap::new();
+    let mut messages = Vec::new();
+    for entry in entries {
+        let Some(usage) = entry.usage else {
+            continue;
+        };
+        let model = entry.model.as_deref();
+        match entry.message_id.as_deref() {
+            Some(id) => match by_message.get(id) {
+                Some(&i) => messages[i] = (model, usage),
+                None => {
+                    by_message.insert(id, messages.len());
+                    messages.push((model, usage));
+                }
+            },
+            None => messages.push((model, usage)),
+        }
+    }
+    messages
+}
+
+/// Total token usage of a session's entries, counting each API message once.
+pub fn token_totals(entries: &[SessionEntry]) -> TokenUsage {
+    let mut total = TokenUsage::default();
+    for (_, usage) in message_usage(entries) {
+        total += usage;
+    }
+    total
+}
+
+/// A Claude Code session log of a workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaudeSession {
+    /// The session ID, which names the log file
+    pub id: String,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+impl ClaudeSession {
+    /// The workspace's most recently written session, across log roots.
+    pub fn latest(logs: &SessionLogs, workspace_path: &Path) -> Option<Self> {
+        let (path, modified) = logs.latest_log(workspace_path)?;
+        Self::at(path, modified)
+    }
+
+    /// Every session of the workspace, across log roots.
+    pub fn all(logs: &SessionLogs, workspace_path: &Path) -> Vec<Self> {
+        logs.logs(workspace_path)
+            .into_iter()
+            .filter_map(|(path, modified)| Self::at(path, modified))
+            .collect()
+    }
+
+    /// A session of the workspace by ID.
+    pub fn find(logs: &SessionLogs, workspace_path: &Path, id: &str) -> Option<Self> {
+        logs.project_dirs(workspace_path)
+            .into_iter()
+            .map(|dir| dir.join(format!("{}.jsonl", id)))
+            .find_map(|path| {
+                let modified = path.metadata().ok()?.modified().ok()?;
+                Self::at(path, modified)
+            })
+    }
+
+    fn at(path: PathBuf, modified: SystemTime) -> Option<Self> {
+        let id = path.file_stem()?.to_str()?.to_string();
+        Some(Self { id, path, modified })
+    }
+
+    /// Every entry of the log, in order.
+    pub fn entries(&self) -> Result<Vec<SessionEntry>> {
+        let file = std::fs::File::open(&self.path)
+            .with_context(|| format!("Failed to open session log {}", self.path.display()))?;
+        Ok(BufReader::new(file)
+            .lines()
+            .map_while(std::result::Result::ok)
+            .filter_map(|line| SessionEntry::parse(&line))
+            .collect())
+    }
+
+    /// The log's last entry, reading only its tail.
+    pub fn last_entry(&self) -> Option<SessionEntry> {
+        read_last_line(&self.path).and_then(|line| SessionEntry::parse(&line))
+    }
+}
+
+/// The last non-empty line of a file, read from its tail.
+pub(crate) fn read_last_line(path: &Path) -> Option<String> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    if file_len == 0 {
+        return None;
+    }
+
+    let read_size = file_len.min(TAIL_BYTES);
+    file.seek(SeekFrom::End(-(read_size as i64))).ok()?;
+    let mut buf = vec![0u8; read_size as usize];
+    file.read_exact(&mut buf).ok()?;
+
+    let text = String::from_utf8_lossy(&buf);
+    text.lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries_and_mid_turn() {
+        let tool_call = SessionEntry::parse(
+            r#"{"type":"assistant","sessionId":"s1","timestamp":"2026-01-02T03:04:05Z","message":{"id":"m1","content":[{"type":"text","text":"Checking"},{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}}],"stop_reason":"tool_use","usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":100}}}"#,
+        )
+        .unwrap();
+        assert_eq!(tool_call.kind, EntryKind::Assistant);
+        assert_eq!(tool_call.session_id.as_deref(), Some("s1"));
+        assert_eq!(tool_call.text().as_deref(), Some("Checking"));
+        assert_eq!(tool_call.tool_uses().next().unwrap().0, "Bash");
+        assert_eq!(tool_call.usage.unwrap().cache_read_input_tokens, 100);
+        assert!(tool_call.is_mid_turn());
+
+        let result = SessionEntry::parse(
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":[{"type":"text","text":"a.txt"}],"is_error":true}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.content,
+            vec![ContentBlock::ToolResult {
+                tool_use_id: "t1".to_string(),
+                content: "a.txt".to_string(),
+                is_error: true,
+            }]
+        );
+        assert!(result.is_mid_turn());
+
+        let prompt = SessionEntry::parse(r#"{"type":"user","message":{"role":"user","content":"Fix it"}}"#).unwrap();
+        assert!(prompt.is_mid_turn());
+        let reply = SessionEntry::parse(r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Done"}]}}"#).unwrap();
+        assert!(!reply.is_mid_turn());
+        assert!(SessionEntry::parse(r#"{"type":"progress"}"#).unwrap().is_mid_turn());
+        assert!(SessionEntry::parse(r#"{"type":"thinking_delta"}"#).unwrap().is_mid_turn());
+        assert!(!SessionEntry::parse(r#"{"type":"summary","summary":"x"}"#).unwrap().is_mid_turn());
+        assert!(SessionEntry::parse("not json").is_none());
+    }
+
+    #[test]
+    fn test_token_totals_count_each_message_once() {
+        let usage = |input, output| TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            ..Default::default()
+        };
+        let entry = |id: &str, usage| SessionEntry {
+            kind: EntryKind::Assistant,
+            session_id: None,
+            timestamp: None,
+            message_id: Some(id.to_string()),
+            model: None,
+            content: Vec::new(),
+            stop_reason: None,
+            usage: Some(usage),
+        };
+        let entries = [entry("m1", usage(10, 1)), entry("m1", usage(10, 7)), entry("m2", usage(20, 3))];
+        assert_eq!(token_totals(&entries), usage(30, 10));
+    }
+
+    #[test]
+    fn test_latest_and_find_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = SessionLogs::new(vec![dir.path().to_path_buf()]);
+        let ws = Path::new("/ws/one");
+        assert!(ClaudeSession::latest(&logs, ws).is_none());
+
+        let project = logs.project_dirs(ws).remove(0);
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("abc.jsonl"),
+            "{\"type\":\"user\",\"message\":{\"content\":\"hi\"}}\nnot json\n{\"type\":\"assistant\",\"message\":{\"content\":[{\"type\":\"text\",\"text\":\"hello\"}]}}\n\n",
+        )
+        .unwrap();
+
+        let session = ClaudeSession::latest(&logs, ws).unwrap();
+        assert_eq!(session.id, "abc");
+        assert_eq!(session.entries().unwrap().len(), 2);
+        assert_eq!(session.last_entry().unwrap().text().as_deref(), Some("hello"));
+        assert_eq!(ClaudeSession::find(&logs, ws, "abc"), Some(session));
+        assert!(ClaudeSession::find(&logs, ws, "missing").is_none());
+    }
+}
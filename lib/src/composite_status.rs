@@ -3,12 +3,18 @@
 //! The real status is derived from four observable signals:
 //! 1. Agent activity (busy/idle) — from Claude session log last-entry-type
 //! 2. Bead assignee — from bd
-//! 3. Has changes — from VCS workspace
+//! 3. Has changes — from VCS workspace, along with unresolved conflicts
 //! 4. Bead status — from bd
 
 use serde::{Deserialize, Serialize};
-use std::io::{Read as _, Seek, SeekFrom};
-use std::path::{Path, PathBuf};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::claude_session::{read_last_line, ClaudeSession, SessionEntry};
+use crate::session::SessionLogs;
+
+/// Session logs untouched for longer than this are never considered busy.
+const STALE_SESSION_SECS: u64 = 300;
 
 /// Composite status signals for an assignment
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +23,9 @@ pub struct CompositeStatus {
     pub agent_activity: String,
     /// Whether the workspace has changes since trunk (committed or uncommitted)
     pub has_changes: bool,
+    /// Whether the working copy has unresolved conflicts
+    #[serde(default)]
+    pub has_conflicts: bool,
     /// Task status: "open", "in_progress", "closed"
     #[serde(alias = "bead_status")]
     pub task_status: String,
@@ -31,7 +40,14 @@ pub struct CompositeStatus {
 ///
 /// `base_ref` is used for git worktrees as the comparison branch (e.g., "main").
 /// For jj workspaces, base_ref is ignored (uses `default@` revset).
-pub fn workspace_has_changes(workspace_path: &Path, base_ref: Option<&str>) -> bool {
+///
+/// `scope` restricts the check to a workspace-relative path, for assignments
+/// focused on a subdirectory.
+pub fn workspace_has_changes(
+    workspace_path: &Path,
+    base_ref: Option<&str>,
+    scope: Option<&Path>,
+) -> bool {
     use crate::workspace::{detect_repo_type, GitWorktreeBackend, JjBackend, RepoType, VcsBackend};
 
     if !workspace_path.exists() {
@@ -39,186 +55,371 @@ pub fn workspace_has_changes(workspace_path: &Path, base_ref: Option<&str>) -> b
     }
 
     match detect_repo_type(workspace_path) {
-        Some(RepoType::Jj) => JjBackend.has_changes(workspace_path, base_ref),
-        Some(RepoType::Git) => GitWorktreeBackend.has_changes(workspace_path, base_ref),
+        Some(RepoType::Jj) => JjBackend.has_changes(workspace_path, base_ref, scope),
+        Some(RepoType::Git) => GitWorktreeBackend.has_changes(workspace_path, base_ref, scope),
         None => false,
     }
 }
 
+/// Paths with unresolved conflicts in a workspace's working copy, via
+/// `jj resolve --list` or git's unmerged paths. Empty for a missing or
+/// unrecognized workspace.
+pub fn workspace_conflicts(workspace_path: &Path) -> Vec<String> {
+    use crate::workspace::{detect_repo_type, GitWorktreeBackend, JjBackend, RepoType, VcsBackend};
+
+    if !workspace_path.exists() {
+        return Vec::new();
+    }
+
+    match detect_repo_type(workspace_path) {
+        Some(RepoType::Jj) => JjBackend.conflicted_paths(workspace_path),
+        Some(RepoType::Git) => GitWorktreeBackend.conflicted_paths(workspace_path),
+        None => Vec::new(),
+    }
+}
+
 /// Detect agent activity by checking the last entry type in Claude Code session logs.
 ///
-/// Claude Code stores per-directory session logs at:
-///   `~/.claude/projects/{dir_name}/{session_id}.jsonl`
-/// where `dir_name` is the workspace path with `/` and `.` replaced by `-`.
+/// Claude Code stores per-directory session logs under the roots resolved by
+/// [`SessionLogs`] (by default `~/.claude/projects/{dir_name}/{session_id}.jsonl`).
 ///
 /// Rather than just checking file modification recency (which misses long-running
 /// tool executions like `sleep 90`), we read the last JSONL entry and check if it
-/// indicates a mid-turn state. Mid-turn entry types include:
-/// - `assistant` with `tool_use` — a tool is executing
-/// - `user` with `tool_result` — Claude is processing tool output
-/// - `user` with `user_message` — Claude is processing user input
-/// - `assistant` with subtype containing `thinking` — extended thinking
-/// - `progress` — streaming in progress
+/// indicates a mid-turn state ([`SessionEntry::is_mid_turn`]): a tool is
+/// executing, Claude owes a response to a prompt or tool result, or it is
+/// still streaming or thinking.
 ///
 /// If the last entry is mid-turn AND the file was modified within 5 minutes,
 /// the agent is busy. The 5-minute threshold catches stale sessions (crashes, etc.).
-pub fn detect_agent_activity(workspace_path: &Path) -> String {
-    if let Some(project_dir) = claude_project_dir(workspace_path) {
-        if session_is_mid_turn(&project_dir) {
-            return "busy".to_string();
-        }
+pub fn detect_agent_activity(logs: &SessionLogs, workspace_path: &Path) -> String {
+    if session_is_mid_turn(logs, workspace_path) {
+        return "busy".to_string();
     }
 
     "idle".to_string()
 }
 
-/// Compute the Claude Code project directory for a workspace path.
-///
-/// Claude Code uses `~/.claude/projects/{dir_name}/` where `dir_name`
-/// is the absolute workspace path with `/` and `.` replaced by `-`.
-fn claude_project_dir(workspace_path: &Path) -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    let dir_name = workspace_path
-        .to_str()?
-        .replace(['/', '.'], "-");
-    let project_dir = home.join(".claude").join("projects").join(&dir_name);
-    if project_dir.is_dir() {
-        Some(project_dir)
-    } else {
-        None
-    }
+/// The latest entry in a workspace's Claude session logs.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionEvent {
+    /// When the log was last written
+    pub at: SystemTime,
+    /// Whether the entry leaves the agent mid-turn
+    pub mid_turn: bool,
 }
 
-/// Check if the most recent session log indicates a mid-turn state.
-///
-/// Finds the most recently modified `.jsonl`, reads its last line, and checks
-/// whether the entry type indicates Claude is mid-turn (busy). Also requires
-/// the file to have been modified within 5 minutes to avoid stale sessions.
-fn session_is_mid_turn(project_dir: &Path) -> bool {
-    let entries = match std::fs::read_dir(project_dir) {
-        Ok(e) => e,
-        Err(_) => return false,
-    };
-
-    // Find the most recently modified .jsonl file
-    let mut most_recent: Option<(PathBuf, std::time::SystemTime)> = None;
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) == Some("jsonl") {
-            if let Ok(meta) = path.metadata() {
-                if let Ok(modified) = meta.modified() {
-                    most_recent = Some(match most_recent {
-                        Some((_, prev_time)) if modified > prev_time => (path, modified),
-                        Some(prev) => prev,
-                        None => (path, modified),
-                    });
-                }
-            }
-        }
-    }
+/// The most recent session log event for a workspace, regardless of age.
+/// None if no Claude session has run there.
+pub fn last_session_event(logs: &SessionLogs, workspace_path: &Path) -> Option<SessionEvent> {
+    let session = ClaudeSession::latest(logs, workspace_path)?;
+    let mid_turn = session.last_entry().is_some_and(|entry| entry.is_mid_turn());
+    Some(SessionEvent {
+        at: session.modified,
+        mid_turn,
+    })
+}
 
-    let (path, modified) = match most_recent {
-        Some(v) => v,
-        None => return false,
+/// Check if the most recent session log indicates a mid-turn state
+/// (see [`SessionEntry::is_mid_turn`]). Also requires the log to have been
+/// written within 5 minutes to avoid stale sessions.
+fn session_is_mid_turn(logs: &SessionLogs, workspace_path: &Path) -> bool {
+    let Some(session) = ClaudeSession::latest(logs, workspace_path) else {
+        return false;
     };
 
     // Check staleness: file must have been modified within 5 minutes
-    let age_secs = modified.elapsed().unwrap_or_default().as_secs();
-    if age_secs > 300 {
+    let age_secs = session.modified.elapsed().unwrap_or_default().as_secs();
+    if age_secs > STALE_SESSION_SECS {
         return false;
     }
 
-    // Read the last line efficiently by seeking from the end
-    let last_line = match read_last_line(&path) {
-        Some(line) => line,
-        None => return false,
-    };
+    session.last_entry().is_some_and(|entry| entry.is_mid_turn())
+}
+
+// ==================== Explanations ====================
 
-    is_mid_turn_entry(&last_line)
+/// How one status signal was derived, for `breq why`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalExplanation {
+    /// Signal name, e.g. "activity"
+    pub signal: &'static str,
+    /// The value `breq list` shows
+    pub value: String,
+    /// What was looked at, in order
+    pub evidence: Vec<String>,
 }
 
-/// Read the last non-empty line of a file by seeking from the end.
-fn read_last_line(path: &Path) -> Option<String> {
-    let mut file = std::fs::File::open(path).ok()?;
-    let file_len = file.metadata().ok()?.len();
-    if file_len == 0 {
-        return None;
+/// Explain [`detect_agent_activity`]: the session directory and log it
+/// read, the log's age and the last entry.
+pub fn explain_agent_activity(logs: &SessionLogs, workspace_path: &Path) -> SignalExplanation {
+    let mut evidence = Vec::new();
+    let value = activity_evidence(logs, workspace_path, &mut evidence);
+    SignalExplanation {
+        signal: "activity",
+        value: value.to_string(),
+        evidence,
     }
+}
 
-    // Read up to 256KB from the end — session log lines can be large due to
-    // tool results (file contents, large outputs).
-    let read_size = file_len.min(262144);
-    file.seek(SeekFrom::End(-(read_size as i64))).ok()?;
+fn activity_evidence(
+    logs: &SessionLogs,
+    workspace_path: &Path,
+    evidence: &mut Vec<String>,
+) -> &'static str {
+    let dirs = logs.project_dirs(workspace_path);
+    if dirs.is_empty() {
+        evidence.push("no session log roots (no home directory or CLAUDE_CONFIG_DIR)".to_string());
+        return "idle";
+    }
+    let (existing, missing): (Vec<_>, Vec<_>) = dirs.iter().partition(|dir| dir.is_dir());
+    for dir in &missing {
+        evidence.push(format!(
+            "session dir {} does not exist (no session has run here)",
+            dir.display()
+        ));
+    }
+    if existing.is_empty() {
+        return "idle";
+    }
+    for dir in &existing {
+        evidence.push(format!("session dir: {}", dir.display()));
+    }
 
-    let mut buf = vec![0u8; read_size as usize];
-    file.read_exact(&mut buf).ok()?;
+    let Some((path, modified)) = logs.latest_log(workspace_path) else {
+        evidence.push("no *.jsonl session logs in it".to_string());
+        return "idle";
+    };
+    let age_secs = modified.elapsed().unwrap_or_default().as_secs();
+    evidence.push(format!(
+        "latest log: {} (modified {} ago)",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        format_secs(age_secs)
+    ));
+    if age_secs > STALE_SESSION_SECS {
+        evidence.push(format!(
+            "older than {}, so treated as stale regardless of its last entry",
+            format_secs(STALE_SESSION_SECS)
+        ));
+        return "idle";
+    }
 
-    // Find the last non-empty line
-    let text = String::from_utf8_lossy(&buf);
-    text.lines()
-        .rev()
-        .find(|line| !line.trim().is_empty())
-        .map(|s| s.to_string())
+    let Some(line) = read_last_line(&path) else {
+        evidence.push("log is empty".to_string());
+        return "idle";
+    };
+    evidence.push(format!("last entry: {}", describe_entry(&line)));
+    if SessionEntry::parse(&line).is_some_and(|entry| entry.is_mid_turn()) {
+        evidence.push("mid-turn: a tool is running or the agent owes a response".to_string());
+        "busy"
+    } else {
+        evidence.push("turn complete: the agent is waiting for input".to_string());
+        "idle"
+    }
 }
 
-/// Determine if a JSONL entry indicates a mid-turn state.
-///
-/// Mid-turn entry types (Claude is busy):
-/// - `assistant` role with `tool_use` content → tool is executing
-/// - `user` role with `tool_result` content → Claude processing tool output
-/// - `user` role with type `user_message` → Claude processing user input
-/// - role containing `thinking` → extended thinking
-/// - type is `progress` → streaming in progress
-///
-/// Idle entry types (turn is complete):
-/// - `assistant` role with `text` content → response delivered
-/// - type is `system` → session ended or system event
-/// - type is `summary` → conversation summary
-fn is_mid_turn_entry(line: &str) -> bool {
-    // Parse the JSON line — session log entries have a `type` field and
-    // sometimes a `message` field with `role` and `content`.
-    let value: serde_json::Value = match serde_json::from_str(line) {
-        Ok(v) => v,
-        Err(_) => return false,
+/// Summarize a session log entry: its type, content block types and stop reason.
+fn describe_entry(line: &str) -> String {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+        return "not valid JSON".to_string();
+    };
+    let mut parts = vec![format!(
+        "type={}",
+        value.get("type").and_then(|t| t.as_str()).unwrap_or("?")
+    )];
+    if let Some(message) = value.get("message") {
+        if let Some(content) = message.get("content").and_then(|c| c.as_array()) {
+            let kinds: Vec<&str> = content
+                .iter()
+                .filter_map(|block| block.get("type").and_then(|t| t.as_str()))
+                .collect();
+            parts.push(format!("content=[{}]", kinds.join(",")));
+        }
+        if let Some(reason) = message.get("stop_reason").and_then(|s| s.as_str()) {
+            parts.push(format!("stop_reason={}", reason));
+        }
+    }
+    parts.join(" ")
+}
+
+/// Explain [`workspace_has_changes`]: the VCS commands run and their output.
+pub fn explain_has_changes(
+    workspace_path: &Path,
+    base_ref: Option<&str>,
+    scope: Option<&Path>,
+) -> SignalExplanation {
+    use crate::workspace::{
+        detect_repo_type, run_change_command, GitWorktreeBackend, JjBackend, RepoType, VcsBackend,
+    };
+
+    let mut evidence = Vec::new();
+    if !workspace_path.exists() {
+        evidence.push(format!(
+            "workspace {} does not exist",
+            workspace_path.display()
+        ));
+        return SignalExplanation {
+            signal: "changes",
+            value: "no".to_string(),
+            evidence,
+        };
+    }
+    let backend: &dyn VcsBackend = match detect_repo_type(workspace_path) {
+        Some(RepoType::Jj) => &JjBackend,
+        Some(RepoType::Git) => &GitWorktreeBackend,
+        None => {
+            evidence.push("neither a jj nor a git workspace".to_string());
+            return SignalExplanation {
+                signal: "changes",
+                value: "no".to_string(),
+                evidence,
+            };
+        }
     };
 
-    let entry_type = value.get("type").and_then(|t| t.as_str()).unwrap_or("");
-
-    match entry_type {
-        // assistant message — check content blocks for tool_use vs text
-        "assistant" => {
-            if let Some(message) = value.get("message") {
-                if let Some(content) = message.get("content").and_then(|c| c.as_array()) {
-                    // If any content block is tool_use, it's mid-turn
-                    return content.iter().any(|block| {
-                        block.get("type").and_then(|t| t.as_str()) == Some("tool_use")
-                    });
+    let mut has_changes = false;
+    for argv in backend.change_commands(base_ref, scope) {
+        evidence.push(format!("$ {}", shell_words(&argv)));
+        match run_change_command(&argv, workspace_path) {
+            None => evidence.push("  (failed; counts as no changes)".to_string()),
+            Some(out) if out.trim().is_empty() => evidence.push("  (no output)".to_string()),
+            Some(out) => {
+                has_changes = true;
+                let lines: Vec<&str> = out.lines().collect();
+                for line in lines.iter().take(5) {
+                    evidence.push(format!("  {}", line));
                 }
-                // If there's a stop_reason of "tool_use", it's mid-turn
-                if message.get("stop_reason").and_then(|s| s.as_str()) == Some("tool_use") {
-                    return true;
+                if lines.len() > 5 {
+                    evidence.push(format!("  … {} more lines", lines.len() - 5));
                 }
             }
-            // assistant message with text content or no content → idle
-            false
         }
-        // user message with tool_result → Claude processing tool output
-        "user" => {
-            if let Some(message) = value.get("message") {
-                if let Some(content) = message.get("content").and_then(|c| c.as_array()) {
-                    return content.iter().any(|block| {
-                        block.get("type").and_then(|t| t.as_str()) == Some("tool_result")
-                    });
-                }
-                // user text message → Claude about to process
-                return true;
-            }
-            false
+    }
+    let conflicts = if has_changes { backend.conflicted_paths(workspace_path) } else { Vec::new() };
+    for path in &conflicts {
+        evidence.push(format!("unresolved conflict: {}", path));
+    }
+    let value = if !conflicts.is_empty() {
+        "conflict"
+    } else if has_changes {
+        "yes"
+    } else {
+        "no"
+    };
+    SignalExplanation {
+        signal: "changes",
+        value: value.to_string(),
+        evidence,
+    }
+}
+
+/// Explain the task status and assignee: which resolver answered and the
+/// fields it returned.
+pub fn explain_task_status(
+    plugin_mgr: &crate::plugins::PluginManager,
+    task_id: Option<&str>,
+    task_source: Option<&str>,
+    config_sources: &[String],
+    ctx: crate::plugins::PluginContext,
+) -> SignalExplanation {
+    let mut evidence = Vec::new();
+    let unknown = |evidence| SignalExplanation {
+        signal: "task",
+        value: "unknown".to_string(),
+        evidence,
+    };
+    let Some(task_id) = task_id else {
+        evidence.push("no task linked to the assignment".to_string());
+        return unknown(evidence);
+    };
+
+    let result = match task_source {
+        Some(source) => {
+            evidence.push(format!("resolver: {} (recorded on the assignment)", source));
+            plugin_mgr.resolve_info(source, task_id, ctx)
+        }
+        None => {
+            let sources = plugin_mgr.effective_sources(config_sources);
+            evidence.push(format!(
+                "no source recorded (breq list shows no assignee); trying resolvers in order: {}",
+                sources.join(", ")
+            ));
+            plugin_mgr.resolve_info_multi(&sources, task_id, ctx)
         }
-        // Progress and thinking entries → mid-turn
-        "progress" => true,
-        t if t.contains("thinking") => true,
-        // System, summary, result → idle
-        _ => false,
+    };
+    let task = match result {
+        Ok(task) => task,
+        Err(e) => {
+            evidence.push(format!("info({}) failed: {:#}", task_id, e));
+            return unknown(evidence);
+        }
+    };
+
+    evidence.push(format!("{}::info({}) returned:", task.source, task_id));
+    let fields = [
+        ("status", task.status.as_deref()),
+        ("assignee", task.assignee.as_deref()),
+        ("kind", task.kind.as_deref()),
+        ("updated_at", task.updated_at.as_deref()),
+    ];
+    for (name, value) in fields {
+        evidence.push(format!("  {}: {}", name, value.unwrap_or("(unset)")));
+    }
+    let value = match (task.status, task.assignee.filter(|a| !a.is_empty())) {
+        (Some(status), Some(assignee)) => format!("{} ({})", status, assignee),
+        (Some(status), None) => status,
+        (None, _) => "unknown".to_string(),
+    };
+    SignalExplanation {
+        signal: "task",
+        value,
+        evidence,
+    }
+}
+
+/// Render a command for display, quoting arguments that need it.
+fn shell_words(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| {
+            if !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./:@=".contains(c))
+            {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn format_secs(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_entry() {
+        let line = r#"{"type":"assistant","message":{"content":[{"type":"text"},{"type":"tool_use"}],"stop_reason":"tool_use"}}"#;
+        assert_eq!(
+            describe_entry(line),
+            "type=assistant content=[text,tool_use] stop_reason=tool_use"
+        );
+        assert_eq!(describe_entry("{"), "not valid JSON");
+    }
+
+    #[test]
+    fn test_shell_words_quotes_revsets() {
+        let argv = ["jj", "log", "-r", "::@ ~ ::default@"].map(String::from);
+        assert_eq!(shell_words(&argv), "jj log -r '::@ ~ ::default@'");
     }
 }
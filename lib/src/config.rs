@@ -5,7 +5,10 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tracing::warn;
 
-use crate::agent::Agent;
+use crate::agent::{Agent, AgentCommandConfig, AgentKind};
+use crate::error::{ErrorCode, TorenError};
+use crate::editor::EditorConfig;
+use crate::reaper::{IdleReaperConfig, ReapPolicy};
 
 /// Return the toren root directory (~/.toren).
 pub fn toren_root() -> PathBuf {
@@ -30,6 +33,9 @@ pub struct Config {
     #[serde(default)]
     pub ancillaries: AncillariesConfig,
 
+    #[serde(default, skip_serializing_if = "SegmentsConfig::is_empty")]
+    pub segments: SegmentsConfig,
+
     #[serde(default)]
     pub proxy: ProxyConfig,
 
@@ -39,6 +45,21 @@ pub struct Config {
     #[serde(default)]
     pub tasks: TasksConfig,
 
+    #[serde(default)]
+    pub ui: UiConfig,
+
+    #[serde(default)]
+    pub status: StatusConfig,
+
+    #[serde(default)]
+    pub filesystem: FilesystemConfig,
+
+    #[serde(default)]
+    pub push: PushConfig,
+
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
     #[serde(default = "crate::alias::default_aliases")]
     pub aliases: HashMap<String, String>,
 }
@@ -47,13 +68,46 @@ fn default_server() -> ServerConfig {
     ServerConfig {
         host: "127.0.0.1".to_string(),
         port: 8787,
+        trusted_addrs: Vec::new(),
+        agent_token_ttl_secs: default_agent_token_ttl_secs(),
+        shutdown_timeout_secs: default_shutdown_timeout_secs(),
+        forget_agent_routes_on_shutdown: false,
+        read_only: false,
     }
 }
 
+fn default_agent_token_ttl_secs() -> u64 {
+    4 * 3600
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Client IPs allowed to call `/api/*` without a session token
+    /// (e.g. `["127.0.0.1", "::1"]` for a localhost-only daemon)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_addrs: Vec<String>,
+    /// Lifetime of the assignment-scoped token handed to each agent the
+    /// daemon runs, in seconds (default: 14400, 0 issues none)
+    #[serde(default = "default_agent_token_ttl_secs")]
+    pub agent_token_ttl_secs: u64,
+    /// How long shutdown waits for interrupted work to stop, in seconds
+    /// (default: 30)
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// Remove the proxy routes agents registered (`POST /api/agent/routes`)
+    /// when the daemon shuts down, since their servers stop with the agent
+    #[serde(default)]
+    pub forget_agent_routes_on_shutdown: bool,
+    /// Reject every mutating `/api/*` call with 403, leaving the daemon
+    /// observable only (agent tokens on `/api/agent/*` are unaffected)
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 /// Configuration for ancillary workspaces and segment discovery.
@@ -75,6 +129,103 @@ pub struct AncillariesConfig {
     /// Coding agent to use (e.g., "claude", "codex:o3"). Auto-detects if unset.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub agent: Option<String>,
+
+    /// Agent memory file (relative to the workspace) that receives a toren
+    /// context block, e.g. "CLAUDE.local.md". Disabled if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_file: Option<String>,
+
+    /// Keep a direnv `.envrc` with toren context in each workspace. A
+    /// toren.kdl `envrc` node overrides this per repo.
+    #[serde(default)]
+    pub envrc: bool,
+
+    /// Directories holding Claude Code session logs (`<config dir>/projects`).
+    /// Derived from CLAUDE_CONFIG_DIR (default ~/.claude) if unset.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub session_log_roots: Vec<PathBuf>,
+
+    /// How often the daemon checks the health of active assignments, in
+    /// seconds (default: 60, 0 disables the monitor)
+    #[serde(default = "default_health_interval_secs")]
+    pub health_interval_secs: u64,
+
+    /// Minutes without session log events before a running agent counts as
+    /// stalled (default: 15)
+    #[serde(default = "default_stall_minutes")]
+    pub stall_minutes: u64,
+
+    /// How often the daemon looks for history, logs and artifacts of
+    /// segments that no longer exist, in seconds (default: 86400, 0 disables)
+    #[serde(default = "default_gc_interval_secs")]
+    pub gc_interval_secs: u64,
+
+    /// How often the daemon looks for processes left running by agent
+    /// sessions that ended, in seconds (default: 300, 0 disables)
+    #[serde(default = "default_reap_interval_secs")]
+    pub reap_interval_secs: u64,
+
+    /// Minutes a session log must be quiet, with the agent gone, before the
+    /// session counts as ended (default: 30)
+    #[serde(default = "default_reap_idle_minutes")]
+    pub reap_idle_minutes: u64,
+
+    /// What to do with processes an ended session left running:
+    /// off, notify or terminate (default: notify)
+    #[serde(default)]
+    pub reap_policy: ReapPolicy,
+
+    /// Flag or abort assignments left idle (`[ancillaries.idle_reaper]`)
+    #[serde(default)]
+    pub idle_reaper: IdleReaperConfig,
+
+    /// How `breq open` opens a workspace (`[ancillaries.editor]`)
+    #[serde(default)]
+    pub editor: EditorConfig,
+
+    /// Workspaces larger than this many MiB are flagged by `breq ws du`
+    /// (default: 2048, 0 disables)
+    #[serde(default = "default_disk_warn_mb")]
+    pub disk_warn_mb: u64,
+
+    /// Minutes after a complete, abort or clean during which `breq undo`
+    /// can restore it (default: 60, 0 disables undo)
+    #[serde(default = "default_undo_retention_minutes")]
+    pub undo_retention_minutes: u64,
+
+    /// Tool permissions for agents the daemon runs, unless a start or resume
+    /// request sets its own (default: bypass)
+    #[serde(default)]
+    pub permission_mode: PermissionPolicy,
+
+    /// Command overrides for Claude (`[ancillaries.claude]`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude: Option<AgentCommandConfig>,
+
+    /// Command overrides for Codex (`[ancillaries.codex]`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codex: Option<AgentCommandConfig>,
+
+    /// Command overrides for Gemini (`[ancillaries.gemini]`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gemini: Option<AgentCommandConfig>,
+
+    /// Command overrides for OpenCode (`[ancillaries.opencode]`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opencode: Option<AgentCommandConfig>,
+}
+
+impl AncillariesConfig {
+    /// Configured command overrides for an agent (empty if none).
+    pub fn agent_command(&self, kind: AgentKind) -> AgentCommandConfig {
+        let command = match kind {
+            AgentKind::Claude => &self.claude,
+            AgentKind::Codex => &self.codex,
+            AgentKind::Gemini => &self.gemini,
+            AgentKind::Opencode => &self.opencode,
+        };
+        command.clone().unwrap_or_default()
+    }
 }
 
 fn default_workspace_root() -> PathBuf {
@@ -87,6 +238,34 @@ fn default_max_per_segment() -> u32 {
     10
 }
 
+fn default_health_interval_secs() -> u64 {
+    60
+}
+
+fn default_stall_minutes() -> u64 {
+    15
+}
+
+fn default_gc_interval_secs() -> u64 {
+    86400
+}
+
+fn default_reap_interval_secs() -> u64 {
+    300
+}
+
+fn default_reap_idle_minutes() -> u64 {
+    30
+}
+
+fn default_disk_warn_mb() -> u64 {
+    2048
+}
+
+fn default_undo_retention_minutes() -> u64 {
+    60
+}
+
 impl Default for AncillariesConfig {
     fn default() -> Self {
         Self {
@@ -94,36 +273,277 @@ impl Default for AncillariesConfig {
             workspace_root: default_workspace_root(),
             max_per_segment: default_max_per_segment(),
             agent: None,
+            context_file: None,
+            envrc: false,
+            session_log_roots: Vec::new(),
+            health_interval_secs: default_health_interval_secs(),
+            stall_minutes: default_stall_minutes(),
+            gc_interval_secs: default_gc_interval_secs(),
+            reap_interval_secs: default_reap_interval_secs(),
+            reap_idle_minutes: default_reap_idle_minutes(),
+            reap_policy: ReapPolicy::default(),
+            idle_reaper: IdleReaperConfig::default(),
+            editor: EditorConfig::default(),
+            disk_warn_mb: default_disk_warn_mb(),
+            undo_retention_minutes: default_undo_retention_minutes(),
+            permission_mode: PermissionPolicy::default(),
+            claude: None,
+            codex: None,
+            gemini: None,
+            opencode: None,
         }
     }
 }
 
+/// How an agent the daemon runs gets permission to use tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionPolicy {
+    /// Every tool runs without asking
+    #[default]
+    Bypass,
+    /// File edits run without asking; other tools follow the agent's own
+    /// permission settings
+    AcceptEdits,
+    /// Tools not allowed by the agent's settings wait for a client to
+    /// approve or deny them over the ancillary WebSocket
+    Ask,
+    /// Read-only planning, no edits or commands
+    Plan,
+}
+
+impl PermissionPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionPolicy::Bypass => "bypass",
+            PermissionPolicy::AcceptEdits => "accept_edits",
+            PermissionPolicy::Ask => "ask",
+            PermissionPolicy::Plan => "plan",
+        }
+    }
+}
+
+impl std::fmt::Display for PermissionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Proxy configuration for station routes.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyConfig {
     /// Base domain for station routes (default: lvh.me)
     #[serde(default = "default_proxy_domain")]
     pub domain: String,
+    /// Reverse proxy station drives: "caddy", "traefik" or "nginx" (default: caddy)
+    #[serde(default = "default_proxy_backend")]
+    pub backend: String,
+    /// How often the daemon removes routes whose workspace no longer exists,
+    /// in seconds (default: 600, 0 disables the periodic pass)
+    #[serde(default = "default_prune_interval_secs")]
+    pub prune_interval_secs: u64,
+    /// Host of a workspace's unnamed route, rendered with `ws.name`, `repo`,
+    /// `segment`, `parent` and `domain`; must begin with `{{ ws.name }}.`
+    /// (default: "{{ ws.name }}.{{ repo }}.{{ domain }}")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_template: Option<String>,
 }
 
 fn default_proxy_domain() -> String {
     "lvh.me".to_string()
 }
 
+fn default_proxy_backend() -> String {
+    "caddy".to_string()
+}
+
+fn default_prune_interval_secs() -> u64 {
+    600
+}
+
 impl Default for ProxyConfig {
     fn default() -> Self {
         Self {
             domain: default_proxy_domain(),
+            backend: default_proxy_backend(),
+            prune_interval_secs: default_prune_interval_secs(),
+            host_template: None,
         }
     }
 }
 
+/// Segment discovery under glob roots, and per-segment settings
+/// (`[segments.overrides.<name>]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentsConfig {
+    /// Directories under a root that are never segments: globs matched
+    /// against the directory name or its path relative to the root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+
+    /// When set, only directories containing one of these entries (e.g.
+    /// ".jj", "toren.kdl") are segments; others are searched beneath
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<String>,
+
+    /// How many levels below a root to search for marked directories
+    /// (1 = immediate children)
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u32,
+
+    /// Overrides keyed by segment name
+    #[serde(default)]
+    pub overrides: HashMap<String, SegmentOverride>,
+
+    /// Segment groups keyed by name: segments whose repos a task can span.
+    /// `breq do --group` creates a workspace in each; the first is primary.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub groups: HashMap<String, Vec<String>>,
+
+    /// Ancillary name vocabularies keyed by segment: slot 1 takes the first
+    /// name, and slots past the end fall back to number words (see
+    /// [`crate::naming`])
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub names: HashMap<String, Vec<String>>,
+}
+
+fn default_max_depth() -> u32 {
+    1
+}
+
+impl Default for SegmentsConfig {
+    fn default() -> Self {
+        Self {
+            ignore: Vec::new(),
+            markers: Vec::new(),
+            max_depth: default_max_depth(),
+            overrides: HashMap::new(),
+            groups: HashMap::new(),
+            names: HashMap::new(),
+        }
+    }
+}
+
+impl SegmentsConfig {
+    fn is_empty(&self) -> bool {
+        self.ignore.is_empty()
+            && self.markers.is_empty()
+            && self.max_depth == default_max_depth()
+            && self.overrides.is_empty()
+            && self.groups.is_empty()
+    }
+}
+
+/// Settings that replace the `[ancillaries]` defaults for one segment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SegmentOverride {
+    /// Max ancillaries for this segment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_segment: Option<u32>,
+
+    /// Where this segment's workspaces are created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_root: Option<PathBuf>,
+
+    /// Intent used by `breq do` when none is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<String>,
+}
+
 /// Intent templates keyed by name (e.g., "act", "plan", "review").
 /// Additional custom intents can be added via config.
+///
+/// An intent is a template string, or a table with a `template` and an
+/// `auto` section (`[intents.review.auto]`); a table without a template
+/// keeps the built-in one.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "HashMap<String, IntentEntry>", into = "HashMap<String, IntentEntry>")]
 pub struct IntentsConfig {
-    #[serde(flatten)]
     pub entries: HashMap<String, String>,
+    /// Auto-finalization settings, by intent name
+    pub auto: HashMap<String, IntentAutoConfig>,
+}
+
+/// Auto-finalization of daemon-run assignments with an intent
+/// (`[intents.<name>.auto]`): when the agent's work completes, or after
+/// `timeout_minutes`, the final report is posted to the task and the
+/// assignment is aborted, so runs are fire-and-forget.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntentAutoConfig {
+    /// Finalize when the agent's work session completes (default: true)
+    #[serde(default = "default_true")]
+    pub on_complete: bool,
+    /// Finalize this many minutes after the agent started working, even if
+    /// it hasn't finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_minutes: Option<u64>,
+    /// Close the task instead of returning it to open (default: false)
+    #[serde(default)]
+    pub close_task: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum IntentEntry {
+    Template(String),
+    Table {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        template: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auto: Option<IntentAutoConfig>,
+    },
+}
+
+impl TryFrom<HashMap<String, IntentEntry>> for IntentsConfig {
+    type Error = String;
+
+    fn try_from(map: HashMap<String, IntentEntry>) -> Result<Self, String> {
+        let defaults = IntentsConfig::default();
+        let mut intents = IntentsConfig {
+            entries: HashMap::new(),
+            auto: HashMap::new(),
+        };
+        for (name, entry) in map {
+            match entry {
+                IntentEntry::Template(template) => {
+                    intents.entries.insert(name, template);
+                }
+                IntentEntry::Table { template, auto } => {
+                    let template = template
+                        .or_else(|| defaults.get(&name).map(String::from))
+                        .ok_or_else(|| format!("intents.{}: missing template", name))?;
+                    intents.entries.insert(name.clone(), template);
+                    if let Some(auto) = auto {
+                        intents.auto.insert(name, auto);
+                    }
+                }
+            }
+        }
+        Ok(intents)
+    }
+}
+
+impl From<IntentsConfig> for HashMap<String, IntentEntry> {
+    fn from(mut intents: IntentsConfig) -> Self {
+        intents
+            .entries
+            .into_iter()
+            .map(|(name, template)| {
+                let entry = match intents.auto.remove(&name) {
+                    Some(auto) => IntentEntry::Table {
+                        template: Some(template),
+                        auto: Some(auto),
+                    },
+                    None => IntentEntry::Template(template),
+                };
+                (name, entry)
+            })
+            .collect()
+    }
 }
 
 impl IntentsConfig {
@@ -131,6 +551,18 @@ impl IntentsConfig {
     pub fn get(&self, name: &str) -> Option<&str> {
         self.entries.get(name).map(|s| s.as_str())
     }
+
+    /// Auto-finalization settings for an intent, if it has any.
+    pub fn auto_for(&self, intent: Option<&str>) -> Option<&IntentAutoConfig> {
+        intent.and_then(|i| self.auto.get(i))
+    }
+
+    /// Intent names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.entries.keys().map(|s| s.as_str()).collect();
+        names.sort();
+        names
+    }
 }
 
 impl Default for IntentsConfig {
@@ -140,7 +572,10 @@ impl Default for IntentsConfig {
         entries.insert("design".to_string(), default_intent_design());
         entries.insert("implement".to_string(), default_intent_implement());
         entries.insert("review".to_string(), default_intent_review());
-        Self { entries }
+        Self {
+            entries,
+            auto: HashMap::new(),
+        }
     }
 }
 
@@ -155,6 +590,28 @@ pub struct TasksConfig {
         alias = "default_source"
     )]
     pub sources: Vec<String>,
+    /// Assignee template for task claims, rendered per ancillary with
+    /// `ws.name`, `ws.num` and `repo.name` (e.g. "toren-{{ ws.name }}").
+    /// Unset claims as "claude".
+    #[serde(default)]
+    pub claim_identity: Option<String>,
+    /// Description template for undescribed commits when a workspace is
+    /// completed or destroyed, rendered with `task.id` and `task.title`.
+    /// Unset uses "{{ task.id }}: {{ task.title }}".
+    #[serde(default)]
+    pub commit_message: Option<String>,
+    /// Intents whose deliverable is a write-up rather than code. Completing
+    /// such an assignment posts its final report as a task comment.
+    #[serde(default = "default_report_intents")]
+    pub report_intents: Vec<String>,
+    /// Lifecycle events (`assign`, `resume`, `complete`, `abort`) that post
+    /// a comment on the assignment's task (see [`crate::task_comments`]).
+    #[serde(default)]
+    pub comment_events: Vec<crate::HookEvent>,
+}
+
+fn default_report_intents() -> Vec<String> {
+    ["design", "plan", "review"].map(String::from).to_vec()
 }
 
 fn default_task_sources() -> Vec<String> {
@@ -181,16 +638,230 @@ impl TasksConfig {
     pub fn default_source(&self) -> Option<&str> {
         self.sources.first().map(|s| s.as_str())
     }
+
+    /// Whether an assignment with this intent produces a report to post.
+    pub fn is_report_intent(&self, intent: Option<&str>) -> bool {
+        intent.is_some_and(|i| self.report_intents.iter().any(|r| r == i))
+    }
 }
 
 impl Default for TasksConfig {
     fn default() -> Self {
         Self {
             sources: default_task_sources(),
+            claim_identity: None,
+            commit_message: None,
+            report_intents: default_report_intents(),
+            comment_events: Vec::new(),
+        }
+    }
+}
+
+/// Display preferences for breq output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    /// Columns shown by `breq list`, in order (default: workspace, bead, activity, title)
+    #[serde(default = "default_list_columns")]
+    pub list_columns: Vec<ListColumn>,
+}
+
+fn default_list_columns() -> Vec<ListColumn> {
+    vec![
+        ListColumn::Workspace,
+        ListColumn::Bead,
+        ListColumn::Activity,
+        ListColumn::Title,
+    ]
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            list_columns: default_list_columns(),
+        }
+    }
+}
+
+/// How the daemon computes composite status (`[status]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusConfig {
+    /// Seconds an assignment's composite status is reused before its jj and
+    /// task tracker checks run again (default: 5, 0 recomputes every request)
+    #[serde(default = "default_status_min_interval_secs")]
+    pub min_interval_secs: u64,
+}
+
+fn default_status_min_interval_secs() -> u64 {
+    5
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            min_interval_secs: default_status_min_interval_secs(),
         }
     }
 }
 
+/// Limits on the daemon's filesystem API (`[filesystem]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilesystemConfig {
+    /// Largest file the API reads or writes, in bytes (default: 10 MiB)
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Filesystem requests allowed per minute across clients
+    /// (default: 600, 0 disables the limit)
+    #[serde(default = "default_max_requests_per_minute")]
+    pub max_requests_per_minute: u32,
+}
+
+fn default_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_requests_per_minute() -> u32 {
+    600
+}
+
+impl Default for FilesystemConfig {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: default_max_file_bytes(),
+            max_requests_per_minute: default_max_requests_per_minute(),
+        }
+    }
+}
+
+/// Checks run on a workspace's changes before they are pushed (`[push]`,
+/// see [`crate::push_checks`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushConfig {
+    /// Shell command that scans the diff (given on stdin) for secrets and
+    /// exits non-zero on a finding, e.g. "gitleaks stdin --redact"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_scan: Option<String>,
+    /// Globs of paths that must not be pushed
+    #[serde(default = "default_blocked_paths")]
+    pub blocked_paths: Vec<String>,
+    /// Most added plus removed lines a push may carry (default: 0, no limit)
+    #[serde(default)]
+    pub max_diff_lines: u64,
+}
+
+fn default_blocked_paths() -> Vec<String> {
+    ["**/.env", "*.pem", "*.key", "**/id_rsa", "**/id_ed25519"]
+        .map(String::from)
+        .to_vec()
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self {
+            secret_scan: None,
+            blocked_paths: default_blocked_paths(),
+            max_diff_lines: 0,
+        }
+    }
+}
+
+/// Where and when the daemon sends notifications about assignment events
+/// (`[notifications]`, see [`crate::notifications`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    /// Events to notify (default: all)
+    #[serde(default = "crate::notifications::NotifyEvent::all")]
+    pub events: Vec<crate::notifications::NotifyEvent>,
+    /// Tries per target before a notification is dropped (default: 3)
+    #[serde(default = "default_notify_attempts")]
+    pub attempts: u32,
+    /// Webhook, ntfy and Slack targets (`[[notifications.targets]]`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<crate::notifications::NotifyTarget>,
+}
+
+fn default_notify_attempts() -> u32 {
+    3
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            events: crate::notifications::NotifyEvent::all(),
+            attempts: default_notify_attempts(),
+            targets: Vec::new(),
+        }
+    }
+}
+
+/// A column in `breq list` output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListColumn {
+    /// Workspace name, marked with `*` when it has changes
+    Workspace,
+    /// Task identifier
+    Bead,
+    /// Agent activity (busy/idle)
+    Activity,
+    /// Task assignee, as reported by the task source
+    Assignee,
+    /// Task title
+    Title,
+    /// Whether the workspace has changes
+    Changes,
+    /// Time since the assignment was created
+    Age,
+    /// Assignment labels
+    Labels,
+}
+
+impl ListColumn {
+    pub const ALL: [ListColumn; 8] = [
+        ListColumn::Workspace,
+        ListColumn::Bead,
+        ListColumn::Activity,
+        ListColumn::Assignee,
+        ListColumn::Title,
+        ListColumn::Changes,
+        ListColumn::Age,
+        ListColumn::Labels,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListColumn::Workspace => "workspace",
+            ListColumn::Bead => "bead",
+            ListColumn::Activity => "activity",
+            ListColumn::Assignee => "assignee",
+            ListColumn::Title => "title",
+            ListColumn::Changes => "changes",
+            ListColumn::Age => "age",
+            ListColumn::Labels => "labels",
+        }
+    }
+}
+
+impl std::str::FromStr for ListColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|c| c.as_str() == name)
+            .ok_or_else(|| {
+                let valid: Vec<_> = Self::ALL.iter().map(|c| c.as_str()).collect();
+                format!("unknown column '{}' (expected one of: {})", s, valid.join(", "))
+            })
+    }
+}
+
+impl std::fmt::Display for ListColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 fn default_intent_debug() -> String {
     "Focus on root cause analysis, not fixing. Reproduce the issue — a failing test is ideal. \
      Trace from symptom to cause, identify contributing factors, then suggest fix options with \
@@ -254,10 +925,22 @@ impl Config {
         if config_path.exists() {
             let content =
                 std::fs::read_to_string(&config_path).context("Failed to read config file")?;
-            let mut config: Config =
-                toml::from_str(&content).context("Failed to parse config file")?;
+            let mut config: Config = toml::from_str(&content).with_context(|| {
+                TorenError::new(
+                    ErrorCode::ConfigInvalid,
+                    format!("Failed to parse config file {}", config_path.display()),
+                )
+                .hint("Fix the TOML at the location shown below (run with -v for details).")
+            })?;
             config.config_path = config_path.display().to_string();
             config.expand_paths();
+            // Ancillary IDs are named from these wherever they're built
+            crate::naming::set_pools(&config.segments.names).with_context(|| {
+                TorenError::new(
+                    ErrorCode::ConfigInvalid,
+                    format!("Invalid [segments.names] in {}", config_path.display()),
+                )
+            })?;
             Ok(config)
         } else if config_path == Self::find_config_file().unwrap_or_default() {
             // Only create default config for auto-discovered paths
@@ -273,6 +956,14 @@ impl Config {
     fn expand_paths(&mut self) {
         // Expand workspace root
         self.ancillaries.workspace_root = expand_path(&self.ancillaries.workspace_root);
+        for root in &mut self.ancillaries.session_log_roots {
+            *root = expand_path(root);
+        }
+        for seg in self.segments.overrides.values_mut() {
+            if let Some(root) = &seg.workspace_root {
+                seg.workspace_root = Some(expand_path(root));
+            }
+        }
         // Cache segment paths (avoids re-expanding globs on each call)
         self.segment_paths = self.compute_segment_paths();
     }
@@ -373,9 +1064,15 @@ impl Default for Config {
             segment_paths: (Vec::new(), Vec::new()),
             server: default_server(),
             ancillaries: AncillariesConfig::default(),
+            segments: SegmentsConfig::default(),
             proxy: ProxyConfig::default(),
             intents: IntentsConfig::default(),
             tasks: TasksConfig::default(),
+            ui: UiConfig::default(),
+            status: StatusConfig::default(),
+            filesystem: FilesystemConfig::default(),
+            push: PushConfig::default(),
+            notifications: NotificationsConfig::default(),
             aliases: crate::alias::default_aliases(),
         }
     }
@@ -397,13 +1094,43 @@ impl Config {
     ///
     /// Priority: CLI override > config file > auto-detect from PATH.
     pub fn resolve_agent(&self, cli_override: Option<&str>) -> Result<Agent> {
-        if let Some(s) = cli_override {
-            return Agent::parse(s);
-        }
-        if let Some(ref s) = self.ancillaries.agent {
-            return Agent::parse(s);
+        let agent = match cli_override.or(self.ancillaries.agent.as_deref()) {
+            Some(s) => Agent::parse(s)?,
+            None => return Agent::detect_with(|kind| self.ancillaries.agent_command(kind)),
+        };
+        let command = self.ancillaries.agent_command(agent.kind);
+        Ok(agent.with_command(command))
+    }
+
+    /// Workspace roots overridden per segment, keyed by segment name.
+    pub fn segment_workspace_roots(&self) -> HashMap<String, PathBuf> {
+        self.segments
+            .overrides
+            .iter()
+            .filter_map(|(name, seg)| Some((name.clone(), seg.workspace_root.clone()?)))
+            .collect()
+    }
+
+    /// Member segments of a `[segments.groups]` group, primary first.
+    pub fn segment_group(&self, name: &str) -> Result<&[String]> {
+        let members = self.segments.groups.get(name).with_context(|| {
+            let mut known: Vec<&str> = self.segments.groups.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            format!("Unknown segment group '{}' (configured: {})", name, known.join(", "))
+        })?;
+        if members.len() < 2 {
+            anyhow::bail!("Segment group '{}' needs at least two segments", name);
         }
-        Agent::detect()
+        Ok(members)
+    }
+
+    /// Intents for a repo: the global `[intents]`, overridden and extended by
+    /// the `intents` block of the repo's toren.kdl.
+    pub fn intents_for(&self, repo_root: &Path) -> Result<IntentsConfig> {
+        let mut intents = self.intents.clone();
+        let repo = crate::workspace_setup::BreqConfig::parse(repo_root)?;
+        intents.entries.extend(repo.intents);
+        Ok(intents)
     }
 }
 
@@ -508,6 +1235,72 @@ domain = "test.local"
         assert_eq!(config.proxy.domain, "test.local");
     }
 
+    #[test]
+    fn parse_agent_command_overrides() {
+        let toml_str = r#"
+[ancillaries]
+agent = "claude"
+
+[ancillaries.claude]
+binary = "claude-wrapper"
+args = ["--verbose"]
+model = "opus"
+env = { CLAUDE_CONFIG_DIR = "~/.claude-work" }
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let agent = config.resolve_agent(None).unwrap();
+        assert_eq!(agent.binary(), "claude-wrapper");
+        assert_eq!(agent.model.as_deref(), Some("opus"));
+        assert_eq!(agent.command.args, vec!["--verbose"]);
+        assert_eq!(agent.command.env["CLAUDE_CONFIG_DIR"], "~/.claude-work");
+
+        // Overrides are per agent; an explicit model still wins
+        let codex = config.resolve_agent(Some("codex")).unwrap();
+        assert_eq!(codex.binary(), "codex");
+        let claude = config.resolve_agent(Some("claude:sonnet")).unwrap();
+        assert_eq!(claude.model.as_deref(), Some("sonnet"));
+    }
+
+    #[test]
+    fn parse_intent_auto() {
+        let toml_str = r#"
+[intents]
+act = "Do {{ task.id }}"
+
+[intents.review.auto]
+timeout_minutes = 45
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.intents.get("act"), Some("Do {{ task.id }}"));
+        // A table without a template keeps the built-in one
+        assert_eq!(config.intents.get("review"), Some(default_intent_review().as_str()));
+        let auto = config.intents.auto_for(Some("review")).unwrap();
+        assert!(auto.on_complete);
+        assert_eq!(auto.timeout_minutes, Some(45));
+        assert!(config.intents.auto_for(Some("act")).is_none());
+
+        // Round-trips through serialization
+        let round: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(round.intents.auto_for(Some("review")), Some(auto));
+
+        let err = toml::from_str::<Config>("[intents.custom.auto]\n").unwrap_err();
+        assert!(err.to_string().contains("intents.custom: missing template"));
+    }
+
+    #[test]
+    fn parse_segment_groups() {
+        let toml_str = r#"
+[segments.groups]
+shop = ["api", "web"]
+solo = ["api"]
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.segment_group("shop").unwrap(), ["api", "web"]);
+        assert!(config.segment_group("solo").is_err());
+        let err = config.segment_group("nope").unwrap_err();
+        assert!(err.to_string().contains("configured: shop, solo"));
+    }
+
     #[test]
     fn find_config_file_prefers_new_location() {
         // This test validates the logic by checking the function exists and returns a path.
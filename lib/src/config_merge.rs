@@ -0,0 +1,133 @@
+//! Structured merges for the `merge` setup action.
+//!
+//! `merge src="config.patch.yaml" dest="config.yaml"` deep-merges a rendered
+//! patch document into a config file in the workspace instead of replacing
+//! it, so a repo only declares the per-workspace delta of a large config.
+//! Merging follows JSON Merge Patch (RFC 7386): objects merge key by key,
+//! any other patch value replaces the target's, and `null` removes a key.
+//! JSON, YAML and TOML are supported; the rewritten file keeps the format but
+//! not comments, and JSON and TOML keys come out sorted.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+/// Document format of a merge source and destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl MergeFormat {
+    pub fn parse(format: &str) -> Result<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "json" => Ok(MergeFormat::Json),
+            "yaml" | "yml" => Ok(MergeFormat::Yaml),
+            "toml" => Ok(MergeFormat::Toml),
+            other => anyhow::bail!("unknown merge format '{}' (expected json, yaml or toml)", other),
+        }
+    }
+
+    /// The format implied by a file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| Self::parse(ext).ok())
+    }
+
+    fn load(self, text: &str) -> Result<Value> {
+        Ok(match self {
+            MergeFormat::Json => serde_json::from_str(text)?,
+            MergeFormat::Yaml => serde_yaml::from_str(text)?,
+            MergeFormat::Toml => serde_json::to_value(toml::from_str::<toml::Value>(text)?)?,
+        })
+    }
+
+    fn dump(self, value: &Value) -> Result<String> {
+        Ok(match self {
+            MergeFormat::Json => format!("{}\n", serde_json::to_string_pretty(value)?),
+            MergeFormat::Yaml => serde_yaml::to_string(value)?,
+            MergeFormat::Toml => toml::to_string_pretty(value)?,
+        })
+    }
+}
+
+/// Apply `patch` to `target` (RFC 7386).
+pub fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else {
+        unreachable!()
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Merge the patch document `patch` into the file at `dest`, which is
+/// created if missing. An empty file counts as an empty document.
+pub fn merge_file(dest: &Path, patch: &str, format: MergeFormat) -> Result<()> {
+    let patch = format
+        .load(patch)
+        .context("Failed to parse merge patch")?;
+    let mut target = match std::fs::read_to_string(dest) {
+        Ok(text) if text.trim().is_empty() => Value::Object(Default::default()),
+        Ok(text) => format
+            .load(&text)
+            .with_context(|| format!("Failed to parse {}", dest.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Value::Object(Default::default()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dest.display())),
+    };
+    merge_patch(&mut target, patch);
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(dest, format.dump(&target)?)
+        .with_context(|| format!("Failed to write: {}", dest.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_file_deep_merges_each_format() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let yaml = tmp.path().join("config.yaml");
+        std::fs::write(&yaml, "server:\n  host: localhost\n  port: 3000\ndebug: true\nplugins: [a, b]\n").unwrap();
+        merge_file(&yaml, "server:\n  port: 3001\ndebug: null\nplugins: [c]\n", MergeFormat::Yaml).unwrap();
+        let merged: Value = serde_yaml::from_str(&std::fs::read_to_string(&yaml).unwrap()).unwrap();
+        assert_eq!(
+            merged,
+            serde_json::json!({"server": {"host": "localhost", "port": 3001}, "plugins": ["c"]})
+        );
+
+        let toml_path = tmp.path().join("app.toml");
+        std::fs::write(&toml_path, "name = \"app\"\n[db]\nurl = \"x\"\npool = 5\n").unwrap();
+        merge_file(&toml_path, "[db]\nurl = \"y\"\n", MergeFormat::Toml).unwrap();
+        let merged: toml::Value = toml::from_str(&std::fs::read_to_string(&toml_path).unwrap()).unwrap();
+        assert_eq!(merged["db"]["url"].as_str(), Some("y"));
+        assert_eq!(merged["db"]["pool"].as_integer(), Some(5));
+        assert_eq!(merged["name"].as_str(), Some("app"));
+
+        let json = tmp.path().join("missing/settings.json");
+        merge_file(&json, r#"{"a": {"b": 1}}"#, MergeFormat::Json).unwrap();
+        assert_eq!(std::fs::read_to_string(&json).unwrap(), "{\n  \"a\": {\n    \"b\": 1\n  }\n}\n");
+
+        assert_eq!(MergeFormat::from_path(Path::new("x.yml")), Some(MergeFormat::Yaml));
+        assert!(MergeFormat::parse("ini").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Cached workspace context (`.toren/context.json`).
+//!
+//! Commands run inside a workspace (`breq shell --hook setup|destroy`) need
+//! its segment and name. Detecting them runs jj or git and walks up to the
+//! segment root, which is slow on network filesystems, so the context is
+//! written into the workspace when it is created and read back instead.
+//! Like `.toren-env`, the file is stripped before the workspace's changes
+//! are committed.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Cache file, relative to the workspace.
+pub const CONTEXT_FILE: &str = ".toren/context.json";
+
+/// Where a workspace lives and which segment it belongs to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedContext {
+    pub segment_path: PathBuf,
+    pub workspace_path: PathBuf,
+    pub workspace_name: String,
+}
+
+impl CachedContext {
+    /// Write the cache into the workspace.
+    pub fn write(&self) -> Result<()> {
+        let path = self.workspace_path.join(CONTEXT_FILE);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The cached context of the workspace containing `dir`. A cache that
+    /// names another directory (the workspace was moved or copied) or a
+    /// segment that no longer exists is ignored.
+    pub fn find(dir: &Path) -> Option<Self> {
+        let (root, content) = dir.ancestors().find_map(|ancestor| {
+            let content = std::fs::read_to_string(ancestor.join(CONTEXT_FILE)).ok()?;
+            Some((ancestor, content))
+        })?;
+        let cached: Self = serde_json::from_str(&content).ok()?;
+        (cached.workspace_path == root && cached.segment_path.is_dir()).then_some(cached)
+    }
+
+    /// Remove the workspace's cache, if any. Returns whether one existed.
+    pub fn remove(workspace_path: &Path) -> Result<bool> {
+        let path = workspace_path.join(CONTEXT_FILE);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_cache_found_from_subdirs_of_its_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let segment = dir.path().join("repo");
+        let ws = dir.path().join("workspaces/repo/one");
+        std::fs::create_dir_all(&segment).unwrap();
+        std::fs::create_dir_all(ws.join("src/deep")).unwrap();
+
+        let cached = CachedContext {
+            segment_path: segment.clone(),
+            workspace_path: ws.clone(),
+            workspace_name: "one".to_string(),
+        };
+        cached.write().unwrap();
+        assert_eq!(CachedContext::find(&ws), Some(cached.clone()));
+        assert_eq!(CachedContext::find(&ws.join("src/deep")), Some(cached));
+        assert_eq!(CachedContext::find(dir.path()), None);
+
+        // A copy of the workspace doesn't trust the original's cache
+        let copy = dir.path().join("copy");
+        std::fs::create_dir_all(copy.join(".toren")).unwrap();
+        std::fs::copy(ws.join(CONTEXT_FILE), copy.join(CONTEXT_FILE)).unwrap();
+        assert_eq!(CachedContext::find(&copy), None);
+
+        assert!(CachedContext::remove(&ws).unwrap());
+        assert!(!CachedContext::remove(&ws).unwrap());
+        assert_eq!(CachedContext::find(&ws), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! Per-workspace disk usage (`breq ws du`, `GET /api/workspaces/usage`).
+//!
+//! Workspaces fill `workspace_root` with their own copies of build outputs
+//! and dependencies. Symlinks, such as those created by `share`, point at
+//! content the workspace doesn't own, so they are not followed.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::{Segment, WorkspaceManager};
+
+/// Disk usage of one workspace directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceUsage {
+    pub segment: String,
+    pub workspace: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Above `[ancillaries] disk_warn_mb`
+    pub over_threshold: bool,
+}
+
+/// Usage of every workspace directory of `segments`, largest first.
+///
+/// Any directory under a segment's workspace dir counts, including ones
+/// whose VCS workspace was forgotten, since those take space too.
+/// `warn_bytes` of 0 flags nothing.
+pub fn workspace_usage(
+    ws_mgr: &WorkspaceManager,
+    segments: &[Segment],
+    warn_bytes: u64,
+) -> Vec<WorkspaceUsage> {
+    let mut usage = Vec::new();
+    for segment in segments {
+        let Ok(entries) = std::fs::read_dir(ws_mgr.segment_dir(&segment.name)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            if !is_dir {
+                continue;
+            }
+            let path = entry.path();
+            let bytes = dir_size(&path);
+            usage.push(WorkspaceUsage {
+                segment: segment.name.clone(),
+                workspace: entry.file_name().to_string_lossy().into_owned(),
+                path,
+                bytes,
+                over_threshold: warn_bytes > 0 && bytes > warn_bytes,
+            });
+        }
+    }
+    usage.sort_by_key(|u| std::cmp::Reverse(u.bytes));
+    usage
+}
+
+/// Space taken by the files under `path`, without following symlinks.
+/// Unreadable entries are skipped.
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) if meta.is_file() => allocated(&meta),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Allocated size, like `du`: sparse files and small files count what they
+/// actually occupy.
+#[cfg(unix)]
+fn allocated(meta: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated(meta: &std::fs::Metadata) -> u64 {
+    meta.len()
+}
+
+/// `1.2 GiB`-style size for display.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_skips_symlinks_and_flags_large_workspaces() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws_root = tmp.path().join("workspaces");
+        let shared = tmp.path().join("shared");
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::write(shared.join("big.bin"), vec![1u8; 256 * 1024]).unwrap();
+
+        let one = ws_root.join("app/one");
+        std::fs::create_dir_all(one.join("node_modules/pkg")).unwrap();
+        std::fs::write(one.join("node_modules/pkg/index.js"), vec![1u8; 64 * 1024]).unwrap();
+        std::os::unix::fs::symlink(&shared, one.join(".claude")).unwrap();
+        let two = ws_root.join("app/two");
+        std::fs::create_dir_all(&two).unwrap();
+        std::fs::write(two.join("README.md"), "hi").unwrap();
+
+        let ws_mgr = WorkspaceManager::new(ws_root, None);
+        let segment = Segment {
+            name: "app".to_string(),
+            path: tmp.path().join("app"),
+        };
+        let usage = workspace_usage(&ws_mgr, &[segment], 32 * 1024);
+
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].workspace, "one");
+        assert!(usage[0].bytes >= 64 * 1024 && usage[0].bytes < 256 * 1024);
+        assert!(usage[0].over_threshold);
+        assert_eq!(usage[1].workspace, "two");
+        assert!(!usage[1].over_threshold);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
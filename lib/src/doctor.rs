@@ -0,0 +1,290 @@
+//! Configuration and environment diagnostics (`breq doctor`, `GET /api/doctor`).
+//!
+//! Each check reports ok, warn or fail, with a one-line detail and, when
+//! something is wrong, the fix most likely to help.
+
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::plugins::PluginManager;
+use crate::segments::SegmentManager;
+use crate::workspace::{detect_repo_type, RepoType};
+use crate::workspace_setup::BreqConfig;
+
+/// Pools larger than this are allowed, but usually a typo.
+const LARGE_POOL: u32 = 100;
+
+/// Tools needed by the contrib task sources.
+const TASK_SOURCE_TOOLS: &[(&str, &str)] = &[("beads", "bd"), ("github", "gh")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// The outcome of one diagnostic.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every check against a loaded config.
+pub fn run_checks(config: &Config) -> Vec<Check> {
+    let mut checks = vec![
+        check_workspace_root(&config.ancillaries.workspace_root),
+        check_pool_size(config.ancillaries.max_per_segment),
+    ];
+    checks.extend(check_segment_patterns(config));
+
+    let segments = SegmentManager::new(config)
+        .map(|mgr| mgr.list_all())
+        .unwrap_or_default();
+
+    checks.push(check_agent(config));
+    checks.push(check_jj(&segments));
+    checks.extend(check_task_tools(config));
+    checks.push(check_proxy(&config.proxy.backend));
+    checks.extend(
+        segments
+            .iter()
+            .filter(|s| BreqConfig::exists(&s.path))
+            .map(|s| check_kdl(&s.name, &s.path)),
+    );
+    checks
+}
+
+/// Whether any check failed.
+pub fn has_failures(checks: &[Check]) -> bool {
+    checks.iter().any(|c| c.status == CheckStatus::Fail)
+}
+
+fn check_workspace_root(root: &Path) -> Check {
+    let name = "workspace_root";
+    if root.is_dir() {
+        Check::ok(name, root.display().to_string())
+    } else if root.exists() {
+        Check::fail(
+            name,
+            format!("{} exists but is not a directory", root.display()),
+            "Point `workspace_root` under [ancillaries] at a directory",
+        )
+    } else {
+        Check::warn(
+            name,
+            format!("{} does not exist yet", root.display()),
+            format!("mkdir -p {}", root.display()),
+        )
+    }
+}
+
+fn check_pool_size(size: u32) -> Check {
+    let name = "max_per_segment";
+    if size == 0 {
+        Check::fail(
+            name,
+            "0 leaves no ancillaries to assign",
+            "Set `max_per_segment` under [ancillaries] to 1 or more (default 10)",
+        )
+    } else if size > LARGE_POOL {
+        Check::warn(
+            name,
+            format!("{} workspaces per segment is unusually large", size),
+            "Lower `max_per_segment` under [ancillaries] unless this is intended",
+        )
+    } else {
+        Check::ok(name, size.to_string())
+    }
+}
+
+fn check_segment_patterns(config: &Config) -> Vec<Check> {
+    let fix = "Edit `segments` under [ancillaries] in the toren config";
+    if config.ancillaries.segments.is_empty() {
+        return vec![Check::fail("segments", "No segments configured", fix)];
+    }
+
+    config
+        .ancillaries
+        .segments
+        .iter()
+        .map(|pattern| {
+            let name = format!("segment {}", pattern);
+            let expanded = shellexpand::tilde(pattern).into_owned();
+            let matches = match glob::glob(&expanded) {
+                Ok(paths) => paths.filter_map(|p| p.ok()).filter(|p| p.is_dir()).count(),
+                Err(e) => return Check::fail(name, format!("Invalid glob: {}", e), fix),
+            };
+            match matches {
+                0 => Check::warn(name, "Matches no directories", fix),
+                1 => Check::ok(name, "1 directory"),
+                n => Check::ok(name, format!("{} directories", n)),
+            }
+        })
+        .collect()
+}
+
+fn check_agent(config: &Config) -> Check {
+    let name = "agent";
+    let resolved = config
+        .resolve_agent(None)
+        .and_then(|agent| agent.ensure_installed().map(|path| (agent, path)));
+    match resolved {
+        Ok((agent, path)) => Check::ok(name, format!("{} ({})", agent, path.display())),
+        Err(e) => match crate::error::find_coded(&e) {
+            Some(coded) => Check::fail(
+                name,
+                coded.message.clone(),
+                coded.hint.clone().unwrap_or_default(),
+            ),
+            None => Check::fail(name, format!("{:#}", e), "Check `agent` under [ancillaries]"),
+        },
+    }
+}
+
+fn check_jj(segments: &[crate::segments::Segment]) -> Check {
+    let name = "jj";
+    let jj_segments = segments
+        .iter()
+        .filter(|s| detect_repo_type(&s.path) == Some(RepoType::Jj))
+        .count();
+    match which::which("jj") {
+        Ok(path) => Check::ok(name, path.display().to_string()),
+        Err(_) if jj_segments > 0 => Check::fail(
+            name,
+            format!("Not found on PATH, but {} segment(s) are jj repos", jj_segments),
+            "Install jj: https://jj-vcs.github.io/jj/latest/install-and-setup/",
+        ),
+        Err(_) => Check::ok(name, "Not installed (no jj segments)"),
+    }
+}
+
+fn check_task_tools(config: &Config) -> Vec<Check> {
+    let sources = match PluginManager::new(&crate::config::toren_root().join("plugins")) {
+        Ok(mgr) => mgr.effective_sources(&config.tasks.sources),
+        Err(_) => config.tasks.sources.clone(),
+    };
+
+    TASK_SOURCE_TOOLS
+        .iter()
+        .filter(|(source, _)| sources.iter().any(|s| s == source))
+        .map(|(source, tool)| match which::which(tool) {
+            Ok(path) => Check::ok(*tool, path.display().to_string()),
+            Err(_) => Check::fail(
+                *tool,
+                format!("Not found on PATH (needed by the {} task source)", source),
+                format!(
+                    "Install `{}`, or remove \"{}\" from `sources` under [tasks]",
+                    tool, source
+                ),
+            ),
+        })
+        .collect()
+}
+
+fn check_proxy(backend: &str) -> Check {
+    let name = "proxy";
+    match backend {
+        "caddy" => {
+            let url = std::env::var("STATION_CADDY_URL")
+                .unwrap_or_else(|_| "http://localhost:2019".to_string());
+            let agent = ureq::Agent::new_with_config(
+                ureq::config::Config::builder()
+                    .timeout_global(Some(Duration::from_secs(2)))
+                    .http_status_as_error(false)
+                    .build(),
+            );
+            match agent.get(format!("{}/config/", url)).call() {
+                Ok(_) => Check::ok(name, format!("Caddy admin API at {}", url)),
+                Err(e) => Check::warn(
+                    name,
+                    format!("Caddy admin API at {} unreachable: {}", url, e),
+                    "Start Caddy (`caddy run`), or set STATION_CADDY_URL; workspaces still work without proxy routes",
+                ),
+            }
+        }
+        "traefik" | "nginx" => Check::ok(name, backend),
+        other => Check::fail(
+            name,
+            format!("Unknown proxy backend '{}'", other),
+            "Set `backend` under [proxy] to caddy, traefik or nginx",
+        ),
+    }
+}
+
+fn check_kdl(segment: &str, path: &Path) -> Check {
+    let name = format!("toren.kdl ({})", segment);
+    match BreqConfig::parse(path) {
+        Ok(_) => Check::ok(name, "Parses"),
+        Err(e) => Check::fail(
+            name,
+            format!("{:#}", e),
+            format!("Fix the file, then check with `breq setup` in {}", path.display()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("workspaces");
+        assert_eq!(check_workspace_root(&missing).status, CheckStatus::Warn);
+        std::fs::create_dir(&missing).unwrap();
+        assert_eq!(check_workspace_root(&missing).status, CheckStatus::Ok);
+
+        assert_eq!(check_pool_size(0).status, CheckStatus::Fail);
+        assert_eq!(check_pool_size(10).status, CheckStatus::Ok);
+        assert_eq!(check_pool_size(500).status, CheckStatus::Warn);
+
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        std::fs::write(repo.join("toren.kdl"), "setup {\n  run \"echo hi\"\n}\n").unwrap();
+        assert_eq!(check_kdl("repo", &repo).status, CheckStatus::Ok);
+        std::fs::write(repo.join("toren.kdl"), "setup {\n").unwrap();
+        let check = check_kdl("repo", &repo);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.fix.is_some());
+
+        assert_eq!(check_proxy("apache").status, CheckStatus::Fail);
+    }
+}
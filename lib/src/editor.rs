@@ -0,0 +1,161 @@
+//! Opening workspaces in an editor (`breq open`).
+//!
+//! `[ancillaries.editor]` sets the command that opens a workspace, a
+//! template like `code {{ path }}`. Over SSH a GUI editor would open on the
+//! remote machine, so `breq open --uri` (or `print_uri_over_ssh`) prints a
+//! URI the local editor can open instead, by default a VS Code Remote-SSH
+//! one.
+
+use anyhow::{Context, Result};
+use minijinja::{context, Environment};
+use serde::{Deserialize, Serialize};
+
+use crate::workspace_setup::WorkspaceContext;
+
+/// URI for a workspace opened over SSH, unless `[ancillaries.editor] uri`
+/// sets one.
+pub const DEFAULT_URI: &str = "vscode://vscode-remote/ssh-remote+{{ host }}{{ path }}";
+
+/// `[ancillaries.editor]`: how `breq open` opens a workspace.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditorConfig {
+    /// Command run in the workspace, e.g. `code {{ path }}` (default:
+    /// `$VISUAL` or `$EDITOR` with the workspace path)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    /// URI printed by `breq open --uri` (default: a VS Code Remote-SSH URI)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+
+    /// SSH host the URI names (default: this machine's hostname)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+
+    /// In an SSH session, print the URI instead of running the command
+    #[serde(default)]
+    pub print_uri_over_ssh: bool,
+}
+
+impl EditorConfig {
+    /// The command opening the workspace in `ctx`: the configured template,
+    /// or `$VISUAL`/`$EDITOR` followed by the quoted workspace path.
+    pub fn command(&self, ctx: &WorkspaceContext) -> Result<String> {
+        match &self.command {
+            Some(template) => render(template, ctx, &self.host()),
+            None => {
+                let editor = std::env::var("VISUAL")
+                    .or_else(|_| std::env::var("EDITOR"))
+                    .ok()
+                    .filter(|e| !e.trim().is_empty())
+                    .context("No editor configured; set [ancillaries.editor] command, or $VISUAL or $EDITOR")?;
+                Ok(format!("{} {}", editor, shell_quote(&ctx.ws.path)))
+            }
+        }
+    }
+
+    /// The URI opening the workspace in `ctx` from another machine.
+    pub fn uri(&self, ctx: &WorkspaceContext) -> Result<String> {
+        render(self.uri.as_deref().unwrap_or(DEFAULT_URI), ctx, &self.host())
+    }
+
+    fn host(&self) -> String {
+        self.host.clone().unwrap_or_else(hostname)
+    }
+}
+
+/// Whether breq is running in an SSH session.
+pub fn in_ssh_session() -> bool {
+    std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some()
+}
+
+/// Render an editor template. It sees the workspace context (`ws.name`,
+/// `repo.name`, `task.id`, ...) plus `path`, the workspace path, and `host`.
+fn render(template: &str, ctx: &WorkspaceContext, host: &str) -> Result<String> {
+    let env = Environment::new();
+    let rendered = env
+        .render_str(
+            template,
+            context! {
+                path => ctx.ws.path,
+                host => host,
+                ws => ctx.ws,
+                repo => ctx.repo,
+                task => ctx.task,
+            },
+        )
+        .with_context(|| format!("Failed to render editor template '{}'", template))?;
+    Ok(rendered.trim().to_string())
+}
+
+fn hostname() -> String {
+    std::process::Command::new("hostname")
+        .output()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Quote `s` for a POSIX shell if it has anything the shell would split or
+/// expand.
+fn shell_quote(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "/._-~+:@".contains(c)) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace_setup::{RepoInfo, WorkspaceInfo};
+
+    fn ctx(path: &str) -> WorkspaceContext {
+        WorkspaceContext {
+            ws: WorkspaceInfo {
+                name: "one".to_string(),
+                num: 1,
+                path: path.to_string(),
+            },
+            repo: RepoInfo {
+                root: "/src/toren".to_string(),
+                name: "toren".to_string(),
+                recent_commits: Vec::new(),
+            },
+            task: None,
+            variant: None,
+            assignment: None,
+            vars: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_renders_command_and_uri() {
+        let config = EditorConfig {
+            command: Some("code --new-window {{ path }}".to_string()),
+            host: Some("devbox".to_string()),
+            ..EditorConfig::default()
+        };
+        let ctx = ctx("/ws/toren/one");
+        assert_eq!(config.command(&ctx).unwrap(), "code --new-window /ws/toren/one");
+        assert_eq!(
+            config.uri(&ctx).unwrap(),
+            "vscode://vscode-remote/ssh-remote+devbox/ws/toren/one"
+        );
+
+        let config = EditorConfig {
+            uri: Some("zed://ssh/{{ host }}{{ path }}#{{ repo.name }}-{{ ws.name }}".to_string()),
+            host: Some("devbox".to_string()),
+            ..EditorConfig::default()
+        };
+        assert_eq!(config.uri(&ctx).unwrap(), "zed://ssh/devbox/ws/toren/one#toren-one");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/ws/toren/one"), "/ws/toren/one");
+        assert_eq!(shell_quote("/My Files/it's"), "'/My Files/it'\\''s'");
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-workspace direnv `.envrc` (`[ancillaries] envrc`, or `envrc #true` in
+//! toren.kdl).
+//!
+//! toren keeps a marked block of `export` lines in the workspace's `.envrc`:
+//! the assignment, task, scratch directory and evaluated `var`s (ports), so a
+//! shell that enters the workspace (e.g. via `breq go`) picks them up. Like
+//! the agent context block, it is rewritten on resume and stripped before
+//! changes are committed, so a repo's own `.envrc` content is preserved.
+
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::agent_context::Markers;
+use crate::workspace_setup::WorkspaceFacts;
+use crate::Assignment;
+
+pub const ENVRC_FILE: &str = ".envrc";
+
+const MARKERS: Markers = Markers {
+    begin: "# toren:begin",
+    end: "# toren:end",
+};
+
+/// Render the toren block (including markers) for an assignment.
+///
+/// Vars are exported under their toren.kdl names; the rest are prefixed
+/// `TOREN_`.
+pub fn render_section(assignment: &Assignment, facts: &WorkspaceFacts, scratch: &Path) -> String {
+    let mut exports = vec![
+        ("TOREN_ASSIGNMENT_ID", assignment.id.clone()),
+        ("TOREN_ANCILLARY", assignment.ancillary_id.to_string()),
+        ("TOREN_SEGMENT", assignment.segment.clone()),
+        ("TOREN_WORKSPACE", assignment.workspace_path.display().to_string()),
+        ("TOREN_SCRATCH", scratch.display().to_string()),
+    ];
+    if let Some(ref task_id) = assignment.task_id {
+        exports.push(("TOREN_TASK_ID", task_id.to_string()));
+    }
+    exports.extend(facts.vars.iter().map(|(name, value)| (name.as_str(), value.clone())));
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", MARKERS.begin);
+    let _ = writeln!(out, "# Maintained by toren; removed before changes are committed.");
+    for (name, value) in exports {
+        let _ = writeln!(out, "export {}={}", name, shell_quote(&value));
+    }
+    out.push_str(MARKERS.end);
+    out.push('\n');
+    out
+}
+
+/// Write (or rewrite) the toren block into the workspace's `.envrc`, then
+/// `direnv allow` it if it changed and direnv is installed.
+pub fn write(workspace_path: &Path, section: &str) -> Result<PathBuf> {
+    let (path, changed) =
+        crate::agent_context::write_block(workspace_path, ENVRC_FILE, section, MARKERS)?;
+    if changed {
+        allow(&path);
+    }
+    Ok(path)
+}
+
+/// Strip the toren block from the workspace's `.envrc`, deleting the file if
+/// nothing else is left in it. Returns whether a block was removed.
+pub fn remove(workspace_path: &Path) -> Result<bool> {
+    crate::agent_context::remove_block_from_file(workspace_path, ENVRC_FILE, MARKERS)
+}
+
+/// direnv refuses to load an `.envrc` whose content changed until it is
+/// allowed again. Missing direnv is not an error.
+fn allow(path: &Path) {
+    let status = Command::new("direnv")
+        .arg("allow")
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(s) if !s.success() => tracing::warn!("direnv allow {} failed", path.display()),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            tracing::warn!("Failed to run direnv allow: {}", e)
+        }
+        _ => {}
+    }
+}
+
+/// Single-quote `value` for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssignmentSource, AssignmentStatus};
+
+    #[test]
+    fn test_envrc_block_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let assignment = Assignment {
+            id: "abc123".to_string(),
+            ancillary_id: "Toren One".parse().unwrap(),
+            task_id: Some("bd-42".parse().unwrap()),
+            segment: "toren".to_string(),
+            workspace_path: tmp.path().to_path_buf(),
+            source: AssignmentSource::Reference,
+            status: AssignmentStatus::Active,
+            created_at: now.clone(),
+            updated_at: now,
+            session_id: None,
+            task_title: None,
+            task_url: None,
+            task_source: None,
+            ancillary_num: Some(1),
+            base_branch: None,
+            labels: Vec::new(),
+            focus_path: None,
+            intent: None,
+            resume_instruction: None,
+            owner: None,
+            notes: Vec::new(),
+            after: None,
+            group: None,
+            companions: Vec::new(),
+            prompt_amended_at: None,
+            usage: None,
+        };
+        let mut facts = WorkspaceFacts::default();
+        facts.vars.insert("WEB_PORT".to_string(), "3001".to_string());
+        facts.vars.insert("GREETING".to_string(), "it's".to_string());
+
+        std::fs::write(tmp.path().join(ENVRC_FILE), "use flake\n").unwrap();
+        let section = render_section(&assignment, &facts, Path::new("/scratch/one"));
+        write(tmp.path(), &section).unwrap();
+        write(tmp.path(), &section).unwrap();
+
+        let content = std::fs::read_to_string(tmp.path().join(ENVRC_FILE)).unwrap();
+        assert!(content.starts_with("use flake\n\n# toren:begin\n"));
+        assert_eq!(content.matches("# toren:begin").count(), 1);
+        assert!(content.contains("export TOREN_ASSIGNMENT_ID='abc123'\n"));
+        assert!(content.contains("export TOREN_TASK_ID='bd-42'\n"));
+        assert!(content.contains("export WEB_PORT='3001'\n"));
+        assert!(content.contains(r"export GREETING='it'\''s'"));
+
+        assert!(remove(tmp.path()).unwrap());
+        let content = std::fs::read_to_string(tmp.path().join(ENVRC_FILE)).unwrap();
+        assert_eq!(content, "use flake\n");
+    }
+}
@@ -0,0 +1,252 @@
+//! Coded, user-facing errors.
+//!
+//! Common failures are raised as a [`TorenError`] carrying an [`ErrorCode`]
+//! and an optional hint. They travel as ordinary `anyhow` errors (usually as
+//! context on the underlying cause), so front ends can find the coded layer
+//! with [`find_coded`], print a one-line summary plus hint, and point at
+//! `breq --explain <code>` for the long form.
+
+use std::fmt;
+
+/// Stable identifiers for the errors users most often hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// `~/.toren/config.toml` (or `--config`) failed to parse
+    ConfigInvalid,
+    /// The current directory isn't inside any configured segment
+    NotInSegment,
+    /// A segment named on the command line doesn't exist
+    SegmentNotFound,
+    /// No assignment matches a workspace/task reference
+    AssignmentNotFound,
+    /// The coding agent binary isn't installed
+    AgentNotInstalled,
+    /// A repo's `toren.kdl` failed to parse
+    KdlInvalid,
+    /// The toren daemon couldn't be reached
+    DaemonUnreachable,
+    /// Every ancillary slot in the segment's pool is occupied
+    PoolExhausted,
+    /// The daemon rejected a request without a valid session token
+    DaemonUnauthorized,
+    /// Another live breq session or daemon work owns the assignment
+    AssignmentOwned,
+}
+
+/// Long-form description of an error code, for `breq --explain`.
+pub struct Explanation {
+    pub title: &'static str,
+    pub details: &'static str,
+    /// Files worth looking at
+    pub files: &'static [&'static str],
+    /// Commands that usually get things moving again
+    pub next: &'static [&'static str],
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 10] = [
+        ErrorCode::ConfigInvalid,
+        ErrorCode::NotInSegment,
+        ErrorCode::SegmentNotFound,
+        ErrorCode::AssignmentNotFound,
+        ErrorCode::AgentNotInstalled,
+        ErrorCode::KdlInvalid,
+        ErrorCode::DaemonUnreachable,
+        ErrorCode::PoolExhausted,
+        ErrorCode::DaemonUnauthorized,
+        ErrorCode::AssignmentOwned,
+    ];
+
+    /// The code as printed, e.g. "E0001".
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::ConfigInvalid => "E0001",
+            ErrorCode::NotInSegment => "E0002",
+            ErrorCode::SegmentNotFound => "E0003",
+            ErrorCode::AssignmentNotFound => "E0004",
+            ErrorCode::AgentNotInstalled => "E0005",
+            ErrorCode::KdlInvalid => "E0006",
+            ErrorCode::DaemonUnreachable => "E0007",
+            ErrorCode::PoolExhausted => "E0008",
+            ErrorCode::DaemonUnauthorized => "E0009",
+            ErrorCode::AssignmentOwned => "E0010",
+        }
+    }
+
+    /// Parse a code, case-insensitively ("E0004", "e0004").
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.as_str().eq_ignore_ascii_case(code.trim()))
+    }
+
+    pub fn explain(self) -> Explanation {
+        match self {
+            ErrorCode::ConfigInvalid => Explanation {
+                title: "Invalid toren configuration",
+                details: "The toren config file could not be parsed as TOML, or a field has \
+                          the wrong type. The error chain (shown with -v) includes the line \
+                          and column of the problem.",
+                files: &["~/.toren/config.toml", "toren.toml.example (reference config)"],
+                next: &["$EDITOR ~/.toren/config.toml", "breq --config <path> <command>"],
+            },
+            ErrorCode::NotInSegment => Explanation {
+                title: "Not inside a segment",
+                details: "Most breq commands work on the segment (repository) containing the \
+                          current directory. The current directory isn't under any path listed \
+                          in `ancillaries.segments`, so breq can't tell which repo you mean.",
+                files: &["~/.toren/config.toml ([ancillaries] segments)"],
+                next: &["cd <repo>", "breq <command> --segment <name>", "breq init"],
+            },
+            ErrorCode::SegmentNotFound => Explanation {
+                title: "Unknown segment",
+                details: "No configured segment has that name. Segment names are the directory \
+                          names of repos matched by `ancillaries.segments`.",
+                files: &["~/.toren/config.toml ([ancillaries] segments)"],
+                next: &["breq list --all"],
+            },
+            ErrorCode::AssignmentNotFound => Explanation {
+                title: "No matching assignment",
+                details: "The reference didn't match an active assignment. References are \
+                          workspace names (\"one\"), numbers (\"1\"), or task IDs, resolved \
+                          against the current segment. The assignment may have been completed, \
+                          aborted, or dismissed already.",
+                files: &["~/.toren/assignments.json"],
+                next: &["breq list", "breq list --all"],
+            },
+            ErrorCode::AgentNotInstalled => Explanation {
+                title: "Coding agent not installed",
+                details: "The configured coding agent's binary isn't on PATH (or at the \
+                          configured `binary` path). Install it, or pick another agent.",
+                files: &["~/.toren/config.toml ([ancillaries] agent, [ancillaries.<agent>] binary)"],
+                next: &["breq do --agent <name> ..."],
+            },
+            ErrorCode::KdlInvalid => Explanation {
+                title: "Invalid toren.kdl",
+                details: "The repo's toren.kdl (workspace setup/destroy hooks) failed to parse. \
+                          Setup hooks didn't run; the error chain (shown with -v) points at the \
+                          offending node.",
+                files: &["<repo>/toren.kdl", "docs/configuration.md"],
+                next: &["breq setup <workspace>"],
+            },
+            ErrorCode::DaemonUnreachable => Explanation {
+                title: "Toren daemon unreachable",
+                details: "The command needs the toren daemon, but nothing answered at the \
+                          configured host and port. Start the daemon, or check `[server]` in \
+                          the config.",
+                files: &["~/.toren/config.toml ([server] host, port)"],
+                next: &["toren-daemon", "breq do ... (without --detach)"],
+            },
+            ErrorCode::PoolExhausted => Explanation {
+                title: "Ancillary pool exhausted",
+                details: "Every slot up to `ancillaries.max_per_segment` in this segment holds \
+                          an assignment or a leftover workspace. The error lists each slot as \
+                          busy (agent mid-turn), dirty (idle with changes), idle (no changes), \
+                          or orphaned (workspace without an assignment), with the command most \
+                          likely to free it. Pass --overflow to allocate past the pool anyway.",
+                files: &["~/.toren/config.toml ([ancillaries] max_per_segment)"],
+                next: &["breq list", "breq complete <workspace>", "breq do --overflow ..."],
+            },
+            ErrorCode::DaemonUnauthorized => Explanation {
+                title: "Not paired with the toren daemon",
+                details: "The daemon requires a session token on its API routes, and breq \
+                          sent none or one the daemon doesn't know. Pair once with the pairing \
+                          token the daemon logs at startup (or sets from PAIRING_TOKEN); breq \
+                          saves the session token and sends it from then on. Alternatively, \
+                          list this machine's address in `server.trusted_addrs`.",
+                files: &["~/.toren/daemon_token", "~/.toren/config.toml ([server] trusted_addrs)"],
+                next: &["breq pair <pairing-token>", "TOREN_TOKEN=<session-token> breq ..."],
+            },
+            ErrorCode::AssignmentOwned => Explanation {
+                title: "Assignment owned by another session",
+                details: "An agent is already running for this assignment, either in another \
+                          terminal (started by breq) or as daemon work. Starting a second agent, \
+                          or completing the workspace underneath it, would have both write to \
+                          the same files. The owner is recorded on the assignment and stops \
+                          counting once its process exits. Pass --steal to take ownership \
+                          anyway.",
+                files: &["~/.toren/assignments.json (owner)"],
+                next: &["breq why <workspace>", "breq do <workspace> --steal ...", "breq destroy <workspace> --steal"],
+            },
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error with a code and an optional hint.
+///
+/// Displays as the message followed by the hint on its own line, so callers
+/// that just print the error chain still see the hint.
+#[derive(Debug, Clone)]
+pub struct TorenError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl TorenError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for TorenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(ref hint) = self.hint {
+            write!(f, "\n{}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TorenError {}
+
+/// The coded layer of an error, whether it is the root cause or context.
+pub fn find_coded(err: &anyhow::Error) -> Option<&TorenError> {
+    err.downcast_ref::<TorenError>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_codes_round_trip() {
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::parse(code.as_str()), Some(code));
+        }
+        assert_eq!(ErrorCode::parse("e0004"), Some(ErrorCode::AssignmentNotFound));
+        assert_eq!(ErrorCode::parse("E9999"), None);
+    }
+
+    #[test]
+    fn test_find_coded_through_context() {
+        let root: anyhow::Result<()> = Err(anyhow::anyhow!("expected '=' at line 3"));
+        let err = root
+            .context(TorenError::new(ErrorCode::ConfigInvalid, "Failed to parse config"))
+            .context("Failed to start")
+            .unwrap_err();
+        let coded = find_coded(&err).unwrap();
+        assert_eq!(coded.code, ErrorCode::ConfigInvalid);
+        assert_eq!(err.chain().count(), 3);
+
+        let plain = anyhow::anyhow!("plain");
+        assert!(find_coded(&plain).is_none());
+    }
+}
@@ -0,0 +1,238 @@
+//! Cleanup for segments that no longer exist (`breq gc`, daemon maintenance).
+//!
+//! Completion history, kept artifacts, daemon work logs, setup manifests and
+//! scratch directories are keyed by segment, directly or through an
+//! assignment, and nothing removes them when a repo is deleted or drops out
+//! of the configured segment roots. [`plan`] finds what belongs to such
+//! segments so it can be reported first; [`apply`] archives or deletes it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::assignment::{AssignmentManager, CompletionRecord};
+
+/// What [`apply`] does with stale records and files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GcAction {
+    /// Move under `~/.toren/archive/gc-<timestamp>/`
+    Archive,
+    Delete,
+}
+
+/// Records and files left behind by one segment.
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleSegment {
+    pub name: String,
+    /// Completion history records
+    pub records: Vec<CompletionRecord>,
+    /// Artifact dirs, work logs, setup manifests and scratch dirs
+    pub paths: Vec<PathBuf>,
+}
+
+/// Everything [`apply`] would remove, by segment.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GcPlan {
+    pub segments: Vec<StaleSegment>,
+}
+
+impl GcPlan {
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn record_count(&self) -> usize {
+        self.segments.iter().map(|s| s.records.len()).sum()
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.segments.iter().map(|s| s.paths.len()).sum()
+    }
+}
+
+/// What [`apply`] removed.
+#[derive(Debug, Clone, Serialize)]
+pub struct GcOutcome {
+    pub records: usize,
+    pub paths: usize,
+    /// Where records and files were moved, when archiving
+    pub archive_dir: Option<PathBuf>,
+}
+
+/// Daemon work log of an assignment:
+/// `<toren_dir>/ancillaries/<ancillary-slug>/work/<assignment_id>.jsonl`.
+pub fn work_log_path(toren_dir: &Path, ancillary_id: &str, assignment_id: &str) -> PathBuf {
+    toren_dir
+        .join("ancillaries")
+        .join(ancillary_id.to_lowercase().replace(' ', "-"))
+        .join("work")
+        .join(format!("{}.jsonl", assignment_id))
+}
+
+/// Find history and files of segments that aren't in `live_segments` and
+/// have no active assignment. Fails when `live_segments` is empty, since a
+/// missing or mistyped segment root would otherwise make everything stale.
+pub fn plan(
+    assignment_mgr: &mut AssignmentManager,
+    toren_dir: &Path,
+    live_segments: &[String],
+) -> Result<GcPlan> {
+    if live_segments.is_empty() {
+        anyhow::bail!(
+            "No segments found under the configured roots; check `segments` under [ancillaries] before collecting"
+        );
+    }
+
+    let mut live: HashSet<String> = live_segments.iter().cloned().collect();
+    live.extend(assignment_mgr.list().into_iter().map(|a| a.segment.clone()));
+
+    let mut stale: BTreeMap<String, StaleSegment> = BTreeMap::new();
+    for record in assignment_mgr.history()? {
+        let Some(segment) = stale_entry(&mut stale, &live, &record.segment) else {
+            continue;
+        };
+        let artifacts = assignment_mgr.artifacts_dir(&record.assignment_id);
+        let work_log = work_log_path(toren_dir, &record.ancillary_id, &record.assignment_id);
+        segment
+            .paths
+            .extend([artifacts, work_log].into_iter().filter(|p| p.exists()));
+        segment.records.push(record);
+    }
+
+    for kind in ["setup", "scratch"] {
+        let Ok(dirs) = std::fs::read_dir(toren_dir.join(kind)) else {
+            continue;
+        };
+        for dir in dirs.flatten().filter(|d| d.path().is_dir()) {
+            if let Some(segment) = stale_entry(&mut stale, &live, &dir.file_name().to_string_lossy()) {
+                segment.paths.push(dir.path());
+            }
+        }
+    }
+
+    Ok(GcPlan {
+        segments: stale.into_values().collect(),
+    })
+}
+
+fn stale_entry<'a>(
+    stale: &'a mut BTreeMap<String, StaleSegment>,
+    live: &HashSet<String>,
+    name: &str,
+) -> Option<&'a mut StaleSegment> {
+    if live.contains(name) {
+        return None;
+    }
+    Some(stale.entry(name.to_string()).or_insert_with(|| StaleSegment {
+        name: name.to_string(),
+        records: Vec::new(),
+        paths: Vec::new(),
+    }))
+}
+
+/// Archive or delete everything in `plan`.
+pub fn apply(
+    assignment_mgr: &AssignmentManager,
+    toren_dir: &Path,
+    plan: &GcPlan,
+    action: GcAction,
+) -> Result<GcOutcome> {
+    let names: HashSet<&str> = plan.segments.iter().map(|s| s.name.as_str()).collect();
+    let paths: Vec<&PathBuf> = plan.segments.iter().flat_map(|s| &s.paths).collect();
+
+    let archive_dir = match action {
+        GcAction::Archive => {
+            let dir = toren_dir
+                .join("archive")
+                .join(format!("gc-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            Some(dir)
+        }
+        GcAction::Delete => None,
+    };
+
+    let removed = assignment_mgr.prune_history(|r| names.contains(r.segment.as_str()))?;
+    if let Some(ref dir) = archive_dir {
+        let mut file = std::fs::File::create(dir.join("completion_history.jsonl"))?;
+        for record in &removed {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+        }
+    }
+
+    for path in &paths {
+        match archive_dir {
+            Some(ref dir) => {
+                let relative = path.strip_prefix(toren_dir).unwrap_or(path.as_path());
+                let target = dir.join(relative);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::rename(path, &target)
+                    .with_context(|| format!("Failed to archive {}", path.display()))?;
+            }
+            None if path.is_dir() => std::fs::remove_dir_all(path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?,
+            None => std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?,
+        }
+    }
+
+    Ok(GcOutcome {
+        records: removed.len(),
+        paths: paths.len(),
+        archive_dir,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assignment::{AssignmentSource, CompletionReason};
+
+    #[test]
+    fn test_plan_and_archive_stale_segment() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let mut mgr = AssignmentManager::with_storage_path(root.join("assignments.json")).unwrap();
+
+        for (ancillary, segment) in [("Gone One", "gone"), ("Live One", "live")] {
+            let a = mgr
+                .create(&ancillary.parse().unwrap(), None, AssignmentSource::Reference, segment, root.join(segment), None, None, None, None)
+                .unwrap();
+            mgr.record_completion(&a, CompletionReason::Completed, None, Vec::new()).unwrap();
+            mgr.remove(&a.id).unwrap();
+            std::fs::create_dir_all(mgr.artifacts_dir(&a.id)).unwrap();
+            let log = work_log_path(root, ancillary, &a.id);
+            std::fs::create_dir_all(log.parent().unwrap()).unwrap();
+            std::fs::write(log, "{}\n").unwrap();
+            std::fs::create_dir_all(root.join("setup").join(segment)).unwrap();
+        }
+        std::fs::create_dir_all(root.join("scratch/gone/one")).unwrap();
+
+        let plan = plan(&mut mgr, root, &["live".to_string()]).unwrap();
+        assert_eq!(plan.segments.len(), 1);
+        assert_eq!(plan.segments[0].name, "gone");
+        assert_eq!(plan.record_count(), 1);
+        assert_eq!(plan.path_count(), 4);
+
+        let outcome = apply(&mgr, root, &plan, GcAction::Archive).unwrap();
+        assert_eq!((outcome.records, outcome.paths), (1, 4));
+        let archive = outcome.archive_dir.unwrap();
+        assert!(archive.join("setup/gone").is_dir());
+        assert!(archive.join("scratch/gone/one").is_dir());
+        assert!(!root.join("setup/gone").exists());
+        assert!(root.join("setup/live").exists());
+
+        let history = mgr.history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].segment, "live");
+        let archived = std::fs::read_to_string(archive.join("completion_history.jsonl")).unwrap();
+        assert_eq!(archived.lines().count(), 1);
+        assert!(super::plan(&mut mgr, root, &["live".to_string()]).unwrap().is_empty());
+        assert!(super::plan(&mut mgr, root, &[]).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Assignment health, as tracked by the daemon's health monitor.
+//!
+//! Composite status answers "what is the agent doing right now"; health
+//! answers "does this assignment need attention". An agent whose last session
+//! log entry is mid-turn but hasn't logged anything for `stall_minutes` is
+//! stalled (a hung tool call, a lost terminal), and an assignment whose
+//! workspace directory is gone can't make progress at all.
+
+use crate::composite_status::{format_secs, last_session_event, SessionEvent};
+use crate::session::SessionLogs;
+use crate::Assignment;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    /// The agent is mid-turn and logging
+    Working,
+    /// The agent finished its turn and is waiting for input
+    Idle,
+    /// The agent should be working but hasn't logged anything recently
+    Stalled,
+    /// The workspace directory no longer exists
+    WorkspaceMissing,
+}
+
+impl HealthState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HealthState::Working => "working",
+            HealthState::Idle => "idle",
+            HealthState::Stalled => "stalled",
+            HealthState::WorkspaceMissing => "workspace_missing",
+        }
+    }
+
+    /// Whether this state needs attention.
+    pub fn is_warning(self) -> bool {
+        matches!(self, HealthState::Stalled | HealthState::WorkspaceMissing)
+    }
+}
+
+/// The result of one health check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignmentHealth {
+    pub state: HealthState,
+    /// Human-readable reason for the state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// When the session log was last written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_event_at: Option<DateTime<Utc>>,
+    /// When the assignment entered this state (as far as the checker knows)
+    pub since: DateTime<Utc>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Check an assignment's health. `agent_running` is whether the caller knows
+/// the agent to be running (e.g. the daemon is driving it); otherwise only
+/// the session logs are consulted.
+pub fn check_health(
+    logs: &SessionLogs,
+    assignment: &Assignment,
+    agent_running: bool,
+    stall_after: Duration,
+) -> AssignmentHealth {
+    let now = SystemTime::now();
+    let event = last_session_event(logs, &assignment.working_dir());
+    let (state, detail) = classify(
+        assignment.workspace_path.exists(),
+        event,
+        agent_running,
+        stall_after,
+        now,
+    );
+    let checked_at = DateTime::<Utc>::from(now);
+    AssignmentHealth {
+        state,
+        detail,
+        last_event_at: event.map(|e| DateTime::<Utc>::from(e.at)),
+        since: checked_at,
+        checked_at,
+    }
+}
+
+fn classify(
+    workspace_exists: bool,
+    event: Option<SessionEvent>,
+    agent_running: bool,
+    stall_after: Duration,
+    now: SystemTime,
+) -> (HealthState, Option<String>) {
+    if !workspace_exists {
+        return (
+            HealthState::WorkspaceMissing,
+            Some("workspace directory no longer exists".to_string()),
+        );
+    }
+    match event {
+        Some(event) if event.mid_turn || agent_running => {
+            let quiet = now.duration_since(event.at).unwrap_or_default();
+            if quiet > stall_after {
+                (
+                    HealthState::Stalled,
+                    Some(format!(
+                        "no session log events for {}",
+                        format_secs(quiet.as_secs())
+                    )),
+                )
+            } else {
+                (HealthState::Working, None)
+            }
+        }
+        // Running, but not a Claude session: nothing to measure progress by
+        None if agent_running => (HealthState::Working, None),
+        _ => (HealthState::Idle, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STALL: Duration = Duration::from_secs(15 * 60);
+
+    fn event(mins_ago: u64, mid_turn: bool, now: SystemTime) -> Option<SessionEvent> {
+        Some(SessionEvent {
+            at: now - Duration::from_secs(mins_ago * 60),
+            mid_turn,
+        })
+    }
+
+    #[test]
+    fn test_classify_mid_turn_by_quiet_time() {
+        let now = SystemTime::now();
+        // A long tool call is still working, past the busy/idle staleness cutoff
+        let (state, _) = classify(true, event(10, true, now), false, STALL, now);
+        assert_eq!(state, HealthState::Working);
+
+        let (state, detail) = classify(true, event(40, true, now), false, STALL, now);
+        assert_eq!(state, HealthState::Stalled);
+        assert_eq!(detail.as_deref(), Some("no session log events for 40m"));
+    }
+
+    #[test]
+    fn test_classify_idle_and_missing() {
+        let now = SystemTime::now();
+        let (state, _) = classify(true, event(40, false, now), false, STALL, now);
+        assert_eq!(state, HealthState::Idle);
+
+        // The daemon knows the agent is running, so a quiet log is a stall
+        let (state, _) = classify(true, event(40, false, now), true, STALL, now);
+        assert_eq!(state, HealthState::Stalled);
+
+        let (state, _) = classify(false, event(1, true, now), true, STALL, now);
+        assert_eq!(state, HealthState::WorkspaceMissing);
+        assert!(state.is_warning());
+    }
+}
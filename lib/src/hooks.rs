@@ -0,0 +1,460 @@
+//! User-level lifecycle hooks.
+//!
+//! Executable scripts in `~/.config/toren/hooks/` named after a lifecycle
+//! event (`assign`, `complete`, `abort`, `resume`) are run whenever that
+//! event happens, from both the breq CLI and the daemon. Each hook receives
+//! the event context as `TOREN_*` environment variables and as a JSON
+//! document on stdin.
+//!
+//! Hooks are best-effort: a missing, failing, or non-executable hook is
+//! logged and never fails the lifecycle operation that triggered it.
+//!
+//! Tools embedding toren_lib can observe the same events in-process by
+//! registering a [`LifecycleObserver`] (or a channel via [`subscribe`]).
+//! [`emit`] notifies observers, then runs the user hook.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, RwLock};
+
+use crate::Assignment;
+
+/// Lifecycle events that can trigger a user hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    /// An assignment was created and its workspace set up
+    Assign,
+    /// An assignment was completed and its workspace cleaned up
+    Complete,
+    /// An assignment was aborted and its workspace cleaned up
+    Abort,
+    /// An assignment was prepared for resuming
+    Resume,
+}
+
+impl HookEvent {
+    /// Hook script name for this event.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::Assign => "assign",
+            HookEvent::Complete => "complete",
+            HookEvent::Abort => "abort",
+            HookEvent::Resume => "resume",
+        }
+    }
+}
+
+impl std::fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Event context passed to a hook (serialized as JSON on stdin).
+#[derive(Debug, Serialize)]
+pub struct HookPayload<'a> {
+    pub event: HookEvent,
+    pub assignment: &'a Assignment,
+    /// VCS revision captured before cleanup (complete/abort)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Whether changes were pushed (complete)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pushed: Option<bool>,
+    /// Whether the workspace had to be recreated (resume)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_recreated: Option<bool>,
+}
+
+impl<'a> HookPayload<'a> {
+    pub fn new(event: HookEvent, assignment: &'a Assignment) -> Self {
+        Self {
+            event,
+            assignment,
+            revision: None,
+            pushed: None,
+            workspace_recreated: None,
+        }
+    }
+
+    /// Environment variables exposed to the hook process.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let a = self.assignment;
+        let mut env = vec![
+            ("TOREN_EVENT", self.event.to_string()),
+            ("TOREN_ASSIGNMENT_ID", a.id.clone()),
+            ("TOREN_ANCILLARY_ID", a.ancillary_id.to_string()),
+            ("TOREN_SEGMENT", a.segment.clone()),
+            ("TOREN_WORKSPACE_PATH", a.workspace_path.display().to_string()),
+        ];
+        if let Some(name) = a.workspace_path.file_name().and_then(|n| n.to_str()) {
+            env.push(("TOREN_WORKSPACE", name.to_string()));
+        }
+        if let Some(ref task_id) = a.task_id {
+            env.push(("TOREN_TASK_ID", task_id.to_string()));
+        }
+        let optional = [
+            ("TOREN_TASK_TITLE", &a.task_title),
+            ("TOREN_TASK_URL", &a.task_url),
+            ("TOREN_TASK_SOURCE", &a.task_source),
+            ("TOREN_SESSION_ID", &a.session_id),
+            ("TOREN_REVISION", &self.revision),
+        ];
+        for (key, value) in optional {
+            if let Some(v) = value {
+                env.push((key, v.clone()));
+            }
+        }
+        if let Some(pushed) = self.pushed {
+            env.push(("TOREN_PUSHED", pushed.to_string()));
+        }
+        if let Some(recreated) = self.workspace_recreated {
+            env.push(("TOREN_WORKSPACE_RECREATED", recreated.to_string()));
+        }
+        env
+    }
+}
+
+// ==================== Observers ====================
+
+/// In-process observer of assignment lifecycle events.
+///
+/// Observers are called synchronously on the thread performing the
+/// operation, after it succeeded and before the user hook runs, so they
+/// should return quickly. All methods default to doing nothing.
+pub trait LifecycleObserver: Send + Sync {
+    /// An assignment was created and its workspace set up
+    fn on_create(&self, _payload: &HookPayload) {}
+    /// An assignment was completed and its workspace cleaned up
+    fn on_complete(&self, _payload: &HookPayload) {}
+    /// An assignment was aborted and its workspace cleaned up
+    fn on_abort(&self, _payload: &HookPayload) {}
+    /// An assignment was prepared for resuming
+    fn on_resume(&self, _payload: &HookPayload) {}
+}
+
+/// Handle for removing a registered observer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObserverId(u64);
+
+type Observers = Vec<(ObserverId, Arc<dyn LifecycleObserver>)>;
+
+static OBSERVERS: RwLock<Observers> = RwLock::new(Vec::new());
+static NEXT_OBSERVER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Register an observer for lifecycle events in this process.
+pub fn register_observer(observer: Arc<dyn LifecycleObserver>) -> ObserverId {
+    let id = ObserverId(NEXT_OBSERVER_ID.fetch_add(1, Ordering::Relaxed));
+    OBSERVERS.write().unwrap().push((id, observer));
+    id
+}
+
+/// Remove an observer. Returns false if it wasn't registered.
+pub fn unregister_observer(id: ObserverId) -> bool {
+    let mut observers = OBSERVERS.write().unwrap();
+    let before = observers.len();
+    observers.retain(|(existing, _)| *existing != id);
+    observers.len() != before
+}
+
+/// An owned lifecycle event, as delivered by [`subscribe`].
+#[derive(Debug, Clone, Serialize)]
+pub struct LifecycleEvent {
+    pub event: HookEvent,
+    pub assignment: Assignment,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pushed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_recreated: Option<bool>,
+}
+
+impl From<&HookPayload<'_>> for LifecycleEvent {
+    fn from(payload: &HookPayload) -> Self {
+        Self {
+            event: payload.event,
+            assignment: payload.assignment.clone(),
+            revision: payload.revision.clone(),
+            pushed: payload.pushed,
+            workspace_recreated: payload.workspace_recreated,
+        }
+    }
+}
+
+struct ChannelObserver(mpsc::Sender<LifecycleEvent>);
+
+impl ChannelObserver {
+    fn send(&self, payload: &HookPayload) {
+        // A dropped receiver just stops receiving; unregistering is the caller's job
+        let _ = self.0.send(payload.into());
+    }
+}
+
+impl LifecycleObserver for ChannelObserver {
+    fn on_create(&self, payload: &HookPayload) {
+        self.send(payload)
+    }
+    fn on_complete(&self, payload: &HookPayload) {
+        self.send(payload)
+    }
+    fn on_abort(&self, payload: &HookPayload) {
+        self.send(payload)
+    }
+    fn on_resume(&self, payload: &HookPayload) {
+        self.send(payload)
+    }
+}
+
+/// Receive every lifecycle event in this process on a channel.
+pub fn subscribe() -> (ObserverId, mpsc::Receiver<LifecycleEvent>) {
+    let (tx, rx) = mpsc::channel();
+    (register_observer(Arc::new(ChannelObserver(tx))), rx)
+}
+
+/// Report a lifecycle event: notify registered observers, then run the
+/// user hook for it.
+pub fn emit(payload: &HookPayload) {
+    // Snapshot so observers can (un)register without deadlocking
+    let observers: Vec<_> = OBSERVERS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(_, observer)| observer.clone())
+        .collect();
+    for observer in observers {
+        match payload.event {
+            HookEvent::Assign => observer.on_create(payload),
+            HookEvent::Complete => observer.on_complete(payload),
+            HookEvent::Abort => observer.on_abort(payload),
+            HookEvent::Resume => observer.on_resume(payload),
+        }
+    }
+    run_hook(payload);
+}
+
+/// Default directory containing user hook scripts: `~/.config/toren/hooks`.
+pub fn hooks_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config/toren/hooks"))
+}
+
+/// Run the user hook for `payload.event` from the default hooks directory.
+pub fn run_hook(payload: &HookPayload) {
+    if let Some(dir) = hooks_dir() {
+        run_hook_in(&dir, payload);
+    }
+}
+
+/// Run the user hook for `payload.event` from `dir`, if one exists.
+///
+/// Returns the hook's exit status when it ran. Hook stdout is redirected to
+/// stderr so it never interferes with structured CLI output.
+pub fn run_hook_in(dir: &Path, payload: &HookPayload) -> Option<std::process::ExitStatus> {
+    let script = dir.join(payload.event.as_str());
+    if !is_executable(&script) {
+        return None;
+    }
+
+    let json = match serde_json::to_vec(payload) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::warn!("Failed to serialize {} hook payload: {}", payload.event, e);
+            return None;
+        }
+    };
+
+    let mut cmd = Command::new(&script);
+    cmd.envs(payload.env())
+        .stdin(Stdio::piped())
+        .stdout(std::io::stderr())
+        .stderr(Stdio::inherit());
+    if payload.assignment.workspace_path.is_dir() {
+        cmd.current_dir(&payload.assignment.workspace_path);
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            tracing::warn!("Failed to run {} hook {}: {}", payload.event, script.display(), e);
+            return None;
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores stdin may exit before we finish writing; that's fine.
+        let _ = stdin.write_all(&json);
+    }
+
+    match child.wait() {
+        Ok(status) => {
+            if !status.success() {
+                tracing::warn!("{} hook exited with {}", payload.event, status);
+            }
+            Some(status)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to wait for {} hook: {}", payload.event, e);
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssignmentSource, AssignmentStatus};
+    use std::os::unix::fs::PermissionsExt;
+
+    fn test_assignment(workspace_path: PathBuf) -> Assignment {
+        let now = chrono::Utc::now().to_rfc3339();
+        Assignment {
+            id: "abc123".to_string(),
+            ancillary_id: "Toren One".parse().unwrap(),
+            task_id: Some("bd-42".parse().unwrap()),
+            segment: "toren".to_string(),
+            workspace_path,
+            source: AssignmentSource::Reference,
+            status: AssignmentStatus::Active,
+            created_at: now.clone(),
+            updated_at: now,
+            task_title: Some("Fix the thing".to_string()),
+            task_url: None,
+            task_source: Some("beads".to_string()),
+            session_id: None,
+            ancillary_num: Some(1),
+            base_branch: None,
+            labels: Vec::new(),
+            focus_path: None,
+            intent: None,
+            resume_instruction: None,
+            owner: None,
+            notes: Vec::new(),
+            after: None,
+            group: None,
+            companions: Vec::new(),
+            prompt_amended_at: None,
+            usage: None,
+        }
+    }
+
+    fn write_hook(dir: &Path, name: &str, body: &str) {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_env_includes_task_fields() {
+        let assignment = test_assignment(PathBuf::from("/tmp/ws/toren/one"));
+        let mut payload = HookPayload::new(HookEvent::Complete, &assignment);
+        payload.revision = Some("deadbeef".to_string());
+        let env: std::collections::HashMap<_, _> = payload.env().into_iter().collect();
+
+        assert_eq!(env["TOREN_EVENT"], "complete");
+        assert_eq!(env["TOREN_WORKSPACE"], "one");
+        assert_eq!(env["TOREN_TASK_ID"], "bd-42");
+        assert_eq!(env["TOREN_REVISION"], "deadbeef");
+        assert!(!env.contains_key("TOREN_TASK_URL"));
+        assert!(!env.contains_key("TOREN_PUSHED"));
+    }
+
+    #[test]
+    fn test_run_hook_receives_env_and_stdin() {
+        let hooks = tempfile::tempdir().unwrap();
+        let ws = tempfile::tempdir().unwrap();
+        let out = ws.path().join("out");
+        write_hook(
+            hooks.path(),
+            "assign",
+            &format!(
+                "echo \"$TOREN_EVENT $TOREN_ANCILLARY_ID\" > {0}; cat >> {0}",
+                out.display()
+            ),
+        );
+
+        let assignment = test_assignment(ws.path().to_path_buf());
+        let payload = HookPayload::new(HookEvent::Assign, &assignment);
+        let status = run_hook_in(hooks.path(), &payload).expect("hook should run");
+        assert!(status.success());
+
+        let written = std::fs::read_to_string(&out).unwrap();
+        let (first, json) = written.split_once('\n').unwrap();
+        assert_eq!(first, "assign Toren One");
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value["event"], "assign");
+        assert_eq!(value["assignment"]["task_id"], "bd-42");
+    }
+
+    #[test]
+    fn test_missing_or_non_executable_hook_is_skipped() {
+        let hooks = tempfile::tempdir().unwrap();
+        std::fs::write(hooks.path().join("abort"), "#!/bin/sh\nexit 0\n").unwrap();
+
+        let assignment = test_assignment(PathBuf::from("/nonexistent"));
+        let abort = HookPayload::new(HookEvent::Abort, &assignment);
+        assert!(run_hook_in(hooks.path(), &abort).is_none());
+        let resume = HookPayload::new(HookEvent::Resume, &assignment);
+        assert!(run_hook_in(hooks.path(), &resume).is_none());
+    }
+
+    #[test]
+    fn test_observers_receive_emitted_events() {
+        struct Aborts(std::sync::Mutex<Vec<String>>);
+        impl LifecycleObserver for Aborts {
+            fn on_abort(&self, payload: &HookPayload) {
+                self.0.lock().unwrap().push(payload.assignment.id.clone());
+            }
+        }
+
+        let mut assignment = test_assignment(PathBuf::from("/nonexistent"));
+        assignment.id = "observed".to_string();
+        let aborts = Arc::new(Aborts(Default::default()));
+        let id = register_observer(aborts.clone());
+        let (sub, rx) = subscribe();
+
+        emit(&HookPayload::new(HookEvent::Assign, &assignment));
+        emit(&HookPayload::new(HookEvent::Abort, &assignment));
+        assert!(unregister_observer(id));
+        assert!(!unregister_observer(id));
+        emit(&HookPayload::new(HookEvent::Abort, &assignment));
+
+        // Other tests may emit concurrently; only count this assignment
+        let seen = aborts.0.lock().unwrap().iter().filter(|a| *a == "observed").count();
+        assert_eq!(seen, 1);
+        let events: Vec<HookEvent> = rx
+            .try_iter()
+            .filter(|e| e.assignment.id == "observed")
+            .map(|e| e.event)
+            .collect();
+        assert_eq!(events, [HookEvent::Assign, HookEvent::Abort, HookEvent::Abort]);
+        unregister_observer(sub);
+    }
+
+    #[test]
+    fn test_failing_hook_reports_status() {
+        let hooks = tempfile::tempdir().unwrap();
+        write_hook(hooks.path(), "complete", "exit 3");
+
+        let assignment = test_assignment(PathBuf::from("/nonexistent"));
+        let payload = HookPayload::new(HookEvent::Complete, &assignment);
+        let status = run_hook_in(hooks.path(), &payload).expect("hook should run");
+        assert_eq!(status.code(), Some(3));
+    }
+}
@@ -0,0 +1,196 @@
+//! Typed identifiers for ancillaries and tasks.
+//!
+//! An ancillary ID ("Toren One") names a workspace slot of a segment; a task
+//! ID ("breq-a1b2") names an item in a task tracker. Both are strings on the
+//! wire and on disk, but distinct types in code, so passing one where the
+//! other is expected doesn't compile. Deserialization accepts any string, so
+//! records written before validation existed still load; [`AncillaryId::parse`]
+//! and [`TaskId::parse`] validate user input.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use crate::naming::{slot_name, slot_number};
+
+macro_rules! string_id {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                Self::parse(s)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> String {
+                id.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<String> for $name {
+            fn eq(&self, other: &String) -> bool {
+                &self.0 == other
+            }
+        }
+    };
+}
+
+/// An ancillary's name: the capitalized segment and the number in words
+/// ("Toren One", "Toren Twenty-One"), or digits past the word range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AncillaryId(String);
+
+string_id!(AncillaryId);
+
+impl AncillaryId {
+    /// The ancillary numbered `number` in `segment`, named from the
+    /// segment's vocabulary if it has one (see [`crate::naming`]).
+    pub fn new(segment: &str, number: u32) -> Self {
+        Self(format!("{} {}", capitalize(segment), slot_name(segment, number)))
+    }
+
+    /// Parse `<segment> <number>`, with the number in words or digits or as
+    /// a name from the segment's vocabulary, into its canonical form
+    /// ("toren 21" -> "Toren Twenty-One").
+    pub fn parse(s: &str) -> Result<Self> {
+        let Some((segment, number)) = s.trim().rsplit_once(' ') else {
+            anyhow::bail!("Invalid ancillary ID '{}': expected `<segment> <number>`, e.g. \"Toren One\"", s);
+        };
+        let segment = segment.trim();
+        let Some(number) = slot_number(segment, number) else {
+            anyhow::bail!("Invalid ancillary ID '{}': '{}' is not a number or one of the segment's ancillary names", s, number);
+        };
+        if segment.is_empty() || segment.contains(char::is_whitespace) {
+            anyhow::bail!("Invalid ancillary ID '{}': the segment must be one word", s);
+        }
+        Ok(Self::new(segment, number))
+    }
+
+    /// An ID as stored, without validation (e.g. a reference that names no
+    /// ancillary, which can then match nothing).
+    pub(crate) fn unchecked(s: impl Into<String>) -> Self {
+        Self(s.into())
+    }
+
+    /// The ancillary number ("Toren One" -> 1).
+    pub fn number(&self) -> Option<u32> {
+        let segment = self.segment()?;
+        self.0.split_whitespace().last().and_then(|word| slot_number(&segment, word))
+    }
+
+    /// The segment, lowercased ("Toren One" -> "toren").
+    pub fn segment(&self) -> Option<String> {
+        self.0.split_whitespace().next().map(|s| s.to_lowercase())
+    }
+
+    /// Whether this names the same ancillary as `other`, ignoring case.
+    pub fn matches(&self, other: &AncillaryId) -> bool {
+        self.0.to_lowercase() == other.0.to_lowercase()
+    }
+}
+
+/// A task tracker item's ID ("breq-a1b2", "proj-123", "42").
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TaskId(String);
+
+string_id!(TaskId);
+
+impl TaskId {
+    /// Validate a task ID: non-empty, without whitespace.
+    pub fn parse(s: &str) -> Result<Self> {
+        if s.is_empty() || s.contains(char::is_whitespace) {
+            anyhow::bail!("Invalid task ID '{}': expected one word, e.g. \"breq-a1b2\"", s);
+        }
+        Ok(Self(s.to_string()))
+    }
+
+    /// An ID as stored or returned by a task plugin, without validation.
+    pub(crate) fn unchecked(s: impl Into<String>) -> Self {
+        Self(s.into())
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_parse_and_canonicalize() {
+        let id = AncillaryId::parse("toren 21").unwrap();
+        assert_eq!(id, "Toren Twenty-One");
+        assert_eq!(id.number(), Some(21));
+        assert_eq!(id.segment().as_deref(), Some("toren"));
+        assert!(id.matches(&AncillaryId::unchecked("TOREN TWENTY-ONE")));
+        assert!(AncillaryId::parse("Toren").is_err());
+        assert!(AncillaryId::parse("Toren Foo").is_err());
+        assert!(AncillaryId::parse("my repo One").is_err());
+
+        assert_eq!(TaskId::parse("breq-a1b2").unwrap().to_string(), "breq-a1b2");
+        assert!(TaskId::parse("").is_err());
+        assert!(TaskId::parse("two words").is_err());
+
+        // Both stay plain strings on the wire
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"Toren Twenty-One\"");
+        let task: TaskId = serde_json::from_str("\"bd-1\"").unwrap();
+        assert_eq!(task, "bd-1");
+    }
+}
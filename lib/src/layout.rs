@@ -0,0 +1,278 @@
+//! Terminal layouts for `breq go --layout` (`.toren/layout.kdl`).
+//!
+//! A repo can describe the panes a workspace should open with, each with a
+//! name, an optional command and working directory:
+//!
+//! ```kdl
+//! backend "tmux"                 // or "zellij"; detected if omitted
+//! pane "editor" "nvim ."
+//! pane "server" "npm run dev" cwd="web"
+//! pane "shell"
+//! ```
+//!
+//! The layout is opened as a tmux or zellij session named after the segment
+//! and workspace. An existing session is attached as-is, so leaving and
+//! re-entering a workspace keeps its running panes.
+
+use anyhow::{Context, Result};
+use kdl::{KdlDocument, KdlNode};
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+/// Layout file, relative to the workspace.
+pub const LAYOUT_FILE: &str = ".toren/layout.kdl";
+
+/// Terminal multiplexer that hosts a layout session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Zellij,
+}
+
+impl Multiplexer {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "tmux" => Ok(Multiplexer::Tmux),
+            "zellij" => Ok(Multiplexer::Zellij),
+            other => anyhow::bail!("unknown layout backend '{}' (expected tmux or zellij)", other),
+        }
+    }
+
+    pub fn binary(&self) -> &'static str {
+        match self {
+            Multiplexer::Tmux => "tmux",
+            Multiplexer::Zellij => "zellij",
+        }
+    }
+
+    /// The multiplexer the current terminal runs in, else the first one
+    /// installed (tmux, then zellij).
+    pub fn detect() -> Option<Self> {
+        if std::env::var_os("TMUX").is_some() {
+            return Some(Multiplexer::Tmux);
+        }
+        if std::env::var_os("ZELLIJ").is_some() {
+            return Some(Multiplexer::Zellij);
+        }
+        [Multiplexer::Tmux, Multiplexer::Zellij]
+            .into_iter()
+            .find(|m| which::which(m.binary()).is_ok())
+    }
+}
+
+impl fmt::Display for Multiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.binary())
+    }
+}
+
+/// One pane of a layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pane {
+    pub name: String,
+    /// Run in the pane's shell; a plain shell if unset
+    pub command: Option<String>,
+    /// Working directory relative to the workspace
+    pub cwd: Option<PathBuf>,
+}
+
+/// A parsed `.toren/layout.kdl`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout {
+    pub backend: Option<Multiplexer>,
+    pub panes: Vec<Pane>,
+}
+
+impl Layout {
+    /// The workspace's layout, or None if it has no layout file.
+    pub fn load(workspace_path: &Path) -> Result<Option<Self>> {
+        let path = workspace_path.join(LAYOUT_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Self::parse(&content)
+            .with_context(|| format!("Invalid {}", path.display()))
+            .map(Some)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let doc: KdlDocument = content.parse()?;
+        let mut layout = Layout::default();
+        for node in doc.nodes() {
+            match node.name().value() {
+                "backend" => {
+                    let name = string_args(node)?;
+                    let [name] = name.as_slice() else {
+                        anyhow::bail!("backend: expected one name, e.g. `backend \"tmux\"`");
+                    };
+                    layout.backend = Some(Multiplexer::parse(name)?);
+                }
+                "pane" => layout.panes.push(parse_pane(node)?),
+                other => anyhow::bail!("unknown node '{}' (expected backend or pane)", other),
+            }
+        }
+        if layout.panes.is_empty() {
+            anyhow::bail!("no panes; add e.g. `pane \"shell\"`");
+        }
+        Ok(layout)
+    }
+
+    /// The multiplexer to open the layout in.
+    pub fn multiplexer(&self) -> Result<Multiplexer> {
+        self.backend
+            .or_else(Multiplexer::detect)
+            .context("Neither tmux nor zellij is installed; install one or set `backend` in the layout")
+    }
+
+    /// tmux arguments that create the session detached, open every pane
+    /// (tiled) and attach to it, or switch to it when `nested` in tmux.
+    pub fn tmux_args(
+        &self,
+        session: &str,
+        workspace_path: &Path,
+        env: &[(String, String)],
+        nested: bool,
+    ) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        for (i, pane) in self.panes.iter().enumerate() {
+            let dir = pane_dir(pane, workspace_path);
+            if i == 0 {
+                args.extend(["new-session", "-d", "-s", session].map(String::from));
+            } else {
+                args.extend([";", "split-window", "-t", session].map(String::from));
+            }
+            args.extend(["-c".to_string(), dir.display().to_string()]);
+            for (key, value) in env {
+                args.extend(["-e".to_string(), format!("{}={}", key, value)]);
+            }
+            args.extend([";", "select-pane", "-t", session, "-T", &pane.name].map(String::from));
+            if let Some(ref command) = pane.command {
+                args.extend([";", "send-keys", "-t", session, command, "Enter"].map(String::from));
+            }
+            args.extend([";", "select-layout", "-t", session, "tiled"].map(String::from));
+        }
+        let attach = if nested { "switch-client" } else { "attach-session" };
+        args.extend([";", attach, "-t", session].map(String::from));
+        args
+    }
+
+    /// A zellij layout document with one pane per layout pane.
+    pub fn zellij_layout(&self, workspace_path: &Path) -> String {
+        let mut out = String::from("layout {\n");
+        for pane in &self.panes {
+            let dir = pane_dir(pane, workspace_path);
+            let _ = write!(
+                out,
+                "    pane name={} cwd={}",
+                kdl_string(&pane.name),
+                kdl_string(&dir.display().to_string())
+            );
+            match pane.command {
+                Some(ref command) => {
+                    let _ = writeln!(out, " command=\"sh\" {{");
+                    let _ = writeln!(out, "        args \"-c\" {}", kdl_string(command));
+                    out.push_str("    }\n");
+                }
+                None => out.push('\n'),
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Session name for a workspace's layout (`toren-<segment>-<workspace>`),
+/// limited to characters tmux and zellij accept.
+pub fn session_name(segment: &str, workspace: &str) -> String {
+    format!("toren-{}-{}", segment, workspace)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect()
+}
+
+fn parse_pane(node: &KdlNode) -> Result<Pane> {
+    if node.children().is_some() {
+        anyhow::bail!("pane: child blocks are not supported");
+    }
+    let args = string_args(node)?;
+    let (name, command) = match args.as_slice() {
+        [name] => (name.clone(), None),
+        [name, command] => (name.clone(), Some(command.clone())),
+        _ => anyhow::bail!("pane: expected a name and optional command, e.g. `pane \"server\" \"npm run dev\"`"),
+    };
+    let mut cwd = None;
+    for entry in node.entries() {
+        let Some(key) = entry.name() else { continue };
+        match (key.value(), entry.value().as_string()) {
+            ("cwd", Some(dir)) => cwd = Some(PathBuf::from(dir)),
+            (key, _) => anyhow::bail!("pane '{}': unknown property '{}' (expected cwd=\"...\")", name, key),
+        }
+    }
+    Ok(Pane { name, command, cwd })
+}
+
+/// The positional string arguments of a node.
+fn string_args(node: &KdlNode) -> Result<Vec<String>> {
+    node.entries()
+        .iter()
+        .filter(|e| e.name().is_none())
+        .map(|e| {
+            e.value()
+                .as_string()
+                .map(String::from)
+                .with_context(|| format!("{}: arguments must be strings", node.name().value()))
+        })
+        .collect()
+}
+
+fn pane_dir(pane: &Pane, workspace_path: &Path) -> PathBuf {
+    match pane.cwd {
+        Some(ref cwd) => workspace_path.join(cwd),
+        None => workspace_path.to_path_buf(),
+    }
+}
+
+fn kdl_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_parses_and_renders_for_both_backends() {
+        let layout = Layout::parse(
+            "backend \"zellij\"\npane \"editor\" \"nvim .\"\npane \"server\" \"npm run \\\"dev\\\"\" cwd=\"web\"\npane \"shell\"\n",
+        )
+        .unwrap();
+        assert_eq!(layout.backend, Some(Multiplexer::Zellij));
+        assert_eq!(layout.panes.len(), 3);
+        assert_eq!(layout.panes[1].cwd.as_deref(), Some(Path::new("web")));
+        assert_eq!(layout.panes[2].command, None);
+
+        let ws = Path::new("/ws/one");
+        let zellij = layout.zellij_layout(ws);
+        assert!(zellij.contains("pane name=\"server\" cwd=\"/ws/one/web\" command=\"sh\" {"));
+        assert!(zellij.contains("args \"-c\" \"npm run \\\"dev\\\"\""));
+        assert!(zellij.contains("pane name=\"shell\" cwd=\"/ws/one\"\n"));
+
+        let env = vec![("PORT".to_string(), "3001".to_string())];
+        let tmux = layout.tmux_args("toren-web-one", ws, &env, false).join(" ");
+        assert!(tmux.starts_with("new-session -d -s toren-web-one -c /ws/one -e PORT=3001 ;"));
+        assert!(tmux.contains("; split-window -t toren-web-one -c /ws/one/web -e PORT=3001 ;"));
+        assert!(tmux.contains("; send-keys -t toren-web-one nvim . Enter ;"));
+        assert!(tmux.ends_with("; attach-session -t toren-web-one"));
+
+        assert_eq!(session_name("my.repo", "one"), "toren-my-repo-one");
+        assert!(Layout::parse("pane \"a\" size=3\n").is_err());
+        assert!(Layout::parse("backend \"screen\"\npane \"a\"\n").is_err());
+        assert!(Layout::parse("").is_err());
+    }
+}
@@ -1,39 +1,84 @@
 pub mod agent;
+pub mod agent_context;
 pub mod alias;
+pub mod artifacts;
 pub mod assignment;
 pub mod assignment_ops;
+pub mod assignment_store;
+pub mod claude_session;
 pub mod composite_status;
 pub mod config;
+pub mod config_merge;
+pub mod context_cache;
+pub mod disk_usage;
+pub mod doctor;
+pub mod editor;
+pub mod envrc;
+pub mod error;
+pub mod gc;
+pub mod health;
+pub mod hooks;
+pub mod ids;
+pub mod layout;
+pub mod naming;
+pub mod notifications;
 pub mod plugins;
+pub mod pool;
+pub mod ownership;
+pub mod ports;
 pub mod process;
+pub mod proxy_domains;
+pub mod proxy_routes;
+pub mod push_checks;
+pub mod reaper;
+pub mod report;
+pub mod schedule;
 pub mod segments;
+pub mod session;
+pub mod setup_manifest;
+pub mod task_comments;
 pub mod tasks;
+pub mod undo;
+pub mod usage;
 pub mod workspace;
 pub mod workspace_setup;
 
 pub use assignment::{
     ancillary_id, ancillary_number, ancillary_segment, number_to_word, word_to_number, Assignment,
-    AssignmentManager, AssignmentRef, AssignmentSource, AssignmentStatus, CompletionReason,
-    CompletionRecord,
+    AssignmentManager, AssignmentNote, AssignmentQuery, AssignmentRef, AssignmentSort, AssignmentSource,
+    AssignmentSourceKind, AssignmentStatus, CompanionWorkspace, CompletionReason, CompletionRecord, normalize_focus_path,
+    normalize_workspace_name, workspace_name,
 };
 pub use assignment_ops::{
-    abort_assignment, clean_assignment, complete_assignment, prepare_resume,
-    render_auto_commit_message, AbortOptions, CleanOptions, CleanResult,
-    CompleteOptions, CompleteResult, ResumeOptions, ResumeResult,
-    DEFAULT_AUTO_COMMIT_MESSAGE,
+    abort_assignment, amend_prompt, auto_finalize_assignment, claim_for_assignment, clean_assignment,
+    complete_assignment, prepare_resume, render_auto_commit_message, render_claim_identity,
+    undo_operation, AbortOptions, AmendOptions, AmendResult, AutoFinalizeOptions, AutoFinalizeResult, CleanOptions,
+    CleanResult, CompleteOptions, CompleteResult, ResumeOptions, ResumeResult, UndoOptions,
+    UndoResult,
+    DEFAULT_AUTO_COMMIT_MESSAGE, DEFAULT_CLAIM_IDENTITY,
 };
-pub use agent::{Agent, AgentKind};
+pub use agent::{Agent, AgentCommandConfig, AgentKind};
 pub use composite_status::CompositeStatus;
-pub use config::{Config, AncillariesConfig, IntentsConfig, TasksConfig, expand_path, expand_path_str, tilde_shorten, toren_root};
+pub use health::{AssignmentHealth, HealthState};
+pub use ids::{AncillaryId, TaskId};
+pub use hooks::{HookEvent, HookPayload, LifecycleEvent, LifecycleObserver};
+pub use config::{Config, AncillariesConfig, FilesystemConfig, IntentAutoConfig, IntentsConfig, PermissionPolicy, PushConfig, ListColumn, SegmentOverride, SegmentsConfig, StatusConfig, TasksConfig, UiConfig, expand_path, expand_path_str, tilde_shorten, toren_root};
 pub use plugins::{DeferredAction, PluginContext, PluginManager, PluginMeta, PluginResult};
-pub use segments::{Segment, SegmentManager};
-pub use tasks::{generate_prompt, infer_task_fields, InferredTaskFields, ResolvedTask};
+pub use schedule::{Schedule, ScheduleManager};
+pub use segments::{DiscoveredDir, Discovery, Segment, SegmentManager, SegmentSettings};
+pub use error::{ErrorCode, TorenError};
+pub use tasks::{generate_prompt, infer_task_fields, is_ready, rank_ready, InferredTaskFields, ResolvedTask, TaskDep};
 pub use workspace::{
-    CleanupMode, CommitInfo, GitWorktreeBackend, JjBackend, RepoType, VcsBackend, WorkspaceManager,
-    detect_repo_type,
+    CleanupMode, CommitInfo, CreateStage, DiffOptions, GitWorktreeBackend, JjBackend, RepoType, VcsBackend,
+    WorkspaceConflicted, WorkspaceCreateFailure, WorkspaceManager,
+    detect_repo_type, workspace_diff,
 };
+pub use pool::{allocate_ancillary, PoolExhausted, PoolSlot};
+pub use ownership::{AssignmentOwned, AssignmentOwner};
 pub use process::{ProcessInfo, WorkspaceProcessesRunning};
 pub use workspace_setup::{
-    render_template, BreqConfig, SetupResult, TaskInfo,
-    WorkspaceContext, WorkspaceInfo, WorkspaceSetup, RepoInfo,
+    read_workspace_env, render_template, with_repo_prompt, ActionFailure, BreqConfig, PromptPart, SetupEvent, SetupProgress,
+    SetupResult, TaskInfo,
+    VariantInfo, AssignmentInfo, WorkspaceContext, WorkspaceFacts, WorkspaceInfo, WorkspaceSetup, RepoInfo,
+    WORKSPACE_ENV_FILE,
 };
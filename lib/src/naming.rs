@@ -0,0 +1,146 @@
+//! Ancillary names per segment.
+//!
+//! Ancillaries are numbered slots within a segment ("Toren One", workspace
+//! `one`). A segment can name its slots from a vocabulary instead, set in
+//! `[segments.names]`: `toren = ["Aleph", "Bet", "Gimel"]` makes slot 1
+//! "Toren Aleph" in workspace `aleph`. Slots past the end of the vocabulary
+//! fall back to number words ("Toren Four").
+//!
+//! The vocabularies are process-wide: breq and the daemon install them with
+//! [`set_pools`] when they load their config. Number words are still accepted
+//! for every slot, so "toren one" names "Toren Aleph".
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::assignment::{number_to_word, word_to_number};
+
+static POOLS: RwLock<BTreeMap<String, Vec<String>>> = RwLock::new(BTreeMap::new());
+
+/// Install the per-segment vocabularies from `[segments.names]`, replacing
+/// any installed before. Fails without installing any if a vocabulary is
+/// invalid (see [`validate_pool`]).
+pub fn set_pools(pools: &HashMap<String, Vec<String>>) -> Result<()> {
+    let mut installed = BTreeMap::new();
+    for (segment, names) in pools {
+        validate_pool(segment, names)?;
+        installed.insert(segment.to_lowercase(), names.clone());
+    }
+    *POOLS.write().unwrap() = installed;
+    Ok(())
+}
+
+/// Check a segment's vocabulary: each name one word, unique ignoring case,
+/// and not a number word or digits, which would name a numbered slot.
+pub fn validate_pool(segment: &str, names: &[String]) -> Result<()> {
+    let mut seen = HashSet::new();
+    for name in names {
+        if name.is_empty() || name.contains(char::is_whitespace) || name.contains('/') {
+            anyhow::bail!("segments.names.{}: '{}' must be one word", segment, name);
+        }
+        if word_to_number(name).is_some() {
+            anyhow::bail!("segments.names.{}: '{}' is a number, which names another slot", segment, name);
+        }
+        if !seen.insert(name.to_lowercase()) {
+            anyhow::bail!("segments.names.{}: '{}' appears more than once", segment, name);
+        }
+    }
+    Ok(())
+}
+
+fn with_pool<T>(segment: &str, f: impl FnOnce(&[String]) -> T) -> T {
+    let pools = POOLS.read().unwrap();
+    f(pools.get(&segment.to_lowercase()).map(Vec::as_slice).unwrap_or_default())
+}
+
+/// Name of slot `n` in a vocabulary, or its number word past the end.
+fn pool_name(pool: &[String], n: u32) -> String {
+    n.checked_sub(1)
+        .and_then(|i| pool.get(i as usize))
+        .cloned()
+        .unwrap_or_else(|| number_to_word(n))
+}
+
+/// Slot named `word` in a vocabulary (ignoring case), or its number.
+fn pool_number(pool: &[String], word: &str) -> Option<u32> {
+    pool.iter()
+        .position(|name| name.eq_ignore_ascii_case(word))
+        .map(|i| i as u32 + 1)
+        .or_else(|| word_to_number(word))
+}
+
+/// Name of slot `n` in `segment` ("Aleph", or "Four" past its vocabulary).
+pub fn slot_name(segment: &str, n: u32) -> String {
+    with_pool(segment, |pool| pool_name(pool, n))
+}
+
+/// Slot of a name or number word in `segment` ("aleph" -> 1, "four" -> 4).
+pub fn slot_number(segment: &str, word: &str) -> Option<u32> {
+    with_pool(segment, |pool| pool_number(pool, word))
+}
+
+/// Workspace directory name of slot `n` in `segment` ("aleph", "four").
+pub fn workspace_name(segment: &str, n: u32) -> String {
+    slot_name(segment, n).to_lowercase()
+}
+
+/// Canonical workspace name in `segment` for a user-supplied name or number:
+/// "1", "one" and "Aleph" all give "aleph" when slot 1 is Aleph. Other names
+/// are lowercased.
+pub fn normalize_workspace_name(segment: &str, name: &str) -> String {
+    match slot_number(segment, name) {
+        Some(n) => workspace_name(segment, n),
+        None => name.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::AncillaryId;
+
+    fn pool(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_pool_maps_names_to_slots_both_ways() {
+        let greek = pool(&["Alpha", "Beta", "Gamma"]);
+        assert_eq!(pool_name(&greek, 1), "Alpha");
+        assert_eq!(pool_name(&greek, 3), "Gamma");
+        // Past the vocabulary: number words
+        assert_eq!(pool_name(&greek, 4), "Four");
+        assert_eq!(pool_name(&greek, 0), "Zero");
+        assert_eq!(pool_number(&greek, "beta"), Some(2));
+        assert_eq!(pool_number(&greek, "one"), Some(1));
+        assert_eq!(pool_number(&greek, "Four"), Some(4));
+        assert_eq!(pool_number(&greek, "Delta"), None);
+        for n in 1..=10 {
+            assert_eq!(pool_number(&greek, &pool_name(&greek, n)), Some(n));
+        }
+    }
+
+    #[test]
+    fn test_validate_pool() {
+        assert!(validate_pool("ships", &pool(&["Enterprise", "Defiant"])).is_ok());
+        assert!(validate_pool("ships", &pool(&["Deep Space"])).is_err());
+        assert!(validate_pool("ships", &pool(&["Two"])).is_err());
+        assert!(validate_pool("ships", &pool(&["Voyager", "voyager"])).is_err());
+    }
+
+    #[test]
+    fn test_installed_pool_names_ancillaries() {
+        // A segment no other test uses, since pools are process-wide
+        let pools = HashMap::from([("Hebrew".to_string(), pool(&["Aleph", "Bet"]))]);
+        set_pools(&pools).unwrap();
+
+        let id = AncillaryId::new("hebrew", 2);
+        assert_eq!(id.as_str(), "Hebrew Bet");
+        assert_eq!(id.number(), Some(2));
+        assert_eq!(AncillaryId::parse("hebrew one").unwrap().as_str(), "Hebrew Aleph");
+        assert_eq!(AncillaryId::new("hebrew", 3).as_str(), "Hebrew Three");
+        assert_eq!(normalize_workspace_name("hebrew", "1"), "aleph");
+        assert_eq!(workspace_name("other", 1), "one");
+    }
+}
@@ -0,0 +1,406 @@
+//! Notifications about assignment events (`[notifications]`).
+//!
+//! Agents run for a long time, often while nobody is watching the terminal
+//! or the web UI. The daemon sends a short [`Notification`] to each
+//! configured target when work finishes or fails, when an agent is waiting
+//! for someone to approve a tool use, and when a workspace's setup fails.
+//! Targets are a generic JSON webhook, an [ntfy](https://ntfy.sh) topic or a
+//! Slack-compatible incoming webhook. A failed delivery is retried with
+//! exponential backoff, then logged and dropped; notifications never fail
+//! the operation they report. `breq notify-test` sends a test notification
+//! to every target.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::config::NotificationsConfig;
+use crate::Assignment;
+
+/// Delay before the first retry; doubled for each one after.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Per-request timeout.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An event that can be notified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    /// An agent's work session ended
+    WorkCompleted,
+    /// An agent's work session failed
+    WorkFailed,
+    /// An agent is waiting for a tool use to be approved
+    AwaitingInput,
+    /// A workspace setup action (toren.kdl) failed
+    SetupFailed,
+    /// Sent by `breq notify-test`, whatever `events` says
+    Test,
+}
+
+impl NotifyEvent {
+    /// The events notified by default: all of them.
+    pub fn all() -> Vec<NotifyEvent> {
+        vec![
+            NotifyEvent::WorkCompleted,
+            NotifyEvent::WorkFailed,
+            NotifyEvent::AwaitingInput,
+            NotifyEvent::SetupFailed,
+        ]
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            NotifyEvent::WorkCompleted => "work_completed",
+            NotifyEvent::WorkFailed => "work_failed",
+            NotifyEvent::AwaitingInput => "awaiting_input",
+            NotifyEvent::SetupFailed => "setup_failed",
+            NotifyEvent::Test => "test",
+        }
+    }
+
+    /// ntfy tag, shown as an emoji
+    fn ntfy_tag(&self) -> &'static str {
+        match self {
+            NotifyEvent::WorkCompleted => "white_check_mark",
+            NotifyEvent::WorkFailed => "x",
+            NotifyEvent::AwaitingInput => "raising_hand",
+            NotifyEvent::SetupFailed => "warning",
+            NotifyEvent::Test => "bell",
+        }
+    }
+
+    fn is_failure(&self) -> bool {
+        matches!(self, NotifyEvent::WorkFailed | NotifyEvent::SetupFailed)
+    }
+}
+
+impl fmt::Display for NotifyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where notifications are sent (`[[notifications.targets]]`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotifyTarget {
+    /// POST the [`Notification`] as JSON
+    Webhook {
+        url: String,
+        /// Extra request headers, e.g. for authentication
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        headers: HashMap<String, String>,
+    },
+    /// Publish to an ntfy topic URL, e.g. `https://ntfy.sh/my-toren`
+    Ntfy {
+        url: String,
+        /// Access token for protected topics
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    /// POST a `{"text": …}` message to a Slack-compatible incoming webhook
+    Slack { url: String },
+}
+
+impl NotifyTarget {
+    fn url(&self) -> &str {
+        match self {
+            NotifyTarget::Webhook { url, .. } | NotifyTarget::Ntfy { url, .. } | NotifyTarget::Slack { url } => url,
+        }
+    }
+}
+
+/// The kind and host of a target; webhook paths often embed secrets.
+impl fmt::Display for NotifyTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            NotifyTarget::Webhook { .. } => "webhook",
+            NotifyTarget::Ntfy { .. } => "ntfy",
+            NotifyTarget::Slack { .. } => "slack",
+        };
+        let url = self.url();
+        let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let host = host.split('/').next().unwrap_or(host);
+        write!(f, "{} ({})", kind, host)
+    }
+}
+
+/// What a notification says. Webhook targets receive it as JSON.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub title: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignment_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ancillary_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+}
+
+impl Notification {
+    pub fn new(event: NotifyEvent, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            event,
+            title: title.into(),
+            message: message.into(),
+            assignment_id: None,
+            ancillary_id: None,
+            segment: None,
+            task_id: None,
+        }
+    }
+
+    /// A notification about an assignment's work: the ancillary in the
+    /// title, its task (or workspace) in the message.
+    pub fn for_assignment(event: NotifyEvent, assignment: &Assignment) -> Self {
+        let title = match event {
+            NotifyEvent::WorkCompleted => format!("{} finished", assignment.ancillary_id),
+            NotifyEvent::WorkFailed => format!("{} failed", assignment.ancillary_id),
+            NotifyEvent::AwaitingInput => format!("{} is waiting for input", assignment.ancillary_id),
+            NotifyEvent::SetupFailed => format!("{}: workspace setup failed", assignment.ancillary_id),
+            NotifyEvent::Test => format!("{}: test notification", assignment.ancillary_id),
+        };
+        let message = match (&assignment.task_id, &assignment.task_title) {
+            (Some(id), Some(title)) => format!("{}: {}", id, title),
+            (Some(id), None) => id.to_string(),
+            (None, Some(title)) => title.clone(),
+            (None, None) => crate::tilde_shorten(&assignment.workspace_path),
+        };
+        Self {
+            assignment_id: Some(assignment.id.clone()),
+            ancillary_id: Some(assignment.ancillary_id.to_string()),
+            segment: Some(assignment.segment.clone()),
+            task_id: assignment.task_id.as_ref().map(|id| id.to_string()),
+            ..Self::new(event, title, message)
+        }
+    }
+}
+
+/// The HTTP request delivering a notification to one target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Delivery {
+    pub fn new(target: &NotifyTarget, notification: &Notification) -> Self {
+        let json = |value: serde_json::Value| value.to_string();
+        let content_type = ("Content-Type".to_string(), "application/json".to_string());
+        match target {
+            NotifyTarget::Webhook { url, headers } => Self {
+                url: url.clone(),
+                headers: std::iter::once(content_type)
+                    .chain(headers.iter().map(|(k, v)| (k.clone(), v.clone())))
+                    .collect(),
+                body: json(serde_json::to_value(notification).unwrap_or_default()),
+            },
+            NotifyTarget::Ntfy { url, token } => {
+                let mut headers = vec![
+                    ("Title".to_string(), notification.title.clone()),
+                    ("Tags".to_string(), notification.event.ntfy_tag().to_string()),
+                ];
+                if notification.event.is_failure() {
+                    headers.push(("Priority".to_string(), "high".to_string()));
+                }
+                if let Some(token) = token {
+                    headers.push(("Authorization".to_string(), format!("Bearer {}", token)));
+                }
+                Self {
+                    url: url.clone(),
+                    headers,
+                    body: notification.message.clone(),
+                }
+            }
+            NotifyTarget::Slack { url } => Self {
+                url: url.clone(),
+                headers: vec![content_type],
+                body: json(serde_json::json!({
+                    "text": format!("*{}*\n{}", notification.title, notification.message),
+                })),
+            },
+        }
+    }
+}
+
+/// Sends notifications to the configured targets.
+pub struct Notifier {
+    config: NotificationsConfig,
+    http: ureq::Agent,
+    backoff: Duration,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationsConfig) -> Self {
+        let http = ureq::Agent::new_with_config(
+            ureq::config::Config::builder()
+                .timeout_global(Some(REQUEST_TIMEOUT))
+                .build(),
+        );
+        Self {
+            config: config.clone(),
+            http,
+            backoff: DEFAULT_BACKOFF,
+        }
+    }
+
+    /// Delay before the first retry (default: 2s), doubled for each one after.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn targets(&self) -> &[NotifyTarget] {
+        &self.config.targets
+    }
+
+    /// Whether `event` should be notified at all.
+    pub fn wants(&self, event: NotifyEvent) -> bool {
+        !self.config.targets.is_empty() && (event == NotifyEvent::Test || self.config.events.contains(&event))
+    }
+
+    /// Send to every target, if the event is wanted. Blocks while retrying;
+    /// failures are logged.
+    pub fn send(&self, notification: &Notification) {
+        if !self.wants(notification.event) {
+            return;
+        }
+        for target in &self.config.targets {
+            if let Err(e) = self.send_to(target, notification) {
+                tracing::warn!("Failed to send {} notification to {}: {:#}", notification.event, target, e);
+            }
+        }
+    }
+
+    /// Send to one target, retrying up to `attempts` times in all.
+    pub fn send_to(&self, target: &NotifyTarget, notification: &Notification) -> Result<()> {
+        let delivery = Delivery::new(target, notification);
+        let attempts = self.config.attempts.max(1);
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match self.deliver(&delivery) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= attempts => {
+                    return Err(e).with_context(|| format!("giving up after {} attempt(s)", attempts))
+                }
+                Err(e) => {
+                    tracing::debug!("Notification to {} failed (attempt {}): {:#}", target, attempt, e);
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn deliver(&self, delivery: &Delivery) -> Result<()> {
+        let mut request = self.http.post(&delivery.url);
+        for (name, value) in &delivery.headers {
+            request = request.header(name, value);
+        }
+        request
+            .send(delivery.body.as_str())
+            .with_context(|| format!("POST {} failed", delivery.url))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(event: NotifyEvent) -> Notification {
+        Notification {
+            ancillary_id: Some("Toren One".to_string()),
+            ..Notification::new(event, "Toren One failed", "bd-7: Fix login")
+        }
+    }
+
+    #[test]
+    fn test_delivery_per_target_kind() {
+        let failed = notification(NotifyEvent::WorkFailed);
+
+        let webhook = NotifyTarget::Webhook {
+            url: "https://example.com/hook".to_string(),
+            headers: HashMap::from([("X-Token".to_string(), "secret".to_string())]),
+        };
+        let delivery = Delivery::new(&webhook, &failed);
+        assert!(delivery.headers.contains(&("X-Token".to_string(), "secret".to_string())));
+        let body: serde_json::Value = serde_json::from_str(&delivery.body).unwrap();
+        assert_eq!(body["event"], "work_failed");
+        assert_eq!(body["ancillary_id"], "Toren One");
+        assert!(body.get("segment").is_none());
+
+        let ntfy = NotifyTarget::Ntfy {
+            url: "https://ntfy.sh/toren".to_string(),
+            token: None,
+        };
+        let delivery = Delivery::new(&ntfy, &failed);
+        assert_eq!(delivery.body, "bd-7: Fix login");
+        assert!(delivery.headers.contains(&("Title".to_string(), "Toren One failed".to_string())));
+        assert!(delivery.headers.contains(&("Priority".to_string(), "high".to_string())));
+        let done = Delivery::new(&ntfy, &notification(NotifyEvent::WorkCompleted));
+        assert!(!done.headers.iter().any(|(name, _)| name == "Priority"));
+
+        let slack = NotifyTarget::Slack {
+            url: "https://hooks.slack.com/services/T0/B0/xyz".to_string(),
+        };
+        let body: serde_json::Value = serde_json::from_str(&Delivery::new(&slack, &failed).body).unwrap();
+        assert_eq!(body["text"], "*Toren One failed*\nbd-7: Fix login");
+        assert_eq!(slack.to_string(), "slack (hooks.slack.com)");
+    }
+
+    #[test]
+    fn test_targets_parse_from_toml() {
+        let config: NotificationsConfig = toml::from_str(
+            r#"
+            events = ["work_failed", "setup_failed"]
+
+            [[targets]]
+            kind = "ntfy"
+            url = "https://ntfy.sh/toren"
+
+            [[targets]]
+            kind = "webhook"
+            url = "http://localhost:9000/toren"
+            headers = { Authorization = "Bearer abc" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.targets.len(), 2);
+        assert_eq!(config.attempts, 3);
+
+        let notifier = Notifier::new(&config);
+        assert!(notifier.wants(NotifyEvent::WorkFailed));
+        assert!(!notifier.wants(NotifyEvent::WorkCompleted));
+        assert!(notifier.wants(NotifyEvent::Test));
+        assert!(!Notifier::new(&NotificationsConfig::default()).wants(NotifyEvent::Test));
+    }
+
+    #[test]
+    fn test_send_to_retries_then_gives_up() {
+        // Nothing listens on port 9 of localhost, so every attempt fails fast
+        let config = NotificationsConfig {
+            attempts: 2,
+            ..Default::default()
+        };
+        let notifier = Notifier::new(&config).with_backoff(Duration::ZERO);
+        let target = NotifyTarget::Slack {
+            url: "http://127.0.0.1:9/hook".to_string(),
+        };
+        let err = notifier
+            .send_to(&target, &notification(NotifyEvent::Test))
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("giving up after 2 attempt(s)"));
+    }
+}
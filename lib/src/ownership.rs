@@ -0,0 +1,114 @@
+//! Which front end is running the agent for an assignment.
+//!
+//! breq and the daemon can both start an agent in the same workspace: breq
+//! execs one in the terminal, the daemon's work manager runs SDK work. Each
+//! records itself as the assignment's [`AssignmentOwner`] before starting, and
+//! resume, assign and complete refuse to act on an assignment another live
+//! process owns unless asked to `--steal` it. An owner whose process has
+//! exited no longer counts, so a crashed session never blocks anyone.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::error::{ErrorCode, TorenError};
+use crate::ids::AncillaryId;
+
+/// The process running the agent for an assignment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AssignmentOwner {
+    /// SDK work `work_id` in the daemon with process ID `pid`
+    Daemon { work_id: String, pid: u32 },
+    /// An agent breq started in a terminal (`breq do` execs into it, so the
+    /// pid is the agent's)
+    Cli { pid: u32 },
+}
+
+impl AssignmentOwner {
+    /// The current daemon process, running new SDK work.
+    pub fn daemon() -> Self {
+        AssignmentOwner::Daemon {
+            work_id: uuid::Uuid::new_v4().to_string(),
+            pid: std::process::id(),
+        }
+    }
+
+    /// The current breq process.
+    pub fn cli() -> Self {
+        AssignmentOwner::Cli {
+            pid: std::process::id(),
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        match self {
+            AssignmentOwner::Daemon { pid, .. } | AssignmentOwner::Cli { pid } => *pid,
+        }
+    }
+
+    /// Whether the owning process is still running.
+    pub fn is_alive(&self) -> bool {
+        crate::process::is_running(self.pid() as i32)
+    }
+
+    /// Whether this owner stops the current process from taking over: it is
+    /// alive and isn't the current process (the daemon stops its own work
+    /// before completing).
+    pub fn blocks_current(&self) -> bool {
+        self.pid() != std::process::id() && self.is_alive()
+    }
+}
+
+impl fmt::Display for AssignmentOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssignmentOwner::Daemon { work_id, pid } => {
+                write!(f, "the daemon (work {}, pid {})", work_id, pid)
+            }
+            AssignmentOwner::Cli { pid } => write!(f, "a breq session (pid {})", pid),
+        }
+    }
+}
+
+/// Another live process owns the assignment.
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[error("{ancillary_id} is being worked on by {owner}")]
+pub struct AssignmentOwned {
+    pub assignment_id: String,
+    pub ancillary_id: AncillaryId,
+    pub owner: AssignmentOwner,
+}
+
+impl AssignmentOwned {
+    /// Wrap in a coded error with the `--steal` hint.
+    pub fn into_error(self) -> anyhow::Error {
+        let coded = TorenError::new(ErrorCode::AssignmentOwned, self.to_string())
+            .hint("Wait for it to finish, or rerun with --steal to take over");
+        anyhow::Error::new(self).context(coded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owner_liveness_and_serde() {
+        let me = AssignmentOwner::cli();
+        assert!(me.is_alive());
+        assert!(!me.blocks_current());
+
+        // pid_max is at most 2^22 on Linux, so this pid never exists
+        let gone = AssignmentOwner::Cli { pid: 1 << 23 };
+        assert!(!gone.is_alive());
+        assert!(!gone.blocks_current());
+
+        let daemon = AssignmentOwner::Daemon {
+            work_id: "w1".to_string(),
+            pid: 42,
+        };
+        let json = serde_json::to_value(&daemon).unwrap();
+        assert_eq!(json, serde_json::json!({"kind": "daemon", "work_id": "w1", "pid": 42}));
+        assert_eq!(serde_json::from_value::<AssignmentOwner>(json).unwrap(), daemon);
+    }
+}
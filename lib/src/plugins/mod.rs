@@ -20,7 +20,8 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tracing::{info, warn};
 
-use crate::tasks::ResolvedTask;
+use crate::ids::TaskId;
+use crate::tasks::{ResolvedTask, TaskDep};
 
 /// Lightweight metadata extracted from a plugin file without compilation.
 #[derive(Debug, Clone)]
@@ -76,6 +77,7 @@ fn parse_doc_comments(source: &str) -> (Option<String>, Option<String>) {
 }
 
 /// The result of running a plugin script.
+#[allow(clippy::large_enum_variant)]
 pub enum PluginResult {
     /// Script completed normally (no deferred action).
     Ok,
@@ -98,6 +100,20 @@ pub enum DeferredAction {
         task_source: Option<String>,
         prompt: Option<String>,
         intent: Option<String>,
+        /// Print the prompt instead of starting the agent
+        no_exec: bool,
+        /// Hand the session off to the daemon instead of this terminal
+        detach: bool,
+        /// Run the agent as a child process and record its session ID
+        wrap: bool,
+        /// Fan out to this many new workspaces instead of starting one session
+        count: Option<u32>,
+        /// Subdirectory of the workspace to scope the assignment to
+        path: Option<String>,
+        /// Task to wait for before the daemon starts the work
+        after: Option<String>,
+        /// Segment group to create workspaces across (`[segments.groups]`)
+        group: Option<String>,
     },
 }
 
@@ -239,18 +255,19 @@ impl PluginManager {
         ctx: PluginContext,
     ) -> Result<ResolvedTask> {
         let map = self.call_resolver_map(source, "info", (id.to_string(),), ctx)?;
+        Ok(resolved_task(&map, source, id))
+    }
 
-        Ok(ResolvedTask {
-            id: get_map_string(&map, "id").unwrap_or_else(|| id.to_string()),
-            source: source.to_string(),
-            kind: get_map_string(&map, "kind"),
-            title: get_map_string(&map, "title").unwrap_or_default(),
-            status: get_map_string(&map, "status"),
-            assignee: get_map_string(&map, "assignee"),
-            description: get_map_string(&map, "description"),
-            created_at: get_map_string(&map, "created_at"),
-            updated_at: get_map_string(&map, "updated_at"),
-        })
+    /// List tasks via a resolver plugin's optional `list()` function, which
+    /// returns an array of maps shaped like `info`'s.
+    pub fn resolve_list(&self, source: &str, ctx: PluginContext) -> Result<Vec<ResolvedTask>> {
+        self.call_resolver_list(source, "list", ctx)
+    }
+
+    /// List the tasks that can be started now via a resolver plugin's
+    /// optional `ready()` function, shaped like `list()`'s.
+    pub fn resolve_ready(&self, source: &str, ctx: PluginContext) -> Result<Vec<ResolvedTask>> {
+        self.call_resolver_list(source, "ready", ctx)
     }
 
     /// Claim a task via a resolver plugin.
@@ -276,12 +293,36 @@ impl PluginManager {
         Ok(())
     }
 
+    /// Post a comment on a task via a resolver plugin.
+    pub fn resolve_comment(&self, source: &str, id: &str, body: &str, ctx: PluginContext) -> Result<()> {
+        let _ = self.call_resolver_raw(source, "comment", (id.to_string(), body.to_string()), ctx)?;
+        Ok(())
+    }
+
     /// Abort a task via a resolver plugin.
     pub fn resolve_abort(&self, source: &str, id: &str, ctx: PluginContext) -> Result<()> {
         let _ = self.call_resolver_raw(source, "abort", (id.to_string(),), ctx)?;
         Ok(())
     }
 
+    /// Delete a task via a resolver plugin's `delete(id)` function.
+    pub fn resolve_delete(&self, source: &str, id: &str, ctx: PluginContext) -> Result<()> {
+        let _ = self.call_resolver_raw(source, "delete", (id.to_string(),), ctx)?;
+        Ok(())
+    }
+
+    /// Replace a task's description via a resolver plugin's optional
+    /// `update_description(id, desc)` function.
+    pub fn resolve_update_description(&self, source: &str, id: &str, desc: &str, ctx: PluginContext) -> Result<()> {
+        let _ = self.call_resolver_raw(
+            source,
+            "update_description",
+            (id.to_string(), desc.to_string()),
+            ctx,
+        )?;
+        Ok(())
+    }
+
     /// Create a task via a resolver plugin. Returns the created task ID.
     pub fn resolve_create(
         &self,
@@ -364,6 +405,23 @@ impl PluginManager {
             .map_err(|e| anyhow::anyhow!("Resolver '{}' {} error: {}", source, fn_name, e))
     }
 
+    /// Call a resolver function that is expected to return an array of task maps.
+    fn call_resolver_list(&self, source: &str, fn_name: &str, ctx: PluginContext) -> Result<Vec<ResolvedTask>> {
+        let result = self.call_resolver_raw(source, fn_name, (), ctx)?;
+        let items = result
+            .try_cast::<rhai::Array>()
+            .ok_or_else(|| anyhow::anyhow!("Resolver '{}' {} did not return an array", source, fn_name))?;
+        items
+            .into_iter()
+            .map(|item| {
+                let map = item.try_cast::<rhai::Map>().ok_or_else(|| {
+                    anyhow::anyhow!("Resolver '{}' {} returned a non-map item", source, fn_name)
+                })?;
+                Ok(resolved_task(&map, source, ""))
+            })
+            .collect()
+    }
+
     /// Call a resolver function that is expected to return a Map.
     fn call_resolver_map<A: rhai::FuncArgs>(
         &self,
@@ -466,6 +524,54 @@ impl PluginManager {
     }
 }
 
+/// A task from a resolver's map; `id` stands in when the map has none.
+fn resolved_task(map: &rhai::Map, source: &str, id: &str) -> ResolvedTask {
+    ResolvedTask {
+        id: TaskId::unchecked(get_map_string(map, "id").unwrap_or_else(|| id.to_string())),
+        source: source.to_string(),
+        kind: get_map_string(map, "kind"),
+        title: get_map_string(map, "title").unwrap_or_default(),
+        status: get_map_string(map, "status"),
+        assignee: get_map_string(map, "assignee"),
+        description: get_map_string(map, "description"),
+        created_at: get_map_string(map, "created_at"),
+        updated_at: get_map_string(map, "updated_at"),
+        priority: task_priority(map),
+        deps: task_deps(map),
+    }
+}
+
+/// The `priority` of a task map: an integer, or a string like "2" or "P2".
+pub(crate) fn task_priority(map: &rhai::Map) -> Option<i64> {
+    let value = map.get("priority")?;
+    value.as_int().ok().or_else(|| {
+        let s = value.clone().into_string().ok()?;
+        s.trim().trim_start_matches(['P', 'p']).parse().ok()
+    })
+}
+
+/// The `deps` of an `info` map: maps with `id`, `title` and `status`, or
+/// bare IDs. Entries without an ID are skipped.
+pub(crate) fn task_deps(map: &rhai::Map) -> Vec<TaskDep> {
+    let Some(deps) = map.get("deps").and_then(|v| v.clone().try_cast::<rhai::Array>()) else {
+        return Vec::new();
+    };
+    deps.into_iter()
+        .filter_map(|dep| match dep.clone().try_cast::<rhai::Map>() {
+            Some(dep) => Some(TaskDep {
+                id: get_map_string(&dep, "id")?,
+                title: get_map_string(&dep, "title"),
+                status: get_map_string(&dep, "status"),
+            }),
+            None => dep.into_string().ok().map(|id| TaskDep {
+                id,
+                title: None,
+                status: None,
+            }),
+        })
+        .collect()
+}
+
 /// Extract a string value from a Rhai Map, returning None for unit values.
 fn get_map_string(map: &rhai::Map, key: &str) -> Option<String> {
     map.get(key).and_then(|v| {
@@ -755,6 +861,74 @@ mod tests {
         assert_eq!(info.source, "mock");
         assert_eq!(info.status.as_deref(), Some("in_progress"));
         assert_eq!(info.assignee.as_deref(), Some("claude"));
+        assert!(info.deps.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_info_deps() {
+        let dir = tempfile::tempdir().unwrap();
+        let tasks_dir = dir.path().join("tasks");
+        std::fs::create_dir_all(&tasks_dir).unwrap();
+        std::fs::write(
+            tasks_dir.join("mock.rhai"),
+            r#"fn info(id) {
+    #{ id: id, title: "T", deps: [#{ id: "a-1", title: "First", status: "closed" }, "a-2", #{ title: "no id" }] }
+}"#,
+        )
+        .unwrap();
+
+        let mgr = PluginManager::new(dir.path()).unwrap();
+        let info = mgr.resolve_info("mock", "a-3", PluginContext::default()).unwrap();
+        let deps: Vec<_> = info
+            .deps
+            .iter()
+            .map(|d| (d.id.as_str(), d.title.as_deref(), d.status.as_deref()))
+            .collect();
+        assert_eq!(deps, [("a-1", Some("First"), Some("closed")), ("a-2", None, None)]);
+    }
+
+    #[test]
+    fn test_resolve_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let tasks_dir = dir.path().join("tasks");
+        std::fs::create_dir_all(&tasks_dir).unwrap();
+        std::fs::write(
+            tasks_dir.join("mock.rhai"),
+            r#"fn list() {
+    [#{ id: "a-1", title: "First", status: "open", priority: 1 }, #{ id: "a-2", title: "Second", assignee: (), priority: "P3" }]
+}"#,
+        )
+        .unwrap();
+
+        let mgr = PluginManager::new(dir.path()).unwrap();
+        let tasks = mgr.resolve_list("mock", PluginContext::default()).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].id, "a-1");
+        assert_eq!(tasks[0].status.as_deref(), Some("open"));
+        assert_eq!(tasks[1].title, "Second");
+        assert_eq!(tasks[1].assignee, None);
+        assert_eq!(tasks[1].source, "mock");
+        assert_eq!(tasks[0].priority, Some(1));
+        assert_eq!(tasks[1].priority, Some(3));
+    }
+
+    #[test]
+    fn test_resolver_shell_runs_in_segment() {
+        let dir = tempfile::tempdir().unwrap();
+        let tasks_dir = dir.path().join("tasks");
+        std::fs::create_dir_all(&tasks_dir).unwrap();
+        std::fs::write(
+            tasks_dir.join("mock.rhai"),
+            r#"fn info(id) { #{ id: id, title: shell("pwd", []) } }"#,
+        )
+        .unwrap();
+        let segment = tempfile::tempdir().unwrap();
+        let segment_path = segment.path().canonicalize().unwrap();
+
+        let mgr = PluginManager::new(dir.path()).unwrap();
+        let ctx = PluginContext::new(Some(segment_path.clone()), Some("seg".to_string()));
+        let info = mgr.resolve_info("mock", "x", ctx).unwrap();
+        assert_eq!(info.title, segment_path.display().to_string());
     }
 
     #[test]
@@ -805,17 +979,22 @@ mod tests {
         std::fs::write(
             tasks_dir.join("mock.rhai"),
             r#"fn complete(id) { }
-fn abort(id) { }"#,
+fn abort(id) { }
+fn delete(id) { }"#,
         )
         .unwrap();
 
         let mgr = PluginManager::new(dir.path()).unwrap();
+        assert!(mgr.resolver_has_fn("mock", "delete"));
 
         let ctx = PluginContext::default();
         mgr.resolve_complete("mock", "abc-123", ctx).unwrap();
 
         let ctx = PluginContext::default();
         mgr.resolve_abort("mock", "abc-123", ctx).unwrap();
+
+        let ctx = PluginContext::default();
+        mgr.resolve_delete("mock", "abc-123", ctx).unwrap();
     }
 
     #[test]
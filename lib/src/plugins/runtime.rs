@@ -6,8 +6,8 @@
 //! - `path::join`, `path::parent`, `path::filename`, `path::ext`
 //! - `toml::parse`
 //! - `http::get`, `http::post`, `http::put`, `http::patch`, `http::delete`
-//! - `toren::config`, `toren::assignment`
-//! - `task::info`, `task::claim`, `task::complete`, `task::abort`, `task::create`
+//! - `toren::config`, `toren::assignment`, `toren::assignments`, `toren::check_agent`
+//! - `task::info`, `task::claim`, `task::complete`, `task::abort`, `task::delete`, `task::create`
 //! - `ws::changes`
 //!
 //! Flat aliases (`task`, `claim_task`, `complete_task`, `abort_task`, `ancillary`,
@@ -16,6 +16,7 @@
 
 use anyhow::Result;
 use rhai::{Dynamic, Engine, Map, Module, Scope, AST};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use super::{DeferredAction, PluginContext, PluginResult};
@@ -35,8 +36,8 @@ pub fn create_engine(ctx: Arc<PluginContext>) -> Engine {
     });
 
     // ── Core registrations ───────────────────────────────────────────
-    register_shell(&mut engine);
-    register_shell_extended(&mut engine);
+    register_shell(&mut engine, None);
+    register_shell_extended(&mut engine, None);
     register_env(&mut engine);
     register_cwd(&mut engine);
     register_platform(&mut engine);
@@ -62,7 +63,11 @@ pub fn create_engine(ctx: Arc<PluginContext>) -> Engine {
 
 /// Create a resolver engine — same as `create_engine` but without `toren::task()`
 /// to prevent infinite recursion when resolvers are called from `toren::task()`.
-pub fn create_resolver_engine(_ctx: Arc<PluginContext>) -> Engine {
+///
+/// `shell` runs in the context's segment directory (when set), so trackers
+/// that keep their data in the repo, like `bd`, find it whatever directory
+/// breq or the daemon was started from.
+pub fn create_resolver_engine(ctx: Arc<PluginContext>) -> Engine {
     let mut engine = Engine::new();
 
     engine.on_print(|s| println!("{}", s));
@@ -74,8 +79,8 @@ pub fn create_resolver_engine(_ctx: Arc<PluginContext>) -> Engine {
         }
     });
 
-    register_shell(&mut engine);
-    register_shell_extended(&mut engine);
+    register_shell(&mut engine, ctx.segment_path.clone());
+    register_shell_extended(&mut engine, ctx.segment_path.clone());
     register_env(&mut engine);
     register_cwd(&mut engine);
     register_platform(&mut engine);
@@ -126,6 +131,13 @@ pub fn interpret_result(value: Dynamic) -> Result<PluginResult> {
                     map.get(key)
                         .and_then(|v| v.clone().into_string().ok())
                 };
+                let get_bool = |key: &str| -> bool {
+                    map.get(key).and_then(|v| v.as_bool().ok()).unwrap_or(false)
+                };
+                let count = map
+                    .get("count")
+                    .and_then(|v| v.as_int().ok())
+                    .and_then(|n| u32::try_from(n).ok());
 
                 return Ok(PluginResult::Action(DeferredAction::Do {
                     task_id: get_str("task_id"),
@@ -134,6 +146,13 @@ pub fn interpret_result(value: Dynamic) -> Result<PluginResult> {
                     task_source: get_str("task_source"),
                     prompt: get_str("prompt"),
                     intent: get_str("intent"),
+                    no_exec: get_bool("no_exec"),
+                    detach: get_bool("detach"),
+                    wrap: get_bool("wrap"),
+                    count,
+                    path: get_str("path"),
+                    after: get_str("after"),
+                    group: get_str("group"),
                 }));
             }
         }
@@ -144,13 +163,17 @@ pub fn interpret_result(value: Dynamic) -> Result<PluginResult> {
 // ── Shell ───────────────────────────────────────────────────────────────────
 
 /// `shell(program, args) -> String` — run command, return stdout, error on non-zero exit.
-fn register_shell(engine: &mut Engine) {
-    engine.register_fn("shell", |program: &str, args: rhai::Array| -> Result<String, Box<rhai::EvalAltResult>> {
+fn register_shell(engine: &mut Engine, dir: Option<PathBuf>) {
+    engine.register_fn("shell", move |program: &str, args: rhai::Array| -> Result<String, Box<rhai::EvalAltResult>> {
         let str_args: Vec<String> = args
             .into_iter()
             .map(|a| a.into_string().unwrap_or_default())
             .collect();
-        let output = std::process::Command::new(program)
+        let mut cmd = std::process::Command::new(program);
+        if let Some(ref dir) = dir {
+            cmd.current_dir(dir);
+        }
+        let output = cmd
             .args(&str_args)
             .output()
             .map_err(|e| format!("Failed to run '{}': {}", program, e))?;
@@ -172,8 +195,8 @@ fn register_shell(engine: &mut Engine) {
 ///
 /// opts keys: `dir`, `env`, `stdin`, `timeout`
 /// Returns `#{ stdout, stderr, status }`
-fn register_shell_extended(engine: &mut Engine) {
-    engine.register_fn("shell", |program: &str, args: rhai::Array, opts: Map| -> Result<Map, Box<rhai::EvalAltResult>> {
+fn register_shell_extended(engine: &mut Engine, default_dir: Option<PathBuf>) {
+    engine.register_fn("shell", move |program: &str, args: rhai::Array, opts: Map| -> Result<Map, Box<rhai::EvalAltResult>> {
         let str_args: Vec<String> = args
             .into_iter()
             .map(|a| a.into_string().unwrap_or_default())
@@ -181,6 +204,9 @@ fn register_shell_extended(engine: &mut Engine) {
 
         let mut cmd = std::process::Command::new(program);
         cmd.args(&str_args);
+        if let Some(ref dir) = default_dir {
+            cmd.current_dir(dir);
+        }
 
         // dir option
         if let Some(dir) = opts.get("dir") {
@@ -725,11 +751,29 @@ fn build_toren_module(ctx: Arc<PluginContext>) -> rhai::Shared<Module> {
         config_impl(key)
     });
 
+    module.set_native_fn("check_agent", || -> Result<String, Box<rhai::EvalAltResult>> {
+        check_agent_impl()
+    });
+
     let assign_ctx = ctx.clone();
     module.set_native_fn("assignment", move |workspace: &str| -> Result<Map, Box<rhai::EvalAltResult>> {
         assignment_impl(workspace, &assign_ctx)
     });
 
+    let report_ctx = ctx.clone();
+    module.set_native_fn("report", move |workspace: &str| -> Result<Dynamic, Box<rhai::EvalAltResult>> {
+        report_impl(workspace, &report_ctx)
+    });
+
+    let list_ctx = ctx.clone();
+    module.set_native_fn("assignments", move || -> Result<rhai::Array, Box<rhai::EvalAltResult>> {
+        assignments_impl(list_ctx.segment_name.as_deref().unwrap_or(""))
+    });
+
+    module.set_native_fn("assignments", |segment: &str| -> Result<rhai::Array, Box<rhai::EvalAltResult>> {
+        assignments_impl(segment)
+    });
+
     module.into()
 }
 
@@ -756,6 +800,16 @@ fn build_task_module(ctx: Arc<PluginContext>) -> rhai::Shared<Module> {
         abort_task_impl(source, id, &abort_ctx)
     });
 
+    let delete_ctx = ctx.clone();
+    module.set_native_fn("delete", move |source: &str, id: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+        call_resolver_void(source, "delete", (id.to_string(),), &delete_ctx)
+    });
+
+    let comment_ctx = ctx.clone();
+    module.set_native_fn("comment", move |source: &str, id: &str, body: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+        call_resolver_void(source, "comment", (id.to_string(), body.to_string()), &comment_ctx)
+    });
+
     let create_ctx = ctx.clone();
     module.set_native_fn("create", move |source: &str, title: &str, desc: &str| -> Result<String, Box<rhai::EvalAltResult>> {
         create_task_impl(source, title, Some(desc), &create_ctx)
@@ -845,6 +899,18 @@ fn config_impl(key: &str) -> Result<String, Box<rhai::EvalAltResult>> {
     Ok(result)
 }
 
+/// Resolve the configured agent and verify its binary is installed.
+/// Returns the agent name (e.g. "claude") for use in claim assignees.
+fn check_agent_impl() -> Result<String, Box<rhai::EvalAltResult>> {
+    let config = crate::Config::load()
+        .map_err(|e| format!("Failed to load config: {}", e))?;
+    let agent = config
+        .resolve_agent(None)
+        .map_err(|e| format!("{:#}", e))?;
+    agent.ensure_installed().map_err(|e| format!("{:#}", e))?;
+    Ok(agent.kind.binary_name().to_string())
+}
+
 fn task_impl(id: &str, ctx: &PluginContext) -> Result<Map, Box<rhai::EvalAltResult>> {
     let config = crate::Config::load()
         .map_err(|e| format!("Failed to load config: {}", e))?;
@@ -878,7 +944,7 @@ fn task_impl(id: &str, ctx: &PluginContext) -> Result<Map, Box<rhai::EvalAltResu
                 let engine = super::runtime::create_resolver_engine(resolver_ctx);
                 let mut scope = Scope::new();
                 if let Some(task) = engine
-                    .call_fn::<Dynamic>(&mut scope, resolver_ast, "info", (task_id.clone(),))
+                    .call_fn::<Dynamic>(&mut scope, resolver_ast, "info", (task_id.to_string(),))
                     .ok()
                     .and_then(|d| d.try_cast::<Map>())
                     .map(|m| {
@@ -888,7 +954,7 @@ fn task_impl(id: &str, ctx: &PluginContext) -> Result<Map, Box<rhai::EvalAltResu
                             })
                         };
                         crate::tasks::ResolvedTask {
-                            id: get_opt("id").unwrap_or_else(|| task_id.clone()),
+                            id: crate::TaskId::unchecked(get_opt("id").unwrap_or_else(|| task_id.to_string())),
                             source: source.clone(),
                             kind: get_opt("kind"),
                             title: get_opt("title").unwrap_or_default(),
@@ -897,6 +963,8 @@ fn task_impl(id: &str, ctx: &PluginContext) -> Result<Map, Box<rhai::EvalAltResu
                             description: get_opt("description"),
                             created_at: get_opt("created_at"),
                             updated_at: get_opt("updated_at"),
+                            priority: super::task_priority(&m),
+                            deps: super::task_deps(&m),
                         }
                     })
                 {
@@ -912,7 +980,7 @@ fn task_impl(id: &str, ctx: &PluginContext) -> Result<Map, Box<rhai::EvalAltResu
 
     let mut map = Map::new();
     if let Some(id) = inferred.task_id {
-        map.insert("id".into(), Dynamic::from(id));
+        map.insert("id".into(), Dynamic::from(id.to_string()));
     }
     let title = fetched.as_ref().map(|t| t.title.clone()).or(inferred.task_title);
     if let Some(t) = title {
@@ -1018,28 +1086,95 @@ fn assignment_impl(workspace: &str, ctx: &PluginContext) -> Result<Map, Box<rhai
     let segment_name = ctx.segment_name.as_deref().unwrap_or("");
 
     // Resolve workspace to assignment
-    let ws_name = workspace.to_lowercase();
-    let ancillary_num = crate::word_to_number(&ws_name).unwrap_or(0);
+    let ws_name = crate::naming::normalize_workspace_name(segment_name, workspace);
+    let ancillary_num = crate::naming::slot_number(segment_name, &ws_name).unwrap_or(0);
     let anc_id = crate::ancillary_id(segment_name, ancillary_num);
 
     let assignment = assignment_mgr
         .get_active_for_ancillary(&anc_id)
         .ok_or_else(|| format!("No assignment found for workspace '{}'", workspace))?;
 
+    Ok(assignment_map(assignment))
+}
+
+/// Final report of a workspace's assignment as `#{source, text}`, or `()` when
+/// its intent isn't one of `tasks.report_intents` or it left no report.
+fn report_impl(workspace: &str, ctx: &PluginContext) -> Result<Dynamic, Box<rhai::EvalAltResult>> {
+    let config = crate::Config::load()
+        .map_err(|e| format!("Failed to load config: {}", e))?;
+    let mut assignment_mgr = crate::AssignmentManager::new()
+        .map_err(|e| format!("Failed to load assignments: {}", e))?;
+
+    let segment_name = ctx.segment_name.as_deref().unwrap_or("");
+    let ws_name = crate::naming::normalize_workspace_name(segment_name, workspace);
+    let ancillary_num = crate::naming::slot_number(segment_name, &ws_name).unwrap_or(0);
+    let anc_id = crate::ancillary_id(segment_name, ancillary_num);
+
+    let assignment = assignment_mgr
+        .get_active_for_ancillary(&anc_id)
+        .ok_or_else(|| format!("No assignment found for workspace '{}'", workspace))?;
+    if !config.tasks.is_report_intent(assignment.intent.as_deref()) {
+        return Ok(Dynamic::UNIT);
+    }
+
+    let logs = crate::session::SessionLogs::from_config(&config);
+    let Some(report) = crate::report::final_report(assignment, &crate::toren_root(), &logs) else {
+        return Ok(Dynamic::UNIT);
+    };
+    let mut map = Map::new();
+    map.insert("source".into(), Dynamic::from(report.source));
+    map.insert("text".into(), Dynamic::from(report.text));
+    Ok(Dynamic::from(map))
+}
+
+/// Active assignments in a segment, with the agent activity and workspace
+/// changes that decide whether they are safe to abort in bulk.
+fn assignments_impl(segment: &str) -> Result<rhai::Array, Box<rhai::EvalAltResult>> {
+    let config = crate::Config::load()
+        .map_err(|e| format!("Failed to load config: {}", e))?;
+    let mut assignment_mgr = crate::AssignmentManager::new()
+        .map_err(|e| format!("Failed to load assignments: {}", e))?;
+    let logs = crate::session::SessionLogs::from_config(&config);
+
+    let list = assignment_mgr
+        .list_active_segment(segment)
+        .into_iter()
+        .map(|a| {
+            let mut map = assignment_map(a);
+            let num = a.ancillary_num.or_else(|| crate::ancillary_number(&a.ancillary_id));
+            let workspace = crate::naming::workspace_name(&a.segment, num.unwrap_or(0));
+            let activity = crate::composite_status::detect_agent_activity(&logs, &a.working_dir());
+            let has_changes = crate::composite_status::workspace_has_changes(
+                &a.workspace_path,
+                a.base_branch.as_deref(),
+                a.focus_path.as_deref(),
+            );
+            map.insert("workspace".into(), Dynamic::from(workspace));
+            map.insert("activity".into(), Dynamic::from(activity));
+            map.insert("has_changes".into(), Dynamic::from(has_changes));
+            Dynamic::from(map)
+        })
+        .collect();
+    Ok(list)
+}
+
+fn assignment_map(assignment: &crate::Assignment) -> Map {
     let mut map = Map::new();
     map.insert("id".into(), Dynamic::from(assignment.id.clone()));
-    map.insert("ancillary_id".into(), Dynamic::from(assignment.ancillary_id.clone()));
+    map.insert("ancillary_id".into(), Dynamic::from(assignment.ancillary_id.to_string()));
     map.insert("segment".into(), Dynamic::from(assignment.segment.clone()));
     map.insert("workspace_path".into(), Dynamic::from(assignment.workspace_path.display().to_string()));
     map.insert("status".into(), Dynamic::from(format!("{:?}", assignment.status)));
-    map.insert("task_id".into(), Dynamic::from(assignment.task_id.clone().unwrap_or_default()));
+    map.insert("task_id".into(), Dynamic::from(assignment.task_id.as_deref().unwrap_or_default().to_string()));
     map.insert("task_title".into(), Dynamic::from(assignment.task_title.clone().unwrap_or_default()));
     map.insert("task_url".into(), Dynamic::from(assignment.task_url.clone().unwrap_or_default()));
     map.insert("task_source".into(), Dynamic::from(assignment.task_source.clone().unwrap_or_default()));
     map.insert("session_id".into(), Dynamic::from(assignment.session_id.clone().unwrap_or_default()));
     map.insert("ancillary_num".into(), Dynamic::from(assignment.ancillary_num.unwrap_or(0) as i64));
     map.insert("base_branch".into(), Dynamic::from(assignment.base_branch.clone().unwrap_or_default()));
-    Ok(map)
+    map.insert("intent".into(), Dynamic::from(assignment.intent.clone().unwrap_or_default()));
+    map.insert("auto_created_bead".into(), Dynamic::from(assignment.source.auto_created_bead()));
+    map
 }
 
 fn ws_changes_impl(workspace: &str, ctx: &PluginContext) -> Result<rhai::Array, Box<rhai::EvalAltResult>> {
@@ -1053,8 +1188,8 @@ fn ws_changes_impl(workspace: &str, ctx: &PluginContext) -> Result<rhai::Array,
         .ok_or_else(|| "No segment path available".to_string())?;
 
     // Resolve workspace to assignment
-    let ws_name = workspace.to_lowercase();
-    let ancillary_num = crate::word_to_number(&ws_name).unwrap_or(0);
+    let ws_name = crate::naming::normalize_workspace_name(segment_name, workspace);
+    let ancillary_num = crate::naming::slot_number(segment_name, &ws_name).unwrap_or(0);
     let anc_id = crate::ancillary_id(segment_name, ancillary_num);
 
     let assignment = assignment_mgr
@@ -1143,6 +1278,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_interpret_result_do_launch_flags() {
+        let mut map = Map::new();
+        map.insert("action".into(), Dynamic::from("do"));
+        map.insert("detach".into(), Dynamic::from(true));
+        map.insert("count".into(), Dynamic::from(3_i64));
+        map.insert("path".into(), Dynamic::from("crates/foo"));
+        map.insert("after".into(), Dynamic::from("breq-1"));
+        map.insert("group".into(), Dynamic::from("shop"));
+
+        match interpret_result(Dynamic::from(map)).unwrap() {
+            PluginResult::Action(DeferredAction::Do { no_exec, detach, count, path, after, group, .. }) => {
+                assert!(!no_exec);
+                assert!(detach);
+                assert_eq!(count, Some(3));
+                assert_eq!(path.as_deref(), Some("crates/foo"));
+                assert_eq!(after.as_deref(), Some("breq-1"));
+                assert_eq!(group.as_deref(), Some("shop"));
+            }
+            _ => panic!("Expected DeferredAction::Do"),
+        }
+    }
+
     #[test]
     fn test_interpret_result_legacy_cmd_action() {
         let mut map = Map::new();
@@ -1240,10 +1398,22 @@ mod tests {
             [p.opts.push, p.args.len()]
         "#).unwrap();
         let result: rhai::Array = engine.eval_ast(&ast).unwrap();
-        assert_eq!(result[0].clone().cast::<bool>(), true);
+        assert!(result[0].clone().cast::<bool>());
         assert_eq!(result[1].clone().cast::<i64>(), 0);
     }
 
+    #[test]
+    fn test_parse_args_hyphenated_flag() {
+        let ctx = Arc::new(PluginContext::default());
+        let engine = create_engine(ctx);
+        let ast = engine.compile(r#"
+            let p = parse_args(["--no-exec"], #{ "no-exec": #{ type: "bool" } });
+            p.opts["no-exec"]
+        "#).unwrap();
+        let result: bool = engine.eval_ast(&ast).unwrap();
+        assert!(result);
+    }
+
     #[test]
     fn test_parse_args_bool_default_false() {
         let ctx = Arc::new(PluginContext::default());
@@ -1327,7 +1497,7 @@ mod tests {
         let result: rhai::Array = engine.eval_ast(&ast).unwrap();
         assert_eq!(result[0].clone().into_string().unwrap(), "foo");
         assert_eq!(result[1].clone().into_string().unwrap(), "bar");
-        assert_eq!(result[2].clone().cast::<bool>(), true);
+        assert!(result[2].clone().cast::<bool>());
     }
 
     #[test]
@@ -1339,7 +1509,7 @@ mod tests {
             [p.opts.push, p.args[0]]
         "#).unwrap();
         let result: rhai::Array = engine.eval_ast(&ast).unwrap();
-        assert_eq!(result[0].clone().cast::<bool>(), false);
+        assert!(!result[0].clone().cast::<bool>());
         assert_eq!(result[1].clone().into_string().unwrap(), "--push");
     }
 
@@ -1372,7 +1542,7 @@ mod tests {
         "#).unwrap();
         let result: rhai::Array = engine.eval_ast(&ast).unwrap();
         assert_eq!(result[0].clone().into_string().unwrap(), "task-123");
-        assert_eq!(result[1].clone().cast::<bool>(), true);
+        assert!(result[1].clone().cast::<bool>());
         assert_eq!(result[2].clone().into_string().unwrap(), "act");
     }
 
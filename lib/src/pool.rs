@@ -0,0 +1,213 @@
+//! Ancillary pool allocation.
+//!
+//! Each segment has `max_per_segment` ancillary slots. New assignments take
+//! the lowest free slot; when every slot is occupied, allocation fails with a
+//! [`PoolExhausted`] triage listing unless the caller opts into overflow.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::assignment::{ancillary_id, ancillary_number, AssignmentManager};
+use crate::composite_status::{detect_agent_activity, workspace_has_changes};
+use crate::error::{ErrorCode, TorenError};
+use crate::ids::{AncillaryId, TaskId};
+use crate::naming::workspace_name;
+use crate::session::SessionLogs;
+use crate::workspace::WorkspaceManager;
+
+/// One occupied pool slot, as shown in the exhaustion triage.
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolSlot {
+    pub ancillary_id: AncillaryId,
+    pub workspace: String,
+    pub task_id: Option<TaskId>,
+    pub task_title: Option<String>,
+    /// Agent activity: "busy" or "idle"
+    pub activity: String,
+    pub has_changes: bool,
+    /// False when the workspace exists on disk without an assignment record
+    pub assigned: bool,
+}
+
+impl PoolSlot {
+    /// Short state label: busy, dirty, idle, or orphaned.
+    pub fn state(&self) -> &'static str {
+        if !self.assigned {
+            "orphaned"
+        } else if self.activity == "busy" {
+            "busy"
+        } else if self.has_changes {
+            "dirty"
+        } else {
+            "idle"
+        }
+    }
+
+    /// The command most likely to free this slot, if any.
+    pub fn suggestion(&self) -> Option<String> {
+        match self.state() {
+            "orphaned" => Some(format!("breq destroy --force {}", self.workspace)),
+            "dirty" => Some(format!("breq complete {}", self.workspace)),
+            "idle" => Some(format!("breq abort {}", self.workspace)),
+            _ => None,
+        }
+    }
+}
+
+/// Every slot in a segment's pool is occupied.
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[error("All {pool_size} ancillaries in {segment} are in use")]
+pub struct PoolExhausted {
+    pub segment: String,
+    pub pool_size: u32,
+    pub slots: Vec<PoolSlot>,
+}
+
+impl PoolExhausted {
+    /// One line per slot, followed by the overflow escape hatch.
+    pub fn triage_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.slots.iter().map(|slot| slot.to_string()).collect();
+        lines.push("Free a slot above, or rerun with --overflow to exceed the pool".to_string());
+        lines
+    }
+}
+
+impl fmt::Display for PoolSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "  {:<8} {:<8}", self.workspace, self.state())?;
+        match (&self.task_id, &self.task_title) {
+            (Some(id), Some(title)) => write!(f, " {} {}", id, title)?,
+            (Some(id), None) => write!(f, " {}", id)?,
+            (None, Some(title)) => write!(f, " {}", title)?,
+            (None, None) => {}
+        }
+        if let Some(cmd) = self.suggestion() {
+            write!(f, "  → {}", cmd)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pick the ancillary for a new assignment in `segment_name`.
+///
+/// Returns the lowest free slot within `pool_size`. When the pool is full,
+/// fails with a coded error wrapping [`PoolExhausted`], or — with `overflow`
+/// — allocates past the pool like before.
+pub fn allocate_ancillary(
+    assignment_mgr: &mut AssignmentManager,
+    ws_mgr: &WorkspaceManager,
+    segment_name: &str,
+    segment_path: &Path,
+    pool_size: u32,
+    overflow: bool,
+    logs: &SessionLogs,
+) -> Result<AncillaryId> {
+    let existing = ws_mgr.list_workspaces(segment_path).unwrap_or_default();
+    let id = assignment_mgr.next_available_ancillary(segment_name, pool_size, &existing);
+    let within_pool = ancillary_number(&id).is_some_and(|n| n <= pool_size);
+    if within_pool || overflow {
+        return Ok(id);
+    }
+
+    let exhausted = triage(assignment_mgr, ws_mgr, segment_name, pool_size, &existing, logs);
+    let coded = TorenError::new(ErrorCode::PoolExhausted, exhausted.to_string())
+        .hint(exhausted.triage_lines().join("\n"));
+    Err(anyhow::Error::new(exhausted).context(coded))
+}
+
+/// Describe each slot in the pool for the exhaustion listing.
+fn triage(
+    assignment_mgr: &mut AssignmentManager,
+    ws_mgr: &WorkspaceManager,
+    segment_name: &str,
+    pool_size: u32,
+    existing: &[String],
+    logs: &SessionLogs,
+) -> PoolExhausted {
+    let slots = (1..=pool_size)
+        .map(|n| {
+            let ws_name = workspace_name(segment_name, n);
+            let anc_id = ancillary_id(segment_name, n);
+            match assignment_mgr.get_active_for_ancillary(&anc_id) {
+                Some(a) => PoolSlot {
+                    ancillary_id: anc_id,
+                    workspace: ws_name,
+                    task_id: a.task_id.clone(),
+                    task_title: a.task_title.clone(),
+                    activity: detect_agent_activity(logs, &a.working_dir()),
+                    has_changes: workspace_has_changes(
+                        &a.workspace_path,
+                        a.base_branch.as_deref(),
+                        a.focus_path.as_deref(),
+                    ),
+                    assigned: true,
+                },
+                None => {
+                    let path = ws_mgr.workspace_path(segment_name, &ws_name);
+                    PoolSlot {
+                        ancillary_id: anc_id,
+                        activity: detect_agent_activity(logs, &path),
+                        has_changes: existing.contains(&ws_name)
+                            && workspace_has_changes(&path, None, None),
+                        workspace: ws_name,
+                        task_id: None,
+                        task_title: None,
+                        assigned: false,
+                    }
+                }
+            }
+        })
+        .collect();
+
+    PoolExhausted {
+        segment: segment_name.to_string(),
+        pool_size,
+        slots,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assignment::AssignmentSource;
+    use crate::error::find_coded;
+
+    #[test]
+    fn test_allocate_fails_when_pool_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(dir.path().join("a.json")).unwrap();
+        let ws_mgr = WorkspaceManager::new(dir.path().join("ws"), None);
+        let repo = dir.path().join("repo");
+        let logs = SessionLogs::new(vec![dir.path().join("sessions")]);
+
+        for n in 1..=2 {
+            mgr.create(
+                &ancillary_id("repo", n),
+                Some(&format!("t-{n}").parse().unwrap()),
+                AssignmentSource::Reference,
+                "repo",
+                dir.path().join(workspace_name("repo", n)),
+                Some(format!("Task {n}")),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        let err = allocate_ancillary(&mut mgr, &ws_mgr, "repo", &repo, 2, false, &logs).unwrap_err();
+        assert_eq!(find_coded(&err).unwrap().code, ErrorCode::PoolExhausted);
+        let exhausted = err.downcast_ref::<PoolExhausted>().unwrap();
+        assert_eq!(exhausted.slots.len(), 2);
+        assert_eq!(exhausted.slots[0].state(), "idle");
+        assert_eq!(exhausted.slots[0].suggestion().as_deref(), Some("breq abort one"));
+        assert!(exhausted.triage_lines().last().unwrap().contains("--overflow"));
+
+        let id = allocate_ancillary(&mut mgr, &ws_mgr, "repo", &repo, 2, true, &logs).unwrap();
+        assert_eq!(id, ancillary_id("repo", 3));
+        let id = allocate_ancillary(&mut mgr, &ws_mgr, "repo", &repo, 3, false, &logs).unwrap();
+        assert_eq!(id, ancillary_id("repo", 3));
+    }
+}
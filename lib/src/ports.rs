@@ -0,0 +1,211 @@
+//! Port allocation registry.
+//!
+//! `(alloc-port)` vars in toren.kdl draw their ports from a single registry
+//! at `~/.toren/ports.json` shared by every segment, so two repos whose
+//! `base + ws.num` ranges overlap can't hand the same upstream port to two
+//! workspaces. Leases are keyed by workspace path and var name: evaluating a
+//! workspace's vars again (resume, destroy, proxy reconcile) returns the same
+//! port, and destroying the workspace releases all of its leases.
+//!
+//! breq and the daemon both update the registry, so every read-modify-write
+//! happens under an exclusive lock on the file.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// A port reserved for one var of one workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortLease {
+    pub workspace: PathBuf,
+    pub var: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Leases {
+    #[serde(default)]
+    ports: BTreeMap<u16, PortLease>,
+}
+
+/// File-backed registry of allocated ports.
+pub struct PortRegistry {
+    path: PathBuf,
+}
+
+impl PortRegistry {
+    /// Registry at the default location (`~/.toren/ports.json`).
+    pub fn new() -> Self {
+        Self::at(crate::toren_root().join("ports.json"))
+    }
+
+    /// Registry backed by a specific file.
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Port leased to `workspace`'s `var`, allocating one if needed.
+    ///
+    /// New allocations take the lowest port at or above `preferred` that is
+    /// neither leased nor currently bound on localhost.
+    pub fn allocate(&self, workspace: &Path, var: &str, preferred: u16) -> Result<u16> {
+        self.with_leases(|leases| {
+            let lease = PortLease {
+                workspace: workspace.to_path_buf(),
+                var: var.to_string(),
+            };
+            if let Some((&port, _)) = leases.ports.iter().find(|(_, l)| **l == lease) {
+                return Ok((port, false));
+            }
+
+            let port = (preferred..=u16::MAX)
+                .find(|p| !leases.ports.contains_key(p) && is_bindable(*p))
+                .with_context(|| format!("No free port at or above {} for var '{}'", preferred, var))?;
+            debug!("Allocated port {} for {} ({})", port, var, workspace.display());
+            leases.ports.insert(port, lease);
+            Ok((port, true))
+        })
+    }
+
+    /// Release every port leased to `workspace`. Returns the released ports.
+    pub fn release(&self, workspace: &Path) -> Result<Vec<u16>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        self.with_leases(|leases| {
+            let released: Vec<u16> = leases
+                .ports
+                .iter()
+                .filter(|(_, l)| l.workspace == workspace)
+                .map(|(&p, _)| p)
+                .collect();
+            for port in &released {
+                leases.ports.remove(port);
+            }
+            let changed = !released.is_empty();
+            Ok((released, changed))
+        })
+    }
+
+    /// All current leases, ordered by port.
+    pub fn list(&self) -> Result<Vec<(u16, PortLease)>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        self.with_leases(|leases| {
+            let all = leases.ports.iter().map(|(&p, l)| (p, l.clone())).collect();
+            Ok((all, false))
+        })
+    }
+
+    /// Run `f` on the leases while holding the file lock, writing them back
+    /// if `f` reports a change.
+    fn with_leases<T>(&self, f: impl FnOnce(&mut Leases) -> Result<(T, bool)>) -> Result<T> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", self.path.display()))?;
+
+        let mut leases = read_leases(&mut file)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let (result, changed) = f(&mut leases)?;
+        if changed {
+            let content = serde_json::to_string_pretty(&leases)?;
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(content.as_bytes())
+                .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        }
+        Ok(result)
+    }
+}
+
+impl Default for PortRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_leases(file: &mut File) -> Result<Leases> {
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    if content.trim().is_empty() {
+        return Ok(Leases::default());
+    }
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn is_bindable(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_is_stable_per_workspace_var() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = PortRegistry::at(dir.path().join("ports.json"));
+        let ws = Path::new("/ws/toren/one");
+
+        let port = registry.allocate(ws, "web_port", 41000).unwrap();
+        assert!(port >= 41000);
+        assert_eq!(registry.allocate(ws, "web_port", 41000).unwrap(), port);
+
+        let other = registry.allocate(ws, "api_port", 41000).unwrap();
+        assert_ne!(other, port);
+    }
+
+    #[test]
+    fn test_overlapping_ranges_get_distinct_ports() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = PortRegistry::at(dir.path().join("ports.json"));
+
+        let a = registry.allocate(Path::new("/ws/app/one"), "port", 42001).unwrap();
+        let b = registry.allocate(Path::new("/ws/api/one"), "port", 42001).unwrap();
+        assert_ne!(a, b);
+
+        // A second registry handle sees the same leases on disk
+        let reopened = PortRegistry::at(dir.path().join("ports.json"));
+        assert_eq!(reopened.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_release_frees_workspace_ports() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = PortRegistry::at(dir.path().join("ports.json"));
+        let one = Path::new("/ws/toren/one");
+        let two = Path::new("/ws/toren/two");
+
+        let port = registry.allocate(one, "port", 43000).unwrap();
+        registry.allocate(two, "port", 43000).unwrap();
+
+        assert_eq!(registry.release(one).unwrap(), vec![port]);
+        let remaining = registry.list().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].1.workspace, two);
+        assert!(registry.release(one).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_release_without_registry_file_is_noop() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ports.json");
+        let registry = PortRegistry::at(path.clone());
+        assert!(registry.release(Path::new("/ws/x/one")).unwrap().is_empty());
+        assert!(!path.exists());
+    }
+}
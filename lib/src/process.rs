@@ -1,17 +1,26 @@
 //! Process discovery and termination for workspace cleanup.
 //!
-//! Finds processes whose working directory is within a workspace path,
-//! and provides graceful termination (SIGTERM + timeout + SIGKILL).
+//! Finds processes whose working directory is within a workspace path
+//! (with their command line and start time, for `breq ps`), and provides
+//! graceful termination (SIGTERM + timeout + SIGKILL).
+//!
+//! Enumeration is implemented for Linux and macOS; elsewhere no workspace
+//! processes are found. On Windows, termination goes through `taskkill`.
 
+use serde::Serialize;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, info, warn};
 
 /// Information about a process running in a workspace.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ProcessInfo {
     pub pid: i32,
     pub name: String,
+    /// Full command line, or the name when it can't be read
+    pub command: String,
+    /// When the process started (RFC 3339), if the platform reports it
+    pub started_at: Option<String>,
 }
 
 impl std::fmt::Display for ProcessInfo {
@@ -53,14 +62,43 @@ pub fn find_workspace_processes(workspace_path: &Path) -> Vec<ProcessInfo> {
         if cwd_canonical.starts_with(&workspace_canonical) {
             let name = process_name(pid).unwrap_or_else(|| "<unknown>".to_string());
             debug!("Found workspace process: {} (pid {})", name, pid);
-            processes.push(ProcessInfo { pid, name });
+            let command = process_command(pid).unwrap_or_else(|| name.clone());
+            let started_at = process_start(pid)
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+            processes.push(ProcessInfo {
+                pid,
+                name,
+                command,
+                started_at,
+            });
         }
     }
 
+    processes.sort_by_key(|p| p.pid);
     processes
 }
 
+/// The workspace processes with the given pids. Fails if any pid isn't a
+/// process in the workspace, so a typo can't signal something unrelated.
+pub fn select_workspace_processes(workspace_path: &Path, pids: &[i32]) -> anyhow::Result<Vec<ProcessInfo>> {
+    let processes = find_workspace_processes(workspace_path);
+    let unknown: Vec<String> = pids
+        .iter()
+        .filter(|pid| !processes.iter().any(|p| p.pid == **pid))
+        .map(|pid| pid.to_string())
+        .collect();
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "Not running in {}: pid {}",
+            workspace_path.display(),
+            unknown.join(", ")
+        );
+    }
+    Ok(processes.into_iter().filter(|p| pids.contains(&p.pid)).collect())
+}
+
 /// Terminate processes: SIGTERM first, wait up to `timeout`, then SIGKILL survivors.
+#[cfg(unix)]
 pub fn terminate_processes(processes: &[ProcessInfo], timeout: Duration) -> anyhow::Result<()> {
     use nix::sys::signal::{kill, Signal};
     use nix::unistd::Pid;
@@ -107,6 +145,71 @@ pub fn terminate_processes(processes: &[ProcessInfo], timeout: Duration) -> anyh
     }
 }
 
+/// Terminate processes: `taskkill` first, wait up to `timeout`, then
+/// `taskkill /F` survivors (with their child processes).
+#[cfg(windows)]
+pub fn terminate_processes(processes: &[ProcessInfo], timeout: Duration) -> anyhow::Result<()> {
+    let taskkill = |pid: i32, force: bool| {
+        let mut cmd = std::process::Command::new("taskkill");
+        cmd.args(["/PID", &pid.to_string(), "/T"]);
+        if force {
+            cmd.arg("/F");
+        }
+        cmd.stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+    };
+
+    for proc in processes {
+        info!("Terminating {}", proc);
+        if let Err(e) = taskkill(proc.pid, false) {
+            debug!("taskkill failed for {}: {}", proc, e);
+        }
+    }
+
+    let start = Instant::now();
+    loop {
+        let alive: Vec<_> = processes.iter().filter(|p| is_running(p.pid)).collect();
+        if alive.is_empty() {
+            info!("All workspace processes terminated gracefully");
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            for proc in &alive {
+                warn!("Force killing {}", proc);
+                let _ = taskkill(proc.pid, true);
+            }
+            info!("Force-killed {} remaining process(es)", alive.len());
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// Whether a process with this pid exists. A process we may not signal
+/// (EPERM) still counts as running.
+#[cfg(unix)]
+pub fn is_running(pid: i32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    pid > 0 && !matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH))
+}
+
+/// Whether a process with this pid exists, as far as `tasklist` can tell.
+#[cfg(windows)]
+pub fn is_running(pid: i32) -> bool {
+    if pid <= 0 {
+        return false;
+    }
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).contains(&format!("\"{}\"", pid)))
+        .unwrap_or(false)
+}
+
 // ---------------------------------------------------------------------------
 // Platform-specific process enumeration
 // ---------------------------------------------------------------------------
@@ -214,6 +317,51 @@ fn process_name(pid: i32) -> Option<String> {
     cstr.to_str().ok().map(|s| s.to_string())
 }
 
+#[cfg(target_os = "macos")]
+fn process_command(_pid: i32) -> Option<String> {
+    // Reading another process's argv needs the KERN_PROCARGS2 sysctl; the
+    // name is enough to tell processes apart here
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn process_start(pid: i32) -> Option<SystemTime> {
+    use std::os::raw::{c_int, c_void};
+
+    const PROC_PIDTBSDINFO: c_int = 3;
+    // Struct layout from <sys/proc_info.h>: proc_bsdinfo is 136 bytes, with
+    // pbi_start_tvsec (u64) at offset 120
+    const PROC_BSDINFO_SIZE: usize = 136;
+    const START_TVSEC_OFFSET: usize = 120;
+
+    extern "C" {
+        fn proc_pidinfo(
+            pid: c_int,
+            flavor: c_int,
+            arg: u64,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+    }
+
+    let mut buf = [0u8; PROC_BSDINFO_SIZE];
+    let ret = unsafe {
+        proc_pidinfo(
+            pid,
+            PROC_PIDTBSDINFO,
+            0,
+            buf.as_mut_ptr() as *mut c_void,
+            PROC_BSDINFO_SIZE as c_int,
+        )
+    };
+    if ret < PROC_BSDINFO_SIZE as c_int {
+        return None;
+    }
+
+    let secs = u64::from_ne_bytes(buf[START_TVSEC_OFFSET..START_TVSEC_OFFSET + 8].try_into().ok()?);
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
 // Linux: read from /proc filesystem
 #[cfg(target_os = "linux")]
 fn list_all_pids() -> Vec<i32> {
@@ -238,6 +386,37 @@ fn process_name(pid: i32) -> Option<String> {
         .map(|s| s.trim().to_string())
 }
 
+#[cfg(target_os = "linux")]
+fn process_command(pid: i32) -> Option<String> {
+    let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let args: Vec<String> = raw
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!args.is_empty()).then(|| args.join(" "))
+}
+
+#[cfg(target_os = "linux")]
+fn process_start(pid: i32) -> Option<SystemTime> {
+    // Field 22 of /proc/<pid>/stat is the start time in clock ticks since
+    // boot. USER_HZ is 100 on every architecture Linux exposes to userspace.
+    const USER_HZ: f64 = 100.0;
+
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name (field 2) is parenthesized and may contain spaces
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    let start_ticks: f64 = after_comm.split_whitespace().nth(19)?.parse().ok()?;
+    let uptime: f64 = std::fs::read_to_string("/proc/uptime")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    let age = (uptime - start_ticks / USER_HZ).max(0.0);
+    SystemTime::now().checked_sub(Duration::from_secs_f64(age))
+}
+
 // Fallback for unsupported platforms: process discovery is a no-op
 #[cfg(not(any(target_os = "macos", target_os = "linux")))]
 fn list_all_pids() -> Vec<i32> {
@@ -253,3 +432,75 @@ fn pidcwd(_pid: i32) -> Option<PathBuf> {
 fn process_name(_pid: i32) -> Option<String> {
     None
 }
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn process_command(_pid: i32) -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn process_start(_pid: i32) -> Option<SystemTime> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inventory_lists_workspace_processes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .current_dir(tmp.path())
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+
+        // The child shows the test binary's command line until it execs
+        let started = Instant::now();
+        let process = loop {
+            let found = find_workspace_processes(tmp.path());
+            match found.into_iter().find(|p| p.pid == pid && p.command == "sleep 30") {
+                Some(process) => break process,
+                None if started.elapsed() < Duration::from_secs(5) => {
+                    std::thread::sleep(Duration::from_millis(10))
+                }
+                None => panic!("sleep 30 not found in the workspace"),
+            }
+        };
+        assert!(process.started_at.is_some());
+
+        assert_eq!(select_workspace_processes(tmp.path(), &[pid]).unwrap().len(), 1);
+        assert!(select_workspace_processes(tmp.path(), &[pid, 1]).is_err());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
+
+// Elsewhere: no enumeration, so no workspace processes are found
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn list_all_pids() -> Vec<i32> {
+    Vec::new()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn pidcwd(_pid: i32) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn process_name(_pid: i32) -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn process_command(_pid: i32) -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn process_start(_pid: i32) -> Option<SystemTime> {
+    None
+}
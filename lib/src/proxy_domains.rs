@@ -0,0 +1,219 @@
+//! Station domains of segments, and which repo owns each.
+//!
+//! A segment's proxy routes live under its station domain, by default
+//! `{repo}.{local_domain}` (so workspace `one` of repo `web` is
+//! `one.web.lvh.me`). `[proxy] host_template` changes the scheme: it renders
+//! the host of a workspace's unnamed route, must begin with `{{ ws.name }}.`,
+//! and everything after that label is the station domain.
+//!
+//! Segments are named after their directory, so two repos both called `web`
+//! compute the same domain and would take over each other's routes. The
+//! first repo to add a route claims the domain in `~/.toren/proxy-domains.json`;
+//! another repo computing the same domain gets it with the first label
+//! qualified by the repo's parent directory (`one.web-oss.lvh.me`). A claim
+//! whose repo no longer exists is taken over.
+//!
+//! breq and the daemon both claim domains, so every read-modify-write
+//! happens under an exclusive lock on the file, as in [`crate::ports`].
+
+use anyhow::{Context, Result};
+use minijinja::{context, Environment};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Host of a workspace's unnamed route when `[proxy] host_template` is unset.
+pub const DEFAULT_HOST_TEMPLATE: &str = "{{ ws.name }}.{{ repo }}.{{ domain }}";
+
+/// Stands in for the workspace name while rendering a host template, so the
+/// station domain (the rest of the host) can be split off.
+const WS_PLACEHOLDER: &str = "toren-ws";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Claims {
+    /// Station domain -> repo root that claimed it
+    #[serde(default)]
+    domains: BTreeMap<String, PathBuf>,
+}
+
+/// The station domain `repo_root`'s routes use: the one `template` renders,
+/// or its qualified fallback if another repo claimed it first.
+pub fn station_domain(repo_root: &Path, local_domain: &str, template: Option<&str>) -> Result<String> {
+    let preferred = preferred_domain(repo_root, local_domain, template)?;
+    DomainRegistry::new().resolve(repo_root, &preferred)
+}
+
+/// The station domain `template` renders for `repo_root`, before checking
+/// for collisions. Template variables: `ws.name`, `repo` (directory name),
+/// `segment` (segment name, the same directory name), `parent` (the repo's
+/// parent directory name) and `domain` (`[proxy] domain`).
+pub fn preferred_domain(repo_root: &Path, local_domain: &str, template: Option<&str>) -> Result<String> {
+    let repo = dir_name(repo_root).unwrap_or("unknown");
+    let parent = repo_root.parent().and_then(dir_name).unwrap_or_default();
+    let template = template.unwrap_or(DEFAULT_HOST_TEMPLATE);
+
+    let host = Environment::new()
+        .render_str(
+            template,
+            context! {
+                ws => context! { name => WS_PLACEHOLDER },
+                repo => repo,
+                segment => repo,
+                parent => parent,
+                domain => local_domain,
+            },
+        )
+        .with_context(|| format!("Failed to render [proxy] host_template: {}", template))?;
+    match host.strip_prefix(WS_PLACEHOLDER).and_then(|rest| rest.strip_prefix('.')) {
+        Some(domain) if !domain.is_empty() && !domain.contains(WS_PLACEHOLDER) => Ok(domain.to_string()),
+        _ => anyhow::bail!(
+            "[proxy] host_template must begin with `{{{{ ws.name }}}}.` and use it only there, got: {}",
+            template
+        ),
+    }
+}
+
+/// `domain` with its first label qualified by `repo_root`'s parent directory.
+fn fallback_domain(domain: &str, repo_root: &Path) -> String {
+    let qualifier = repo_root.parent().and_then(dir_name).unwrap_or("2");
+    match domain.split_once('.') {
+        Some((first, rest)) => format!("{}-{}.{}", first, qualifier, rest),
+        None => format!("{}-{}", domain, qualifier),
+    }
+}
+
+fn dir_name(path: &Path) -> Option<&str> {
+    path.file_name().and_then(|n| n.to_str())
+}
+
+/// File-backed record of which repo claimed each station domain.
+pub struct DomainRegistry {
+    path: PathBuf,
+}
+
+impl DomainRegistry {
+    /// Registry at the default location (`~/.toren/proxy-domains.json`).
+    pub fn new() -> Self {
+        Self::at(crate::toren_root().join("proxy-domains.json"))
+    }
+
+    /// Registry backed by a specific file.
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The domain `repo_root` would get for `preferred`, without claiming it.
+    pub fn resolve(&self, repo_root: &Path, preferred: &str) -> Result<String> {
+        if !self.path.exists() {
+            return Ok(preferred.to_string());
+        }
+        self.with_claims(|claims| Ok((choose(claims, repo_root, preferred), false)))
+    }
+
+    /// Claim the domain `repo_root` gets for `preferred` (see [`Self::resolve`]).
+    pub fn claim(&self, repo_root: &Path, preferred: &str) -> Result<String> {
+        self.with_claims(|claims| {
+            let domain = choose(claims, repo_root, preferred);
+            if claims.domains.get(&domain).map(PathBuf::as_path) == Some(repo_root) {
+                return Ok((domain, false));
+            }
+            debug!("Claimed station domain {} for {}", domain, repo_root.display());
+            claims.domains.insert(domain.clone(), repo_root.to_path_buf());
+            Ok((domain, true))
+        })
+    }
+
+    /// Run `f` on the claims while holding the file lock, writing them back
+    /// if `f` reports a change.
+    fn with_claims<T>(&self, f: impl FnOnce(&mut Claims) -> Result<(T, bool)>) -> Result<T> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", self.path.display()))?;
+
+        let mut claims = read_claims(&mut file)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let (result, changed) = f(&mut claims)?;
+        if changed {
+            let content = serde_json::to_string_pretty(&claims)?;
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(content.as_bytes())
+                .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        }
+        Ok(result)
+    }
+}
+
+impl Default for DomainRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `preferred` unless another existing repo holds it; a repo that already
+/// fell back keeps its fallback, so its routes don't move if the other repo
+/// goes away.
+fn choose(claims: &Claims, repo_root: &Path, preferred: &str) -> String {
+    let fallback = fallback_domain(preferred, repo_root);
+    let owned_by = |domain: &str| claims.domains.get(domain).map(PathBuf::as_path);
+    if owned_by(&fallback) == Some(repo_root) {
+        return fallback;
+    }
+    match owned_by(preferred) {
+        Some(owner) if owner != repo_root && owner.exists() => fallback,
+        _ => preferred.to_string(),
+    }
+}
+
+fn read_claims(file: &mut File) -> Result<Claims> {
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    if content.trim().is_empty() {
+        return Ok(Claims::default());
+    }
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_named_repos_get_distinct_domains() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work/web");
+        let oss = dir.path().join("oss/web");
+        std::fs::create_dir_all(&work).unwrap();
+        std::fs::create_dir_all(&oss).unwrap();
+
+        let preferred = preferred_domain(&work, "lvh.me", None).unwrap();
+        assert_eq!(preferred, "web.lvh.me");
+        assert_eq!(
+            preferred_domain(&work, "lvh.me", Some("{{ ws.name }}.{{ segment }}.{{ parent }}.{{ domain }}")).unwrap(),
+            "web.work.lvh.me"
+        );
+        assert!(preferred_domain(&work, "lvh.me", Some("{{ repo }}.{{ domain }}")).is_err());
+
+        let registry = DomainRegistry::at(dir.path().join("proxy-domains.json"));
+        assert_eq!(registry.claim(&work, &preferred).unwrap(), "web.lvh.me");
+        assert_eq!(registry.resolve(&oss, &preferred).unwrap(), "web-oss.lvh.me");
+        assert_eq!(registry.claim(&oss, &preferred).unwrap(), "web-oss.lvh.me");
+        assert_eq!(registry.claim(&work, &preferred).unwrap(), "web.lvh.me");
+
+        // The fallback sticks once claimed, even after the first repo is gone
+        std::fs::remove_dir_all(&work).unwrap();
+        assert_eq!(registry.resolve(&oss, &preferred).unwrap(), "web-oss.lvh.me");
+    }
+}
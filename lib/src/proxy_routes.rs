@@ -0,0 +1,320 @@
+//! Station proxy routes attributed to workspaces, and garbage collection of
+//! stale ones.
+//!
+//! Proxy routes are normally forgotten by the workspace's destroy hooks, but
+//! a crashed daemon or a workspace deleted by hand leaves its routes behind in
+//! the proxy. Toren's routes are recognizable by their host:
+//! `[{name}.]{workspace}.{repo}.{local_domain}`, where `{repo}.{local_domain}`
+//! is the `STATION_DOMAIN` of a known segment. A route whose workspace is
+//! neither on disk nor held by an active assignment is stale and is removed
+//! with `station forget`. Routes under other domains are left alone.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Exit code `station` uses when its proxy backend is unreachable (`EX_TEMPFAIL`).
+const STATION_EXIT_UNAVAILABLE: i32 = 75;
+
+/// A route as reported by `station list --json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationRoute {
+    pub host: String,
+    pub upstream: String,
+    pub port: u16,
+    #[serde(default)]
+    pub tls: bool,
+}
+
+/// A segment's station domain, used to attribute routes to workspaces.
+#[derive(Debug, Clone)]
+pub struct SegmentDomain {
+    pub segment: String,
+    /// `{repo}.{local_domain}`
+    pub domain: String,
+}
+
+/// A route whose workspace no longer exists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleRoute {
+    pub host: String,
+    pub segment: String,
+    pub workspace: String,
+    /// Route name relative to `domain` (what `station forget` expects)
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub domain: String,
+}
+
+/// A station route that belongs to a toren workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceRoute {
+    pub host: String,
+    pub upstream: String,
+    pub port: u16,
+    pub tls: bool,
+    pub segment: String,
+    pub workspace: String,
+    /// Route name relative to `domain` (what `station forget` expects)
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub domain: String,
+}
+
+/// Routes under a known segment's domain, attributed to their workspace.
+/// Routes under other domains are skipped.
+pub fn workspace_routes(routes: &[StationRoute], segments: &[SegmentDomain]) -> Vec<WorkspaceRoute> {
+    routes
+        .iter()
+        .filter_map(|route| {
+            let (seg, name) = segments.iter().find_map(|seg| {
+                let name = route.host.strip_suffix(&seg.domain)?.strip_suffix('.')?;
+                Some((seg, name))
+            })?;
+            let workspace = name.rsplit('.').next().filter(|w| !w.is_empty())?;
+            Some(WorkspaceRoute {
+                host: route.host.clone(),
+                upstream: route.upstream.clone(),
+                port: route.port,
+                tls: route.tls,
+                segment: seg.segment.clone(),
+                workspace: workspace.to_string(),
+                name: name.to_string(),
+                domain: seg.domain.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Routes that belong to a known segment but whose workspace isn't live.
+///
+/// `is_live(segment, workspace)` decides whether a workspace still exists.
+/// Each stale host is reported once, even if it is routed on several ports.
+pub fn stale_routes(
+    routes: &[StationRoute],
+    segments: &[SegmentDomain],
+    is_live: impl Fn(&str, &str) -> bool,
+) -> Vec<StaleRoute> {
+    let mut stale: Vec<StaleRoute> = Vec::new();
+    for route in workspace_routes(routes, segments) {
+        if is_live(&route.segment, &route.workspace) || stale.iter().any(|s| s.host == route.host) {
+            continue;
+        }
+        stale.push(StaleRoute {
+            host: route.host,
+            segment: route.segment,
+            workspace: route.workspace,
+            name: route.name,
+            domain: route.domain,
+        });
+    }
+    stale
+}
+
+/// Render routes as a standalone Caddyfile, one site block per route.
+///
+/// Mirrors what station registers through the admin API, for running Caddy
+/// from a config file instead.
+pub fn to_caddyfile(routes: &[WorkspaceRoute]) -> String {
+    let mut out = String::from("# Generated by `breq proxy export` from active workspaces' toren.kdl\n");
+    for route in routes {
+        out.push_str(&format!(
+            "\n# {}/{}\n{} {{\n\treverse_proxy {}\n}}\n",
+            route.segment,
+            route.workspace,
+            site_address(route),
+            caddy_upstream(&route.upstream),
+        ));
+    }
+    out
+}
+
+/// `scheme://host[:port]`, leaving out the scheme's default port.
+fn site_address(route: &WorkspaceRoute) -> String {
+    let scheme = if route.tls { "https" } else { "http" };
+    match (route.tls, route.port) {
+        (true, 443) | (false, 80) => format!("{}://{}", scheme, route.host),
+        _ => format!("{}://{}:{}", scheme, route.host, route.port),
+    }
+}
+
+/// Station accepts a bare port as upstream; Caddy wants `host:port`.
+fn caddy_upstream(upstream: &str) -> String {
+    if upstream.parse::<u16>().is_ok() {
+        format!("localhost:{}", upstream)
+    } else {
+        upstream.to_string()
+    }
+}
+
+/// List every station-managed route.
+#[tracing::instrument(name = "proxy.list", skip_all)]
+pub fn list_routes(backend: Option<&str>) -> Result<Vec<StationRoute>> {
+    let mut cmd = Command::new("station");
+    cmd.args(["list", "--json"]);
+    if let Some(backend) = backend {
+        cmd.env("STATION_BACKEND", backend);
+    }
+    let output = cmd
+        .output()
+        .context("Failed to run `station list`. Is station installed?")?;
+    if !output.status.success() {
+        if output.status.code() == Some(STATION_EXIT_UNAVAILABLE) {
+            anyhow::bail!("Proxy backend is unreachable");
+        }
+        anyhow::bail!(
+            "station list failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    serde_json::from_slice(&output.stdout).context("Failed to parse `station list` output")
+}
+
+/// Route `name` under `domain` (host `{name}.{domain}`, listening on `port`)
+/// to `upstream`, as a toren.kdl `proxy` action does.
+#[tracing::instrument(name = "proxy.register", skip(backend))]
+pub fn register(
+    name: &str,
+    domain: &str,
+    upstream: &str,
+    port: u16,
+    tls: bool,
+    backend: Option<&str>,
+) -> Result<()> {
+    let host = format!("{}.{}", name, domain);
+    let mut cmd = Command::new("station");
+    cmd.arg("proxy")
+        .arg(name)
+        .arg("-u")
+        .arg(upstream)
+        .arg("-p")
+        .arg(port.to_string())
+        .env("STATION_DOMAIN", domain);
+    if tls {
+        cmd.arg("--tls");
+    }
+    if let Some(backend) = backend {
+        cmd.env("STATION_BACKEND", backend);
+    }
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run station proxy for '{}'", host))?;
+    if !output.status.success() {
+        if output.status.code() == Some(STATION_EXIT_UNAVAILABLE) {
+            anyhow::bail!("Proxy backend is unreachable");
+        }
+        anyhow::bail!(
+            "station proxy failed for '{}': {}",
+            host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Remove all routes for `name` under `domain` (the route's host).
+#[tracing::instrument(name = "proxy.forget", skip(backend))]
+pub fn forget(name: &str, domain: &str, backend: Option<&str>) -> Result<()> {
+    let host = format!("{}.{}", name, domain);
+    let mut cmd = Command::new("station");
+    cmd.arg("forget").arg(name).env("STATION_DOMAIN", domain);
+    if let Some(backend) = backend {
+        cmd.env("STATION_BACKEND", backend);
+    }
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run station forget for '{}'", host))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "station forget failed for '{}': {}",
+            host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(host: &str, port: u16) -> StationRoute {
+        StationRoute {
+            host: host.to_string(),
+            upstream: "localhost:5173".to_string(),
+            port,
+            tls: false,
+        }
+    }
+
+    fn segments() -> Vec<SegmentDomain> {
+        vec![SegmentDomain {
+            segment: "toren".to_string(),
+            domain: "toren.lvh.me".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_stale_routes_matches_dead_workspaces() {
+        let routes = vec![
+            route("one.toren.lvh.me", 80),
+            route("api.two.toren.lvh.me", 80),
+            route("api.two.toren.lvh.me", 443),
+            route("three.toren.lvh.me", 80),
+        ];
+        let stale = stale_routes(&routes, &segments(), |seg, ws| seg == "toren" && ws == "one");
+
+        let hosts: Vec<_> = stale.iter().map(|s| s.host.as_str()).collect();
+        assert_eq!(hosts, vec!["api.two.toren.lvh.me", "three.toren.lvh.me"]);
+        assert_eq!(stale[0].workspace, "two");
+        assert_eq!(stale[0].name, "api.two");
+        assert_eq!(stale[0].domain, "toren.lvh.me");
+    }
+
+    #[test]
+    fn test_to_caddyfile() {
+        let mut tls = route("web.one.toren.lvh.me", 443);
+        tls.tls = true;
+        tls.upstream = "5171".to_string();
+        let routes = workspace_routes(&[tls, route("one.toren.lvh.me", 8080)], &segments());
+
+        let caddyfile = to_caddyfile(&routes);
+        assert!(caddyfile.contains("# toren/one\nhttps://web.one.toren.lvh.me {\n\treverse_proxy localhost:5171\n}"));
+        assert!(caddyfile.contains("http://one.toren.lvh.me:8080 {\n\treverse_proxy localhost:5173\n}"));
+    }
+
+    #[test]
+    fn test_stale_routes_ignores_foreign_hosts() {
+        let routes = vec![
+            route("myapp.localhost", 80),
+            route("one.other.lvh.me", 80),
+            route("toren.lvh.me", 80),
+            route("xtoren.lvh.me", 80),
+        ];
+        assert!(stale_routes(&routes, &segments(), |_, _| false).is_empty());
+    }
+
+    #[test]
+    fn test_workspace_routes_attributes_each_port() {
+        let routes = vec![
+            route("one.toren.lvh.me", 80),
+            route("api.one.toren.lvh.me", 443),
+            route("myapp.localhost", 80),
+        ];
+        let routes = workspace_routes(&routes, &segments());
+        assert_eq!(routes.len(), 2);
+        assert!(routes.iter().all(|r| r.segment == "toren" && r.workspace == "one"));
+        assert_eq!(routes[1].name, "api.one");
+        assert_eq!(routes[1].port, 443);
+    }
+
+    #[test]
+    fn test_parses_station_list_json() {
+        let json = r#"[{"host":"one.toren.lvh.me","upstream":"localhost:5173","port":80,"tls":false}]"#;
+        let routes: Vec<StationRoute> = serde_json::from_str(json).unwrap();
+        assert_eq!(routes[0].host, "one.toren.lvh.me");
+        assert_eq!(routes[0].port, 80);
+    }
+}
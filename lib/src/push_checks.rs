@@ -0,0 +1,195 @@
+//! Checks on a workspace's changes before `--push` publishes them (`[push]`).
+//!
+//! Agents commit whatever they leave in the workspace, so a push could carry
+//! credentials or stray files. The checks look at the diff the push would
+//! publish: changed paths against `blocked_paths`, its size against
+//! `max_diff_lines`, and the whole diff through an optional `secret_scan`
+//! command. Any finding fails the push; `--no-verify` skips the checks.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::config::PushConfig;
+
+/// Longest secret scanner output kept in a finding.
+const MAX_SCAN_OUTPUT: usize = 4000;
+
+/// One reason a push was refused.
+#[derive(Debug, Clone, Serialize)]
+pub struct PushFinding {
+    /// `blocked_path`, `diff_size` or `secret_scan`
+    pub check: String,
+    pub detail: String,
+}
+
+/// The push checks found problems; carries the full report.
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+pub struct PushChecksFailed {
+    pub findings: Vec<PushFinding>,
+}
+
+impl fmt::Display for PushChecksFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Push checks failed ({} finding(s)); nothing was pushed:", self.findings.len())?;
+        for finding in &self.findings {
+            write!(f, "\n  [{}] {}", finding.check, finding.detail)?;
+        }
+        write!(f, "\nFix the changes, or pass --no-verify to push anyway.")
+    }
+}
+
+/// Run the configured checks on `diff` (git format). Fails with
+/// [`PushChecksFailed`] when any check finds something.
+pub fn verify(config: &PushConfig, workspace_path: &Path, diff: &str) -> Result<()> {
+    let stats = DiffStats::parse(diff);
+    let mut findings = Vec::new();
+
+    let patterns = config
+        .blocked_paths
+        .iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid [push] blocked_paths glob: {}", p)))
+        .collect::<Result<Vec<_>>>()?;
+    for path in &stats.files {
+        if let Some(pattern) = patterns.iter().find(|p| p.matches(path)) {
+            findings.push(PushFinding {
+                check: "blocked_path".to_string(),
+                detail: format!("{} matches blocked path '{}'", path, pattern),
+            });
+        }
+    }
+
+    if config.max_diff_lines > 0 && stats.lines > config.max_diff_lines {
+        findings.push(PushFinding {
+            check: "diff_size".to_string(),
+            detail: format!(
+                "{} changed lines across {} file(s), over the limit of {}",
+                stats.lines,
+                stats.files.len(),
+                config.max_diff_lines
+            ),
+        });
+    }
+
+    if let Some(ref command) = config.secret_scan {
+        if let Some(output) = scan(command, workspace_path, diff)? {
+            findings.push(PushFinding {
+                check: "secret_scan".to_string(),
+                detail: format!("`{}` reported:\n{}", command, output),
+            });
+        }
+    }
+
+    if findings.is_empty() {
+        Ok(())
+    } else {
+        Err(PushChecksFailed { findings }.into())
+    }
+}
+
+/// Run the scanner with the diff on stdin. Its output when it exits non-zero.
+fn scan(command: &str, workspace_path: &Path, diff: &str) -> Result<Option<String>> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .current_dir(workspace_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run secret scan: {}", command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A scanner that exits early closes the pipe; its exit status still counts
+        let _ = stdin.write_all(diff.as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(None);
+    }
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let text = text.trim();
+    Ok(Some(match text.char_indices().nth(MAX_SCAN_OUTPUT) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None if text.is_empty() => format!("exited with {}", output.status),
+        None => text.to_string(),
+    }))
+}
+
+/// Changed paths and added plus removed lines of a git-format diff.
+#[derive(Debug, Default, PartialEq)]
+struct DiffStats {
+    files: Vec<String>,
+    lines: u64,
+}
+
+impl DiffStats {
+    fn parse(diff: &str) -> Self {
+        let mut stats = Self::default();
+        for line in diff.lines() {
+            if let Some(header) = line.strip_prefix("diff --git ") {
+                // `a/<old> b/<new>`; the new path is what gets pushed
+                if let Some((_, new)) = header.rsplit_once(" b/") {
+                    stats.files.push(new.to_string());
+                }
+            } else if line.starts_with("+++") || line.starts_with("---") {
+                continue;
+            } else if line.starts_with('+') || line.starts_with('-') {
+                stats.lines += 1;
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
+-fn old() {}
++fn new() {}
+diff --git a/.env b/.env
+new file mode 100644
+--- /dev/null
++++ b/.env
+@@ -0,0 +1 @@
++API_KEY=hunter2
+";
+
+    #[test]
+    fn test_verify_reports_every_finding() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(
+            DiffStats::parse(DIFF),
+            DiffStats {
+                files: vec!["src/lib.rs".to_string(), ".env".to_string()],
+                lines: 3,
+            }
+        );
+
+        let config = PushConfig {
+            secret_scan: Some("! grep -n API_KEY".to_string()),
+            max_diff_lines: 2,
+            ..PushConfig::default()
+        };
+        let err = verify(&config, tmp.path(), DIFF).unwrap_err();
+        let failed = err.downcast_ref::<PushChecksFailed>().unwrap();
+        let checks: Vec<&str> = failed.findings.iter().map(|f| f.check.as_str()).collect();
+        assert_eq!(checks, ["blocked_path", "diff_size", "secret_scan"]);
+        assert!(failed.findings[2].detail.contains("API_KEY=hunter2"));
+
+        let clean = PushConfig {
+            blocked_paths: Vec::new(),
+            secret_scan: Some("! grep -q PRIVATE".to_string()),
+            ..PushConfig::default()
+        };
+        verify(&clean, tmp.path(), DIFF).unwrap();
+    }
+}
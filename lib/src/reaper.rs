@@ -0,0 +1,196 @@
+//! Leftover processes of agent sessions that ended, for the daemon's reaper.
+//!
+//! An agent that crashes mid-run can leave dev servers and watchers it
+//! started running in its workspace indefinitely. A session has ended when
+//! the daemon isn't running the agent, no agent process is left in the
+//! workspace and the session log has been quiet for `reap_idle_minutes`.
+//! Its leftovers are the workspace processes that started before the last
+//! session log event: anything started later (a shell opened with `breq go`,
+//! a server the user started by hand) wasn't started by the agent.
+//!
+//! The idle reaper (`[ancillaries.idle_reaper]`) looks at whole assignments
+//! instead: one whose workspace has no changes and whose agent hasn't been
+//! active for `idle_days` is flagged or aborted (see [`idle_for`]).
+
+use crate::composite_status::SessionEvent;
+use crate::process::ProcessInfo;
+use crate::{AgentKind, AncillariesConfig};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// What the reaper does with leftover processes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReapPolicy {
+    /// Leave them alone
+    Off,
+    /// Log them and broadcast an event
+    #[default]
+    Notify,
+    /// Terminate them (SIGTERM, then SIGKILL)
+    Terminate,
+}
+
+/// What the idle reaper does with an idle assignment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleAction {
+    /// Log it and broadcast an event
+    #[default]
+    Flag,
+    /// Abort it, returning its task to open
+    Abort,
+}
+
+/// `[ancillaries.idle_reaper]`: flag or abort assignments left idle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdleReaperConfig {
+    /// Days without changes or agent activity before an assignment counts
+    /// as idle (default: 0, which disables the reaper)
+    #[serde(default)]
+    pub idle_days: u64,
+
+    /// flag or abort (default: flag)
+    #[serde(default)]
+    pub action: IdleAction,
+
+    /// Report what would be aborted without aborting it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// How long an assignment has been idle, or None if it isn't idle for
+/// `idle_after` yet. Activity is the latest of the assignment's creation or
+/// last update and its last session log event; an assignment whose agent
+/// the daemon is running, or whose workspace has changes, is never idle.
+pub fn idle_for(
+    last_update: SystemTime,
+    event: Option<SessionEvent>,
+    agent_running: bool,
+    has_changes: bool,
+    idle_after: Duration,
+    now: SystemTime,
+) -> Option<Duration> {
+    if agent_running || has_changes {
+        return None;
+    }
+    let last_activity = event.map_or(last_update, |e| e.at.max(last_update));
+    let idle = now.duration_since(last_activity).unwrap_or_default();
+    (idle >= idle_after).then_some(idle)
+}
+
+/// Process names of the agents, including configured binary overrides.
+pub fn agent_process_names(config: &AncillariesConfig) -> Vec<String> {
+    let mut names = Vec::new();
+    for &kind in AgentKind::all() {
+        names.push(kind.binary_name().to_string());
+        if let Some(binary) = config.agent_command(kind).binary {
+            let name = std::path::Path::new(&binary)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or(binary);
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// The processes left over from an ended session, or None if the session
+/// hasn't ended. Without a session log there is no session to attribute
+/// processes to, so nothing is reaped.
+pub fn leftover_processes(
+    processes: &[ProcessInfo],
+    event: Option<SessionEvent>,
+    agent_running: bool,
+    agent_names: &[String],
+    idle_after: Duration,
+    now: SystemTime,
+) -> Option<Vec<ProcessInfo>> {
+    let event = event?;
+    if agent_running || now.duration_since(event.at).unwrap_or_default() < idle_after {
+        return None;
+    }
+    if processes.iter().any(|p| agent_names.contains(&p.name)) {
+        return None;
+    }
+    let last_event = DateTime::<Utc>::from(event.at);
+    Some(
+        processes
+            .iter()
+            .filter(|p| {
+                p.started_at
+                    .as_deref()
+                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                    .is_some_and(|started| started <= last_event)
+            })
+            .cloned()
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDLE: Duration = Duration::from_secs(30 * 60);
+
+    fn process(pid: i32, name: &str, mins_ago: u64, now: SystemTime) -> ProcessInfo {
+        let started = now - Duration::from_secs(mins_ago * 60);
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            command: name.to_string(),
+            started_at: Some(DateTime::<Utc>::from(started).to_rfc3339()),
+        }
+    }
+
+    #[test]
+    fn test_leftovers_only_after_session_ends() {
+        let now = SystemTime::now();
+        let names = agent_process_names(&AncillariesConfig::default());
+        let event = Some(SessionEvent {
+            at: now - Duration::from_secs(60 * 60),
+            mid_turn: true,
+        });
+        let processes = vec![
+            process(10, "node", 90, now),
+            process(11, "zsh", 5, now),
+        ];
+
+        let leftovers = leftover_processes(&processes, event, false, &names, IDLE, now).unwrap();
+        assert_eq!(leftovers.iter().map(|p| p.pid).collect::<Vec<_>>(), vec![10]);
+
+        // Still running, recently active, or never logged: not ended
+        assert!(leftover_processes(&processes, event, true, &names, IDLE, now).is_none());
+        assert!(leftover_processes(&processes, event, false, &names, Duration::from_secs(2 * 3600), now).is_none());
+        assert!(leftover_processes(&processes, None, false, &names, IDLE, now).is_none());
+
+        // An agent process in the workspace means the session is alive
+        let mut with_agent = processes.clone();
+        with_agent.push(process(12, "claude", 90, now));
+        assert!(leftover_processes(&with_agent, event, false, &names, IDLE, now).is_none());
+    }
+
+    #[test]
+    fn test_idle_for_needs_no_changes_and_no_activity() {
+        const DAY: Duration = Duration::from_secs(24 * 3600);
+        let now = SystemTime::now();
+        let created = now - 10 * DAY;
+        let event = |days: u32| {
+            Some(SessionEvent {
+                at: now - days * DAY,
+                mid_turn: false,
+            })
+        };
+
+        assert_eq!(idle_for(created, event(8), false, false, 7 * DAY, now), Some(8 * DAY));
+        assert_eq!(idle_for(created, None, false, false, 7 * DAY, now), Some(10 * DAY));
+
+        // Recent activity, changes or a running agent keep it alive
+        assert!(idle_for(created, event(2), false, false, 7 * DAY, now).is_none());
+        assert!(idle_for(now - DAY, None, false, false, 7 * DAY, now).is_none());
+        assert!(idle_for(created, event(8), false, true, 7 * DAY, now).is_none());
+        assert!(idle_for(created, event(8), true, false, 7 * DAY, now).is_none());
+    }
+}
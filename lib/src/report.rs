@@ -0,0 +1,142 @@
+//! Final reports of assignments whose intent produces a write-up instead of
+//! code (`[tasks] report_intents`, e.g. plan or review).
+//!
+//! Completing such an assignment posts the report as a task comment, since
+//! there is usually nothing to integrate.
+
+use serde::Serialize;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::claude_session::{ClaudeSession, EntryKind};
+use crate::session::SessionLogs;
+use crate::Assignment;
+
+/// File an agent may leave its write-up in, checked in the working dir and
+/// then the workspace root.
+pub const REPORT_FILE: &str = "PLAN.md";
+
+/// An assignment's write-up and where it was found.
+#[derive(Debug, Clone, Serialize)]
+pub struct FinalReport {
+    /// `PLAN.md`, `work log` or `session log`
+    pub source: String,
+    pub text: String,
+}
+
+/// The assignment's final report: [`REPORT_FILE`] if the agent wrote one,
+/// otherwise its last assistant message from the daemon work log or the
+/// agent's session log. Must be called before the workspace is removed.
+pub fn final_report(
+    assignment: &Assignment,
+    toren_dir: &Path,
+    logs: &SessionLogs,
+) -> Option<FinalReport> {
+    let report_file = [assignment.working_dir(), assignment.workspace_path.clone()]
+        .into_iter()
+        .map(|dir| dir.join(REPORT_FILE))
+        .find(|path| path.is_file());
+    if let Some(text) = report_file.and_then(|p| std::fs::read_to_string(p).ok()) {
+        if !text.trim().is_empty() {
+            return Some(FinalReport {
+                source: REPORT_FILE.to_string(),
+                text: text.trim().to_string(),
+            });
+        }
+    }
+
+    let work_log = crate::gc::work_log_path(toren_dir, &assignment.ancillary_id, &assignment.id);
+    if let Some(text) = last_line_text(&work_log, work_log_message) {
+        return Some(FinalReport {
+            source: "work log".to_string(),
+            text,
+        });
+    }
+
+    let session = ClaudeSession::latest(logs, &assignment.working_dir())?;
+    session
+        .entries()
+        .ok()?
+        .iter()
+        .rev()
+        .filter(|entry| entry.kind == EntryKind::Assistant)
+        .filter_map(|entry| entry.text())
+        .map(|text| text.trim().to_string())
+        .find(|text| !text.is_empty())
+        .map(|text| FinalReport {
+            source: "session log".to_string(),
+            text,
+        })
+}
+
+/// The last non-empty text `extract` finds in a JSONL file's lines.
+fn last_line_text(path: &Path, extract: fn(&serde_json::Value) -> Option<String>) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(&line).ok())
+        .filter_map(|value| extract(&value))
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .last()
+}
+
+/// `{"op": {"type": "assistant_message", "content": "..."}}` in a daemon work log.
+fn work_log_message(value: &serde_json::Value) -> Option<String> {
+    let op = value.get("op")?;
+    if op.get("type")?.as_str()? != "assistant_message" {
+        return None;
+    }
+    op.get("content")?.as_str().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assignment::{AssignmentManager, AssignmentSource};
+
+    #[test]
+    fn test_final_report_sources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let ws = root.join("workspaces/app/one");
+        std::fs::create_dir_all(&ws).unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(root.join("assignments.json")).unwrap();
+        let a = mgr
+            .create(&"App One".parse().unwrap(), None, AssignmentSource::Reference, "app", ws.clone(), None, None, None, None)
+            .unwrap();
+        let logs = SessionLogs::new(vec![root.join("projects")]);
+        assert!(final_report(&a, root, &logs).is_none());
+
+        // Session log: last assistant text wins, tool-only turns are skipped
+        let session_dir = logs.project_dirs(&ws).remove(0);
+        std::fs::create_dir_all(&session_dir).unwrap();
+        std::fs::write(
+            session_dir.join("s1.jsonl"),
+            concat!(
+                r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Looking"}]}}"#, "\n",
+                r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Review: LGTM"}]}}"#, "\n",
+                r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Bash"}]}}"#, "\n",
+            ),
+        )
+        .unwrap();
+        let report = final_report(&a, root, &logs).unwrap();
+        assert_eq!((report.source.as_str(), report.text.as_str()), ("session log", "Review: LGTM"));
+
+        // The daemon work log takes precedence over the session log
+        let work_log = crate::gc::work_log_path(root, "App One", &a.id);
+        std::fs::create_dir_all(work_log.parent().unwrap()).unwrap();
+        std::fs::write(
+            &work_log,
+            r#"{"seq":1,"timestamp":"2026-01-01T00:00:00Z","op":{"type":"assistant_message","content":"Plan: split it"}}"#,
+        )
+        .unwrap();
+        assert_eq!(final_report(&a, root, &logs).unwrap().text, "Plan: split it");
+
+        // And PLAN.md over both
+        std::fs::write(ws.join(REPORT_FILE), "# Plan\n\n1. Do it\n").unwrap();
+        let report = final_report(&a, root, &logs).unwrap();
+        assert_eq!((report.source.as_str(), report.text.as_str()), ("PLAN.md", "# Plan\n\n1. Do it"));
+    }
+}
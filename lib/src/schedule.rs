@@ -0,0 +1,238 @@
+//! Recurring assignments.
+//!
+//! A schedule pairs a cron expression with a prompt and a segment. The daemon
+//! checks schedules once a minute; when one is due it runs the normal
+//! create-assignment pipeline (task creation, workspace setup, agent start).
+//! Schedules are stored in `~/.toren/schedules.json`.
+//!
+//! Cron expressions use the standard five fields (minute, hour, day of month,
+//! month, day of week) and are evaluated in local time.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::debug;
+
+/// A recurring assignment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    /// Five-field cron expression, e.g. "0 3 * * *"
+    pub cron: String,
+    pub segment: String,
+    pub prompt: String,
+    /// Task source for the task created on each run (default: `tasks.sources`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_source: Option<String>,
+    /// Agent override (e.g. "codex:o3"); the daemon default if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the schedule last fired (whether or not an assignment was created)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<DateTime<Utc>>,
+    /// Assignment created by the last run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_assignment_id: Option<String>,
+    /// Why the last run didn't create an assignment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Parse a five-field cron expression.
+pub fn parse_cron(expr: &str) -> Result<Cron> {
+    Cron::new(expr)
+        .parse()
+        .with_context(|| format!("Invalid cron expression '{}'", expr))
+}
+
+impl Schedule {
+    /// The first trigger time after the last run (or after creation).
+    pub fn next_run(&self) -> Option<DateTime<Utc>> {
+        let cron = parse_cron(&self.cron).ok()?;
+        let since = self.last_run.unwrap_or(self.created_at).with_timezone(&Local);
+        cron.find_next_occurrence(&since, false)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    /// Whether a trigger time has passed since the last run. Missed triggers
+    /// (e.g. while the daemon was down) collapse into a single run.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_run().is_some_and(|next| next <= now)
+    }
+}
+
+/// Persists schedules to `~/.toren/schedules.json`.
+pub struct ScheduleManager {
+    storage_path: PathBuf,
+    schedules: Vec<Schedule>,
+}
+
+impl ScheduleManager {
+    pub fn new() -> Result<Self> {
+        let storage_path = dirs::home_dir()
+            .context("Could not determine home directory")?
+            .join(".toren")
+            .join("schedules.json");
+        Self::with_storage_path(storage_path)
+    }
+
+    pub fn with_storage_path(storage_path: PathBuf) -> Result<Self> {
+        let schedules = if storage_path.exists() {
+            let content = std::fs::read_to_string(&storage_path)
+                .with_context(|| format!("Failed to read {}", storage_path.display()))?;
+            serde_json::from_str(&content).with_context(|| "Failed to parse schedules.json")?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            storage_path,
+            schedules,
+        })
+    }
+
+    fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.schedules)
+            .with_context(|| "Failed to serialize schedules")?;
+        if let Some(parent) = self.storage_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.storage_path, content)
+            .with_context(|| format!("Failed to write {}", self.storage_path.display()))?;
+        debug!("Saved {} schedules to disk", self.schedules.len());
+        Ok(())
+    }
+
+    pub fn list(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Schedule> {
+        self.schedules.iter().find(|s| s.id == id)
+    }
+
+    /// Register a schedule. The cron expression is validated up front.
+    pub fn add(
+        &mut self,
+        cron: &str,
+        segment: &str,
+        prompt: &str,
+        task_source: Option<String>,
+        agent: Option<String>,
+    ) -> Result<Schedule> {
+        parse_cron(cron)?;
+        let schedule = Schedule {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            cron: cron.trim().to_string(),
+            segment: segment.to_string(),
+            prompt: prompt.to_string(),
+            task_source,
+            agent,
+            created_at: Utc::now(),
+            last_run: None,
+            last_assignment_id: None,
+            last_error: None,
+        };
+        self.schedules.push(schedule.clone());
+        self.save()?;
+        Ok(schedule)
+    }
+
+    /// Remove a schedule. Returns false if no schedule has that ID.
+    pub fn remove(&mut self, id: &str) -> Result<bool> {
+        let before = self.schedules.len();
+        self.schedules.retain(|s| s.id != id);
+        if self.schedules.len() == before {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Schedules with a trigger time at or before `now`.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<Schedule> {
+        self.schedules
+            .iter()
+            .filter(|s| s.is_due(now))
+            .cloned()
+            .collect()
+    }
+
+    /// Record the outcome of a run: the assignment it created, or why not.
+    pub fn record_run(
+        &mut self,
+        id: &str,
+        at: DateTime<Utc>,
+        outcome: Result<String, String>,
+    ) -> Result<()> {
+        let Some(schedule) = self.schedules.iter_mut().find(|s| s.id == id) else {
+            return Ok(());
+        };
+        schedule.last_run = Some(at);
+        match outcome {
+            Ok(assignment_id) => {
+                schedule.last_assignment_id = Some(assignment_id);
+                schedule.last_error = None;
+            }
+            Err(error) => schedule.last_error = Some(error),
+        }
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn schedule(cron: &str, created_at: DateTime<Utc>) -> Schedule {
+        Schedule {
+            id: "s1".to_string(),
+            cron: cron.to_string(),
+            segment: "toren".to_string(),
+            prompt: "update dependencies and run tests".to_string(),
+            task_source: None,
+            agent: None,
+            created_at,
+            last_run: None,
+            last_assignment_id: None,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn test_due_after_trigger_time() {
+        let created = Local.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap().to_utc();
+        let mut s = schedule("0 3 * * *", created);
+        let next = s.next_run().unwrap().with_timezone(&Local);
+        assert_eq!(next, Local.with_ymd_and_hms(2026, 3, 2, 3, 0, 0).unwrap());
+
+        assert!(!s.is_due(created + chrono::Duration::hours(12)));
+        assert!(s.is_due(created + chrono::Duration::hours(16)));
+
+        // Several missed nights still produce a single run
+        s.last_run = Some(created + chrono::Duration::days(5));
+        assert!(!s.is_due(created + chrono::Duration::days(5) + chrono::Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_add_validates_and_persists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("schedules.json");
+        let mut mgr = ScheduleManager::with_storage_path(path.clone()).unwrap();
+
+        assert!(mgr.add("not a cron", "toren", "x", None, None).is_err());
+        let s = mgr.add("0 3 * * *", "toren", "nightly", None, None).unwrap();
+        mgr.record_run(&s.id, Utc::now(), Err("pool full".to_string()))
+            .unwrap();
+
+        let mut reopened = ScheduleManager::with_storage_path(path).unwrap();
+        assert_eq!(reopened.list().len(), 1);
+        assert_eq!(reopened.get(&s.id).unwrap().last_error.as_deref(), Some("pool full"));
+        assert!(reopened.remove(&s.id).unwrap());
+        assert!(!reopened.remove(&s.id).unwrap());
+    }
+}
@@ -1,9 +1,10 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-use crate::config::Config;
+use crate::config::{Config, SegmentOverride};
 
 /// A segment is a directory under a configured root, or a literal segment path.
 /// Segments are resolved dynamically rather than pre-discovered.
@@ -13,15 +14,88 @@ pub struct Segment {
     pub path: PathBuf,
 }
 
+/// Why a directory was or wasn't taken as a segment (`breq segments list`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Discovery {
+    /// Listed by path in `ancillaries.segments`
+    Literal,
+    /// A directory under a root (no `segments.markers` configured)
+    Child,
+    /// Contains this `segments.markers` entry
+    Marker { marker: String },
+    /// Matched this `segments.ignore` pattern
+    Ignored { pattern: String },
+    /// Has no marker, and is at `segments.max_depth`
+    Unmarked,
+    /// An earlier segment already has this name
+    Shadowed { by: PathBuf },
+}
+
+impl Discovery {
+    pub fn is_segment(&self) -> bool {
+        matches!(self, Discovery::Literal | Discovery::Child | Discovery::Marker { .. })
+    }
+}
+
+impl std::fmt::Display for Discovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Discovery::Literal => write!(f, "listed in ancillaries.segments"),
+            Discovery::Child => write!(f, "directory under a root"),
+            Discovery::Marker { marker } => write!(f, "contains {}", marker),
+            Discovery::Ignored { pattern } => write!(f, "ignored by \"{}\"", pattern),
+            Discovery::Unmarked => write!(f, "no marker at max_depth"),
+            Discovery::Shadowed { by } => write!(f, "name taken by {}", by.display()),
+        }
+    }
+}
+
+/// A directory considered during segment discovery.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredDir {
+    pub name: String,
+    pub path: PathBuf,
+    pub discovery: Discovery,
+}
+
+/// What discovery does with one directory under a root.
+enum Verdict {
+    Found(Discovery),
+    Skipped(Discovery),
+    /// Not a segment itself; look at its children
+    Descend,
+}
+
+/// Effective settings for one segment: the `[ancillaries]` defaults merged
+/// with any `[segments.overrides.<name>]` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SegmentSettings {
+    pub max_per_segment: u32,
+    pub workspace_root: PathBuf,
+    /// Default intent for new assignments, if any
+    pub intent: Option<String>,
+}
+
 /// Manages segment discovery and resolution.
 /// Supports glob-based roots (e.g., "~/proj/*") and literal segment paths.
 #[derive(Debug, Clone)]
 pub struct SegmentManager {
-    /// Parent directories that contain segments as immediate children.
+    /// Parent directories that contain segments (as immediate children,
+    /// or deeper with `segments.markers`).
     roots: Vec<PathBuf>,
     /// Literal segment paths (non-glob entries in ancillaries.segments).
     literal_segments: Vec<PathBuf>,
+    /// `segments.ignore` patterns
+    ignore: Vec<glob::Pattern>,
+    /// `segments.markers`; empty takes every directory under a root
+    markers: Vec<String>,
+    /// `segments.max_depth`, at least 1
+    max_depth: u32,
     workspace_root: Option<PathBuf>,
+    /// Defaults from `[ancillaries]`, before per-segment overrides.
+    defaults: SegmentSettings,
+    overrides: HashMap<String, SegmentOverride>,
 }
 
 impl SegmentManager {
@@ -44,13 +118,50 @@ impl SegmentManager {
             }
         };
 
+        let ignore = config
+            .segments
+            .ignore
+            .iter()
+            .filter_map(|pattern| match glob::Pattern::new(pattern) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    warn!("Invalid segments.ignore pattern '{}': {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+
         Ok(Self {
             roots,
             literal_segments,
+            ignore,
+            markers: config.segments.markers.clone(),
+            max_depth: config.segments.max_depth.max(1),
             workspace_root,
+            defaults: SegmentSettings {
+                max_per_segment: config.ancillaries.max_per_segment,
+                workspace_root: ws_root.clone(),
+                intent: None,
+            },
+            overrides: config.segments.overrides.clone(),
         })
     }
 
+    /// Effective settings for a segment, with its overrides applied.
+    pub fn settings(&self, name: &str) -> SegmentSettings {
+        let mut settings = self.defaults.clone();
+        if let Some(o) = self.overrides.get(name) {
+            if let Some(max) = o.max_per_segment {
+                settings.max_per_segment = max;
+            }
+            if let Some(root) = &o.workspace_root {
+                settings.workspace_root = root.clone();
+            }
+            settings.intent = o.intent.clone();
+        }
+        settings
+    }
+
     /// Resolve a segment from a path.
     /// Checks literal segments first, then roots, then workspace-aware fallback,
     /// and finally CWD repo-root inference.
@@ -79,16 +190,25 @@ impl SegmentManager {
             }
 
             if canonical.starts_with(root) {
+                // The discovered segment enclosing the path, if any
                 let relative = canonical.strip_prefix(root).ok()?;
-                let segment_name = relative.components().next()?;
-                let segment_path = root.join(segment_name);
-
-                if segment_path.is_dir() {
-                    let name = segment_name.as_os_str().to_string_lossy().to_string();
-                    return Some(Segment {
-                        name,
-                        path: segment_path,
-                    });
+                let mut dir = root.clone();
+                for (depth, component) in relative.components().enumerate() {
+                    if depth as u32 >= self.max_depth {
+                        break;
+                    }
+                    dir.push(component);
+                    if !dir.is_dir() {
+                        break;
+                    }
+                    match self.classify(root, &dir, depth as u32 + 1) {
+                        Verdict::Found(_) => {
+                            let name = component.as_os_str().to_string_lossy().to_string();
+                            return Some(Segment { name, path: dir });
+                        }
+                        Verdict::Skipped(_) => break,
+                        Verdict::Descend => {}
+                    }
                 }
             }
         }
@@ -102,6 +222,15 @@ impl SegmentManager {
                 return self.find_by_name(&name);
             }
         }
+        for (name, o) in &self.overrides {
+            let Some(root) = &o.workspace_root else {
+                continue;
+            };
+            let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+            if canonical.starts_with(root.join(name)) {
+                return self.find_by_name(name);
+            }
+        }
 
         // CWD fallback: infer segment from repo root
         self.infer_segment_from_repo(path)
@@ -146,13 +275,18 @@ impl SegmentManager {
         // Check roots
         for root in &self.roots {
             let segment_path = root.join(name);
-            if segment_path.is_dir() {
+            if segment_path.is_dir() && matches!(self.classify(root, &segment_path, 1), Verdict::Found(_)) {
                 return Some(Segment {
                     name: name.to_string(),
                     path: segment_path,
                 });
             }
         }
+
+        // Nested segments are only found by searching
+        if self.max_depth > 1 && !self.markers.is_empty() {
+            return self.list_all().into_iter().find(|s| s.name == name);
+        }
         None
     }
 
@@ -161,47 +295,105 @@ impl SegmentManager {
         &self.roots
     }
 
-    /// List all segments from all roots and literal segments.
-    /// Scans each root directory and returns all subdirectories as segments,
-    /// plus all literal segments.
+    /// List all segments from all roots and literal segments, by name.
     pub fn list_all(&self) -> Vec<Segment> {
-        let mut segments = Vec::new();
+        let mut segments: Vec<Segment> = self
+            .discover()
+            .into_iter()
+            .filter(|d| d.discovery.is_segment())
+            .map(|d| Segment { name: d.name, path: d.path })
+            .collect();
+        segments.sort_by(|a, b| a.name.cmp(&b.name));
+        segments
+    }
+
+    /// Every directory discovery considered, segment or not, with the reason.
+    /// Literal segments come first, then each root's directories in path
+    /// order. Hidden directories are skipped silently.
+    pub fn discover(&self) -> Vec<DiscoveredDir> {
+        let mut found: Vec<DiscoveredDir> = Vec::new();
 
-        // Add literal segments
         for lit in &self.literal_segments {
             if let Some(name) = lit.file_name() {
                 let name = name.to_string_lossy().to_string();
                 if !name.starts_with('.') {
-                    segments.push(Segment {
+                    found.push(DiscoveredDir {
                         name,
                         path: lit.clone(),
+                        discovery: Discovery::Literal,
                     });
                 }
             }
         }
 
-        // Add segments from roots
         for root in &self.roots {
-            if let Ok(entries) = std::fs::read_dir(root) {
-                for entry in entries.filter_map(|e| e.ok()) {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        if let Some(name) = path.file_name() {
-                            let name = name.to_string_lossy().to_string();
-                            // Skip hidden directories and duplicates
-                            if !name.starts_with('.')
-                                && !segments.iter().any(|s| s.name == name)
-                            {
-                                segments.push(Segment { name, path });
-                            }
-                        }
+            self.discover_under(root, root, 1, &mut found);
+        }
+        found
+    }
+
+    fn discover_under(&self, root: &Path, dir: &Path, depth: u32, found: &mut Vec<DiscoveredDir>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut children: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect();
+        children.sort();
+
+        for path in children {
+            let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            let discovery = match self.classify(root, &path, depth) {
+                Verdict::Descend => {
+                    self.discover_under(root, &path, depth + 1, found);
+                    continue;
+                }
+                Verdict::Found(discovery) => {
+                    let earlier = found
+                        .iter()
+                        .find(|d| d.discovery.is_segment() && d.name == name);
+                    match earlier {
+                        Some(earlier) => Discovery::Shadowed { by: earlier.path.clone() },
+                        None => discovery,
                     }
                 }
-            }
+                Verdict::Skipped(discovery) => discovery,
+            };
+            found.push(DiscoveredDir { name, path, discovery });
         }
+    }
 
-        segments.sort_by(|a, b| a.name.cmp(&b.name));
-        segments
+    /// Decide whether `dir`, `depth` levels below `root`, is a segment.
+    fn classify(&self, root: &Path, dir: &Path, depth: u32) -> Verdict {
+        let relative = dir.strip_prefix(root).unwrap_or(dir);
+        let name = dir.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        if let Some(pattern) = self
+            .ignore
+            .iter()
+            .find(|p| p.matches(&name) || p.matches_path(relative))
+        {
+            return Verdict::Skipped(Discovery::Ignored {
+                pattern: pattern.as_str().to_string(),
+            });
+        }
+        if self.markers.is_empty() {
+            return Verdict::Found(Discovery::Child);
+        }
+        if let Some(marker) = self.markers.iter().find(|m| dir.join(m).exists()) {
+            return Verdict::Found(Discovery::Marker { marker: marker.clone() });
+        }
+        if depth < self.max_depth {
+            Verdict::Descend
+        } else {
+            Verdict::Skipped(Discovery::Unmarked)
+        }
     }
 
     /// Check if a directory is a valid segment root for creating new segments.
@@ -380,4 +572,78 @@ mod tests {
         assert!(names.contains(&"literal-seg"));
         assert!(names.contains(&"child-seg"));
     }
+
+    #[test]
+    fn discovery_honors_ignore_markers_and_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("proj");
+        std::fs::create_dir_all(root.join("web/.jj")).unwrap();
+        std::fs::create_dir_all(root.join("archive/.jj")).unwrap();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::create_dir_all(root.join("work/api/.jj")).unwrap();
+        std::fs::create_dir_all(root.join("work/deep/svc/.jj")).unwrap();
+        std::fs::create_dir_all(root.join("work/web")).unwrap();
+        std::fs::write(root.join("work/web/toren.kdl"), "").unwrap();
+
+        let mut config = make_config_with_segments(vec![format!("{}/*", root.display())]);
+        config.segments.ignore = vec!["archive".to_string()];
+        config.segments.markers = vec![".jj".to_string(), "toren.kdl".to_string()];
+        config.segments.max_depth = 2;
+        let mgr = SegmentManager::new(&config).unwrap();
+
+        let names: Vec<String> = mgr.list_all().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["api", "web"]);
+
+        let why = |name: &str| {
+            mgr.discover()
+                .into_iter()
+                .find(|d| d.path.ends_with(name))
+                .map(|d| d.discovery)
+        };
+        assert_eq!(why("archive"), Some(Discovery::Ignored { pattern: "archive".to_string() }));
+        // Searched beneath, but nothing to report about the directory itself
+        assert_eq!(why("notes"), None);
+        assert_eq!(why("work/deep"), Some(Discovery::Unmarked));
+        assert!(matches!(why("work/web"), Some(Discovery::Shadowed { .. })));
+        assert_eq!(why("proj/web"), Some(Discovery::Marker { marker: ".jj".to_string() }));
+
+        let api = mgr.find_by_name("api").unwrap();
+        assert!(api.path.ends_with("work/api"));
+        let resolved = mgr.resolve_from_path(&root.join("work/api/.jj")).unwrap();
+        assert_eq!(resolved.name, "api");
+        assert!(mgr.find_by_name("archive").is_none());
+    }
+
+    #[test]
+    fn settings_apply_segment_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("big-repo");
+        let ws_root = dir.path().join("fast-disk");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::create_dir_all(ws_root.join("big-repo/one")).unwrap();
+
+        let mut config = make_config_with_segments(vec![repo.display().to_string()]);
+        config.segments.overrides.insert(
+            "big-repo".to_string(),
+            SegmentOverride {
+                max_per_segment: Some(3),
+                workspace_root: Some(ws_root.clone()),
+                intent: Some("review".to_string()),
+            },
+        );
+        let mgr = SegmentManager::new(&config).unwrap();
+
+        let settings = mgr.settings("big-repo");
+        assert_eq!(settings.max_per_segment, 3);
+        assert_eq!(settings.workspace_root, ws_root);
+        assert_eq!(settings.intent.as_deref(), Some("review"));
+
+        let other = mgr.settings("other");
+        assert_eq!(other.max_per_segment, config.ancillaries.max_per_segment);
+        assert_eq!(other.workspace_root, config.ancillaries.workspace_root);
+        assert_eq!(other.intent, None);
+
+        let segment = mgr.resolve_from_path(&ws_root.join("big-repo/one")).unwrap();
+        assert_eq!(segment.name, "big-repo");
+    }
 }
@@ -0,0 +1,310 @@
+//! Claude Code session logs.
+//!
+//! Claude Code writes one JSONL log per session at
+//! `{config_dir}/projects/{dir_name}/{session_id}.jsonl`, where `config_dir`
+//! is `CLAUDE_CONFIG_DIR` (default `~/.claude`) and `dir_name` is the
+//! workspace path with `/` and `.` replaced by `-`. [`SessionLogs`] resolves
+//! those directories for everything that reads them: activity detection,
+//! health checks, and the watcher that learns the session ID of an
+//! interactive session so it can be resumed later.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+use crate::config::{expand_path_str, Config};
+
+/// How often the session directory is polled while the agent runs.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where session logs live: one or more `projects` directories, each holding
+/// a subdirectory per workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionLogs {
+    roots: Vec<PathBuf>,
+}
+
+impl SessionLogs {
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        Self { roots }
+    }
+
+    /// Resolve the roots for a config.
+    ///
+    /// Priority: `ancillaries.session_log_roots` > `CLAUDE_CONFIG_DIR` in
+    /// `[ancillaries.claude].env` > `CLAUDE_CONFIG_DIR` in the environment >
+    /// `~/.claude`.
+    pub fn from_config(config: &Config) -> Self {
+        if !config.ancillaries.session_log_roots.is_empty() {
+            return Self::new(config.ancillaries.session_log_roots.clone());
+        }
+        let configured = config
+            .ancillaries
+            .claude
+            .as_ref()
+            .and_then(|claude| claude.env.get("CLAUDE_CONFIG_DIR"))
+            .map(|dir| expand_path_str(dir));
+        let config_dir = configured
+            .or_else(|| std::env::var_os("CLAUDE_CONFIG_DIR").map(PathBuf::from))
+            .or_else(|| Some(dirs::home_dir()?.join(".claude")));
+        Self::new(config_dir.map(|dir| dir.join("projects")).into_iter().collect())
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// The session directory for a workspace under each root (which may not
+    /// exist yet).
+    pub fn project_dirs(&self, workspace_path: &Path) -> Vec<PathBuf> {
+        let Some(path) = workspace_path.to_str() else {
+            return Vec::new();
+        };
+        let dir_name = path.replace(['/', '.'], "-");
+        self.roots.iter().map(|root| root.join(&dir_name)).collect()
+    }
+
+    /// The most recently modified session log for a workspace, across roots.
+    pub fn latest_log(&self, workspace_path: &Path) -> Option<(PathBuf, SystemTime)> {
+        self.logs(workspace_path)
+            .into_iter()
+            .max_by_key(|(_, modified)| *modified)
+    }
+
+    /// Every session log for a workspace, across roots, with its
+    /// modification time.
+    pub fn logs(&self, workspace_path: &Path) -> Vec<(PathBuf, SystemTime)> {
+        self.project_dirs(workspace_path)
+            .iter()
+            .flat_map(|dir| session_files(dir))
+            .map(|((dir, id), modified)| (dir.join(format!("{}.jsonl", id)), modified))
+            .collect()
+    }
+}
+
+/// Detects a session log created after the watcher was started.
+pub struct SessionWatcher {
+    project_dirs: Vec<PathBuf>,
+    known: HashSet<String>,
+}
+
+impl SessionWatcher {
+    /// Snapshot the sessions that already exist for `workspace_path`.
+    pub fn new(logs: &SessionLogs, workspace_path: &Path) -> Option<Self> {
+        let dirs = logs.project_dirs(workspace_path);
+        (!dirs.is_empty()).then(|| Self::in_dirs(dirs))
+    }
+
+    /// Watch an explicit session directory.
+    pub fn in_dir(project_dir: PathBuf) -> Self {
+        Self::in_dirs(vec![project_dir])
+    }
+
+    fn in_dirs(project_dirs: Vec<PathBuf>) -> Self {
+        let known = project_dirs
+            .iter()
+            .flat_map(|dir| session_files(dir))
+            .map(|((_, id), _)| id)
+            .collect();
+        Self { project_dirs, known }
+    }
+
+    /// The most recently modified session that wasn't present at startup.
+    pub fn poll(&self) -> Option<String> {
+        self.project_dirs
+            .iter()
+            .flat_map(|dir| session_files(dir))
+            .filter(|((_, id), _)| !self.known.contains(id))
+            .max_by_key(|(_, modified)| *modified)
+            .map(|((_, id), _)| id)
+    }
+}
+
+/// Session IDs (file stems) and modification times of `*.jsonl` logs in
+/// `dir`, each paired with the directory it was found in.
+fn session_files(dir: &Path) -> Vec<((&Path, String), SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.to_string();
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some(((dir, id), modified))
+        })
+        .collect()
+}
+
+/// Run an interactive agent as a child process, reporting its session ID.
+///
+/// Unlike exec'ing the agent, breq stays alive as a thin wrapper: it watches
+/// the workspace's session directory and calls `on_session` once with the ID
+/// of the first new session log. The terminal's interrupt and quit signals
+/// are ignored by the wrapper while the child runs so Ctrl-C reaches the
+/// agent only.
+pub fn run_tracking_session(
+    cmd: &mut Command,
+    watcher: Option<SessionWatcher>,
+    on_session: impl FnOnce(String) + Send + 'static,
+) -> std::io::Result<ExitStatus> {
+    let mut child = cmd.spawn()?;
+    let _signals = IgnoreTerminalSignals::new();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let watch_thread = watcher.map(|watcher| {
+        let done = done.clone();
+        std::thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                if let Some(id) = watcher.poll() {
+                    debug!("Discovered agent session {}", id);
+                    on_session(id);
+                    return;
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })
+    });
+
+    let status = child.wait();
+    done.store(true, Ordering::Relaxed);
+    if let Some(handle) = watch_thread {
+        let _ = handle.join();
+    }
+    status
+}
+
+/// Ignores SIGINT/SIGQUIT for the wrapper's lifetime, restoring defaults on
+/// drop. A no-op on Windows, where Ctrl-C ends the wrapper along with the
+/// agent.
+struct IgnoreTerminalSignals;
+
+#[cfg(not(unix))]
+impl IgnoreTerminalSignals {
+    fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(unix)]
+impl IgnoreTerminalSignals {
+    fn new() -> Self {
+        use nix::sys::signal::{signal, SigHandler, Signal};
+        for sig in [Signal::SIGINT, Signal::SIGQUIT] {
+            // SAFETY: SIG_IGN installs no handler code, so there is nothing to race with.
+            let _ = unsafe { signal(sig, SigHandler::SigIgn) };
+        }
+        Self
+    }
+}
+
+#[cfg(unix)]
+impl Drop for IgnoreTerminalSignals {
+    fn drop(&mut self) {
+        use nix::sys::signal::{signal, SigHandler, Signal};
+        for sig in [Signal::SIGINT, Signal::SIGQUIT] {
+            // SAFETY: restoring the default disposition installs no handler code.
+            let _ = unsafe { signal(sig, SigHandler::SigDfl) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_watcher_ignores_existing_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("old-session.jsonl"), "{}\n").unwrap();
+
+        let watcher = SessionWatcher::in_dir(dir.path().to_path_buf());
+        assert_eq!(watcher.poll(), None);
+
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        std::fs::write(dir.path().join("new-session.jsonl"), "{}\n").unwrap();
+        assert_eq!(watcher.poll().as_deref(), Some("new-session"));
+    }
+
+    #[test]
+    fn test_watcher_handles_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_dir = dir.path().join("not-yet-created");
+        let watcher = SessionWatcher::in_dir(project_dir.clone());
+        assert_eq!(watcher.poll(), None);
+
+        std::fs::create_dir(&project_dir).unwrap();
+        std::fs::write(project_dir.join("abc.jsonl"), "").unwrap();
+        assert_eq!(watcher.poll().as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_run_tracking_session_reports_new_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let watcher = SessionWatcher::in_dir(dir.path().to_path_buf());
+        let (tx, rx) = mpsc::channel();
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!("touch {}/s-123.jsonl; sleep 1", dir.path().display()));
+        let status = run_tracking_session(&mut cmd, Some(watcher), move |id| {
+            tx.send(id).unwrap();
+        })
+        .unwrap();
+
+        assert!(status.success());
+        assert_eq!(rx.try_recv().unwrap(), "s-123");
+    }
+
+    #[test]
+    fn test_project_dirs_mangle_path() {
+        let logs = SessionLogs::new(vec![PathBuf::from("/home/u/.claude/projects")]);
+        let dirs = logs.project_dirs(Path::new("/ws/toren.dev/one"));
+        assert_eq!(dirs, vec![PathBuf::from("/home/u/.claude/projects/-ws-toren-dev-one")]);
+    }
+
+    #[test]
+    fn test_session_logs_from_config() {
+        let mut config = Config::default();
+        config.ancillaries.claude = Some(crate::AgentCommandConfig {
+            env: std::collections::HashMap::from([(
+                "CLAUDE_CONFIG_DIR".to_string(),
+                "/opt/claude-work".to_string(),
+            )]),
+            ..Default::default()
+        });
+        let logs = SessionLogs::from_config(&config);
+        assert_eq!(logs.roots(), [PathBuf::from("/opt/claude-work/projects")]);
+
+        config.ancillaries.session_log_roots = vec![PathBuf::from("/a"), PathBuf::from("/b")];
+        assert_eq!(SessionLogs::from_config(&config).roots().len(), 2);
+    }
+
+    #[test]
+    fn test_latest_log_spans_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = vec![dir.path().join("a"), dir.path().join("b")];
+        let logs = SessionLogs::new(roots.clone());
+        let ws = Path::new("/ws/one");
+        assert!(logs.latest_log(ws).is_none());
+
+        let dirs = logs.project_dirs(ws);
+        for d in &dirs {
+            std::fs::create_dir_all(d).unwrap();
+        }
+        std::fs::write(dirs[0].join("old.jsonl"), "{}\n").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(dirs[1].join("new.jsonl"), "{}\n").unwrap();
+        let (path, _) = logs.latest_log(ws).unwrap();
+        assert_eq!(path, dirs[1].join("new.jsonl"));
+    }
+}
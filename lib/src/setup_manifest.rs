@@ -0,0 +1,201 @@
+//! Input fingerprints of completed setup actions, for differential re-setup.
+//!
+//! Each `template`, `copy` and `run … { inputs … }` action that completes is
+//! recorded by a fingerprint of everything it depends on: the rendered
+//! destination, the template source, the copy source's mtime and size, or the
+//! run command, its env and the contents of its declared inputs. When a
+//! workspace is set up again (e.g. recreated by resume), actions whose
+//! fingerprint is unchanged and whose outputs are still present are skipped.
+//!
+//! The manifest lives outside the workspace, at
+//! `~/.toren/setup/{segment}/{workspace}.json`, so it survives the workspace
+//! directory being deleted; destroying the workspace removes it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// `~/.toren/setup/{segment}/{workspace}.json`
+pub fn manifest_path(segment: &str, workspace_name: &str) -> PathBuf {
+    crate::toren_root()
+        .join("setup")
+        .join(segment)
+        .join(format!("{}.json", workspace_name))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ManifestFile {
+    completed: BTreeSet<String>,
+}
+
+/// Fingerprints from the previous setup, and those completed by this one.
+#[derive(Debug)]
+pub struct SetupManifest {
+    path: PathBuf,
+    previous: BTreeSet<String>,
+    completed: BTreeSet<String>,
+}
+
+impl SetupManifest {
+    /// Load the manifest at `path`. A missing or unreadable manifest is empty,
+    /// so every action runs.
+    pub fn load(path: PathBuf) -> Self {
+        let previous = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<ManifestFile>(&content).ok())
+            .map(|file| file.completed)
+            .unwrap_or_default();
+        Self {
+            path,
+            previous,
+            completed: BTreeSet::new(),
+        }
+    }
+
+    /// Whether the previous setup completed an action with this fingerprint.
+    pub fn is_current(&self, fingerprint: &str) -> bool {
+        self.previous.contains(fingerprint)
+    }
+
+    pub fn record(&mut self, fingerprint: String) {
+        self.completed.insert(fingerprint);
+    }
+
+    /// Replace the stored fingerprints with those completed by this setup.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = ManifestFile {
+            completed: self.completed.clone(),
+        };
+        std::fs::write(&self.path, serde_json::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Stable 64-bit FNV-1a hasher. `DefaultHasher` output may change between
+/// Rust releases, which would needlessly invalidate stored fingerprints.
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    pub fn new(kind: &str) -> Self {
+        let mut fp = Fingerprint(0xcbf2_9ce4_8422_2325);
+        fp.field(kind.as_bytes());
+        fp
+    }
+
+    /// Add a length-prefixed field, so adjacent fields can't run together.
+    pub fn field(&mut self, bytes: &[u8]) -> &mut Self {
+        self.write_u64(bytes.len() as u64);
+        self.write(bytes);
+        self
+    }
+
+    /// Add a file's (or directory tree's) contents. Missing paths hash as absent.
+    pub fn contents(&mut self, path: &Path) -> Result<&mut Self> {
+        if path.is_dir() {
+            let mut entries: Vec<_> = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .collect();
+            entries.sort();
+            self.field(b"dir");
+            for entry in entries {
+                let name = entry.file_name().unwrap_or_default().to_string_lossy();
+                self.field(name.as_bytes());
+                self.contents(&entry)?;
+            }
+        } else if path.exists() {
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            self.field(b"file").field(&bytes);
+        } else {
+            self.field(b"absent");
+        }
+        Ok(self)
+    }
+
+    /// Add a path's modification time and size (cheaper than contents, for
+    /// large copy sources).
+    pub fn metadata(&mut self, path: &Path) -> &mut Self {
+        match std::fs::metadata(path) {
+            Ok(meta) => {
+                let mtime = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos())
+                    .unwrap_or(0);
+                self.field(&mtime.to_le_bytes()).field(&meta.len().to_le_bytes())
+            }
+            Err(_) => self.field(b"absent"),
+        }
+    }
+
+    pub fn hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+impl Hasher for Fingerprint {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_tracks_input_contents() {
+        let tmp = tempfile::tempdir().unwrap();
+        let lock = tmp.path().join("pnpm-lock.yaml");
+        std::fs::write(&lock, "a").unwrap();
+
+        let fp = |cmd: &str| {
+            let mut fp = Fingerprint::new("run");
+            fp.field(cmd.as_bytes()).contents(&lock).unwrap();
+            fp.hex()
+        };
+        let before = fp("pnpm install");
+        assert_eq!(before, fp("pnpm install"));
+        assert_ne!(before, fp("pnpm install --frozen-lockfile"));
+
+        std::fs::write(&lock, "b").unwrap();
+        assert_ne!(before, fp("pnpm install"));
+    }
+
+    #[test]
+    fn test_manifest_keeps_only_latest_run() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("setup/toren/one.json");
+
+        let mut first = SetupManifest::load(path.clone());
+        assert!(!first.is_current("aaaa"));
+        first.record("aaaa".to_string());
+        first.record("bbbb".to_string());
+        first.save().unwrap();
+
+        let mut second = SetupManifest::load(path.clone());
+        assert!(second.is_current("aaaa"));
+        second.record("aaaa".to_string());
+        second.save().unwrap();
+
+        let third = SetupManifest::load(path);
+        assert!(third.is_current("aaaa"));
+        assert!(!third.is_current("bbbb"));
+    }
+}
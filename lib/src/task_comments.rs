@@ -0,0 +1,141 @@
+//! Lifecycle comments on tasks (`[tasks] comment_events`).
+//!
+//! Teammates who follow work in the task tracker alone don't see which
+//! ancillary picked a task up or what it left behind. With
+//! `comment_events` set, a [`TaskCommenter`] registered as a
+//! [`LifecycleObserver`] posts a short comment to the assignment's task,
+//! through its source's `comment` resolver, whenever one of those events
+//! happens: the ancillary, its workspace and, on complete or abort, the
+//! final revision. Comments are best-effort; a failure is logged and never
+//! fails the operation.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::hooks::{HookEvent, HookPayload, LifecycleObserver};
+use crate::{Config, PluginContext, PluginManager, SegmentManager};
+
+/// Posts lifecycle comments to assignments' tasks.
+pub struct TaskCommenter {
+    config: Config,
+    plugin_mgr: PluginManager,
+}
+
+impl TaskCommenter {
+    /// Register a commenter for this process, if `[tasks] comment_events`
+    /// names any event.
+    pub fn register(config: &Config) {
+        if config.tasks.comment_events.is_empty() {
+            return;
+        }
+        match PluginManager::new(&crate::toren_root().join("plugins")) {
+            Ok(plugin_mgr) => {
+                crate::hooks::register_observer(Arc::new(Self {
+                    config: config.clone(),
+                    plugin_mgr,
+                }));
+            }
+            Err(e) => tracing::warn!("Task comments disabled: failed to load plugins: {:#}", e),
+        }
+    }
+
+    fn post(&self, payload: &HookPayload) {
+        if !self.config.tasks.comment_events.contains(&payload.event) {
+            return;
+        }
+        let assignment = payload.assignment;
+        let (Some(task_id), Some(source)) = (&assignment.task_id, &assignment.task_source) else {
+            return;
+        };
+        let segment_path: Option<PathBuf> = SegmentManager::new(&self.config)
+            .ok()
+            .and_then(|segments| segments.find_by_name(&assignment.segment))
+            .map(|segment| segment.path);
+        let ctx = PluginContext::new(segment_path, Some(assignment.segment.clone()));
+        if let Err(e) = self.plugin_mgr.resolve_comment(source, task_id, &comment_body(payload), ctx) {
+            tracing::warn!("Failed to comment on task {} ({}): {:#}", task_id, payload.event, e);
+        }
+    }
+}
+
+impl LifecycleObserver for TaskCommenter {
+    fn on_create(&self, payload: &HookPayload) {
+        self.post(payload)
+    }
+    fn on_complete(&self, payload: &HookPayload) {
+        self.post(payload)
+    }
+    fn on_abort(&self, payload: &HookPayload) {
+        self.post(payload)
+    }
+    fn on_resume(&self, payload: &HookPayload) {
+        self.post(payload)
+    }
+}
+
+/// The comment posted for a lifecycle event.
+pub fn comment_body(payload: &HookPayload) -> String {
+    let assignment = payload.assignment;
+    let workspace = assignment
+        .workspace_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let verb = match payload.event {
+        HookEvent::Assign => "Assigned to",
+        HookEvent::Resume => "Resumed by",
+        HookEvent::Complete => "Completed by",
+        HookEvent::Abort => "Aborted by",
+    };
+    let mut details = vec![format!(
+        "workspace {} ({})",
+        workspace,
+        crate::tilde_shorten(&assignment.workspace_path)
+    )];
+    if let Some(ref revision) = payload.revision {
+        details.push(format!("revision {}", revision));
+    }
+    if payload.pushed == Some(true) {
+        details.push("pushed".to_string());
+    }
+    if payload.workspace_recreated == Some(true) {
+        details.push("workspace recreated".to_string());
+    }
+    format!("{} {}: {}", verb, assignment.ancillary_id, details.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssignmentManager, AssignmentSource};
+
+    #[test]
+    fn test_comment_body_names_ancillary_workspace_and_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(dir.path().join("assignments.json")).unwrap();
+        let assignment = mgr
+            .create(
+                &crate::ancillary_id("toren", 2),
+                Some(&"bd-7".parse().unwrap()),
+                AssignmentSource::Reference,
+                "toren",
+                PathBuf::from("/ws/toren/two"),
+                None,
+                None,
+                None,
+                Some("beads"),
+            )
+            .unwrap();
+
+        let payload = HookPayload::new(HookEvent::Assign, &assignment);
+        assert_eq!(comment_body(&payload), "Assigned to Toren Two: workspace two (/ws/toren/two)");
+
+        let mut payload = HookPayload::new(HookEvent::Complete, &assignment);
+        payload.revision = Some("kxqz1234".to_string());
+        payload.pushed = Some(true);
+        assert_eq!(
+            comment_body(&payload),
+            "Completed by Toren Two: workspace two (/ws/toren/two), revision kxqz1234, pushed"
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 
+use crate::ids::TaskId;
+
 /// Inferred task fields from various input formats.
 pub struct InferredTaskFields {
-    pub task_id: Option<String>,
+    pub task_id: Option<TaskId>,
     pub task_title: Option<String>,
     pub task_url: Option<String>,
     pub task_source: Option<String>,
@@ -62,7 +64,7 @@ pub fn infer_task_fields(
     }
 
     InferredTaskFields {
-        task_id: id,
+        task_id: id.map(TaskId::unchecked),
         task_title: title,
         task_url: url,
         task_source: source,
@@ -75,7 +77,7 @@ pub fn infer_task_fields(
 /// may return different subsets of fields.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResolvedTask {
-    pub id: String,
+    pub id: TaskId,
     /// Source name (e.g., "runes", "linear").
     pub source: String,
     /// Task kind (e.g., "bug", "task", "feature").
@@ -92,6 +94,54 @@ pub struct ResolvedTask {
     pub created_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<String>,
+    /// Urgency, lower first (beads' 0–4); `"P1"` strings are accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i64>,
+    /// Tasks this one depends on, from the resolver's `deps`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deps: Vec<TaskDep>,
+}
+
+/// A task another task depends on. Resolvers return each of `deps` as a
+/// map with `id` and optionally `title` and `status`, or as a bare ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskDep {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// Whether a task can be started now: open (or without a status), with no
+/// assignee, and with every dependency closed. A dependency without a
+/// status counts as open.
+pub fn is_ready(task: &ResolvedTask) -> bool {
+    task.status.as_deref().is_none_or(|s| s == "open")
+        && task.assignee.as_deref().is_none_or(|a| a.trim().is_empty())
+        && task
+            .deps
+            .iter()
+            .all(|dep| matches!(dep.status.as_deref(), Some("closed" | "done" | "completed")))
+}
+
+/// Sort tasks most urgent first: by priority (lower first, unset last), then
+/// oldest first by `created_at`.
+pub fn rank_ready(tasks: &mut [ResolvedTask]) {
+    let created = |task: &ResolvedTask| {
+        task.created_at
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+    };
+    tasks.sort_by(|a, b| {
+        let priority = |t: &ResolvedTask| t.priority.unwrap_or(i64::MAX);
+        priority(a).cmp(&priority(b)).then_with(|| match (created(a), created(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        })
+    });
 }
 
 /// Generate a prompt from a task using the provided template.
@@ -107,14 +157,18 @@ pub fn generate_prompt(task: &ResolvedTask, template: &str) -> String {
         repo: crate::workspace_setup::RepoInfo {
             root: String::new(),
             name: String::new(),
+            recent_commits: Vec::new(),
         },
+        variant: None,
+        assignment: None,
         vars: std::collections::HashMap::new(),
         task: Some(crate::workspace_setup::TaskInfo {
-            id: task.id.clone(),
+            id: task.id.to_string(),
             title: task.title.clone(),
             description: task.description.clone(),
             url: None,
             source: Some(task.source.clone()),
+            deps: task.deps.clone(),
         }),
     };
     crate::workspace_setup::render_template(template, &ctx).unwrap_or_else(|_| {
@@ -129,6 +183,56 @@ pub fn generate_prompt(task: &ResolvedTask, template: &str) -> String {
 mod tests {
     use super::*;
 
+    fn task(id: &str, priority: Option<i64>, created_at: Option<&str>) -> ResolvedTask {
+        ResolvedTask {
+            id: TaskId::unchecked(id.to_string()),
+            source: "beads".to_string(),
+            kind: None,
+            title: id.to_string(),
+            status: Some("open".to_string()),
+            assignee: None,
+            description: None,
+            created_at: created_at.map(str::to_string),
+            updated_at: None,
+            priority,
+            deps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ready_tasks_ranked_by_priority_then_age() {
+        let blocked = ResolvedTask {
+            deps: vec![TaskDep {
+                id: "a".to_string(),
+                title: None,
+                status: Some("open".to_string()),
+            }],
+            ..task("blocked", Some(0), None)
+        };
+        let claimed = ResolvedTask {
+            assignee: Some("claude".to_string()),
+            ..task("claimed", Some(0), None)
+        };
+        let started = ResolvedTask {
+            status: Some("in_progress".to_string()),
+            ..task("started", Some(0), None)
+        };
+        assert!(!is_ready(&blocked));
+        assert!(!is_ready(&claimed));
+        assert!(!is_ready(&started));
+        assert!(is_ready(&task("free", None, None)));
+
+        let mut tasks = vec![
+            task("p2-new", Some(2), Some("2026-03-01T00:00:00Z")),
+            task("unprioritized", None, Some("2020-01-01T00:00:00Z")),
+            task("p2-old", Some(2), Some("2026-01-01T00:00:00Z")),
+            task("p1-undated", Some(1), None),
+        ];
+        rank_ready(&mut tasks);
+        let order: Vec<_> = tasks.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(order, ["p1-undated", "p2-old", "p2-new", "unprioritized"]);
+    }
+
     #[test]
     fn test_infer_source_id_splitting() {
         let result = infer_task_fields(Some("beads:breq-abc"), None, None, None);
@@ -0,0 +1,197 @@
+//! Undo journal for destructive assignment operations.
+//!
+//! Completing, aborting or cleaning an assignment destroys its workspace,
+//! removes its record and moves its task along. Each of these appends an
+//! [`UndoEntry`] to `~/.toren/undo.jsonl` recording what was removed: the
+//! assignment, the workspace's last revision, what happened to the task and
+//! the workspace's proxy routes. `breq undo` takes the most recent entry
+//! within `ancillaries.undo_retention_minutes` and puts it all back (see
+//! [`crate::assignment_ops::undo_operation`]). Only the last
+//! [`MAX_ENTRIES`] operations are kept.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::Assignment;
+
+/// Operations kept in the journal; older ones are dropped as new ones are
+/// recorded.
+pub const MAX_ENTRIES: usize = 20;
+
+/// The operation an entry undoes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UndoOperation {
+    Complete,
+    Abort,
+    Clean,
+}
+
+impl std::fmt::Display for UndoOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Complete => "complete",
+            Self::Abort => "abort",
+            Self::Clean => "clean",
+        })
+    }
+}
+
+/// What the operation did to the assignment's task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskTransition {
+    /// Closed (complete, or abort with `close_task`)
+    Closed,
+    /// Unassigned and returned to open
+    Reopened,
+    /// Deleted; it can't be restored
+    Deleted,
+}
+
+/// Everything a destructive operation removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoEntry {
+    pub operation: UndoOperation,
+    pub recorded_at: DateTime<Utc>,
+    /// The assignment record as it was before removal
+    pub assignment: Assignment,
+    /// The workspace's revision when it was destroyed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Whether the changes were pushed (undo can't take that back)
+    #[serde(default)]
+    pub pushed: bool,
+    /// What happened to the task; None if it was left alone (e.g. `breq
+    /// clean`, whose callers move the task themselves)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<TaskTransition>,
+    /// URLs of the workspace's proxy routes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proxy_urls: Vec<String>,
+}
+
+impl UndoEntry {
+    /// Whether the entry is older than `retention`.
+    pub fn expired(&self, retention: chrono::Duration) -> bool {
+        Utc::now() - self.recorded_at > retention
+    }
+}
+
+/// The journal file (`undo.jsonl`), newest entry last.
+pub struct UndoJournal {
+    path: PathBuf,
+}
+
+impl UndoJournal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Append an entry, dropping the oldest beyond [`MAX_ENTRIES`].
+    pub fn record(&self, entry: &UndoEntry) -> Result<()> {
+        let mut entries = self.entries()?;
+        entries.push(entry.clone());
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        self.write(&entries[excess..])
+    }
+
+    /// The most recent entry, if any.
+    pub fn latest(&self) -> Result<Option<UndoEntry>> {
+        Ok(self.entries()?.pop())
+    }
+
+    /// Remove the entries of an assignment, once it has been restored.
+    pub fn remove(&self, assignment_id: &str) -> Result<()> {
+        let mut entries = self.entries()?;
+        let before = entries.len();
+        entries.retain(|e| e.assignment.id != assignment_id);
+        if entries.len() != before {
+            self.write(&entries)?;
+        }
+        Ok(())
+    }
+
+    /// Entries, oldest first. Unparseable lines are skipped.
+    fn entries(&self) -> Result<Vec<UndoEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    fn write(&self, entries: &[UndoEntry]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut content = String::new();
+        for entry in entries {
+            content.push_str(&serde_json::to_string(entry).context("Failed to serialize undo entry")?);
+            content.push('\n');
+        }
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssignmentManager, AssignmentSource};
+
+    #[test]
+    fn test_undo_journal_keeps_the_latest_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut mgr = AssignmentManager::with_storage_path(dir.path().join("assignments.json")).unwrap();
+        let journal = mgr.undo_journal();
+        assert!(journal.latest().unwrap().is_none());
+
+        let mut last = None;
+        for n in 1..=(MAX_ENTRIES as u32 + 2) {
+            let assignment = mgr
+                .create(
+                    &crate::ancillary_id("toren", n),
+                    None,
+                    AssignmentSource::Reference,
+                    "toren",
+                    dir.path().join(crate::workspace_name(n)),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap();
+            journal
+                .record(&UndoEntry {
+                    operation: UndoOperation::Abort,
+                    recorded_at: Utc::now(),
+                    assignment: assignment.clone(),
+                    revision: Some(format!("rev{}", n)),
+                    pushed: false,
+                    task: None,
+                    proxy_urls: Vec::new(),
+                })
+                .unwrap();
+            last = Some(assignment);
+        }
+        assert_eq!(journal.entries().unwrap().len(), MAX_ENTRIES);
+
+        let latest = journal.latest().unwrap().unwrap();
+        let last = last.unwrap();
+        assert_eq!(latest.assignment.id, last.id);
+        assert!(!latest.expired(chrono::Duration::minutes(5)));
+        assert!(latest.expired(chrono::Duration::zero() - chrono::Duration::seconds(1)));
+
+        journal.remove(&last.id).unwrap();
+        assert_ne!(journal.latest().unwrap().unwrap().assignment.id, last.id);
+        assert_eq!(journal.entries().unwrap().len(), MAX_ENTRIES - 1);
+    }
+}
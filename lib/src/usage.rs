@@ -0,0 +1,218 @@
+//! Token usage and estimated cost of assignments.
+//!
+//! Usage is summed from the workspace's Claude session logs (see
+//! [`crate::claude_session`]): every API message written since the
+//! assignment was created counts once. Cost is an estimate from list
+//! prices per model family; it ignores discounts and batch pricing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+use std::time::SystemTime;
+
+use crate::assignment::{Assignment, CompletionRecord};
+use crate::claude_session::{message_usage, ClaudeSession, TokenUsage};
+use crate::session::SessionLogs;
+
+/// Tokens an assignment's agent used, and what they cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(flatten)]
+    pub tokens: TokenUsage,
+    /// Estimated cost in US dollars
+    pub cost_usd: f64,
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.tokens += other.tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+impl Usage {
+    /// Tokens of every kind, input and output.
+    pub fn total_tokens(&self) -> u64 {
+        let t = &self.tokens;
+        t.input_tokens + t.output_tokens + t.cache_creation_input_tokens + t.cache_read_input_tokens
+    }
+}
+
+/// List price per million tokens: `(input, output)`. Cache writes cost
+/// 1.25× input and cache reads 0.1× input. Unknown models are priced as
+/// Sonnet.
+fn price_per_mtok(model: Option<&str>) -> (f64, f64) {
+    let model = model.unwrap_or_default();
+    if model.contains("opus") {
+        if model.contains("opus-4-5") || model.contains("opus-4-6") {
+            (5.0, 25.0)
+        } else {
+            (15.0, 75.0)
+        }
+    } else if model.contains("haiku") {
+        if model.contains("haiku-4") {
+            (1.0, 5.0)
+        } else {
+            (0.8, 4.0)
+        }
+    } else {
+        (3.0, 15.0)
+    }
+}
+
+/// Estimated cost in US dollars of `tokens` on `model`.
+pub fn estimate_cost(model: Option<&str>, tokens: &TokenUsage) -> f64 {
+    let (input, output) = price_per_mtok(model);
+    let input_cost = tokens.input_tokens as f64 * input
+        + tokens.cache_creation_input_tokens as f64 * input * 1.25
+        + tokens.cache_read_input_tokens as f64 * input * 0.1;
+    (input_cost + tokens.output_tokens as f64 * output) / 1_000_000.0
+}
+
+/// Usage of an assignment so far, from the sessions in its workspace since
+/// it was created. None if no session has recorded any usage.
+pub fn assignment_usage(logs: &SessionLogs, assignment: &Assignment) -> Option<Usage> {
+    let created = chrono::DateTime::parse_from_rfc3339(&assignment.created_at).ok();
+    let since = created.map(SystemTime::from).unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut total: Option<Usage> = None;
+    for session in ClaudeSession::all(logs, &assignment.working_dir()) {
+        if session.modified < since {
+            continue;
+        }
+        let Ok(mut entries) = session.entries() else {
+            continue;
+        };
+        // Workspaces are reused, so earlier assignments' entries can share the log
+        if let Some(created) = created {
+            entries.retain(|e| {
+                e.timestamp
+                    .as_deref()
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                    .is_none_or(|t| t >= created)
+            });
+        }
+        for (model, tokens) in message_usage(&entries) {
+            *total.get_or_insert_with(Usage::default) += Usage {
+                tokens,
+                cost_usd: estimate_cost(model, &tokens),
+            };
+        }
+    }
+    total
+}
+
+/// The assignment with its usage refreshed from the session logs, for
+/// recording in the history. Keeps the stored usage if the logs have none.
+pub fn with_usage(logs: &SessionLogs, assignment: &Assignment) -> Assignment {
+    let mut assignment = assignment.clone();
+    if let Some(usage) = assignment_usage(logs, &assignment) {
+        assignment.usage = Some(usage);
+    }
+    assignment
+}
+
+/// Usage summed over assignments, overall and per segment.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageSummary {
+    #[serde(flatten)]
+    pub total: Usage,
+    /// Assignments that recorded usage
+    pub assignments: usize,
+    pub segments: BTreeMap<String, SegmentUsage>,
+}
+
+/// Usage of one segment's assignments.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SegmentUsage {
+    #[serde(flatten)]
+    pub usage: Usage,
+    pub assignments: usize,
+}
+
+impl UsageSummary {
+    /// Sum the usage of finished assignments (history) and active ones.
+    pub fn from_records(history: &[CompletionRecord], active: &[&Assignment]) -> Self {
+        let finished = history.iter().map(|r| (r.segment.as_str(), r.usage));
+        let running = active.iter().map(|a| (a.segment.as_str(), a.usage));
+        let mut summary = Self::default();
+        for (segment, usage) in finished.chain(running) {
+            let Some(usage) = usage else {
+                continue;
+            };
+            summary.total += usage;
+            summary.assignments += 1;
+            let entry = summary.segments.entry(segment.to_string()).or_default();
+            entry.usage += usage;
+            entry.assignments += 1;
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assignment::CompletionReason;
+    use crate::ids::AncillaryId;
+
+    #[test]
+    fn test_estimate_cost() {
+        let tokens = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 1_000_000,
+        };
+        // Sonnet: 3 input + 1.5 output + 0.3 cache read
+        let sonnet = estimate_cost(Some("claude-sonnet-4-5-20250929"), &tokens);
+        assert!((sonnet - 4.8).abs() < 1e-9);
+        // Unknown models are priced as Sonnet
+        assert_eq!(estimate_cost(None, &tokens), sonnet);
+        let opus = estimate_cost(Some("claude-opus-4-1-20250805"), &tokens);
+        assert!((opus - 24.0).abs() < 1e-9);
+
+        let cache_write = TokenUsage {
+            cache_creation_input_tokens: 1_000_000,
+            ..TokenUsage::default()
+        };
+        let haiku = estimate_cost(Some("claude-haiku-4-5"), &cache_write);
+        assert!((haiku - 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_usage_summary() {
+        let usage = |output_tokens, cost_usd| Usage {
+            tokens: TokenUsage {
+                output_tokens,
+                ..TokenUsage::default()
+            },
+            cost_usd,
+        };
+        let record = |segment: &str, usage| CompletionRecord {
+            assignment_id: "a".into(),
+            ancillary_id: AncillaryId::new("toren", 1),
+            task_id: None,
+            segment: segment.into(),
+            completed_at: "2026-01-01T00:00:00Z".into(),
+            reason: CompletionReason::Completed,
+            final_revision: None,
+            artifacts: Vec::new(),
+            usage,
+        };
+        let history = vec![
+            record("toren", Some(usage(100, 1.0))),
+            record("toren", Some(usage(50, 0.5))),
+            record("web", Some(usage(10, 0.25))),
+            // Recorded before usage was tracked
+            record("web", None),
+        ];
+
+        let summary = UsageSummary::from_records(&history, &[]);
+        assert_eq!(summary.assignments, 3);
+        assert_eq!(summary.total.tokens.output_tokens, 160);
+        assert!((summary.total.cost_usd - 1.75).abs() < 1e-9);
+        assert_eq!(summary.segments["toren"].assignments, 2);
+        assert_eq!(summary.segments["toren"].usage.tokens.output_tokens, 150);
+        assert_eq!(summary.segments["web"].assignments, 1);
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
 use tracing::{debug, info, warn};
 
 /// Recursively remove a directory without following symlinks.
@@ -19,6 +22,19 @@ fn remove_dir_all_no_follow(path: &Path) -> std::io::Result<()> {
     std::fs::remove_dir(path)
 }
 
+/// Record the workspace's context for in-workspace commands (see
+/// [`crate::context_cache`]). A failure only costs them the fast path.
+fn write_context_cache(segment_path: &Path, ws_path: &Path, workspace_name: &str) {
+    let cached = crate::context_cache::CachedContext {
+        segment_path: segment_path.to_path_buf(),
+        workspace_path: ws_path.to_path_buf(),
+        workspace_name: workspace_name.to_string(),
+    };
+    if let Err(e) = cached.write() {
+        warn!("Failed to cache workspace context: {:#}", e);
+    }
+}
+
 /// Spawn a background thread to delete all `.cleanup-*` directories under `parent`.
 fn spawn_background_cleanup(parent: PathBuf) {
     std::thread::spawn(move || {
@@ -49,7 +65,11 @@ fn spawn_background_cleanup(parent: PathBuf) {
     });
 }
 
-use crate::workspace_setup::{BreqConfig, SetupResult, WorkspaceSetup};
+use crate::workspace_setup::{
+    ActionFailure, BreqConfig, SetupProgress, SetupResult, WorkspaceFacts, WorkspaceSetup,
+};
+use crate::proxy_routes::{self, SegmentDomain, StaleRoute, WorkspaceRoute};
+use crate::{Assignment, Segment};
 
 /// Version control system type for a repository
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -69,8 +89,44 @@ pub fn detect_repo_type(path: &Path) -> Option<RepoType> {
     }
 }
 
+/// What [`VcsBackend::diff`] shows of a workspace's changes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiffOptions {
+    /// Per-file change counts instead of the patch
+    #[serde(default)]
+    pub stat: bool,
+    /// Limit to one file (or directory), relative to the directory diffed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Revision to diff from; defaults to where the workspace forked from base
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Revision to diff to; defaults to the working copy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+/// Diff of a workspace's changes, run in `dir` (the workspace or its focus
+/// directory, which scopes the diff to it).
+pub fn workspace_diff(
+    workspace_path: &Path,
+    dir: &Path,
+    base_ref: Option<&str>,
+    opts: &DiffOptions,
+) -> Result<String> {
+    let backend: Box<dyn VcsBackend> = match detect_repo_type(workspace_path) {
+        Some(RepoType::Git) => Box::new(GitWorktreeBackend),
+        Some(RepoType::Jj) => Box::new(JjBackend),
+        None => anyhow::bail!("No jj or git workspace at {}", workspace_path.display()),
+    };
+    backend.diff(dir, base_ref, opts)
+}
+
+/// Trunk commits [`WorkspaceManager::recent_commits`] returns.
+pub const RECENT_COMMITS: usize = 10;
+
 /// A commit in a workspace
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CommitInfo {
     /// Commit/change identifier (commit_id for git, change_id for jj)
     pub id: String,
@@ -135,12 +191,44 @@ pub trait VcsBackend: Send + Sync {
     /// Returns true if a commit was made.
     fn auto_commit(&self, workspace_path: &Path, message: &str) -> Result<bool>;
 
-    /// Check if workspace has changes (committed or uncommitted) vs base
-    fn has_changes(&self, workspace_path: &Path, base_ref: Option<&str>) -> bool;
+    /// Check if workspace has changes (committed or uncommitted) vs base.
+    /// With `scope`, only changes under that workspace-relative path count.
+    fn has_changes(&self, workspace_path: &Path, base_ref: Option<&str>, scope: Option<&Path>)
+        -> bool {
+        workspace_path.exists()
+            && self
+                .change_commands(base_ref, scope)
+                .iter()
+                .any(|argv| run_change_command(argv, workspace_path).is_some_and(|out| !out.trim().is_empty()))
+    }
+
+    /// The commands `has_changes` runs (program first). The workspace has
+    /// changes if any of them succeeds with non-empty output.
+    fn change_commands(&self, base_ref: Option<&str>, scope: Option<&Path>) -> Vec<Vec<String>>;
+
+    /// Paths with unresolved conflicts in the working copy (for jj, in `@`).
+    /// Empty if there are none or they can't be determined.
+    fn conflicted_paths(&self, workspace_path: &Path) -> Vec<String>;
+
+    /// Git-format diff of what a push publishes: the workspace's changes
+    /// since it forked from base.
+    fn push_diff(&self, workspace_path: &Path, base_ref: Option<&str>) -> Result<String>;
+
+    /// Diff of the workspace's changes in `dir`, by default from where it
+    /// forked from base to the working copy.
+    fn diff(&self, dir: &Path, base_ref: Option<&str>, opts: &DiffOptions) -> Result<String>;
+
+    /// The latest `limit` commits on the repo's trunk (`base_ref` for git),
+    /// newest first.
+    fn recent_commits(&self, repo_path: &Path, base_ref: Option<&str>, limit: usize) -> Result<Vec<CommitInfo>>;
 
     /// Capture the current revision/commit hash
     fn capture_revision(&self, workspace_path: &Path) -> Option<String>;
 
+    /// Move a freshly created workspace to a revision `capture_revision`
+    /// returned, to bring back a destroyed workspace's work
+    fn checkout_revision(&self, workspace_path: &Path, revision: &str) -> Result<()>;
+
     /// Detect the active branch in a segment repo (for base_branch recording at assign time)
     fn active_branch(&self, segment_path: &Path) -> Option<String>;
 }
@@ -321,57 +409,113 @@ impl VcsBackend for JjBackend {
             .unwrap_or(true);
 
         if is_empty {
+            // The agent may have run `jj new` itself, leaving its work in an
+            // undescribed @-. Describe it rather than push "(no description set)".
+            if jj_undescribed(workspace_path, "@- ~ ::default@ ~ empty()") {
+                info!("Describing undescribed jj commit @-");
+                jj_describe(workspace_path, "@-", message)?;
+                return Ok(true);
+            }
             debug!("jj working commit is empty, skipping auto-commit");
             return Ok(false);
         }
 
+        // Keep a description the agent already wrote; only fill in empty ones
+        if jj_undescribed(workspace_path, "@") {
+            info!("Describing jj working commit");
+            jj_describe(workspace_path, "@", message)?;
+        }
+
         info!("Auto-committing jj changes");
         let status = Command::new("jj")
-            .args(["commit", "-m", message])
+            .args(["new"])
             .current_dir(workspace_path)
             .status()?;
 
         if !status.success() {
-            anyhow::bail!("jj commit failed");
+            anyhow::bail!("jj new failed");
         }
 
         Ok(true)
     }
 
-    fn has_changes(&self, workspace_path: &Path, _base_ref: Option<&str>) -> bool {
-        if !workspace_path.exists() {
-            return false;
-        }
-
+    fn change_commands(&self, _base_ref: Option<&str>, scope: Option<&Path>) -> Vec<Vec<String>> {
         // Non-empty commits ahead of default@ (includes dirty working copy
         // since jj auto-snapshots when this command runs, making @ reflect
-        // the actual disk state).
-        let log_output = Command::new("jj")
-            .args([
-                "log",
-                "-r",
-                "::@ ~ ::default@ ~ empty()",
-                "--no-graph",
-                "-T",
-                r#"change_id ++ "\n""#,
-            ])
+        // the actual disk state). A scope narrows this to commits touching it.
+        let revset = match scope {
+            Some(scope) => format!(
+                "(::@ ~ ::default@) & files({})",
+                serde_json::to_string(&scope.to_string_lossy()).unwrap_or_default()
+            ),
+            None => "::@ ~ ::default@ ~ empty()".to_string(),
+        };
+        vec![[
+            "jj",
+            "log",
+            "-r",
+            &revset,
+            "--no-graph",
+            "-T",
+            r#"change_id ++ "\n""#,
+        ]
+        .map(String::from)
+        .to_vec()]
+    }
+
+    fn push_diff(&self, workspace_path: &Path, _base_ref: Option<&str>) -> Result<String> {
+        command_stdout(
+            Command::new("jj")
+                .args(["diff", "--git", "--from", "heads(::@ & ::default@)", "--to", "@"])
+                .current_dir(workspace_path),
+            "jj diff",
+        )
+    }
+
+    fn diff(&self, dir: &Path, _base_ref: Option<&str>, opts: &DiffOptions) -> Result<String> {
+        let mut cmd = Command::new("jj");
+        cmd.arg("diff")
+            .arg(if opts.stat { "--stat" } else { "--git" })
+            .args(["--from", opts.from.as_deref().unwrap_or("heads(::@ & ::default@)")])
+            .args(["--to", opts.to.as_deref().unwrap_or("@")])
+            .args(["--", opts.file.as_deref().unwrap_or(".")])
+            .current_dir(dir);
+        command_stdout(&mut cmd, "jj diff")
+    }
+
+    fn conflicted_paths(&self, workspace_path: &Path) -> Vec<String> {
+        // Fails with "No conflicts found" when @ is clean
+        Command::new("jj")
+            .args(["resolve", "--list"])
             .current_dir(workspace_path)
             .output()
-            .ok();
-
-        if let Some(output) = log_output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                return !stdout.trim().is_empty();
-            }
-        }
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| jj_conflict_lines(&String::from_utf8_lossy(&o.stdout)))
+            .unwrap_or_default()
+    }
 
-        false
+    fn recent_commits(&self, repo_path: &Path, _base_ref: Option<&str>, limit: usize) -> Result<Vec<CommitInfo>> {
+        let revset = format!("ancestors(trunk(), {})", limit);
+        let stdout = command_stdout(
+            Command::new("jj")
+                .args(["log", "-r", &revset, "--no-graph", "-T"])
+                .arg(r#"commit_id.short() ++ " " ++ description.first_line() ++ "\n""#)
+                .current_dir(repo_path),
+            "jj log",
+        )?;
+        Ok(commit_lines(&stdout))
     }
 
     fn capture_revision(&self, workspace_path: &Path) -> Option<String> {
+        // After an auto-commit @ is a fresh, empty change; the work is in @-
+        let rev = if jj_undescribed(workspace_path, "@ & empty()") {
+            "@-"
+        } else {
+            "@"
+        };
         let output = Command::new("jj")
-            .args(["log", "-r", "@", "--no-graph", "-T", "commit_id"])
+            .args(["log", "-r", rev, "--no-graph", "-T", "commit_id"])
             .current_dir(workspace_path)
             .output()
             .ok()?;
@@ -385,12 +529,89 @@ impl VcsBackend for JjBackend {
         None
     }
 
+    fn checkout_revision(&self, workspace_path: &Path, revision: &str) -> Result<()> {
+        // The commit outlives `jj workspace forget`; continue on top of it
+        command_stdout(
+            Command::new("jj").args(["new", revision]).current_dir(workspace_path),
+            "jj new",
+        )?;
+        Ok(())
+    }
+
     fn active_branch(&self, _segment_path: &Path) -> Option<String> {
         // jj doesn't have a "current branch" — the default workspace is the reference
         None
     }
 }
 
+/// Commits listed as `<id> <summary>` lines.
+fn commit_lines(stdout: &str) -> Vec<CommitInfo> {
+    stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (id, summary) = line.split_once(' ').unwrap_or((line, ""));
+            CommitInfo {
+                id: id.to_string(),
+                summary: summary.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Paths from `jj resolve --list`, whose lines pad the path with spaces
+/// before describing the conflict (`src/lib.rs    2-sided conflict`).
+fn jj_conflict_lines(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| line.split("  ").next())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Stdout of a command that must succeed; `what` names it in errors.
+fn command_stdout(cmd: &mut Command, what: &str) -> Result<String> {
+    let output = cmd.output().with_context(|| format!("Failed to execute {}", what))?;
+    if !output.status.success() {
+        anyhow::bail!("{} failed: {}", what, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run one of a backend's `change_commands` in `cwd`; its stdout if it succeeded.
+pub fn run_change_command(argv: &[String], cwd: &Path) -> Option<String> {
+    let (program, args) = argv.split_first()?;
+    let output = Command::new(program).args(args).current_dir(cwd).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Whether `revset` resolves to at least one commit with an empty description.
+fn jj_undescribed(workspace_path: &Path, revset: &str) -> bool {
+    let revset = format!(r#"({}) & description(exact:"")"#, revset);
+    Command::new("jj")
+        .args(["log", "-r", &revset, "--no-graph", "-T", r#"change_id ++ "\n""#])
+        .current_dir(workspace_path)
+        .output()
+        .map(|o| o.status.success() && !String::from_utf8_lossy(&o.stdout).trim().is_empty())
+        .unwrap_or(false)
+}
+
+fn jj_describe(workspace_path: &Path, rev: &str, message: &str) -> Result<()> {
+    let status = Command::new("jj")
+        .args(["describe", "-r", rev, "-m", message])
+        .current_dir(workspace_path)
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("jj describe failed");
+    }
+    Ok(())
+}
+
 // ==================== Git Worktree Backend ====================
 
 pub struct GitWorktreeBackend;
@@ -725,45 +946,70 @@ impl VcsBackend for GitWorktreeBackend {
         Ok(true)
     }
 
-    fn has_changes(&self, workspace_path: &Path, base_ref: Option<&str>) -> bool {
-        if !workspace_path.exists() {
-            return false;
-        }
-
+    fn change_commands(&self, base_ref: Option<&str>, scope: Option<&Path>) -> Vec<Vec<String>> {
         let base = base_ref.unwrap_or("main");
+        let pathspec: Vec<String> = match scope {
+            Some(scope) => vec!["--".to_string(), scope.to_string_lossy().into_owned()],
+            None => Vec::new(),
+        };
 
         // Check 1: commits ahead of base
-        let log_range = format!("{}..HEAD", base);
-        let log_output = Command::new("git")
-            .args(["log", &log_range, "--oneline"])
-            .current_dir(workspace_path)
-            .output()
-            .ok();
+        let mut log = ["git", "log", &format!("{}..HEAD", base), "--oneline"]
+            .map(String::from)
+            .to_vec();
+        log.extend(pathspec.iter().cloned());
 
-        if let Some(output) = log_output {
-            if output.status.success()
-                && !String::from_utf8_lossy(&output.stdout).trim().is_empty()
-            {
-                return true;
-            }
+        // Check 2: dirty working tree (uncommitted/unstaged changes)
+        let mut status = ["git", "status", "--porcelain"].map(String::from).to_vec();
+        status.extend(pathspec);
+
+        vec![log, status]
+    }
+
+    fn push_diff(&self, workspace_path: &Path, base_ref: Option<&str>) -> Result<String> {
+        let range = format!("{}...HEAD", base_ref.unwrap_or("main"));
+        command_stdout(
+            Command::new("git").args(["diff", &range]).current_dir(workspace_path),
+            "git diff",
+        )
+    }
+
+    fn diff(&self, dir: &Path, base_ref: Option<&str>, opts: &DiffOptions) -> Result<String> {
+        let from = opts.from.as_deref().or(base_ref).unwrap_or("main");
+        let mut cmd = Command::new("git");
+        cmd.arg("diff");
+        if opts.stat {
+            cmd.arg("--stat");
         }
+        match opts.to {
+            Some(ref to) => cmd.arg(format!("{}...{}", from, to)),
+            // Against the working tree, so uncommitted changes show too
+            None => cmd.args(["--merge-base", from]),
+        };
+        cmd.args(["--", opts.file.as_deref().unwrap_or(".")]).current_dir(dir);
+        command_stdout(&mut cmd, "git diff")
+    }
 
-        // Check 2: dirty working tree (uncommitted/unstaged changes)
-        let diff_output = Command::new("git")
-            .args(["status", "--porcelain"])
+    fn conflicted_paths(&self, workspace_path: &Path) -> Vec<String> {
+        // Unmerged paths left by a merge, rebase or cherry-pick
+        Command::new("git")
+            .args(["diff", "--name-only", "--diff-filter=U"])
             .current_dir(workspace_path)
             .output()
-            .ok();
-
-        if let Some(output) = diff_output {
-            if output.status.success()
-                && !String::from_utf8_lossy(&output.stdout).trim().is_empty()
-            {
-                return true;
-            }
-        }
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
 
-        false
+    fn recent_commits(&self, repo_path: &Path, base_ref: Option<&str>, limit: usize) -> Result<Vec<CommitInfo>> {
+        let stdout = command_stdout(
+            Command::new("git")
+                .args(["log", &format!("-{}", limit), "--format=%h %s", base_ref.unwrap_or("HEAD"), "--"])
+                .current_dir(repo_path),
+            "git log",
+        )?;
+        Ok(commit_lines(&stdout))
     }
 
     fn capture_revision(&self, workspace_path: &Path) -> Option<String> {
@@ -782,6 +1028,16 @@ impl VcsBackend for GitWorktreeBackend {
         None
     }
 
+    fn checkout_revision(&self, workspace_path: &Path, revision: &str) -> Result<()> {
+        // The commit stays in the object store after its worktree (and
+        // possibly its branch) is removed, until git gc prunes it
+        command_stdout(
+            Command::new("git").args(["reset", "--hard", revision]).current_dir(workspace_path),
+            "git reset",
+        )?;
+        Ok(())
+    }
+
     fn active_branch(&self, segment_path: &Path) -> Option<String> {
         let output = Command::new("git")
             .args(["rev-parse", "--abbrev-ref", "HEAD"])
@@ -805,7 +1061,19 @@ impl VcsBackend for GitWorktreeBackend {
 /// to the appropriate backend (jj or git) based on segment repo type.
 pub struct WorkspaceManager {
     workspace_root: PathBuf,
+    /// Per-segment workspace roots that replace `workspace_root`
+    segment_roots: HashMap<String, PathBuf>,
     local_domain: Option<String>,
+    /// Station proxy backend name ("caddy", "traefik", "nginx")
+    proxy_backend: Option<String>,
+    /// `[proxy] host_template` for workspace route hosts
+    host_template: Option<String>,
+    /// Agent memory file that receives the toren context block (e.g. "CLAUDE.local.md")
+    context_file: Option<String>,
+    /// Keep a direnv `.envrc` in workspaces unless toren.kdl says otherwise
+    envrc: bool,
+    /// Receiver of setup progress (see [`SetupProgress`])
+    setup_progress: Option<Arc<dyn SetupProgress>>,
 }
 
 impl WorkspaceManager {
@@ -818,7 +1086,52 @@ impl WorkspaceManager {
                 .unwrap_or_else(|_| PathBuf::from("."))
                 .join(&workspace_root)
         };
-        Self { workspace_root, local_domain }
+        Self {
+            workspace_root,
+            segment_roots: HashMap::new(),
+            local_domain,
+            proxy_backend: None,
+            host_template: None,
+            context_file: None,
+            envrc: false,
+            setup_progress: None,
+        }
+    }
+
+    /// Maintain a toren context block in `file_name` inside each workspace.
+    pub fn with_context_file(mut self, file_name: Option<String>) -> Self {
+        self.context_file = file_name;
+        self
+    }
+
+    /// Maintain a toren block in each workspace's `.envrc` (see [`crate::envrc`]).
+    pub fn with_envrc(mut self, envrc: bool) -> Self {
+        self.envrc = envrc;
+        self
+    }
+
+    /// Place the workspaces of specific segments under their own roots.
+    pub fn with_segment_roots(mut self, roots: HashMap<String, PathBuf>) -> Self {
+        self.segment_roots = roots;
+        self
+    }
+
+    /// Select the station proxy backend for proxy setup and route cleanup.
+    pub fn with_proxy_backend(mut self, backend: Option<String>) -> Self {
+        self.proxy_backend = backend;
+        self
+    }
+
+    /// Render workspace route hosts with `[proxy] host_template`.
+    pub fn with_host_template(mut self, template: Option<String>) -> Self {
+        self.host_template = template;
+        self
+    }
+
+    /// Report setup progress to `progress` instead of printing it.
+    pub fn with_setup_progress(mut self, progress: Option<Arc<dyn SetupProgress>>) -> Self {
+        self.setup_progress = progress;
+        self
     }
 
     /// Get the VCS backend for a segment based on repo type detection
@@ -835,9 +1148,18 @@ impl WorkspaceManager {
     }
 
     /// Get the workspace directory path for a given segment and workspace name
-    /// Pattern: $workspace_root/$segment_name/$workspace_name
+    /// Pattern: $workspace_root/$segment_name/$workspace_name, where the root
+    /// may be overridden for the segment
     pub fn workspace_path(&self, segment_name: &str, workspace_name: &str) -> PathBuf {
-        self.workspace_root.join(segment_name).join(workspace_name)
+        self.segment_dir(segment_name).join(workspace_name)
+    }
+
+    /// Directory holding a segment's workspaces
+    pub fn segment_dir(&self, segment_name: &str) -> PathBuf {
+        self.segment_roots
+            .get(segment_name)
+            .unwrap_or(&self.workspace_root)
+            .join(segment_name)
     }
 
     /// Detect the repo type for a segment
@@ -861,15 +1183,44 @@ impl WorkspaceManager {
             .workspace_info(workspace_path, base_ref)
     }
 
-    /// Check if workspace has changes (committed or uncommitted) vs base
+    /// Check if workspace has changes (committed or uncommitted) vs base,
+    /// optionally only under the workspace-relative `scope`
     pub fn has_changes(
         &self,
         segment_path: &Path,
         workspace_path: &Path,
         base_ref: Option<&str>,
+        scope: Option<&Path>,
     ) -> bool {
         self.backend_for(segment_path)
-            .has_changes(workspace_path, base_ref)
+            .has_changes(workspace_path, base_ref, scope)
+    }
+
+    /// Diff of what pushing the workspace would publish (see [`crate::push_checks`]).
+    pub fn push_diff(
+        &self,
+        segment_path: &Path,
+        workspace_path: &Path,
+        base_ref: Option<&str>,
+    ) -> Result<String> {
+        self.backend_for(segment_path).push_diff(workspace_path, base_ref)
+    }
+
+    /// The latest [`RECENT_COMMITS`] commits on the segment's trunk, newest
+    /// first, for prompt templates (`repo.recent_commits`). Empty if the log
+    /// can't be read.
+    pub fn recent_commits(&self, segment_path: &Path, base_ref: Option<&str>) -> Vec<CommitInfo> {
+        self.backend_for(segment_path)
+            .recent_commits(segment_path, base_ref, RECENT_COMMITS)
+            .unwrap_or_else(|e| {
+                debug!("No recent commits for {}: {:#}", segment_path.display(), e);
+                Vec::new()
+            })
+    }
+
+    /// Paths with unresolved conflicts in a workspace's working copy
+    pub fn conflicted_paths(&self, segment_path: &Path, workspace_path: &Path) -> Vec<String> {
+        self.backend_for(segment_path).conflicted_paths(workspace_path)
     }
 
     /// Push workspace changes to remote
@@ -904,6 +1255,7 @@ impl WorkspaceManager {
     ///
     /// If the directory exists but is not tracked by VCS (orphaned from a previous
     /// cleanup), it is removed before creating the new workspace.
+    #[tracing::instrument(name = "workspace.create", skip(self, segment_path), fields(segment = segment_name, workspace = workspace_name))]
     pub fn create_workspace(
         &self,
         segment_path: &Path,
@@ -935,6 +1287,7 @@ impl WorkspaceManager {
 
             if is_tracked && backend.is_valid_workspace(&ws_path) {
                 // Valid, tracked workspace - reuse it
+                write_context_cache(segment_path, &ws_path, workspace_name);
                 return Ok(ws_path);
             }
 
@@ -954,6 +1307,7 @@ impl WorkspaceManager {
 
         // Create VCS workspace
         backend.create_workspace(segment_path, &ws_path, workspace_name)?;
+        write_context_cache(segment_path, &ws_path, workspace_name);
 
         info!("Created workspace at {}", ws_path.display());
         Ok(ws_path)
@@ -1012,12 +1366,18 @@ impl WorkspaceManager {
         // external resources (proxy routes, daemons, etc.) for a partially
         // destroyed workspace. Individual actions opt into warn/ignore via
         // on_fail if they need the workspace dir.
-        if let Err(e) = self.run_destroy(segment_path, &ws_path, workspace_name) {
-            // {:#} prints the full anyhow chain inline (outer + each `.context()`
-            // + the source error), instead of just the outermost message.
-            warn!("Workspace destroy hooks failed: {:#}", e);
-            // Continue with cleanup even if destroy fails
-        }
+        let result = match self.run_destroy(segment_path, &ws_path, workspace_name) {
+            Ok(result) => result,
+            Err(e) => {
+                // {:#} prints the full anyhow chain inline (outer + each `.context()`
+                // + the source error), instead of just the outermost message.
+                warn!("Workspace destroy hooks failed: {:#}", e);
+                // Continue with cleanup even if destroy fails
+                SetupResult {
+                    failed: ActionFailure::from_error(&e).into_iter().collect(),
+                }
+            }
+        };
 
         // Remove VCS tracking (backend-specific behavior based on mode)
         let backend = self.backend_for(segment_path);
@@ -1026,7 +1386,29 @@ impl WorkspaceManager {
         // Delete workspace directory (if VCS removal didn't already do it)
         self.delete_workspace(segment_name, workspace_name)?;
 
-        Ok(SetupResult)
+        // Release leased ports after destroy, which may still have needed them
+        match crate::ports::PortRegistry::new().release(&ws_path) {
+            Ok(ports) if !ports.is_empty() => debug!("Released ports {:?}", ports),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to release ports for '{}': {:#}", workspace_name, e),
+        }
+
+        // A new workspace with this name must run every setup action again
+        let manifest = crate::setup_manifest::manifest_path(segment_name, workspace_name);
+        if manifest.exists() {
+            if let Err(e) = std::fs::remove_file(&manifest) {
+                warn!("Failed to remove setup manifest {}: {}", manifest.display(), e);
+            }
+        }
+
+        let scratch = crate::agent_context::scratch_dir(segment_name, workspace_name);
+        if scratch.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&scratch) {
+                warn!("Failed to remove scratch dir {}: {}", scratch.display(), e);
+            }
+        }
+
+        Ok(result)
     }
 
     /// List workspaces for a segment
@@ -1041,6 +1423,7 @@ impl WorkspaceManager {
     }
 
     /// Run workspace setup hooks if toren.kdl exists
+    #[tracing::instrument(name = "workspace.setup", skip_all, fields(workspace = workspace_name))]
     pub fn run_setup(
         &self,
         segment_path: &Path,
@@ -1050,7 +1433,7 @@ impl WorkspaceManager {
     ) -> Result<SetupResult> {
         if !BreqConfig::exists(segment_path) {
             debug!("No toren.kdl found, skipping setup");
-            return Ok(SetupResult);
+            return Ok(SetupResult::default());
         }
 
         let setup = WorkspaceSetup::new(
@@ -1059,12 +1442,24 @@ impl WorkspaceManager {
             workspace_name.to_string(),
             ancillary_num,
             self.local_domain.clone(),
-        );
+        )
+        .with_proxy_backend(self.proxy_backend.clone())
+        .with_host_template(self.host_template.clone())
+        .with_manifest(Self::setup_manifest_path(workspace_path))
+        .with_progress(self.setup_progress.clone());
 
         setup.run_setup()
     }
 
+    /// Setup manifest for a workspace at `{workspace_root}/{segment}/{name}`.
+    fn setup_manifest_path(workspace_path: &Path) -> Option<PathBuf> {
+        let name = workspace_path.file_name()?.to_str()?;
+        let segment = workspace_path.parent()?.file_name()?.to_str()?;
+        Some(crate::setup_manifest::manifest_path(segment, name))
+    }
+
     /// Run workspace destroy hooks if toren.kdl exists
+    #[tracing::instrument(name = "workspace.destroy", skip_all, fields(workspace = workspace_name))]
     pub fn run_destroy(
         &self,
         segment_path: &Path,
@@ -1073,7 +1468,7 @@ impl WorkspaceManager {
     ) -> Result<SetupResult> {
         if !BreqConfig::exists(segment_path) {
             debug!("No toren.kdl found, skipping destroy");
-            return Ok(SetupResult);
+            return Ok(SetupResult::default());
         }
 
         let setup = WorkspaceSetup::new(
@@ -1082,11 +1477,261 @@ impl WorkspaceManager {
             workspace_name.to_string(),
             0, // ancillary_num not available during destroy
             self.local_domain.clone(),
-        );
+        )
+        .with_proxy_backend(self.proxy_backend.clone())
+        .with_host_template(self.host_template.clone());
 
         setup.run_destroy()
     }
 
+    /// Re-apply proxy routes from toren.kdl for an existing workspace.
+    /// Returns the number of routes applied.
+    pub fn reconcile_proxies(
+        &self,
+        segment_path: &Path,
+        workspace_path: &Path,
+        workspace_name: &str,
+        ancillary_num: u32,
+    ) -> Result<usize> {
+        if !BreqConfig::exists(segment_path) {
+            return Ok(0);
+        }
+
+        let setup = WorkspaceSetup::new(
+            segment_path.to_path_buf(),
+            workspace_path.to_path_buf(),
+            workspace_name.to_string(),
+            ancillary_num,
+            self.local_domain.clone(),
+        )
+        .with_proxy_backend(self.proxy_backend.clone())
+        .with_host_template(self.host_template.clone());
+
+        setup.run_proxies()
+    }
+
+    /// Station domains of the given segments, as their setup resolves them
+    /// (`{repo}.{local_domain}` by default; see [`crate::proxy_domains`]).
+    fn segment_domains(&self, segments: &[Segment]) -> Vec<SegmentDomain> {
+        let Some(ref local_domain) = self.local_domain else {
+            return Vec::new();
+        };
+        segments
+            .iter()
+            .filter_map(|seg| {
+                let domain = crate::proxy_domains::station_domain(
+                    &seg.path,
+                    local_domain,
+                    self.host_template.as_deref(),
+                )
+                .map_err(|e| warn!("Skipping routes of segment {}: {:#}", seg.name, e))
+                .ok()?;
+                Some(SegmentDomain {
+                    segment: seg.name.clone(),
+                    domain,
+                })
+            })
+            .collect()
+    }
+
+    /// Routes a workspace's toren.kdl `proxy` directives resolve to, attributed
+    /// like live station routes. Doesn't consult the proxy.
+    pub fn declared_proxy_routes(
+        &self,
+        segment: &Segment,
+        workspace_path: &Path,
+        workspace_name: &str,
+        ancillary_num: u32,
+    ) -> Result<Vec<WorkspaceRoute>> {
+        let facts =
+            self.describe_workspace(&segment.path, workspace_path, workspace_name, ancillary_num)?;
+        let domains = self.segment_domains(std::slice::from_ref(segment));
+        Ok(proxy_routes::workspace_routes(&facts.proxy_routes, &domains))
+    }
+
+    /// Station routes that belong to workspaces of the given segments.
+    pub fn proxy_routes(&self, segments: &[Segment]) -> Result<Vec<WorkspaceRoute>> {
+        let domains = self.segment_domains(segments);
+        if domains.is_empty() {
+            return Ok(Vec::new());
+        }
+        let routes = proxy_routes::list_routes(self.proxy_backend.as_deref())?;
+        Ok(proxy_routes::workspace_routes(&routes, &domains))
+    }
+
+    /// Forget every station route of one workspace. Returns the removed routes.
+    pub fn remove_workspace_routes(
+        &self,
+        segments: &[Segment],
+        segment: &str,
+        workspace: &str,
+    ) -> Result<Vec<WorkspaceRoute>> {
+        let routes: Vec<_> = self
+            .proxy_routes(segments)?
+            .into_iter()
+            .filter(|r| r.segment == segment && r.workspace == workspace)
+            .collect();
+        let mut forgotten: Vec<&str> = Vec::new();
+        for route in &routes {
+            if forgotten.contains(&route.name.as_str()) {
+                continue;
+            }
+            proxy_routes::forget(&route.name, &route.domain, self.proxy_backend.as_deref())?;
+            forgotten.push(&route.name);
+        }
+        Ok(routes)
+    }
+
+    /// Proxy routes whose workspace no longer exists.
+    ///
+    /// A workspace is live if its directory exists or an active assignment
+    /// still points at it.
+    pub fn stale_proxy_routes(
+        &self,
+        segments: &[Segment],
+        active: &[Assignment],
+    ) -> Result<Vec<StaleRoute>> {
+        let domains = self.segment_domains(segments);
+        if domains.is_empty() {
+            return Ok(Vec::new());
+        }
+        let routes = proxy_routes::list_routes(self.proxy_backend.as_deref())?;
+        Ok(proxy_routes::stale_routes(&routes, &domains, |segment, workspace| {
+            let path = self.workspace_path(segment, workspace);
+            path.exists() || active.iter().any(|a| a.workspace_path == path)
+        }))
+    }
+
+    /// Forget proxy routes whose workspace no longer exists.
+    ///
+    /// Returns the removed routes; a route that fails to be removed is
+    /// logged and skipped.
+    pub fn gc_proxy_routes(
+        &self,
+        segments: &[Segment],
+        active: &[Assignment],
+    ) -> Result<Vec<StaleRoute>> {
+        let mut removed = Vec::new();
+        for route in self.stale_proxy_routes(segments, active)? {
+            match proxy_routes::forget(&route.name, &route.domain, self.proxy_backend.as_deref()) {
+                Ok(()) => {
+                    info!("Removed stale proxy route {}", route.host);
+                    removed.push(route);
+                }
+                Err(e) => warn!("Failed to remove stale route {}: {:#}", route.host, e),
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Evaluate toren.kdl vars and proxy routes for a workspace without running setup.
+    pub fn describe_workspace(
+        &self,
+        segment_path: &Path,
+        workspace_path: &Path,
+        workspace_name: &str,
+        ancillary_num: u32,
+    ) -> Result<WorkspaceFacts> {
+        if !BreqConfig::exists(segment_path) {
+            return Ok(WorkspaceFacts::default());
+        }
+
+        let setup = WorkspaceSetup::new(
+            segment_path.to_path_buf(),
+            workspace_path.to_path_buf(),
+            workspace_name.to_string(),
+            ancillary_num,
+            self.local_domain.clone(),
+        )
+        .with_proxy_backend(self.proxy_backend.clone())
+        .with_host_template(self.host_template.clone());
+
+        setup.describe()
+    }
+
+    /// Evaluate toren.kdl vars for a workspace, for templates outside setup
+    /// (intent and resume prompts). Empty when the repo has no toren.kdl.
+    pub fn workspace_vars(
+        &self,
+        segment_path: &Path,
+        workspace_path: &Path,
+        workspace_name: &str,
+        ancillary_num: u32,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        if !BreqConfig::exists(segment_path) {
+            return Ok(HashMap::new());
+        }
+        WorkspaceSetup::new(
+            segment_path.to_path_buf(),
+            workspace_path.to_path_buf(),
+            workspace_name.to_string(),
+            ancillary_num,
+            self.local_domain.clone(),
+        )
+        .vars()
+    }
+
+    /// Write (or refresh) the toren context block for an assignment's workspace,
+    /// and its `.envrc` block when enabled. Returns the context file written,
+    /// or None if no context file is configured.
+    pub fn write_agent_context(
+        &self,
+        segment_path: &Path,
+        assignment: &Assignment,
+    ) -> Result<Option<PathBuf>> {
+        let envrc = self.envrc_enabled(segment_path);
+        if self.context_file.is_none() && !envrc {
+            return Ok(None);
+        }
+        let ws_path = &assignment.workspace_path;
+        let ws_name = ws_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .context("Invalid workspace path")?;
+        let ancillary_num = crate::ancillary_number(&assignment.ancillary_id).unwrap_or(0);
+
+        let facts = self.describe_workspace(segment_path, ws_path, ws_name, ancillary_num)?;
+        let scratch = crate::agent_context::scratch_dir(&assignment.segment, ws_name);
+        std::fs::create_dir_all(&scratch)
+            .with_context(|| format!("Failed to create scratch dir {}", scratch.display()))?;
+
+        if envrc {
+            let section = crate::envrc::render_section(assignment, &facts, &scratch);
+            crate::envrc::write(ws_path, &section)?;
+        }
+        let Some(ref file_name) = self.context_file else {
+            return Ok(None);
+        };
+        let section = crate::agent_context::render_section(assignment, &facts, &scratch);
+        crate::agent_context::write_file(ws_path, file_name, &section).map(Some)
+    }
+
+    /// Whether workspaces of this repo get an `.envrc`: toren.kdl's `envrc`
+    /// node if present, else `[ancillaries] envrc`.
+    fn envrc_enabled(&self, segment_path: &Path) -> bool {
+        BreqConfig::parse(segment_path)
+            .ok()
+            .and_then(|config| config.envrc)
+            .unwrap_or(self.envrc)
+    }
+
+    /// Strip the toren context and `.envrc` blocks, setup's `.toren-env` and
+    /// the context cache from a workspace (before committing its changes).
+    pub fn remove_agent_context(&self, workspace_path: &Path) -> Result<bool> {
+        let env_file = workspace_path.join(crate::WORKSPACE_ENV_FILE);
+        if env_file.exists() {
+            std::fs::remove_file(&env_file)
+                .with_context(|| format!("Failed to remove {}", env_file.display()))?;
+        }
+        crate::context_cache::CachedContext::remove(workspace_path)?;
+        let envrc = crate::envrc::remove(workspace_path)?;
+        let context = match self.context_file {
+            Some(ref file_name) => crate::agent_context::remove_from_file(workspace_path, file_name)?,
+            None => false,
+        };
+        Ok(envrc || context)
+    }
+
     /// Create workspace and run setup hooks.
     /// If setup fails, the workspace is rolled back.
     pub fn create_workspace_with_setup(
@@ -1096,7 +1741,37 @@ impl WorkspaceManager {
         workspace_name: &str,
         ancillary_num: u32,
     ) -> Result<(PathBuf, SetupResult)> {
-        let ws_path = self.create_workspace(segment_path, segment_name, workspace_name)?;
+        self.restore_workspace(segment_path, segment_name, workspace_name, ancillary_num, None)
+    }
+
+    /// Create a workspace at `revision` (as captured when a workspace was
+    /// destroyed) and run setup hooks, which also re-adds its proxy routes.
+    /// Without a revision this is [`Self::create_workspace_with_setup`].
+    pub fn restore_workspace(
+        &self,
+        segment_path: &Path,
+        segment_name: &str,
+        workspace_name: &str,
+        ancillary_num: u32,
+        revision: Option<&str>,
+    ) -> Result<(PathBuf, SetupResult)> {
+        let ws_path = self
+            .create_workspace(segment_path, segment_name, workspace_name)
+            .and_then(|ws_path| {
+                if let Some(revision) = revision {
+                    let backend = self.backend_for(segment_path);
+                    if let Err(e) = backend.checkout_revision(&ws_path, revision) {
+                        let _ = backend.remove_vcs_tracking(segment_path, &ws_path, workspace_name, CleanupMode::Abort);
+                        let _ = self.delete_workspace(segment_name, workspace_name);
+                        return Err(e.context(format!("Failed to restore revision {}", revision)));
+                    }
+                }
+                Ok(ws_path)
+            })
+            .map_err(|e| {
+                let failure = WorkspaceCreateFailure::new(CreateStage::Vcs, workspace_name, &e);
+                e.context(failure)
+            })?;
 
         // Run setup hooks if toren.kdl exists - fail if setup fails
         match self.run_setup(
@@ -1112,6 +1787,7 @@ impl WorkspaceManager {
                     "Setup failed for '{}', rolling back workspace: {}",
                     workspace_name, e
                 );
+                let mut failure = WorkspaceCreateFailure::new(CreateStage::Setup, workspace_name, &e);
                 if let Err(rollback_err) = self.cleanup_workspace(
                     segment_path,
                     segment_name,
@@ -1119,18 +1795,114 @@ impl WorkspaceManager {
                     CleanupMode::Abort,
                 ) {
                     warn!("Rollback cleanup also failed: {:#}", rollback_err);
+                    failure.rollback_error = Some(format!("{:#}", rollback_err));
                 }
-                Err(e).with_context(|| {
-                    format!(
-                        "Workspace setup failed for '{}' (workspace rolled back)",
-                        workspace_name
-                    )
-                })
+                Err(e.context(failure))
             }
         }
     }
 }
 
+/// Where [`WorkspaceManager::create_workspace_with_setup`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreateStage {
+    /// Creating the jj workspace or git worktree
+    Vcs,
+    /// Setup before any action ran (toren.kdl, vars, env)
+    Setup,
+    /// A setup action
+    SetupAction,
+}
+
+/// Error returned when completing a workspace whose working copy still has
+/// unresolved conflicts, unless `--allow-conflicts` was set.
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[error("Unresolved conflicts in workspace {workspace}:\n{}\nResolve them, or rerun with --allow-conflicts to complete anyway", paths.iter().map(|p| format!("  {p}")).collect::<Vec<_>>().join("\n"))]
+pub struct WorkspaceConflicted {
+    pub workspace: String,
+    pub paths: Vec<String>,
+}
+
+/// Structured cause of a failed workspace creation, attached as context to
+/// the error of [`WorkspaceManager::create_workspace_with_setup`] so API
+/// clients can show what failed instead of the whole error chain.
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+pub struct WorkspaceCreateFailure {
+    pub stage: CreateStage,
+    pub workspace: String,
+    /// 1-based index of the failed setup action
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<usize>,
+    /// Short description of the failed action, e.g. `run pnpm install`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_label: Option<String>,
+    /// Exit code of a failed `run` command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Stderr of a failed `run` command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// The underlying error chain
+    pub detail: String,
+    /// Set when removing the partially created workspace failed too
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollback_error: Option<String>,
+}
+
+impl WorkspaceCreateFailure {
+    fn new(stage: CreateStage, workspace: &str, err: &anyhow::Error) -> Self {
+        let action = err.downcast_ref::<crate::workspace_setup::ActionFailed>();
+        let command = err.downcast_ref::<crate::workspace_setup::CommandFailed>();
+        let stage = match (stage, action) {
+            (CreateStage::Setup, Some(_)) => CreateStage::SetupAction,
+            (stage, _) => stage,
+        };
+        Self {
+            stage,
+            workspace: workspace.to_string(),
+            action: action.map(|a| a.index),
+            action_label: action.map(|a| a.label.clone()),
+            exit_code: command.map(|c| c.exit_code),
+            output: command.map(|c| c.stderr.clone()).filter(|s| !s.is_empty()),
+            detail: format!("{:#}", err),
+            rollback_error: None,
+        }
+    }
+
+    /// One line for display, e.g. `run pnpm install failed (exit 1)`.
+    pub fn summary(&self) -> String {
+        match (self.stage, &self.action_label, self.exit_code) {
+            (CreateStage::SetupAction, Some(label), Some(code)) => {
+                format!("{} failed (exit {})", label, code)
+            }
+            (CreateStage::SetupAction, Some(label), None) => format!("{} failed", label),
+            (CreateStage::Vcs, ..) => "Creating the VCS workspace failed".to_string(),
+            _ => "Workspace setup failed".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for WorkspaceCreateFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.stage, &self.rollback_error) {
+            (CreateStage::Vcs, _) => {
+                write!(f, "Failed to create workspace '{}'", self.workspace)
+            }
+            (_, None) => write!(
+                f,
+                "Workspace setup failed for '{}' (workspace rolled back)",
+                self.workspace
+            ),
+            (_, Some(rollback)) => write!(
+                f,
+                "Workspace setup failed for '{}' (rollback also failed: {})",
+                self.workspace, rollback
+            ),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1156,14 +1928,46 @@ mod tests {
     fn test_jj_backend_nonexistent_workspace() {
         let backend = JjBackend;
         assert!(!backend.is_valid_workspace(std::path::Path::new("/nonexistent")));
-        assert!(!backend.has_changes(std::path::Path::new("/nonexistent"), None));
+        assert!(!backend.has_changes(std::path::Path::new("/nonexistent"), None, None));
     }
 
     #[test]
     fn test_git_backend_nonexistent_workspace() {
         let backend = GitWorktreeBackend;
         assert!(!backend.is_valid_workspace(std::path::Path::new("/nonexistent")));
-        assert!(!backend.has_changes(std::path::Path::new("/nonexistent"), None));
+        assert!(!backend.has_changes(std::path::Path::new("/nonexistent"), None, None));
+    }
+
+    #[test]
+    fn test_create_failure_classifies_setup_action() {
+        use crate::workspace_setup::{ActionFailed, CommandFailed};
+
+        let err = anyhow::Error::new(CommandFailed {
+            command: "pnpm install".to_string(),
+            exit_code: 1,
+            stderr: "ERR_PNPM_NO_LOCKFILE".to_string(),
+        })
+        .context(ActionFailed {
+            index: 3,
+            label: "run pnpm install".to_string(),
+        });
+        let failure = WorkspaceCreateFailure::new(CreateStage::Setup, "one", &err);
+        assert_eq!(failure.stage, CreateStage::SetupAction);
+        assert_eq!(failure.action, Some(3));
+        assert_eq!(failure.summary(), "run pnpm install failed (exit 1)");
+
+        let json = serde_json::to_value(&failure).unwrap();
+        assert_eq!(json["stage"], "setup_action");
+        assert_eq!(json["output"], "ERR_PNPM_NO_LOCKFILE");
+        assert!(json.get("rollback_error").is_none());
+
+        let wrapped = err.context(failure);
+        assert!(wrapped.downcast_ref::<WorkspaceCreateFailure>().is_some());
+        assert!(format!("{:#}", wrapped)
+            .starts_with("Workspace setup failed for 'one' (workspace rolled back): Action 3 failed"));
+
+        let vcs = WorkspaceCreateFailure::new(CreateStage::Vcs, "two", &anyhow::anyhow!("jj failed"));
+        assert_eq!((vcs.stage, vcs.action), (CreateStage::Vcs, None));
     }
 
     #[test]
@@ -1181,6 +1985,41 @@ mod tests {
         assert!(!mgr.workspace_exists("toren", "one"));
     }
 
+    #[test]
+    fn test_jj_conflict_lines() {
+        let out = "src/lib.rs    2-sided conflict\nREADME.md    2-sided conflict including 1 deletion\n";
+        assert_eq!(jj_conflict_lines(out), vec!["src/lib.rs", "README.md"]);
+        assert!(jj_conflict_lines("").is_empty());
+    }
+
+    #[test]
+    fn test_git_conflicted_paths_integration() {
+        let tmp = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(tmp.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        std::fs::write(tmp.path().join("a.txt"), "base").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-m", "base"]);
+        git(&["checkout", "-b", "other"]);
+        std::fs::write(tmp.path().join("a.txt"), "other").unwrap();
+        git(&["commit", "-am", "other"]);
+        git(&["checkout", "-"]);
+        std::fs::write(tmp.path().join("a.txt"), "ours").unwrap();
+        git(&["commit", "-am", "ours"]);
+
+        let backend = GitWorktreeBackend;
+        assert!(backend.conflicted_paths(tmp.path()).is_empty());
+        git(&["merge", "other"]);
+        assert_eq!(backend.conflicted_paths(tmp.path()), vec!["a.txt"]);
+    }
+
     #[test]
     fn test_git_worktree_list_parse() {
         // Test that we can create the backend and it handles nonexistent dirs gracefully
@@ -1243,11 +2082,13 @@ mod tests {
 
         // Initially no changes
         let active_branch = backend.active_branch(&repo_path).unwrap();
-        assert!(!backend.has_changes(&ws_path, Some(&active_branch)));
+        assert!(!backend.has_changes(&ws_path, Some(&active_branch), None));
 
         // Make a change
         std::fs::write(ws_path.join("test.txt"), "hello").unwrap();
-        assert!(backend.has_changes(&ws_path, Some(&active_branch)));
+        assert!(backend.has_changes(&ws_path, Some(&active_branch), None));
+        let scope = Path::new("crates/foo");
+        assert!(!backend.has_changes(&ws_path, Some(&active_branch), Some(scope)));
 
         // Auto-commit
         let committed = backend
@@ -1256,7 +2097,20 @@ mod tests {
         assert!(committed);
 
         // Should still have changes (committed but not in base)
-        assert!(backend.has_changes(&ws_path, Some(&active_branch)));
+        assert!(backend.has_changes(&ws_path, Some(&active_branch), None));
+        assert!(!backend.has_changes(&ws_path, Some(&active_branch), Some(scope)));
+        assert!(backend.has_changes(&ws_path, Some(&active_branch), Some(Path::new("test.txt"))));
+
+        // Diff from the fork point, uncommitted changes included
+        std::fs::write(ws_path.join("README.md"), "# Changed").unwrap();
+        let diff = backend.diff(&ws_path, Some(&active_branch), &DiffOptions::default()).unwrap();
+        assert!(diff.contains("+++ b/test.txt") && diff.contains("+# Changed"));
+        let stat = DiffOptions { stat: true, ..Default::default() };
+        assert!(backend.diff(&ws_path, Some(&active_branch), &stat).unwrap().contains("2 files changed"));
+        let one_file = DiffOptions { file: Some("test.txt".to_string()), ..Default::default() };
+        let diff = backend.diff(&ws_path, Some(&active_branch), &one_file).unwrap();
+        assert!(diff.contains("test.txt") && !diff.contains("README.md"));
+        std::fs::write(ws_path.join("README.md"), "# Test").unwrap();
 
         // Capture revision
         let rev = backend.capture_revision(&ws_path);
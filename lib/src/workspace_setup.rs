@@ -4,39 +4,203 @@
 //! using `toren.kdl` configuration files. It supports these primitive actions:
 //! - `template`: Copy and render files with workspace context
 //! - `copy`: Copy files verbatim
-//! - `run`: Execute shell commands (auto-gets `STATION_DOMAIN` env var)
+//! - `link`: Hardlink a directory tree file by file (falls back to `copy`)
+//! - `run`: Execute shell commands (auto-gets `STATION_DOMAIN`/`STATION_BACKEND` env vars)
 //! - `proxy`: Manage station reverse-proxy routes (auto-cleanup on destroy)
+//!
+//! Setup through [`WorkspaceManager`](crate::WorkspaceManager) is differential:
+//! actions whose inputs are unchanged since the last setup are skipped (see
+//! [`crate::setup_manifest`]). A [`SetupProgress`] receives each action's
+//! start, output and result as it runs.
 
 use anyhow::{Context, Result};
 use clonetree::Options as CloneOptions;
 use kdl::{KdlDocument, KdlNode};
 use minijinja::{context, Environment};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::cell::{Cell, OnceCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, trace, warn};
 
+use crate::error::{ErrorCode, TorenError};
+use crate::ports::PortRegistry;
+use crate::proxy_routes::StationRoute;
+use crate::setup_manifest::{Fingerprint, SetupManifest};
+use crate::tasks::TaskDep;
+use crate::workspace::CommitInfo;
+
 const TOREN_CONFIG_FILE: &str = "toren.kdl";
 const TOREN_CONFIG_FILE_HIDDEN: &str = ".toren.kdl";
 
+/// File in the workspace root that setup writes toren.kdl's `env { ... }`
+/// block to.
+pub const WORKSPACE_ENV_FILE: &str = ".toren-env";
+
 /// Extract an i64 from a KdlValue (kdl 6.x uses i128 internally)
 fn kdl_value_as_i64(val: &kdl::KdlValue) -> Option<i64> {
     val.as_integer().and_then(|n| i64::try_from(n).ok())
 }
 
+/// Parse a `timeout=` value: whole seconds, or a string with an `s`, `m`
+/// or `h` suffix (`"90s"`, `"5m"`, `"1h"`).
+fn parse_timeout(val: &kdl::KdlValue) -> Result<Duration> {
+    if let Some(secs) = kdl_value_as_i64(val) {
+        let secs = u64::try_from(secs).context("timeout must not be negative")?;
+        return Ok(Duration::from_secs(secs));
+    }
+    let s = val
+        .as_string()
+        .context("timeout must be seconds or a string like \"5m\"")?
+        .trim();
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid timeout '{}'", s))?;
+    let secs = match unit {
+        "" | "s" => n,
+        "m" => n * 60,
+        "h" => n * 60 * 60,
+        _ => anyhow::bail!("Invalid timeout '{}': expected a unit of s, m or h", s),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+/// Directories searched for `{% include %}`d templates, highest priority
+/// first: the repo's `.toren/templates`, then `~/.config/toren/templates`.
+pub fn template_search_path(repo_root: Option<&Path>) -> Vec<PathBuf> {
+    let mut search = Vec::new();
+    if let Some(root) = repo_root {
+        search.push(root.join(".toren").join("templates"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        search.push(home.join(".config").join("toren").join("templates"));
+    }
+    search
+}
+
+/// Load a named template from the first search directory that has it.
+/// Names are relative paths; `..` and absolute paths are rejected.
+fn load_template(search_path: &[PathBuf], name: &str) -> Option<String> {
+    let safe = !name.is_empty()
+        && !name.starts_with('/')
+        && name.split('/').all(|part| !part.is_empty() && part != "." && part != "..");
+    if !safe {
+        return None;
+    }
+    search_path
+        .iter()
+        .find_map(|dir| fs::read_to_string(dir.join(name)).ok())
+}
+
+/// Recreate `src` at `dest` as a hardlink farm: directories are created and
+/// every file is hardlinked into them, so no file data is copied. Deleting or
+/// replacing a file in one tree leaves the other alone, but writing to a
+/// linked file in place changes it in both. Symlinks are recreated.
+fn hardlink_tree(src: &Path, dest: &Path) -> std::io::Result<()> {
+    let meta = fs::symlink_metadata(src)?;
+    if meta.is_dir() {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            hardlink_tree(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if meta.file_type().is_symlink() {
+        let target = fs::read_link(src)?;
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(target, dest);
+        #[cfg(windows)]
+        return if src.is_dir() {
+            std::os::windows::fs::symlink_dir(target, dest)
+        } else {
+            std::os::windows::fs::symlink_file(target, dest)
+        };
+    } else {
+        fs::hard_link(src, dest)
+    }
+}
+
+/// Remove a file, symlink or directory tree, if present.
+fn remove_path(path: &Path) -> Result<()> {
+    let Ok(meta) = path.symlink_metadata() else {
+        return Ok(());
+    };
+    if meta.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Share `src` at `dest` on Windows. Symlinks need Developer Mode or an
+/// elevated shell, so without them a directory becomes a junction (which
+/// needs no privilege) and a file becomes a copy, which isn't kept in sync.
+#[cfg(windows)]
+fn share_windows(src: &Path, dest: &Path) -> Result<()> {
+    let linked = if src.is_dir() {
+        std::os::windows::fs::symlink_dir(src, dest)
+    } else {
+        std::os::windows::fs::symlink_file(src, dest)
+    };
+    let Err(e) = linked else {
+        return Ok(());
+    };
+    debug!("  share: symlink failed ({}), falling back", e);
+    if src.is_dir() {
+        let status = std::process::Command::new("cmd")
+            .arg("/C")
+            .arg("mklink")
+            .arg("/J")
+            .arg(dest)
+            .arg(src)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .context("Failed to run mklink")?;
+        if !status.success() {
+            anyhow::bail!(
+                "Failed to create junction {} -> {}",
+                dest.display(),
+                src.display()
+            );
+        }
+    } else {
+        warn!(
+            "  share: copying {} (enable Developer Mode to symlink shared files)",
+            src.display()
+        );
+        fs::copy(src, dest).with_context(|| {
+            format!("Failed to copy {} -> {}", src.display(), dest.display())
+        })?;
+    }
+    Ok(())
+}
+
 /// Render a template string with workspace context using minijinja.
-/// Available variables: ws.name, ws.num, ws.path, repo.root, repo.name, task.id, task.title, vars.*
+/// Available variables: ws.name, ws.num, ws.path, repo.root, repo.name, task.id, task.title,
+/// variant.index, variant.count, assignment.path, vars.*
+///
+/// `{% include %}`, `{% import %}` and `{% extends %}` load templates from
+/// [`template_search_path`] for `repo.root`.
 pub fn render_template(template: &str, ctx: &WorkspaceContext) -> Result<String> {
+    let repo_root = Some(Path::new(&ctx.repo.root)).filter(|root| !root.as_os_str().is_empty());
+    let search_path = template_search_path(repo_root);
     let mut env = Environment::new();
+    env.set_loader(move |name| Ok(load_template(&search_path, name)));
     env.add_template("inline", template)?;
     let tmpl = env.get_template("inline")?;
     let rendered = tmpl.render(context! {
         ws => ctx.ws,
         repo => ctx.repo,
         task => ctx.task,
+        variant => ctx.variant,
+        assignment => ctx.assignment,
         vars => ctx.vars,
     })?;
     Ok(rendered)
@@ -71,6 +235,8 @@ pub struct TaskInfo {
     /// Task source (e.g., "runes", "github")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+    /// Tasks this one depends on (`{% for dep in task.deps %}`)
+    pub deps: Vec<TaskDep>,
 }
 
 /// Workspace context available to templates
@@ -80,6 +246,12 @@ pub struct WorkspaceContext {
     pub repo: RepoInfo,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub task: Option<TaskInfo>,
+    /// Position within a `--count` fan-out (None for a single assignment)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<VariantInfo>,
+    /// The assignment being rendered for (None during workspace setup)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignment: Option<AssignmentInfo>,
     #[serde(default)]
     pub vars: HashMap<String, serde_json::Value>,
 }
@@ -94,12 +266,43 @@ pub struct WorkspaceInfo {
     pub path: String,
 }
 
+/// Fan-out position available to intent templates as `variant.*`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct VariantInfo {
+    /// 1-based index of this variant
+    pub index: u32,
+    /// Total number of variants
+    pub count: u32,
+}
+
+/// Assignment fields available to intent templates as `assignment.*`
+#[derive(Debug, Clone, Serialize)]
+pub struct AssignmentInfo {
+    /// Focus path relative to the workspace root ("" when unscoped)
+    pub path: String,
+}
+
+impl From<&crate::Assignment> for AssignmentInfo {
+    fn from(assignment: &crate::Assignment) -> Self {
+        Self {
+            path: assignment
+                .focus_path
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct RepoInfo {
     /// Repository root path
     pub root: String,
     /// Repository name
     pub name: String,
+    /// Latest commits on trunk, newest first (`repo.recent_commits`); only
+    /// filled in for intent and resume prompts
+    pub recent_commits: Vec<CommitInfo>,
 }
 
 // ==================== Variable Definitions ====================
@@ -110,6 +313,9 @@ pub struct RepoInfo {
 pub struct VarDef {
     pub name: String,
     pub value: String,
+    /// `var NAME=(alloc-port)START`: the rendered value is the preferred port,
+    /// and the var holds a port leased from the shared [`crate::ports::PortRegistry`]
+    pub alloc_port: bool,
 }
 
 /// Validate that an identifier matches the POSIX env/var name shape:
@@ -143,18 +349,18 @@ fn kdl_value_as_str(val: &kdl::KdlValue) -> Option<String> {
         Some(n.to_string())
     } else if let Some(f) = val.as_float() {
         Some(f.to_string())
-    } else if let Some(b) = val.as_bool() {
-        Some(b.to_string())
     } else {
-        None
+        val.as_bool().map(|b| b.to_string())
     }
 }
 
 /// Evaluate a list of variable definitions sequentially.
 /// Each var can reference previously-defined vars via the context.
+/// `alloc_port(name, preferred)` supplies the port for `(alloc-port)` vars.
 pub fn evaluate_vars(
     vars: &[VarDef],
     ctx: &WorkspaceContext,
+    alloc_port: &mut dyn FnMut(&str, u16) -> Result<u16>,
 ) -> Result<HashMap<String, serde_json::Value>> {
     let mut result = HashMap::new();
 
@@ -171,7 +377,12 @@ pub fn evaluate_vars(
         })?;
 
         // Try to parse as integer, otherwise store as string
-        let json_val = if let Ok(n) = rendered.trim().parse::<i64>() {
+        let json_val = if var.alloc_port {
+            let preferred: u16 = rendered.trim().parse().with_context(|| {
+                format!("var '{}': alloc-port start must be a u16, got \"{}\"", var.name, rendered)
+            })?;
+            serde_json::Value::Number(alloc_port(&var.name, preferred)?.into())
+        } else if let Ok(n) = rendered.trim().parse::<i64>() {
             serde_json::Value::Number(n.into())
         } else {
             serde_json::Value::String(rendered)
@@ -222,6 +433,37 @@ fn parse_env_file(path: &Path) -> Result<Vec<(String, String)>> {
     Ok(pairs)
 }
 
+/// Variables exported to a workspace's `.toren-env` by setup, for shells and
+/// agent runs in it. Empty when the file is missing.
+pub fn read_workspace_env(workspace_path: &Path) -> Result<Vec<(String, String)>> {
+    let path = workspace_path.join(WORKSPACE_ENV_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    parse_env_file(&path)
+}
+
+/// Write `.toren-env` in `KEY=VALUE` form (read back literally by
+/// [`read_workspace_env`]); with nothing to export, remove a stale one.
+fn write_workspace_env(workspace_path: &Path, vars: &[(String, String)]) -> Result<()> {
+    let path = workspace_path.join(WORKSPACE_ENV_FILE);
+    if vars.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        return Ok(());
+    }
+    let mut content = String::from("# Written by toren setup from toren.kdl's env block\n");
+    for (key, value) in vars {
+        if value.contains('\n') {
+            anyhow::bail!("env {}: exported values can't span lines", key);
+        }
+        content.push_str(&format!("{}={}\n", key, value));
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
 // ==================== Env Directives ====================
 
 /// A single `env` node. Either loads files or sets inline pairs — never both.
@@ -239,14 +481,36 @@ pub enum EnvDirective {
 /// An action to execute during setup or destroy
 #[derive(Debug, Clone)]
 pub enum Action {
-    /// Copy and render a template with workspace context
-    Template { src: String, dest: String },
+    /// Copy and render a template with workspace context. A directory `src`
+    /// renders every file under it into `dest`, keeping the layout and
+    /// skipping files that match `exclude` (a glob relative to `src`).
+    Template {
+        src: String,
+        dest: String,
+        exclude: Option<String>,
+    },
+    /// Render a patch document and deep-merge it into a JSON, YAML or TOML
+    /// file in the workspace (see [`crate::config_merge`]). `format` defaults
+    /// to the one implied by `dest`'s extension.
+    Merge {
+        src: String,
+        dest: String,
+        format: Option<String>,
+    },
     /// Copy a file or directory using CoW when available, with fallback to regular copy
     Copy {
         src: String,
         dest: String,
         from: Option<String>,
     },
+    /// Recreate a file or directory as a hardlink farm: each file is
+    /// hardlinked under newly created directories. Falls back to a copy on
+    /// filesystems (or across devices) where hardlinks fail.
+    Link {
+        src: String,
+        dest: String,
+        from: Option<String>,
+    },
     /// Create a symlink for truly shared content
     Share { src: String, from: Option<String> },
     /// Execute a shell command
@@ -256,6 +520,12 @@ pub enum Action {
         /// Run-child env directives. Applied on top of the surrounding env state for
         /// this command only — never leak to subsequent actions.
         child_env: Vec<EnvDirective>,
+        /// Files or directories (relative to the command's cwd) whose contents
+        /// decide whether re-setup must rerun the command. Without inputs the
+        /// command always runs.
+        inputs: Vec<String>,
+        /// Paths the command creates; re-setup reruns it if any is missing.
+        outputs: Vec<String>,
     },
     /// Manage a station reverse-proxy route
     Proxy {
@@ -285,11 +555,324 @@ pub enum OnFail {
 pub struct ParsedAction {
     pub action: Action,
     pub on_fail: OnFail,
+    /// Time limit for a `run` command, from its `timeout=` attribute or
+    /// the enclosing block's. None waits for it to exit.
+    pub timeout: Option<Duration>,
+}
+
+/// Timeout for `destroy` commands when neither the action nor the block
+/// sets one, so a hung command can't block abort or complete.
+pub const DEFAULT_DESTROY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How long a timed-out command gets to exit after SIGTERM before SIGKILL.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// Add the repo's `prompt` block context to a rendered prompt.
+///
+/// The context follows the prompt, separated by a blank line; either may be
+/// missing. Used for intent system prompts and resume prompts alike. `append`
+/// text is rendered with `ctx`, so it can mention the workspace's `vars.*`.
+pub fn with_repo_prompt(
+    prompt: Option<String>,
+    repo_root: &Path,
+    ctx: &WorkspaceContext,
+) -> Result<Option<String>> {
+    let context = BreqConfig::parse(repo_root)?.prompt_context(repo_root, ctx)?;
+    Ok(match (prompt, context) {
+        (Some(prompt), Some(context)) => Some(format!("{}\n\n{}", prompt, context)),
+        (prompt, context) => prompt.or(context),
+    })
 }
 
 /// Result from running setup or destroy actions
 #[derive(Debug, Default)]
-pub struct SetupResult;
+pub struct SetupResult {
+    /// Actions that failed or timed out without stopping the block
+    pub failed: Vec<ActionFailure>,
+}
+
+/// A setup or destroy action that failed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionFailure {
+    /// 1-based position in execution order
+    pub index: usize,
+    /// Short description, e.g. `run pnpm install`
+    pub label: String,
+    /// The error with its causes
+    pub error: String,
+    /// Whether its command was killed for running past its timeout
+    pub timed_out: bool,
+}
+
+impl ActionFailure {
+    /// Describe an error from running setup or destroy actions. None if it
+    /// didn't come from an action (e.g. toren.kdl failed to parse).
+    pub fn from_error(err: &anyhow::Error) -> Option<Self> {
+        let action = err.downcast_ref::<ActionFailed>()?;
+        Some(Self {
+            index: action.index,
+            label: action.label.clone(),
+            error: format!("{:#}", err),
+            timed_out: err.chain().any(|e| e.is::<CommandTimedOut>()),
+        })
+    }
+}
+
+/// Context on the error of a setup or destroy action that failed, so
+/// callers can tell which action it was.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Action {index} failed")]
+pub struct ActionFailed {
+    /// 1-based position in execution order
+    pub index: usize,
+    /// Short description, e.g. `run pnpm install`
+    pub label: String,
+}
+
+/// A `run` command exited unsuccessfully.
+#[derive(Debug, Clone, thiserror::Error)]
+pub struct CommandFailed {
+    pub command: String,
+    pub exit_code: i32,
+    /// Collected stderr, trimmed
+    pub stderr: String,
+}
+
+/// A `run` command was killed for running past its timeout.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Command timed out after {}s: {}", .timeout.as_secs(), .command)]
+pub struct CommandTimedOut {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Command failed (exit {}): {}", self.exit_code, self.command)?;
+        if !self.stderr.is_empty() {
+            write!(f, "\nstderr: {}", self.stderr)?;
+        }
+        Ok(())
+    }
+}
+
+/// Kills a `run` command, and the processes it started, once it runs past
+/// its timeout: SIGTERM first, then SIGKILL after [`KILL_GRACE`].
+struct Watchdog {
+    done: std::sync::mpsc::Sender<()>,
+    handle: std::thread::JoinHandle<bool>,
+}
+
+impl Watchdog {
+    fn start(pid: u32, timeout: Duration) -> Self {
+        use std::sync::mpsc::{channel, RecvTimeoutError};
+
+        let (done, finished) = channel::<()>();
+        let handle = std::thread::spawn(move || {
+            if finished.recv_timeout(timeout) != Err(RecvTimeoutError::Timeout) {
+                return false;
+            }
+            kill_process_group(pid, false);
+            if finished.recv_timeout(KILL_GRACE) == Err(RecvTimeoutError::Timeout) {
+                kill_process_group(pid, true);
+            }
+            true
+        });
+        Self { done, handle }
+    }
+
+    /// Stop watching the exited command; true if it was killed for timing out.
+    fn finish(self) -> bool {
+        let _ = self.done.send(());
+        self.handle.join().unwrap_or(false)
+    }
+}
+
+#[cfg(unix)]
+fn kill_process_group(pid: u32, force: bool) {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    let signal = if force { Signal::SIGKILL } else { Signal::SIGTERM };
+    if let Err(e) = killpg(Pid::from_raw(pid as i32), signal) {
+        debug!("Failed to signal process group {}: {}", pid, e);
+    }
+}
+
+#[cfg(windows)]
+fn kill_process_group(pid: u32, force: bool) {
+    let mut cmd = Command::new("taskkill");
+    cmd.args(["/PID", &pid.to_string(), "/T"]);
+    if force {
+        cmd.arg("/F");
+    }
+    if let Err(e) = cmd.stdout(Stdio::null()).stderr(Stdio::null()).status() {
+        debug!("taskkill failed for {}: {}", pid, e);
+    }
+}
+
+/// A `proxy` action with its templates rendered
+#[derive(Debug, Clone)]
+struct ProxyRoute {
+    station_name: String,
+    upstream: String,
+    port: u16,
+    tls: bool,
+}
+
+/// Values derived from toren.kdl for a specific workspace
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct WorkspaceFacts {
+    /// Evaluated `var` definitions, rendered as strings
+    pub vars: BTreeMap<String, String>,
+    /// URLs of the workspace's proxy routes
+    pub proxy_urls: Vec<String>,
+    /// The same routes as station would register them
+    pub proxy_routes: Vec<StationRoute>,
+}
+
+/// Exit code `station` uses when its proxy backend is unreachable (`EX_TEMPFAIL`).
+const STATION_EXIT_UNAVAILABLE: i32 = 75;
+
+/// Source and destination of each file a `template` action renders: the
+/// file itself, or every file under a directory source (in path order) whose
+/// path relative to it doesn't match `exclude`.
+fn template_files(src: &Path, dest: &Path, exclude: Option<&str>) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if !src.is_dir() {
+        return Ok(vec![(src.to_path_buf(), dest.to_path_buf())]);
+    }
+    let exclude = exclude
+        .map(|glob| glob::Pattern::new(glob).with_context(|| format!("Invalid template exclude glob: {}", glob)))
+        .transpose()?;
+
+    let mut files = Vec::new();
+    let mut pending = vec![src.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let rel = path.strip_prefix(src).unwrap_or(&path).to_path_buf();
+            if exclude.as_ref().is_some_and(|p| p.matches_path(&rel)) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push((path, dest.join(rel)));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Short description of an action for setup output.
+fn action_label(action: &Action) -> String {
+    match action {
+        Action::Template { dest, .. } => format!("template {}", dest),
+        Action::Merge { dest, .. } => format!("merge {}", dest),
+        Action::Copy { dest, .. } => format!("copy {}", dest),
+        Action::Link { dest, .. } => format!("link {}", dest),
+        Action::Share { src, .. } => format!("share {}", src),
+        Action::Run { command, .. } => format!("run {}", command),
+        Action::Proxy { port, .. } => format!("proxy {:?}", port),
+        Action::Env(_) => "env".to_string(),
+    }
+}
+
+/// A step of a workspace setup, reported to a [`SetupProgress`] as it happens.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum SetupEvent {
+    /// Action `index` (1-based) of `total` is starting
+    ActionStarted { index: usize, total: usize, label: String },
+    /// A line a `run` action printed
+    Output { index: usize, line: String, stderr: bool },
+    /// Action `index` finished; `skipped` when re-setup found it unchanged
+    ActionFinished {
+        index: usize,
+        total: usize,
+        label: String,
+        ok: bool,
+        skipped: bool,
+        elapsed_ms: u64,
+    },
+}
+
+/// Receives setup progress, e.g. to draw a spinner or broadcast it to
+/// clients. With one attached, setup doesn't print its own progress to
+/// stderr.
+pub trait SetupProgress: Send + Sync {
+    fn on_setup_event(&self, workspace_path: &Path, event: &SetupEvent);
+}
+
+/// Dependency attributes of an action, used only to order its block.
+#[derive(Debug, Default)]
+struct ActionDeps {
+    label: Option<String>,
+    needs: Vec<String>,
+}
+
+/// Order a block's actions so each runs after every action labeled with
+/// one of its `needs`. Otherwise declaration order is kept: the earliest
+/// declared action that is ready runs next. Several actions may share a
+/// label; `needs` waits for all of them.
+fn order_by_needs(
+    block: &str,
+    actions: Vec<ParsedAction>,
+    deps: Vec<ActionDeps>,
+) -> Result<Vec<ParsedAction>> {
+    if deps.iter().all(|d| d.needs.is_empty()) {
+        return Ok(actions);
+    }
+
+    let labels: HashSet<&str> = deps.iter().filter_map(|d| d.label.as_deref()).collect();
+    for (i, dep) in deps.iter().enumerate() {
+        if let Some(missing) = dep.needs.iter().find(|n| !labels.contains(n.as_str())) {
+            anyhow::bail!(
+                "{} action {} needs '{}', but no action in the block has label=\"{}\"",
+                block,
+                i + 1,
+                missing,
+                missing
+            );
+        }
+    }
+
+    let mut pending: Vec<usize> = (0..actions.len()).collect();
+    let mut order = Vec::with_capacity(actions.len());
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|&i| {
+            deps[i].needs.iter().all(|need| {
+                !pending
+                    .iter()
+                    .any(|&j| deps[j].label.as_deref() == Some(need.as_str()))
+            })
+        });
+        match ready {
+            Some(pos) => order.push(pending.remove(pos)),
+            None => {
+                let mut stuck: Vec<&str> = pending
+                    .iter()
+                    .filter_map(|&i| deps[i].label.as_deref())
+                    .collect();
+                stuck.sort_unstable();
+                stuck.dedup();
+                anyhow::bail!(
+                    "{} actions have a dependency cycle involving labels: {}",
+                    block,
+                    stuck.join(", ")
+                );
+            }
+        }
+    }
+
+    let mut actions: Vec<Option<ParsedAction>> = actions.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .map(|i| actions[i].take().expect("each action is ordered once"))
+        .collect())
+}
 
 // ==================== Config Parsing ====================
 
@@ -301,6 +884,30 @@ pub struct BreqConfig {
     pub vars: Vec<VarDef>,
     /// Top-level `env` directives, applied in source order before setup or destroy actions.
     pub global_env: Vec<EnvDirective>,
+    /// `env { KEY "value"; ... }` block: rendered by setup into the
+    /// workspace's `.toren-env`, which `breq go` shells and agent runs load.
+    pub export_env: Vec<(String, String)>,
+    /// Intent templates from an `intents { name "template"; ... }` block,
+    /// merged over the global `[intents]`.
+    pub intents: HashMap<String, String>,
+    /// Workspace-relative globs from an `artifacts { keep "PLAN.md" }` block,
+    /// copied out of the workspace when the assignment completes.
+    pub artifacts: Vec<String>,
+    /// Repo context from a `prompt { append "..."; file "..." }` block,
+    /// added to every generated agent prompt.
+    pub prompt: Vec<PromptPart>,
+    /// `envrc #true|#false`: keep a direnv `.envrc` in workspaces,
+    /// overriding `[ancillaries] envrc`
+    pub envrc: Option<bool>,
+}
+
+/// One directive of a `prompt` block, kept in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptPart {
+    /// `append "text"`: literal text
+    Append(String),
+    /// `file "path"`: contents of a repo-relative file
+    File(String),
 }
 
 impl BreqConfig {
@@ -341,8 +948,12 @@ impl BreqConfig {
         let content = fs::read_to_string(&config_path)
             .with_context(|| format!("Failed to read {}", config_path.display()))?;
 
-        Self::parse_kdl(&content)
-            .with_context(|| format!("Failed to parse {}", config_path.display()))
+        Self::parse_kdl(&content).with_context(|| {
+            TorenError::new(
+                ErrorCode::KdlInvalid,
+                format!("Failed to parse {}", config_path.display()),
+            )
+        })
     }
 
     fn parse_kdl(content: &str) -> Result<Self> {
@@ -352,17 +963,36 @@ impl BreqConfig {
         for node in doc.nodes() {
             match node.name().value() {
                 "setup" => {
-                    config.setup = Self::parse_block(node)?;
+                    config.setup = Self::parse_block(node, None)?;
                 }
                 "destroy" => {
-                    config.destroy = Self::parse_block(node)?;
+                    config.destroy = Self::parse_block(node, Some(DEFAULT_DESTROY_TIMEOUT))?;
                 }
                 "var" => {
                     Self::parse_var_node(node, &mut config.vars)?;
                 }
+                "env" if node.children().is_some() => {
+                    Self::parse_env_block(node, &mut config.export_env)?;
+                }
                 "env" => {
                     config.global_env.push(Self::parse_env_node(node)?);
                 }
+                "intents" => {
+                    Self::parse_intents_node(node, &mut config.intents)?;
+                }
+                "artifacts" => {
+                    Self::parse_artifacts_node(node, &mut config.artifacts)?;
+                }
+                "prompt" => {
+                    Self::parse_prompt_node(node, &mut config.prompt)?;
+                }
+                "envrc" => {
+                    let enabled = match node.entries() {
+                        [entry] if entry.name().is_none() => entry.value().as_bool(),
+                        _ => None,
+                    };
+                    config.envrc = Some(enabled.context("envrc: expected #true or #false")?);
+                }
                 other => {
                     warn!("Unknown top-level node in toren.kdl: {}", other);
                 }
@@ -395,9 +1025,155 @@ impl BreqConfig {
                 .map(|n| n.value().to_string())
                 .expect("filtered to property entries");
             validate_identifier(&name, "var")?;
+            let alloc_port = match entry.ty().map(|t| t.value()) {
+                None => false,
+                Some("alloc-port") => true,
+                Some(other) => anyhow::bail!("var '{}': unknown type annotation ({})", name, other),
+            };
             let value = kdl_value_as_str(entry.value())
                 .with_context(|| format!("var '{}' has unsupported value type", name))?;
-            vars.push(VarDef { name, value });
+            vars.push(VarDef { name, value, alloc_port });
+        }
+        Ok(())
+    }
+
+    /// Parse an `intents` block: one `name "template"` child per intent.
+    fn parse_intents_node(node: &KdlNode, intents: &mut HashMap<String, String>) -> Result<()> {
+        if !node.entries().is_empty() {
+            anyhow::bail!("intents: expected a block, e.g. `intents {{ docs \"...\" }}`");
+        }
+        let Some(children) = node.children() else {
+            return Ok(());
+        };
+        for child in children.nodes() {
+            let name = child.name().value();
+            let template = match child.entries() {
+                [entry] if entry.name().is_none() => entry.value().as_string(),
+                _ => None,
+            };
+            let Some(template) = template else {
+                anyhow::bail!("intent '{}': expected a single template string", name);
+            };
+            if child.children().is_some() {
+                anyhow::bail!("intent '{}': child blocks are not supported", name);
+            }
+            intents.insert(name.to_string(), template.to_string());
+        }
+        Ok(())
+    }
+
+    /// Parse an `artifacts` block: `keep "<glob>"` children, one or more
+    /// patterns each.
+    fn parse_artifacts_node(node: &KdlNode, artifacts: &mut Vec<String>) -> Result<()> {
+        if !node.entries().is_empty() {
+            anyhow::bail!("artifacts: expected a block, e.g. `artifacts {{ keep \"PLAN.md\" }}`");
+        }
+        let Some(children) = node.children() else {
+            return Ok(());
+        };
+        for child in children.nodes() {
+            if child.name().value() != "keep" {
+                anyhow::bail!("artifacts: unknown directive '{}' (expected `keep`)", child.name().value());
+            }
+            let patterns: Vec<&str> = child
+                .entries()
+                .iter()
+                .filter(|e| e.name().is_none())
+                .filter_map(|e| e.value().as_string())
+                .collect();
+            if patterns.is_empty() || patterns.len() != child.entries().len() {
+                anyhow::bail!("keep: expected one or more glob strings, e.g. `keep \"coverage/**\"`");
+            }
+            for pattern in patterns {
+                if Path::new(pattern).is_absolute() || pattern.split('/').any(|c| c == "..") {
+                    anyhow::bail!("keep '{}': patterns must stay inside the workspace", pattern);
+                }
+                artifacts.push(pattern.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a `prompt` block: `append "<text>"` and `file "<path>"` children.
+    fn parse_prompt_node(node: &KdlNode, parts: &mut Vec<PromptPart>) -> Result<()> {
+        if !node.entries().is_empty() {
+            anyhow::bail!("prompt: expected a block, e.g. `prompt {{ append \"...\" }}`");
+        }
+        let Some(children) = node.children() else {
+            return Ok(());
+        };
+        for child in children.nodes() {
+            let kind = child.name().value();
+            let value = match child.entries() {
+                [entry] if entry.name().is_none() => entry.value().as_string(),
+                _ => None,
+            };
+            let Some(value) = value else {
+                anyhow::bail!("prompt {}: expected a single string", kind);
+            };
+            match kind {
+                "append" => parts.push(PromptPart::Append(value.to_string())),
+                "file" => {
+                    if Path::new(value).is_absolute() || value.split('/').any(|c| c == "..") {
+                        anyhow::bail!("prompt file '{}': path must stay inside the repo", value);
+                    }
+                    parts.push(PromptPart::File(value.to_string()));
+                }
+                other => anyhow::bail!(
+                    "prompt: unknown directive '{}' (expected `append` or `file`)",
+                    other
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Repo context from the `prompt` block, with `append` text rendered
+    /// against `ctx` and `file` directives read verbatim from `repo_root`.
+    /// `None` when the block is absent or empty.
+    pub fn prompt_context(&self, repo_root: &Path, ctx: &WorkspaceContext) -> Result<Option<String>> {
+        let mut sections = Vec::new();
+        for part in &self.prompt {
+            let text = match part {
+                PromptPart::Append(text) => {
+                    render_template(text, ctx).context("Failed to render prompt append")?
+                }
+                PromptPart::File(file) => {
+                    let path = repo_root.join(file);
+                    fs::read_to_string(&path)
+                        .with_context(|| format!("prompt file {}", path.display()))?
+                }
+            };
+            if !text.trim().is_empty() {
+                sections.push(text.trim().to_string());
+            }
+        }
+        Ok((!sections.is_empty()).then(|| sections.join("\n\n")))
+    }
+
+    /// Parse a top-level `env` block: one `KEY "value"` child per exported
+    /// variable. Values are minijinja templates.
+    fn parse_env_block(node: &KdlNode, exports: &mut Vec<(String, String)>) -> Result<()> {
+        if !node.entries().is_empty() {
+            anyhow::bail!("env: a block can't also take file paths or KEY=VALUE pairs; use a separate `env` line");
+        }
+        let Some(children) = node.children() else {
+            return Ok(());
+        };
+        for child in children.nodes() {
+            let name = child.name().value();
+            validate_identifier(name, "env")?;
+            let value = match child.entries() {
+                [entry] if entry.name().is_none() => kdl_value_as_str(entry.value()),
+                _ => None,
+            };
+            let Some(value) = value else {
+                anyhow::bail!("env '{}': expected a single value, e.g. `{} \"...\"`", name, name);
+            };
+            if child.children().is_some() {
+                anyhow::bail!("env '{}': child blocks are not supported", name);
+            }
+            exports.push((name.to_string(), value));
         }
         Ok(())
     }
@@ -451,17 +1227,52 @@ impl BreqConfig {
         }
     }
 
-    fn parse_block(node: &KdlNode) -> Result<Vec<ParsedAction>> {
+    /// Parse a `setup` or `destroy` block. Its `timeout=` (or else
+    /// `default_timeout`) applies to actions without their own.
+    fn parse_block(node: &KdlNode, default_timeout: Option<Duration>) -> Result<Vec<ParsedAction>> {
         let mut actions = Vec::new();
+        let mut deps = Vec::new();
+        let block_timeout = match node.get("timeout") {
+            Some(value) => Some(
+                parse_timeout(value).with_context(|| format!("{}: invalid timeout", node.name().value()))?,
+            ),
+            None => default_timeout,
+        };
 
         if let Some(children) = node.children() {
             for child in children.nodes() {
-                let action = Self::parse_action(child)?;
+                let mut action = Self::parse_action(child)?;
+                action.timeout = action.timeout.or(block_timeout);
                 actions.push(action);
+                deps.push(Self::parse_dependency(child));
             }
         }
 
-        Ok(actions)
+        order_by_needs(node.name().value(), actions, deps)
+    }
+
+    /// `label="name"` and `needs="a,b"` of an action. `env` lines take
+    /// neither, since their properties are the variables they set.
+    fn parse_dependency(node: &KdlNode) -> ActionDeps {
+        if node.name().value() == "env" {
+            return ActionDeps::default();
+        }
+        let label = node
+            .get("label")
+            .and_then(|v| v.as_string())
+            .map(|s| s.to_string());
+        let needs = node
+            .get("needs")
+            .and_then(|v| v.as_string())
+            .map(|s| {
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        ActionDeps { label, needs }
     }
 
     fn parse_on_fail(node: &KdlNode) -> Result<OnFail> {
@@ -477,10 +1288,53 @@ impl BreqConfig {
         }
     }
 
+    /// Parse `inputs "a" "b"` / `outputs "a"`: one or more path strings.
+    fn parse_path_list(node: &KdlNode) -> Result<Vec<String>> {
+        let kind = node.name().value();
+        let paths = node
+            .entries()
+            .iter()
+            .map(|e| {
+                if e.name().is_some() {
+                    anyhow::bail!("{}: expected paths, not attributes", kind);
+                }
+                e.value()
+                    .as_string()
+                    .map(String::from)
+                    .with_context(|| format!("{}: paths must be strings", kind))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if paths.is_empty() {
+            anyhow::bail!("{} requires at least one path", kind);
+        }
+        Ok(paths)
+    }
+
     fn parse_action(node: &KdlNode) -> Result<ParsedAction> {
-        let on_fail = Self::parse_on_fail(node)?;
+        let mut on_fail = Self::parse_on_fail(node)?;
         let action = Self::parse_action_inner(node)?;
-        Ok(ParsedAction { action, on_fail })
+        // `env` properties are the variables it sets, not attributes
+        if node.name().value() == "env" {
+            return Ok(ParsedAction { action, on_fail, timeout: None });
+        }
+
+        if let Some(value) = node.get("continue-on-error") {
+            let enabled = value
+                .as_bool()
+                .context("continue-on-error: expected #true or #false")?;
+            if node.get("on_fail").is_some() {
+                anyhow::bail!("continue-on-error and on_fail can't both be set");
+            }
+            if enabled {
+                on_fail = OnFail::Warn;
+            }
+        }
+        let timeout = node
+            .get("timeout")
+            .map(parse_timeout)
+            .transpose()
+            .with_context(|| format!("{}: invalid timeout", node.name().value()))?;
+        Ok(ParsedAction { action, on_fail, timeout })
     }
 
     fn parse_action_inner(node: &KdlNode) -> Result<Action> {
@@ -496,13 +1350,37 @@ impl BreqConfig {
                     .and_then(|v| v.as_string())
                     .context("template requires dest= attribute")?
                     .to_string();
-                Ok(Action::Template { src, dest })
+                let exclude = node
+                    .get("exclude")
+                    .and_then(|v| v.as_string())
+                    .map(|s| s.to_string());
+                Ok(Action::Template { src, dest, exclude })
+            }
+            "merge" => {
+                let src = node
+                    .get("src")
+                    .and_then(|v| v.as_string())
+                    .context("merge requires src= attribute")?
+                    .to_string();
+                let dest = node
+                    .get("dest")
+                    .and_then(|v| v.as_string())
+                    .context("merge requires dest= attribute")?
+                    .to_string();
+                let format = node
+                    .get("format")
+                    .and_then(|v| v.as_string())
+                    .map(|s| s.to_string());
+                if let Some(ref format) = format {
+                    crate::config_merge::MergeFormat::parse(format)?;
+                }
+                Ok(Action::Merge { src, dest, format })
             }
-            "copy" => {
+            kind @ ("copy" | "link") => {
                 let src = node
                     .get("src")
                     .and_then(|v| v.as_string())
-                    .context("copy requires src= attribute")?
+                    .with_context(|| format!("{} requires src= attribute", kind))?
                     .to_string();
                 let dest = node
                     .get("dest")
@@ -514,7 +1392,11 @@ impl BreqConfig {
                     .map(|s| s.to_string());
                 // dest defaults to src if relative, or basename of src if absolute
                 let dest = dest.unwrap_or_else(|| default_dest(&src));
-                Ok(Action::Copy { src, dest, from })
+                if kind == "link" {
+                    Ok(Action::Link { src, dest, from })
+                } else {
+                    Ok(Action::Copy { src, dest, from })
+                }
             }
             "share" => {
                 let src = node
@@ -542,18 +1424,28 @@ impl BreqConfig {
                     .map(|s| s.to_string());
                 // run-child env directives (scoped to this command only)
                 let mut child_env = Vec::new();
+                let mut inputs = Vec::new();
+                let mut outputs = Vec::new();
                 if let Some(children) = node.children() {
                     for child in children.nodes() {
                         match child.name().value() {
                             "env" => child_env.push(Self::parse_env_node(child)?),
+                            kind @ ("inputs" | "outputs") => {
+                                let paths = Self::parse_path_list(child)?;
+                                if kind == "inputs" {
+                                    inputs.extend(paths);
+                                } else {
+                                    outputs.extend(paths);
+                                }
+                            }
                             other => anyhow::bail!(
-                                "run: unsupported child node '{}' (only `env` is allowed)",
+                                "run: unsupported child node '{}' (expected `env`, `inputs` or `outputs`)",
                                 other
                             ),
                         }
                     }
                 }
-                Ok(Action::Run { command, cwd, child_env })
+                Ok(Action::Run { command, cwd, child_env, inputs, outputs })
             }
             "env" => Ok(Action::Env(Self::parse_env_node(node)?)),
             "proxy" => {
@@ -622,6 +1514,22 @@ pub struct WorkspaceSetup {
     ancillary_num: u32,
     /// Local domain for station proxy (e.g. "lvh.me")
     local_domain: Option<String>,
+    /// Station proxy backend name (`STATION_BACKEND`); None uses station's default
+    proxy_backend: Option<String>,
+    /// Set once station reports its backend unreachable; later proxy actions are skipped
+    proxy_unavailable: Cell<bool>,
+    /// Setup manifest for differential re-setup; None runs every action
+    manifest_path: Option<PathBuf>,
+    /// `[proxy] host_template`; None uses `{ws}.{repo}.{domain}`
+    host_template: Option<String>,
+    /// Station domain, resolved on first use
+    station_domain: OnceCell<Option<String>>,
+    /// Receiver of setup progress; None prints it to stderr
+    progress: Option<Arc<dyn SetupProgress>>,
+    /// Index of the action being executed, for its output events
+    current_action: Cell<usize>,
+    /// Timeout of the action being executed
+    current_timeout: Cell<Option<Duration>>,
 }
 
 impl WorkspaceSetup {
@@ -638,20 +1546,103 @@ impl WorkspaceSetup {
             workspace_name,
             ancillary_num,
             local_domain,
+            proxy_backend: None,
+            proxy_unavailable: Cell::new(false),
+            manifest_path: None,
+            host_template: None,
+            station_domain: OnceCell::new(),
+            progress: None,
+            current_action: Cell::new(0),
+            current_timeout: Cell::new(None),
         }
     }
 
-    /// Compute the STATION_DOMAIN value: `{repo_name}.{local_domain}`
-    /// Returns None if local_domain is not configured.
+    /// Skip setup actions whose inputs are unchanged since the setup recorded
+    /// in this manifest (see [`crate::setup_manifest`]).
+    pub fn with_manifest(mut self, path: Option<PathBuf>) -> Self {
+        self.manifest_path = path;
+        self
+    }
+
+    /// Select the station proxy backend used for proxy actions.
+    pub fn with_proxy_backend(mut self, backend: Option<String>) -> Self {
+        self.proxy_backend = backend;
+        self
+    }
+
+    /// Render proxy hosts with `[proxy] host_template`.
+    pub fn with_host_template(mut self, template: Option<String>) -> Self {
+        self.host_template = template;
+        self
+    }
+
+    /// Report progress to `progress` instead of printing it.
+    pub fn with_progress(mut self, progress: Option<Arc<dyn SetupProgress>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    fn emit(&self, event: SetupEvent) {
+        if let Some(ref progress) = self.progress {
+            progress.on_setup_event(&self.workspace_path, &event);
+        }
+    }
+
+    /// Compute the STATION_DOMAIN value: `{repo_name}.{local_domain}` unless
+    /// `host_template` or a same-named repo says otherwise (see
+    /// [`crate::proxy_domains`]). Returns None if local_domain is not configured.
     fn station_domain(&self) -> Option<String> {
-        let repo_name = self
-            .repo_root
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-        self.local_domain
-            .as_ref()
-            .map(|domain| format!("{}.{}", repo_name, domain))
+        self.station_domain
+            .get_or_init(|| {
+                let local_domain = self.local_domain.as_deref()?;
+                let resolved = crate::proxy_domains::station_domain(
+                    &self.repo_root,
+                    local_domain,
+                    self.host_template.as_deref(),
+                );
+                Some(resolved.unwrap_or_else(|e| {
+                    warn!("{:#}; using the default proxy host", e);
+                    let repo_name = self
+                        .repo_root
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown");
+                    format!("{}.{}", repo_name, local_domain)
+                }))
+            })
+            .clone()
+    }
+
+    /// Claim the station domain for this repo before adding a route, so a
+    /// same-named repo set up later picks a different one.
+    fn claim_station_domain(&self) -> Result<()> {
+        let Some(ref local_domain) = self.local_domain else {
+            return Ok(());
+        };
+        let preferred = crate::proxy_domains::preferred_domain(
+            &self.repo_root,
+            local_domain,
+            self.host_template.as_deref(),
+        )?;
+        let domain = crate::proxy_domains::DomainRegistry::new().claim(&self.repo_root, &preferred)?;
+        if domain != preferred {
+            eprintln!(
+                "[setup:proxy] {} belongs to another repo; routing under {}",
+                preferred, domain
+            );
+        }
+        Ok(())
+    }
+
+    /// Point a command at this workspace's station: `STATION_DOMAIN` and
+    /// `STATION_BACKEND`, when configured.
+    fn station_env(&self, cmd: &mut Command) {
+        if let Some(domain) = self.station_domain() {
+            cmd.env("STATION_DOMAIN", &domain);
+        }
+        if let Some(ref backend) = self.proxy_backend {
+            cmd.env("STATION_BACKEND", backend);
+        }
     }
 
     /// Compute the station route name for a proxy action.
@@ -689,6 +1680,18 @@ impl WorkspaceSetup {
         Ok(names)
     }
 
+    /// Evaluate vars, leasing ports for `(alloc-port)` vars from the shared registry.
+    fn evaluate_vars(
+        &self,
+        vars: &[VarDef],
+        ctx: &WorkspaceContext,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let registry = PortRegistry::new();
+        evaluate_vars(vars, ctx, &mut |name, preferred| {
+            registry.allocate(&self.workspace_path, name, preferred)
+        })
+    }
+
     /// Build workspace context for template rendering
     fn build_context(&self) -> WorkspaceContext {
         let repo_name = self
@@ -707,8 +1710,11 @@ impl WorkspaceSetup {
             repo: RepoInfo {
                 root: self.repo_root.display().to_string(),
                 name: repo_name,
+                recent_commits: Vec::new(),
             },
             task: None,
+            variant: None,
+            assignment: None,
             vars: HashMap::new(),
         }
     }
@@ -717,9 +1723,14 @@ impl WorkspaceSetup {
     pub fn run_setup(&self) -> Result<SetupResult> {
         let config = BreqConfig::parse(&self.repo_root)?;
 
-        if config.setup.is_empty() && config.vars.is_empty() && config.global_env.is_empty() {
+        if config.setup.is_empty()
+            && config.vars.is_empty()
+            && config.global_env.is_empty()
+            && config.export_env.is_empty()
+        {
             debug!("No setup actions defined");
-            return Ok(SetupResult);
+            write_workspace_env(&self.workspace_path, &[])?;
+            return Ok(SetupResult::default());
         }
 
         info!(
@@ -732,8 +1743,7 @@ impl WorkspaceSetup {
 
         // Evaluate vars and inject into context
         if !config.vars.is_empty() {
-            let vars = evaluate_vars(&config.vars, &ctx)?;
-            ctx.vars = vars;
+            ctx.vars = self.evaluate_vars(&config.vars, &ctx)?;
         }
 
         // Apply global env directives in source order (last-wins on key collision)
@@ -742,10 +1752,30 @@ impl WorkspaceSetup {
             self.apply_env_directive(directive, &mut env_state, &ctx)?;
         }
 
-        self.execute_actions(&config.setup, &ctx, &mut env_state)?;
+        // Exported variables are also set for the setup actions themselves
+        let exports = config
+            .export_env
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), self.render_string(v, &ctx)?)))
+            .collect::<Result<Vec<_>>>()?;
+        write_workspace_env(&self.workspace_path, &exports)?;
+        env_state.extend(exports);
+
+        let failed = match self.manifest_path {
+            Some(ref path) => {
+                let mut manifest = SetupManifest::load(path.clone());
+                let failed =
+                    self.execute_actions_tracked(&config.setup, &ctx, &mut env_state, Some(&mut manifest))?;
+                if let Err(e) = manifest.save() {
+                    warn!("Failed to save setup manifest: {:#}", e);
+                }
+                failed
+            }
+            None => self.execute_actions(&config.setup, &ctx, &mut env_state)?,
+        };
 
         info!("Workspace setup complete");
-        Ok(SetupResult)
+        Ok(SetupResult { failed })
     }
 
     /// Run the destroy block, then auto-forget any proxy routes from the setup block.
@@ -760,7 +1790,7 @@ impl WorkspaceSetup {
 
         if !has_destroy && !has_proxy_actions {
             debug!("No destroy actions or proxy routes to clean up");
-            return Ok(SetupResult);
+            return Ok(SetupResult::default());
         }
 
         info!(
@@ -773,8 +1803,7 @@ impl WorkspaceSetup {
 
         // Evaluate vars for destroy too (needed for template rendering in proxy names)
         if !config.vars.is_empty() {
-            let vars = evaluate_vars(&config.vars, &ctx)?;
-            ctx.vars = vars;
+            ctx.vars = self.evaluate_vars(&config.vars, &ctx)?;
         }
 
         // Destroy is isolated from setup but inherits global env, just like setup.
@@ -783,9 +1812,11 @@ impl WorkspaceSetup {
             self.apply_env_directive(directive, &mut env_state, &ctx)?;
         }
 
-        if has_destroy {
-            self.execute_actions(&config.destroy, &ctx, &mut env_state)?;
-        }
+        let failed = if has_destroy {
+            self.execute_actions(&config.destroy, &ctx, &mut env_state)?
+        } else {
+            Vec::new()
+        };
 
         // Auto-forget proxy routes from setup block (best-effort)
         let proxy_names =
@@ -797,33 +1828,279 @@ impl WorkspaceSetup {
         }
 
         info!("Workspace destroy complete");
-        Ok(SetupResult)
+        Ok(SetupResult { failed })
+    }
+
+    /// Re-apply only the `proxy` actions from the setup block.
+    ///
+    /// Used to restore routes skipped while the proxy backend was down.
+    /// Returns the number of routes applied.
+    pub fn run_proxies(&self) -> Result<usize> {
+        let config = BreqConfig::parse(&self.repo_root)?;
+        let proxies: Vec<_> = config
+            .setup
+            .iter()
+            .filter(|pa| matches!(&pa.action, Action::Proxy { .. }))
+            .collect();
+        if proxies.is_empty() {
+            return Ok(0);
+        }
+
+        let mut ctx = self.build_context();
+        if !config.vars.is_empty() {
+            ctx.vars = self.evaluate_vars(&config.vars, &ctx)?;
+        }
+
+        let mut env_state = HashMap::new();
+        for parsed in &proxies {
+            self.execute_action(&parsed.action, &ctx, &mut env_state)?;
+            if self.proxy_unavailable.get() {
+                anyhow::bail!("Proxy backend is unreachable; routes for '{}' not restored", self.workspace_name);
+            }
+        }
+        Ok(proxies.len())
+    }
+
+    /// Evaluate the toren.kdl vars as templates see them (`vars.*`), without
+    /// running any actions. Ports are leased as setup would lease them.
+    pub fn vars(&self) -> Result<HashMap<String, serde_json::Value>> {
+        let config = BreqConfig::parse(&self.repo_root)?;
+        if config.vars.is_empty() {
+            return Ok(HashMap::new());
+        }
+        self.evaluate_vars(&config.vars, &self.build_context())
+    }
+
+    /// Evaluate vars and proxy routes without running any actions.
+    ///
+    /// Both are pure functions of the workspace identity, so this reports the
+    /// same values setup used (or will use) for this workspace.
+    pub fn describe(&self) -> Result<WorkspaceFacts> {
+        let config = BreqConfig::parse(&self.repo_root)?;
+        let mut ctx = self.build_context();
+        if !config.vars.is_empty() {
+            ctx.vars = self.evaluate_vars(&config.vars, &ctx)?;
+        }
+
+        let vars = ctx
+            .vars
+            .iter()
+            .map(|(k, v)| {
+                let value = match v {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (k.clone(), value)
+            })
+            .collect();
+
+        let mut proxy_urls = Vec::new();
+        let mut proxy_routes = Vec::new();
+        for pa in &config.setup {
+            if let Action::Proxy { port, upstream, tls, name } = &pa.action {
+                let route = self.resolve_proxy(port, upstream, *tls, name.as_deref(), &ctx)?;
+                proxy_urls.push(self.proxy_url(&route));
+                proxy_routes.push(StationRoute {
+                    host: self.proxy_host(&route),
+                    upstream: route.upstream,
+                    port: route.port,
+                    tls: route.tls,
+                });
+            }
+        }
+
+        Ok(WorkspaceFacts { vars, proxy_urls, proxy_routes })
     }
 
     /// Execute a list of actions in order, threading the env state through `Env` directives
-    /// and run-child env scopes. Respects on_fail metadata on each action.
+    /// and run-child env scopes. Respects on_fail metadata on each action, and
+    /// returns the actions that failed without stopping the block.
     fn execute_actions(
         &self,
         actions: &[ParsedAction],
         ctx: &WorkspaceContext,
         env_state: &mut HashMap<String, String>,
-    ) -> Result<()> {
+    ) -> Result<Vec<ActionFailure>> {
+        self.execute_actions_tracked(actions, ctx, env_state, None)
+    }
+
+    /// Like `execute_actions`, but with a manifest: actions it lists as
+    /// current are skipped, and completed actions are recorded in it.
+    fn execute_actions_tracked(
+        &self,
+        actions: &[ParsedAction],
+        ctx: &WorkspaceContext,
+        env_state: &mut HashMap<String, String>,
+        mut manifest: Option<&mut SetupManifest>,
+    ) -> Result<Vec<ActionFailure>> {
+        let total = actions.len();
+        let mut failed = Vec::new();
         for (i, parsed) in actions.iter().enumerate() {
+            let label = action_label(&parsed.action);
+            let fingerprint = match manifest {
+                Some(_) => self
+                    .fingerprint(&parsed.action, ctx, env_state)
+                    .unwrap_or_else(|e| {
+                        debug!("Action {}: no fingerprint ({:#}), running it", i + 1, e);
+                        None
+                    }),
+                None => None,
+            };
+            if let (Some(manifest), Some(fp)) = (manifest.as_deref_mut(), &fingerprint) {
+                if manifest.is_current(fp) && self.outputs_present(&parsed.action, ctx) {
+                    if self.progress.is_none() {
+                        eprintln!("[setup:skip] action {} unchanged: {}", i + 1, label);
+                    }
+                    self.emit(SetupEvent::ActionFinished {
+                        index: i + 1,
+                        total,
+                        label,
+                        ok: true,
+                        skipped: true,
+                        elapsed_ms: 0,
+                    });
+                    manifest.record(fp.clone());
+                    continue;
+                }
+            }
+
             trace!("Executing action {}: {:?}", i + 1, parsed.action);
+            self.current_action.set(i + 1);
+            self.current_timeout.set(parsed.timeout);
+            self.emit(SetupEvent::ActionStarted { index: i + 1, total, label: label.clone() });
+            let started = Instant::now();
             let res = self
                 .execute_action(&parsed.action, ctx, env_state)
-                .with_context(|| format!("Action {} failed", i + 1));
-
-            if let Err(e) = res {
-                match parsed.on_fail {
-                    OnFail::Exit => return Err(e),
-                    OnFail::Warn => warn!("Action {} failed (continuing): {:#}", i + 1, e),
-                    OnFail::Ignore => debug!("Action {} failed (ignored): {:#}", i + 1, e),
+                .with_context(|| ActionFailed {
+                    index: i + 1,
+                    label: label.clone(),
+                });
+            self.emit(SetupEvent::ActionFinished {
+                index: i + 1,
+                total,
+                label,
+                ok: res.is_ok(),
+                skipped: false,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            });
+
+            match res {
+                Ok(()) => {
+                    if let (Some(manifest), Some(fp)) = (manifest.as_deref_mut(), fingerprint) {
+                        manifest.record(fp);
+                    }
+                }
+                Err(e) => {
+                    match parsed.on_fail {
+                        OnFail::Exit => return Err(e),
+                        OnFail::Warn => warn!("Action {} failed (continuing): {:#}", i + 1, e),
+                        OnFail::Ignore => debug!("Action {} failed (ignored): {:#}", i + 1, e),
+                    }
+                    failed.extend(ActionFailure::from_error(&e));
                 }
             }
         }
 
-        Ok(())
+        Ok(failed)
+    }
+
+    /// Fingerprint of everything an action's result depends on, for actions
+    /// that re-setup may skip: `template`, `copy`, `link`, and `run` with
+    /// `inputs`.
+    fn fingerprint(
+        &self,
+        action: &Action,
+        ctx: &WorkspaceContext,
+        env_state: &HashMap<String, String>,
+    ) -> Result<Option<String>> {
+        let fp = match action {
+            Action::Template { src, dest, exclude } => {
+                let src = self.render_string(src, ctx)?;
+                let dest = self.render_string(dest, ctx)?;
+                let template_ctx = serde_json::to_vec(&serde_json::json!({
+                    "ws": ctx.ws,
+                    "repo": ctx.repo,
+                    "task": ctx.task,
+                    "vars": ctx.vars,
+                }))?;
+                let mut fp = Fingerprint::new("template");
+                fp.field(dest.as_bytes())
+                    .field(exclude.as_deref().unwrap_or_default().as_bytes())
+                    .field(&template_ctx)
+                    .contents(&self.repo_root.join(&src))?;
+                fp
+            }
+            Action::Copy { src, dest, from } | Action::Link { src, dest, from } => {
+                let src = self.render_string(src, ctx)?;
+                let dest = self.render_string(dest, ctx)?;
+                let src_path = match from {
+                    Some(from) => PathBuf::from(self.render_string(from, ctx)?).join(&src),
+                    None => self.repo_root.join(&src),
+                };
+                let kind = if matches!(action, Action::Link { .. }) { "link" } else { "copy" };
+                let mut fp = Fingerprint::new(kind);
+                fp.field(src_path.to_string_lossy().as_bytes())
+                    .field(dest.as_bytes())
+                    .metadata(&src_path);
+                fp
+            }
+            Action::Run { command, cwd, child_env, inputs, .. } if !inputs.is_empty() => {
+                let command = self.render_string(command, ctx)?;
+                let cwd = cwd.as_deref().map(|c| self.render_string(c, ctx)).transpose()?;
+                let work_dir = match cwd {
+                    Some(ref dir) => self.workspace_path.join(dir),
+                    None => self.workspace_path.clone(),
+                };
+                let mut run_env = env_state.clone();
+                for directive in child_env {
+                    self.apply_env_directive(directive, &mut run_env, ctx)?;
+                }
+
+                let mut fp = Fingerprint::new("run");
+                fp.field(command.as_bytes())
+                    .field(cwd.unwrap_or_default().as_bytes());
+                for (k, v) in run_env.iter().collect::<BTreeMap<_, _>>() {
+                    fp.field(k.as_bytes()).field(v.as_bytes());
+                }
+                for input in inputs {
+                    let input = self.render_string(input, ctx)?;
+                    fp.field(input.as_bytes()).contents(&work_dir.join(&input))?;
+                }
+                fp
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(fp.hex()))
+    }
+
+    /// Whether the files a skippable action produces are still in the
+    /// workspace (they're gone when resume recreated it, for instance).
+    fn outputs_present(&self, action: &Action, ctx: &WorkspaceContext) -> bool {
+        let render = |s: &str| self.render_string(s, ctx).ok();
+        match action {
+            Action::Template { src, dest, exclude } => {
+                let (Some(src), Some(dest)) = (render(src), render(dest)) else {
+                    return false;
+                };
+                template_files(&self.repo_root.join(src), &self.workspace_path.join(dest), exclude.as_deref())
+                    .is_ok_and(|files| files.iter().all(|(_, dest)| dest.exists()))
+            }
+            Action::Copy { dest, .. } | Action::Link { dest, .. } => render(dest)
+                .is_some_and(|dest| self.workspace_path.join(dest).exists()),
+            Action::Run { cwd, outputs, .. } => {
+                let work_dir = match cwd.as_deref() {
+                    Some(cwd) => match render(cwd) {
+                        Some(dir) => self.workspace_path.join(dir),
+                        None => return false,
+                    },
+                    None => self.workspace_path.clone(),
+                };
+                outputs
+                    .iter()
+                    .all(|out| render(out).is_some_and(|out| work_dir.join(out).exists()))
+            }
+            _ => false,
+        }
     }
 
     fn execute_action(
@@ -833,10 +2110,14 @@ impl WorkspaceSetup {
         env_state: &mut HashMap<String, String>,
     ) -> Result<()> {
         match action {
-            Action::Template { src, dest } => self.execute_template(src, dest, ctx),
+            Action::Template { src, dest, exclude } => {
+                self.execute_template(src, dest, exclude.as_deref(), ctx)
+            }
+            Action::Merge { src, dest, format } => self.execute_merge(src, dest, format.as_deref(), ctx),
             Action::Copy { src, dest, from } => self.execute_copy(src, dest, from.as_deref(), ctx),
+            Action::Link { src, dest, from } => self.execute_link(src, dest, from.as_deref(), ctx),
             Action::Share { src, from } => self.execute_share(src, from.as_deref(), ctx),
-            Action::Run { command, cwd, child_env } => {
+            Action::Run { command, cwd, child_env, .. } => {
                 // Build a per-command env scope: clone surrounding state and overlay child_env.
                 // Mutations here do not leak back to env_state.
                 let mut run_env = env_state.clone();
@@ -888,7 +2169,13 @@ impl WorkspaceSetup {
         Ok(())
     }
 
-    fn execute_template(&self, src: &str, dest: &str, ctx: &WorkspaceContext) -> Result<()> {
+    fn execute_template(
+        &self,
+        src: &str,
+        dest: &str,
+        exclude: Option<&str>,
+        ctx: &WorkspaceContext,
+    ) -> Result<()> {
         let src = self.render_string(src, ctx)?;
         let dest = self.render_string(dest, ctx)?;
         // Source is relative to repo root (template files are versioned)
@@ -898,30 +2185,71 @@ impl WorkspaceSetup {
 
         eprintln!("[setup:template] creating {}", dest);
 
-        let template_content = fs::read_to_string(&src_path)
-            .with_context(|| format!("Failed to read template: {}", src_path.display()))?;
+        for (src_file, dest_file) in template_files(&src_path, &dest_path, exclude)? {
+            let template_content = fs::read_to_string(&src_file).with_context(|| {
+                if src_path.is_dir() {
+                    format!(
+                        "Failed to read template: {} (use exclude= to skip binary files)",
+                        src_file.display()
+                    )
+                } else {
+                    format!("Failed to read template: {}", src_file.display())
+                }
+            })?;
 
-        let mut env = Environment::new();
-        env.add_template("template", &template_content)?;
+            let mut env = Environment::new();
+            env.add_template("template", &template_content)?;
 
-        let template = env.get_template("template")?;
-        let rendered = template.render(context! {
-            ws => ctx.ws,
-            repo => ctx.repo,
-            task => ctx.task,
-            vars => ctx.vars,
-        })?;
+            let template = env.get_template("template")?;
+            let rendered = template
+                .render(context! {
+                    ws => ctx.ws,
+                    repo => ctx.repo,
+                    task => ctx.task,
+                    vars => ctx.vars,
+                })
+                .with_context(|| format!("Failed to render template: {}", src_file.display()))?;
 
-        // Ensure parent directory exists
-        if let Some(parent) = dest_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+            // Ensure parent directory exists
+            if let Some(parent) = dest_file.parent() {
+                fs::create_dir_all(parent)?;
+            }
 
-        fs::write(&dest_path, rendered)
-            .with_context(|| format!("Failed to write: {}", dest_path.display()))?;
+            fs::write(&dest_file, rendered)
+                .with_context(|| format!("Failed to write: {}", dest_file.display()))?;
+        }
         Ok(())
     }
 
+    fn execute_merge(
+        &self,
+        src: &str,
+        dest: &str,
+        format: Option<&str>,
+        ctx: &WorkspaceContext,
+    ) -> Result<()> {
+        use crate::config_merge::{merge_file, MergeFormat};
+
+        let src = self.render_string(src, ctx)?;
+        let dest = self.render_string(dest, ctx)?;
+        // Patch is relative to repo root (versioned), dest to the workspace
+        let src_path = self.repo_root.join(&src);
+        let dest_path = self.workspace_path.join(&dest);
+        let format = match format {
+            Some(format) => MergeFormat::parse(format)?,
+            None => MergeFormat::from_path(&dest_path).with_context(|| {
+                format!("merge: can't infer the format of {}; set format=\"json|yaml|toml\"", dest)
+            })?,
+        };
+
+        eprintln!("[setup:merge] merging {} into {}", src, dest);
+
+        let patch = fs::read_to_string(&src_path)
+            .with_context(|| format!("Failed to read merge patch: {}", src_path.display()))?;
+        let patch = self.render_string(&patch, ctx)?;
+        merge_file(&dest_path, &patch, format)
+    }
+
     fn execute_copy(
         &self,
         src: &str,
@@ -959,6 +2287,48 @@ impl WorkspaceSetup {
         Ok(())
     }
 
+    fn execute_link(
+        &self,
+        src: &str,
+        dest: &str,
+        from: Option<&str>,
+        ctx: &WorkspaceContext,
+    ) -> Result<()> {
+        let src = self.render_string(src, ctx)?;
+        let dest = self.render_string(dest, ctx)?;
+        let src_path = match from {
+            Some(from) => PathBuf::from(self.render_string(from, ctx)?).join(&src),
+            None => self.repo_root.join(&src),
+        };
+        let dest_path = self.workspace_path.join(&dest);
+
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // A previous setup's farm is stale once the source changed
+        remove_path(&dest_path)?;
+
+        eprintln!("[setup:link] creating {}", dest);
+
+        let Err(e) = hardlink_tree(&src_path, &dest_path) else {
+            return Ok(());
+        };
+        warn!(
+            "  link: hardlinking {} failed ({}), copying instead",
+            src_path.display(),
+            e
+        );
+        remove_path(&dest_path)?;
+        clonetree::clone_tree(&src_path, &dest_path, &CloneOptions::new()).with_context(|| {
+            format!(
+                "Failed to copy {} to {}",
+                src_path.display(),
+                dest_path.display()
+            )
+        })?;
+        Ok(())
+    }
+
     fn execute_share(&self, src: &str, from: Option<&str>, ctx: &WorkspaceContext) -> Result<()> {
         let src = self.render_string(src, ctx)?;
         // Resolve source: from attribute (with template rendering) or repo root
@@ -992,8 +2362,12 @@ impl WorkspaceSetup {
                 "  share: removing stale entry at {}",
                 dest_path.display()
             );
-            if dest_path.is_dir() && !dest_path.symlink_metadata().map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+            let is_link = dest_path.symlink_metadata().map(|m| m.file_type().is_symlink()).unwrap_or(false);
+            if dest_path.is_dir() && !is_link {
                 fs::remove_dir_all(&dest_path)?;
+            } else if cfg!(windows) && is_link && dest_path.is_dir() {
+                // Directory symlinks and junctions are directories to Windows
+                fs::remove_dir(&dest_path)?;
             } else {
                 fs::remove_file(&dest_path)?;
             }
@@ -1012,20 +2386,7 @@ impl WorkspaceSetup {
         })?;
 
         #[cfg(windows)]
-        {
-            if src_path.is_dir() {
-                std::os::windows::fs::symlink_dir(&src_path, &dest_path)
-            } else {
-                std::os::windows::fs::symlink_file(&src_path, &dest_path)
-            }
-            .with_context(|| {
-                format!(
-                    "Failed to symlink {} -> {}",
-                    dest_path.display(),
-                    src_path.display()
-                )
-            })?;
-        }
+        share_windows(&src_path, &dest_path)?;
         Ok(())
     }
 
@@ -1052,10 +2413,12 @@ impl WorkspaceSetup {
             None => self.workspace_path.clone(),
         };
 
-        if let Some(dir) = &cwd_rendered {
-            eprintln!("[setup:run] {} (in {})", command, dir);
-        } else {
-            eprintln!("[setup:run] {}", command);
+        let quiet = self.progress.is_some();
+        if !quiet {
+            match &cwd_rendered {
+                Some(dir) => eprintln!("[setup:run] {} (in {})", command, dir),
+                None => eprintln!("[setup:run] {}", command),
+            }
         }
 
         let mut cmd = Command::new("sh");
@@ -1065,11 +2428,17 @@ impl WorkspaceSetup {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        // Inject STATION_DOMAIN env var if available
-        if let Some(domain) = self.station_domain() {
-            cmd.env("STATION_DOMAIN", &domain);
+        // A timed-out command is killed with everything it started, which
+        // needs it to lead its own process group
+        let timeout = self.current_timeout.get();
+        #[cfg(unix)]
+        if timeout.is_some() {
+            std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
         }
 
+        // Inject STATION_DOMAIN/STATION_BACKEND env vars if available
+        self.station_env(&mut cmd);
+
         // Apply env overrides on top of inherited shell env. Config wins over inherited.
         for (k, v) in env_overrides {
             cmd.env(k, v);
@@ -1078,15 +2447,22 @@ impl WorkspaceSetup {
         let mut child = cmd
             .spawn()
             .with_context(|| format!("Failed to execute: {}", command))?;
+        let watchdog = timeout.map(|timeout| Watchdog::start(child.id(), timeout));
 
         // Stream stdout (in-place last line) and stderr (collected) concurrently
+        let index = self.current_action.get();
         let stderr_handle = child.stderr.take().map(|stderr| {
+            let progress = self.progress.clone();
+            let workspace_path = self.workspace_path.clone();
             std::thread::spawn(move || {
                 let mut output = String::new();
                 let reader = BufReader::new(stderr);
                 for line in reader.lines().map_while(Result::ok) {
                     output.push_str(&line);
                     output.push('\n');
+                    if let Some(ref progress) = progress {
+                        progress.on_setup_event(&workspace_path, &SetupEvent::Output { index, line, stderr: true });
+                    }
                 }
                 output
             })
@@ -1096,6 +2472,10 @@ impl WorkspaceSetup {
         if let Some(stdout) = child.stdout.take() {
             let reader = BufReader::new(stdout);
             for line in reader.lines().map_while(Result::ok) {
+                if quiet {
+                    self.emit(SetupEvent::Output { index, line, stderr: false });
+                    continue;
+                }
                 // Clear current line, write new last line
                 eprint!("\r\x1b[2K  {}", line);
                 last_line = line;
@@ -1112,37 +2492,47 @@ impl WorkspaceSetup {
         }
 
         let status = child.wait().with_context(|| format!("Failed to wait for: {}", command))?;
+        let timed_out = watchdog.is_some_and(Watchdog::finish);
         let code = status.code().unwrap_or(-1);
-        eprintln!("[exit {}]", code);
+        if !quiet {
+            match timeout.filter(|_| timed_out) {
+                Some(timeout) => eprintln!("[timed out after {}s]", timeout.as_secs()),
+                None => eprintln!("[exit {}]", code),
+            }
+        }
 
+        if let Some(timeout) = timeout.filter(|_| timed_out) {
+            return Err(CommandTimedOut { command, timeout }.into());
+        }
         if !status.success() {
-            let mut msg = format!("Command failed (exit {}): {}", code, command);
-            if !stderr_output.is_empty() {
-                msg.push_str("\nstderr: ");
-                msg.push_str(stderr_output.trim());
+            return Err(CommandFailed {
+                command,
+                exit_code: code,
+                stderr: stderr_output.trim().to_string(),
             }
-            anyhow::bail!(msg);
+            .into());
         }
 
         Ok(())
     }
 
-    fn execute_proxy(
+    /// Resolve a `proxy` action's templates into a concrete route.
+    fn resolve_proxy(
         &self,
         port: &PortSpec,
         upstream: &str,
         tls: Option<bool>,
         name: Option<&str>,
         ctx: &WorkspaceContext,
-    ) -> Result<()> {
-        let upstream_val = self.render_string(upstream, ctx)?;
+    ) -> Result<ProxyRoute> {
+        let upstream = self.render_string(upstream, ctx)?;
         let rendered_name = name
             .map(|n| self.render_string(n, ctx))
             .transpose()?;
         let station_name = self.station_name(rendered_name.as_deref());
 
         // Resolve port: Numeric uses directly, Named renders template then resolves
-        let (resolved_port, implicit_tls) = match port {
+        let (port, implicit_tls) = match port {
             PortSpec::Numeric(n) => (*n, false),
             PortSpec::Named(s) => {
                 let rendered = self.render_string(s, ctx)?;
@@ -1162,35 +2552,83 @@ impl WorkspaceSetup {
             }
         };
 
-        // Explicit tls= overrides implicit protocol tls
-        let use_tls = tls.unwrap_or(implicit_tls);
+        Ok(ProxyRoute {
+            station_name,
+            upstream,
+            port,
+            // Explicit tls= overrides implicit protocol tls
+            tls: tls.unwrap_or(implicit_tls),
+        })
+    }
+
+    /// URL a proxy route listens on, e.g. `https://web.one.toren.lvh.me`.
+    fn proxy_url(&self, route: &ProxyRoute) -> String {
+        let scheme = if route.tls { "https" } else { "http" };
+        let listen = if self.station_domain().is_some() {
+            let port_suffix = match (route.tls, route.port) {
+                (false, 80) | (true, 443) => String::new(),
+                _ => format!(":{}", route.port),
+            };
+            format!("{}{}", self.proxy_host(route), port_suffix)
+        } else {
+            format!("{}:{}", route.station_name, route.port)
+        };
+        format!("{}://{}", scheme, listen)
+    }
+
+    /// Hostname a proxy route matches, e.g. `web.one.toren.lvh.me`.
+    fn proxy_host(&self, route: &ProxyRoute) -> String {
+        match self.station_domain() {
+            Some(domain) => format!("{}.{}", route.station_name, domain),
+            None => route.station_name.clone(),
+        }
+    }
+
+    fn execute_proxy(
+        &self,
+        port: &PortSpec,
+        upstream: &str,
+        tls: Option<bool>,
+        name: Option<&str>,
+        ctx: &WorkspaceContext,
+    ) -> Result<()> {
+        let route = self.resolve_proxy(port, upstream, tls, name, ctx)?;
+        let station_name = &route.station_name;
+
+        if self.proxy_unavailable.get() {
+            debug!("Skipping proxy for '{}': backend unreachable", station_name);
+            return Ok(());
+        }
+        self.claim_station_domain()?;
 
         let mut cmd = Command::new("station");
         cmd.arg("proxy")
-            .arg(&station_name)
+            .arg(station_name)
             .arg("-u")
-            .arg(&upstream_val)
+            .arg(&route.upstream)
             .arg("-p")
-            .arg(resolved_port.to_string());
+            .arg(route.port.to_string());
 
-        if use_tls {
+        if route.tls {
             cmd.arg("--tls");
         }
 
-        if let Some(domain) = self.station_domain() {
-            cmd.env("STATION_DOMAIN", &domain);
-        }
+        self.station_env(&mut cmd);
 
         let output = cmd
             .output()
             .with_context(|| format!("Failed to run station proxy for '{}'", station_name))?;
 
+        if self.check_proxy_unavailable(&output.status) {
+            return Ok(());
+        }
+
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             // Build manual command hint
             let mut hint_args = format!("station proxy {} -u {} -p {}",
-                station_name, upstream_val, resolved_port);
-            if use_tls {
+                station_name, route.upstream, route.port);
+            if route.tls {
                 hint_args.push_str(" --tls");
             }
             anyhow::bail!(
@@ -1200,36 +2638,31 @@ impl WorkspaceSetup {
         }
 
         // Show the domain mapping on success
-        let scheme = if use_tls { "https" } else { "http" };
-        let listen_display = if let Some(domain) = self.station_domain() {
-            let port_suffix = match (use_tls, resolved_port) {
-                (false, 80) | (true, 443) => String::new(),
-                _ => format!(":{}", resolved_port),
-            };
-            format!("{}.{}{}",
-                station_name, domain, port_suffix)
-        } else {
-            format!("{}:{}", station_name, resolved_port)
-        };
-        eprintln!("[setup:proxy] {}://{} => {}", scheme, listen_display, upstream_val);
+        eprintln!("[setup:proxy] {} => {}", self.proxy_url(&route), route.upstream);
 
         Ok(())
     }
 
     fn execute_station_forget(&self, station_name: &str) -> Result<()> {
+        if self.proxy_unavailable.get() {
+            debug!("Skipping station forget for '{}': backend unreachable", station_name);
+            return Ok(());
+        }
         info!("  station forget: {}", station_name);
 
         let mut cmd = Command::new("station");
         cmd.arg("forget").arg(station_name);
 
-        if let Some(domain) = self.station_domain() {
-            cmd.env("STATION_DOMAIN", &domain);
-        }
+        self.station_env(&mut cmd);
 
         let output = cmd
             .output()
             .with_context(|| format!("Failed to run station forget for '{}'", station_name))?;
 
+        if self.check_proxy_unavailable(&output.status) {
+            return Ok(());
+        }
+
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             anyhow::bail!("station forget failed for '{}': {}", station_name, stderr.trim());
@@ -1237,6 +2670,22 @@ impl WorkspaceSetup {
 
         Ok(())
     }
+
+    /// Detect station's "backend unreachable" exit. Proxy routes are a
+    /// convenience, so an outage skips them (with a single warning) rather
+    /// than failing workspace setup or teardown.
+    fn check_proxy_unavailable(&self, status: &std::process::ExitStatus) -> bool {
+        if status.code() != Some(STATION_EXIT_UNAVAILABLE) {
+            return false;
+        }
+        if !self.proxy_unavailable.replace(true) {
+            eprintln!(
+                "[setup:proxy] Proxy backend is unreachable; skipping proxy routes. \
+                 Run `breq proxy reconcile` once it's back."
+            );
+        }
+        true
+    }
 }
 
 #[cfg(test)]
@@ -1262,7 +2711,7 @@ destroy {
         assert_eq!(config.destroy.len(), 1);
 
         match &config.setup[0].action {
-            Action::Template { src, dest } => {
+            Action::Template { src, dest, .. } => {
                 assert_eq!(src, ".env.breq");
                 assert_eq!(dest, ".env");
             }
@@ -1270,7 +2719,7 @@ destroy {
         }
 
         match &config.setup[1].action {
-            Action::Run { command, cwd, child_env } => {
+            Action::Run { command, cwd, child_env, .. } => {
                 assert_eq!(command, "pnpm install");
                 assert!(cwd.is_none());
                 assert!(child_env.is_empty());
@@ -1367,6 +2816,39 @@ setup {
         }
     }
 
+    #[test]
+    fn test_link_builds_hardlink_farm() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let ws = dir.path().join("ws");
+        fs::create_dir_all(repo.join("node_modules/pkg/lib")).unwrap();
+        fs::create_dir_all(&ws).unwrap();
+        fs::write(repo.join("toren.kdl"), "setup {\n    link src=\"node_modules\"\n}\n").unwrap();
+        fs::write(repo.join("node_modules/pkg/lib/index.js"), "module.exports = 1").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("pkg/lib/index.js", repo.join("node_modules/main.js")).unwrap();
+
+        let setup = WorkspaceSetup::new(repo.clone(), ws.clone(), "one".to_string(), 1, None);
+        setup.run_setup().unwrap();
+        let linked = ws.join("node_modules/pkg/lib/index.js");
+        assert_eq!(fs::read_to_string(&linked).unwrap(), "module.exports = 1");
+        assert!(!ws.join("node_modules").symlink_metadata().unwrap().file_type().is_symlink());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let original = repo.join("node_modules/pkg/lib/index.js");
+            assert_eq!(fs::metadata(&linked).unwrap().ino(), fs::metadata(&original).unwrap().ino());
+            assert_eq!(
+                fs::read_link(ws.join("node_modules/main.js")).unwrap(),
+                PathBuf::from("pkg/lib/index.js")
+            );
+        }
+
+        // Removing a file in the workspace leaves the repo's copy alone
+        fs::remove_file(&linked).unwrap();
+        assert!(repo.join("node_modules/pkg/lib/index.js").exists());
+    }
+
     #[test]
     fn test_parse_kdl_copy_absolute_src() {
         let content = r#"
@@ -1410,7 +2892,7 @@ setup {
 
         assert_eq!(config.setup.len(), 2);
         match &config.setup[0].action {
-            Action::Run { command, cwd, child_env } => {
+            Action::Run { command, cwd, child_env, .. } => {
                 assert_eq!(command, "pnpm install");
                 assert_eq!(cwd.as_deref(), Some("web"));
                 assert!(child_env.is_empty());
@@ -1418,7 +2900,7 @@ setup {
             _ => panic!("Expected Run action"),
         }
         match &config.setup[1].action {
-            Action::Run { command, cwd, child_env } => {
+            Action::Run { command, cwd, child_env, .. } => {
                 assert_eq!(command, "cargo build");
                 assert!(cwd.is_none());
                 assert!(child_env.is_empty());
@@ -1483,6 +2965,92 @@ setup { }
         assert_eq!(config.vars[1].value, "world");
     }
 
+    #[test]
+    fn test_parse_intents_block() {
+        let content = r#"
+intents {
+    implement "Implement {{ task.id }} following docs/CONTRIBUTING.md"
+    docs "Document {{ task.title }}"
+}
+"#;
+        let config = BreqConfig::parse_kdl(content).unwrap();
+        assert_eq!(config.intents.len(), 2);
+        assert_eq!(config.intents["docs"], "Document {{ task.title }}");
+
+        let err = BreqConfig::parse_kdl("intents { docs }").unwrap_err();
+        assert!(err.to_string().contains("single template string"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_parse_artifacts_block() {
+        let content = r#"
+artifacts {
+    keep "PLAN.md"
+    keep "coverage/**" "target/report.html"
+}
+"#;
+        let config = BreqConfig::parse_kdl(content).unwrap();
+        assert_eq!(config.artifacts, vec!["PLAN.md", "coverage/**", "target/report.html"]);
+
+        let err = BreqConfig::parse_kdl(r#"artifacts { keep "../secrets" }"#).unwrap_err();
+        assert!(err.to_string().contains("inside the workspace"), "unexpected error: {}", err);
+        let err = BreqConfig::parse_kdl(r#"artifacts { copy "PLAN.md" }"#).unwrap_err();
+        assert!(err.to_string().contains("expected `keep`"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_prompt_block_appends_repo_context() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("AGENTS.md"), "Run `cargo nextest run`.\n").unwrap();
+        std::fs::write(
+            dir.path().join("toren.kdl"),
+            r#"
+prompt {
+    append "Build with `just build`."
+    append "The dev server is at {{ vars.web_url }}."
+    file "AGENTS.md"
+}
+"#,
+        )
+        .unwrap();
+        let config = BreqConfig::parse(dir.path()).unwrap();
+        assert_eq!(
+            config.prompt,
+            vec![
+                PromptPart::Append("Build with `just build`.".to_string()),
+                PromptPart::Append("The dev server is at {{ vars.web_url }}.".to_string()),
+                PromptPart::File("AGENTS.md".to_string()),
+            ]
+        );
+
+        let mut ctx = ctx_for_test();
+        ctx.vars.insert("web_url".to_string(), serde_json::json!("http://one.repo.lvh.me"));
+        let merged = with_repo_prompt(Some("Plan it.".to_string()), dir.path(), &ctx).unwrap();
+        assert_eq!(
+            merged.as_deref(),
+            Some("Plan it.\n\nBuild with `just build`.\n\nThe dev server is at http://one.repo.lvh.me.\n\nRun `cargo nextest run`.")
+        );
+        let alone = with_repo_prompt(None, dir.path(), &ctx).unwrap().unwrap();
+        assert!(alone.starts_with("Build with"));
+        let none = with_repo_prompt(None, &dir.path().join("missing"), &ctx).unwrap();
+        assert!(none.is_none());
+
+        let err = BreqConfig::parse_kdl(r#"prompt { file "../x.md" }"#).unwrap_err();
+        assert!(err.to_string().contains("inside the repo"), "unexpected error: {}", err);
+        let err = BreqConfig::parse_kdl(r#"prompt { include "x.md" }"#).unwrap_err();
+        assert!(err.to_string().contains("expected `append` or `file`"), "unexpected error: {}", err);
+
+        assert_eq!(BreqConfig::parse_kdl("envrc #true").unwrap().envrc, Some(true));
+        let err = BreqConfig::parse_kdl(r#"envrc "yes""#).unwrap_err();
+        assert!(err.to_string().contains("#true or #false"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_parse_prompt_unknown_directive() {
+        let err = BreqConfig::parse_kdl(r#"prompt { include "x.md" }"#).unwrap_err();
+        assert!(err.to_string().contains("expected `append` or `file`"), "unexpected error: {}", err);
+    }
+
     #[test]
     fn test_parse_var_rejects_positional() {
         let content = r#"var "foo""#;
@@ -1526,10 +3094,12 @@ setup { }
             VarDef {
                 name: "base_port".to_string(),
                 value: "5170".to_string(),
+                alloc_port: false,
             },
             VarDef {
                 name: "port".to_string(),
                 value: "{{ vars.base_port + ws.num }}".to_string(),
+                alloc_port: false,
             },
         ];
 
@@ -1542,16 +3112,47 @@ setup { }
             repo: RepoInfo {
                 root: "/tmp/repo".to_string(),
                 name: "myrepo".to_string(),
+                recent_commits: Vec::new(),
             },
             task: None,
+            variant: None,
+            assignment: None,
             vars: HashMap::new(),
         };
 
-        let result = evaluate_vars(&vars, &ctx).unwrap();
+        let result = evaluate_vars(&vars, &ctx, &mut |_, _| unreachable!()).unwrap();
         assert_eq!(result.get("base_port"), Some(&serde_json::json!(5170)));
         assert_eq!(result.get("port"), Some(&serde_json::json!(5173)));
     }
 
+    #[test]
+    fn test_parse_and_evaluate_alloc_port_var() {
+        let content = r#"
+var base=8000
+var web_port=(alloc-port)"{{ vars.base }}"
+var url="http://localhost:{{ vars.web_port }}"
+"#;
+        let config = BreqConfig::parse_kdl(content).unwrap();
+        assert!(!config.vars[0].alloc_port);
+        assert!(config.vars[1].alloc_port);
+
+        let mut requested = Vec::new();
+        let result = evaluate_vars(&config.vars, &ctx_for_test(), &mut |name, preferred| {
+            requested.push((name.to_string(), preferred));
+            Ok(8003)
+        })
+        .unwrap();
+        assert_eq!(requested, vec![("web_port".to_string(), 8000)]);
+        assert_eq!(result["web_port"], serde_json::json!(8003));
+        assert_eq!(result["url"], serde_json::json!("http://localhost:8003"));
+    }
+
+    #[test]
+    fn test_parse_var_rejects_unknown_type_annotation() {
+        let err = BreqConfig::parse_kdl("var port=(port)8000").unwrap_err();
+        assert!(err.to_string().contains("unknown type annotation"), "{}", err);
+    }
+
     #[test]
     fn test_render_template_with_vars() {
         let ctx = WorkspaceContext {
@@ -1563,8 +3164,11 @@ setup { }
             repo: RepoInfo {
                 root: "/tmp/repo".to_string(),
                 name: "myrepo".to_string(),
+                recent_commits: Vec::new(),
             },
             task: None,
+            variant: None,
+            assignment: None,
             vars: {
                 let mut m = HashMap::new();
                 m.insert("upstream_url".to_string(), serde_json::json!("http://localhost:5173"));
@@ -1582,6 +3186,73 @@ setup { }
         assert_eq!(render_template("{{vars.port}}", &ctx).unwrap(), "5173");
     }
 
+    #[test]
+    fn test_render_template_includes_repo_partials() {
+        let repo = tempfile::tempdir().unwrap();
+        let templates = repo.path().join(".toren/templates");
+        std::fs::create_dir_all(templates.join("partials")).unwrap();
+        std::fs::write(templates.join("partials/guardrails.j2"), "Stay in {{ repo.name }}.").unwrap();
+
+        let mut ctx = ctx_for_test();
+        ctx.repo.root = repo.path().display().to_string();
+        let template = r#"Fix it. {% include "partials/guardrails.j2" %}"#;
+        assert_eq!(render_template(template, &ctx).unwrap(), "Fix it. Stay in repo.");
+
+        assert!(render_template(r#"{% include "missing.j2" %}"#, &ctx).is_err());
+        let search = [templates];
+        assert!(load_template(&search, "../templates/partials/guardrails.j2").is_none());
+        assert!(load_template(&search, "partials/guardrails.j2").is_some());
+    }
+
+    #[test]
+    fn test_render_template_with_variant() {
+        let mut ctx = ctx_for_test();
+        let template = "{% if variant %}take {{ variant.index }} of {{ variant.count }}{% else %}solo{% endif %}";
+        assert_eq!(render_template(template, &ctx).unwrap(), "solo");
+
+        ctx.variant = Some(VariantInfo { index: 2, count: 3 });
+        assert_eq!(render_template(template, &ctx).unwrap(), "take 2 of 3");
+    }
+
+    #[test]
+    fn test_render_template_with_assignment_path() {
+        let mut ctx = ctx_for_test();
+        let template = "{% if assignment.path %}Focus on {{ assignment.path }}{% endif %}";
+        assert_eq!(render_template(template, &ctx).unwrap(), "");
+
+        ctx.assignment = Some(AssignmentInfo {
+            path: "crates/foo".to_string(),
+        });
+        assert_eq!(render_template(template, &ctx).unwrap(), "Focus on crates/foo");
+    }
+
+    #[test]
+    fn test_render_template_with_task_deps_and_recent_commits() {
+        let mut ctx = ctx_for_test();
+        ctx.task = Some(TaskInfo {
+            id: "bd-3".to_string(),
+            title: "Wire it up".to_string(),
+            description: Some("Connect the parts".to_string()),
+            url: None,
+            source: None,
+            deps: vec![TaskDep {
+                id: "bd-1".to_string(),
+                title: Some("Build the parts".to_string()),
+                status: Some("closed".to_string()),
+            }],
+        });
+        ctx.repo.recent_commits = vec![CommitInfo {
+            id: "abc123".to_string(),
+            summary: "Add parts".to_string(),
+        }];
+        let template = "{{ task.description }}.{% for dep in task.deps %} After {{ dep.id }} ({{ dep.status }}).{% endfor %}\
+{% for c in repo.recent_commits %} {{ c.id }}: {{ c.summary }}{% endfor %}";
+        assert_eq!(
+            render_template(template, &ctx).unwrap(),
+            "Connect the parts. After bd-1 (closed). abc123: Add parts"
+        );
+    }
+
     #[test]
     fn test_parse_on_fail_default() {
         let content = r#"
@@ -1658,6 +3329,85 @@ destroy {
         assert_eq!(config.destroy[0].on_fail, OnFail::Ignore);
     }
 
+    #[test]
+    fn test_parse_timeout_and_continue_on_error() {
+        let content = r#"
+setup timeout="5m" {
+    run "pnpm install" timeout=90
+    run "cargo build"
+    run "flaky" continue-on-error=#true timeout="1h"
+}
+destroy {
+    run "docker compose down" timeout="30s"
+    run "cleanup"
+}
+"#;
+        let config = BreqConfig::parse_kdl(content).unwrap();
+        let timeouts: Vec<_> = config.setup.iter().map(|pa| pa.timeout).collect();
+        assert_eq!(
+            timeouts,
+            [90, 300, 3600].map(|s| Some(Duration::from_secs(s)))
+        );
+        assert_eq!(config.setup[1].on_fail, OnFail::Exit);
+        assert_eq!(config.setup[2].on_fail, OnFail::Warn);
+        assert_eq!(config.destroy[0].timeout, Some(Duration::from_secs(30)));
+        assert_eq!(config.destroy[1].timeout, Some(DEFAULT_DESTROY_TIMEOUT));
+
+        let err = BreqConfig::parse_kdl(r#"setup { run "x" timeout="soon" }"#).unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid timeout 'soon'"), "{:#}", err);
+        let err = BreqConfig::parse_kdl(r#"setup { run "x" continue-on-error=#true on_fail="ignore" }"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("can't both be set"), "{}", err);
+    }
+
+    fn run_commands(actions: &[ParsedAction]) -> Vec<&str> {
+        actions
+            .iter()
+            .filter_map(|pa| match &pa.action {
+                Action::Run { command, .. } => Some(command.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_needs_orders_actions() {
+        let content = r#"
+destroy {
+    run "rm -rf data" needs="db"
+    run "dropdb app" label="db" needs="services"
+    run "echo unrelated"
+    run "stop web" label="services"
+    run "stop worker" label="services"
+}
+"#;
+        let config = BreqConfig::parse_kdl(content).unwrap();
+        assert_eq!(
+            run_commands(&config.destroy),
+            ["echo unrelated", "stop web", "stop worker", "dropdb app", "rm -rf data"]
+        );
+    }
+
+    #[test]
+    fn test_parse_needs_rejects_cycles_and_unknown_labels() {
+        let cycle = r#"
+setup {
+    run "a" label="a" needs="b"
+    run "b" label="b" needs="a"
+}
+"#;
+        let err = BreqConfig::parse_kdl(cycle).unwrap_err().to_string();
+        assert!(err.contains("cycle") && err.contains("a, b"), "{}", err);
+
+        let unknown = r#"
+setup {
+    run "a" needs="missing"
+}
+"#;
+        let err = BreqConfig::parse_kdl(unknown).unwrap_err().to_string();
+        assert!(err.contains("missing"), "{}", err);
+    }
+
     fn test_setup() -> WorkspaceSetup {
         let dir = std::env::temp_dir().join("toren-test-ws");
         let _ = fs::create_dir_all(&dir);
@@ -1670,8 +3420,11 @@ destroy {
                 command: command.to_string(),
                 cwd: None,
                 child_env: Vec::new(),
+                inputs: Vec::new(),
+                outputs: Vec::new(),
             },
             on_fail,
+            timeout: None,
         }
     }
 
@@ -1718,6 +3471,78 @@ destroy {
         assert!(result.is_err(), "on_fail=exit should abort");
     }
 
+    #[test]
+    fn test_failed_run_carries_action_and_command() {
+        let setup = test_setup();
+        let ctx = setup.build_context();
+        let mut env = HashMap::new();
+        let actions = vec![
+            run_action("true", OnFail::Exit),
+            run_action("echo boom >&2; exit 3", OnFail::Exit),
+        ];
+        let err = setup.execute_actions(&actions, &ctx, &mut env).unwrap_err();
+        let action = err.downcast_ref::<ActionFailed>().unwrap();
+        assert_eq!(action.index, 2);
+        assert_eq!(action.label, "run echo boom >&2; exit 3");
+        let command = err.downcast_ref::<CommandFailed>().unwrap();
+        assert_eq!((command.exit_code, command.stderr.as_str()), (3, "boom"));
+        assert_eq!(
+            format!("{:#}", err),
+            "Action 2 failed: Command failed (exit 3): echo boom >&2; exit 3\nstderr: boom"
+        );
+    }
+
+    #[test]
+    fn test_timed_out_run_is_killed_and_reported() {
+        let setup = test_setup();
+        let ctx = setup.build_context();
+        let mut env = HashMap::new();
+        let mut hung = run_action("sleep 30 | cat", OnFail::Warn);
+        hung.timeout = Some(Duration::from_millis(200));
+        let actions = vec![hung.clone(), run_action("true", OnFail::Exit)];
+
+        let started = Instant::now();
+        let failed = setup.execute_actions(&actions, &ctx, &mut env).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(failed.len(), 1);
+        assert_eq!((failed[0].index, failed[0].timed_out), (1, true));
+        assert_eq!(failed[0].label, "run sleep 30 | cat");
+
+        hung.on_fail = OnFail::Exit;
+        let err = setup.execute_actions(&[hung], &ctx, &mut env).unwrap_err();
+        assert!(err.downcast_ref::<CommandTimedOut>().is_some(), "{:#}", err);
+    }
+
+    #[test]
+    fn test_progress_receives_action_events_and_output() {
+        struct Collect(std::sync::Mutex<Vec<SetupEvent>>);
+        impl SetupProgress for Collect {
+            fn on_setup_event(&self, _: &Path, event: &SetupEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+        let collect = Arc::new(Collect(Default::default()));
+        let setup = test_setup().with_progress(Some(collect.clone()));
+        let ctx = setup.build_context();
+        let mut env = HashMap::new();
+        let actions = vec![run_action("echo hello", OnFail::Exit), run_action("exit 2", OnFail::Exit)];
+        assert!(setup.execute_actions(&actions, &ctx, &mut env).is_err());
+
+        let events = collect.0.lock().unwrap();
+        let stages: Vec<String> = events
+            .iter()
+            .map(|e| match e {
+                SetupEvent::ActionStarted { index, total, .. } => format!("start {}/{}", index, total),
+                SetupEvent::Output { index, line, .. } => format!("{}: {}", index, line),
+                SetupEvent::ActionFinished { index, ok, .. } => format!("finish {} {}", index, ok),
+            })
+            .collect();
+        assert_eq!(
+            stages,
+            ["start 1/2", "1: hello", "finish 1 true", "start 2/2", "finish 2 false"]
+        );
+    }
+
     // ─── Proxy parsing tests ───────────────────────────────────────────
 
     #[test]
@@ -1865,6 +3690,44 @@ setup {
         assert_eq!(setup.station_domain(), Some("myrepo.lvh.me".to_string()));
     }
 
+    #[test]
+    fn test_describe_renders_vars_and_proxy_urls() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::write(
+            repo.path().join("toren.kdl"),
+            r#"
+var port="{{ 5170 + ws.num }}"
+setup {
+    proxy "https" upstream="{{ vars.port }}" name="web"
+    proxy 8080 upstream=3000
+}
+"#,
+        )
+        .unwrap();
+        let setup = WorkspaceSetup::new(
+            repo.path().to_path_buf(),
+            PathBuf::from("/ws/two"),
+            "two".to_string(),
+            2,
+            Some("lvh.me".to_string()),
+        );
+
+        let facts = setup.describe().unwrap();
+        assert_eq!(facts.vars["port"], "5172");
+        let domain = setup.station_domain().unwrap();
+        assert_eq!(
+            facts.proxy_urls,
+            vec![
+                format!("https://web.two.{}", domain),
+                format!("http://two.{}:8080", domain),
+            ]
+        );
+        assert_eq!(facts.proxy_routes[0].host, format!("web.two.{}", domain));
+        assert_eq!(facts.proxy_routes[0].upstream, "5172");
+        assert!(facts.proxy_routes[0].tls);
+        assert_eq!(facts.proxy_routes[1].port, 8080);
+    }
+
     #[test]
     fn test_station_domain_none() {
         let setup = WorkspaceSetup::new(
@@ -1901,8 +3764,11 @@ setup {
             repo: RepoInfo {
                 root: "/tmp/repo".to_string(),
                 name: "myrepo".to_string(),
+                recent_commits: Vec::new(),
             },
             task: None,
+            variant: None,
+            assignment: None,
             vars: HashMap::new(),
         };
         let actions = vec![
@@ -1914,6 +3780,7 @@ setup {
                     name: None,
                 },
                 on_fail: OnFail::Exit,
+                timeout: None,
             },
             ParsedAction {
                 action: Action::Proxy {
@@ -1923,14 +3790,18 @@ setup {
                     name: Some("api".to_string()),
                 },
                 on_fail: OnFail::Exit,
+                timeout: None,
             },
             ParsedAction {
                 action: Action::Run {
                     command: "echo hi".to_string(),
                     cwd: None,
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
                     child_env: Vec::new(),
                 },
                 on_fail: OnFail::Exit,
+                timeout: None,
             },
         ];
         let names = WorkspaceSetup::collect_proxy_station_names(&actions, "one", &ctx).unwrap();
@@ -1991,17 +3862,52 @@ setup {
 
     #[test]
     fn test_parse_env_block_form_rejected() {
-        // The abandoned block form should get a precise "child blocks not supported"
+        // Inside setup the block form should get a precise "child blocks not supported"
         // error, not the generic "requires either file paths or pairs" message.
-        let content = r#"env { PORT 3000; NODE_ENV "dev" }"#;
+        let content = r#"setup { env { PORT 3000; NODE_ENV "dev" } }"#;
         let err = BreqConfig::parse_kdl(content).unwrap_err();
         assert!(
-            err.to_string().contains("child blocks"),
-            "unexpected error: {}",
+            format!("{:#}", err).contains("child blocks"),
+            "unexpected error: {:#}",
             err
         );
     }
 
+    #[test]
+    fn test_env_block_exports_to_workspace_env_file() {
+        let content = r#"env { DATABASE_URL "postgres://localhost/app_{{ ws.num }}"; PORT 3000 }"#;
+        let config = BreqConfig::parse_kdl(content).unwrap();
+        assert!(config.global_env.is_empty());
+        assert_eq!(config.export_env.len(), 2);
+        assert!(BreqConfig::parse_kdl(r#"env ".env" { PORT 1 }"#).is_err());
+        assert!(BreqConfig::parse_kdl(r#"env { PORT }"#).is_err());
+
+        let repo = tempfile::tempdir().unwrap();
+        let ws = tempfile::tempdir().unwrap();
+        std::fs::write(repo.path().join("toren.kdl"), content).unwrap();
+        let setup = WorkspaceSetup::new(
+            repo.path().to_path_buf(),
+            ws.path().to_path_buf(),
+            "three".to_string(),
+            3,
+            None,
+        );
+        setup.run_setup().unwrap();
+        assert_eq!(
+            read_workspace_env(ws.path()).unwrap(),
+            vec![
+                ("DATABASE_URL".to_string(), "postgres://localhost/app_3".to_string()),
+                ("PORT".to_string(), "3000".to_string()),
+            ]
+        );
+
+        // Dropping the block removes the stale file
+        std::fs::write(repo.path().join("toren.kdl"), "").unwrap();
+        setup.run_setup().unwrap();
+        assert!(!ws.path().join(WORKSPACE_ENV_FILE).exists());
+        assert!(read_workspace_env(ws.path()).unwrap().is_empty());
+    }
+
     #[test]
     fn test_parse_env_in_setup_block() {
         let content = r#"
@@ -2054,6 +3960,92 @@ setup {
         }
     }
 
+    #[test]
+    fn test_parse_run_inputs_and_outputs() {
+        let content = r#"
+setup {
+    run "pnpm install" cwd="web" {
+        inputs "package.json" "pnpm-lock.yaml"
+        outputs "node_modules"
+    }
+}
+"#;
+        let config = BreqConfig::parse_kdl(content).unwrap();
+        match &config.setup[0].action {
+            Action::Run { inputs, outputs, .. } => {
+                assert_eq!(inputs, &["package.json", "pnpm-lock.yaml"]);
+                assert_eq!(outputs, &["node_modules"]);
+            }
+            _ => panic!("expected Run"),
+        }
+
+        let err = BreqConfig::parse_kdl(r#"setup { run "x" { inputs; }; }"#).unwrap_err();
+        assert!(err.to_string().contains("at least one path"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_resetup_skips_unchanged_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let ws = dir.path().join("ws");
+        fs::create_dir_all(&repo).unwrap();
+        fs::create_dir_all(&ws).unwrap();
+        fs::write(repo.join("toren.kdl"), r#"
+setup {
+    template src="env.tpl" dest=".env"
+    run "echo ran >> runs.log" {
+        inputs "deps.txt"
+        outputs "runs.log"
+    }
+}
+"#).unwrap();
+        fs::write(repo.join("env.tpl"), "WS={{ ws.name }}").unwrap();
+        fs::write(ws.join("deps.txt"), "a").unwrap();
+
+        let setup = WorkspaceSetup::new(repo.clone(), ws.clone(), "one".to_string(), 1, None)
+            .with_manifest(Some(dir.path().join("manifest.json")));
+        let runs = || fs::read_to_string(ws.join("runs.log")).unwrap().lines().count();
+
+        setup.run_setup().unwrap();
+        assert_eq!(fs::read_to_string(ws.join(".env")).unwrap(), "WS=one");
+        setup.run_setup().unwrap();
+        assert_eq!(runs(), 1, "unchanged run should be skipped");
+
+        // A changed input reruns the command
+        fs::write(ws.join("deps.txt"), "b").unwrap();
+        setup.run_setup().unwrap();
+        assert_eq!(runs(), 2);
+
+        // A missing output reruns the action even though inputs are unchanged
+        fs::remove_file(ws.join(".env")).unwrap();
+        setup.run_setup().unwrap();
+        assert!(ws.join(".env").exists());
+        assert_eq!(runs(), 2);
+    }
+
+    #[test]
+    fn test_template_directory_preserves_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let ws = dir.path().join("ws");
+        fs::create_dir_all(repo.join("templates/dev/config")).unwrap();
+        fs::create_dir_all(&ws).unwrap();
+        fs::write(repo.join("toren.kdl"), r#"
+setup {
+    template src="templates/dev/" dest="." exclude="*.png"
+}
+"#).unwrap();
+        fs::write(repo.join("templates/dev/.env"), "WS={{ ws.name }}").unwrap();
+        fs::write(repo.join("templates/dev/config/app.toml"), "num = {{ ws.num }}").unwrap();
+        fs::write(repo.join("templates/dev/config/logo.png"), [0x89, 0xff, 0xfe]).unwrap();
+
+        let setup = WorkspaceSetup::new(repo, ws.clone(), "one".to_string(), 1, None);
+        setup.run_setup().unwrap();
+        assert_eq!(fs::read_to_string(ws.join(".env")).unwrap(), "WS=one");
+        assert_eq!(fs::read_to_string(ws.join("config/app.toml")).unwrap(), "num = 1");
+        assert!(!ws.join("config/logo.png").exists());
+    }
+
     #[test]
     fn test_parse_run_rejects_unknown_child() {
         let content = r#"
@@ -2147,8 +4139,11 @@ setup {
             repo: RepoInfo {
                 root: "/tmp/repo".to_string(),
                 name: "repo".to_string(),
+                recent_commits: Vec::new(),
             },
             task: None,
+            variant: None,
+            assignment: None,
             vars: HashMap::new(),
         }
     }
@@ -2211,20 +4206,26 @@ setup {
                 action: Action::Run {
                     command: r#"test "${RUN_ONLY:-}" = "scoped""#.to_string(),
                     cwd: None,
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
                     child_env: vec![EnvDirective::Pairs(vec![(
                         "RUN_ONLY".to_string(),
                         "scoped".to_string(),
                     )])],
                 },
                 on_fail: OnFail::Exit,
+                timeout: None,
             },
             ParsedAction {
                 action: Action::Run {
                     command: r#"test -z "${RUN_ONLY:-}""#.to_string(),
                     cwd: None,
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
                     child_env: Vec::new(),
                 },
                 on_fail: OnFail::Exit,
+                timeout: None,
             },
         ];
         let result = setup.execute_actions(&actions, &ctx, &mut env_state);
@@ -2247,14 +4248,18 @@ setup {
                     "yes".to_string(),
                 )])),
                 on_fail: OnFail::Exit,
+                timeout: None,
             },
             ParsedAction {
                 action: Action::Run {
                     command: r#"test "${PERSIST:-}" = "yes""#.to_string(),
                     cwd: None,
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
                     child_env: Vec::new(),
                 },
                 on_fail: OnFail::Exit,
+                timeout: None,
             },
         ];
         let result = setup.execute_actions(&actions, &ctx, &mut env_state);
@@ -51,12 +51,12 @@ log "Paired successfully, got session token"
 
 # List segments
 log "Listing segments..."
-SEGMENTS=$(curl -sf "${DAEMON_URL}/api/segments/list")
+SEGMENTS=$(curl -sf -H "Authorization: Bearer ${SESSION_TOKEN}" "${DAEMON_URL}/api/segments/list")
 echo "$SEGMENTS" | jq .
 
 # Create an assignment with a simple prompt
 log "Creating assignment with prompt..."
-ASSIGNMENT_RESPONSE=$(curl -sf -X POST "${DAEMON_URL}/api/assignments" \
+ASSIGNMENT_RESPONSE=$(curl -sf -X POST -H "Authorization: Bearer ${SESSION_TOKEN}" "${DAEMON_URL}/api/assignments" \
     -H "Content-Type: application/json" \
     -d "{
         \"segment\": \"${SEGMENT}\",
@@ -83,7 +83,7 @@ ANCILLARY_ID_ENCODED=$(echo "$ANCILLARY_ID" | sed 's/ /%20/g')
 
 # Start work on the ancillary
 log "Starting work on ancillary..."
-START_RESPONSE=$(curl -sf -X POST "${DAEMON_URL}/api/ancillaries/${ANCILLARY_ID_ENCODED}/start" \
+START_RESPONSE=$(curl -sf -X POST -H "Authorization: Bearer ${SESSION_TOKEN}" "${DAEMON_URL}/api/ancillaries/${ANCILLARY_ID_ENCODED}/start" \
     -H "Content-Type: application/json" \
     -d "{\"assignment_id\": \"${ASSIGNMENT_ID}\"}")
 
@@ -109,11 +109,11 @@ sleep 5
 
 # List ancillaries to see status
 log "Checking ancillary status..."
-curl -sf "${DAEMON_URL}/api/ancillaries/list" | jq .
+curl -sf -H "Authorization: Bearer ${SESSION_TOKEN}" "${DAEMON_URL}/api/ancillaries/list" | jq .
 
 log "Test complete!"
 echo ""
 echo "Next steps:"
 echo "1. Monitor the WebSocket for live events"
 echo "2. Check the work log at ~/.toren/ancillaries/$(echo "$ANCILLARY_ID" | tr ' ' '-' | tr '[:upper:]' '[:lower:]')/work/${BEAD_ID}.jsonl"
-echo "3. Stop work with: curl -X POST ${DAEMON_URL}/api/ancillaries/${ANCILLARY_ID_ENCODED}/stop"
+echo "3. Stop work with: curl -X POST -H \"Authorization: Bearer ${SESSION_TOKEN}\" ${DAEMON_URL}/api/ancillaries/${ANCILLARY_ID_ENCODED}/stop"
@@ -0,0 +1,114 @@
+//! Proxy backend abstraction.
+//!
+//! Station drives one of several reverse proxies: Caddy through its admin
+//! API, or Traefik and nginx through generated config files. Each implements
+//! [`ProxyBackend`]; [`Backend`] selects one at runtime.
+
+use anyhow::Result;
+use std::future::Future;
+use std::path::PathBuf;
+
+use crate::caddy::CaddyBackend;
+use crate::nginx::NginxBackend;
+use crate::traefik::TraefikBackend;
+use crate::RouteInfo;
+
+/// Operations every proxy backend supports.
+pub trait ProxyBackend {
+    /// Add (or replace) the route for `host` on `port`.
+    fn add_route(
+        &self,
+        host: &str,
+        upstream: &str,
+        port: u16,
+        tls: bool,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Remove every route for `host`, across all ports.
+    fn remove_routes(&self, host: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// List all station-managed routes.
+    fn list_routes(&self) -> impl Future<Output = Result<Vec<RouteInfo>>> + Send;
+
+    /// Check that the backend can be reached (or written to).
+    fn health(&self) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Which backend to use and where its configuration lives.
+#[derive(Debug, Clone, Default)]
+pub enum BackendConfig {
+    /// Caddy admin API (see `StationConfig::caddy_admin_url`)
+    #[default]
+    Caddy,
+    /// Traefik file provider: routes are written to `config_file`, which
+    /// Traefik watches for changes
+    Traefik { config_file: PathBuf },
+    /// nginx: server blocks are written to `config_file` (included from the
+    /// main nginx config), then `reload_command` is run
+    Nginx {
+        config_file: PathBuf,
+        reload_command: String,
+    },
+}
+
+impl BackendConfig {
+    /// Parse a backend name (`caddy`, `traefik`, `nginx`), using `config_dir`
+    /// for the generated config files of file-based backends.
+    pub fn from_name(name: &str, config_dir: PathBuf) -> Result<Self> {
+        match name {
+            "caddy" => Ok(BackendConfig::Caddy),
+            "traefik" => Ok(BackendConfig::Traefik {
+                config_file: config_dir.join("traefik.yml"),
+            }),
+            "nginx" => Ok(BackendConfig::Nginx {
+                config_file: config_dir.join("nginx.conf"),
+                reload_command: "nginx -s reload".to_string(),
+            }),
+            other => anyhow::bail!(
+                "Unknown proxy backend '{}' (expected caddy, traefik or nginx)",
+                other
+            ),
+        }
+    }
+}
+
+/// The configured backend.
+pub enum Backend {
+    Caddy(CaddyBackend),
+    Traefik(TraefikBackend),
+    Nginx(NginxBackend),
+}
+
+impl ProxyBackend for Backend {
+    async fn add_route(&self, host: &str, upstream: &str, port: u16, tls: bool) -> Result<()> {
+        match self {
+            Backend::Caddy(b) => b.add_route(host, upstream, port, tls).await,
+            Backend::Traefik(b) => b.add_route(host, upstream, port, tls).await,
+            Backend::Nginx(b) => b.add_route(host, upstream, port, tls).await,
+        }
+    }
+
+    async fn remove_routes(&self, host: &str) -> Result<()> {
+        match self {
+            Backend::Caddy(b) => b.remove_routes(host).await,
+            Backend::Traefik(b) => b.remove_routes(host).await,
+            Backend::Nginx(b) => b.remove_routes(host).await,
+        }
+    }
+
+    async fn list_routes(&self) -> Result<Vec<RouteInfo>> {
+        match self {
+            Backend::Caddy(b) => ProxyBackend::list_routes(b).await,
+            Backend::Traefik(b) => b.list_routes().await,
+            Backend::Nginx(b) => b.list_routes().await,
+        }
+    }
+
+    async fn health(&self) -> Result<()> {
+        match self {
+            Backend::Caddy(b) => b.health().await,
+            Backend::Traefik(b) => b.health().await,
+            Backend::Nginx(b) => b.health().await,
+        }
+    }
+}
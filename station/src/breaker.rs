@@ -0,0 +1,177 @@
+//! Circuit breaker for the proxy backend.
+//!
+//! When the Caddy admin API can't be reached, every route operation would
+//! otherwise wait out its own connection timeout. After a connection failure
+//! the breaker opens for a cooldown period, during which route operations fail
+//! immediately with [`BackendUnavailable`]. The open state is persisted to a
+//! small state file so it spans separate `station` invocations.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+/// Returned instead of attempting a route operation while the breaker is open.
+#[derive(Debug, Clone)]
+pub struct BackendUnavailable {
+    /// Time left before the backend will be tried again
+    pub retry_in: Duration,
+}
+
+impl std::fmt::Display for BackendUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Caddy admin API unreachable; skipping route changes for {}s (run `station health` once it's back)",
+            self.retry_in.as_secs().max(1)
+        )
+    }
+}
+
+impl std::error::Error for BackendUnavailable {}
+
+/// Tracks backend connection failures and short-circuits requests while open.
+pub struct CircuitBreaker {
+    cooldown: Duration,
+    state_path: Option<PathBuf>,
+    opened_at: Mutex<Option<SystemTime>>,
+}
+
+impl CircuitBreaker {
+    /// A breaker that opens for `cooldown` after a failure, persisting its
+    /// state to `state_path` when given. A zero cooldown disables it.
+    pub fn new(cooldown: Duration, state_path: Option<PathBuf>) -> Self {
+        let opened_at = state_path.as_deref().and_then(read_state);
+        Self {
+            cooldown,
+            state_path,
+            opened_at: Mutex::new(opened_at),
+        }
+    }
+
+    /// A breaker that never opens.
+    pub fn disabled() -> Self {
+        Self::new(Duration::ZERO, None)
+    }
+
+    /// Fail fast if the breaker is open.
+    pub fn check(&self) -> Result<(), BackendUnavailable> {
+        let opened_at = *self.opened_at.lock().unwrap();
+        match opened_at.and_then(|t| self.remaining(t)) {
+            Some(retry_in) => Err(BackendUnavailable { retry_in }),
+            None => Ok(()),
+        }
+    }
+
+    /// Record a connection failure, opening the breaker.
+    ///
+    /// Warns once when the breaker transitions to open; repeat failures while
+    /// already open stay quiet.
+    pub fn trip(&self) {
+        if self.cooldown.is_zero() {
+            return;
+        }
+        let mut opened_at = self.opened_at.lock().unwrap();
+        let already_open = opened_at.and_then(|t| self.remaining(t)).is_some();
+        let now = SystemTime::now();
+        *opened_at = Some(now);
+
+        if !already_open {
+            warn!(
+                "Caddy admin API unreachable; pausing proxy route changes for {}s",
+                self.cooldown.as_secs()
+            );
+        }
+        if let Some(ref path) = self.state_path {
+            let secs = now
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            if let Err(e) = std::fs::write(path, secs.to_string()) {
+                debug!("Failed to persist breaker state to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Close the breaker after a successful request.
+    pub fn reset(&self) {
+        let mut opened_at = self.opened_at.lock().unwrap();
+        if opened_at.take().is_none() {
+            return;
+        }
+        debug!("Caddy admin API reachable again; closing breaker");
+        if let Some(ref path) = self.state_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    fn remaining(&self, opened_at: SystemTime) -> Option<Duration> {
+        let elapsed = SystemTime::now()
+            .duration_since(opened_at)
+            .unwrap_or_default();
+        self.cooldown.checked_sub(elapsed).filter(|d| !d.is_zero())
+    }
+}
+
+fn read_state(path: &Path) -> Option<SystemTime> {
+    let secs: u64 = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "station-breaker-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_trip_opens_until_reset() {
+        let breaker = CircuitBreaker::new(Duration::from_secs(30), None);
+        assert!(breaker.check().is_ok());
+
+        breaker.trip();
+        let err = breaker.check().unwrap_err();
+        assert!(err.retry_in <= Duration::from_secs(30));
+
+        breaker.reset();
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn test_disabled_never_opens() {
+        let breaker = CircuitBreaker::disabled();
+        breaker.trip();
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn test_state_persists_across_instances() {
+        let path = state_file("persist");
+        CircuitBreaker::new(Duration::from_secs(30), Some(path.clone())).trip();
+
+        let reopened = CircuitBreaker::new(Duration::from_secs(30), Some(path.clone()));
+        assert!(reopened.check().is_err());
+
+        reopened.reset();
+        assert!(!path.exists());
+        let fresh = CircuitBreaker::new(Duration::from_secs(30), Some(path));
+        assert!(fresh.check().is_ok());
+    }
+
+    #[test]
+    fn test_expired_state_is_closed() {
+        let path = state_file("expired");
+        std::fs::write(&path, "0").unwrap();
+        let breaker = CircuitBreaker::new(Duration::from_secs(30), Some(path.clone()));
+        assert!(breaker.check().is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -5,22 +5,83 @@
 //! with routes differentiated by hostname.
 
 use anyhow::{Context, Result};
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
+use crate::backend::ProxyBackend;
+use crate::breaker::CircuitBreaker;
 use crate::RouteInfo;
 
+/// How long to wait for the admin API to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Upper bound on a single admin API request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Caddy-based proxy backend that manages routes via the Caddy admin API.
 pub struct CaddyBackend {
     client: reqwest::Client,
     admin_url: String,
+    breaker: CircuitBreaker,
 }
 
 impl CaddyBackend {
     /// Create a new CaddyBackend targeting the given admin API URL.
     pub fn new(admin_url: String) -> Self {
+        Self::with_breaker(admin_url, CircuitBreaker::disabled())
+    }
+
+    /// Create a CaddyBackend that short-circuits route operations via `breaker`
+    /// after the admin API fails to connect.
+    pub fn with_breaker(admin_url: String, breaker: CircuitBreaker) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
         Self {
-            client: reqwest::Client::new(),
+            client,
             admin_url,
+            breaker,
+        }
+    }
+
+    /// Send an admin API request through the circuit breaker.
+    ///
+    /// Fails fast with [`BackendUnavailable`] while the breaker is open, and
+    /// opens it when the request can't connect or times out.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.breaker.check()?;
+        match request.send().await {
+            Ok(resp) => {
+                self.breaker.reset();
+                Ok(resp)
+            }
+            Err(e) if e.is_connect() || e.is_timeout() => {
+                debug!("Caddy admin API request failed: {}", e);
+                self.breaker.trip();
+                match self.breaker.check() {
+                    Err(unavailable) => Err(unavailable.into()),
+                    // Breaker disabled: surface the underlying error
+                    Ok(()) => Err(e.into()),
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Probe the admin API regardless of breaker state, closing the breaker if
+    /// it responds and opening it if it doesn't.
+    pub async fn reconnect(&self) -> Result<()> {
+        let url = format!("{}/config/", self.admin_url);
+        match self.client.get(&url).send().await {
+            Ok(_) => {
+                self.breaker.reset();
+                Ok(())
+            }
+            Err(e) => {
+                self.breaker.trip();
+                Err(e).with_context(|| format!("Caddy admin API not reachable at {}", self.admin_url))
+            }
         }
     }
 
@@ -71,10 +132,7 @@ impl CaddyBackend {
             self.admin_url, server_name
         );
         let resp = self
-            .client
-            .post(&url)
-            .json(&route)
-            .send()
+            .send(self.client.post(&url).json(&route))
             .await
             .context("Failed to add Caddy route")?;
 
@@ -98,9 +156,7 @@ impl CaddyBackend {
         let route_id = route_id(host, port);
         let url = format!("{}/id/{}", self.admin_url, route_id);
         let resp = self
-            .client
-            .delete(&url)
-            .send()
+            .send(self.client.delete(&url))
             .await
             .context("Failed to remove Caddy route")?;
 
@@ -140,9 +196,7 @@ impl CaddyBackend {
     pub async fn list_routes(&self) -> Result<Vec<RouteInfo>> {
         let url = format!("{}/config/apps/http/servers/", self.admin_url);
         let resp = self
-            .client
-            .get(&url)
-            .send()
+            .send(self.client.get(&url))
             .await
             .context("Failed to connect to Caddy admin API")?;
 
@@ -207,41 +261,37 @@ impl CaddyBackend {
 
         // Check if server already exists (Caddy returns null/200 for missing keys,
         // so we must verify the body is an actual JSON object)
-        if let Ok(r) = self.client.get(&server_url).send().await {
-            if r.status().is_success() {
-                let body: serde_json::Value =
-                    r.json().await.unwrap_or(serde_json::Value::Null);
-                if body.is_object() {
-                    // Server exists — verify routes array exists
-                    if body.get("routes").and_then(|v| v.as_array()).is_some() {
-                        debug!("Caddy server '{}' ready", server_name);
-                        return Ok(());
-                    }
-                    // Routes array missing — recreate it
-                    debug!(
-                        "Caddy server '{}' missing routes array, recreating",
-                        server_name
-                    );
-                    let routes_url = format!("{}/routes", server_url);
-                    let resp = self
-                        .client
-                        .put(&routes_url)
-                        .json(&serde_json::json!([]))
-                        .send()
-                        .await
-                        .context("Failed to connect to Caddy admin API")?;
-                    if resp.status().is_success() {
-                        return Ok(());
-                    }
-                    let body = resp.text().await.unwrap_or_default();
-                    anyhow::bail!(
-                        "Failed to recreate routes array for Caddy server '{}': {}",
-                        server_name,
-                        body
-                    );
+        let r = self.send(self.client.get(&server_url)).await?;
+        if r.status().is_success() {
+            let body: serde_json::Value =
+                r.json().await.unwrap_or(serde_json::Value::Null);
+            if body.is_object() {
+                // Server exists — verify routes array exists
+                if body.get("routes").and_then(|v| v.as_array()).is_some() {
+                    debug!("Caddy server '{}' ready", server_name);
+                    return Ok(());
                 }
-                // Body is null — server doesn't actually exist, fall through to create
+                // Routes array missing — recreate it
+                debug!(
+                    "Caddy server '{}' missing routes array, recreating",
+                    server_name
+                );
+                let routes_url = format!("{}/routes", server_url);
+                let resp = self
+                    .send(self.client.put(&routes_url).json(&serde_json::json!([])))
+                    .await
+                    .context("Failed to connect to Caddy admin API")?;
+                if resp.status().is_success() {
+                    return Ok(());
+                }
+                let body = resp.text().await.unwrap_or_default();
+                anyhow::bail!(
+                    "Failed to recreate routes array for Caddy server '{}': {}",
+                    server_name,
+                    body
+                );
             }
+            // Body is null — server doesn't actually exist, fall through to create
         }
 
         // Create server config for this port
@@ -258,10 +308,7 @@ impl CaddyBackend {
         }
 
         let resp = self
-            .client
-            .put(&server_url)
-            .json(&server_config)
-            .send()
+            .send(self.client.put(&server_url).json(&server_config))
             .await
             .context("Failed to connect to Caddy admin API")?;
 
@@ -278,6 +325,24 @@ impl CaddyBackend {
     }
 }
 
+impl ProxyBackend for CaddyBackend {
+    async fn add_route(&self, host: &str, upstream: &str, port: u16, tls: bool) -> Result<()> {
+        CaddyBackend::add_route(self, host, upstream, port, tls).await
+    }
+
+    async fn remove_routes(&self, host: &str) -> Result<()> {
+        self.remove_routes_for_host(host).await
+    }
+
+    async fn list_routes(&self) -> Result<Vec<RouteInfo>> {
+        CaddyBackend::list_routes(self).await
+    }
+
+    async fn health(&self) -> Result<()> {
+        self.reconnect().await
+    }
+}
+
 /// Generate a Caddy server name from a port number.
 fn server_name_for_port(port: u16) -> String {
     format!("station-{}", port)
@@ -342,6 +407,46 @@ mod tests {
         CaddyBackend::new(admin_url.to_string())
     }
 
+    // -- circuit breaker tests --
+
+    #[tokio::test]
+    async fn test_connection_failure_opens_breaker() {
+        // Nothing listens on port 1, so the connection is refused
+        let breaker = CircuitBreaker::new(std::time::Duration::from_secs(30), None);
+        let backend = CaddyBackend::with_breaker("http://127.0.0.1:1".to_string(), breaker);
+
+        let err = backend.list_routes().await.unwrap_err();
+        assert!(err.downcast_ref::<crate::BackendUnavailable>().is_some());
+
+        let err = backend.add_route("app.localhost", "5173", 80, false).await.unwrap_err();
+        assert!(err.downcast_ref::<crate::BackendUnavailable>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_skips_requests() {
+        let mock = MockServer::start().await;
+        let breaker = CircuitBreaker::new(std::time::Duration::from_secs(30), None);
+        breaker.trip();
+        let backend = CaddyBackend::with_breaker(mock.uri(), breaker);
+
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock)
+            .await;
+
+        assert!(backend.remove_route("app.localhost", 80).await.is_err());
+
+        // A successful probe closes the breaker again
+        Mock::given(method("GET"))
+            .and(path("/config/"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock)
+            .await;
+        backend.reconnect().await.unwrap();
+        assert!(backend.breaker.check().is_ok());
+    }
+
     // -- ensure_port_server tests --
 
     #[tokio::test]
@@ -0,0 +1,157 @@
+//! Route table for file-based backends.
+//!
+//! Traefik and nginx are configured by generated files rather than an API,
+//! so station keeps the routes it manages in a JSON table next to the
+//! generated config (`<config_file>.routes.json`) and regenerates the config
+//! from the whole table on every change. The table is updated under an
+//! exclusive file lock, since separate `station` invocations may race.
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::RouteInfo;
+
+/// Routes managed by a file-based backend.
+pub struct RouteTable {
+    path: PathBuf,
+}
+
+impl RouteTable {
+    /// The table that belongs to a generated config file.
+    pub fn for_config(config_file: &Path) -> Self {
+        let mut name = config_file.as_os_str().to_owned();
+        name.push(".routes.json");
+        Self {
+            path: PathBuf::from(name),
+        }
+    }
+
+    /// All routes, ordered by host then port.
+    pub fn list(&self) -> Result<Vec<RouteInfo>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        self.update(|_| false)
+    }
+
+    /// Apply `f` to the routes under the lock, saving them if `f` reports a
+    /// change. Returns the resulting routes.
+    pub fn update(&self, f: impl FnOnce(&mut Vec<RouteInfo>) -> bool) -> Result<Vec<RouteInfo>> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", self.path.display()))?;
+
+        let mut routes = read_routes(&mut file)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        if f(&mut routes) {
+            routes.sort_by(|a, b| (&a.host, a.port).cmp(&(&b.host, b.port)));
+            let content = serde_json::to_string_pretty(&routes)?;
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(content.as_bytes())
+                .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        }
+        Ok(routes)
+    }
+}
+
+fn read_routes(file: &mut File) -> Result<Vec<RouteInfo>> {
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Insert or replace the route for `route.host` on `route.port`.
+pub fn upsert(routes: &mut Vec<RouteInfo>, route: RouteInfo) {
+    routes.retain(|r| !(r.host == route.host && r.port == route.port));
+    routes.push(route);
+}
+
+/// Upstream as a URL (`http://host:port[/path]`) for backends that accept paths.
+pub fn upstream_url(upstream: &str) -> String {
+    if upstream.starts_with("http://") || upstream.starts_with("https://") {
+        upstream.to_string()
+    } else if upstream.parse::<u16>().is_ok() {
+        format!("http://localhost:{}", upstream)
+    } else {
+        format!("http://{}", upstream)
+    }
+}
+
+/// Write `content` to `path` via a temp file and rename, so the proxy never
+/// reads a half-written config.
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(host: &str, port: u16) -> RouteInfo {
+        RouteInfo {
+            host: host.to_string(),
+            upstream: "http://localhost:5173".to_string(),
+            port,
+            tls: false,
+        }
+    }
+
+    #[test]
+    fn test_table_upserts_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = RouteTable::for_config(&dir.path().join("nginx.conf"));
+        assert!(table.list().unwrap().is_empty());
+
+        table.update(|routes| {
+            upsert(routes, route("b.localhost", 80));
+            upsert(routes, route("a.localhost", 80));
+            true
+        })
+        .unwrap();
+        table.update(|routes| {
+            let mut replacement = route("b.localhost", 80);
+            replacement.upstream = "http://localhost:3000".to_string();
+            upsert(routes, replacement);
+            true
+        })
+        .unwrap();
+
+        let routes = RouteTable::for_config(&dir.path().join("nginx.conf")).list().unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].host, "a.localhost");
+        assert_eq!(routes[1].upstream, "http://localhost:3000");
+        assert!(dir.path().join("nginx.conf.routes.json").exists());
+    }
+
+    #[test]
+    fn test_upstream_url() {
+        assert_eq!(upstream_url("5173"), "http://localhost:5173");
+        assert_eq!(upstream_url("127.0.0.1:3000"), "http://127.0.0.1:3000");
+        assert_eq!(upstream_url("http://127.0.0.1:3000/v2"), "http://127.0.0.1:3000/v2");
+    }
+}
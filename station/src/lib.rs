@@ -1,33 +1,60 @@
 //! Station — reverse proxy management for local dev services.
 //!
-//! Station manages named routes through a proxy backend (currently Caddy),
-//! mapping `<name>.<domain>:<port>` to local upstream services.
+//! Station manages named routes through a proxy backend (Caddy, Traefik or
+//! nginx), mapping `<name>.<domain>:<port>` to local upstream services.
 
+mod backend;
+mod breaker;
 mod caddy;
+mod file_routes;
+mod nginx;
+mod traefik;
 
+pub use backend::{Backend, BackendConfig, ProxyBackend};
+pub use breaker::{BackendUnavailable, CircuitBreaker};
 pub use caddy::CaddyBackend;
+pub use nginx::NginxBackend;
+pub use traefik::TraefikBackend;
 
 use anyhow::Result;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Exit code used by the `station` CLI when the backend is unavailable
+/// (`EX_TEMPFAIL`), so callers can skip proxy work instead of failing.
+pub const EXIT_UNAVAILABLE: i32 = 75;
+
+/// Default time route operations are skipped after Caddy fails to connect.
+const DEFAULT_BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
 
 /// Configuration for a Station instance.
 pub struct StationConfig {
     /// Base domain for proxied services (e.g., "localhost", "lvh.me").
     pub domain: String,
+    /// Which proxy backend manages the routes (default: Caddy).
+    pub backend: BackendConfig,
     /// Caddy admin API URL (default: "http://localhost:2019").
     pub caddy_admin_url: String,
+    /// How long to skip route operations after a connection failure (zero disables).
+    pub breaker_cooldown: Duration,
+    /// Where breaker state is persisted between invocations (None keeps it in memory).
+    pub breaker_state_path: Option<PathBuf>,
 }
 
 impl Default for StationConfig {
     fn default() -> Self {
         Self {
             domain: "localhost".to_string(),
+            backend: BackendConfig::default(),
             caddy_admin_url: "http://localhost:2019".to_string(),
+            breaker_cooldown: DEFAULT_BREAKER_COOLDOWN,
+            breaker_state_path: None,
         }
     }
 }
 
 /// Info about an active proxy route.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RouteInfo {
     /// The full hostname (e.g., "myapp.localhost").
     pub host: String,
@@ -39,17 +66,31 @@ pub struct RouteInfo {
     pub tls: bool,
 }
 
-/// Station manages named proxy routes via a backend (Caddy).
+/// Station manages named proxy routes via a backend.
 pub struct Station {
     domain: String,
-    backend: CaddyBackend,
+    backend: Backend,
 }
 
 impl Station {
     /// Create a new Station with the given config.
     pub fn new(config: StationConfig) -> Self {
+        let backend = match config.backend {
+            BackendConfig::Caddy => {
+                let breaker =
+                    CircuitBreaker::new(config.breaker_cooldown, config.breaker_state_path);
+                Backend::Caddy(CaddyBackend::with_breaker(config.caddy_admin_url, breaker))
+            }
+            BackendConfig::Traefik { config_file } => {
+                Backend::Traefik(TraefikBackend::new(config_file))
+            }
+            BackendConfig::Nginx {
+                config_file,
+                reload_command,
+            } => Backend::Nginx(NginxBackend::new(config_file, reload_command)),
+        };
         Self {
-            backend: CaddyBackend::new(config.caddy_admin_url),
+            backend,
             domain: config.domain,
         }
     }
@@ -57,15 +98,42 @@ impl Station {
     /// Create from environment variables.
     ///
     /// - `STATION_DOMAIN` — base domain (default: "localhost")
+    /// - `STATION_BACKEND` — `caddy`, `traefik` or `nginx` (default: "caddy")
+    /// - `STATION_CONFIG_DIR` — where the traefik/nginx backends write their
+    ///   generated config (default: "~/.config/station")
+    /// - `STATION_NGINX_RELOAD` — command run after the nginx config changes
+    ///   (default: "nginx -s reload")
     /// - `STATION_CADDY_URL` — Caddy admin API URL (default: "http://localhost:2019")
-    pub fn from_env() -> Self {
+    /// - `STATION_BREAKER_SECS` — seconds to skip route operations after Caddy
+    ///   fails to connect (default: 30, 0 disables)
+    pub fn from_env() -> Result<Self> {
         let domain = std::env::var("STATION_DOMAIN").unwrap_or_else(|_| "localhost".to_string());
+        let backend_name =
+            std::env::var("STATION_BACKEND").unwrap_or_else(|_| "caddy".to_string());
+        let config_dir = std::env::var_os("STATION_CONFIG_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(default_config_dir);
+        let mut backend = BackendConfig::from_name(&backend_name, config_dir)?;
+        if let (BackendConfig::Nginx { reload_command, .. }, Ok(cmd)) =
+            (&mut backend, std::env::var("STATION_NGINX_RELOAD"))
+        {
+            *reload_command = cmd;
+        }
         let caddy_url = std::env::var("STATION_CADDY_URL")
             .unwrap_or_else(|_| "http://localhost:2019".to_string());
-        Self::new(StationConfig {
+        let breaker_cooldown = std::env::var("STATION_BREAKER_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_BREAKER_COOLDOWN);
+        let breaker_state_path = Some(breaker_state_path(&caddy_url));
+        Ok(Self::new(StationConfig {
             domain,
+            backend,
             caddy_admin_url: caddy_url,
-        })
+            breaker_cooldown,
+            breaker_state_path,
+        }))
     }
 
     /// Register a named proxy route.
@@ -80,7 +148,7 @@ impl Station {
     /// Remove all routes for a named service across all ports.
     pub async fn forget(&self, name: &str) -> Result<()> {
         let host = self.host_for_name(name);
-        self.backend.remove_routes_for_host(&host).await
+        self.backend.remove_routes(&host).await
     }
 
     /// List all active station-managed routes.
@@ -88,6 +156,12 @@ impl Station {
         self.backend.list_routes().await
     }
 
+    /// Check that the backend is reachable (or its config writable), clearing
+    /// the Caddy circuit breaker if so.
+    pub async fn health(&self) -> Result<()> {
+        self.backend.health().await
+    }
+
     /// The configured domain.
     pub fn domain(&self) -> &str {
         &self.domain
@@ -97,3 +171,22 @@ impl Station {
         format!("{}.{}", name, self.domain)
     }
 }
+
+/// Per-admin-URL breaker state file in the system temp dir, shared by all
+/// `station` invocations talking to the same Caddy.
+fn breaker_state_path(admin_url: &str) -> PathBuf {
+    let key: String = admin_url
+        .trim_start_matches("http://")
+        .trim_start_matches("https://")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    std::env::temp_dir().join(format!("station-breaker-{}", key))
+}
+
+/// `~/.config/station`, falling back to the temp dir without a home directory.
+fn default_config_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(".config/station"))
+        .unwrap_or_else(|| std::env::temp_dir().join("station"))
+}
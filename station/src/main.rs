@@ -35,15 +35,36 @@ enum Commands {
     },
 
     /// List all active station-managed routes
-    List,
+    List {
+        /// Print routes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check that the proxy backend is reachable and resume route changes if it is
+    Health,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let station = station::Station::from_env();
+    let station = station::Station::from_env()?;
 
-    match cli.command {
+    if let Err(e) = run(&station, cli.command).await {
+        // Backend outages get a distinct exit code so callers can skip proxy
+        // work rather than fail the whole operation.
+        if let Some(unavailable) = e.downcast_ref::<station::BackendUnavailable>() {
+            eprintln!("warning: {}", unavailable);
+            std::process::exit(station::EXIT_UNAVAILABLE);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+async fn run(station: &station::Station, command: Commands) -> Result<()> {
+
+    match command {
         Commands::Proxy {
             name,
             upstream,
@@ -64,9 +85,11 @@ async fn main() -> Result<()> {
             station.forget(&name).await?;
             println!("Removed routes for {}", name);
         }
-        Commands::List => {
+        Commands::List { json } => {
             let routes = station.list().await?;
-            if routes.is_empty() {
+            if json {
+                println!("{}", serde_json::to_string(&routes)?);
+            } else if routes.is_empty() {
                 println!("No active routes");
             } else {
                 for route in routes {
@@ -82,6 +105,13 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Commands::Health => {
+            if let Err(e) = station.health().await {
+                eprintln!("{:#}", e);
+                std::process::exit(station::EXIT_UNAVAILABLE);
+            }
+            println!("Proxy backend reachable");
+        }
     }
 
     Ok(())
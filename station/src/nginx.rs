@@ -0,0 +1,160 @@
+//! nginx backend.
+//!
+//! Routes are rendered as `server` blocks into a config file that the main
+//! nginx configuration includes (e.g. `include /path/to/nginx.conf;` inside
+//! the `http` block). nginx has no dynamic configuration, so every change is
+//! followed by the configured reload command.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::backend::ProxyBackend;
+use crate::file_routes::{self, RouteTable};
+use crate::RouteInfo;
+
+/// nginx backend writing server blocks and reloading nginx.
+pub struct NginxBackend {
+    config_file: PathBuf,
+    reload_command: String,
+    table: RouteTable,
+}
+
+impl NginxBackend {
+    pub fn new(config_file: PathBuf, reload_command: String) -> Self {
+        Self {
+            table: RouteTable::for_config(&config_file),
+            config_file,
+            reload_command,
+        }
+    }
+
+    async fn apply(&self, routes: &[RouteInfo]) -> Result<()> {
+        file_routes::write_atomic(&self.config_file, &render(routes))?;
+        self.reload().await
+    }
+
+    async fn reload(&self) -> Result<()> {
+        if self.reload_command.trim().is_empty() {
+            return Ok(());
+        }
+        let output = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.reload_command)
+            .output()
+            .await
+            .with_context(|| format!("Failed to run '{}'", self.reload_command))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "'{}' failed: {}",
+                self.reload_command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl ProxyBackend for NginxBackend {
+    async fn add_route(&self, host: &str, upstream: &str, port: u16, tls: bool) -> Result<()> {
+        if tls {
+            // Certificates are the operator's job with nginx; station only
+            // manages plain-HTTP server blocks.
+            anyhow::bail!("The nginx backend does not support TLS routes");
+        }
+        let route = RouteInfo {
+            host: host.to_string(),
+            upstream: file_routes::upstream_url(upstream),
+            port,
+            tls,
+        };
+        let routes = self.table.update(|routes| {
+            file_routes::upsert(routes, route);
+            true
+        })?;
+        self.apply(&routes).await?;
+        info!("Added route: {} -> {} on :{}", host, upstream, port);
+        Ok(())
+    }
+
+    async fn remove_routes(&self, host: &str) -> Result<()> {
+        let mut changed = false;
+        let routes = self.table.update(|routes| {
+            let before = routes.len();
+            routes.retain(|r| r.host != host);
+            changed = routes.len() != before;
+            changed
+        })?;
+        if changed {
+            self.apply(&routes).await?;
+        }
+        Ok(())
+    }
+
+    async fn list_routes(&self) -> Result<Vec<RouteInfo>> {
+        self.table.list()
+    }
+
+    async fn health(&self) -> Result<()> {
+        let routes = self.table.list()?;
+        self.apply(&routes).await
+    }
+}
+
+/// Render routes as nginx `server` blocks.
+fn render(routes: &[RouteInfo]) -> String {
+    let mut out = String::from("# Generated by station; changes will be overwritten.\n");
+    for route in routes {
+        out.push('\n');
+        out.push_str("server {\n");
+        let _ = writeln!(out, "    listen {};", route.port);
+        let _ = writeln!(out, "    server_name {};", route.host);
+        out.push_str("    location / {\n");
+        let _ = writeln!(out, "        proxy_pass {};", route.upstream);
+        out.push_str("        proxy_http_version 1.1;\n");
+        out.push_str("        proxy_set_header Host $host;\n");
+        out.push_str("        proxy_set_header Upgrade $http_upgrade;\n");
+        out.push_str("        proxy_set_header Connection \"upgrade\";\n");
+        out.push_str("    }\n");
+        out.push_str("}\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_route_writes_server_block_and_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("nginx.conf");
+        let marker = dir.path().join("reloaded");
+        let backend = NginxBackend::new(config.clone(), format!("touch {}", marker.display()));
+
+        backend.add_route("app.localhost", "5173", 8080, false).await.unwrap();
+
+        let conf = std::fs::read_to_string(&config).unwrap();
+        assert!(conf.contains("listen 8080;"));
+        assert!(conf.contains("server_name app.localhost;"));
+        assert!(conf.contains("proxy_pass http://localhost:5173;"));
+        assert!(marker.exists());
+
+        backend.remove_routes("app.localhost").await.unwrap();
+        let conf = std::fs::read_to_string(&config).unwrap();
+        assert!(!conf.contains("server {"));
+    }
+
+    #[tokio::test]
+    async fn test_tls_and_failed_reload_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = NginxBackend::new(dir.path().join("nginx.conf"), "false".to_string());
+
+        let err = backend.add_route("app.localhost", "5173", 443, true).await.unwrap_err();
+        assert!(err.to_string().contains("TLS"));
+
+        let err = backend.add_route("app.localhost", "5173", 80, false).await.unwrap_err();
+        assert!(err.to_string().contains("'false' failed"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Traefik file-provider backend.
+//!
+//! Routes are rendered into a dynamic configuration file that Traefik's file
+//! provider watches (`providers.file.filename`, or a file inside
+//! `providers.file.directory`), so changes apply without a reload. Each
+//! listen port maps to an entry point named `station-{port}`, which must be
+//! defined in Traefik's static configuration.
+
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::backend::ProxyBackend;
+use crate::file_routes::{self, RouteTable};
+use crate::RouteInfo;
+
+/// Traefik backend writing a dynamic configuration file.
+pub struct TraefikBackend {
+    config_file: PathBuf,
+    table: RouteTable,
+}
+
+impl TraefikBackend {
+    pub fn new(config_file: PathBuf) -> Self {
+        Self {
+            table: RouteTable::for_config(&config_file),
+            config_file,
+        }
+    }
+
+    fn write_config(&self, routes: &[RouteInfo]) -> Result<()> {
+        file_routes::write_atomic(&self.config_file, &render(routes))
+    }
+}
+
+impl ProxyBackend for TraefikBackend {
+    async fn add_route(&self, host: &str, upstream: &str, port: u16, tls: bool) -> Result<()> {
+        let route = RouteInfo {
+            host: host.to_string(),
+            upstream: file_routes::upstream_url(upstream),
+            port,
+            tls,
+        };
+        let routes = self.table.update(|routes| {
+            file_routes::upsert(routes, route);
+            true
+        })?;
+        self.write_config(&routes)?;
+        info!("Added route: {} -> {} on :{}", host, upstream, port);
+        Ok(())
+    }
+
+    async fn remove_routes(&self, host: &str) -> Result<()> {
+        let routes = self.table.update(|routes| {
+            let before = routes.len();
+            routes.retain(|r| r.host != host);
+            routes.len() != before
+        })?;
+        self.write_config(&routes)
+    }
+
+    async fn list_routes(&self) -> Result<Vec<RouteInfo>> {
+        self.table.list()
+    }
+
+    async fn health(&self) -> Result<()> {
+        // Nothing to connect to; make sure the config file can be written
+        let routes = self.table.list()?;
+        self.write_config(&routes)
+    }
+}
+
+/// Render routes as a Traefik dynamic configuration (YAML).
+fn render(routes: &[RouteInfo]) -> String {
+    let mut out = String::from("# Generated by station; changes will be overwritten.\n");
+    if routes.is_empty() {
+        out.push_str("http: {}\n");
+        return out;
+    }
+
+    out.push_str("http:\n  routers:\n");
+    for route in routes {
+        let id = route_id(route);
+        let _ = writeln!(out, "    {}:", id);
+        let _ = writeln!(out, "      rule: \"Host(`{}`)\"", route.host);
+        let _ = writeln!(out, "      entryPoints: [\"station-{}\"]", route.port);
+        let _ = writeln!(out, "      service: {}", id);
+        if route.tls {
+            out.push_str("      tls: {}\n");
+        }
+    }
+    out.push_str("  services:\n");
+    for route in routes {
+        let _ = writeln!(out, "    {}:", route_id(route));
+        out.push_str("      loadBalancer:\n        servers:\n");
+        let _ = writeln!(out, "          - url: \"{}\"", route.upstream);
+    }
+    out
+}
+
+/// Router/service name, matching the Caddy backend's route IDs.
+fn route_id(route: &RouteInfo) -> String {
+    format!("station-{}-{}", route.host.replace('.', "-"), route.port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_remove_rewrite_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("traefik.yml");
+        let backend = TraefikBackend::new(config.clone());
+
+        backend.add_route("app.localhost", "5173", 80, false).await.unwrap();
+        backend.add_route("api.localhost", "http://127.0.0.1:3000/v2", 443, true).await.unwrap();
+
+        let yaml = std::fs::read_to_string(&config).unwrap();
+        assert!(yaml.contains("station-app-localhost-80:"));
+        assert!(yaml.contains("rule: \"Host(`app.localhost`)\""));
+        assert!(yaml.contains("entryPoints: [\"station-443\"]"));
+        assert!(yaml.contains("tls: {}"));
+        assert!(yaml.contains("url: \"http://localhost:5173\""));
+        assert!(yaml.contains("url: \"http://127.0.0.1:3000/v2\""));
+        assert_eq!(backend.list_routes().await.unwrap().len(), 2);
+
+        backend.remove_routes("app.localhost").await.unwrap();
+        backend.remove_routes("api.localhost").await.unwrap();
+        let yaml = std::fs::read_to_string(&config).unwrap();
+        assert!(yaml.contains("http: {}"));
+        assert!(backend.list_routes().await.unwrap().is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 import { get } from 'svelte/store';
 import { beforeEach, describe, expect, it } from 'vitest';
-import { torenStore } from './toren';
+import { splitNdjson, torenStore } from './toren';
 
 describe('Toren Store', () => {
 	beforeEach(() => {
@@ -105,3 +105,11 @@ describe('Toren Store', () => {
 		expect(state.messages).toEqual([]);
 	});
 });
+
+describe('splitNdjson', () => {
+	it('keeps a partial last line for the next chunk', () => {
+		expect(splitNdjson('{"a":1}\n{"b":2}\n{"c"')).toEqual([['{"a":1}', '{"b":2}'], '{"c"']);
+		expect(splitNdjson('{"a":1}\n\n')).toEqual([['{"a":1}'], '']);
+		expect(splitNdjson('')).toEqual([[], '']);
+	});
+});
@@ -7,6 +7,7 @@ import type {
 	BeadDisplayStatus,
 	CommandOutput,
 	CreateAssignmentRequest,
+	FileDiff,
 	Segment,
 	WsRequest,
 	WsResponse,
@@ -244,6 +245,13 @@ export function getBeadDisplayStatus(assignment: Assignment): BeadDisplayStatus
 	}
 }
 
+// Split newline-delimited JSON text into complete lines and the partial rest
+export function splitNdjson(buffer: string): [string[], string] {
+	const lines = buffer.split('\n');
+	const rest = lines.pop() ?? '';
+	return [lines.filter((line) => line.trim() !== ''), rest];
+}
+
 // Create the store with a custom store that includes helper methods
 function createTorenStore() {
 	const initialState: TorenState = {
@@ -266,6 +274,14 @@ function createTorenStore() {
 
 	const { subscribe, set, update } = writable(initialState);
 
+	// API routes require the session token obtained by pairing
+	const apiFetch = (url: string, init: RequestInit = {}) => {
+		const token = get({ subscribe }).sessionToken;
+		const headers = new Headers(init.headers);
+		if (token) headers.set('Authorization', `Bearer ${token}`);
+		return fetch(url, { ...init, headers });
+	};
+
 	return {
 		subscribe,
 		set,
@@ -275,7 +291,7 @@ function createTorenStore() {
 		async loadSegments(shipUrl: string) {
 			update((state) => ({ ...state, loadingSegments: true }));
 			try {
-				const response = await fetch(`${shipUrl}/api/segments/list`);
+				const response = await apiFetch(`${shipUrl}/api/segments/list`);
 				if (!response.ok) throw new Error('Failed to fetch segments');
 				const data = await response.json();
 				update((state) => ({
@@ -303,7 +319,7 @@ function createTorenStore() {
 		},
 		async createSegment(name: string, root: string, shipUrl: string) {
 			try {
-				const response = await fetch(`${shipUrl}/api/segments/create`, {
+				const response = await apiFetch(`${shipUrl}/api/segments/create`, {
 					method: 'POST',
 					headers: { 'Content-Type': 'application/json' },
 					body: JSON.stringify({ name, root }),
@@ -323,7 +339,7 @@ function createTorenStore() {
 		async loadAssignments(shipUrl: string) {
 			update((state) => ({ ...state, loadingAssignments: true }));
 			try {
-				const response = await fetch(`${shipUrl}/api/assignments`);
+				const response = await apiFetch(`${shipUrl}/api/assignments`);
 				if (!response.ok) throw new Error('Failed to fetch assignments');
 				const data = await response.json();
 				update((state) => ({
@@ -341,7 +357,7 @@ function createTorenStore() {
 		},
 		async loadAncillaries(shipUrl: string) {
 			try {
-				const response = await fetch(`${shipUrl}/api/ancillaries/list`);
+				const response = await apiFetch(`${shipUrl}/api/ancillaries/list`);
 				if (!response.ok) throw new Error('Failed to fetch ancillaries');
 				const data = await response.json();
 				update((state) => ({
@@ -356,7 +372,7 @@ function createTorenStore() {
 			update((state) => ({ ...state, selectedAncillary: assignment }));
 		},
 		async createAssignment(shipUrl: string, request: CreateAssignmentRequest): Promise<Assignment> {
-			const response = await fetch(`${shipUrl}/api/assignments`, {
+			const response = await apiFetch(`${shipUrl}/api/assignments`, {
 				method: 'POST',
 				headers: { 'Content-Type': 'application/json' },
 				body: JSON.stringify(request),
@@ -376,7 +392,7 @@ function createTorenStore() {
 		},
 		async startWork(shipUrl: string, ancillaryId: string, assignmentId: string): Promise<void> {
 			const encoded = encodeURIComponent(ancillaryId);
-			const response = await fetch(`${shipUrl}/api/ancillaries/${encoded}/start`, {
+			const response = await apiFetch(`${shipUrl}/api/ancillaries/${encoded}/start`, {
 				method: 'POST',
 				headers: { 'Content-Type': 'application/json' },
 				body: JSON.stringify({ assignment_id: assignmentId }),
@@ -391,7 +407,7 @@ function createTorenStore() {
 			assignmentId: string,
 			options?: { push?: boolean; keep_open?: boolean },
 		): Promise<{ revision?: string; pushed: boolean }> {
-			const response = await fetch(`${shipUrl}/api/assignments/${assignmentId}/complete`, {
+			const response = await apiFetch(`${shipUrl}/api/assignments/${assignmentId}/complete`, {
 				method: 'POST',
 				headers: { 'Content-Type': 'application/json' },
 				body: JSON.stringify({
@@ -416,7 +432,7 @@ function createTorenStore() {
 			assignmentId: string,
 			options?: { close_bead?: boolean },
 		): Promise<void> {
-			const response = await fetch(`${shipUrl}/api/assignments/${assignmentId}/abort`, {
+			const response = await apiFetch(`${shipUrl}/api/assignments/${assignmentId}/abort`, {
 				method: 'POST',
 				headers: { 'Content-Type': 'application/json' },
 				body: JSON.stringify({ close_bead: options?.close_bead ?? false }),
@@ -436,7 +452,7 @@ function createTorenStore() {
 			assignmentId: string,
 			options?: { instruction?: string; start_work?: boolean },
 		): Promise<{ assignment: Assignment; work_started: boolean }> {
-			const response = await fetch(`${shipUrl}/api/assignments/${assignmentId}/resume`, {
+			const response = await apiFetch(`${shipUrl}/api/assignments/${assignmentId}/resume`, {
 				method: 'POST',
 				headers: { 'Content-Type': 'application/json' },
 				body: JSON.stringify({
@@ -456,6 +472,42 @@ function createTorenStore() {
 			}));
 			return { assignment: data.assignment, work_started: data.work_started };
 		},
+		// Stream the working copy diff of `path`, calling `onFile` as each file arrives
+		async streamDiff(
+			shipUrl: string,
+			path: string,
+			onFile: (file: FileDiff) => void,
+			options?: { paths?: string[]; max_lines_per_file?: number; signal?: AbortSignal },
+		): Promise<void> {
+			const response = await apiFetch(`${shipUrl}/api/vcs/diff/stream`, {
+				method: 'POST',
+				headers: { 'Content-Type': 'application/json' },
+				body: JSON.stringify({
+					path,
+					paths: options?.paths ?? [],
+					max_lines_per_file: options?.max_lines_per_file,
+				}),
+				signal: options?.signal,
+			});
+			if (!response.ok || !response.body) {
+				throw new Error('Failed to load diff');
+			}
+			const reader = response.body.getReader();
+			const decoder = new TextDecoder();
+			let buffer = '';
+			for (;;) {
+				const { done, value } = await reader.read();
+				buffer += decoder.decode(value, { stream: !done });
+				const [lines, rest] = splitNdjson(done ? `${buffer}\n` : buffer);
+				buffer = rest;
+				for (const line of lines) {
+					const data = JSON.parse(line);
+					if (data.error) throw new Error(data.error);
+					onFile(data as FileDiff);
+				}
+				if (done) break;
+			}
+		},
 	};
 }
 
@@ -80,6 +80,8 @@ export interface Assignment {
 	// Composite status signals (from API enrichment)
 	agent_activity?: AgentActivity;
 	has_changes?: boolean;
+	/** Working copy has unresolved conflicts */
+	has_conflicts?: boolean;
 	/** Task status from provider */
 	task_status?: BeadStatus;
 	/** @deprecated Use task_status */
@@ -164,6 +166,16 @@ export interface PairResponse {
 	session_id: string;
 }
 
+/** One file's part of a working copy diff (`/api/vcs/diff/stream`) */
+export interface FileDiff {
+	path: string;
+	diff: string;
+	/** Lines in the file's full patch, including any cut off */
+	lines: number;
+	/** Whether `max_lines_per_file` cut the patch short */
+	truncated: boolean;
+}
+
 export interface HealthResponse {
 	status: string;
 	version: string;